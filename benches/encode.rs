@@ -0,0 +1,48 @@
+//! Encoding benchmarks
+//!
+//! Run with `cargo bench --features parallel` to compare table encoding on
+//! one thread against the whole rayon pool.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde_json::{json, Value};
+use toon_rust::encode;
+
+fn table(rows: usize) -> Value {
+    let items: Vec<Value> = (0..rows)
+        .map(|i| {
+            json!({
+                "id": i,
+                "sku": format!("SKU-{i:06}"),
+                "name": format!("Product number {i}"),
+                "price": i as f64 * 0.37,
+                "in_stock": i % 3 != 0
+            })
+        })
+        .collect();
+    json!({ "items": items })
+}
+
+fn bench_tables(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_table");
+    group.sample_size(10);
+    for rows in [1_000, 100_000, 300_000] {
+        let data = table(rows);
+        group.bench_with_input(BenchmarkId::new("default", rows), &data, |b, data| {
+            b.iter(|| encode(black_box(data), None).unwrap())
+        });
+        #[cfg(feature = "parallel")]
+        {
+            let single = rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap();
+            group.bench_with_input(BenchmarkId::new("one_thread", rows), &data, |b, data| {
+                b.iter(|| single.install(|| encode(black_box(data), None).unwrap()))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_tables);
+criterion_main!(benches);
@@ -42,7 +42,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Decode using streaming API - reads incrementally without loading entire file
     let file = File::open("example_output.toon")?;
-    let decoded = decode_stream(file, None)?;
+    let decoded = decode_stream(BufReader::new(file), None)?;
 
     println!("✓ Decoded successfully");
     println!("\nDecoded data summary:");
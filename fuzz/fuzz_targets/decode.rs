@@ -0,0 +1,11 @@
+//! Decoding any text under any options must return, not panic
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use toon_rust::{decode, DecodeOptions};
+
+fuzz_target!(|input: (&str, DecodeOptions)| {
+    let (text, options) = input;
+    let _ = decode(text, Some(&options));
+});
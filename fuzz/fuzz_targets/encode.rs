@@ -0,0 +1,15 @@
+//! Encoding any value under any options, and decoding what comes out,
+//! must return, not panic
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use toon_rust::fuzzing::ArbitraryValue;
+use toon_rust::{decode, encode, DecodeOptions, EncodeOptions};
+
+fuzz_target!(|input: (ArbitraryValue, EncodeOptions, DecodeOptions)| {
+    let (ArbitraryValue(value), encode_options, decode_options) = input;
+    if let Ok(toon) = encode(&value, Some(&encode_options)) {
+        let _ = decode(&toon, Some(&decode_options));
+    }
+});
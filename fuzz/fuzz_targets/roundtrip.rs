@@ -0,0 +1,20 @@
+//! Decoding the TOON of any value must give back the value `serde_json`
+//! holds
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use toon_rust::fuzzing::ArbitraryValue;
+use toon_rust::{decode, encode, EncodeOptions};
+
+fuzz_target!(|input: ArbitraryValue| {
+    let ArbitraryValue(value) = input;
+    let options = EncodeOptions::new().roundtrip_safe(true);
+    let toon = encode(&value, Some(&options)).expect("finite values encode");
+    let decoded = decode(&toon, None).expect("encoded TOON decodes");
+    assert_eq!(
+        serde_json::to_string(&decoded).unwrap(),
+        serde_json::to_string(&value).unwrap(),
+        "{toon}"
+    );
+});
@@ -0,0 +1,240 @@
+//! Encoding Arrow record batches as a TOON table
+//!
+//! Requires the `arrow` feature. Cells are read straight from the typed
+//! column arrays and written row by row, so a query result held as Arrow
+//! data goes into a prompt without a JSON object per row in between.
+
+use crate::encode::{
+    encode_scalar, format_fields, length_header, transform_leaf, Sink, Slot, WriteSink,
+};
+use crate::error::Error;
+use crate::options::EncodeOptions;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Float16Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type,
+    UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow_array::{Array, ArrayRef, RecordBatch};
+use arrow_cast::display::{ArrayFormatter, FormatOptions};
+use arrow_schema::{DataType, Fields};
+use serde_json::{Number, Value};
+use std::io::Write;
+
+/// Write record batches as one tabular root array
+///
+/// The columns are the schema's fields, and every batch must have the same
+/// fields as the first. Integers, floats, booleans and strings are written
+/// as such, with NaN and infinities as null. Decimals, dates, times,
+/// durations and binary data are written as their Arrow display text,
+/// such as `2024-05-01` or `12.50`; dictionary columns as their values.
+/// Lists, structs and maps can't be table cells and are errors.
+///
+/// No batches, or batches without rows, give an empty array.
+///
+/// # Example
+///
+/// ```rust
+/// use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+/// use std::sync::Arc;
+/// use toon_rust::encode_record_batches;
+///
+/// let batch = RecordBatch::try_from_iter([
+///     ("id", Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef),
+///     ("name", Arc::new(StringArray::from(vec![Some("Ada"), None])) as ArrayRef),
+/// ])?;
+/// let mut output = Vec::new();
+/// encode_record_batches(&[batch], &mut output, None)?;
+/// assert_eq!(String::from_utf8(output)?, "[2]{id,name}:\n  1,Ada\n  2,null\n");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn encode_record_batches<W: Write>(
+    batches: &[RecordBatch],
+    writer: &mut W,
+    options: Option<&EncodeOptions>,
+) -> Result<(), Error> {
+    let default_opts = EncodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let rows = batches.iter().map(RecordBatch::num_rows).sum();
+    let fields = batches.first().map(|batch| batch.schema().fields().clone());
+    let columns = fields.as_ref().map(column_names);
+    let mut sink = WriteSink(&mut *writer);
+    write_header(columns.as_deref(), rows, &mut sink, opts)?;
+    for (i, batch) in batches.iter().enumerate() {
+        if Some(batch.schema().fields()) != fields.as_ref() {
+            return Err(Error::Serialization(format!(
+                "Record batch {i} has different fields from the first"
+            )));
+        }
+        write_rows(batch, &mut sink, opts)?;
+    }
+    writer.flush().map_err(|e| Error::Io(e.to_string()))
+}
+
+/// The names of `fields`, which are the table's columns
+pub(crate) fn column_names(fields: &Fields) -> Vec<String> {
+    fields.iter().map(|field| field.name().clone()).collect()
+}
+
+/// Write the header of a root table of `rows` rows, or an empty array
+/// when there are none
+pub(crate) fn write_header(
+    columns: Option<&[String]>,
+    rows: usize,
+    output: &mut impl Sink,
+    options: &EncodeOptions,
+) -> Result<(), Error> {
+    match columns.filter(|_| rows > 0) {
+        Some(columns) => output.push_str(&format!(
+            "{}{{{}}}:\n",
+            length_header(rows, options),
+            format_fields(columns, options)
+        )),
+        None => output.push_str(&format!("{}:", length_header(0, options))),
+    }
+}
+
+/// Write each row of `batch` as a table row
+pub(crate) fn write_rows(
+    batch: &RecordBatch,
+    output: &mut impl Sink,
+    options: &EncodeOptions,
+) -> Result<(), Error> {
+    let schema = batch.schema();
+    let columns = column_names(schema.fields());
+    let cells = batch
+        .columns()
+        .iter()
+        .zip(&columns)
+        .map(|(array, name)| Cells::new(array, name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let indent = options.get_indent_string();
+    for row in 0..batch.num_rows() {
+        let mut line = indent.to_string();
+        for (i, (column, name)) in cells.iter().zip(&columns).enumerate() {
+            if i > 0 {
+                line.push(options.get_delimiter());
+            }
+            let value = column.value(row);
+            let value = transform_leaf(&value, Some(name), options);
+            match value.is_null() {
+                true => line.push_str("null"),
+                false => encode_scalar(&value, &mut line, options, Slot::Cell)?,
+            }
+        }
+        line.push('\n');
+        output.push_str(&line)?;
+    }
+    Ok(())
+}
+
+/// A column of one batch, ready to give the value of each of its cells
+enum Cells<'a> {
+    /// A column read through its typed array
+    Typed(ArrayRef),
+    /// A column written as Arrow's display text
+    Text(&'a dyn Array, ArrayFormatter<'a>),
+}
+
+impl<'a> Cells<'a> {
+    fn new(array: &'a ArrayRef, name: &str) -> Result<Self, Error> {
+        let unsupported = || {
+            Error::Serialization(format!(
+                "Column `{name}` has type {}, which can't be a table cell",
+                array.data_type()
+            ))
+        };
+        match array.data_type() {
+            ty if is_typed(ty) => Ok(Cells::Typed(array.clone())),
+            DataType::Dictionary(_, values) if is_typed(values) => {
+                let values = arrow_cast::cast(array, values).map_err(|_| unsupported())?;
+                Ok(Cells::Typed(values))
+            }
+            DataType::List(_)
+            | DataType::LargeList(_)
+            | DataType::ListView(_)
+            | DataType::LargeListView(_)
+            | DataType::FixedSizeList(..)
+            | DataType::Struct(_)
+            | DataType::Union(..)
+            | DataType::Map(..)
+            | DataType::RunEndEncoded(..) => Err(unsupported()),
+            _ => Cells::text(array, name),
+        }
+    }
+
+    fn text(array: &'a ArrayRef, name: &str) -> Result<Self, Error> {
+        let formatter = ArrayFormatter::try_new(array.as_ref(), &FormatOptions::default())
+            .map_err(|err| {
+                Error::Serialization(format!("Column `{name}` can't be written: {err}"))
+            })?;
+        Ok(Cells::Text(array.as_ref(), formatter))
+    }
+
+    /// The value of the cell in `row`
+    fn value(&self, row: usize) -> Value {
+        let array = match self {
+            Cells::Typed(array) => array.as_ref(),
+            Cells::Text(array, formatter) => {
+                return match array.is_null(row) {
+                    true => Value::Null,
+                    false => Value::String(formatter.value(row).to_string()),
+                }
+            }
+        };
+        if array.is_null(row) {
+            return Value::Null;
+        }
+        match array.data_type() {
+            DataType::Boolean => Value::Bool(array.as_boolean().value(row)),
+            DataType::Int8 => Value::from(array.as_primitive::<Int8Type>().value(row)),
+            DataType::Int16 => Value::from(array.as_primitive::<Int16Type>().value(row)),
+            DataType::Int32 => Value::from(array.as_primitive::<Int32Type>().value(row)),
+            DataType::Int64 => Value::from(array.as_primitive::<Int64Type>().value(row)),
+            DataType::UInt8 => Value::from(array.as_primitive::<UInt8Type>().value(row)),
+            DataType::UInt16 => Value::from(array.as_primitive::<UInt16Type>().value(row)),
+            DataType::UInt32 => Value::from(array.as_primitive::<UInt32Type>().value(row)),
+            DataType::UInt64 => Value::from(array.as_primitive::<UInt64Type>().value(row)),
+            DataType::Float16 => {
+                let f = array.as_primitive::<Float16Type>().value(row);
+                float(f.to_string().parse().unwrap_or(f.to_f64()))
+            }
+            // Through the shortest text of the `f32`, so 0.1 stays 0.1
+            DataType::Float32 => {
+                let f = array.as_primitive::<Float32Type>().value(row);
+                float(f.to_string().parse().unwrap_or(f64::from(f)))
+            }
+            DataType::Float64 => float(array.as_primitive::<Float64Type>().value(row)),
+            DataType::Utf8 => Value::from(array.as_string::<i32>().value(row)),
+            DataType::LargeUtf8 => Value::from(array.as_string::<i64>().value(row)),
+            DataType::Utf8View => Value::from(array.as_string_view().value(row)),
+            _ => Value::Null,
+        }
+    }
+}
+
+/// Whether cells of type `ty` are read through their typed array
+fn is_typed(ty: &DataType) -> bool {
+    matches!(
+        ty,
+        DataType::Null
+            | DataType::Boolean
+            | DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::UInt64
+            | DataType::Float16
+            | DataType::Float32
+            | DataType::Float64
+            | DataType::Utf8
+            | DataType::LargeUtf8
+            | DataType::Utf8View
+    )
+}
+
+fn float(f: f64) -> Value {
+    Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
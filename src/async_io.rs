@@ -0,0 +1,224 @@
+//! Encoding and decoding over tokio's `AsyncWrite` and `AsyncRead`
+//!
+//! Requires the `async` feature. The functions mirror [`crate::encode_stream`]
+//! and [`crate::decode_stream`] for services that already run on tokio, so
+//! bodies can be written and read without `spawn_blocking`.
+
+use crate::decode::{merge_entry, PushEvent, PushParser};
+use crate::encode::{check_indent, encode, encode_entry, non_finite_literal, Placement};
+use crate::error::Error;
+use crate::options::{DecodeOptions, EncodeOptions};
+#[cfg(feature = "serde")]
+use crate::ser_stream::RowRenderer;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Encode a JSON value to TOON format and write it to an async writer
+///
+/// A top-level object is written one entry at a time, awaiting the writer
+/// between entries, so only one entry's text is held at once; any other
+/// value is rendered whole first. The output is the same as [`encode`].
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::encode_stream_async;
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let mut body = Vec::new();
+/// encode_stream_async(&json!({"name": "Alice", "age": 30}), &mut body, None).await?;
+/// assert_eq!(body, b"age: 30\nname: Alice");
+/// # Ok::<(), toon_rust::Error>(())
+/// # }).unwrap();
+/// ```
+pub async fn encode_stream_async<W: AsyncWrite + Unpin>(
+    value: &Value,
+    writer: &mut W,
+    options: Option<&EncodeOptions>,
+) -> Result<(), Error> {
+    let default_opts = EncodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    check_indent(opts)?;
+    match value {
+        Value::Object(obj) if non_finite_literal(value).is_none() => {
+            let mut first = true;
+            let mut entry = String::new();
+            for (key, value) in obj {
+                entry.clear();
+                let placement = Placement::Line { first };
+                if encode_entry(key, value, Some(obj), &mut entry, 0, placement, opts)? {
+                    first = false;
+                    write(writer, &entry).await?;
+                }
+            }
+        }
+        _ => write(writer, &encode(value, Some(opts))?).await?,
+    }
+    writer.flush().await.map_err(|e| Error::Io(e.to_string()))
+}
+
+/// Decode a TOON-formatted stream from an async reader to a JSON value
+///
+/// The input is read in chunks and fed to a [`PushParser`], so only the
+/// top-level entry being read is held as text; a document that isn't an
+/// object, such as a root array, is buffered whole.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::decode_stream_async;
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let body: &[u8] = b"name: Alice\nage: 30";
+/// let value = decode_stream_async(body, None).await?;
+/// assert_eq!(value, json!({"name": "Alice", "age": 30}));
+/// # Ok::<(), toon_rust::Error>(())
+/// # }).unwrap();
+/// ```
+pub async fn decode_stream_async<R: AsyncRead + Unpin>(
+    mut reader: R,
+    options: Option<&DecodeOptions>,
+) -> Result<Value, Error> {
+    let default_opts = DecodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let mut parser = PushParser::new(Some(opts));
+    let mut map = Map::new();
+    let mut chunk = vec![0u8; 8192];
+    loop {
+        let n = reader
+            .read(&mut chunk)
+            .await
+            .map_err(|e| Error::Io(e.to_string()))?;
+        if n == 0 {
+            break;
+        }
+        for event in parser.feed(&chunk[..n])? {
+            if let Some(root) = collect(opts, &mut map, event) {
+                return Ok(root);
+            }
+        }
+    }
+    for event in parser.finish()? {
+        if let Some(root) = collect(opts, &mut map, event) {
+            return Ok(root);
+        }
+    }
+    Ok(Value::Object(map))
+}
+
+/// Add an entry to the document, or return a non-object document whole
+///
+/// Repeated keys have already been resolved by the parser; entries are
+/// merged rather than replaced when dotted keys are expanded, since
+/// `a.b` and `a.c` arrive as two entries under `a`.
+fn collect(
+    options: &DecodeOptions,
+    map: &mut Map<String, Value>,
+    event: PushEvent,
+) -> Option<Value> {
+    match event {
+        PushEvent::Entry { key, value } if options.get_expand_paths() => {
+            merge_entry(options, map, key, value);
+            None
+        }
+        PushEvent::Entry { key, value } => {
+            map.insert(key, value);
+            None
+        }
+        PushEvent::Root(value) => Some(value),
+    }
+}
+
+async fn write<W: AsyncWrite + Unpin>(writer: &mut W, s: &str) -> Result<(), Error> {
+    writer
+        .write_all(s.as_bytes())
+        .await
+        .map_err(|e| Error::Io(e.to_string()))
+}
+
+/// Async counterpart of [`TabularWriter`](crate::TabularWriter), for
+/// streaming a table straight into a response body
+///
+/// Requires the `serde` feature as well. Rows are laid out exactly as
+/// [`TabularWriter`](crate::TabularWriter) lays them out. With lengths
+/// turned off through [`EncodeOptions::emit_lengths`], each
+/// [`push_row`](Self::push_row) writes its row and waits for the writer to
+/// accept it, so a slow client slows the producer down; otherwise rows are
+/// held until [`finish`](Self::finish), which needs the count for the header.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::{AsyncTabularWriter, EncodeOptions};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let options = EncodeOptions::new().emit_lengths(false);
+/// let mut table = AsyncTabularWriter::with_options("items", ["sku", "qty"], Vec::new(), &options);
+/// table.push_row(&json!({"sku": "A1", "qty": 2})).await?;
+/// table.push_row(&json!({"sku": "B2", "qty": 1})).await?;
+/// let body = table.finish().await?;
+/// assert_eq!(body, b"items{sku,qty}:\n  A1,2\n  B2,1\n");
+/// # Ok::<(), toon_rust::Error>(())
+/// # }).unwrap();
+/// ```
+#[cfg(feature = "serde")]
+pub struct AsyncTabularWriter<W: AsyncWrite + Unpin> {
+    writer: W,
+    rows: RowRenderer,
+}
+
+#[cfg(feature = "serde")]
+impl<W: AsyncWrite + Unpin> AsyncTabularWriter<W> {
+    /// Create a writer for the table `key` with the given columns
+    pub fn new<I>(key: impl Into<String>, columns: I, writer: W) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self::with_options(key, columns, writer, &EncodeOptions::default())
+    }
+
+    /// Create a writer for the table `key` with the given columns and options
+    pub fn with_options<I>(
+        key: impl Into<String>,
+        columns: I,
+        writer: W,
+        options: &EncodeOptions,
+    ) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        AsyncTabularWriter {
+            writer,
+            rows: RowRenderer::new(key, columns, options),
+        }
+    }
+
+    /// Serialize one row and write it, or hold it until [`finish`] when
+    /// the row count goes in the header
+    ///
+    /// [`finish`]: AsyncTabularWriter::finish
+    pub async fn push_row<T: Serialize + ?Sized>(&mut self, row: &T) -> Result<(), Error> {
+        if let Some(text) = self.rows.push(row)? {
+            write(&mut self.writer, &text).await?;
+        }
+        Ok(())
+    }
+
+    /// Write the header and any rows still held back, and return the writer
+    pub async fn finish(mut self) -> Result<W, Error> {
+        let text = self.rows.finish();
+        write(&mut self.writer, &text).await?;
+        self.writer
+            .flush()
+            .await
+            .map_err(|e| Error::Io(e.to_string()))?;
+        Ok(self.writer)
+    }
+}
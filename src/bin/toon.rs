@@ -0,0 +1,363 @@
+//! The `toon` command line tool
+//!
+//! ```text
+//! toon get <FILE> <PATH> [--raw]     print the value at a pointer or TOON path
+//! toon query <FILE> <QUERY> [--raw]  print every value a query outputs
+//! toon from-csv <FILE> [--key KEY] [--delimiter C] [--no-infer]
+//!                                    convert CSV with a header row to a table
+//! toon to-csv <FILE> <PATH> [--delimiter C]
+//!                                    write the table at a path as CSV
+//! toon to-yaml <FILE>                convert TOON to YAML (`yaml` feature)
+//! toon from-yaml <FILE>              convert YAML to TOON (`yaml` feature)
+//! toon fmt <FILE> [--indent N]       re-indent and tidy a TOON document
+//! toon lint <FILE>                   report problems that don't stop decoding
+//! ```
+//!
+//! `FILE` may be `-` for standard input. Values are printed as TOON, one
+//! after another; with `--raw` each goes on a line of its own, strings as
+//! they are and anything else as compact JSON, for use in shell scripts.
+//!
+//! Errors in a document and lints are shown with the line they point at;
+//! built with the `color` feature, they are colored when written to a
+//! terminal.
+
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, IsTerminal, Read, Write};
+use std::process::ExitCode;
+use toon_rust::lint::Lint;
+use toon_rust::options::{CsvOptions, FormatOptions};
+use toon_rust::{decode_stream, encode, format_str, from_csv, lint, pointer, query_stream, to_csv};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "\
+Usage:
+  toon get <FILE> <PATH> [--raw]
+  toon query <FILE> <QUERY> [--raw]
+  toon from-csv <FILE> [--key KEY] [--delimiter C] [--no-infer]
+  toon to-csv <FILE> <PATH> [--delimiter C]
+  toon to-yaml <FILE>
+  toon from-yaml <FILE>
+  toon fmt <FILE> [--indent N]
+  toon lint <FILE>
+
+FILE may be `-` to read standard input. PATH is a JSON Pointer
+(`/items/2/price`) or a TOON path (`items[2].price`).
+
+Options:
+  --raw      Print each value on its own line, strings without quotes
+  --key KEY  Write the CSV table under KEY rather than as a root array
+  --delimiter C
+             Separate CSV fields with C rather than `,` (`\t` for a tab)
+  --no-infer Keep CSV cells as strings rather than reading numbers and
+             booleans
+  --indent N Indent each level by N spaces (default: 2)
+";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(command) = Command::parse(&args) else {
+        eprint!("{USAGE}");
+        return ExitCode::from(2);
+    };
+    match command.run(&mut io::stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            match err.downcast_ref::<SourceError>() {
+                Some(SourceError { error, input }) => {
+                    eprint!("{}", render_error(error, input, io::stderr().is_terminal()));
+                }
+                None => eprintln!("toon: {err}"),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// A subcommand and its arguments
+enum Command {
+    Get {
+        file: String,
+        path: String,
+        raw: bool,
+    },
+    Query {
+        file: String,
+        query: String,
+        raw: bool,
+    },
+    FromCsv {
+        file: String,
+        key: Option<String>,
+        delimiter: Option<char>,
+        infer: bool,
+    },
+    ToCsv {
+        file: String,
+        path: String,
+        delimiter: Option<char>,
+    },
+    ToYaml {
+        file: String,
+    },
+    FromYaml {
+        file: String,
+    },
+    Fmt {
+        file: String,
+        indent: Option<usize>,
+    },
+    Lint {
+        file: String,
+    },
+}
+
+impl Command {
+    /// Read the command line, or `None` if it doesn't match any command
+    fn parse(args: &[String]) -> Option<Self> {
+        let mut positional = Vec::new();
+        let (mut raw, mut key, mut indent) = (false, None, None);
+        let (mut delimiter, mut infer) = (None, true);
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--raw" => raw = true,
+                "--key" => key = Some(args.next()?.clone()),
+                "--indent" => indent = Some(args.next()?.parse().ok()?),
+                "--delimiter" => delimiter = Some(parse_delimiter(args.next()?)?),
+                "--no-infer" => infer = false,
+                flag if flag.starts_with("--") => return None,
+                _ => positional.push(arg.clone()),
+            }
+        }
+        // Each command takes only the options it uses
+        if indent.is_some() && positional.first().is_some_and(|command| command != "fmt") {
+            return None;
+        }
+        let command = positional.first().map(String::as_str);
+        if (delimiter.is_some() && !matches!(command, Some("from-csv" | "to-csv")))
+            || (!infer && command != Some("from-csv"))
+        {
+            return None;
+        }
+        let command = match positional.as_slice() {
+            [command, file, path] if command == "get" && key.is_none() => Command::Get {
+                file: file.clone(),
+                path: path.clone(),
+                raw,
+            },
+            [command, file, query] if command == "query" && key.is_none() => Command::Query {
+                file: file.clone(),
+                query: query.clone(),
+                raw,
+            },
+            [command, file] if command == "from-csv" && !raw => Command::FromCsv {
+                file: file.clone(),
+                key,
+                delimiter,
+                infer,
+            },
+            [command, file, path] if command == "to-csv" && !raw && key.is_none() => {
+                Command::ToCsv {
+                    file: file.clone(),
+                    path: path.clone(),
+                    delimiter,
+                }
+            }
+            [command, file] if command == "to-yaml" && !raw && key.is_none() => {
+                Command::ToYaml { file: file.clone() }
+            }
+            [command, file] if command == "from-yaml" && !raw && key.is_none() => {
+                Command::FromYaml { file: file.clone() }
+            }
+            [command, file] if command == "fmt" && !raw && key.is_none() => Command::Fmt {
+                file: file.clone(),
+                indent,
+            },
+            [command, file] if command == "lint" && !raw && key.is_none() => {
+                Command::Lint { file: file.clone() }
+            }
+            _ => return None,
+        };
+        Some(command)
+    }
+
+    fn run(&self, out: &mut impl Write) -> Result<()> {
+        match self {
+            Command::Get { file, path, raw } => {
+                let document = decode_stream(open(file)?, None)?;
+                let value =
+                    pointer::get(&document, path).ok_or_else(|| format!("no value at {path}"))?;
+                print(out, std::slice::from_ref(value), *raw)
+            }
+            Command::Query { file, query, raw } => {
+                let values = query_stream(open(file)?, query, None)?;
+                print(out, &values, *raw)
+            }
+            Command::FromCsv {
+                file,
+                key,
+                delimiter,
+                infer,
+            } => {
+                let mut options = CsvOptions::new().infer_types(*infer);
+                if let Some(key) = key {
+                    options = options.key(key);
+                }
+                if let Some(delimiter) = delimiter {
+                    options = options.delimiter(*delimiter);
+                }
+                from_csv(BufReader::new(open(file)?), out, Some(&options))?;
+                Ok(())
+            }
+            Command::ToCsv {
+                file,
+                path,
+                delimiter,
+            } => {
+                let document = decode_stream(open(file)?, None)?;
+                let mut options = CsvOptions::new();
+                if let Some(delimiter) = delimiter {
+                    options = options.delimiter(*delimiter);
+                }
+                to_csv(&document, path, out, Some(&options))?;
+                Ok(())
+            }
+            Command::ToYaml { file } => to_yaml(file, out),
+            Command::FromYaml { file } => from_yaml(file, out),
+            Command::Fmt { file, indent } => {
+                let input = read(file)?;
+                let mut options = FormatOptions::new();
+                if let Some(indent) = indent {
+                    options = options.indent(*indent);
+                }
+                let formatted =
+                    format_str(&input, &options).map_err(|error| SourceError { error, input })?;
+                out.write_all(formatted.as_bytes())?;
+                Ok(())
+            }
+            Command::Lint { file } => {
+                let input = read(file)?;
+                let lints = lint(&input);
+                let color = io::stdout().is_terminal();
+                for found in &lints {
+                    write!(out, "{}", render_lint(found, &input, color))?;
+                }
+                match lints.len() {
+                    0 => Ok(()),
+                    1 => Err("1 problem found".into()),
+                    n => Err(format!("{n} problems found").into()),
+                }
+            }
+        }
+    }
+}
+
+/// An error in a document, kept with the document to show it against
+#[derive(Debug)]
+struct SourceError {
+    error: toon_rust::Error,
+    input: String,
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl Error for SourceError {}
+
+#[cfg(feature = "color")]
+fn render_error(error: &toon_rust::Error, input: &str, color: bool) -> String {
+    match color {
+        true => toon_rust::render_error(error, input),
+        false => error.render(input),
+    }
+}
+
+#[cfg(not(feature = "color"))]
+fn render_error(error: &toon_rust::Error, input: &str, _color: bool) -> String {
+    error.render(input)
+}
+
+#[cfg(feature = "color")]
+fn render_lint(lint: &Lint, input: &str, color: bool) -> String {
+    match color {
+        true => toon_rust::render_lint(lint, input),
+        false => plain_lint(lint),
+    }
+}
+
+#[cfg(not(feature = "color"))]
+fn render_lint(lint: &Lint, _input: &str, _color: bool) -> String {
+    plain_lint(lint)
+}
+
+/// A lint on a line of its own, as `line:column: warning: message`
+fn plain_lint(lint: &Lint) -> String {
+    format!("{}:{}: warning: {}\n", lint.line, lint.column, lint.message)
+}
+
+#[cfg(feature = "yaml")]
+fn to_yaml(file: &str, out: &mut impl Write) -> Result<()> {
+    toon_rust::toon_to_yaml_stream(open(file)?, out, None)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "yaml"))]
+fn to_yaml(_file: &str, _out: &mut impl Write) -> Result<()> {
+    Err("to-yaml needs toon built with the `yaml` feature".into())
+}
+
+#[cfg(feature = "yaml")]
+fn from_yaml(file: &str, out: &mut impl Write) -> Result<()> {
+    let toon = toon_rust::yaml_to_toon(&read(file)?, None)?;
+    writeln!(out, "{}", toon.trim_end_matches('\n'))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "yaml"))]
+fn from_yaml(_file: &str, _out: &mut impl Write) -> Result<()> {
+    Err("from-yaml needs toon built with the `yaml` feature".into())
+}
+
+/// The character a `--delimiter` argument names, with `\t` for a tab
+fn parse_delimiter(arg: &str) -> Option<char> {
+    if arg == "\\t" {
+        return Some('\t');
+    }
+    let mut chars = arg.chars();
+    chars.next().filter(|_| chars.next().is_none())
+}
+
+/// All of the file at `path`, or of standard input for `-`
+fn read(path: &str) -> Result<String> {
+    let mut input = String::new();
+    open(path)?.read_to_string(&mut input)?;
+    Ok(input)
+}
+
+/// The file at `path`, or standard input for `-`
+fn open(path: &str) -> Result<Box<dyn Read>> {
+    if path == "-" {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    let file = File::open(path).map_err(|err| format!("{path}: {err}"))?;
+    Ok(Box::new(file))
+}
+
+fn print(out: &mut impl Write, values: &[Value], raw: bool) -> Result<()> {
+    for value in values {
+        let text = match (raw, value) {
+            (true, Value::String(s)) => s.clone(),
+            (true, value) => value.to_string(),
+            (false, value) => encode(value, None)?,
+        };
+        writeln!(out, "{}", text.trim_end_matches('\n'))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,340 @@
+//! Encoding a document to fit a token budget
+//!
+//! [`encode_with_budget`] encodes a value as TOON and, when the text costs
+//! more tokens than allowed, trims the value the way a [`BudgetPolicy`]
+//! says until it fits, reporting each cut so a prompt can say what it
+//! leaves out.
+
+use crate::encode::encode;
+use crate::error::Error;
+use crate::options::EncodeOptions;
+use crate::tokens::{estimate_tokens, Model};
+use serde_json::Value;
+use std::cmp::Reverse;
+
+/// How [`encode_with_budget`] trims a document that is over its budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BudgetPolicy {
+    /// Drop the same share of rows from every array, spread evenly over
+    /// it and keeping at least one
+    #[default]
+    DropRows,
+    /// Cut every string longer than a limit short, ending it with `…`
+    TruncateStrings,
+    /// Replace the largest objects and arrays with `…`, one at a time
+    ElideSections,
+}
+
+/// A part of the document trimmed to fit the budget
+///
+/// Paths are TOON paths such as `users[3].name`, empty for the root, and
+/// index the document as it was before trimming.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Removal {
+    /// `count` items dropped from the array at `path`
+    Rows { path: String, count: usize },
+    /// `chars` characters cut from the end of the string at `path`
+    Truncated { path: String, chars: usize },
+    /// The object or array at `path` replaced by `…`
+    Elided { path: String },
+}
+
+/// A document encoded within a token budget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Budgeted {
+    /// The TOON text
+    pub toon: String,
+    /// The tokens the text takes, as [`estimate_tokens`] counts them
+    pub tokens: usize,
+    /// What was trimmed, empty when the whole document fit
+    pub removed: Vec<Removal>,
+}
+
+/// Encode `value` in at most `max_tokens` tokens, trimming it by `policy`
+///
+/// Tokens are counted for [`Model::Gpt4`] and the document is encoded
+/// with the default options; [`encode_with_budget_for`] picks both. Fails
+/// if the document doesn't fit even trimmed as far as the policy goes.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::budget::{BudgetPolicy, Removal};
+/// use toon_rust::encode_with_budget;
+///
+/// let logs: Vec<_> = (0..100).map(|i| json!({"id": i, "level": "info"})).collect();
+/// let budgeted = encode_with_budget(&json!({"logs": logs}), 60, BudgetPolicy::DropRows)?;
+/// assert!(budgeted.tokens <= 60);
+/// assert!(matches!(&budgeted.removed[..], [Removal::Rows { path, .. }] if path == "logs"));
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn encode_with_budget(
+    value: &Value,
+    max_tokens: usize,
+    policy: BudgetPolicy,
+) -> Result<Budgeted, Error> {
+    encode_with_budget_for(value, max_tokens, policy, Model::default(), None)
+}
+
+/// Encode `value` with `options` in at most `max_tokens` tokens of
+/// `model`, trimming it by `policy`
+pub fn encode_with_budget_for(
+    value: &Value,
+    max_tokens: usize,
+    policy: BudgetPolicy,
+    model: Model,
+    options: Option<&EncodeOptions>,
+) -> Result<Budgeted, Error> {
+    let budget = Budget {
+        max_tokens,
+        model,
+        options,
+    };
+    if let Some(budgeted) = budget.fit(value, Vec::new())? {
+        return Ok(budgeted);
+    }
+    let trimmed = match policy {
+        BudgetPolicy::DropRows => {
+            let longest = longest_array(value).max(1);
+            budget.search(longest, |level| {
+                let mut removed = Vec::new();
+                // Every array keeps the same share of its rows
+                let keep = |len: usize| (len * level).div_ceil(longest).max(1);
+                let value = keep_rows(value, "", &keep, &mut removed);
+                (value, removed)
+            })?
+        }
+        BudgetPolicy::TruncateStrings => budget.search(longest_string(value), |max_chars| {
+            let mut removed = Vec::new();
+            let value = truncate_strings(value, "", max_chars, &mut removed);
+            (value, removed)
+        })?,
+        BudgetPolicy::ElideSections => elide_sections(value, &budget)?,
+    };
+    trimmed.ok_or_else(|| {
+        Error::Serialization(format!(
+            "The document doesn't fit in {max_tokens} tokens, even trimmed"
+        ))
+    })
+}
+
+struct Budget<'a> {
+    max_tokens: usize,
+    model: Model,
+    options: Option<&'a EncodeOptions>,
+}
+
+impl Budget<'_> {
+    /// `value` encoded, if it fits in the budget
+    fn fit(&self, value: &Value, removed: Vec<Removal>) -> Result<Option<Budgeted>, Error> {
+        let toon = encode(value, self.options)?;
+        let tokens = estimate_tokens(&toon, self.model);
+        Ok((tokens <= self.max_tokens).then_some(Budgeted {
+            toon,
+            tokens,
+            removed,
+        }))
+    }
+
+    /// The document trimmed at the highest level in `0..=max` that fits,
+    /// where `trim` keeps more of it the higher the level
+    fn search(
+        &self,
+        max: usize,
+        trim: impl Fn(usize) -> (Value, Vec<Removal>),
+    ) -> Result<Option<Budgeted>, Error> {
+        let (value, removed) = trim(0);
+        let Some(mut best) = self.fit(&value, removed)? else {
+            return Ok(None);
+        };
+        let (mut low, mut high) = (0, max);
+        while low < high {
+            let level = low + (high - low).div_ceil(2);
+            let (value, removed) = trim(level);
+            match self.fit(&value, removed)? {
+                Some(budgeted) => {
+                    best = budgeted;
+                    low = level;
+                }
+                None => high = level - 1,
+            }
+        }
+        Ok(Some(best))
+    }
+}
+
+fn longest_array(value: &Value) -> usize {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .map(longest_array)
+            .fold(items.len(), usize::max),
+        Value::Object(map) => map.values().map(longest_array).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn longest_string(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.chars().count(),
+        Value::Array(items) => items.iter().map(longest_string).max().unwrap_or(0),
+        Value::Object(map) => map.values().map(longest_string).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn keep_rows(
+    value: &Value,
+    path: &str,
+    keep: &impl Fn(usize) -> usize,
+    removed: &mut Vec<Removal>,
+) -> Value {
+    match value {
+        Value::Array(items) => {
+            let kept = keep(items.len()).min(items.len());
+            if kept < items.len() {
+                removed.push(Removal::Rows {
+                    path: path.to_string(),
+                    count: items.len() - kept,
+                });
+            }
+            // Rows are picked at even steps, starting with the first
+            (0..kept)
+                .map(|i| i * items.len() / kept)
+                .map(|i| keep_rows(&items[i], &index(path, i), keep, removed))
+                .collect()
+        }
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, item)| {
+                    (
+                        key.clone(),
+                        keep_rows(item, &child(path, key), keep, removed),
+                    )
+                })
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+fn truncate_strings(
+    value: &Value,
+    path: &str,
+    max_chars: usize,
+    removed: &mut Vec<Removal>,
+) -> Value {
+    match value {
+        Value::String(s) => {
+            let len = s.chars().count();
+            if len <= max_chars {
+                return value.clone();
+            }
+            removed.push(Removal::Truncated {
+                path: path.to_string(),
+                chars: len - max_chars,
+            });
+            let mut cut: String = s.chars().take(max_chars).collect();
+            cut.push('…');
+            Value::String(cut)
+        }
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| truncate_strings(item, &index(path, i), max_chars, removed))
+            .collect(),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, item)| {
+                    let item = truncate_strings(item, &child(path, key), max_chars, removed);
+                    (key.clone(), item)
+                })
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// An object or array below the root that may be elided
+struct Section {
+    /// JSON Pointer to the section
+    pointer: String,
+    path: String,
+    tokens: usize,
+}
+
+fn elide_sections(value: &Value, budget: &Budget) -> Result<Option<Budgeted>, Error> {
+    let mut sections = Vec::new();
+    collect_sections(value, "", "", budget, &mut sections)?;
+    // Biggest first; a section comes before the smaller ones inside it
+    sections.sort_by_key(|section| Reverse(section.tokens));
+
+    let mut value = value.clone();
+    let mut removed = Vec::new();
+    let mut elided: Vec<String> = Vec::new();
+    for section in sections {
+        let inside = |pointer: &String| section.pointer.starts_with(&format!("{pointer}/"));
+        if elided.iter().any(inside) {
+            continue;
+        }
+        if let Some(target) = value.pointer_mut(&section.pointer) {
+            *target = Value::String("…".to_string());
+        }
+        removed.push(Removal::Elided { path: section.path });
+        elided.push(section.pointer);
+        if let Some(budgeted) = budget.fit(&value, removed.clone())? {
+            return Ok(Some(budgeted));
+        }
+    }
+    Ok(None)
+}
+
+fn collect_sections(
+    value: &Value,
+    pointer: &str,
+    path: &str,
+    budget: &Budget,
+    sections: &mut Vec<Section>,
+) -> Result<(), Error> {
+    let children: Vec<(String, String, &Value)> = match value {
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| (format!("{pointer}/{i}"), index(path, i), item))
+            .collect(),
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, item)| {
+                let token = key.replace('~', "~0").replace('/', "~1");
+                (format!("{pointer}/{token}"), child(path, key), item)
+            })
+            .collect(),
+        _ => return Ok(()),
+    };
+    for (pointer, path, item) in children {
+        if !matches!(item, Value::Array(_) | Value::Object(_)) {
+            continue;
+        }
+        let tokens = estimate_tokens(&encode(item, budget.options)?, budget.model);
+        sections.push(Section {
+            pointer: pointer.clone(),
+            path: path.clone(),
+            tokens,
+        });
+        collect_sections(item, &pointer, &path, budget, sections)?;
+    }
+    Ok(())
+}
+
+fn child(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn index(path: &str, i: usize) -> String {
+    format!("{path}[{i}]")
+}
@@ -0,0 +1,15 @@
+//! Byte strings from binary formats, written as JSON values
+
+use crate::options::BytesPolicy;
+use base64::Engine;
+use serde_json::Value;
+
+/// The value a byte string is written as under `policy`
+pub(crate) fn bytes_value(bytes: &[u8], policy: BytesPolicy) -> Value {
+    match policy {
+        BytesPolicy::Base64 => {
+            Value::String(base64::engine::general_purpose::STANDARD.encode(bytes))
+        }
+        BytesPolicy::Array => Value::Array(bytes.iter().map(|&b| Value::from(b)).collect()),
+    }
+}
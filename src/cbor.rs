@@ -0,0 +1,140 @@
+//! Conversion between TOON and CBOR
+//!
+//! Requires the `cbor` feature. CBOR values are converted straight to and
+//! from the decoded document, with no JSON text in between. Byte strings
+//! are written as set by [`EncodeOptions::bytes_policy`], base64 strings by
+//! default, so binary sensor payloads stay readable as table cells.
+
+use crate::bytes::bytes_value;
+use crate::decode::decode;
+use crate::encode::encode;
+use crate::error::Error;
+use crate::options::{DecodeOptions, EncodeOptions};
+use ciborium::value::Value as Cbor;
+use serde_json::{Map, Number, Value};
+
+/// Convert a CBOR data item to TOON
+///
+/// Tags are dropped, keeping the value they tag, so a tagged date is its
+/// string or epoch number. Integer, boolean and text map keys are kept
+/// as strings; other keys are errors, as are bytes after the item.
+/// Integers beyond 64 bits and floats that aren't finite become floats
+/// and null, as in JSON.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::{cbor_to_toon, toon_to_cbor};
+///
+/// let toon = "readings[2]{sensor,value}:\n  t1,21.5\n  t2,19";
+/// let bytes = toon_to_cbor(toon, None)?;
+/// assert_eq!(cbor_to_toon(&bytes, None)?, "readings[2]{sensor,value}:\n  t1,21.5\n  t2,19\n");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn cbor_to_toon(bytes: &[u8], options: Option<&EncodeOptions>) -> Result<String, Error> {
+    let default_opts = EncodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let mut rest = bytes;
+    let value: Cbor = ciborium::from_reader(&mut rest).map_err(|err| {
+        let message = match err {
+            ciborium::de::Error::Io(err) => format!("Invalid CBOR: {err}"),
+            ciborium::de::Error::Syntax(offset) => format!("Invalid CBOR at byte {offset}"),
+            ciborium::de::Error::Semantic(_, message) => format!("Invalid CBOR: {message}"),
+            ciborium::de::Error::RecursionLimitExceeded => {
+                "Invalid CBOR: nested too deeply".to_string()
+            }
+        };
+        invalid(message, None)
+    })?;
+    if !rest.is_empty() {
+        return Err(invalid(
+            format!("Invalid CBOR: {} bytes after the data item", rest.len()),
+            None,
+        ));
+    }
+    encode(&from_cbor(value, opts, "")?, Some(opts))
+}
+
+/// Convert a TOON document to CBOR
+pub fn toon_to_cbor(input: &str, options: Option<&DecodeOptions>) -> Result<Vec<u8>, Error> {
+    let value = to_cbor(decode(input, options)?);
+    let mut bytes = Vec::new();
+    ciborium::into_writer(&value, &mut bytes).map_err(|e| Error::Serialization(e.to_string()))?;
+    Ok(bytes)
+}
+
+/// The document a CBOR value stands for, with `path` naming it in errors
+fn from_cbor(value: Cbor, options: &EncodeOptions, path: &str) -> Result<Value, Error> {
+    Ok(match value {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(b) => Value::Bool(b),
+        Cbor::Integer(n) => {
+            let n = i128::from(n);
+            i64::try_from(n)
+                .map(Value::from)
+                .or_else(|_| u64::try_from(n).map(Value::from))
+                .unwrap_or_else(|_| float(n as f64))
+        }
+        Cbor::Float(f) => float(f),
+        Cbor::Text(s) => Value::String(s),
+        Cbor::Bytes(bytes) => bytes_value(&bytes, options.get_bytes_policy()),
+        Cbor::Tag(_, value) => from_cbor(*value, options, path)?,
+        Cbor::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| from_cbor(item, options, &format!("{path}[{i}]")))
+                .collect::<Result<_, _>>()?,
+        ),
+        Cbor::Map(entries) => {
+            let mut map = Map::with_capacity(entries.len());
+            for (key, value) in entries {
+                let key = match key {
+                    Cbor::Text(s) => s,
+                    Cbor::Integer(n) => i128::from(n).to_string(),
+                    Cbor::Bool(b) => b.to_string(),
+                    _ => return Err(invalid("Map key is not a string", Some(path))),
+                };
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                map.insert(key, from_cbor(value, options, &child)?);
+            }
+            Value::Object(map)
+        }
+        _ => return Err(invalid("CBOR value has no TOON form", Some(path))),
+    })
+}
+
+fn float(f: f64) -> Value {
+    Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+fn to_cbor(value: Value) -> Cbor {
+    match value {
+        Value::Null => Cbor::Null,
+        Value::Bool(b) => Cbor::Bool(b),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => Cbor::from(u),
+            (None, Some(i)) => Cbor::from(i),
+            (None, None) => Cbor::Float(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => Cbor::Text(s),
+        Value::Array(items) => Cbor::Array(items.into_iter().map(to_cbor).collect()),
+        Value::Object(map) => Cbor::Map(
+            map.into_iter()
+                .map(|(key, value)| (Cbor::Text(key), to_cbor(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn invalid(message: impl Into<String>, path: Option<&str>) -> Error {
+    Error::Deserialization {
+        message: message.into(),
+        path: path.filter(|path| !path.is_empty()).map(str::to_string),
+        line: None,
+    }
+}
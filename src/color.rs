@@ -0,0 +1,120 @@
+//! Colored rendering of errors and lints for terminals (`color` feature)
+//!
+//! [`render_error`] and [`render_lint`] lay a problem out as
+//! [`Error::render`] does, with the source line it points at and carets
+//! under the failure, but in ANSI colors: the severity in red or yellow,
+//! the gutter in blue and the source line highlighted from
+//! [`tokenize`]. The `toon` binary uses them when writing to a terminal.
+
+use crate::error::{caret_padding, Error, Located};
+use crate::lint::Lint;
+use crate::syntax::{tokenize, TokenKind};
+use std::ops::Range;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Render an error with the line of `input` it points at, in color
+///
+/// `input` must be the document that produced the error. Errors without
+/// location information render as a single `error:` line.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::{decode, render_error};
+///
+/// let input = "user:\n  id 1";
+/// let err = decode(input, None).unwrap_err();
+/// let rendered = render_error(&err, input);
+/// assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m"));
+/// assert!(rendered.contains("\x1b[1;34m2 |\x1b[0m"));
+/// ```
+pub fn render_error(err: &Error, input: &str) -> String {
+    match err.locate(input) {
+        Some(located) => snippet(RED, "error", input, &located, 1),
+        None => format!("{RED}error{RESET}{BOLD}: {err}{RESET}\n"),
+    }
+}
+
+/// Render a lint with the line of `input` it points at, in color, with its
+/// span underlined
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::{lint, render_lint};
+///
+/// let input = "tags[3]: a,b";
+/// let rendered = render_lint(&lint(input)[0], input);
+/// assert!(rendered.starts_with("\x1b[1;33mwarning\x1b[0m"));
+/// assert!(rendered.contains("\x1b[1;33m^^^\x1b[0m"));
+/// ```
+pub fn render_lint(lint: &Lint, input: &str) -> String {
+    let located = Located {
+        message: lint.message.clone(),
+        source: input.lines().nth(lint.line - 1).unwrap_or_default(),
+        line: lint.line,
+        column: lint.column,
+    };
+    // Underline the span as far as the end of its first line
+    let width = input[lint.span.clone()]
+        .lines()
+        .next()
+        .map_or(0, |text| text.chars().count());
+    snippet(YELLOW, "warning", input, &located, width.max(1))
+}
+
+fn snippet(color: &str, label: &str, input: &str, at: &Located, width: usize) -> String {
+    let Located {
+        message,
+        source,
+        line,
+        column,
+    } = at;
+    let start: usize = input
+        .split_inclusive('\n')
+        .take(line - 1)
+        .map(str::len)
+        .sum();
+    let gutter = " ".repeat(line.to_string().len());
+    format!(
+        "{color}{label}{RESET}{BOLD}: {message}{RESET}\n\
+         {gutter}{BLUE}-->{RESET} line {line}, column {column}\n\
+         {gutter} {BLUE}|{RESET}\n\
+         {BLUE}{line} |{RESET} {source}\n\
+         {gutter} {BLUE}|{RESET} {padding}{color}{carets}{RESET}\n",
+        source = highlight(input, start..start + source.len()),
+        padding = caret_padding(source, *column),
+        carets = "^".repeat(width),
+    )
+}
+
+/// The text of `line` in `input`, with its tokens colored by kind
+fn highlight(input: &str, line: Range<usize>) -> String {
+    let mut out = String::new();
+    let mut pos = line.start;
+    for token in tokenize(input) {
+        let span = token.span.start.max(pos)..token.span.end.min(line.end);
+        if span.is_empty() {
+            continue;
+        }
+        let color = match token.kind {
+            TokenKind::Key | TokenKind::Field => "\x1b[36m",
+            TokenKind::Length => "\x1b[33m",
+            TokenKind::Scalar => "\x1b[35m",
+            TokenKind::String => "\x1b[32m",
+            TokenKind::Punctuation => "\x1b[2m",
+        };
+        out.push_str(&input[pos..span.start]);
+        out.push_str(color);
+        out.push_str(&input[span.clone()]);
+        out.push_str(RESET);
+        pos = span.end;
+    }
+    out.push_str(&input[pos..line.end]);
+    out
+}
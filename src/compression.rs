@@ -0,0 +1,88 @@
+//! Encoding and decoding of compressed TOON streams
+//!
+//! TOON archives are usually stored compressed, so these wrap the reader or
+//! writer handed to [`encode_stream`] and [`decode_stream`] in a
+//! compressor. The gzip functions require the `gzip` feature and the zstd
+//! ones the `zstd` feature; both use the format's default level.
+
+use crate::decode::decode_stream;
+use crate::encode::encode_stream;
+use crate::error::Error;
+use crate::options::{DecodeOptions, EncodeOptions};
+use serde_json::Value;
+use std::io::{Read, Write};
+
+/// Encode a JSON value to TOON format and write it gzip-compressed
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::{decode_stream_gz, encode_stream_gz};
+///
+/// let value = json!({"name": "Alice", "age": 30});
+/// let mut archive = Vec::new();
+/// encode_stream_gz(&value, &mut archive, None)?;
+/// assert_eq!(decode_stream_gz(archive.as_slice(), None)?, value);
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+#[cfg(feature = "gzip")]
+pub fn encode_stream_gz<W: Write>(
+    value: &Value,
+    writer: &mut W,
+    options: Option<&EncodeOptions>,
+) -> Result<(), Error> {
+    let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+    encode_stream(value, &mut encoder, options)?;
+    encoder.finish().map_err(|e| Error::Io(e.to_string()))?;
+    Ok(())
+}
+
+/// Decode a gzip-compressed TOON stream to a JSON value
+///
+/// Files made of several gzip members, as `cat a.gz b.gz` produces, are
+/// read as one stream.
+#[cfg(feature = "gzip")]
+pub fn decode_stream_gz<R: Read>(
+    reader: R,
+    options: Option<&DecodeOptions>,
+) -> Result<Value, Error> {
+    decode_stream(flate2::read::MultiGzDecoder::new(reader), options)
+}
+
+/// Encode a JSON value to TOON format and write it zstd-compressed
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::{decode_stream_zstd, encode_stream_zstd};
+///
+/// let value = json!({"items": [{"sku": "A1", "qty": 2}]});
+/// let mut archive = Vec::new();
+/// encode_stream_zstd(&value, &mut archive, None)?;
+/// assert_eq!(decode_stream_zstd(archive.as_slice(), None)?, value);
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+#[cfg(feature = "zstd")]
+pub fn encode_stream_zstd<W: Write>(
+    value: &Value,
+    writer: &mut W,
+    options: Option<&EncodeOptions>,
+) -> Result<(), Error> {
+    let mut encoder = zstd::stream::write::Encoder::new(writer, zstd::DEFAULT_COMPRESSION_LEVEL)
+        .map_err(|e| Error::Io(e.to_string()))?;
+    encode_stream(value, &mut encoder, options)?;
+    encoder.finish().map_err(|e| Error::Io(e.to_string()))?;
+    Ok(())
+}
+
+/// Decode a zstd-compressed TOON stream to a JSON value
+#[cfg(feature = "zstd")]
+pub fn decode_stream_zstd<R: Read>(
+    reader: R,
+    options: Option<&DecodeOptions>,
+) -> Result<Value, Error> {
+    let decoder = zstd::stream::read::Decoder::new(reader).map_err(|e| Error::Io(e.to_string()))?;
+    decode_stream(decoder, options)
+}
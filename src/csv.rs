@@ -0,0 +1,373 @@
+//! Conversion between CSV and TOON tables
+//!
+//! A CSV file with a header row is a table already: [`from_csv`] turns the
+//! header into the fields of a TOON table and each record into a row,
+//! reading a record at a time so the data is never held as one `Value`.
+//! [`to_csv`] goes the other way, writing a table from a decoded document
+//! as CSV for spreadsheets.
+
+use crate::error::Error;
+use crate::options::{ColumnType, CsvOptions};
+use crate::pointer;
+use crate::transcode::TableSink;
+use serde_json::{Map, Number, Value};
+use std::borrow::Cow;
+use std::io::{BufRead, Write};
+
+/// Convert CSV with a header row to a TOON table
+///
+/// Fields follow RFC 4180: they are separated by commas, or by
+/// [`CsvOptions::delimiter`], and a field in double quotes may hold
+/// delimiters, line breaks and doubled quotes (`""`). Cells that read as
+/// JSON numbers or `true`/`false` become numbers and booleans, empty cells
+/// become null, and everything else, `04532` included, stays a string.
+/// [`CsvOptions::infer_types`] and [`CsvOptions::empty_as_null`] turn
+/// these guesses off, and [`CsvOptions::column_type`] fixes a column's
+/// type, so a cell of another type is an error; empty cells are null in
+/// any column typed other than `str`. Every record must have as many
+/// fields as the header; blank lines are skipped.
+///
+/// The table is a root array unless [`CsvOptions::key`] names a key for
+/// it. As with [`ndjson_to_toon`](crate::ndjson_to_toon), rows are written
+/// as they are read once lengths are turned off through
+/// [`EncodeOptions::emit_lengths`](crate::EncodeOptions::emit_lengths).
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::from_csv;
+/// use toon_rust::options::CsvOptions;
+///
+/// let csv = "sku,qty,note\nA1,2,\"fragile, glass\"\n04532,1,\n";
+/// let mut toon = Vec::new();
+/// from_csv(csv.as_bytes(), &mut toon, Some(&CsvOptions::new().key("items")))?;
+/// assert_eq!(
+///     String::from_utf8(toon).unwrap(),
+///     "items[2]{sku,qty,note}:\n  A1,2,\"fragile, glass\"\n  \"04532\",1,null\n"
+/// );
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn from_csv<R: BufRead, W: Write>(
+    reader: R,
+    writer: &mut W,
+    options: Option<&CsvOptions>,
+) -> Result<(), Error> {
+    let default_opts = CsvOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let encode_options = opts.get_encode_options();
+    let delimiter = delimiter(opts)?;
+    let table = TableSink::new(writer, &encode_options).under_key(opts.get_key());
+    let mut records = Records::new(reader, delimiter);
+
+    let Some(header) = records.next_record()? else {
+        return table.finish();
+    };
+    let mut columns = Vec::with_capacity(header.fields.len());
+    for (i, name) in header.fields.iter().enumerate() {
+        // Only the first field may carry a byte order mark
+        let name = match i {
+            0 => name.trim_start_matches('\u{feff}').to_string(),
+            _ => name.clone(),
+        };
+        if columns.contains(&name) {
+            return Err(header.error(format!("Duplicate column `{name}`")));
+        }
+        columns.push(name);
+    }
+    for (name, _) in opts.column_types.iter().flatten() {
+        if !columns.contains(name) {
+            return Err(header.error(format!("No column `{name}` to give a type")));
+        }
+    }
+    let types: Vec<_> = columns
+        .iter()
+        .map(|name| opts.get_column_type(name))
+        .collect();
+    let mut table = table.with_columns(columns.clone());
+
+    while let Some(record) = records.next_record()? {
+        if record.fields.len() != columns.len() {
+            return Err(record.error(format!(
+                "Expected {} fields, found {}",
+                columns.len(),
+                record.fields.len()
+            )));
+        }
+        let mut row = Map::with_capacity(columns.len());
+        for ((name, ty), cell) in columns.iter().zip(&types).zip(&record.fields) {
+            let value = match ty {
+                Some(ty) => {
+                    typed(cell, name, *ty, opts).map_err(|message| record.error(message))?
+                }
+                None => untyped(cell, opts),
+            };
+            row.insert(name.clone(), value);
+        }
+        let row = Value::Object(row);
+        table.check(&row).map_err(|message| record.error(message))?;
+        table.write(&row)?;
+    }
+    table.finish()
+}
+
+/// Write the table at `path` in `value` as CSV with a header row
+///
+/// `path` is a JSON Pointer or TOON path, as for [`crate::pointer::get`],
+/// and the empty path is the document itself. The value there must be an
+/// array of objects; the columns are their keys, in the order they first
+/// appear, and a row without one of them has an empty cell. A table
+/// without columns writes nothing. Null is an empty cell, strings are
+/// written as they are, and other values as compact JSON.
+///
+/// Output follows RFC 4180: records end with CRLF, and fields holding the
+/// delimiter ([`CsvOptions::delimiter`]), a quote or a line break are put
+/// in double quotes, with quotes doubled. The other options only apply to
+/// [`from_csv`].
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::{decode, to_csv};
+///
+/// let doc = decode("items[2]{note,sku}:\n  \"fragile, glass\",A1\n  null,B2", None)?;
+/// let mut csv = Vec::new();
+/// to_csv(&doc, "items", &mut csv, None)?;
+/// assert_eq!(
+///     String::from_utf8(csv).unwrap(),
+///     "note,sku\r\n\"fragile, glass\",A1\r\n,B2\r\n"
+/// );
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn to_csv<W: Write>(
+    value: &Value,
+    path: &str,
+    writer: &mut W,
+    options: Option<&CsvOptions>,
+) -> Result<(), Error> {
+    let default_opts = CsvOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let delimiter = delimiter(opts)?;
+    let table = pointer::get(value, path)
+        .ok_or_else(|| Error::Serialization(format!("No value at `{path}`")))?;
+    let not_table = || Error::Serialization(format!("The value at `{path}` is not a table"));
+    let Value::Array(items) = table else {
+        return Err(not_table());
+    };
+    let mut rows = Vec::with_capacity(items.len());
+    let mut columns: Vec<&str> = Vec::new();
+    for item in items {
+        let Value::Object(row) = item else {
+            return Err(not_table());
+        };
+        for key in row.keys() {
+            if !columns.contains(&key.as_str()) {
+                columns.push(key);
+            }
+        }
+        rows.push(row);
+    }
+
+    if columns.is_empty() {
+        return Ok(());
+    }
+
+    let io = |e: std::io::Error| Error::Io(e.to_string());
+    let mut line = String::new();
+    write_record(&mut line, columns.iter().copied(), delimiter);
+    writer.write_all(line.as_bytes()).map_err(io)?;
+    for row in rows {
+        line.clear();
+        let cells = columns.iter().map(|column| match row.get(*column) {
+            None | Some(Value::Null) => Cow::Borrowed(""),
+            Some(Value::String(s)) => Cow::Borrowed(s.as_str()),
+            Some(other) => Cow::Owned(other.to_string()),
+        });
+        write_record(&mut line, cells, delimiter);
+        writer.write_all(line.as_bytes()).map_err(io)?;
+    }
+    writer.flush().map_err(io)
+}
+
+/// Add a CSV record to `line`, quoting fields that need it
+fn write_record(line: &mut String, fields: impl Iterator<Item = impl AsRef<str>>, delimiter: char) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            line.push(delimiter);
+        }
+        let field = field.as_ref();
+        if field.contains([delimiter, '"', '\n', '\r']) {
+            line.push('"');
+            line.push_str(&field.replace('"', "\"\""));
+            line.push('"');
+        } else {
+            line.push_str(field);
+        }
+    }
+    line.push_str("\r\n");
+}
+
+/// The delimiter the options ask for, unless it would clash with quoting or
+/// line breaks
+fn delimiter(options: &CsvOptions) -> Result<char, Error> {
+    match options.get_delimiter() {
+        delimiter @ ('"' | '\n' | '\r') => Err(Error::Syntax(format!(
+            "Invalid CSV delimiter {delimiter:?}"
+        ))),
+        delimiter => Ok(delimiter),
+    }
+}
+
+/// The value a cell of a column without a type stands for
+fn untyped(cell: &str, options: &CsvOptions) -> Value {
+    match cell {
+        "" if options.get_empty_as_null() => Value::Null,
+        _ if !options.get_infer_types() => Value::String(cell.to_string()),
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        // JSON's grammar keeps `04532`, `+1` and `1.` as strings
+        _ => match cell.parse::<Number>() {
+            Ok(n) => Value::Number(n),
+            Err(_) => Value::String(cell.to_string()),
+        },
+    }
+}
+
+/// The value of a cell in a column of type `ty`
+fn typed(cell: &str, column: &str, ty: ColumnType, options: &CsvOptions) -> Result<Value, String> {
+    if cell.is_empty() && (ty != ColumnType::Str || options.get_empty_as_null()) {
+        return Ok(Value::Null);
+    }
+    let value = match ty {
+        ColumnType::Str => Some(Value::String(cell.to_string())),
+        ColumnType::Int => cell.parse::<i64>().ok().map(Value::from),
+        ColumnType::Float => cell
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number),
+        ColumnType::Bool => match cell {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+    };
+    value.ok_or_else(|| format!("Cell {cell} in column '{column}' is not of its type {ty}"))
+}
+
+/// One CSV record and where it started
+struct Record {
+    fields: Vec<String>,
+    /// Byte offset of the record in the input
+    offset: usize,
+    /// 1-based line the record starts on
+    line: usize,
+}
+
+impl Record {
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::parse(self.offset, self.line, 1, message)
+    }
+}
+
+/// Reads CSV records, which may span lines inside quoted fields
+struct Records<R> {
+    reader: R,
+    delimiter: char,
+    offset: usize,
+    line: usize,
+    text: String,
+}
+
+impl<R: BufRead> Records<R> {
+    fn new(reader: R, delimiter: char) -> Self {
+        Records {
+            reader,
+            delimiter,
+            offset: 0,
+            line: 0,
+            text: String::new(),
+        }
+    }
+
+    /// Read one more line onto `text`, returning its length
+    fn read_line(&mut self) -> Result<usize, Error> {
+        let n = self
+            .reader
+            .read_line(&mut self.text)
+            .map_err(|e| Error::Io(e.to_string()))?;
+        if n > 0 {
+            self.line += 1;
+        }
+        Ok(n)
+    }
+
+    /// The next record, skipping blank lines, or `None` at the end
+    fn next_record(&mut self) -> Result<Option<Record>, Error> {
+        loop {
+            self.text.clear();
+            let (offset, line) = (self.offset, self.line + 1);
+            if self.read_line()? == 0 {
+                return Ok(None);
+            }
+            if self.text.trim_end_matches(['\n', '\r']).is_empty() {
+                self.offset += self.text.len();
+                continue;
+            }
+            // A quoted field left open goes on to the next line
+            while open_quote(&self.text) {
+                if self.read_line()? == 0 {
+                    return Err(Error::parse(offset, line, 1, "Unterminated quoted field"));
+                }
+            }
+            self.offset += self.text.len();
+            let text = self.text.trim_end_matches(['\n', '\r']);
+            let fields = split_record(text, self.delimiter)
+                .map_err(|message| Error::parse(offset, line, 1, message))?;
+            return Ok(Some(Record {
+                fields,
+                offset,
+                line,
+            }));
+        }
+    }
+}
+
+/// Whether `text` ends inside a quoted field
+fn open_quote(text: &str) -> bool {
+    // Quotes inside a quoted field come in pairs, so an odd count leaves
+    // one open
+    text.chars().filter(|&ch| ch == '"').count() % 2 == 1
+}
+
+/// Split a record into its fields, unquoting quoted ones
+fn split_record(text: &str, delimiter: char) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut chars = text.chars().peekable();
+    loop {
+        let mut field = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(ch) => field.push(ch),
+                    None => return Err("Unterminated quoted field".to_string()),
+                }
+            }
+            if chars.peek().is_some_and(|&ch| ch != delimiter) {
+                return Err("Unexpected text after a quoted field".to_string());
+            }
+        } else {
+            while let Some(ch) = chars.next_if(|&ch| ch != delimiter) {
+                if ch == '"' {
+                    return Err("Unexpected quote inside a field".to_string());
+                }
+                field.push(ch);
+            }
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
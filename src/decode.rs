@@ -1,10 +1,16 @@
 //! Decoding TOON format to JSON values
 
+use crate::encode::non_finite_marker;
 use crate::error::Error;
-use crate::options::DecodeOptions;
+use crate::options::{
+    ColumnType, DecodeOptions, DuplicateKeyPolicy, EmptyCellPolicy, LengthPolicy, Progress, RawRow,
+    SpecVersion,
+};
 use crate::simd;
 use serde_json::{Map, Value};
-use std::io::{BufReader, Read};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader, Read};
 
 /// Decode a TOON-formatted string to a JSON value
 ///
@@ -16,56 +22,2443 @@ use std::io::{BufReader, Read};
 /// # Returns
 ///
 /// A `Result` containing the decoded JSON value or an error
+///
+/// With `strict(false)`, blank lines and trailing whitespace are ignored and
+/// the indentation unit is inferred from the document, so error positions
+/// refer to the normalized text.
 pub fn decode(input: &str, options: Option<&DecodeOptions>) -> Result<Value, Error> {
     let default_opts = DecodeOptions::default();
     let opts = options.unwrap_or(&default_opts);
-    let mut parser = Parser::new(input, opts);
+    let input = prepare_input(input, opts);
+    let mut parser = Parser::new(&input, opts);
     parser.parse()
 }
 
-struct Parser<'a> {
-    input: &'a str,
-    pos: usize,
-    options: &'a DecodeOptions,
-}
+/// Re-indent a custom indent unit as spaces and normalize whitespace for
+/// non-strict decoding, borrowing the input when neither applies
+pub(crate) fn prepare_input<'a>(input: &'a str, options: &DecodeOptions) -> Cow<'a, str> {
+    let input = match indent_unit(options) {
+        Some(unit) => {
+            let mut expanded = String::with_capacity(input.len());
+            for line in input.split_inclusive('\n') {
+                expand_indent(line, unit, options.get_indent(), &mut expanded);
+            }
+            Cow::Owned(expanded)
+        }
+        None => Cow::Borrowed(input),
+    };
+    if options.get_strict() {
+        return input;
+    }
+    let mut normalizer = IndentNormalizer::new(options.get_indent());
+    let mut normalized = String::with_capacity(input.len());
+    for line in input.lines() {
+        normalizer.push_line(line, &mut normalized);
+    }
+    Cow::Owned(normalized)
+}
+
+/// The indent string to replace with spaces before parsing, if it isn't
+/// made of spaces already
+pub(crate) fn indent_unit(options: &DecodeOptions) -> Option<&str> {
+    options
+        .get_indent_string()
+        .filter(|unit| !unit.is_empty() && unit.bytes().any(|byte| byte != b' '))
+}
+
+/// Push `line` with each leading `unit` replaced by `width` spaces
+pub(crate) fn expand_indent(line: &str, unit: &str, width: usize, out: &mut String) {
+    let mut rest = line;
+    while let Some(after) = rest.strip_prefix(unit) {
+        out.push_str(&" ".repeat(width));
+        rest = after;
+    }
+    out.push_str(rest);
+}
+
+/// Re-levels loosely formatted lines for non-strict decoding
+///
+/// Blank lines are dropped, trailing spaces are trimmed, and leading
+/// whitespace is rewritten as multiples of the configured indent. Levels are
+/// inferred from the document itself: a deeper line opens a new level, and a
+/// line that falls between two open levels joins the enclosing one.
+///
+/// Within a table's declared length a blank line is a row with one empty
+/// cell, as the encoder writes a null in a one-column table, so it is kept
+/// and indented to the table's rows.
+pub(crate) struct IndentNormalizer {
+    indent: usize,
+    /// Leading widths of the currently open levels, outermost first
+    levels: Vec<usize>,
+    /// Open block string body, whose lines are passed through verbatim
+    block: Option<BlockBody>,
+    /// Table whose declared rows haven't all been read
+    table: Option<TableRows>,
+    /// Leading width of a list item whose first field may open a nested
+    /// value, and the width its other fields are indented by: a line
+    /// indented further belongs to the first field, two levels below the item
+    item: Option<(usize, usize)>,
+}
+
+/// Rows of a table being normalized
+struct TableRows {
+    /// Normalized level of the table's header line
+    header: usize,
+    /// Normalized level of its rows: one deeper, or two for the first field
+    /// of a list item
+    rows: usize,
+    /// Rows declared by the header and not read yet
+    remaining: usize,
+}
+
+/// Layout of a block string body being normalized
+struct BlockBody {
+    /// Leading width of the `key: |` field in the source, which for the
+    /// first field of a list item is that of the item's other fields
+    key_width: usize,
+    /// Normalized level of the `key: |` field, one below its `- ` marker
+    /// when it's the first field of a list item
+    level: usize,
+    /// Leading width of the first body line in the source
+    body_width: Option<usize>,
+}
+
+impl IndentNormalizer {
+    pub(crate) fn new(indent: usize) -> Self {
+        Self {
+            indent,
+            levels: vec![0],
+            block: None,
+            table: None,
+            item: None,
+        }
+    }
+
+    pub(crate) fn push_line(&mut self, line: &str, out: &mut String) {
+        self.push_line_with(line, out, |content, _, out| out.push_str(content));
+    }
+
+    /// Push a line as [`push_line`](Self::push_line) does, writing the
+    /// content of lines outside block strings through `write`, which is
+    /// also given the line's level
+    pub(crate) fn push_line_with(
+        &mut self,
+        line: &str,
+        out: &mut String,
+        write: impl FnOnce(&str, usize, &mut String),
+    ) {
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if self.push_block_line(line, out) {
+            return;
+        }
+        // A trailing tab may delimit an empty last cell, so only spaces go
+        let line = line.trim_end_matches(' ');
+        let content = line.trim_start_matches([' ', '\t']);
+        if content.is_empty() {
+            if let Some(table) = self.table.as_mut().filter(|table| table.remaining > 0) {
+                table.remaining -= 1;
+                out.push_str(&" ".repeat(table.rows * self.indent));
+                out.push('\n');
+            }
+            return;
+        }
+        let width = line.len() - content.len();
+        while self.levels.last().is_some_and(|&open| open > width) {
+            self.levels.pop();
+        }
+        if let Some((item, step)) = self.item.take() {
+            // Leave a level between the item and its first field's value
+            // for the item's other fields
+            if width > item + step && self.levels.last() == Some(&item) {
+                self.levels.push(item + step);
+            }
+        }
+        if self.levels.last().copied().unwrap_or(0) < width {
+            self.levels.push(width);
+        }
+        let level = self.levels.len() - 1;
+        out.push_str(&" ".repeat(level * self.indent));
+        write(content, level, out);
+        out.push('\n');
+
+        match &mut self.table {
+            // An elision marker ends the table, leaving no rows to come
+            Some(table)
+                if level > table.header
+                    && elided_rows(content, &DecodeOptions::new().strict(false)).is_some() =>
+            {
+                table.remaining = 0;
+            }
+            Some(table) if level > table.header => {
+                table.remaining = table.remaining.saturating_sub(1);
+            }
+            _ => self.table = None,
+        }
+        // The first field of an object list item follows its `- ` marker
+        let field = content
+            .strip_prefix("- ")
+            .map(str::trim_start)
+            .filter(|field| !field.starts_with('['));
+        if let Some(remaining) = table_length(field.unwrap_or(content)) {
+            self.table = Some(TableRows {
+                header: level,
+                rows: level + 1 + usize::from(field.is_some()),
+                remaining,
+            });
+        }
+        // The item's other fields are a step deeper than it, as the item
+        // is than its parent
+        let step = field.map(|_| {
+            let parent = self.levels.len().checked_sub(2).map(|i| self.levels[i]);
+            parent.map_or(self.indent, |parent| width - parent)
+        });
+        if field.is_some_and(|field| field.ends_with(':')) {
+            self.item = step.map(|step| (width, step));
+        }
+
+        let header = content.rsplit_once(':').map(|(_, rest)| rest.trim());
+        if header.is_some_and(|rest| block_header(rest).is_some()) {
+            self.block = Some(BlockBody {
+                key_width: width + step.unwrap_or_default(),
+                level: level + usize::from(field.is_some()),
+                body_width: None,
+            });
+        }
+    }
+
+    /// Re-indent a line of an open block string body, keeping its content
+    /// exactly; returns false once the line falls outside the block
+    fn push_block_line(&mut self, line: &str, out: &mut String) -> bool {
+        let Some(block) = &mut self.block else {
+            return false;
+        };
+        let content = line.trim_start_matches([' ', '\t']);
+        let width = line.len() - content.len();
+        if content.is_empty() {
+            out.push('\n');
+            return true;
+        }
+        if width <= block.key_width {
+            self.block = None;
+            return false;
+        }
+        let body_width = *block.body_width.get_or_insert(width);
+        out.push_str(&" ".repeat((block.level + 1) * self.indent));
+        out.push_str(&line[width.min(body_width)..]);
+        out.push('\n');
+        true
+    }
+}
+
+/// Declared length of a `key[N]{fields}:` table header line, which ends at
+/// its colon
+fn table_length(content: &str) -> Option<usize> {
+    let head = content.strip_suffix(':')?.strip_suffix('}')?;
+    let open = head.rfind("]{")?;
+    let bracket = head[..open].rfind('[')?;
+    head[bracket + 1..open]
+        .trim_start_matches('#')
+        .trim_end_matches([',', '|', '\t'])
+        .parse()
+        .ok()
+}
+
+/// `Read` adapter that replaces a custom indent unit with spaces and, in
+/// non-strict mode, applies [`IndentNormalizer`], line by line
+struct NormalizedReader<R> {
+    reader: BufReader<R>,
+    /// Whitespace normalization, in non-strict mode
+    normalizer: Option<IndentNormalizer>,
+    /// Custom indent unit to replace with spaces, and the spaces per level
+    unit: Option<(String, usize)>,
+    line: String,
+    expanded: String,
+    out: String,
+    pos: usize,
+}
+
+impl<R: Read> NormalizedReader<R> {
+    fn new(reader: R, options: &DecodeOptions) -> Self {
+        let indent = options.get_indent();
+        Self {
+            reader: BufReader::new(reader),
+            normalizer: (!options.get_strict()).then(|| IndentNormalizer::new(indent)),
+            unit: indent_unit(options).map(|unit| (unit.to_string(), indent)),
+            line: String::new(),
+            expanded: String::new(),
+            out: String::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for NormalizedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.out.len() {
+            self.out.clear();
+            self.pos = 0;
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(0);
+            }
+            let line = match &self.unit {
+                Some((unit, width)) => {
+                    self.expanded.clear();
+                    expand_indent(&self.line, unit, *width, &mut self.expanded);
+                    &self.expanded
+                }
+                None => &self.line,
+            };
+            match &mut self.normalizer {
+                Some(normalizer) => normalizer.push_line(line, &mut self.out),
+                None => self.out.push_str(line),
+            }
+        }
+        let n = buf.len().min(self.out.len() - self.pos);
+        buf[..n].copy_from_slice(&self.out.as_bytes()[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Decode a TOON-formatted string, collecting every recoverable error
+///
+/// Instead of stopping at the first problem, the decoder records it and
+/// carries on: malformed rows and list items are skipped, lines with a bad
+/// `key: value` are dropped, and array length mismatches keep the items that
+/// were found. The result holds everything that could be parsed, alongside
+/// the full list of errors in document order.
+///
+/// If the document is unusable from the start (for example a broken root
+/// array header), the value is `Value::Null` and the list holds that error.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::decode_all_errors;
+///
+/// let (value, errors) = decode_all_errors("a: 1\nb 2\nc: 3", None);
+/// assert_eq!(value, json!({"a": 1, "c": 3}));
+/// assert_eq!(errors.len(), 1);
+/// ```
+pub fn decode_all_errors(input: &str, options: Option<&DecodeOptions>) -> (Value, Vec<Error>) {
+    let default_opts = DecodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let input = prepare_input(input, opts);
+    let mut parser = Parser::new(&input, opts);
+    parser.errors = Some(Vec::new());
+    let result = parser.parse();
+    let mut errors = parser.errors.take().unwrap_or_default();
+    match result {
+        Ok(value) => (value, errors),
+        Err(err) => {
+            errors.push(err);
+            (Value::Null, errors)
+        }
+    }
+}
+
+/// Decode the value of one top-level key, without parsing the rest
+///
+/// The document is scanned line by line for the entries at the top level,
+/// and only those under `key` are parsed; everything else is skipped over
+/// without being decoded, so a large document holding one wanted table
+/// costs little more than reading it. Returns `None` when the key is absent
+/// or the document isn't an object. Repeated keys follow
+/// [`DecodeOptions::duplicate_keys`], and with
+/// [`expand_paths`](DecodeOptions::expand_paths) folded entries such as
+/// `key.inner: 1` are gathered too.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::decode_key;
+///
+/// let toon = "meta: \n  source: erp\nitems[2]{sku,qty}:\n  A1,2\n  B2,1\ncount: 2";
+/// let items = decode_key(toon, "items", None).unwrap();
+/// assert_eq!(items, Some(json!([{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}])));
+/// assert_eq!(decode_key(toon, "missing", None).unwrap(), None);
+/// ```
+pub fn decode_key(
+    input: &str,
+    key: &str,
+    options: Option<&DecodeOptions>,
+) -> Result<Option<Value>, Error> {
+    let default_opts = DecodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let input = prepare_input(input, opts);
+    if input.trim_start().starts_with('[') {
+        return Ok(None);
+    }
+    decode_entries(&input, &top_level_entries(&input), key, opts, false)
+}
+
+/// Decode the value of one top-level key from those of the given entries
+/// that hold it, skipping the others
+///
+/// With `select`, [`DecodeOptions::include_keys`] narrows the value as
+/// [`decode`] does, and a key it leaves out gives `None`.
+pub(crate) fn decode_entries(
+    input: &str,
+    entries: &[(usize, usize)],
+    key: &str,
+    opts: &DecodeOptions,
+    select: bool,
+) -> Result<Option<Value>, Error> {
+    let folded = format!("{key}.");
+    let mut map = Map::new();
+    for &(start, end) in entries {
+        let mut parser = Parser::new(&input[..end], opts);
+        parser.pos = start;
+        let Ok((entry_key, quoted)) = parser.parse_entry_key() else {
+            continue;
+        };
+        let wanted = entry_key == key
+            || (opts.get_expand_paths() && !quoted && entry_key.starts_with(&folded));
+        if !wanted {
+            continue;
+        }
+        parser.pos = start;
+        if select && opts.get_include_keys().is_some() {
+            parser.include = Some(String::new());
+        }
+        if map.contains_key(&entry_key) && is_duplicate_error(opts) {
+            return Err(parser.error(format!("Duplicate key '{entry_key}'")));
+        }
+        if let Value::Object(entry) = parser.parse_object()? {
+            for (entry_key, value) in entry {
+                // Already expanded by the parser
+                insert_key(opts, &mut map, entry_key, true, value);
+            }
+        }
+    }
+    Ok(map.remove(key))
+}
+
+/// Decode the value at a path of keys, parsing only the top-level entry
+/// the path starts in
+///
+/// The first segment is looked up with [`decode_key`]; the rest index into
+/// the decoded value, as object keys or as array positions. Returns `None`
+/// when any segment is missing.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::decode_path;
+///
+/// let toon = "user: \n  name: Ann\n  tags[2]: a,b\nother: 1";
+/// assert_eq!(decode_path(toon, &["user", "name"], None).unwrap(), Some(json!("Ann")));
+/// assert_eq!(decode_path(toon, &["user", "tags", "1"], None).unwrap(), Some(json!("b")));
+/// ```
+pub fn decode_path(
+    input: &str,
+    path: &[&str],
+    options: Option<&DecodeOptions>,
+) -> Result<Option<Value>, Error> {
+    let Some((first, rest)) = path.split_first() else {
+        return decode(input, options).map(Some);
+    };
+    let Some(mut value) = decode_key(input, first, options)? else {
+        return Ok(None);
+    };
+    for segment in rest {
+        let next = match &mut value {
+            Value::Object(map) => map.remove(*segment),
+            Value::Array(items) => segment
+                .parse::<usize>()
+                .ok()
+                .filter(|&index| index < items.len())
+                .map(|index| items.swap_remove(index)),
+            _ => None,
+        };
+        let Some(next) = next else {
+            return Ok(None);
+        };
+        value = next;
+    }
+    Ok(Some(value))
+}
+
+/// The key of the top-level entry at `start..end`, and where it ends
+pub(crate) fn entry_key(
+    input: &str,
+    (start, end): (usize, usize),
+    options: &DecodeOptions,
+) -> Result<(String, usize), Error> {
+    let mut parser = Parser::new(&input[..end], options);
+    parser.pos = start;
+    let key = parser.parse_key()?;
+    Ok((key, parser.pos))
+}
+
+/// Byte ranges of the top-level entries of a document: each starts at an
+/// unindented line and runs up to the next one
+///
+/// Quoted values spanning lines are stepped over as a whole, so their text
+/// never starts an entry.
+pub(crate) fn top_level_entries(input: &str) -> Vec<(usize, usize)> {
+    let mut entries = Vec::new();
+    let mut start = None;
+    let mut pos = 0;
+    while pos < input.len() {
+        let len = row_len(&input[pos..], true).unwrap_or(input.len() - pos);
+        let line = &input[pos..pos + len];
+        if !line.trim().is_empty() && !line.starts_with([' ', '\t']) {
+            if let Some(start) = start.replace(pos) {
+                entries.push((start, pos));
+            }
+        }
+        pos += len + 1;
+    }
+    if let Some(start) = start {
+        entries.push((start, input.len()));
+    }
+    entries
+}
+
+/// Lazily decode the rows of one tabular array
+///
+/// `key` names a top-level tabular array (`items[N]{...}:`); an empty key
+/// selects a root array (`[N]{...}:`). Rows are parsed one at a time as the
+/// iterator advances, so a table never has to be held in memory as a whole.
+///
+/// The table header is read on the first call to `next`, and a missing or
+/// malformed header is yielded as the only item. A malformed row is yielded
+/// as an error and iteration continues with the next one. If the table ends
+/// early and the length policy is [`LengthPolicy::Error`], the last item is a
+/// length mismatch error.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::decode_rows;
+///
+/// let toon = "count: 2\nitems[2]{sku,qty}:\n  A1,2\n  B2,1";
+/// let mut total = 0;
+/// for row in decode_rows(toon, "items", None) {
+///     total += row.unwrap()["qty"].as_i64().unwrap();
+/// }
+/// assert_eq!(total, 3);
+/// ```
+pub fn decode_rows<'a>(input: &'a str, key: &str, options: Option<&'a DecodeOptions>) -> Rows<'a> {
+    let options = match options {
+        Some(options) => Cow::Borrowed(options),
+        None => Cow::Owned(DecodeOptions::default()),
+    };
+    Rows {
+        input: prepare_input(input, &options),
+        options,
+        key: key.to_string(),
+        pos: 0,
+        cells: 0,
+        table: None,
+        done: false,
+    }
+}
+
+/// Iterator over the rows of a tabular array, created by [`decode_rows`]
+pub struct Rows<'a> {
+    input: Cow<'a, str>,
+    options: Cow<'a, DecodeOptions>,
+    key: String,
+    pos: usize,
+    /// Cells decoded so far, checked against `max_cells`
+    cells: usize,
+    /// Header of the table, once it has been found
+    table: Option<TableHeader>,
+    done: bool,
+}
+
+/// Header of the table being iterated by [`Rows`]
+#[derive(Debug, Clone, PartialEq)]
+struct TableHeader {
+    fields: Vec<String>,
+    columns: Vec<Column>,
+    delimiter: char,
+    base_indent: usize,
+    expected: Option<usize>,
+    read: usize,
+}
+
+impl Rows<'_> {
+    /// Find the table's header line and parse it
+    fn read_header(&mut self) -> Result<TableHeader, Error> {
+        // The key may be written bare or quoted
+        let quoted = serde_json::to_string(&self.key).unwrap_or_default();
+        let mut offset = 0;
+        let start = self
+            .input
+            .split_inclusive('\n')
+            .find_map(|line| {
+                let found = [self.key.as_str(), quoted.as_str()]
+                    .into_iter()
+                    .find_map(|key| {
+                        line.strip_prefix(key)
+                            .filter(|rest| opens_array(rest.chars().next(), &self.options))
+                            .map(|_| offset + key.len())
+                    });
+                offset += line.len();
+                found
+            })
+            .ok_or_else(|| Error::parse(0, 1, 1, format!("No tabular array '{}'", self.key)))?;
+
+        let mut parser = Parser::new(&self.input, &self.options);
+        parser.pos = start;
+        let (expected, declared) = if parser.peek_char() == Some('[') {
+            parser.parse_array_length()?
+        } else {
+            (None, None)
+        };
+        if parser.peek_char() != Some('{') {
+            return Err(parser.error(format!("'{}' is not a tabular array", self.key)));
+        }
+        let ((fields, columns), delimiter, base_indent) = parser.parse_table_header(declared)?;
+        let header = TableHeader {
+            fields,
+            columns,
+            delimiter,
+            base_indent,
+            expected,
+            read: 0,
+        };
+        self.pos = parser.pos;
+        Ok(header)
+    }
+
+    /// Parse the next row, `Ok(None)` for one the row filter rejects, or
+    /// return `None` once the table ends
+    fn next_row(&mut self) -> Option<Result<Option<Map<String, Value>>, Error>> {
+        let table = self.table.as_mut()?;
+        if table
+            .expected
+            .is_some_and(|expected| table.read >= expected)
+        {
+            return None;
+        }
+
+        let mut parser = Parser::new(&self.input, &self.options);
+        parser.pos = self.pos;
+        parser.cells = self.cells;
+        let Some(row) = parser.next_table_row(table.base_indent) else {
+            return match table.expected {
+                Some(expected) if self.options.get_length_policy() == LengthPolicy::Error => {
+                    table.expected = None;
+                    Some(Err(Error::LengthMismatch {
+                        expected,
+                        found: table.read,
+                    }))
+                }
+                _ => None,
+            };
+        };
+        if let Some(count) = elided_rows(row, &self.options) {
+            // The rows left out of a preview end the table
+            if parser.peek_char() == Some('\n') {
+                parser.advance();
+            }
+            self.pos = parser.pos;
+            table.read += elided_count(&self.options, count);
+            return match table.expected {
+                Some(expected)
+                    if expected != table.read
+                        && self.options.get_strict()
+                        && self.options.get_length_policy() == LengthPolicy::Error =>
+                {
+                    table.expected = None;
+                    Some(Err(Error::LengthMismatch {
+                        expected,
+                        found: table.read,
+                    }))
+                }
+                _ => None,
+            };
+        }
+        table.read += 1;
+        let fields: Vec<&str> = table.fields.iter().map(String::as_str).collect();
+        let result = parser
+            .count_cells(fields.len())
+            .and_then(|()| parser.parse_row(row, &fields, &table.columns, table.delimiter));
+        // Skip to next line
+        if parser.peek_char() == Some('\n') {
+            parser.advance();
+        }
+        self.pos = parser.pos;
+        self.cells = parser.cells;
+        Some(result)
+    }
+}
+
+impl Iterator for Rows<'_> {
+    type Item = Result<Map<String, Value>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.table.is_none() {
+            match self.read_header() {
+                Ok(table) => self.table = Some(table),
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        let row = loop {
+            match self.next_row() {
+                Some(Ok(None)) => continue,
+                Some(Ok(Some(row))) => break Some(Ok(row)),
+                Some(Err(err)) => break Some(Err(err)),
+                None => break None,
+            }
+        };
+        if row.is_none() {
+            self.done = true;
+        }
+        row
+    }
+}
+
+/// Decode the rows of one tabular array as they are read from a stream
+///
+/// This is [`decode_rows`] over a reader: lines are read one at a time and
+/// each row is yielded as soon as its line is complete, so only the current
+/// line is held. `key` names a top-level tabular array, or is empty for a
+/// root array.
+///
+/// With [`DecodeOptions::follow`] set, reaching the end of the input waits
+/// and reads again instead of ending the table, like `tail -f`, so rows
+/// appended to a growing log file are picked up as they are written. A
+/// followed table ends once its declared length is reached or a line with
+/// less indentation than the rows is read; otherwise iteration blocks until
+/// more input arrives, so stop by dropping the iterator.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::decode_rows_stream;
+///
+/// let log = "events[2]{level,msg}:\n  info,started\n  warn,disk low\n";
+/// let levels: Vec<String> = decode_rows_stream(log.as_bytes(), "events", None)
+///     .map(|row| row.unwrap()["level"].as_str().unwrap().to_string())
+///     .collect();
+/// assert_eq!(levels, ["info", "warn"]);
+/// ```
+///
+/// Following a log file:
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use std::time::Duration;
+/// use toon_rust::{decode_rows_stream, DecodeOptions};
+///
+/// let options = DecodeOptions::new()
+///     .lenient_arrays(true)
+///     .follow(Duration::from_millis(200));
+/// let file = File::open("events.toon").unwrap();
+/// for row in decode_rows_stream(file, "events", Some(&options)) {
+///     println!("{}", serde_json::Value::Object(row.unwrap()));
+/// }
+/// ```
+pub fn decode_rows_stream<'a, R: Read>(
+    reader: R,
+    key: &str,
+    options: Option<&'a DecodeOptions>,
+) -> StreamRows<'a, R> {
+    let options = match options {
+        Some(options) => Cow::Borrowed(options),
+        None => Cow::Owned(DecodeOptions::default()),
+    };
+    StreamRows {
+        reader: BufReader::new(reader),
+        options,
+        key: key.to_string(),
+        partial: Vec::new(),
+        consumed: 0,
+        line_start: 0,
+        line_number: 0,
+        row_lines: 0,
+        cells: 0,
+        table: None,
+        done: false,
+    }
+}
+
+/// Iterator over the rows of a tabular array read from a stream, created
+/// by [`decode_rows_stream`]
+pub struct StreamRows<'a, R: Read> {
+    reader: BufReader<R>,
+    options: Cow<'a, DecodeOptions>,
+    key: String,
+    /// Bytes of a line whose end hasn't been read yet
+    partial: Vec<u8>,
+    /// Bytes read as complete lines so far
+    consumed: usize,
+    /// Byte offset and 1-based number of the last line read, for errors
+    line_start: usize,
+    line_number: usize,
+    /// Lines after the first of the last row read, whose quoted cells
+    /// spanned them
+    row_lines: usize,
+    /// Cells decoded so far, checked against `max_cells`
+    cells: usize,
+    /// Header of the table, once it has been found
+    table: Option<TableHeader>,
+    done: bool,
+}
+
+impl<R: Read> StreamRows<'_, R> {
+    /// Record how far the iterator has got, so a later run can carry on
+    /// from the next row with [`resume_rows_stream`]
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            key: self.key.clone(),
+            offset: self.consumed,
+            line_number: self.line_number,
+            cells: self.cells,
+            table: self.table.clone(),
+            done: self.done,
+        }
+    }
+
+    /// Read the next line, without its line break, or `None` at the end
+    ///
+    /// At the end of the input this waits and reads again when following,
+    /// keeping an unfinished line until the rest of it arrives; otherwise an
+    /// unfinished line is returned as the last one.
+    fn read_line(&mut self) -> Result<Option<String>, Error> {
+        loop {
+            let n = self
+                .reader
+                .read_until(b'\n', &mut self.partial)
+                .map_err(|e| Error::Io(e.to_string()))?;
+            if self.partial.last() != Some(&b'\n') {
+                if n > 0 {
+                    continue;
+                }
+                if let Some(interval) = self.options.get_follow() {
+                    std::thread::sleep(interval);
+                    continue;
+                }
+                if self.partial.is_empty() {
+                    return Ok(None);
+                }
+            }
+            let bytes = std::mem::take(&mut self.partial);
+            self.line_start = self.consumed;
+            self.row_lines = 0;
+            self.consumed += bytes.len();
+            self.line_number += 1;
+            let mut line = String::from_utf8(bytes)
+                .map_err(|e| Error::Io(format!("Invalid UTF-8: {}", e.utf8_error())))?;
+            while line.ends_with(['\n', '\r']) {
+                line.pop();
+            }
+            return Ok(Some(line));
+        }
+    }
+
+    /// Read the next table row, or `None` at the end
+    ///
+    /// Like [`decode_rows`], leading indent strings are read as spaces and
+    /// a quoted cell may span lines, which are joined with their line
+    /// breaks.
+    fn read_row(&mut self) -> Result<Option<String>, Error> {
+        let Some(line) = self.read_line()? else {
+            return Ok(None);
+        };
+        let (start, mut row) = (self.line_start, self.expand(&line));
+        row.push('\n');
+        let mut extra = 0;
+        let len = loop {
+            if let Some(len) = row_len(&row, false) {
+                break len;
+            }
+            match self.read_line()? {
+                Some(next) => {
+                    row.push_str(&self.expand(&next));
+                    row.push('\n');
+                    extra += 1;
+                }
+                None => break row_len(&row, true).unwrap_or(row.len()),
+            }
+        };
+        row.truncate(len);
+        self.line_start = start;
+        self.row_lines = extra;
+        Ok(Some(row))
+    }
+
+    /// `line` with its leading indent strings written as spaces
+    fn expand(&self, line: &str) -> String {
+        let mut out = String::with_capacity(line.len());
+        match indent_unit(&self.options) {
+            Some(unit) => expand_indent(line, unit, self.options.get_indent(), &mut out),
+            None => out.push_str(line),
+        }
+        out
+    }
+
+    /// Give an error from parsing the last row its place in the stream
+    fn locate(&self, err: Error) -> Error {
+        match err {
+            Error::Parse {
+                position,
+                line,
+                column,
+                message,
+            } => Error::parse(
+                self.line_start + position,
+                self.line_number - self.row_lines + line.saturating_sub(1),
+                column,
+                message,
+            ),
+            err => err,
+        }
+    }
+
+    /// Read up to the table's header line and parse it
+    fn read_header(&mut self) -> Result<TableHeader, Error> {
+        // The key may be written bare or quoted
+        let quoted = serde_json::to_string(&self.key).unwrap_or_default();
+        loop {
+            let Some(line) = self.read_line()? else {
+                return Err(Error::parse(
+                    self.consumed,
+                    self.line_number + 1,
+                    1,
+                    format!("No tabular array '{}'", self.key),
+                ));
+            };
+            let Some(start) = [self.key.as_str(), quoted.as_str()]
+                .into_iter()
+                .find(|key| {
+                    line.strip_prefix(key)
+                        .is_some_and(|rest| opens_array(rest.chars().next(), &self.options))
+                })
+                .map(str::len)
+            else {
+                continue;
+            };
+
+            let mut parser = Parser::new(&line, &self.options);
+            parser.pos = start;
+            let header = (|| {
+                let (expected, declared) = if parser.peek_char() == Some('[') {
+                    parser.parse_array_length()?
+                } else {
+                    (None, None)
+                };
+                if parser.peek_char() != Some('{') {
+                    return Err(parser.error(format!("'{}' is not a tabular array", self.key)));
+                }
+                let ((fields, columns), delimiter, _) = parser.parse_table_header(declared)?;
+                Ok(TableHeader {
+                    fields,
+                    columns,
+                    delimiter,
+                    // Rows sit one level below the top-level header
+                    base_indent: 1,
+                    expected,
+                    read: 0,
+                })
+            })();
+            return header.map_err(|err| self.locate(err));
+        }
+    }
+
+    /// Parse the next row, `Ok(None)` for one the row filter rejects, or
+    /// return `None` once the table ends
+    fn next_row(&mut self) -> Option<Result<Option<Map<String, Value>>, Error>> {
+        let table = self.table.as_ref()?;
+        if table
+            .expected
+            .is_some_and(|expected| table.read >= expected)
+        {
+            return None;
+        }
+
+        // A blank line indented to the rows holds an empty cell, as the
+        // encoder writes a null in a one-column table; other blank lines
+        // are skipped, except that without strictness any blank line within
+        // a declared length is a row, as `decode_rows` reads the normalized
+        // text
+        let blank_row = " ".repeat(table.base_indent * self.options.get_indent());
+        let declared = table.expected.is_some() && !self.options.get_strict();
+        let line = loop {
+            match self.read_row() {
+                Ok(Some(line)) if line.trim().is_empty() && line.len() < blank_row.len() => {
+                    if declared {
+                        break Some(blank_row);
+                    }
+                }
+                Ok(line) => break line,
+                Err(err) => return Some(Err(err)),
+            }
+        };
+        let options = &*self.options;
+        let table = self.table.as_mut()?;
+        if let Some(count) = line.as_deref().and_then(|line| elided_rows(line, options)) {
+            // The rows left out of a preview end the table
+            table.read += elided_count(options, count);
+            return match table.expected {
+                Some(expected)
+                    if expected != table.read
+                        && options.get_strict()
+                        && options.get_length_policy() == LengthPolicy::Error =>
+                {
+                    table.expected = None;
+                    Some(Err(Error::LengthMismatch {
+                        expected,
+                        found: table.read,
+                    }))
+                }
+                _ => None,
+            };
+        }
+        let row = line.and_then(|line| {
+            let mut parser = Parser::new(&line, options);
+            let indent = options.get_indent();
+            let line_indent = parser.count_indent(indent);
+            if line_indent < table.base_indent {
+                return None; // Back at lower indentation level
+            }
+            parser.pos = line.len() - line.trim_start_matches(' ').len();
+            parser.cells = self.cells;
+            let fields: Vec<&str> = table.fields.iter().map(String::as_str).collect();
+            let result = parser.count_cells(fields.len()).and_then(|()| {
+                let row = &line[parser.pos..];
+                parser.parse_row(row, &fields, &table.columns, table.delimiter)
+            });
+            self.cells = parser.cells;
+            Some(result)
+        });
+        let Some(row) = row else {
+            return match table.expected {
+                Some(expected) if options.get_length_policy() == LengthPolicy::Error => {
+                    table.expected = None;
+                    Some(Err(Error::LengthMismatch {
+                        expected,
+                        found: table.read,
+                    }))
+                }
+                _ => None,
+            };
+        };
+        table.read += 1;
+        Some(row.map_err(|err| self.locate(err)))
+    }
+}
+
+impl<R: Read> Iterator for StreamRows<'_, R> {
+    type Item = Result<Map<String, Value>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.table.is_none() {
+            match self.read_header() {
+                Ok(table) => self.table = Some(table),
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        let row = loop {
+            match self.next_row() {
+                Some(Ok(None)) => continue,
+                Some(Ok(Some(row))) => break Some(Ok(row)),
+                Some(Err(err)) => break Some(Err(err)),
+                None => break None,
+            }
+        };
+        if row.is_none() {
+            self.done = true;
+        }
+        row
+    }
+}
+
+/// Carry on iterating a table's rows from a [`Checkpoint`]
+///
+/// `reader` must start at [`Checkpoint::offset`] of the same input, which
+/// for a file means seeking to it first. The rows already yielded aren't
+/// read again, and lengths, cell limits and error positions carry on from
+/// where the checkpoint was taken.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::{decode_rows_stream, resume_rows_stream, Checkpoint};
+///
+/// let log = "events[3]{id,level}:\n  1,info\n  2,warn\n  3,info\n";
+/// let mut rows = decode_rows_stream(log.as_bytes(), "events", None);
+/// rows.next().unwrap()?;
+/// // Saved somewhere before the run is interrupted
+/// let saved = rows.checkpoint().to_string();
+///
+/// let checkpoint: Checkpoint = saved.parse()?;
+/// let rest = &log.as_bytes()[checkpoint.offset()..];
+/// let ids: Vec<_> = resume_rows_stream(rest, &checkpoint, None)
+///     .map(|row| row.map(|row| row["id"].clone()))
+///     .collect::<Result<_, _>>()?;
+/// assert_eq!(ids, [2, 3]);
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn resume_rows_stream<'a, R: Read>(
+    reader: R,
+    checkpoint: &Checkpoint,
+    options: Option<&'a DecodeOptions>,
+) -> StreamRows<'a, R> {
+    let options = match options {
+        Some(options) => Cow::Borrowed(options),
+        None => Cow::Owned(DecodeOptions::default()),
+    };
+    StreamRows {
+        reader: BufReader::new(reader),
+        options,
+        key: checkpoint.key.clone(),
+        partial: Vec::new(),
+        consumed: checkpoint.offset,
+        line_start: checkpoint.offset,
+        line_number: checkpoint.line_number,
+        row_lines: 0,
+        cells: checkpoint.cells,
+        table: checkpoint.table.clone(),
+        done: checkpoint.done,
+    }
+}
+
+/// Where a [`StreamRows`] iterator had got to, taken with
+/// [`StreamRows::checkpoint`]
+///
+/// It holds the byte offset of the first line not yet read, along with the
+/// table header and counts the iterator needs to carry on. Its `Display`
+/// form is a single line of text that parses back with [`str::parse`], so
+/// it can be stored between runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    key: String,
+    offset: usize,
+    line_number: usize,
+    cells: usize,
+    table: Option<TableHeader>,
+    done: bool,
+}
+
+impl Checkpoint {
+    /// Byte offset in the input to resume reading from
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl std::fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let table = self.table.as_ref().map(|table| {
+            serde_json::json!({
+                "fields": table.fields,
+                "types": table.columns.iter().map(|column| column.ty.map(ColumnType::as_str)).collect::<Vec<_>>(),
+                "defaults": table.columns.iter().map(|column| column.default.clone()).collect::<Vec<_>>(),
+                "dictionaries": table.columns.iter().map(|column| column.dictionary.clone()).collect::<Vec<_>>(),
+                "quoted": table.columns.iter().map(|column| column.quoted).collect::<Vec<_>>(),
+                "delimiter": table.delimiter.to_string(),
+                "base_indent": table.base_indent,
+                "expected": table.expected,
+                "read": table.read,
+            })
+        });
+        let state = serde_json::json!({
+            "key": self.key,
+            "offset": self.offset,
+            "line": self.line_number,
+            "cells": self.cells,
+            "table": table,
+            "done": self.done,
+        });
+        write!(f, "{state}")
+    }
+}
+
+/// The types and defaults of a checkpointed table's columns; checkpoints
+/// from before they were declared have none
+fn checkpoint_columns(table: &Value) -> Option<Vec<Column>> {
+    let list = |name: &str| match &table[name] {
+        Value::Null => Some(Vec::new()),
+        list => list.as_array().cloned(),
+    };
+    let (types, defaults) = (list("types")?, list("defaults")?);
+    let (dictionaries, quoted) = (list("dictionaries")?, list("quoted")?);
+    (0..types
+        .len()
+        .max(defaults.len())
+        .max(dictionaries.len())
+        .max(quoted.len()))
+        .map(|i| {
+            let ty = match types.get(i) {
+                None | Some(Value::Null) => None,
+                Some(ty) => Some(ColumnType::from_name(ty.as_str()?)?),
+            };
+            let default = defaults.get(i).filter(|value| !value.is_null()).cloned();
+            let dictionary = match dictionaries.get(i) {
+                None | Some(Value::Null) => None,
+                Some(entries) => Some(serde_json::from_value(entries.clone()).ok()?),
+            };
+            Some(Column {
+                ty,
+                default,
+                dictionary,
+                quoted: quoted.get(i).and_then(Value::as_bool).unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+impl std::str::FromStr for Checkpoint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let invalid = || Error::Syntax("Invalid checkpoint".to_string());
+        let state: Value = serde_json::from_str(s).map_err(|_| invalid())?;
+        let count = |value: &Value, name: &str| {
+            value[name]
+                .as_u64()
+                .and_then(|n| usize::try_from(n).ok())
+                .ok_or_else(invalid)
+        };
+        let table = match &state["table"] {
+            Value::Null => None,
+            table => Some(TableHeader {
+                fields: serde_json::from_value(table["fields"].clone()).map_err(|_| invalid())?,
+                columns: checkpoint_columns(table).ok_or_else(invalid)?,
+                delimiter: table["delimiter"]
+                    .as_str()
+                    .and_then(|d| d.chars().next())
+                    .ok_or_else(invalid)?,
+                base_indent: count(table, "base_indent")?,
+                expected: match table["expected"] {
+                    Value::Null => None,
+                    _ => Some(count(table, "expected")?),
+                },
+                read: count(table, "read")?,
+            }),
+        };
+        Ok(Checkpoint {
+            key: state["key"].as_str().ok_or_else(invalid)?.to_string(),
+            offset: count(&state, "offset")?,
+            line_number: count(&state, "line")?,
+            cells: count(&state, "cells")?,
+            table,
+            done: state["done"].as_bool().ok_or_else(invalid)?,
+        })
+    }
+}
+
+/// Lazily decode the items of a root array
+///
+/// For a document whose root is an array (`[N]:` with list items, inline
+/// values or tabular rows), items are parsed one at a time as the iterator
+/// advances, so a caller can stop early or handle each item without the
+/// whole array being built first.
+///
+/// The header is read on the first call to `next`, and a document that isn't
+/// a root array is yielded as the only error. A malformed item is yielded as
+/// an error and iteration continues with the next one. If the array ends
+/// early and the length policy is [`LengthPolicy::Error`], the last item is a
+/// length mismatch error.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::decode_iter;
+///
+/// let toon = "[3]:\n  - id: 1\n  - id: 2\n  - id: 3";
+/// let first: Vec<_> = decode_iter(toon, None).take(2).map(Result::unwrap).collect();
+/// assert_eq!(first, vec![json!({"id": 1}), json!({"id": 2})]);
+/// ```
+pub fn decode_iter<'a>(input: &'a str, options: Option<&'a DecodeOptions>) -> Items<'a> {
+    let options = match options {
+        Some(options) => Cow::Borrowed(options),
+        None => Cow::Owned(DecodeOptions::default()),
+    };
+    Items {
+        state: ItemsState::Start {
+            input: prepare_input(input, &options),
+            options,
+        },
+    }
+}
+
+/// Iterator over the items of a root array, created by [`decode_iter`]
+pub struct Items<'a> {
+    state: ItemsState<'a>,
+}
+
+enum ItemsState<'a> {
+    /// The header hasn't been read yet
+    Start {
+        input: Cow<'a, str>,
+        options: Cow<'a, DecodeOptions>,
+    },
+    /// Rows of a tabular root array
+    Table(Rows<'a>),
+    /// Items of a list root array
+    List(ListItems<'a>),
+    /// Values of an inline root array, which sit on the header line and are
+    /// parsed together
+    Inline(std::vec::IntoIter<Value>),
+    Done,
+}
+
+/// Position within a list root array
+struct ListItems<'a> {
+    input: Cow<'a, str>,
+    options: Cow<'a, DecodeOptions>,
+    pos: usize,
+    cells: usize,
+    base_indent: usize,
+    expected: Option<usize>,
+    read: usize,
+}
+
+impl<'a> Items<'a> {
+    /// Read the root array's header and pick how its items are laid out
+    fn read_header(
+        input: Cow<'a, str>,
+        options: Cow<'a, DecodeOptions>,
+    ) -> Result<ItemsState<'a>, Error> {
+        let mut parser = Parser::new(&input, &options);
+        parser.skip_whitespace();
+        let (expected, declared) = match parser.peek_char() {
+            Some('{') if options.lengthless_headers() => (None, None),
+            Some('[') => parser.parse_array_length()?,
+            _ => return Err(parser.error("Document is not a root array")),
+        };
+        if parser.peek_char() == Some('{') {
+            let ((fields, columns), delimiter, base_indent) =
+                parser.parse_table_header(declared)?;
+            let pos = parser.pos;
+            return Ok(ItemsState::Table(Rows {
+                input,
+                options,
+                key: String::new(),
+                pos,
+                cells: 0,
+                table: Some(TableHeader {
+                    fields,
+                    columns,
+                    delimiter,
+                    base_indent,
+                    expected,
+                    read: 0,
+                }),
+                done: false,
+            }));
+        }
+        if parser.peek_char() != Some(':') {
+            return Err(parser.error("Expected ':' or '{' after array length"));
+        }
+        parser.advance(); // consume ':'
+        parser.skip_whitespace();
+        if expected == Some(0) {
+            return Ok(ItemsState::Inline(Vec::new().into_iter()));
+        }
+        if parser.peek_char() != Some('\n') && parser.pos < parser.input.len() {
+            let Value::Array(items) = parser.parse_inline_array(expected, declared)? else {
+                unreachable!("inline arrays parse to an array");
+            };
+            return Ok(ItemsState::Inline(items.into_iter()));
+        }
+        if parser.peek_char() == Some('\n') {
+            parser.advance();
+        }
+        let base_indent = parser.count_indent(options.get_indent());
+        let pos = parser.pos;
+        Ok(ItemsState::List(ListItems {
+            input,
+            options,
+            pos,
+            cells: 0,
+            base_indent,
+            expected,
+            read: 0,
+        }))
+    }
+}
+
+impl ListItems<'_> {
+    /// Parse the next item, or return `None` once the list ends
+    fn next_item(&mut self) -> Option<Result<Value, Error>> {
+        if self.expected.is_some_and(|expected| self.read >= expected) {
+            return None;
+        }
+
+        let mut parser = Parser::new(&self.input, &self.options);
+        parser.pos = self.pos;
+        parser.cells = self.cells;
+        let indent = self.options.get_indent();
+        let line_indent = parser.count_indent(indent);
+        let ended = parser.pos >= parser.input.len() || line_indent < self.base_indent;
+        if !ended {
+            // Skip the indentation whitespace
+            for _ in 0..(line_indent * indent) {
+                if parser.peek_char() == Some(' ') {
+                    parser.advance();
+                } else {
+                    break;
+                }
+            }
+        }
+        // Without a declared length, the list ends at the first line that isn't an item
+        if ended || (self.expected.is_none() && parser.peek_char() != Some('-')) {
+            return match self.expected {
+                Some(expected) if self.options.get_length_policy() == LengthPolicy::Error => {
+                    self.expected = None;
+                    Some(Err(Error::LengthMismatch {
+                        expected,
+                        found: self.read,
+                    }))
+                }
+                _ => None,
+            };
+        }
+
+        self.read += 1;
+        let result = parser.parse_list_item(line_indent);
+        if result.is_err() {
+            parser.skip_to_next_line();
+        } else if parser.peek_char() == Some('\n') {
+            parser.advance();
+        }
+        self.pos = parser.pos;
+        self.cells = parser.cells;
+        Some(result)
+    }
+}
+
+impl Iterator for Items<'_> {
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = loop {
+            match &mut self.state {
+                ItemsState::Start { .. } => {
+                    let ItemsState::Start { input, options } =
+                        std::mem::replace(&mut self.state, ItemsState::Done)
+                    else {
+                        unreachable!();
+                    };
+                    match Items::read_header(input, options) {
+                        Ok(state) => self.state = state,
+                        Err(err) => return Some(Err(err)),
+                    }
+                }
+                ItemsState::Table(rows) => {
+                    let row = rows.next();
+                    break row.map(|row| row.map(Value::Object));
+                }
+                ItemsState::List(list) => {
+                    let item = list.next_item();
+                    break item;
+                }
+                ItemsState::Inline(values) => break values.next().map(Ok),
+                ItemsState::Done => return None,
+            }
+        };
+        if item.is_none() {
+            self.state = ItemsState::Done;
+        }
+        item
+    }
+}
+
+/// A completed piece of a document fed to a [`PushParser`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum PushEvent {
+    /// A top-level `key: value` entry, including any nested lines
+    Entry { key: String, value: Value },
+    /// A document that isn't an object, such as a root array, emitted once
+    /// the input is finished
+    Root(Value),
+}
+
+/// Incremental decoder for input that arrives in chunks
+///
+/// Bytes are handed over with [`feed`](Self::feed) as they arrive, from a
+/// socket or an LLM token stream for example. Each top-level entry of the
+/// document is decoded and returned as soon as the next one starts, and
+/// [`finish`](Self::finish) flushes the last entry once the input is done.
+/// Chunks may split lines and multi-byte characters anywhere.
+///
+/// Error positions refer to the whole document fed so far.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::decode::{PushEvent, PushParser};
+///
+/// let mut parser = PushParser::new(None);
+/// assert!(parser.feed(b"name: Ali").unwrap().is_empty());
+/// let events = parser.feed(b"ce\ntags[2]: a,b\n").unwrap();
+/// assert_eq!(
+///     events,
+///     vec![PushEvent::Entry { key: "name".into(), value: json!("Alice") }]
+/// );
+/// let events = parser.finish().unwrap();
+/// assert_eq!(
+///     events,
+///     vec![PushEvent::Entry { key: "tags".into(), value: json!(["a", "b"]) }]
+/// );
+/// ```
+#[derive(Debug)]
+pub struct PushParser {
+    options: DecodeOptions,
+    /// Text of the entry being received
+    buffer: String,
+    /// Trailing bytes of an incomplete UTF-8 sequence
+    partial: Vec<u8>,
+    /// End of the lines in `buffer` known to belong to the current entry
+    scanned: usize,
+    /// Bytes and lines of the document already emitted, for error positions
+    offset: usize,
+    lines: usize,
+    /// Keys emitted so far, for the duplicate key policy
+    keys: HashSet<String>,
+}
+
+impl PushParser {
+    /// Create a parser with the given decoding options
+    pub fn new(options: Option<&DecodeOptions>) -> Self {
+        Self {
+            options: options.cloned().unwrap_or_default(),
+            buffer: String::new(),
+            partial: Vec::new(),
+            scanned: 0,
+            offset: 0,
+            lines: 0,
+            keys: HashSet::new(),
+        }
+    }
+
+    /// Add a chunk of input, returning the entries it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<PushEvent>, Error> {
+        self.partial.extend_from_slice(chunk);
+        push_utf8(&mut self.partial, &mut self.buffer)?;
+
+        let mut events = Vec::new();
+        while let Some(end) = self.entry_end() {
+            let text: String = self.buffer.drain(..end).collect();
+            self.scanned = 0;
+            self.emit(&text, false, &mut events)?;
+        }
+        Ok(events)
+    }
+
+    /// Signal the end of input, returning the remaining entries
+    pub fn finish(mut self) -> Result<Vec<PushEvent>, Error> {
+        if !self.partial.is_empty() {
+            return Err(Error::Io(
+                "Invalid UTF-8: incomplete sequence at end of input".to_string(),
+            ));
+        }
+        let text = std::mem::take(&mut self.buffer);
+        let mut events = Vec::new();
+        self.emit(&text, true, &mut events)?;
+        Ok(events)
+    }
+
+    /// Length of the current entry, once a line at the top level follows it
+    fn entry_end(&mut self) -> Option<usize> {
+        entry_end(&self.buffer, &mut self.scanned)
+    }
+
+    /// Decode one entry's text and queue its events
+    ///
+    /// `last` means nothing follows the text, so it may be a document made
+    /// of a single primitive.
+    fn emit(&mut self, text: &str, last: bool, events: &mut Vec<PushEvent>) -> Result<(), Error> {
+        // Blank lines before the entry only count towards positions
+        let leading = text.len() - text.trim_start().len();
+        let (blank, text) = text.split_at(text[..leading].rfind('\n').map_or(0, |i| i + 1));
+        let offset = self.offset + blank.len();
+        let lines = self.lines + blank.matches('\n').count();
+        self.offset = offset + text.len();
+        self.lines = lines + text.matches('\n').count();
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+
+        let value = decode(text, Some(&self.options)).map_err(|err| match err {
+            Error::Parse {
+                position,
+                line,
+                column,
+                message,
+            } => Error::parse(position + offset, line + lines, column, message),
+            err => err,
+        })?;
+        let Value::Object(map) = value else {
+            if !value.is_array() && (!last || !self.keys.is_empty()) {
+                return Err(Error::parse(
+                    offset,
+                    lines + 1,
+                    1,
+                    "Expected a `key:` entry",
+                ));
+            }
+            events.push(PushEvent::Root(value));
+            return Ok(());
+        };
+        for (key, value) in map {
+            if !self.keys.insert(key.clone()) {
+                match self.options.get_duplicate_keys() {
+                    DuplicateKeyPolicy::Error => {
+                        let message = format!("Duplicate key '{key}'");
+                        return Err(Error::parse(offset, lines + 1, 1, message));
+                    }
+                    DuplicateKeyPolicy::FirstWins => continue,
+                    DuplicateKeyPolicy::LastWins => {}
+                }
+            }
+            events.push(PushEvent::Entry { key, value });
+        }
+        Ok(())
+    }
+}
+
+/// Length of the top-level entry at the start of `buffer`, once a line at
+/// the top level follows it
+///
+/// `scanned` is the end of the lines already known to belong to the entry,
+/// so text is only scanned once as it grows.
+pub(crate) fn entry_end(buffer: &str, scanned: &mut usize) -> Option<usize> {
+    // Only objects can be split into entries
+    if buffer.trim_start().starts_with('[') {
+        return None;
+    }
+    loop {
+        let len = row_len(&buffer[*scanned..], false)?;
+        let next = *scanned + len + 1;
+        match buffer[next..].chars().next() {
+            None => return None,
+            Some(' ' | '\t' | '\r' | '\n') => *scanned = next,
+            Some(_) if buffer[..next].trim().is_empty() => *scanned = next,
+            Some(_) => return Some(next),
+        }
+    }
+}
+
+/// Counts gathered by [`validate_stream`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Lines in the document
+    pub lines: usize,
+    /// Top-level entries, or 1 for a document that isn't an object
+    pub sections: usize,
+    /// Table rows and list items, at any depth
+    pub rows: usize,
+}
+
+/// Check a TOON stream without keeping what it decodes to
+///
+/// This is a pre-flight for large files: syntax, declared lengths, table
+/// delimiters and the limits set in the options are checked as
+/// [`decode_all_errors`] checks them, collecting every error in document
+/// order rather than stopping at the first. The input is read one top-level entry at a time, and each entry
+/// is dropped as soon as it has been checked, so memory is bounded by the
+/// largest entry rather than the document; a root array is one entry.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::validate_stream;
+///
+/// let toon = "name: report\nitems[2]{sku,qty}:\n  A1,2\n  B2,1\n";
+/// let stats = validate_stream(toon.as_bytes(), None).unwrap();
+/// assert_eq!((stats.sections, stats.rows), (2, 2));
+///
+/// let errors = validate_stream("a: 1\nb 2\nitems[3]: x,y".as_bytes(), None).unwrap_err();
+/// assert_eq!(errors.len(), 2);
+/// ```
+pub fn validate_stream<R: Read>(
+    reader: R,
+    options: Option<&DecodeOptions>,
+) -> Result<Stats, Vec<Error>> {
+    let default_opts = DecodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let mut reader = BufReader::new(reader);
+    let mut validator = Validator {
+        options: opts,
+        stats: Stats::default(),
+        errors: Vec::new(),
+        offset: 0,
+        lines: 0,
+        keys: HashSet::new(),
+        cells: 0,
+    };
+    let mut buffer = String::new();
+    let mut partial = Vec::new();
+    let mut scanned = 0;
+    loop {
+        let n = reader
+            .read_until(b'\n', &mut partial)
+            .map_err(|e| vec![Error::Io(e.to_string())])?;
+        if n == 0 {
+            break;
+        }
+        push_utf8(&mut partial, &mut buffer).map_err(|err| vec![err])?;
+        while let Some(end) = entry_end(&buffer, &mut scanned) {
+            let text: String = buffer.drain(..end).collect();
+            scanned = 0;
+            validator.check(&text, false);
+        }
+    }
+    if !partial.is_empty() {
+        validator.errors.push(Error::Io(
+            "Invalid UTF-8: incomplete sequence at end of input".to_string(),
+        ));
+    }
+    validator.check(&buffer, true);
+    validator.stats.lines =
+        validator.lines + usize::from(!buffer.is_empty() && !buffer.ends_with('\n'));
+    if validator.errors.is_empty() {
+        Ok(validator.stats)
+    } else {
+        Err(validator.errors)
+    }
+}
+
+/// State of [`validate_stream`] between entries
+struct Validator<'a> {
+    options: &'a DecodeOptions,
+    stats: Stats,
+    errors: Vec<Error>,
+    /// Bytes and lines of the document already checked, for error positions
+    offset: usize,
+    lines: usize,
+    /// Keys seen so far, for the duplicate key policy
+    keys: HashSet<String>,
+    /// Array cells in the entries checked so far, held to `max_cells`
+    /// across the whole document as when decoding
+    cells: usize,
+}
+
+impl Validator<'_> {
+    /// Check one entry's text, recording its counts and errors
+    ///
+    /// `last` means nothing follows the text, so it may be a document made
+    /// of a single primitive.
+    fn check(&mut self, text: &str, last: bool) {
+        let (offset, lines) = (self.offset, self.lines);
+        self.offset += text.len();
+        self.lines += text.matches('\n').count();
+        if text.trim().is_empty() {
+            return;
+        }
+        // Where the entry itself starts, past any blank lines
+        let leading = text.len() - text.trim_start().len();
+        let (start, start_line) = (
+            offset + leading,
+            lines + text[..leading].matches('\n').count() + 1,
+        );
+
+        let input = prepare_input(text, self.options);
+        let mut parser = Parser::new(&input, self.options);
+        parser.errors = Some(Vec::new());
+        parser.cells = self.cells;
+        let result = parser.parse();
+        self.stats.rows += parser.rows;
+        self.cells = parser.cells;
+        let locate = |err| match err {
+            Error::Parse {
+                position,
+                line,
+                column,
+                message,
+            } => Error::parse(position + offset, line + lines, column, message),
+            err => err,
+        };
+        self.errors.extend(
+            parser
+                .errors
+                .take()
+                .unwrap_or_default()
+                .into_iter()
+                .map(locate),
+        );
+        match result {
+            Ok(Value::Object(map)) if !text.trim_start().starts_with('[') => {
+                for key in map.keys() {
+                    self.stats.sections += 1;
+                    if !self.keys.insert(key.clone())
+                        && self.options.get_duplicate_keys() == DuplicateKeyPolicy::Error
+                    {
+                        let message = format!("Duplicate key '{key}'");
+                        let leading = text.len() - text.trim_start().len();
+                        let blank = text[..leading].matches('\n').count();
+                        self.errors.push(Error::parse(
+                            offset + leading,
+                            lines + blank + 1,
+                            1,
+                            message,
+                        ));
+                    }
+                }
+            }
+            Ok(value) if !value.is_array() && (!last || self.stats.sections > 0) => {
+                let message = "Expected a `key:` entry";
+                self.errors
+                    .push(Error::parse(start, start_line, 1, message));
+            }
+            Ok(_) => self.stats.sections += 1,
+            Err(err) => self.errors.push(locate(err)),
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    options: &'a DecodeOptions,
+    /// Errors recorded so far when running in recovery mode
+    errors: Option<Vec<Error>>,
+    /// Array cells decoded so far, checked against `max_cells`
+    cells: usize,
+    /// Table rows and list items read so far, for [`validate_stream`]
+    rows: usize,
+    /// Path of the object being parsed while `include_keys` narrows what is
+    /// decoded, or `None` when everything is
+    include: Option<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, options: &'a DecodeOptions) -> Self {
+        Self {
+            input,
+            pos: 0,
+            options,
+            errors: None,
+            cells: 0,
+            rows: 0,
+            include: None,
+        }
+    }
+
+    /// Record `err` and keep going in recovery mode, otherwise fail with it
+    fn recover(&mut self, err: Error) -> Result<(), Error> {
+        match &mut self.errors {
+            Some(errors) => {
+                errors.push(err);
+                Ok(())
+            }
+            None => Err(err),
+        }
+    }
+
+    fn parse(&mut self) -> Result<Value, Error> {
+        self.skip_whitespace();
+        if self.pos >= self.input.len() {
+            return Ok(Value::Object(Map::new()));
+        }
+
+        // Check if it's a root array (starts with [)
+        let value = if self.peek_char() == Some('[') {
+            self.parse_array_value()?
+        } else if let Some(line) = self.root_primitive() {
+            parse_primitive_value(self.options, line.trim())?
+        } else {
+            if self.options.get_include_keys().is_some() {
+                self.include = Some(String::new());
+            }
+            self.parse_object()?
+        };
+        Ok(value)
+    }
+
+    /// The document's only line, when it is a primitive rather than an entry
+    fn root_primitive(&self) -> Option<&'a str> {
+        let line = self.rest_of_line();
+        let rest = &self.input[self.pos + line.len()..];
+        (rest.trim().is_empty() && is_root_primitive(line, self.options)).then_some(line)
+    }
+
+    fn parse_object(&mut self) -> Result<Value, Error> {
+        let mut map = Map::new();
+        let mut seen = HashSet::new();
+        let indent = self.options.get_indent();
+        let initial_indent = self.count_indent(indent);
+
+        loop {
+            // Count indentation first (before skipping whitespace)
+            let line_indent = self.count_indent(indent);
+            if line_indent < initial_indent {
+                // We've gone back to a lower indentation level
+                break;
+            }
+
+            // Now skip the indentation whitespace
+            for _ in 0..(line_indent * indent) {
+                if self.peek_char() == Some(' ') {
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+
+            if self.pos >= self.input.len() {
+                break;
+            }
+            if line_indent == 0 && !map.is_empty() && initial_indent == 0 {
+                // Check if there's actually more content on this line
+                let saved_pos = self.pos;
+                let key_result = self.parse_key();
+                self.pos = saved_pos;
+                if key_result.is_err() {
+                    break;
+                }
+            }
+
+            // Parse key (may include array notation like "tags[3]")
+            let (key, quoted) = self.parse_entry_key()?;
+            if !seen.insert(entry_name(self.options, &key, quoted))
+                && is_duplicate_error(self.options)
+            {
+                let err = self.error(format!("Duplicate key '{key}'"));
+                self.recover(err)?;
+            }
+            self.skip_whitespace();
+
+            // Check if we have array notation in the key (e.g., "tags[3]:")
+            let has_array_notation = opens_array(self.peek_char(), self.options);
+
+            if !has_array_notation {
+                // Normal key-value: key: value
+                if self.peek_char() != Some(':') {
+                    let err = self.error(format!("Expected ':' after key '{key}'"));
+                    self.recover(err)?;
+                    self.skip_to_next_line();
+                    continue;
+                }
+                self.advance(); // consume ':'
+                self.skip_whitespace();
+            } else {
+                // Key with array notation: key[3]: value
+                // The array part will be parsed as the value
+            }
+
+            // Step over entries `include_keys` leaves out, and narrow it for
+            // the nested object of a partly selected one
+            let outer = self.include.take();
+            let inner = match &outer {
+                None => None,
+                Some(path) => match select_entry(self.options, path, &key, has_array_notation) {
+                    Selection::All => None,
+                    Selection::Part(path) => Some(path),
+                    Selection::Nothing => {
+                        self.include = outer;
+                        self.skip_entry(line_indent);
+                        continue;
+                    }
+                },
+            };
+            let partial = inner.is_some();
+            self.include = inner;
+            let result = self.parse_entry_value(has_array_notation, line_indent);
+            self.include = outer;
+            let value = match result {
+                Ok(value) => value,
+                Err(err) => {
+                    self.recover(err)?;
+                    self.skip_to_next_line();
+                    continue;
+                }
+            };
+            if partial && !value.is_object() {
+                continue;
+            }
+
+            insert_key(self.options, &mut map, key, quoted, value);
+
+            // After inserting a nested object, check if we should continue
+            // If we're at the end or at a lower indentation level, break
+            if self.pos >= self.input.len() {
+                break;
+            }
+
+            // Check indentation for next iteration
+            let next_line_indent = self.count_indent(indent);
+            if next_line_indent < initial_indent {
+                break;
+            }
+            if next_line_indent == 0 && initial_indent == 0 && !map.is_empty() {
+                // Check if there's actually a key to parse
+                let saved_pos = self.pos;
+                let key_result = self.parse_key();
+                self.pos = saved_pos;
+                if key_result.is_err() {
+                    break;
+                }
+            }
+        }
+
+        Ok(Value::Object(map))
+    }
+
+    /// Parse the value following a key, either inline or as an indented block
+    fn parse_entry_value(
+        &mut self,
+        has_array_notation: bool,
+        line_indent: usize,
+    ) -> Result<Value, Error> {
+        let indent = self.options.get_indent();
+        // Check if value is on next line (indented) or inline
+        if has_array_notation {
+            // Array notation: key[3]: value
+            // Parse the array value
+            let value = self.parse_array_value()?;
+            // Skip to next line
+            if self.pos < self.input.len() && self.peek_char() == Some('\n') {
+                self.advance();
+            }
+            Ok(value)
+        } else if matches!(self.peek_char(), Some('\n') | None) {
+            self.advance(); // consume '\n'
+                            // Check if next line is more indented (nested object/array)
+            let next_indent = self.count_indent(indent);
+            if next_indent > line_indent {
+                // Parse nested object or array
+                if self.peek_char() == Some('[') {
+                    self.parse_array_value()
+                } else if self.options.get_lenient_arrays() && is_list_item(self.rest_of_line()) {
+                    self.parse_list_array(None)
+                } else {
+                    // Parse nested object
+                    // After parsing nested object, check if we should continue
+                    // The recursive parse_object() will have consumed all nested content
+                    // and positioned us at the end or at a lower indentation level
+                    self.parse_object()
+                }
+            } else {
+                // Same or less indent means we're done with this value
+                Ok(Value::Null)
+            }
+        } else if is_empty_object(self.rest_of_line()) {
+            self.skip_to_next_line();
+            Ok(Value::Object(Map::new()))
+        } else if let Some(keep_newline) = block_header(self.rest_of_line()) {
+            self.parse_block_string(line_indent, keep_newline)
+        } else {
+            // Inline value - parse until end of line or newline
+            let value =
+                if self.options.get_lenient_arrays() && is_unbracketed_array(self.rest_of_line()) {
+                    self.parse_inline_array(None, None)?
+                } else {
+                    self.parse_value_until_newline()?
+                };
+            // Skip to next line (if not already at end)
+            if self.pos < self.input.len() && self.peek_char() != Some('\n') {
+                self.skip_to_next_line();
+            } else if self.peek_char() == Some('\n') {
+                self.advance(); // consume newline
+            }
+            Ok(value)
+        }
+    }
+
+    /// Step over the rest of an entry without parsing it: the rest of its
+    /// line and every line indented past `line_indent` after it
+    fn skip_entry(&mut self, line_indent: usize) {
+        let limit = line_indent * self.options.get_indent();
+        let mut first = true;
+        while self.pos < self.input.len() {
+            let rest = &self.input[self.pos..];
+            let content = rest.trim_start_matches(' ');
+            let blank = content.starts_with(['\n', '\r']);
+            if !first && !blank && rest.len() - content.len() <= limit {
+                break;
+            }
+            first = false;
+            let len = row_len(rest, true).unwrap_or(rest.len());
+            self.pos += (len + 1).min(rest.len());
+        }
+    }
+
+    /// Text from the cursor to the end of the current line
+    fn rest_of_line(&self) -> &'a str {
+        let input = self.input;
+        let rest = &input[self.pos..];
+        &rest[..rest.find('\n').unwrap_or(rest.len())]
+    }
+
+    /// Parse the indented body of a `|` or `|-` block string
+    fn parse_block_string(
+        &mut self,
+        line_indent: usize,
+        keep_newline: bool,
+    ) -> Result<Value, Error> {
+        self.skip_to_next_line();
+        let prefix_len = (line_indent + 1) * self.options.get_indent();
+        let mut lines = Vec::new();
+        while self.pos < self.input.len() {
+            let Some(content) = block_line(self.rest_of_line(), prefix_len) else {
+                break;
+            };
+            lines.push(content);
+            self.skip_to_next_line();
+        }
+        let text = join_block_lines(&lines, keep_newline);
+        check_string_length(self.options, text.len()).map_err(|msg| self.error(msg))?;
+        Ok(Value::String(text))
+    }
+
+    fn parse_value(&mut self) -> Result<Value, Error> {
+        self.skip_whitespace();
+        match self.peek_char() {
+            Some('[') => self.parse_array_value(),
+            Some('"') => self.parse_string(),
+            // A list item marker, unlike the sign of `-1`
+            Some('-') if is_list_item(self.rest_of_line()) => {
+                self.advance();
+                self.skip_whitespace();
+                self.parse_value()
+            }
+            Some(ch) if (ch.is_ascii_digit() || ch == '-') && self.options.get_coerce_numbers() => {
+                self.parse_number()
+            }
+            Some(ch) if ch.is_ascii_alphabetic() => {
+                // Try boolean/null first, then fall back to string
+                match self.parse_boolean_or_null() {
+                    Some(value) => Ok(value),
+                    None => self.parse_unquoted_string(),
+                }
+            }
+            _ => self.parse_unquoted_string(),
+        }
+    }
+
+    fn parse_unquoted_string(&mut self) -> Result<Value, Error> {
+        let start = self.pos;
+        // Parse until we hit whitespace, newline, or end
+        while self.pos < self.input.len() {
+            match self.peek_char() {
+                Some(ch) if ch == ' ' || ch == '\n' || ch == '\t' || ch == '\r' => break,
+                Some(_) => self.advance(),
+                None => break,
+            }
+        }
+        if self.pos == start {
+            return Err(self.error("Expected value"));
+        }
+        check_string_length(self.options, self.pos - start).map_err(|msg| self.error(msg))?;
+        Ok(Value::String(self.input[start..self.pos].to_string()))
+    }
+
+    fn parse_value_until_newline(&mut self) -> Result<Value, Error> {
+        self.skip_whitespace();
+
+        // Check what type of value we have
+        match self.peek_char() {
+            Some('[') => {
+                // Array - parse array value
+                self.parse_array_value()
+            }
+            Some('"') => self.parse_string(),
+            Some(ch) if (ch.is_ascii_digit() || ch == '-') && self.options.get_coerce_numbers() => {
+                self.parse_number()
+            }
+            Some(ch) if ch.is_ascii_alphabetic() => {
+                // Try boolean/null first, then fall back to string
+                match self.parse_boolean_or_null() {
+                    Some(value) => Ok(value),
+                    None => self.parse_unquoted_string(),
+                }
+            }
+            _ => self.parse_unquoted_string(),
+        }
+    }
+
+    fn parse_array_value(&mut self) -> Result<Value, Error> {
+        // A length-less table header: key{field1,field2}:
+        if self.peek_char() == Some('{') && self.options.lengthless_headers() {
+            return self.parse_tabular_array(None, None);
+        }
+        let (length, declared) = self.parse_array_length()?;
+
+        // Check for tabular format: {field1,field2}:
+        if self.peek_char() == Some('{') {
+            self.parse_tabular_array(length, declared)
+        } else if self.peek_char() == Some(':') {
+            self.advance(); // consume ':'
+            self.skip_whitespace();
+
+            // Check if it's inline (same line) or list format (next line)
+            if length == Some(0) {
+                // Empty array - skip any whitespace and newline
+                self.skip_whitespace();
+                if self.peek_char() == Some('\n') {
+                    self.advance();
+                }
+                Ok(Value::Array(Vec::new()))
+            } else if self.peek_char() == Some('\n') || self.pos >= self.input.len() {
+                self.parse_list_array(length)
+            } else {
+                self.parse_inline_array(length, declared)
+            }
+        } else {
+            Err(self.error("Expected ':' or '{' after array length"))
+        }
+    }
+
+    /// Parse the `[N]` part of an array header, where `N` may be empty with
+    /// `lenient_arrays`, along with the delimiter it may declare (`[N|]`)
+    fn parse_array_length(&mut self) -> Result<(Option<usize>, Option<char>), Error> {
+        if self.peek_char() != Some('[') {
+            return Err(self.error("Expected '['"));
+        }
+        self.advance(); // consume '['
 
-impl<'a> Parser<'a> {
-    fn new(input: &'a str, options: &'a DecodeOptions) -> Self {
-        Self {
-            input,
-            pos: 0,
-            options,
+        // Parse length marker (optional #) and length
+        let has_length_marker = self.peek_char() == Some('#');
+        if has_length_marker {
+            if let Some(message) = length_marker_error(self.options) {
+                return Err(self.error(message));
+            }
+            self.advance(); // consume '#'
+        }
+
+        let length_str = self.parse_while(|ch| ch.is_ascii_digit());
+        let length = if length_str.is_empty() && self.options.lengthless_headers() {
+            None
+        } else {
+            let length: usize = length_str
+                .parse()
+                .map_err(|_| self.error("Invalid array length"))?;
+            check_array_length(self.options, length).map_err(|msg| self.error(msg))?;
+            Some(length)
+        };
+        let declared = self.peek_char().filter(|&ch| is_declared_delimiter(ch));
+        if declared.is_some() {
+            self.advance();
+        }
+
+        if self.peek_char() != Some(']') {
+            return Err(self.error("Expected ']'"));
         }
+        self.advance(); // consume ']'
+        Ok((length, declared))
     }
 
-    fn parse(&mut self) -> Result<Value, Error> {
-        self.skip_whitespace();
+    /// Parse the `{field1,field2}:` part of a tabular array header, returning
+    /// the fields, their declared types and defaults, the row delimiter and the
+    /// indentation level of the rows
+    fn parse_table_header(
+        &mut self,
+        declared: Option<char>,
+    ) -> Result<(Fields, char, usize), Error> {
+        if self.peek_char() != Some('{') {
+            return Err(self.error("Expected '{'"));
+        }
+        self.advance(); // consume '{'
+
+        // Parse field names
+        let fields_str = self.parse_while(until_unquoted('}'));
+        let first_row = self.input[self.pos..].split('\n').nth(1);
+        let delimiter = declared.unwrap_or_else(|| table_delimiter(fields_str, first_row));
+        let (fields, columns) = header_fields(fields_str, delimiter, self.options)?;
+        let names: Vec<&str> = fields.iter().map(String::as_str).collect();
+        if let Some(field) = duplicate_field(self.options, &names) {
+            return Err(self.error(format!("Duplicate key '{field}'")));
+        }
+
+        if self.peek_char() != Some('}') {
+            return Err(self.error("Expected '}'"));
+        }
+        self.advance(); // consume '}'
+
+        if self.peek_char() != Some(':') {
+            return Err(self.error("Expected ':'"));
+        }
+        self.advance(); // consume ':'
+                        // Skip to next line (consume newline if present)
+        if self.peek_char() == Some('\n') {
+            self.advance();
+        }
+
+        // Count base indentation of first row
+        let base_indent = self.count_indent(self.options.get_indent());
+        Ok(((fields, columns), delimiter, base_indent))
+    }
+
+    /// Text of the next row of a table whose rows sit at `base_indent`, or
+    /// `None` once the table ends; the cursor is left at the end of the row
+    fn next_table_row(&mut self, base_indent: usize) -> Option<&'a str> {
         if self.pos >= self.input.len() {
-            return Ok(Value::Object(Map::new()));
+            return None;
         }
 
-        // Check if it's a root array (starts with [)
-        if self.peek_char() == Some('[') {
-            self.parse_array_value()
-        } else {
-            self.parse_object()
+        // Count indentation of current line
+        let indent = self.options.get_indent();
+        let line_indent = self.count_indent(indent);
+        if line_indent < base_indent {
+            return None; // Back at lower indentation level
+        }
+
+        // Skip the indentation whitespace
+        for _ in 0..(line_indent * indent) {
+            if self.peek_char() == Some(' ') {
+                self.advance();
+            } else {
+                break;
+            }
         }
+
+        let start = self.pos;
+        self.pos += row_len(&self.input[start..], true).unwrap_or_default();
+        Some(&self.input[start..self.pos])
     }
 
-    fn parse_object(&mut self) -> Result<Value, Error> {
-        let mut map = Map::new();
+    fn parse_tabular_array(
+        &mut self,
+        expected_length: Option<usize>,
+        declared: Option<char>,
+    ) -> Result<Value, Error> {
+        let ((fields, columns), delimiter, base_indent) = self.parse_table_header(declared)?;
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+
+        // Parse rows
+        let mut items = Vec::new();
+        let mut skipped = 0;
+        let mut elided = None;
+        for _ in 0..expected_length.unwrap_or(usize::MAX) {
+            let Some(row) = self.next_table_row(base_indent) else {
+                break;
+            };
+            if let Some(count) = elided_rows(row, self.options) {
+                // The rows left out of a preview end the table
+                elided = Some(count);
+                if self.peek_char() == Some('\n') {
+                    self.advance();
+                }
+                break;
+            }
+            self.rows += 1;
+            self.count_cells(fields.len())?;
+            match self.parse_row(row, &fields, &columns, delimiter) {
+                Ok(Some(obj)) => items.push(Value::Object(obj)),
+                // Filtered out, but still part of the table
+                Ok(None) => skipped += 1,
+                Err(err) => {
+                    // Drop the malformed row but still count it towards the length
+                    self.recover(err)?;
+                    skipped += 1;
+                }
+            }
+            // Skip to next line
+            if self.pos < self.input.len() && self.peek_char() == Some('\n') {
+                self.advance();
+            }
+        }
+
+        let found = items.len() + skipped;
+        if let Some(expected) = expected_length {
+            let fitted = match elided {
+                Some(count) => fit_elided(self.options, &mut items, expected, found, count),
+                None => fit_array(self.options, &mut items, expected, found),
+            };
+            if let Err(err) = fitted {
+                self.recover(err)?;
+            }
+        }
+
+        Ok(Value::Array(items))
+    }
+
+    fn parse_row(
+        &self,
+        row: &str,
+        fields: &[&str],
+        columns: &[Column],
+        delimiter: char,
+    ) -> Result<Option<Map<String, Value>>, Error> {
+        let values: Vec<&str> = self.split_row(row, delimiter);
+        for value_str in &values {
+            check_string_length(self.options, value_str.len()).map_err(|msg| self.error(msg))?;
+        }
+        check_row_delimiter(self.options, row, fields.len(), values.len(), delimiter)?;
+        row_object(self.options, fields, columns, &values)
+    }
+
+    fn parse_inline_array(
+        &mut self,
+        expected_length: Option<usize>,
+        declared: Option<char>,
+    ) -> Result<Value, Error> {
+        let start = self.pos;
+        self.pos += row_len(&self.input[start..], true).unwrap_or_default();
+        let input = self.input;
+        let row = &input[start..self.pos];
+        let delimiter = declared.unwrap_or_else(|| inline_delimiter(row, expected_length));
+        let values = inline_items(self.split_row(row, delimiter));
+
+        let mut items = Vec::new();
+        let mut skipped = 0;
+        for value_str in values {
+            let trimmed = value_str.trim();
+            check_string_length(self.options, trimmed.len()).map_err(|msg| self.error(msg))?;
+            match parse_primitive_value(self.options, trimmed) {
+                Ok(value) => items.push(value),
+                Err(err) => {
+                    self.recover(err)?;
+                    skipped += 1;
+                }
+            }
+        }
+
+        self.count_cells(items.len() + skipped)?;
+        let found = items.len() + skipped;
+        if let Some(expected) = expected_length {
+            if let Err(err) = fit_array(self.options, &mut items, expected, found) {
+                self.recover(err)?;
+            }
+        }
+
+        Ok(Value::Array(items))
+    }
+
+    fn parse_list_array(&mut self, expected_length: Option<usize>) -> Result<Value, Error> {
+        // Skip to next line if we're not already there
+        if self.peek_char() == Some('\n') {
+            self.advance();
+        }
         let indent = self.options.get_indent();
-        let initial_indent = self.count_indent(indent);
+        // Count base indentation of first item
+        let base_indent = self.count_indent(indent);
+        let mut items = Vec::new();
+        let mut skipped = 0;
 
-        loop {
-            // Count indentation first (before skipping whitespace)
-            let line_indent = self.count_indent(indent);
-            if line_indent < initial_indent {
-                // We've gone back to a lower indentation level
+        for _ in 0..expected_length.unwrap_or(usize::MAX) {
+            if self.pos >= self.input.len() {
                 break;
             }
 
-            // Now skip the indentation whitespace
+            // Count indentation of current line
+            let line_indent = self.count_indent(indent);
+            if line_indent < base_indent {
+                break; // Back at lower indentation level
+            }
+
+            // Skip the indentation whitespace
+            let line_start = self.pos;
             for _ in 0..(line_indent * indent) {
                 if self.peek_char() == Some(' ') {
                     self.advance();
@@ -74,453 +2467,934 @@ impl<'a> Parser<'a> {
                 }
             }
 
-            if self.pos >= self.input.len() {
+            // Without a declared length, the list ends at the first line that isn't an item
+            if expected_length.is_none() && self.peek_char() != Some('-') {
+                self.pos = line_start;
                 break;
             }
-            if line_indent == 0 && !map.is_empty() && initial_indent == 0 {
-                // Check if there's actually more content on this line
-                let saved_pos = self.pos;
-                let key_result = self.parse_key();
-                self.pos = saved_pos;
-                if key_result.is_err() {
-                    break;
+
+            self.rows += 1;
+            match self.parse_list_item(line_indent) {
+                Ok(value) => items.push(value),
+                Err(err) => {
+                    self.recover(err)?;
+                    skipped += 1;
+                    self.skip_to_next_line();
+                    continue;
                 }
             }
+            // Skip to next line
+            if self.pos < self.input.len() && self.peek_char() == Some('\n') {
+                self.advance();
+            }
+        }
 
-            // Parse key (may include array notation like "tags[3]")
-            let key = self.parse_key()?;
+        let found = items.len() + skipped;
+        if let Some(expected) = expected_length {
+            if let Err(err) = fit_array(self.options, &mut items, expected, found) {
+                self.recover(err)?;
+            }
+        }
+
+        Ok(Value::Array(items))
+    }
+
+    /// Parse a single list item after its indentation
+    fn parse_list_item(&mut self, line_indent: usize) -> Result<Value, Error> {
+        // Check if there's a '-' marker (optional in some formats)
+        let has_dash = self.peek_char() == Some('-');
+        if has_dash {
+            self.advance(); // consume '-'
             self.skip_whitespace();
+        }
 
-            // Check if we have array notation in the key (e.g., "tags[3]:")
-            let has_array_notation = self.peek_char() == Some('[');
+        // Parse the value - we've already skipped indentation and optionally the '-'
+        // The value could be a primitive, object, or array
+        // Check if this line looks like an object (has key: value format)
+        let line_start = self.pos;
+        let line_end = self.input[line_start..]
+            .find('\n')
+            .map(|i| line_start + i)
+            .unwrap_or(self.input.len());
+        let line = &self.input[line_start..line_end].trim();
 
-            if !has_array_notation {
-                // Normal key-value: key: value
-                if self.peek_char() != Some(':') {
-                    return Err(Error::parse(
-                        self.pos,
-                        format!("Expected ':' after key '{key}'"),
-                    ));
-                }
-                self.advance(); // consume ':'
-                self.skip_whitespace();
-            } else {
-                // Key with array notation: key[3]: value
-                // The array part will be parsed as the value
+        if self.peek_char() == Some('[') {
+            self.parse_array_value()
+        } else if is_empty_object(line) {
+            self.pos = line_end;
+            Ok(Value::Object(Map::new()))
+        } else if is_object_item(line, self.options) {
+            self.parse_list_object(line_indent)
+        } else {
+            // Primitive value (number, string, boolean, etc.)
+            self.parse_value()
+        }
+    }
+
+    /// Parse an object list item: its first field follows the `- ` marker
+    /// and any further fields sit one level deeper than the marker
+    fn parse_list_object(&mut self, line_indent: usize) -> Result<Value, Error> {
+        let field_indent = line_indent + 1;
+        let (key, quoted) = self.parse_entry_key()?;
+        self.skip_whitespace();
+        let has_array_notation = opens_array(self.peek_char(), self.options);
+        if !has_array_notation {
+            if self.peek_char() != Some(':') {
+                return Err(self.error(format!("Expected ':' after key '{key}'")));
             }
+            self.advance(); // consume ':'
+            self.skip_whitespace();
+        }
+        let mut map = Map::new();
+        let value = self.parse_entry_value(has_array_notation, field_indent)?;
+        insert_key(self.options, &mut map, key, quoted, value);
 
-            // Check if value is on next line (indented) or inline
-            let value = if has_array_notation {
-                // Array notation: key[3]: value
-                // Parse the array value
-                let value = self.parse_array_value()?;
-                // Skip to next line
-                if self.pos < self.input.len() && self.peek_char() == Some('\n') {
-                    self.advance();
-                }
-                value
-            } else if self.peek_char() == Some('\n') {
-                self.advance(); // consume '\n'
-                                // Check if next line is more indented (nested object/array)
-                let next_indent = self.count_indent(indent);
-                if next_indent > line_indent {
-                    // Parse nested object or array
-                    if self.peek_char() == Some('[') {
-                        self.parse_array_value()?
-                    } else {
-                        // Parse nested object
-                        // After parsing nested object, check if we should continue
-                        // The recursive parse_object() will have consumed all nested content
-                        // and positioned us at the end or at a lower indentation level
-                        self.parse_object()?
+        let indent = self.options.get_indent();
+        if self.pos < self.input.len()
+            && self.count_indent(indent) == field_indent
+            && !is_list_item(self.rest_of_line())
+        {
+            if let Value::Object(fields) = self.parse_object()? {
+                for (key, value) in fields {
+                    if repeats(self.options, &map, &key, &value) {
+                        return Err(self.error(format!("Duplicate key '{key}'")));
                     }
-                } else {
-                    // Same or less indent means we're done with this value
-                    Value::Null
-                }
-            } else {
-                // Inline value - parse until end of line or newline
-                let value = self.parse_value_until_newline()?;
-                // Skip to next line (if not already at end)
-                if self.pos < self.input.len() && self.peek_char() != Some('\n') {
-                    self.skip_to_next_line();
-                } else if self.peek_char() == Some('\n') {
-                    self.advance(); // consume newline
+                    // Already expanded by `parse_object`
+                    insert_key(self.options, &mut map, key, true, value);
                 }
-                value
-            };
+            }
+        }
+        Ok(Value::Object(map))
+    }
 
-            map.insert(key, value);
+    fn parse_string(&mut self) -> Result<Value, Error> {
+        if self.peek_char() != Some('"') {
+            return Err(self.error("Expected '\"'"));
+        }
+        self.advance(); // consume opening quote
 
-            // After inserting a nested object, check if we should continue
-            // If we're at the end or at a lower indentation level, break
-            if self.pos >= self.input.len() {
-                break;
+        let start = self.pos;
+        let mut escaped = false;
+
+        while self.pos < self.input.len() {
+            let ch = self.peek_char().unwrap();
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == '"' {
+                check_string_length(self.options, self.pos - start)
+                    .map_err(|msg| self.error(msg))?;
+                let s = &self.input[start..self.pos];
+                self.advance(); // consume closing quote
+                return parse_quoted_string(&format!("\"{s}\""));
             }
+            self.advance();
+        }
 
-            // Check indentation for next iteration
-            let next_line_indent = self.count_indent(indent);
-            if next_line_indent < initial_indent {
-                break;
+        Err(Error::UnterminatedString)
+    }
+
+    fn parse_lenient_number(&mut self) -> Result<Option<Value>, Error> {
+        let token = self.input[self.pos..]
+            .split([' ', '\t', '\r', '\n'])
+            .next()
+            .unwrap_or("");
+        Ok(lenient_integer(token).inspect(|_| self.pos += token.len()))
+    }
+
+    fn parse_number(&mut self) -> Result<Value, Error> {
+        if self.options.get_lenient_numbers() {
+            if let Some(value) = self.parse_lenient_number()? {
+                return Ok(value);
             }
-            if next_line_indent == 0 && initial_indent == 0 && !map.is_empty() {
-                // Check if there's actually a key to parse
-                let saved_pos = self.pos;
-                let key_result = self.parse_key();
-                self.pos = saved_pos;
-                if key_result.is_err() {
-                    break;
+        }
+
+        let start = self.pos;
+        let mut has_dot = false;
+
+        if self.peek_char() == Some('-') {
+            self.advance();
+        }
+        if self.options.get_allow_nan() && self.input[self.pos..].starts_with("inf") {
+            self.pos += 3;
+            if self.at_value_end() {
+                return Ok(non_finite_value(&self.input[start..self.pos]));
+            }
+        }
+
+        while self.pos < self.input.len() {
+            match self.peek_char() {
+                Some(ch) if ch.is_ascii_digit() => {
+                    self.advance();
+                }
+                Some('.') if !has_dot => {
+                    has_dot = true;
+                    self.advance();
                 }
+                _ => break,
             }
         }
 
-        Ok(Value::Object(map))
+        // Optional exponent, only consumed when followed by digits
+        let mut has_exponent = false;
+        if matches!(self.peek_char(), Some('e') | Some('E')) {
+            let rest = &self.input[self.pos + 1..];
+            let digits = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+            if digits.starts_with(|ch: char| ch.is_ascii_digit()) {
+                has_exponent = true;
+                self.pos += rest.len() - digits.len() + 1;
+                self.parse_while(|ch| ch.is_ascii_digit());
+            }
+        }
+
+        // Text running on from the number, as in `2024-01-01` or `12abc`,
+        // makes the whole token a string, as it would be in a table cell
+        if !self.at_value_end() {
+            self.pos = start;
+            return self.parse_unquoted_string();
+        }
+
+        let s = &self.input[start..self.pos];
+        if has_dot || has_exponent {
+            let n = s
+                .parse::<f64>()
+                .map_err(|_| Error::InvalidNumber(s.to_string()))?;
+            serde_json::Number::from_f64(n)
+                .ok_or_else(|| Error::InvalidNumber(s.to_string()))
+                .map(Value::Number)
+        } else {
+            parse_integer(s).ok_or_else(|| Error::InvalidNumber(s.to_string()))
+        }
     }
 
-    fn parse_value(&mut self) -> Result<Value, Error> {
-        self.skip_whitespace();
-        match self.peek_char() {
-            Some('[') => self.parse_array_value(),
-            Some('"') => self.parse_string(),
-            Some('-') => {
-                // List item marker
-                self.advance();
-                self.skip_whitespace();
-                self.parse_value()
+    /// Whether the cursor is at the end of an unquoted value
+    fn at_value_end(&self) -> bool {
+        matches!(self.peek_char(), None | Some(' ' | '\t' | '\r' | '\n'))
+    }
+
+    fn parse_boolean_or_null(&mut self) -> Option<Value> {
+        let start = self.pos;
+        self.parse_while(|ch| ch.is_ascii_alphabetic());
+        let s = &self.input[start..self.pos];
+
+        match s {
+            "true" if self.options.get_coerce_booleans() => Some(Value::Bool(true)),
+            "false" if self.options.get_coerce_booleans() => Some(Value::Bool(false)),
+            "null" => Some(Value::Null),
+            "nan" | "inf" if self.options.get_allow_nan() && self.options.get_coerce_numbers() => {
+                Some(non_finite_marker(s))
             }
-            Some(ch) if ch.is_ascii_digit() || ch == '-' => self.parse_number(),
-            Some(ch) if ch.is_ascii_alphabetic() => {
-                // Try boolean/null first, then fall back to string
-                let start = self.pos;
-                let value = self.parse_boolean_or_null();
-                if value.is_ok() {
-                    return value;
-                }
-                // Reset and parse as string
+            _ => {
+                // Not a boolean/null, reset position
                 self.pos = start;
-                self.parse_unquoted_string()
+                None
             }
-            _ => self.parse_unquoted_string(),
         }
     }
 
-    fn parse_unquoted_string(&mut self) -> Result<Value, Error> {
+    fn parse_key(&mut self) -> Result<String, Error> {
+        self.parse_entry_key().map(|(key, _)| key)
+    }
+
+    /// Parse a key along with whether it was quoted, which makes a dotted
+    /// key literal rather than a path
+    fn parse_entry_key(&mut self) -> Result<(String, bool), Error> {
+        self.skip_whitespace();
+        if self.peek_char() == Some('"') {
+            let len = quoted_len(self.rest_of_line())
+                .ok_or_else(|| self.error("Unterminated quoted key"))?;
+            let key = unescape(&self.input[self.pos..self.pos + len])?;
+            self.pos += len;
+            return Ok((key, true));
+        }
         let start = self.pos;
-        // Parse until we hit whitespace, newline, or end
+        // Parse key - stop at ':', '[', space, newline, or tab (or '{' for
+        // length-less table headers)
         while self.pos < self.input.len() {
             match self.peek_char() {
-                Some(ch) if ch == ' ' || ch == '\n' || ch == '\t' || ch == '\r' => break,
+                Some(ch) if ends_key(ch, self.options) => break,
                 Some(_) => self.advance(),
                 None => break,
             }
         }
         if self.pos == start {
-            return Err(Error::parse(self.pos, "Expected value"));
+            return Err(self.error("Expected key"));
         }
-        Ok(Value::String(self.input[start..self.pos].to_string()))
+        Ok((self.input[start..self.pos].to_string(), false))
     }
 
-    fn parse_value_until_newline(&mut self) -> Result<Value, Error> {
-        self.skip_whitespace();
+    fn split_row<'b>(&self, row: &'b str, delimiter: char) -> Vec<&'b str> {
+        // Use SIMD for larger inputs, fallback for small ones
+        // Threshold: use SIMD if row is large enough to benefit (>= 32 bytes)
+        if row.len() >= 32 {
+            simd::split_row_simd(row, delimiter)
+        } else {
+            simd::split_row_fallback(row, delimiter)
+        }
+    }
 
-        // Check what type of value we have
-        match self.peek_char() {
-            Some('[') => {
-                // Array - parse array value
-                self.parse_array_value()
-            }
-            Some('"') => self.parse_string(),
-            Some(ch) if ch.is_ascii_digit() || ch == '-' => self.parse_number(),
-            Some(ch) if ch.is_ascii_alphabetic() => {
-                // Try boolean/null first, then fall back to string
-                let start_pos = self.pos;
-                let value = self.parse_boolean_or_null();
-                if value.is_ok() {
-                    return value;
+    fn count_indent(&mut self, indent_size: usize) -> usize {
+        let start = self.pos;
+        let mut count = 0;
+        let indent_str = " ".repeat(indent_size);
+        while self.pos < self.input.len() {
+            if self.pos + indent_size <= self.input.len() {
+                let slice = &self.input.as_bytes()[self.pos..self.pos + indent_size];
+                if slice == indent_str.as_bytes() {
+                    count += 1;
+                    self.pos += indent_size;
+                } else {
+                    break;
                 }
-                // Reset and parse as string
-                self.pos = start_pos;
-                self.parse_unquoted_string()
+            } else {
+                break;
             }
-            _ => self.parse_unquoted_string(),
         }
+        let indent_level = count;
+        self.pos = start;
+        indent_level
     }
 
-    fn parse_array_value(&mut self) -> Result<Value, Error> {
-        if self.peek_char() != Some('[') {
-            return Err(Error::parse(self.pos, "Expected '['"));
+    fn skip_whitespace(&mut self) {
+        let bytes = self.input.as_bytes();
+        while matches!(bytes.get(self.pos), Some(b' ' | b'\t')) {
+            self.pos += 1;
         }
-        self.advance(); // consume '['
+    }
 
-        // Parse length marker (optional #) and length
-        let has_length_marker = self.peek_char() == Some('#');
-        if has_length_marker {
-            self.advance(); // consume '#'
-        }
+    fn skip_to_next_line(&mut self) {
+        self.pos = match self.input[self.pos..].find('\n') {
+            Some(i) => self.pos + i + 1,
+            None => self.input.len(),
+        };
+    }
 
-        let length_str = self.parse_while(|ch| ch.is_ascii_digit());
-        let length: usize = length_str
-            .parse()
-            .map_err(|_| Error::parse(self.pos, "Invalid array length"))?;
+    fn parse_while<F>(&mut self, mut pred: F) -> &'a str
+    where
+        F: FnMut(char) -> bool,
+    {
+        let input = self.input;
+        let start = self.pos;
+        let rest = &input[start..];
+        self.pos += rest.find(|ch| !pred(ch)).unwrap_or(rest.len());
+        &input[start..self.pos]
+    }
 
-        if self.peek_char() != Some(']') {
-            return Err(Error::parse(self.pos, "Expected ']'"));
-        }
-        self.advance(); // consume ']'
+    fn count_cells(&mut self, count: usize) -> Result<(), Error> {
+        self.cells = self.cells.saturating_add(count);
+        check_cells(self.options, self.cells).map_err(|msg| self.error(msg))
+    }
 
-        // Check for tabular format: {field1,field2}:
-        if self.peek_char() == Some('{') {
-            self.parse_tabular_array(length)
-        } else if self.peek_char() == Some(':') {
-            self.advance(); // consume ':'
-            self.skip_whitespace();
+    fn error(&self, message: impl Into<String>) -> Error {
+        let (line, column) = line_column(self.input, self.pos);
+        Error::parse(self.pos, line, column, message)
+    }
 
-            // Check if it's inline (same line) or list format (next line)
-            if length == 0 {
-                // Empty array - skip any whitespace and newline
-                self.skip_whitespace();
-                if self.peek_char() == Some('\n') {
-                    self.advance();
-                }
-                Ok(Value::Array(Vec::new()))
-            } else if self.peek_char() == Some('\n') || self.pos >= self.input.len() {
-                self.parse_list_array(length)
-            } else {
-                self.parse_inline_array(length)
-            }
-        } else {
-            Err(Error::parse(
-                self.pos,
-                "Expected ':' or '{' after array length",
-            ))
+    /// Character at the byte cursor, which always sits on a char boundary
+    fn peek_char(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn advance(&mut self) {
+        if let Some(ch) = self.peek_char() {
+            self.pos += ch.len_utf8();
         }
     }
+}
 
-    fn parse_tabular_array(&mut self, expected_length: usize) -> Result<Value, Error> {
-        if self.peek_char() != Some('{') {
-            return Err(Error::parse(self.pos, "Expected '{'"));
+/// Decode the `XXXX` of a `\uXXXX` escape starting at `start`, combining a
+/// surrogate pair when a second escape follows
+///
+/// Returns the character and the number of chars consumed after the `u`.
+fn unicode_escape(chars: &[char], start: usize) -> Result<(char, usize), Error> {
+    let unit = hex_unit(chars, start)?;
+    if (0xD800..0xDC00).contains(&unit)
+        && chars.get(start + 4) == Some(&'\\')
+        && chars.get(start + 5) == Some(&'u')
+    {
+        let low = hex_unit(chars, start + 6)?;
+        if (0xDC00..0xE000).contains(&low) {
+            let code = 0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00);
+            if let Some(ch) = char::from_u32(code) {
+                return Ok((ch, 10));
+            }
         }
-        self.advance(); // consume '{'
+    }
+    char::from_u32(unit)
+        .map(|ch| (ch, 4))
+        .ok_or_else(|| Error::InvalidEscape(format!("\\u{unit:04x} (unpaired surrogate)")))
+}
 
-        // Parse field names
-        let fields_str = self.parse_while(|ch| ch != '}');
-        let fields: Vec<&str> = fields_str.split(',').map(|s| s.trim()).collect();
-        let delimiter = self.detect_delimiter();
+/// Read four hex digits as a UTF-16 code unit
+fn hex_unit(chars: &[char], start: usize) -> Result<u32, Error> {
+    let digits: String = chars
+        .get(start..start + 4)
+        .unwrap_or_default()
+        .iter()
+        .collect();
+    if digits.len() != 4 || !digits.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return Err(Error::InvalidEscape(format!("\\u{digits}")));
+    }
+    u32::from_str_radix(&digits, 16).map_err(|_| Error::InvalidEscape(format!("\\u{digits}")))
+}
 
-        if self.peek_char() != Some('}') {
-            return Err(Error::parse(self.pos, "Expected '}'"));
-        }
-        self.advance(); // consume '}'
+/// Whether a character ends a key: `:`, whitespace, or the start of an
+/// array header
+pub(crate) fn ends_key(ch: char, options: &DecodeOptions) -> bool {
+    matches!(ch, ':' | ' ' | '\n' | '\t') || opens_array(Some(ch), options)
+}
 
-        if self.peek_char() != Some(':') {
-            return Err(Error::parse(self.pos, "Expected ':'"));
-        }
-        self.advance(); // consume ':'
-                        // Skip to next line (consume newline if present)
-        if self.peek_char() == Some('\n') {
-            self.advance();
+/// Whether a character after a key opens an array header: `[N]`, or a
+/// length-less `{fields}` table header with `lenient_arrays` or when not
+/// strict
+pub(crate) fn opens_array(ch: Option<char>, options: &DecodeOptions) -> bool {
+    match ch {
+        Some('[') => true,
+        Some('{') => options.lengthless_headers(),
+        _ => false,
+    }
+}
+
+/// Whether a list item starts with a `key:` or `key[N]` field (or a
+/// length-less `key{fields}:` table with `lenient_arrays`), making it an object
+pub(crate) fn is_object_item(line: &str, options: &DecodeOptions) -> bool {
+    if line.starts_with(['[', '-']) {
+        return false;
+    }
+    let end = if line.starts_with('"') {
+        quoted_len(line)
+    } else {
+        line.find([':', '[', '{', ' ', '\t']).filter(|&end| end > 0)
+    };
+    let Some(end) = end else {
+        return false;
+    };
+    let rest = &line[end..];
+    if rest.starts_with('{') {
+        return options.lengthless_headers() && rest.contains("}:");
+    }
+    if let Some(after) = rest.strip_prefix(':') {
+        return after.is_empty() || after.starts_with([' ', '\t']);
+    }
+    if let Some(after) = rest.strip_prefix('[') {
+        let after = after
+            .trim_start_matches('#')
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .trim_start_matches(is_declared_delimiter);
+        return after.starts_with("]:") || after.starts_with("]{");
+    }
+    false
+}
+
+/// Whether an inline value or list item is `{}`, an empty object
+pub(crate) fn is_empty_object(rest: &str) -> bool {
+    rest.trim_end() == "{}"
+}
+
+/// Whether the line at the root is a bare primitive rather than a `key:`
+/// entry or `key[N]` header, making it the whole document
+pub(crate) fn is_root_primitive(line: &str, options: &DecodeOptions) -> bool {
+    let line = line.trim();
+    let key_len = if line.starts_with('"') {
+        match quoted_len(line) {
+            Some(len) => len,
+            None => return false,
         }
+    } else {
+        line.find(|ch| ends_key(ch, options)).unwrap_or(line.len())
+    };
+    let rest = line[key_len..].trim_start_matches([' ', '\t']);
+    !rest.starts_with(':') && !opens_array(rest.chars().next(), options)
+}
 
-        // Parse rows
-        let mut items = Vec::new();
-        let indent = self.options.get_indent();
-        // Count base indentation of first row
-        let base_indent = self.count_indent(indent);
+/// Whether an indented line is a `- item` list entry
+pub(crate) fn is_list_item(line: &str) -> bool {
+    let line = line.trim_start();
+    line == "-" || line.starts_with("- ")
+}
 
-        for _ in 0..expected_length {
-            if self.pos >= self.input.len() {
-                break;
-            }
+/// Whether an inline value is a comma-separated list written without an
+/// array header (`tags: a,b,c`)
+pub(crate) fn is_unbracketed_array(rest: &str) -> bool {
+    simd::split_row_fallback(rest.trim_end(), ',').len() > 1
+}
 
-            // Count indentation of current line
-            let line_indent = self.count_indent(indent);
-            if line_indent < base_indent {
-                break; // Back at lower indentation level
+/// Byte length of the delimited row at the start of `rest`
+///
+/// The row ends at the first newline outside quotes, so a quoted cell may
+/// span lines. Returns `None` when more input is needed to decide; once
+/// `complete` is set, an unterminated quote ends the row at its first newline
+/// rather than swallowing the rest of the document.
+pub(crate) fn row_len(rest: &str, complete: bool) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut prev = None;
+    let mut first_newline = None;
+    for (i, ch) in rest.char_indices() {
+        match ch {
+            '"' if prev != Some('\\') => in_quotes = !in_quotes,
+            '\n' if !in_quotes => return Some(i),
+            '\n' => {
+                first_newline.get_or_insert(i);
             }
+            _ => {}
+        }
+        prev = Some(ch);
+    }
+    if !complete {
+        return None;
+    }
+    Some(if in_quotes {
+        first_newline.unwrap_or(rest.len())
+    } else {
+        rest.len()
+    })
+}
 
-            // Skip the indentation whitespace
-            for _ in 0..(line_indent * indent) {
-                if self.peek_char() == Some(' ') {
-                    self.advance();
-                } else {
-                    break;
-                }
-            }
+/// Recognize a block string header, returning whether its trailing newline is kept
+///
+/// `|` keeps a single trailing newline and `|-` strips it, as in YAML.
+pub(crate) fn block_header(rest: &str) -> Option<bool> {
+    match rest.trim_end() {
+        "|" => Some(true),
+        "|-" => Some(false),
+        _ => None,
+    }
+}
 
-            let mut obj = Map::new();
-            let start = self.pos;
-            // Parse until newline
-            while self.pos < self.input.len() && self.peek_char() != Some('\n') {
-                self.advance();
-            }
-            let row = &self.input[start..self.pos];
-            let values: Vec<&str> = self.split_row(row, delimiter);
+/// Whether a line's value, after its key or `- ` marker, is a `|` or `|-`
+/// block string
+pub(crate) fn opens_block(content: &str) -> bool {
+    let Some(marker) = content.rfind('|') else {
+        return false;
+    };
+    let before = content[..marker].trim_end();
+    block_header(&content[marker..]).is_some() && (before.ends_with(':') || before == "-")
+}
 
-            if values.len() != fields.len() && self.options.get_strict() {
-                return Err(Error::LengthMismatch {
-                    expected: fields.len(),
-                    found: values.len(),
-                });
-            }
+/// Content of a block string line, or `None` once a line is indented less
+/// than the block body (blank lines always belong to the block)
+pub(crate) fn block_line(line: &str, prefix_len: usize) -> Option<&str> {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let indented =
+        line.len() >= prefix_len && line.as_bytes()[..prefix_len].iter().all(|&b| b == b' ');
+    if indented {
+        Some(&line[prefix_len..])
+    } else if line.trim().is_empty() {
+        Some("")
+    } else {
+        None
+    }
+}
 
-            for (i, field) in fields.iter().enumerate() {
-                let value_str = values.get(i).unwrap_or(&"");
-                let value = self.parse_primitive_value(value_str.trim())?;
-                obj.insert(field.to_string(), value);
-            }
+/// Join block string lines, dropping trailing blank lines as YAML's clip does
+pub(crate) fn join_block_lines<S: AsRef<str>>(lines: &[S], keep_newline: bool) -> String {
+    let end = lines
+        .iter()
+        .rposition(|line| !line.as_ref().is_empty())
+        .map_or(0, |i| i + 1);
+    let mut text = lines[..end]
+        .iter()
+        .map(AsRef::as_ref)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if keep_newline {
+        text.push('\n');
+    }
+    text
+}
 
-            items.push(Value::Object(obj));
-            // Skip to next line
-            if self.pos < self.input.len() && self.peek_char() == Some('\n') {
-                self.advance();
-            }
-        }
+/// How much of an entry [`DecodeOptions::include_keys`] selects
+enum Selection {
+    /// All of it
+    All,
+    /// Some of its nested object, whose path is given
+    Part(String),
+    Nothing,
+}
 
-        if self.options.get_strict() && items.len() != expected_length {
-            return Err(Error::LengthMismatch {
-                expected: expected_length,
-                found: items.len(),
-            });
-        }
+/// Match the entry `key` of the object at `path` against the selected paths
+fn select_entry(options: &DecodeOptions, path: &str, key: &str, is_array: bool) -> Selection {
+    let path = if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    };
+    let below = |outer: &str, inner: &str| {
+        inner
+            .strip_prefix(outer)
+            .is_some_and(|rest| rest.starts_with('.'))
+    };
+    let keys = options.get_include_keys().unwrap_or_default();
+    if keys.iter().any(|k| *k == path || below(k, &path)) {
+        Selection::All
+    } else if !is_array && keys.iter().any(|k| below(&path, k)) {
+        Selection::Part(path)
+    } else {
+        Selection::Nothing
+    }
+}
 
-        Ok(Value::Array(items))
+/// Whether repeated keys are rejected rather than resolved
+fn is_duplicate_error(options: &DecodeOptions) -> bool {
+    options.get_duplicate_keys() == DuplicateKeyPolicy::Error
+}
+
+/// First repeated field in a table header, when duplicates are rejected
+fn duplicate_field<'f>(options: &DecodeOptions, fields: &[&'f str]) -> Option<&'f str> {
+    if !is_duplicate_error(options) {
+        return None;
     }
+    fields
+        .iter()
+        .enumerate()
+        .find(|(i, field)| fields[..*i].contains(field))
+        .map(|(_, field)| *field)
+}
 
-    fn parse_inline_array(&mut self, expected_length: usize) -> Result<Value, Error> {
-        let delimiter = self.detect_delimiter();
-        let start = self.pos;
-        // Parse until newline
-        while self.pos < self.input.len() && self.peek_char() != Some('\n') {
-            self.advance();
+/// How a parsed key is told apart from the others of its object when
+/// looking for repeats: a quoted dotted key differs from the path written
+/// the same way once paths are expanded
+fn entry_name(options: &DecodeOptions, key: &str, quoted: bool) -> (String, bool) {
+    let literal = quoted && options.get_expand_paths() && key.contains('.');
+    (key.to_string(), literal)
+}
+
+/// Whether an expanded entry repeats a key of `map` under
+/// `DuplicateKeyPolicy::Error`, rather than adding to an object that paths
+/// such as `a.b` and `a.c` build together
+fn repeats(options: &DecodeOptions, map: &Map<String, Value>, key: &str, value: &Value) -> bool {
+    let merges = options.get_expand_paths()
+        && value.is_object()
+        && map.get(key).is_some_and(Value::is_object);
+    is_duplicate_error(options) && !merges && map.contains_key(key)
+}
+
+/// Insert an entry, resolving a repeated key with the duplicate key policy
+///
+/// Under `DuplicateKeyPolicy::Error` the repeat has already been reported, so
+/// reaching it here means recovery mode, which keeps the first value.
+fn insert_entry(options: &DecodeOptions, map: &mut Map<String, Value>, key: String, value: Value) {
+    match options.get_duplicate_keys() {
+        DuplicateKeyPolicy::LastWins => {
+            map.insert(key, value);
         }
-        let row = &self.input[start..self.pos];
-        let values: Vec<&str> = self.split_row(row, delimiter);
+        DuplicateKeyPolicy::FirstWins | DuplicateKeyPolicy::Error => {
+            map.entry(key).or_insert(value);
+        }
+    }
+}
 
-        let mut items = Vec::new();
-        for value_str in values {
-            let trimmed = value_str.trim();
-            if !trimmed.is_empty() {
-                items.push(self.parse_primitive_value(trimmed)?);
+/// Insert a parsed entry, expanding a dotted key into nested objects when
+/// [`DecodeOptions::expand_paths`] is set
+///
+/// Only keys written unquoted are paths: a quoted `"a.b"` is a literal key,
+/// which is how the encoder writes one under key folding. Expanded entries
+/// are merged into objects already under the same key.
+pub(crate) fn insert_key(
+    options: &DecodeOptions,
+    map: &mut Map<String, Value>,
+    key: String,
+    quoted: bool,
+    value: Value,
+) {
+    if !options.get_expand_paths() {
+        return insert_entry(options, map, key, value);
+    }
+    let mut segments: Vec<&str> = key.split('.').collect();
+    // Keys with an empty segment are kept as they are
+    if quoted || segments.len() == 1 || segments.iter().any(|segment| segment.is_empty()) {
+        return merge_entry(options, map, key, value);
+    }
+    let first = segments.remove(0).to_string();
+    let nested = segments.into_iter().rev().fold(value, |value, segment| {
+        let mut map = Map::new();
+        map.insert(segment.to_string(), value);
+        Value::Object(map)
+    });
+    merge_entry(options, map, first, nested);
+}
+
+/// Insert an entry, merging it into an existing object under the same key
+pub(crate) fn merge_entry(
+    options: &DecodeOptions,
+    map: &mut Map<String, Value>,
+    key: String,
+    value: Value,
+) {
+    match (map.get_mut(&key), value) {
+        (Some(Value::Object(existing)), Value::Object(incoming)) => {
+            for (key, value) in incoming {
+                merge_entry(options, existing, key, value);
             }
         }
+        (_, value) => insert_entry(options, map, key, value),
+    }
+}
 
-        if self.options.get_strict() && items.len() != expected_length {
-            return Err(Error::LengthMismatch {
-                expected: expected_length,
-                found: items.len(),
-            });
+/// Reject a declared array length above `DecodeOptions::max_array_length`
+pub(crate) fn check_array_length(options: &DecodeOptions, length: usize) -> Result<(), String> {
+    let max = options.get_max_array_length();
+    if length > max {
+        return Err(format!("Array length {length} exceeds the limit of {max}"));
+    }
+    Ok(())
+}
+
+/// Reject a running cell count above `DecodeOptions::max_cells`
+pub(crate) fn check_cells(options: &DecodeOptions, cells: usize) -> Result<(), String> {
+    let max = options.get_max_cells();
+    if cells > max {
+        return Err(format!("Document exceeds the limit of {max} array cells"));
+    }
+    Ok(())
+}
+
+/// Build a table row from its cells, reshaping it to the header's fields
+/// unless the length policy is [`LengthPolicy::Error`]
+/// Report a row that has the wrong number of cells because it was written
+/// with a different delimiter than its header declares
+///
+/// Only applies when the length policy would reject the row anyway, and
+/// gives a clearer error than the length mismatch it replaces.
+fn check_row_delimiter(
+    options: &DecodeOptions,
+    row: &str,
+    fields: usize,
+    cells: usize,
+    delimiter: char,
+) -> Result<(), Error> {
+    if cells == fields || fields < 2 || options.get_length_policy() != LengthPolicy::Error {
+        return Ok(());
+    }
+    match [',', '\t', '|']
+        .into_iter()
+        .find(|&other| other != delimiter && simd::split_row_fallback(row, other).len() == fields)
+    {
+        Some(found) => Err(Error::DelimiterMismatch {
+            expected: delimiter,
+            found,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Build a table row's object, or `None` when the row filter rejects it
+///
+/// Empty cells, and cells missing from a row padded by the length policy,
+/// take their column's default.
+fn row_object(
+    options: &DecodeOptions,
+    fields: &[&str],
+    columns: &[Column],
+    values: &[&str],
+) -> Result<Option<Map<String, Value>>, Error> {
+    build_row(options, fields, columns, values, |i| {
+        let cell = values[i].trim();
+        match (fields.get(i), columns.get(i)) {
+            (Some(field), Some(column)) => column_cell(options, cell, field, column),
+            _ => parse_primitive_value(options, cell),
         }
+    })
+}
 
-        Ok(Value::Array(items))
+/// Build a table row's object as [`row_object`] does, taking the value of
+/// the cell at each index from `cell`
+///
+/// `cell` is only asked for cells that are kept and not left to a default,
+/// including those past the header's fields when they go under
+/// [`DecodeOptions::extra_cells_key`].
+pub(crate) fn build_row(
+    options: &DecodeOptions,
+    fields: &[&str],
+    columns: &[Column],
+    values: &[&str],
+    mut cell: impl FnMut(usize) -> Result<Value, Error>,
+) -> Result<Option<Map<String, Value>>, Error> {
+    let policy = options.get_length_policy();
+    if values.len() != fields.len() && policy == LengthPolicy::Error {
+        return Err(Error::LengthMismatch {
+            expected: fields.len(),
+            found: values.len(),
+        });
     }
-
-    fn parse_list_array(&mut self, expected_length: usize) -> Result<Value, Error> {
-        // Skip to next line if we're not already there
-        if self.peek_char() == Some('\n') {
-            self.advance();
+    if let Some(filter) = options.get_row_filter() {
+        if !filter.keep(&RawRow::new(fields, values)) {
+            return Ok(None);
         }
-        let indent = self.options.get_indent();
-        // Count base indentation of first item
-        let base_indent = self.count_indent(indent);
-        let mut items = Vec::new();
+    }
 
-        for _ in 0..expected_length {
-            if self.pos >= self.input.len() {
-                break;
-            }
+    let included = options.get_include_columns();
+    let mut obj = Map::new();
+    for (i, field) in fields.iter().enumerate() {
+        if included.is_some_and(|included| !included.iter().any(|column| column == field)) {
+            continue;
+        }
+        let column = columns.get(i);
+        let default = column.and_then(|column| column.default.as_ref());
+        let value = match (values.get(i).map(|cell| cell.trim()), default) {
+            (Some(""), Some(default)) => default.clone(),
+            (None, Some(default)) if policy != LengthPolicy::Truncate => default.clone(),
+            (Some(""), None) if options.get_empty_cells() == EmptyCellPolicy::Omit => continue,
+            (Some(_), _) => cell(i)?,
+            (None, _) if policy == LengthPolicy::Truncate => continue,
+            (None, _) => Value::Null,
+        };
+        let quoted = column.is_some_and(|column| column.quoted);
+        insert_key(options, &mut obj, field.to_string(), quoted, value);
+    }
+    if let Some(key) = options.get_extra_cells_key() {
+        if values.len() > fields.len() {
+            let extra = (fields.len()..values.len())
+                .map(&mut cell)
+                .collect::<Result<Vec<_>, _>>()?;
+            insert_entry(options, &mut obj, key.to_string(), Value::Array(extra));
+        }
+    }
+    Ok(Some(obj))
+}
 
-            // Count indentation of current line
-            let line_indent = self.count_indent(indent);
-            if line_indent < base_indent {
-                break; // Back at lower indentation level
-            }
+/// Items of an inline array row: an empty row has none, while an empty
+/// cell between delimiters is a null item
+fn inline_items(mut values: Vec<&str>) -> Vec<&str> {
+    if let [only] = values.as_slice() {
+        if only.trim().is_empty() {
+            values.clear();
+        }
+    }
+    values
+}
 
-            // Skip the indentation whitespace
-            for _ in 0..(line_indent * indent) {
-                if self.peek_char() == Some(' ') {
-                    self.advance();
-                } else {
-                    break;
-                }
-            }
+/// Reconcile an array of `found` items (including any skipped during error
+/// recovery) with its declared length, following the length policy
+fn fit_array(
+    options: &DecodeOptions,
+    items: &mut Vec<Value>,
+    expected: usize,
+    found: usize,
+) -> Result<(), Error> {
+    if found == expected {
+        return Ok(());
+    }
+    match options.get_length_policy() {
+        LengthPolicy::Error => return Err(Error::LengthMismatch { expected, found }),
+        LengthPolicy::Pad if found < expected => {
+            items.resize(items.len() + expected - found, Value::Null);
+        }
+        _ => items.truncate(items.len().saturating_sub(found.saturating_sub(expected))),
+    }
+    Ok(())
+}
 
-            // Check if there's a '-' marker (optional in some formats)
-            let has_dash = self.peek_char() == Some('-');
-            if has_dash {
-                self.advance(); // consume '-'
-                self.skip_whitespace();
-            }
+/// Whether a table row is an elision marker standing for rows left out,
+/// and if so how many it says there were
+///
+/// Strict decoding takes only the `… (9950 more rows)` form written by
+/// [`crate::EncodeOptions::max_rows_per_table`]. Lenient decoding also takes
+/// the `...` and `…` that models write when they cut a table short, alone
+/// or followed by a note in parentheses, giving `Some(None)` when no count
+/// can be read from them.
+pub(crate) fn elided_rows(row: &str, options: &DecodeOptions) -> Option<Option<usize>> {
+    let row = row.trim();
+    let rest = match row.strip_prefix('…') {
+        Some(rest) => rest,
+        None if !options.get_strict() => row.strip_prefix("...")?,
+        None => return None,
+    };
+    let rest = rest.trim_start();
+    if rest.is_empty() && !options.get_strict() {
+        return Some(None);
+    }
+    let note = rest.strip_prefix('(')?.strip_suffix(')')?;
+    let count = note
+        .strip_suffix(" more rows")
+        .or_else(|| note.strip_suffix(" more row"))
+        .and_then(|count| count.parse().ok());
+    match count {
+        Some(count) => Some(Some(count)),
+        None if !options.get_strict() => Some(None),
+        None => None,
+    }
+}
 
-            // Parse the value - we've already skipped indentation and optionally the '-'
-            // The value could be a primitive, object, or array
-            // Check if this line looks like an object (has key: value format)
-            let line_start = self.pos;
-            let line_end = self.input[line_start..]
-                .find('\n')
-                .map(|i| line_start + i)
-                .unwrap_or(self.input.len());
-            let line = &self.input[line_start..line_end].trim();
+/// Rows an elision marker makes up for: its count when strict decoding
+/// allows elided tables, and none otherwise, so the shortfall is a length
+/// mismatch
+fn elided_count(options: &DecodeOptions, elided: Option<usize>) -> usize {
+    match options.get_allow_elided_rows() {
+        true => elided.unwrap_or_default(),
+        false => 0,
+    }
+}
 
-            let value = if self.peek_char() == Some('[') {
-                self.parse_array_value()?
-            } else if line.contains(':')
-                && !line.starts_with('"')
-                && line.matches(':').count() == 1
-                && !line.trim_start().starts_with('-')
-            {
-                // It's an object (single key:value on this line, like "a: 1")
-                // Parse as a simple key-value pair manually (don't use parse_object which expects indentation)
-                let key = self.parse_key()?;
-                self.skip_whitespace();
-                if self.peek_char() != Some(':') {
-                    return Err(Error::parse(
-                        self.pos,
-                        format!("Expected ':' after key '{key}'"),
-                    ));
-                }
-                self.advance(); // consume ':'
-                self.skip_whitespace();
-                let val = self.parse_value()?;
-                let mut obj = Map::new();
-                obj.insert(key, val);
-                Value::Object(obj)
-            } else {
-                // Primitive value (number, string, boolean, etc.)
-                self.parse_value()?
-            };
-            items.push(value);
-            // Skip to next line
-            if self.pos < self.input.len() && self.peek_char() == Some('\n') {
-                self.advance();
-            }
-        }
+/// Check a table ended by an elision marker against its declared length
+///
+/// Strict decoding needs the marker's count to make up the difference,
+/// which it only counts with `allow_elided_rows`. Lenient decoding keeps
+/// the rows it has, neither padding nor failing.
+fn fit_elided(
+    options: &DecodeOptions,
+    items: &mut Vec<Value>,
+    expected: usize,
+    found: usize,
+    elided: Option<usize>,
+) -> Result<(), Error> {
+    if !options.get_strict() {
+        return Ok(());
+    }
+    fit_array(
+        options,
+        items,
+        expected,
+        found + elided_count(options, elided),
+    )
+}
 
-        if self.options.get_strict() && items.len() != expected_length {
-            return Err(Error::LengthMismatch {
-                expected: expected_length,
-                found: items.len(),
-            });
-        }
+/// Reject a string longer than `DecodeOptions::max_string_length`
+pub(crate) fn check_string_length(options: &DecodeOptions, len: usize) -> Result<(), String> {
+    let max = options.get_max_string_length();
+    if len > max {
+        return Err(format!(
+            "String of {len} bytes exceeds the limit of {max} bytes"
+        ));
+    }
+    Ok(())
+}
 
-        Ok(Value::Array(items))
+/// Parse a single delimited cell or inline array item
+pub(crate) fn parse_primitive_value(options: &DecodeOptions, s: &str) -> Result<Value, Error> {
+    let as_string = options.get_cells_as_strings();
+    if s.is_empty() && !as_string {
+        return Ok(Value::Null);
     }
 
-    fn parse_primitive_value(&self, s: &str) -> Result<Value, Error> {
-        if s.is_empty() {
-            return Ok(Value::Null);
-        }
+    if s == "null" && !as_string {
+        return Ok(Value::Null);
+    }
 
-        // Try boolean
+    // Try boolean
+    if options.get_coerce_booleans() && !as_string {
         if s == "true" {
             return Ok(Value::Bool(true));
         }
         if s == "false" {
             return Ok(Value::Bool(false));
         }
+    }
+
+    if options.get_coerce_numbers() && !as_string {
+        // Non-finite literals have no JSON representation
+        if is_non_finite_literal(s) {
+            return if options.get_allow_nan() {
+                Ok(non_finite_value(s))
+            } else {
+                Err(Error::InvalidNumber(s.to_string()))
+            };
+        }
 
         // Try number
-        if let Ok(n) = s.parse::<i64>() {
-            return Ok(Value::Number(n.into()));
+        if let Some(n) = parse_integer(s) {
+            return Ok(n);
         }
         if let Ok(n) = s.parse::<f64>() {
             return Ok(Value::Number(
@@ -529,245 +3403,420 @@ impl<'a> Parser<'a> {
             ));
         }
 
-        // Must be a string (possibly quoted)
-        if s.starts_with('"') && s.ends_with('"') {
-            self.parse_quoted_string(s)
-        } else {
-            Ok(Value::String(s.to_string()))
+        if options.get_lenient_numbers() {
+            if let Some(n) = lenient_integer(s) {
+                return Ok(n);
+            }
         }
     }
 
-    fn parse_quoted_string(&self, s: &str) -> Result<Value, Error> {
-        let mut result = String::new();
-        let chars: Vec<char> = s.chars().collect();
-        let mut i = 1; // Skip opening quote
+    // Must be a string (possibly quoted)
+    if s.starts_with('"') && s.ends_with('"') {
+        parse_quoted_string(s)
+    } else {
+        Ok(Value::String(s.to_string()))
+    }
+}
+
+/// Unescape a quoted string, including its surrounding quotes
+fn parse_quoted_string(s: &str) -> Result<Value, Error> {
+    unescape(s).map(Value::String)
+}
 
-        while i < chars.len() - 1 {
-            // Skip closing quote
-            match chars[i] {
-                '\\' => {
-                    i += 1;
-                    if i >= chars.len() - 1 {
-                        return Err(Error::InvalidEscape("Unterminated escape".to_string()));
+/// The text of a quoted string or key, including its surrounding quotes
+pub(crate) fn unescape(s: &str) -> Result<String, Error> {
+    let mut result = String::new();
+    let chars: Vec<char> = s.chars().collect();
+    let mut i = 1; // Skip opening quote
+
+    while i < chars.len() - 1 {
+        // Skip closing quote
+        match chars[i] {
+            '\\' => {
+                i += 1;
+                if i >= chars.len() - 1 {
+                    return Err(Error::InvalidEscape("Unterminated escape".to_string()));
+                }
+                match chars[i] {
+                    '"' => result.push('"'),
+                    '\\' => result.push('\\'),
+                    'n' => result.push('\n'),
+                    'r' => result.push('\r'),
+                    't' => result.push('\t'),
+                    'u' => {
+                        let (ch, len) = unicode_escape(&chars[..chars.len() - 1], i + 1)?;
+                        result.push(ch);
+                        i += len;
                     }
-                    match chars[i] {
-                        '"' => result.push('"'),
-                        '\\' => result.push('\\'),
-                        'n' => result.push('\n'),
-                        'r' => result.push('\r'),
-                        't' => result.push('\t'),
-                        _ => {
-                            return Err(Error::InvalidEscape(format!("\\{}", chars[i])));
-                        }
+                    _ => {
+                        return Err(Error::InvalidEscape(format!("\\{}", chars[i])));
                     }
                 }
-                ch => result.push(ch),
             }
-            i += 1;
+            ch => result.push(ch),
         }
-
-        Ok(Value::String(result))
+        i += 1;
     }
 
-    fn parse_string(&mut self) -> Result<Value, Error> {
-        if self.peek_char() != Some('"') {
-            return Err(Error::parse(self.pos, "Expected '\"'"));
-        }
-        self.advance(); // consume opening quote
+    Ok(result)
+}
 
-        let start = self.pos;
-        let mut escaped = false;
+/// Byte length of the quoted key at the start of `s`, including its quotes,
+/// or `None` if it isn't closed on the same line
+pub(crate) fn quoted_len(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, ch) in s.char_indices().skip(1) {
+        match ch {
+            '\n' => return None,
+            '"' if !escaped => return Some(i + 1),
+            _ => {}
+        }
+        escaped = ch == '\\' && !escaped;
+    }
+    None
+}
 
-        while self.pos < self.input.len() {
-            let ch = self.input.chars().nth(self.pos).unwrap();
-            if escaped {
-                escaped = false;
-            } else if ch == '\\' {
-                escaped = true;
-            } else if ch == '"' {
-                let s = &self.input[start..self.pos];
-                self.advance(); // consume closing quote
-                return self.parse_quoted_string(&format!("\"{s}\""));
-            }
-            self.advance();
+/// Predicate for scanning up to `end`, skipping over quoted text
+pub(crate) fn until_unquoted(end: char) -> impl FnMut(char) -> bool {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    move |ch| {
+        if in_quotes {
+            in_quotes = escaped || ch != '"';
+            escaped = ch == '\\' && !escaped;
+            true
+        } else {
+            in_quotes = ch == '"';
+            ch != end
         }
+    }
+}
 
-        Err(Error::UnterminatedString)
+/// The delimiters that split `text` outside quotes, tab first and comma last,
+/// with the number of pieces each gives; comma stands alone when none do
+fn delimiter_candidates(text: &str) -> Vec<(char, usize)> {
+    let pieces = |delimiter| simd::split_row_fallback(text, delimiter).len();
+    let mut candidates = Vec::new();
+    // A cheap scan rules out tabs and pipes for most text
+    if simd::detect_delimiter_simd(text) != ',' {
+        candidates.extend(['\t', '|'].map(|d| (d, pieces(d))));
     }
+    candidates.push((',', pieces(',')));
+    candidates.retain(|&(_, n)| n > 1);
+    if candidates.is_empty() {
+        candidates.push((',', 1));
+    }
+    candidates
+}
 
-    fn parse_number(&mut self) -> Result<Value, Error> {
-        let start = self.pos;
-        let mut has_dot = false;
+/// Whether `ch` is a delimiter an array length may declare after its digits
+/// (`[2|]`, `[2\t]`); a comma is the default and isn't written
+pub(crate) fn is_declared_delimiter(ch: char) -> bool {
+    matches!(ch, '|' | '\t')
+}
 
-        if self.peek_char() == Some('-') {
-            self.advance();
-        }
+/// Delimiter of a table, read from its header fields; when several split
+/// the header, the one that splits the first row as many ways wins
+pub(crate) fn table_delimiter(fields: &str, first_row: Option<&str>) -> char {
+    let candidates = delimiter_candidates(fields);
+    first_row
+        .and_then(|row| {
+            candidates
+                .iter()
+                .find(|&&(d, n)| simd::split_row_fallback(row, d).len() == n)
+        })
+        .unwrap_or(&candidates[0])
+        .0
+}
 
-        while self.pos < self.input.len() {
-            match self.peek_char() {
-                Some(ch) if ch.is_ascii_digit() => {
-                    self.advance();
-                }
-                Some('.') if !has_dot => {
-                    has_dot = true;
-                    self.advance();
-                }
-                _ => break,
-            }
-        }
+/// Delimiter of an inline array, read from its own row; a declared length
+/// picks between several that split it
+pub(crate) fn inline_delimiter(row: &str, expected_length: Option<usize>) -> char {
+    let candidates = delimiter_candidates(row);
+    candidates
+        .iter()
+        .find(|&&(_, n)| Some(n) == expected_length)
+        .unwrap_or(&candidates[0])
+        .0
+}
 
-        let s = &self.input[start..self.pos];
-        if has_dot {
-            let n = s
-                .parse::<f64>()
-                .map_err(|_| Error::InvalidNumber(s.to_string()))?;
-            serde_json::Number::from_f64(n)
-                .ok_or_else(|| Error::InvalidNumber(s.to_string()))
-                .map(Value::Number)
-        } else {
-            s.parse::<i64>()
-                .map(|n| Value::Number(n.into()))
-                .map_err(|_| Error::InvalidNumber(s.to_string()))
-        }
-    }
+/// What a table header declares about a column besides its name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Column {
+    /// Type the column's cells are held to (`qty:int`)
+    pub(crate) ty: Option<ColumnType>,
+    /// Value of the column's empty cells (`region=EU`)
+    pub(crate) default: Option<Value>,
+    /// Strings the column's cells refer to by index
+    /// (`status@[active;pending]`)
+    pub(crate) dictionary: Option<Vec<String>>,
+    /// Whether the name was quoted, so a dotted one is never expanded
+    pub(crate) quoted: bool,
+}
 
-    fn parse_boolean_or_null(&mut self) -> Result<Value, Error> {
-        let start = self.pos;
-        self.parse_while(|ch| ch.is_ascii_alphabetic());
-        let s = &self.input[start..self.pos];
+/// The names of a table header's fields and what it declares about each
+pub(crate) type Fields = (Vec<String>, Vec<Column>);
 
-        match s {
-            "true" => Ok(Value::Bool(true)),
-            "false" => Ok(Value::Bool(false)),
-            "null" => Ok(Value::Null),
-            _ => {
-                // Not a boolean/null, reset position
-                self.pos = start;
-                Err(Error::parse(
-                    self.pos,
-                    format!("Not a boolean or null: {s}"),
-                ))
-            }
-        }
-    }
+/// Why the `#` length marker is refused, if the targeted spec revision
+/// lacks it
+fn length_marker_error(options: &DecodeOptions) -> Option<String> {
+    let version = options.get_spec_version()?;
+    (version >= SpecVersion::V2).then(|| format!("`#` length markers aren't part of {version}"))
+}
 
-    fn parse_key(&mut self) -> Result<String, Error> {
-        self.skip_whitespace();
-        let start = self.pos;
-        // Parse key - stop at ':', '[', space, newline, or tab
-        while self.pos < self.input.len() {
-            match self.peek_char() {
-                Some(ch) if ch == ':' || ch == '[' || ch == ' ' || ch == '\n' || ch == '\t' => {
-                    break
-                }
-                Some(_) => self.advance(),
-                None => break,
+/// Split the fields of a `{...}` table header, unquoting quoted ones, along
+/// with the type (`qty:int`), dictionary (`status@[active;pending]`) and
+/// default (`region=EU`) declared after each, in that order
+pub(crate) fn header_fields(
+    fields: &str,
+    delimiter: char,
+    options: &DecodeOptions,
+) -> Result<Fields, Error> {
+    simd::split_row_fallback(fields, delimiter)
+        .into_iter()
+        .map(|field| {
+            let field = field.trim();
+            let invalid =
+                |problem: &str| Error::InvalidHeader(format!("{problem} in field {field}"));
+            let quoted = field.starts_with('"');
+            let (name, rest) = if quoted {
+                let len = quoted_len(field).ok_or_else(|| invalid("malformed quoting"))?;
+                (unescape(&field[..len])?, &field[len..])
+            } else {
+                let end = field_name_len(field);
+                (field[..end].trim_end().to_string(), &field[end..])
+            };
+            let mut rest = rest.trim_start();
+            if let Some(version) = options.get_spec_version().filter(|_| !rest.is_empty()) {
+                return Err(invalid(&format!(
+                    "column types, defaults and dictionaries aren't part of {version}"
+                )));
             }
-        }
-        if self.pos == start {
-            return Err(Error::parse(self.pos, "Expected key"));
-        }
-        Ok(self.input[start..self.pos].to_string())
-    }
+            let ty = match rest.strip_prefix(':') {
+                Some(after) => {
+                    let end = after.find(['@', '=']).unwrap_or(after.len());
+                    rest = after[end..].trim_start();
+                    Some(
+                        ColumnType::from_name(after[..end].trim())
+                            .ok_or_else(|| invalid("unknown column type"))?,
+                    )
+                }
+                None => None,
+            };
+            let dictionary = match rest.strip_prefix("@[") {
+                Some(after) => {
+                    let mut inside = until_unquoted(']');
+                    let end = after
+                        .find(|ch| !inside(ch))
+                        .ok_or_else(|| invalid("unclosed dictionary"))?;
+                    rest = after[end + 1..].trim_start();
+                    Some(dictionary_entries(&after[..end], field)?)
+                }
+                None => None,
+            };
+            let default = match rest.strip_prefix('=').map(str::trim) {
+                None if rest.is_empty() => None,
+                None => return Err(invalid("unexpected text")),
+                Some("") => return Err(invalid("missing default value")),
+                Some(text) => Some(match ty {
+                    Some(ty) => typed_cell(options, text, &name, ty)?,
+                    None => parse_primitive_value(options, text)?,
+                }),
+            };
+            Ok((
+                name,
+                Column {
+                    ty,
+                    default,
+                    dictionary,
+                    quoted,
+                },
+            ))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|fields| fields.into_iter().unzip())
+}
 
-    fn detect_delimiter(&self) -> char {
-        // Look ahead to detect delimiter
-        let remaining = &self.input[self.pos..];
+/// Length of an unquoted header field's name, which ends at a type,
+/// dictionary or default
+pub(crate) fn field_name_len(field: &str) -> usize {
+    let end = field.find([':', '=']).unwrap_or(field.len());
+    field[..end].find("@[").unwrap_or(end)
+}
 
-        // Use SIMD for larger inputs, fallback for small ones
-        // Threshold: use SIMD if input is large enough to benefit (>= 32 bytes)
-        if remaining.len() >= 32 {
-            simd::detect_delimiter_simd(remaining)
-        } else {
-            simd::detect_delimiter_fallback(remaining)
-        }
-    }
+/// The `;`-separated entries of the dictionary of a header field, quoted
+/// ones unescaped and the rest kept as written
+fn dictionary_entries(text: &str, field: &str) -> Result<Vec<String>, Error> {
+    simd::split_row_fallback(text, ';')
+        .into_iter()
+        .map(|entry| match entry.trim() {
+            "" => Err(Error::InvalidHeader(format!(
+                "empty dictionary entry in field {field}"
+            ))),
+            entry if entry.starts_with('"') => unescape(entry),
+            entry => Ok(entry.to_string()),
+        })
+        .collect()
+}
 
-    fn split_row<'b>(&self, row: &'b str, delimiter: char) -> Vec<&'b str> {
-        // Use SIMD for larger inputs, fallback for small ones
-        // Threshold: use SIMD if row is large enough to benefit (>= 32 bytes)
-        if row.len() >= 32 {
-            simd::split_row_simd(row, delimiter)
-        } else {
-            simd::split_row_fallback(row, delimiter)
-        }
+/// Parse a cell of a column as its header declares: by index into its
+/// dictionary, held to its type, or as a plain primitive
+pub(crate) fn column_cell(
+    options: &DecodeOptions,
+    cell: &str,
+    field: &str,
+    column: &Column,
+) -> Result<Value, Error> {
+    match (&column.dictionary, column.ty) {
+        (Some(_), _) if cell.is_empty() || cell == "null" => Ok(Value::Null),
+        (Some(dictionary), _) => cell
+            .parse::<usize>()
+            .ok()
+            .and_then(|code| dictionary.get(code))
+            .map(|entry| Value::String(entry.clone()))
+            .ok_or_else(|| {
+                Error::Syntax(format!(
+                    "Cell {cell} in column '{field}' is not an entry of its dictionary"
+                ))
+            }),
+        (None, Some(ty)) => typed_cell(options, cell, field, ty),
+        (None, None) => parse_primitive_value(options, cell),
     }
+}
 
-    fn count_indent(&mut self, indent_size: usize) -> usize {
-        let start = self.pos;
-        let mut count = 0;
-        let indent_str = " ".repeat(indent_size);
-        while self.pos < self.input.len() {
-            if self.pos + indent_size <= self.input.len() {
-                let slice = &self.input[self.pos..self.pos + indent_size];
-                if slice == indent_str {
-                    count += 1;
-                    self.pos += indent_size;
-                } else {
-                    break;
-                }
+/// Parse a cell of a column declared with a type, rejecting one of another
+/// type
+///
+/// Empty cells and `null` are null in any column. An unquoted `str` cell is
+/// the text as written, so `04532` and `true` stay strings, and `float`
+/// cells are floats even when written without a fraction.
+pub(crate) fn typed_cell(
+    options: &DecodeOptions,
+    cell: &str,
+    field: &str,
+    ty: ColumnType,
+) -> Result<Value, Error> {
+    if cell.is_empty() || cell == "null" {
+        return Ok(Value::Null);
+    }
+    let value = match ty {
+        ColumnType::Str if cell.starts_with('"') => Some(parse_quoted_string(cell)?),
+        ColumnType::Str => Some(Value::String(cell.to_string())),
+        ColumnType::Int => parse_integer(cell)
+            .or_else(|| lenient_integer(cell).filter(|_| options.get_lenient_numbers())),
+        ColumnType::Float if is_non_finite_literal(cell) => {
+            return if options.get_allow_nan() {
+                Ok(non_finite_value(cell))
             } else {
-                break;
-            }
+                Err(Error::InvalidNumber(cell.to_string()))
+            };
         }
-        let indent_level = count;
-        self.pos = start;
-        indent_level
-    }
+        ColumnType::Float => cell
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        ColumnType::Bool => match cell {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+    };
+    value.ok_or_else(|| {
+        Error::Syntax(format!(
+            "Cell {cell} in column '{field}' is not of its declared type {ty}"
+        ))
+    })
+}
 
-    fn skip_whitespace(&mut self) {
-        while self.pos < self.input.len() {
-            match self.input.chars().nth(self.pos) {
-                Some(' ') | Some('\t') => self.pos += 1,
-                _ => break,
-            }
-        }
+/// An integer in the range of `i64` or `u64`, so unsigned values above
+/// `i64::MAX` keep every digit
+fn parse_integer(s: &str) -> Option<Value> {
+    match s.parse::<i64>() {
+        Ok(n) => Some(n.into()),
+        Err(_) => s.parse::<u64>().ok().map(Value::from),
     }
+}
 
-    fn skip_to_next_line(&mut self) {
-        while self.pos < self.input.len() {
-            if self.input.chars().nth(self.pos) == Some('\n') {
-                self.pos += 1;
-                break;
-            }
-            self.pos += 1;
-        }
+/// Parse hex (`0xFF`) and underscore-separated (`1_000_000`) integer literals
+/// up to `u64::MAX`, or `None` for anything else
+fn lenient_integer(s: &str) -> Option<Value> {
+    let (negative, body) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (radix, digits) = match body.strip_prefix("0x").or_else(|| body.strip_prefix("0X")) {
+        Some(hex) => (16, hex),
+        None => (10, body),
+    };
+    // Plain decimals are handled by the regular number path
+    if radix == 10 && !digits.contains('_') {
+        return None;
     }
-
-    fn parse_while<F>(&mut self, mut pred: F) -> &'a str
-    where
-        F: FnMut(char) -> bool,
+    // Underscores are only allowed between digits
+    if digits.is_empty()
+        || digits.starts_with('_')
+        || digits.ends_with('_')
+        || digits.contains("__")
     {
-        let start = self.pos;
-        while self.pos < self.input.len() {
-            if let Some(ch) = self.input.chars().nth(self.pos) {
-                if pred(ch) {
-                    self.pos += 1;
-                } else {
-                    break;
-                }
-            } else {
-                break;
-            }
-        }
-        &self.input[start..self.pos]
+        return None;
     }
+    let cleaned: String = digits.chars().filter(|&ch| ch != '_').collect();
+    if !cleaned.chars().all(|ch| ch.is_digit(radix)) {
+        return None;
+    }
+    let magnitude = u64::from_str_radix(&cleaned, radix).ok()?;
+    if negative {
+        0i64.checked_sub_unsigned(magnitude).map(Value::from)
+    } else {
+        Some(Value::from(magnitude))
+    }
+}
+
+/// Compute the 1-based line and column (in characters) of a byte offset
+pub(crate) fn line_column(input: &str, pos: usize) -> (usize, usize) {
+    let prefix = input.get(..pos).unwrap_or(input);
+    let line = prefix.matches('\n').count() + 1;
+    let column = prefix.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+/// Move the complete UTF-8 text at the front of `partial` onto `buffer`
+///
+/// A multi-byte character may straddle two reads, so its leading bytes are
+/// held back in `partial` until the rest arrives.
+pub(crate) fn push_utf8(partial: &mut Vec<u8>, buffer: &mut String) -> Result<(), Error> {
+    let valid = match std::str::from_utf8(partial) {
+        Ok(chunk) => chunk.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(e) => return Err(Error::Io(format!("Invalid UTF-8: {}", e))),
+    };
+    let chunk = std::str::from_utf8(&partial[..valid])
+        .map_err(|e| Error::Io(format!("Invalid UTF-8: {}", e)))?;
+    buffer.push_str(chunk);
+    partial.drain(..valid);
+    Ok(())
+}
 
-    fn peek_char(&self) -> Option<char> {
-        self.input.chars().nth(self.pos)
-    }
+/// Check for the `nan`, `inf` and `-inf` literals
+fn is_non_finite_literal(s: &str) -> bool {
+    matches!(s, "nan" | "inf" | "-inf" | "+inf")
+}
 
-    fn advance(&mut self) {
-        if self.pos < self.input.len() {
-            self.pos += 1;
-        }
-    }
+/// Value of an accepted `nan`, `inf` or `-inf` literal: the marker that
+/// [`from_str`](crate::from_str) reads back as the float and that
+/// [`NanPolicy::Token`](crate::options::NanPolicy::Token) writes as the literal
+fn non_finite_value(literal: &str) -> Value {
+    non_finite_marker(literal.strip_prefix('+').unwrap_or(literal))
 }
 
 /// Decode a TOON-formatted stream from a reader to a JSON value
 ///
 /// This function reads and parses the TOON format incrementally from the reader
 /// without loading the entire input into memory, making it suitable for large datasets.
+/// Input is read a line at a time, and lines are released once the entry, list
+/// item or table row they belong to has been parsed, so the text held at any
+/// moment is bounded by the largest such section rather than by the document.
 ///
 /// # Arguments
 ///
@@ -790,73 +3839,279 @@ impl<'a> Parser<'a> {
 pub fn decode_stream<R: Read>(reader: R, options: Option<&DecodeOptions>) -> Result<Value, Error> {
     let default_opts = DecodeOptions::default();
     let opts = options.unwrap_or(&default_opts);
-    let mut buf_reader = BufReader::with_capacity(8192, reader);
-    let mut parser = StreamingParser::new(&mut buf_reader, opts)?;
-    parser.parse()
+    if opts.get_strict() && indent_unit(opts).is_none() {
+        decode_reader(reader, opts, None)
+    } else {
+        decode_reader(NormalizedReader::new(reader, opts), opts, None)
+    }
+}
+
+/// Decode a TOON stream like [`decode_stream`], also returning a summary
+/// of the document's shape
+///
+/// The counts are gathered while parsing, so they cost little beyond the
+/// decode itself. With `strict(false)` they describe the normalized text,
+/// without the blank lines it drops.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::decode_with_stats;
+///
+/// let toon = "name: report\norders[1]:\n  - id: 7\n    lines[2]{sku,qty}:\n      A1,2\n      B2,1\n";
+/// let (value, stats) = decode_with_stats(toon.as_bytes(), None)?;
+/// assert_eq!(value["orders"][0]["lines"][1]["sku"], "B2");
+/// assert_eq!((stats.lines, stats.sections, stats.max_depth), (6, 2, 5));
+/// assert_eq!(stats.tables, [("orders[0].lines".to_string(), 2)]);
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn decode_with_stats<R: Read>(
+    reader: R,
+    options: Option<&DecodeOptions>,
+) -> Result<(Value, DecodeStats), Error> {
+    let default_opts = DecodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let mut stats = DecodeStats::default();
+    let value = if opts.get_strict() && indent_unit(opts).is_none() {
+        decode_reader(reader, opts, Some(&mut stats))
+    } else {
+        decode_reader(NormalizedReader::new(reader, opts), opts, Some(&mut stats))
+    }?;
+    Ok((value, stats))
+}
+
+/// Shape of a document, gathered by [`decode_with_stats`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeStats {
+    /// Lines in the document
+    pub lines: usize,
+    /// Top-level entries, or 1 for a document that isn't an object
+    pub sections: usize,
+    /// The row count of each tabular array in document order, by its path
+    /// (`orders[0].lines`), which is empty for a root table
+    pub tables: Vec<(String, usize)>,
+    /// Tables that ended with an elision marker (`…` or `... (N more rows)`)
+    /// by path, with the number of rows the table was said to have: its
+    /// declared length, or failing that the rows read plus those the marker
+    /// counts
+    pub truncated: Vec<(String, usize)>,
+    /// Deepest nesting of objects and arrays: 0 for a primitive document, 1
+    /// for an object of primitives
+    pub max_depth: usize,
+}
+
+fn decode_reader<R: Read>(
+    reader: R,
+    options: &DecodeOptions,
+    stats: Option<&mut DecodeStats>,
+) -> Result<Value, Error> {
+    let mut parser = StreamingParser::new(BufReader::with_capacity(8192, reader), options)?;
+    if stats.is_some() {
+        parser.tables = Some(Vec::new());
+        parser.truncated = Some(Vec::new());
+    }
+    let value = parser.parse();
+    if let Some(err) = parser.failed.take() {
+        return Err(err);
+    }
+    parser.report();
+    let value = value?;
+    if let Some(stats) = stats {
+        // Trailing blank lines count too
+        while !parser.eof {
+            let buffered = parser.buffer.len() - parser.pos;
+            parser.fill_buffer(buffered + 8192)?;
+        }
+        let rest = &parser.buffer;
+        let partial_line = !rest.is_empty() && !rest.ends_with('\n');
+        *stats = DecodeStats {
+            lines: parser.consumed_lines + rest.matches('\n').count() + usize::from(partial_line),
+            sections: if value.is_object() {
+                parser.sections
+            } else {
+                1
+            },
+            tables: parser.tables.take().unwrap_or_default(),
+            truncated: parser.truncated.take().unwrap_or_default(),
+            max_depth: depth(&value),
+        };
+    }
+    Ok(value)
+}
+
+/// Nesting depth of objects and arrays in `value`
+fn depth(value: &Value) -> usize {
+    match value {
+        Value::Object(map) => 1 + map.values().map(depth).max().unwrap_or(0),
+        Value::Array(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
+        _ => 0,
+    }
 }
 
-struct StreamingParser<'a, R: Read> {
-    reader: &'a mut BufReader<R>,
+/// Parser over a [`BufRead`] that holds only the lines of the section being
+/// parsed
+///
+/// `buffer` grows by whole lines as the cursor needs them, and [`release`]
+/// drops the lines before the cursor at points where no position into them
+/// is still held: the start of each entry, list item and table row.
+///
+/// [`release`]: StreamingParser::release
+struct StreamingParser<'a, R: BufRead> {
+    reader: R,
     buffer: String,
+    /// Trailing bytes of a UTF-8 sequence split across reads
+    partial: Vec<u8>,
     pos: usize,
     options: &'a DecodeOptions,
     eof: bool,
+    /// Bytes and lines released from the front of the buffer
+    consumed: usize,
+    consumed_lines: usize,
+    /// Array cells decoded so far, checked against `max_cells`
+    cells: usize,
+    /// Read error hit while peeking, which can't return it
+    failed: Option<Error>,
+    /// Table rows and list items parsed so far, and the top-level key being
+    /// parsed, for the progress callback
+    rows: usize,
+    section: Option<String>,
+    /// Top-level entries parsed, and with stats wanted, the row count of
+    /// each table along with the path to the value being parsed
+    sections: usize,
+    tables: Option<Vec<(String, usize)>>,
+    truncated: Option<Vec<(String, usize)>>,
+    path: Vec<String>,
+    /// Path of the object being parsed while `include_keys` narrows what is
+    /// decoded, or `None` when everything is
+    include: Option<String>,
 }
 
-impl<'a, R: Read> StreamingParser<'a, R> {
-    fn new(reader: &'a mut BufReader<R>, options: &'a DecodeOptions) -> Result<Self, Error> {
+impl<'a, R: BufRead> StreamingParser<'a, R> {
+    fn new(reader: R, options: &'a DecodeOptions) -> Result<Self, Error> {
         let mut parser = Self {
             reader,
             buffer: String::new(),
+            partial: Vec::new(),
             pos: 0,
             options,
             eof: false,
+            consumed: 0,
+            consumed_lines: 0,
+            cells: 0,
+            failed: None,
+            rows: 0,
+            section: None,
+            sections: 0,
+            tables: None,
+            truncated: None,
+            path: Vec::new(),
+            include: None,
         };
-        // Read initial chunk (8KB buffer)
-        parser.fill_buffer(8192)?;
+        parser.fill_buffer(1)?;
         Ok(parser)
     }
 
+    /// Read whole lines until the buffer holds `min_size` bytes past the cursor
     fn fill_buffer(&mut self, min_size: usize) -> Result<(), Error> {
-        if self.eof {
-            return Ok(());
+        while self.pos + min_size > self.buffer.len() && !self.eof {
+            match self.reader.read_until(b'\n', &mut self.partial) {
+                Ok(0) => {
+                    if !self.partial.is_empty() {
+                        return Err(Error::Io(
+                            "Invalid UTF-8: incomplete sequence at end of input".to_string(),
+                        ));
+                    }
+                    self.eof = true;
+                }
+                Ok(_) => push_utf8(&mut self.partial, &mut self.buffer)?,
+                Err(e) => return Err(Error::Io(e.to_string())),
+            }
         }
+        Ok(())
+    }
 
-        // Only drain if we've consumed a significant portion and need more space
-        // This helps with memory efficiency for very large streams
-        if self.pos > 8192 && self.pos > self.buffer.len() / 2 {
-            self.buffer.drain(..self.pos);
-            self.pos = 0;
+    /// Tell the progress callback, if there is one, how far parsing has got
+    fn report(&self) {
+        if let Some(callback) = &self.options.progress {
+            callback.report(&Progress {
+                bytes: self.consumed + self.pos,
+                rows: self.rows,
+                section: self.section.as_deref(),
+            });
         }
+    }
 
-        // Read more data if needed
-        let needed = if self.pos + min_size > self.buffer.len() {
-            self.pos + min_size - self.buffer.len()
-        } else {
-            0
-        };
+    /// Count a parsed table row or list item
+    fn row_done(&mut self) {
+        self.rows += 1;
+        self.report();
+    }
 
-        if needed > 0 {
-            let mut temp_buf = vec![0u8; needed.max(8192)];
-            match self.reader.read(&mut temp_buf) {
-                Ok(0) => {
-                    self.eof = true;
-                }
-                Ok(n) => {
-                    let chunk = String::from_utf8(temp_buf[..n].to_vec())
-                        .map_err(|e| Error::Io(format!("Invalid UTF-8: {}", e)))?;
-                    self.buffer.push_str(&chunk);
-                }
-                Err(e) => return Err(Error::Io(e.to_string())),
+    /// Step into a key or list index of the path, when tables are recorded
+    fn enter(&mut self, segment: impl FnOnce() -> String) {
+        if self.tables.is_some() {
+            self.path.push(segment());
+        }
+    }
+
+    fn leave(&mut self) {
+        self.path.pop();
+    }
+
+    /// Record the row count of the table just parsed
+    fn table_done(&mut self, rows: usize) {
+        if self.tables.is_some() {
+            let path = self.table_path();
+            self.tables.get_or_insert_with(Vec::new).push((path, rows));
+        }
+    }
+
+    /// Record the length of the table just parsed, which an elision marker
+    /// cut short
+    fn table_cut(&mut self, declared: usize) {
+        if self.truncated.is_some() {
+            let path = self.table_path();
+            self.truncated
+                .get_or_insert_with(Vec::new)
+                .push((path, declared));
+        }
+    }
+
+    /// Path of the value being parsed, as written in [`DecodeStats`]
+    fn table_path(&self) -> String {
+        let mut path = String::new();
+        for segment in &self.path {
+            if !path.is_empty() && !segment.starts_with('[') {
+                path.push('.');
             }
+            path.push_str(segment);
         }
+        path
+    }
 
-        Ok(())
+    /// Drop the lines before the cursor's line once they make up at least
+    /// half the buffer, keeping the cost of shifting the rest down constant
+    /// per byte
+    ///
+    /// Only called where no earlier position is kept, since it moves the
+    /// cursor.
+    fn release(&mut self) {
+        let Some(end) = self.buffer[..self.pos].rfind('\n').map(|i| i + 1) else {
+            return;
+        };
+        if end < self.buffer.len() / 2 {
+            return;
+        }
+        let released = &self.buffer[..end];
+        self.consumed += released.len();
+        self.consumed_lines += released.matches('\n').count();
+        self.buffer.drain(..end);
+        self.pos -= end;
     }
 
     fn ensure_buffer(&mut self, needed: usize) -> Result<(), Error> {
         if self.pos + needed > self.buffer.len() && !self.eof {
-            self.fill_buffer(self.pos + needed)?;
+            self.fill_buffer(needed)?;
         }
         Ok(())
     }
@@ -868,19 +4123,27 @@ impl<'a, R: Read> StreamingParser<'a, R> {
         }
 
         // Check if it's a root array (starts with [)
-        if self.peek_char() == Some('[') {
-            self.parse_array_value()
+        let value = if self.peek_char() == Some('[') {
+            self.parse_array_value()?
+        } else if let Some(line) = self.root_primitive()? {
+            parse_primitive_value(self.options, line.trim())?
         } else {
-            self.parse_object()
-        }
+            if self.options.get_include_keys().is_some() {
+                self.include = Some(String::new());
+            }
+            self.parse_object()?
+        };
+        Ok(value)
     }
 
     fn parse_object(&mut self) -> Result<Value, Error> {
         let mut map = Map::new();
+        let mut seen = HashSet::new();
         let indent = self.options.get_indent();
         let initial_indent = self.count_indent(indent)?;
 
         loop {
+            self.release();
             // Count indentation first (before skipping whitespace)
             let line_indent = self.count_indent(indent)?;
             if line_indent < initial_indent {
@@ -911,19 +4174,26 @@ impl<'a, R: Read> StreamingParser<'a, R> {
             }
 
             // Parse key (may include array notation like "tags[3]")
-            let key = self.parse_key()?;
+            let (key, quoted) = self.parse_entry_key()?;
+            if !seen.insert(entry_name(self.options, &key, quoted))
+                && is_duplicate_error(self.options)
+            {
+                return Err(self.error(format!("Duplicate key '{key}'")));
+            }
+            if initial_indent == 0 {
+                self.sections += 1;
+                self.section = Some(key.clone());
+                self.report();
+            }
             self.skip_whitespace();
 
             // Check if we have array notation in the key (e.g., "tags[3]:")
-            let has_array_notation = self.peek_char() == Some('[');
+            let has_array_notation = opens_array(self.peek_char(), self.options);
 
             if !has_array_notation {
                 // Normal key-value: key: value
                 if self.peek_char() != Some(':') {
-                    return Err(Error::parse(
-                        self.pos,
-                        format!("Expected ':' after key '{key}'"),
-                    ));
+                    return Err(self.error(format!("Expected ':' after key '{key}'")));
                 }
                 self.advance(); // consume ':'
                 self.skip_whitespace();
@@ -932,45 +4202,32 @@ impl<'a, R: Read> StreamingParser<'a, R> {
                 // The array part will be parsed as the value
             }
 
-            // Check if value is on next line (indented) or inline
-            let value = if has_array_notation {
-                // Array notation: key[3]: value
-                // Parse the array value
-                let value = self.parse_array_value()?;
-                // Skip to next line
-                if self.pos < self.buffer.len() && self.peek_char() == Some('\n') {
-                    self.advance();
-                }
-                value
-            } else if self.peek_char() == Some('\n') {
-                self.advance(); // consume '\n'
-                                // Check if next line is more indented (nested object/array)
-                let next_indent = self.count_indent(indent)?;
-                if next_indent > line_indent {
-                    // Parse nested object or array
-                    if self.peek_char() == Some('[') {
-                        self.parse_array_value()?
-                    } else {
-                        // Parse nested object
-                        self.parse_object()?
+            // Step over entries `include_keys` leaves out, and narrow it for
+            // the nested object of a partly selected one
+            let outer = self.include.take();
+            let inner = match &outer {
+                None => None,
+                Some(path) => match select_entry(self.options, path, &key, has_array_notation) {
+                    Selection::All => None,
+                    Selection::Part(path) => Some(path),
+                    Selection::Nothing => {
+                        self.include = outer;
+                        self.skip_entry(line_indent)?;
+                        continue;
                     }
-                } else {
-                    // Same or less indent means we're done with this value
-                    Value::Null
-                }
-            } else {
-                // Inline value - parse until end of line or newline
-                let value = self.parse_value_until_newline()?;
-                // Skip to next line (if not already at end)
-                if self.pos < self.buffer.len() && self.peek_char() != Some('\n') {
-                    self.skip_to_next_line();
-                } else if self.peek_char() == Some('\n') {
-                    self.advance(); // consume newline
-                }
-                value
+                },
             };
+            let partial = inner.is_some();
+            self.include = inner;
+            self.enter(|| key.clone());
+            let value = self.parse_entry_value(has_array_notation, line_indent)?;
+            self.leave();
+            self.include = outer;
+            if partial && !value.is_object() {
+                continue;
+            }
 
-            map.insert(key, value);
+            insert_key(self.options, &mut map, key, quoted, value);
 
             // After inserting a nested object, check if we should continue
             if self.pos >= self.buffer.len() && self.eof {
@@ -996,28 +4253,180 @@ impl<'a, R: Read> StreamingParser<'a, R> {
         Ok(Value::Object(map))
     }
 
+    /// The document's only line, when it is a primitive rather than an entry
+    ///
+    /// Only reads ahead as far as the next non-blank text after the line.
+    fn root_primitive(&mut self) -> Result<Option<String>, Error> {
+        let options = self.options;
+        let line = self.rest_of_line()?;
+        if !is_root_primitive(line, options) {
+            return Ok(None);
+        }
+        let line = line.to_string();
+        loop {
+            if !self.buffer[self.pos + line.len()..].trim().is_empty() {
+                return Ok(None);
+            }
+            if self.eof {
+                return Ok(Some(line));
+            }
+            let buffered = self.buffer.len() - self.pos;
+            self.fill_buffer(buffered + 8192)?;
+        }
+    }
+
+    /// Step over the rest of an entry without parsing it: the rest of its
+    /// line and every line indented past `line_indent` after it
+    fn skip_entry(&mut self, line_indent: usize) -> Result<(), Error> {
+        let limit = line_indent * self.options.get_indent();
+        let mut first = true;
+        loop {
+            self.release();
+            let line = self.rest_of_line()?;
+            let content = line.trim_start_matches(' ');
+            let sibling =
+                !content.trim_end_matches('\r').is_empty() && line.len() - content.len() <= limit;
+            if self.pos >= self.buffer.len() || !first && sibling {
+                return Ok(());
+            }
+            first = false;
+            let len = self.buffer_row()?;
+            self.pos += len;
+            self.skip_to_next_line();
+        }
+    }
+
+    /// Text from the cursor to the end of the current line, buffering as needed
+    fn rest_of_line(&mut self) -> Result<&str, Error> {
+        loop {
+            if let Some(i) = self.buffer[self.pos..].find('\n') {
+                return Ok(&self.buffer[self.pos..self.pos + i]);
+            }
+            if self.eof {
+                return Ok(&self.buffer[self.pos..]);
+            }
+            let buffered = self.buffer.len() - self.pos;
+            self.fill_buffer(buffered + 1)?;
+        }
+    }
+
+    /// Byte length of the delimited row at the cursor, buffering until a
+    /// quoted cell that spans lines is complete
+    fn buffer_row(&mut self) -> Result<usize, Error> {
+        loop {
+            if let Some(len) = row_len(&self.buffer[self.pos..], self.eof) {
+                return Ok(len);
+            }
+            let buffered = self.buffer.len() - self.pos;
+            self.fill_buffer(buffered + 1)?;
+        }
+    }
+
+    /// Parse the indented body of a `|` or `|-` block string
+    fn parse_block_string(
+        &mut self,
+        line_indent: usize,
+        keep_newline: bool,
+    ) -> Result<Value, Error> {
+        self.skip_to_next_line();
+        let prefix_len = (line_indent + 1) * self.options.get_indent();
+        let mut lines = Vec::new();
+        loop {
+            self.ensure_buffer(1)?;
+            if self.pos >= self.buffer.len() {
+                break;
+            }
+            let Some(content) = block_line(self.rest_of_line()?, prefix_len) else {
+                break;
+            };
+            lines.push(content.to_string());
+            self.skip_to_next_line();
+        }
+        let text = join_block_lines(&lines, keep_newline);
+        check_string_length(self.options, text.len()).map_err(|msg| self.error(msg))?;
+        Ok(Value::String(text))
+    }
+
+    /// Parse the value following a key, either inline or as an indented block
+    fn parse_entry_value(
+        &mut self,
+        has_array_notation: bool,
+        line_indent: usize,
+    ) -> Result<Value, Error> {
+        let indent = self.options.get_indent();
+        self.ensure_buffer(1)?;
+        // Check if value is on next line (indented) or inline
+        if has_array_notation {
+            // Array notation: key[3]: value
+            // Parse the array value
+            let value = self.parse_array_value()?;
+            // Skip to next line
+            if self.peek_char() == Some('\n') {
+                self.advance();
+            }
+            Ok(value)
+        } else if matches!(self.peek_char(), Some('\n') | None) {
+            self.advance(); // consume '\n'
+                            // Check if next line is more indented (nested object/array)
+            let next_indent = self.count_indent(indent)?;
+            if next_indent > line_indent {
+                // Parse nested object or array
+                if self.peek_char() == Some('[') {
+                    self.parse_array_value()
+                } else if self.options.get_lenient_arrays() && is_list_item(self.rest_of_line()?) {
+                    self.parse_list_array(None)
+                } else {
+                    // Parse nested object
+                    self.parse_object()
+                }
+            } else {
+                // Same or less indent means we're done with this value
+                Ok(Value::Null)
+            }
+        } else if is_empty_object(self.rest_of_line()?) {
+            self.skip_to_next_line();
+            Ok(Value::Object(Map::new()))
+        } else if let Some(keep_newline) = block_header(self.rest_of_line()?) {
+            self.parse_block_string(line_indent, keep_newline)
+        } else {
+            // Inline value - parse until end of line or newline
+            let value = if self.options.get_lenient_arrays()
+                && is_unbracketed_array(self.rest_of_line()?)
+            {
+                self.parse_inline_array(None, None)?
+            } else {
+                self.parse_value_until_newline()?
+            };
+            // Skip to next line (if not already at end)
+            if self.peek_char() != Some('\n') {
+                self.skip_to_next_line();
+            } else if self.peek_char() == Some('\n') {
+                self.advance(); // consume newline
+            }
+            Ok(value)
+        }
+    }
+
     fn parse_value(&mut self) -> Result<Value, Error> {
         self.skip_whitespace();
         match self.peek_char() {
             Some('[') => self.parse_array_value(),
             Some('"') => self.parse_string(),
-            Some('-') => {
-                // List item marker
+            // A list item marker, unlike the sign of `-1`
+            Some('-') if is_list_item(self.rest_of_line()?) => {
                 self.advance();
                 self.skip_whitespace();
                 self.parse_value()
             }
-            Some(ch) if ch.is_ascii_digit() || ch == '-' => self.parse_number(),
+            Some(ch) if (ch.is_ascii_digit() || ch == '-') && self.options.get_coerce_numbers() => {
+                self.parse_number()
+            }
             Some(ch) if ch.is_ascii_alphabetic() => {
                 // Try boolean/null first, then fall back to string
-                let start = self.pos;
-                let value = self.parse_boolean_or_null();
-                if value.is_ok() {
-                    return value;
+                match self.parse_boolean_or_null()? {
+                    Some(value) => Ok(value),
+                    None => self.parse_unquoted_string(),
                 }
-                // Reset and parse as string
-                self.pos = start;
-                self.parse_unquoted_string()
             }
             _ => self.parse_unquoted_string(),
         }
@@ -1038,8 +4447,9 @@ impl<'a, R: Read> StreamingParser<'a, R> {
             }
         }
         if self.pos == start {
-            return Err(Error::parse(self.pos, "Expected value"));
+            return Err(self.error("Expected value"));
         }
+        check_string_length(self.options, self.pos - start).map_err(|msg| self.error(msg))?;
         Ok(Value::String(self.buffer[start..self.pos].to_string()))
     }
 
@@ -1053,53 +4463,68 @@ impl<'a, R: Read> StreamingParser<'a, R> {
                 self.parse_array_value()
             }
             Some('"') => self.parse_string(),
-            Some(ch) if ch.is_ascii_digit() || ch == '-' => self.parse_number(),
+            Some(ch) if (ch.is_ascii_digit() || ch == '-') && self.options.get_coerce_numbers() => {
+                self.parse_number()
+            }
             Some(ch) if ch.is_ascii_alphabetic() => {
                 // Try boolean/null first, then fall back to string
-                let start_pos = self.pos;
-                let value = self.parse_boolean_or_null();
-                if value.is_ok() {
-                    return value;
+                match self.parse_boolean_or_null()? {
+                    Some(value) => Ok(value),
+                    None => self.parse_unquoted_string(),
                 }
-                // Reset and parse as string
-                self.pos = start_pos;
-                self.parse_unquoted_string()
             }
             _ => self.parse_unquoted_string(),
         }
     }
 
     fn parse_array_value(&mut self) -> Result<Value, Error> {
+        // A length-less table header: key{field1,field2}:
+        if self.peek_char() == Some('{') && self.options.lengthless_headers() {
+            return self.parse_tabular_array(None, None);
+        }
         if self.peek_char() != Some('[') {
-            return Err(Error::parse(self.pos, "Expected '['"));
+            return Err(self.error("Expected '['"));
         }
         self.advance(); // consume '['
 
         // Parse length marker (optional #) and length
         let has_length_marker = self.peek_char() == Some('#');
         if has_length_marker {
+            if let Some(message) = length_marker_error(self.options) {
+                return Err(self.error(message));
+            }
             self.advance(); // consume '#'
         }
 
         let length_str = self.parse_while(|ch| ch.is_ascii_digit())?;
-        let length: usize = length_str
-            .parse()
-            .map_err(|_| Error::parse(self.pos, "Invalid array length"))?;
+        let length = if length_str.is_empty() && self.options.lengthless_headers() {
+            None
+        } else {
+            let length: usize = length_str
+                .parse()
+                .map_err(|_| self.error("Invalid array length"))?;
+            check_array_length(self.options, length).map_err(|msg| self.error(msg))?;
+            Some(length)
+        };
+        let declared = self.peek_char().filter(|&ch| is_declared_delimiter(ch));
+        if declared.is_some() {
+            self.advance();
+        }
 
         if self.peek_char() != Some(']') {
-            return Err(Error::parse(self.pos, "Expected ']'"));
+            return Err(self.error("Expected ']'"));
         }
         self.advance(); // consume ']'
 
         // Check for tabular format: {field1,field2}:
         if self.peek_char() == Some('{') {
-            self.parse_tabular_array(length)
+            self.parse_tabular_array(length, declared)
         } else if self.peek_char() == Some(':') {
             self.advance(); // consume ':'
             self.skip_whitespace();
 
             // Check if it's inline (same line) or list format (next line)
-            if length == 0 {
+            if length == Some(0) {
                 // Empty array - skip any whitespace and newline
                 self.skip_whitespace();
                 if self.peek_char() == Some('\n') {
@@ -1110,50 +4535,61 @@ impl<'a, R: Read> StreamingParser<'a, R> {
             {
                 self.parse_list_array(length)
             } else {
-                self.parse_inline_array(length)
+                self.parse_inline_array(length, declared)
             }
         } else {
-            Err(Error::parse(
-                self.pos,
-                "Expected ':' or '{' after array length",
-            ))
+            Err(self.error("Expected ':' or '{' after array length"))
         }
     }
 
-    fn parse_tabular_array(&mut self, expected_length: usize) -> Result<Value, Error> {
+    fn parse_tabular_array(
+        &mut self,
+        expected_length: Option<usize>,
+        declared: Option<char>,
+    ) -> Result<Value, Error> {
         if self.peek_char() != Some('{') {
-            return Err(Error::parse(self.pos, "Expected '{'"));
+            return Err(self.error("Expected '{'"));
         }
         self.advance(); // consume '{'
 
         // Parse field names
-        let fields_str = self.parse_while(|ch| ch != '}')?;
-        let fields: Vec<&str> = fields_str.split(',').map(|s| s.trim()).collect();
-        let delimiter = self.detect_delimiter();
+        let fields_str = self.parse_while(until_unquoted('}'))?;
+        let header_pos = self.pos;
 
         if self.peek_char() != Some('}') {
-            return Err(Error::parse(self.pos, "Expected '}'"));
+            return Err(self.error("Expected '}'"));
         }
         self.advance(); // consume '}'
 
         if self.peek_char() != Some(':') {
-            return Err(Error::parse(self.pos, "Expected ':'"));
+            return Err(self.error("Expected ':'"));
         }
         self.advance(); // consume ':'
                         // Skip to next line (consume newline if present)
+        let mut first_row = None;
         if self.peek_char() == Some('\n') {
             self.advance();
+            first_row = Some(self.rest_of_line()?);
+        }
+        let delimiter = declared.unwrap_or_else(|| table_delimiter(&fields_str, first_row));
+        let (fields, columns) = header_fields(&fields_str, delimiter, self.options)?;
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+        if let Some(field) = duplicate_field(self.options, &fields) {
+            self.pos = header_pos;
+            return Err(self.error(format!("Duplicate key '{field}'")));
         }
 
         // Parse rows
         let mut items = Vec::new();
+        let mut read = 0;
+        let mut elided = None;
         let indent = self.options.get_indent();
         // Count base indentation of first row
         let base_indent = self.count_indent(indent)?;
 
-        for _ in 0..expected_length {
-            // Ensure we have enough buffer to read a line
-            self.ensure_buffer(100)?;
+        for _ in 0..expected_length.unwrap_or(usize::MAX) {
+            self.release();
+            self.ensure_buffer(1)?;
 
             if self.pos >= self.buffer.len() && self.eof {
                 break;
@@ -1174,85 +4610,80 @@ impl<'a, R: Read> StreamingParser<'a, R> {
                 }
             }
 
-            let mut obj = Map::new();
+            let len = self.buffer_row()?;
             let start = self.pos;
-            // Parse until newline - keep reading if we hit buffer end
-            loop {
-                self.ensure_buffer(1)?;
-                if self.pos >= self.buffer.len() && self.eof {
-                    break;
-                }
-                if self.pos < self.buffer.len() && self.peek_char() == Some('\n') {
-                    break;
-                }
-                if self.pos < self.buffer.len() {
+            self.pos += len;
+            if let Some(count) = elided_rows(&self.buffer[start..self.pos], self.options) {
+                // The rows left out of a preview end the table
+                elided = Some(count);
+                if self.peek_char() == Some('\n') {
                     self.advance();
-                } else {
-                    break;
                 }
+                break;
             }
+            self.count_cells(fields.len())?;
             let row = &self.buffer[start..self.pos];
             let values: Vec<&str> = self.split_row(row, delimiter);
-
-            if values.len() != fields.len() && self.options.get_strict() {
-                return Err(Error::LengthMismatch {
-                    expected: fields.len(),
-                    found: values.len(),
-                });
+            for value_str in &values {
+                check_string_length(self.options, value_str.len())
+                    .map_err(|msg| self.error(msg))?;
             }
-
-            for (i, field) in fields.iter().enumerate() {
-                let value_str = values.get(i).unwrap_or(&"");
-                let value = self.parse_primitive_value(value_str.trim())?;
-                obj.insert(field.to_string(), value);
+            check_row_delimiter(self.options, row, fields.len(), values.len(), delimiter)?;
+            if let Some(obj) = row_object(self.options, &fields, &columns, &values)? {
+                items.push(Value::Object(obj));
             }
-
-            items.push(Value::Object(obj));
+            read += 1;
+            self.row_done();
             // Skip to next line
-            if self.pos < self.buffer.len() && self.peek_char() == Some('\n') {
+            if self.peek_char() == Some('\n') {
                 self.advance();
             }
         }
+        self.table_done(read);
 
-        if self.options.get_strict() && items.len() != expected_length {
-            return Err(Error::LengthMismatch {
-                expected: expected_length,
-                found: items.len(),
-            });
+        if let Some(count) = elided {
+            let declared = expected_length.unwrap_or(read + count.unwrap_or_default());
+            self.table_cut(declared);
+        }
+        if let Some(expected) = expected_length {
+            match elided {
+                Some(count) => fit_elided(self.options, &mut items, expected, read, count)?,
+                None => fit_array(self.options, &mut items, expected, read)?,
+            }
         }
 
         Ok(Value::Array(items))
     }
 
-    fn parse_inline_array(&mut self, expected_length: usize) -> Result<Value, Error> {
-        let delimiter = self.detect_delimiter();
+    fn parse_inline_array(
+        &mut self,
+        expected_length: Option<usize>,
+        declared: Option<char>,
+    ) -> Result<Value, Error> {
+        let len = self.buffer_row()?;
         let start = self.pos;
-        // Parse until newline
-        while self.pos < self.buffer.len() && self.peek_char() != Some('\n') {
-            self.advance();
-        }
+        self.pos += len;
         let row = &self.buffer[start..self.pos];
-        let values: Vec<&str> = self.split_row(row, delimiter);
+        let delimiter = declared.unwrap_or_else(|| inline_delimiter(row, expected_length));
+        let values = inline_items(self.split_row(row, delimiter));
 
         let mut items = Vec::new();
         for value_str in values {
             let trimmed = value_str.trim();
-            if !trimmed.is_empty() {
-                items.push(self.parse_primitive_value(trimmed)?);
-            }
+            check_string_length(self.options, trimmed.len()).map_err(|msg| self.error(msg))?;
+            items.push(parse_primitive_value(self.options, trimmed)?);
         }
 
-        if self.options.get_strict() && items.len() != expected_length {
-            return Err(Error::LengthMismatch {
-                expected: expected_length,
-                found: items.len(),
-            });
+        self.count_cells(items.len())?;
+        if let Some(expected) = expected_length {
+            let found = items.len();
+            fit_array(self.options, &mut items, expected, found)?;
         }
 
         Ok(Value::Array(items))
     }
 
-    fn parse_list_array(&mut self, expected_length: usize) -> Result<Value, Error> {
+    fn parse_list_array(&mut self, expected_length: Option<usize>) -> Result<Value, Error> {
         // Skip to next line if we're not already there
         if self.peek_char() == Some('\n') {
             self.advance();
@@ -1262,7 +4693,8 @@ impl<'a, R: Read> StreamingParser<'a, R> {
         let base_indent = self.count_indent(indent)?;
         let mut items = Vec::new();
 
-        for _ in 0..expected_length {
+        for _ in 0..expected_length.unwrap_or(usize::MAX) {
+            self.release();
             if self.pos >= self.buffer.len() && self.eof {
                 break;
             }
@@ -1274,6 +4706,7 @@ impl<'a, R: Read> StreamingParser<'a, R> {
             }
 
             // Skip the indentation whitespace
+            let line_start = self.pos;
             for _ in 0..(line_indent * indent) {
                 if self.peek_char() == Some(' ') {
                     self.advance();
@@ -1282,6 +4715,12 @@ impl<'a, R: Read> StreamingParser<'a, R> {
                 }
             }
 
+            // Without a declared length, the list ends at the first line that isn't an item
+            if expected_length.is_none() && self.peek_char() != Some('-') {
+                self.pos = line_start;
+                break;
+            }
+
             // Check if there's a '-' marker (optional in some formats)
             let has_dash = self.peek_char() == Some('-');
             if has_dash {
@@ -1290,123 +4729,84 @@ impl<'a, R: Read> StreamingParser<'a, R> {
             }
 
             // Parse the value
-            let line_start = self.pos;
-            let line_end = self.buffer[line_start..]
-                .find('\n')
-                .map(|i| line_start + i)
-                .unwrap_or(self.buffer.len());
-            let line = &self.buffer[line_start..line_end].trim();
-
+            let index = items.len();
+            self.enter(|| format!("[{index}]"));
+            let options = self.options;
+            let line = self.rest_of_line()?;
+            let (line_len, is_empty, is_object) = (
+                line.len(),
+                is_empty_object(line),
+                is_object_item(line.trim(), options),
+            );
             let value = if self.peek_char() == Some('[') {
                 self.parse_array_value()?
-            } else if line.contains(':')
-                && !line.starts_with('"')
-                && line.matches(':').count() == 1
-                && !line.trim_start().starts_with('-')
-            {
-                // It's an object (single key:value on this line)
-                let key = self.parse_key()?;
-                self.skip_whitespace();
-                if self.peek_char() != Some(':') {
-                    return Err(Error::parse(
-                        self.pos,
-                        format!("Expected ':' after key '{key}'"),
-                    ));
-                }
-                self.advance(); // consume ':'
-                self.skip_whitespace();
-                let val = self.parse_value()?;
-                let mut obj = Map::new();
-                obj.insert(key, val);
-                Value::Object(obj)
+            } else if is_empty {
+                self.pos += line_len;
+                Value::Object(Map::new())
+            } else if is_object {
+                self.parse_list_object(line_indent)?
             } else {
                 // Primitive value
                 self.parse_value()?
             };
+            self.leave();
             items.push(value);
+            self.row_done();
             // Skip to next line
-            if self.pos < self.buffer.len() && self.peek_char() == Some('\n') {
+            if self.peek_char() == Some('\n') {
                 self.advance();
             }
         }
 
-        if self.options.get_strict() && items.len() != expected_length {
-            return Err(Error::LengthMismatch {
-                expected: expected_length,
-                found: items.len(),
-            });
+        if let Some(expected) = expected_length {
+            let found = items.len();
+            fit_array(self.options, &mut items, expected, found)?;
         }
 
         Ok(Value::Array(items))
     }
 
-    fn parse_primitive_value(&self, s: &str) -> Result<Value, Error> {
-        if s.is_empty() {
-            return Ok(Value::Null);
-        }
-
-        // Try boolean
-        if s == "true" {
-            return Ok(Value::Bool(true));
-        }
-        if s == "false" {
-            return Ok(Value::Bool(false));
-        }
-
-        // Try number
-        if let Ok(n) = s.parse::<i64>() {
-            return Ok(Value::Number(n.into()));
-        }
-        if let Ok(n) = s.parse::<f64>() {
-            return Ok(Value::Number(
-                serde_json::Number::from_f64(n)
-                    .ok_or_else(|| Error::InvalidNumber(s.to_string()))?,
-            ));
-        }
-
-        // Must be a string (possibly quoted)
-        if s.starts_with('"') && s.ends_with('"') {
-            self.parse_quoted_string(s)
-        } else {
-            Ok(Value::String(s.to_string()))
+    /// Parse an object list item: its first field follows the `- ` marker
+    /// and any further fields sit one level deeper than the marker
+    fn parse_list_object(&mut self, line_indent: usize) -> Result<Value, Error> {
+        let field_indent = line_indent + 1;
+        let (key, quoted) = self.parse_entry_key()?;
+        self.skip_whitespace();
+        let has_array_notation = opens_array(self.peek_char(), self.options);
+        if !has_array_notation {
+            if self.peek_char() != Some(':') {
+                return Err(self.error(format!("Expected ':' after key '{key}'")));
+            }
+            self.advance(); // consume ':'
+            self.skip_whitespace();
         }
-    }
-
-    fn parse_quoted_string(&self, s: &str) -> Result<Value, Error> {
-        let mut result = String::new();
-        let chars: Vec<char> = s.chars().collect();
-        let mut i = 1; // Skip opening quote
+        let mut map = Map::new();
+        self.enter(|| key.clone());
+        let value = self.parse_entry_value(has_array_notation, field_indent)?;
+        self.leave();
+        insert_key(self.options, &mut map, key, quoted, value);
 
-        while i < chars.len() - 1 {
-            // Skip closing quote
-            match chars[i] {
-                '\\' => {
-                    i += 1;
-                    if i >= chars.len() - 1 {
-                        return Err(Error::InvalidEscape("Unterminated escape".to_string()));
-                    }
-                    match chars[i] {
-                        '"' => result.push('"'),
-                        '\\' => result.push('\\'),
-                        'n' => result.push('\n'),
-                        'r' => result.push('\r'),
-                        't' => result.push('\t'),
-                        _ => {
-                            return Err(Error::InvalidEscape(format!("\\{}", chars[i])));
-                        }
+        let indent = self.options.get_indent();
+        if self.count_indent(indent)? == field_indent
+            && !is_list_item(self.rest_of_line()?)
+            && !(self.pos >= self.buffer.len() && self.eof)
+        {
+            if let Value::Object(fields) = self.parse_object()? {
+                for (key, value) in fields {
+                    if repeats(self.options, &map, &key, &value) {
+                        return Err(self.error(format!("Duplicate key '{key}'")));
                     }
+                    // Already expanded by `parse_object`
+                    insert_key(self.options, &mut map, key, true, value);
                 }
-                ch => result.push(ch),
             }
-            i += 1;
         }
-
-        Ok(Value::String(result))
+        Ok(Value::Object(map))
     }
 
     fn parse_string(&mut self) -> Result<Value, Error> {
         if self.peek_char() != Some('"') {
-            return Err(Error::parse(self.pos, "Expected '\"'"));
+            return Err(self.error("Expected '\"'"));
         }
         self.advance(); // consume opening quote
 
@@ -1418,15 +4818,17 @@ impl<'a, R: Read> StreamingParser<'a, R> {
             if self.pos >= self.buffer.len() {
                 break;
             }
-            let ch = self.buffer.chars().nth(self.pos).unwrap();
+            let ch = self.peek_char().unwrap();
             if escaped {
                 escaped = false;
             } else if ch == '\\' {
                 escaped = true;
             } else if ch == '"' {
+                check_string_length(self.options, self.pos - start)
+                    .map_err(|msg| self.error(msg))?;
                 let s = self.buffer[start..self.pos].to_string();
                 self.advance(); // consume closing quote
-                return self.parse_quoted_string(&format!("\"{s}\""));
+                return parse_quoted_string(&format!("\"{s}\""));
             }
             self.advance();
         }
@@ -1434,13 +4836,40 @@ impl<'a, R: Read> StreamingParser<'a, R> {
         Err(Error::UnterminatedString)
     }
 
+    fn parse_lenient_number(&mut self) -> Result<Option<Value>, Error> {
+        // Integer literals are short, so a small lookahead holds the whole token
+        self.ensure_buffer(64)?;
+        let remaining = &self.buffer[self.pos..];
+        let token = remaining
+            .split([' ', '\t', '\r', '\n'])
+            .next()
+            .unwrap_or("");
+        if token.len() == remaining.len() && !self.eof {
+            return Ok(None);
+        }
+        Ok(lenient_integer(token).inspect(|_| self.pos += token.len()))
+    }
+
     fn parse_number(&mut self) -> Result<Value, Error> {
+        if self.options.get_lenient_numbers() {
+            if let Some(value) = self.parse_lenient_number()? {
+                return Ok(value);
+            }
+        }
+
         let start = self.pos;
         let mut has_dot = false;
 
         if self.peek_char() == Some('-') {
             self.advance();
         }
+        self.ensure_buffer(3)?;
+        if self.options.get_allow_nan() && self.buffer[self.pos..].starts_with("inf") {
+            self.pos += 3;
+            if self.at_value_end() {
+                return Ok(non_finite_value(&self.buffer[start..self.pos]));
+            }
+        }
 
         while self.pos < self.buffer.len() || !self.eof {
             self.ensure_buffer(1)?;
@@ -1459,8 +4888,28 @@ impl<'a, R: Read> StreamingParser<'a, R> {
             }
         }
 
+        // Optional exponent, only consumed when followed by digits
+        let mut has_exponent = false;
+        self.ensure_buffer(3)?;
+        if matches!(self.peek_char(), Some('e') | Some('E')) {
+            let rest = &self.buffer[self.pos + 1..];
+            let digits = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+            if digits.starts_with(|ch: char| ch.is_ascii_digit()) {
+                has_exponent = true;
+                self.pos += rest.len() - digits.len() + 1;
+                self.parse_while(|ch| ch.is_ascii_digit())?;
+            }
+        }
+
+        // Text running on from the number, as in `2024-01-01` or `12abc`,
+        // makes the whole token a string, as it would be in a table cell
+        if !self.at_value_end() {
+            self.pos = start;
+            return self.parse_unquoted_string();
+        }
+
         let s = &self.buffer[start..self.pos];
-        if has_dot {
+        if has_dot || has_exponent {
             let n = s
                 .parse::<f64>()
                 .map_err(|_| Error::InvalidNumber(s.to_string()))?;
@@ -1468,65 +4917,69 @@ impl<'a, R: Read> StreamingParser<'a, R> {
                 .ok_or_else(|| Error::InvalidNumber(s.to_string()))
                 .map(Value::Number)
         } else {
-            s.parse::<i64>()
-                .map(|n| Value::Number(n.into()))
-                .map_err(|_| Error::InvalidNumber(s.to_string()))
+            parse_integer(s).ok_or_else(|| Error::InvalidNumber(s.to_string()))
         }
     }
 
-    fn parse_boolean_or_null(&mut self) -> Result<Value, Error> {
+    /// Whether the cursor is at the end of an unquoted value
+    fn at_value_end(&mut self) -> bool {
+        matches!(self.peek_char(), None | Some(' ' | '\t' | '\r' | '\n'))
+    }
+
+    fn parse_boolean_or_null(&mut self) -> Result<Option<Value>, Error> {
         let start = self.pos;
         self.parse_while(|ch| ch.is_ascii_alphabetic())?;
         let s = &self.buffer[start..self.pos];
 
-        match s {
-            "true" => Ok(Value::Bool(true)),
-            "false" => Ok(Value::Bool(false)),
-            "null" => Ok(Value::Null),
+        Ok(match s {
+            "true" if self.options.get_coerce_booleans() => Some(Value::Bool(true)),
+            "false" if self.options.get_coerce_booleans() => Some(Value::Bool(false)),
+            "null" => Some(Value::Null),
+            "nan" | "inf" if self.options.get_allow_nan() && self.options.get_coerce_numbers() => {
+                Some(non_finite_marker(s))
+            }
             _ => {
                 // Not a boolean/null, reset position
                 self.pos = start;
-                Err(Error::parse(
-                    self.pos,
-                    format!("Not a boolean or null: {s}"),
-                ))
+                None
             }
-        }
+        })
     }
 
     fn parse_key(&mut self) -> Result<String, Error> {
+        self.parse_entry_key().map(|(key, _)| key)
+    }
+
+    /// Parse a key along with whether it was quoted, which makes a dotted
+    /// key literal rather than a path
+    fn parse_entry_key(&mut self) -> Result<(String, bool), Error> {
         self.skip_whitespace();
+        self.ensure_buffer(1)?;
+        if self.peek_char() == Some('"') {
+            let len = quoted_len(self.rest_of_line()?)
+                .ok_or_else(|| self.error("Unterminated quoted key"))?;
+            let key = unescape(&self.buffer[self.pos..self.pos + len])?;
+            self.pos += len;
+            return Ok((key, true));
+        }
         let start = self.pos;
-        // Parse key - stop at ':', '[', space, newline, or tab
+        // Parse key - stop at ':', '[', space, newline, or tab (or '{' for
+        // length-less table headers)
         while self.pos < self.buffer.len() || !self.eof {
             self.ensure_buffer(1)?;
             if self.pos >= self.buffer.len() {
                 break;
             }
             match self.peek_char() {
-                Some(ch) if ch == ':' || ch == '[' || ch == ' ' || ch == '\n' || ch == '\t' => {
-                    break
-                }
+                Some(ch) if ends_key(ch, self.options) => break,
                 Some(_) => self.advance(),
                 None => break,
             }
         }
         if self.pos == start {
-            return Err(Error::parse(self.pos, "Expected key"));
-        }
-        Ok(self.buffer[start..self.pos].to_string())
-    }
-
-    fn detect_delimiter(&self) -> char {
-        // Look ahead to detect delimiter
-        let remaining = &self.buffer[self.pos..];
-
-        // Use SIMD for larger inputs, fallback for small ones
-        if remaining.len() >= 32 {
-            simd::detect_delimiter_simd(remaining)
-        } else {
-            simd::detect_delimiter_fallback(remaining)
+            return Err(self.error("Expected key"));
         }
+        Ok((self.buffer[start..self.pos].to_string(), false))
     }
 
     fn split_row<'b>(&self, row: &'b str, delimiter: char) -> Vec<&'b str> {
@@ -1547,8 +5000,8 @@ impl<'a, R: Read> StreamingParser<'a, R> {
             if self.pos + indent_size > self.buffer.len() {
                 break;
             }
-            let slice = &self.buffer[self.pos..self.pos + indent_size];
-            if slice == indent_str {
+            let slice = &self.buffer.as_bytes()[self.pos..self.pos + indent_size];
+            if slice == indent_str.as_bytes() {
                 count += 1;
                 self.pos += indent_size;
             } else {
@@ -1566,8 +5019,8 @@ impl<'a, R: Read> StreamingParser<'a, R> {
             if self.pos >= self.buffer.len() {
                 break;
             }
-            match self.buffer.chars().nth(self.pos) {
-                Some(' ') | Some('\t') => self.pos += 1,
+            match self.buffer.as_bytes()[self.pos] {
+                b' ' | b'\t' => self.pos += 1,
                 _ => break,
             }
         }
@@ -1579,11 +5032,13 @@ impl<'a, R: Read> StreamingParser<'a, R> {
             if self.pos >= self.buffer.len() {
                 break;
             }
-            if self.buffer.chars().nth(self.pos) == Some('\n') {
-                self.pos += 1;
-                break;
+            match self.buffer[self.pos..].find('\n') {
+                Some(i) => {
+                    self.pos += i + 1;
+                    break;
+                }
+                None => self.pos = self.buffer.len(),
             }
-            self.pos += 1;
         }
     }
 
@@ -1597,9 +5052,9 @@ impl<'a, R: Read> StreamingParser<'a, R> {
             if self.pos >= self.buffer.len() {
                 break;
             }
-            if let Some(ch) = self.buffer.chars().nth(self.pos) {
+            if let Some(ch) = self.peek_char() {
                 if pred(ch) {
-                    self.pos += 1;
+                    self.pos += ch.len_utf8();
                 } else {
                     break;
                 }
@@ -1610,17 +5065,38 @@ impl<'a, R: Read> StreamingParser<'a, R> {
         Ok(self.buffer[start..self.pos].to_string())
     }
 
-    fn peek_char(&self) -> Option<char> {
-        if self.pos < self.buffer.len() {
-            self.buffer.chars().nth(self.pos)
-        } else {
-            None
+    fn count_cells(&mut self, count: usize) -> Result<(), Error> {
+        self.cells = self.cells.saturating_add(count);
+        check_cells(self.options, self.cells).map_err(|msg| self.error(msg))
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        // Released input always ends at a line break, so only lines shift
+        let (line, column) = line_column(&self.buffer, self.pos);
+        Error::parse(
+            self.consumed + self.pos,
+            self.consumed_lines + line,
+            column,
+            message,
+        )
+    }
+
+    /// Character at the byte cursor, which always sits on a char boundary,
+    /// reading the next line if the cursor is at the end of the buffer
+    ///
+    /// A failed read ends the input here and is kept in `failed`, to be
+    /// reported in place of whatever the parser makes of the early end.
+    fn peek_char(&mut self) -> Option<char> {
+        if let Err(err) = self.ensure_buffer(1) {
+            self.failed.get_or_insert(err);
+            self.eof = true;
         }
+        self.buffer[self.pos..].chars().next()
     }
 
     fn advance(&mut self) {
-        if self.pos < self.buffer.len() {
-            self.pos += 1;
+        if let Some(ch) = self.peek_char() {
+            self.pos += ch.len_utf8();
         }
     }
 }
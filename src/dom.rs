@@ -0,0 +1,1686 @@
+//! Lossless syntax tree of a TOON document
+//!
+//! [`parse`] reads a document into a [`Document`] that keeps the source text
+//! and, for every key, array header, value and table cell, the byte range it
+//! was read from. Indentation is read as [`crate::decode`] reads it, with
+//! the options' indent string and, when not strict, loose indentation
+//! re-levelled. [`Document::value`] gives the value `decode` would, with the
+//! same options shaping it, but the tree is for tools that need to point
+//! back into the text, such as editors and linters.
+//!
+//! The tree describes the text as written: declared lengths are kept even
+//! where an array holds a different number of items, and repeated keys are
+//! all kept, so problems a decoder would reject are left for the caller to
+//! find. Blank lines and indentation have no nodes of their own, but sit
+//! between the spans in the source that [`Document`] prints back unchanged.
+//!
+//! [`Document::set`] and [`Document::push_row`] edit the document in
+//! place. Each edit rewrites only the text of what it changes, so the rest
+//! of the document keeps its layout, quoting and delimiters.
+
+use crate::decode::{
+    block_header, block_line, build_row, check_array_length, check_cells, check_string_length,
+    column_cell, ends_key, expand_indent, field_name_len, header_fields, indent_unit,
+    inline_delimiter, insert_key, is_declared_delimiter, is_empty_object, is_list_item,
+    is_object_item, is_root_primitive, is_unbracketed_array, join_block_lines, line_column,
+    opens_array, parse_primitive_value, quoted_len, row_len, table_delimiter, unescape,
+    until_unquoted, Column, IndentNormalizer,
+};
+use crate::encode::{encode_primitive_value, encode_scalar, Slot};
+use crate::error::Error;
+use crate::options::{ColumnType, DecodeOptions, Delimiter, EncodeOptions};
+use crate::simd;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::ops::Range;
+
+/// Parse a TOON document into a syntax tree
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::dom::{self, Items, Node};
+///
+/// let toon = "name: shop\nitems[2]{sku,qty}:\n  A1,2\n  B2,1";
+/// let doc = dom::parse(toon, None)?;
+/// let Node::Object(root) = doc.root() else { unreachable!() };
+/// let Node::Array(items) = &root.entries[1].value else { unreachable!() };
+/// let Items::Table(rows) = &items.items else { unreachable!() };
+/// let qty = &rows[1].cells[1];
+/// assert_eq!(&toon[qty.span.clone()], "1");
+/// assert_eq!(doc.to_string(), toon);
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn parse(input: &str, options: Option<&DecodeOptions>) -> Result<Document, Error> {
+    let default_opts = DecodeOptions::default();
+    let options = options.unwrap_or(&default_opts);
+    // The tree is read from the text the decoder would parse, and its spans
+    // are then carried back to the source
+    let prepared = Prepared::new(input, options);
+    let text = prepared.as_ref().map_or(input, |prepared| &prepared.text);
+    let mut parser = TreeParser {
+        source: text,
+        options,
+        lines: line_spans(text),
+        line: 0,
+        cells: 0,
+    };
+    let mut root = match (parser.document(), &prepared) {
+        (Ok(root), _) => root,
+        (Err(err), None) => return Err(err),
+        (Err(err), Some(prepared)) => return Err(prepared.error(input, err)),
+    };
+    if let Some(prepared) = &prepared {
+        each_span(&mut root, &mut |span| {
+            span.start = prepared.position(span.start);
+            span.end = prepared.position(span.end);
+        });
+    }
+    Ok(Document {
+        source: input.to_string(),
+        root,
+        options: options.clone(),
+    })
+}
+
+/// The text the decoder parses in place of a source whose indentation it
+/// rewrites, with where each of its lines sits in the source
+///
+/// Preparing only changes the indentation of lines, drops blank ones and
+/// trims trailing whitespace, so the rest of a line is the same text in
+/// both and positions in it carry over.
+struct Prepared {
+    text: String,
+    /// Start of each line of `text`, start of its content, and start of
+    /// that content in the source
+    lines: Vec<(usize, usize, usize)>,
+}
+
+impl Prepared {
+    /// `None` when the decoder reads the source as it is
+    fn new(source: &str, options: &DecodeOptions) -> Option<Self> {
+        let unit = indent_unit(options);
+        if unit.is_none() && options.get_strict() {
+            return None;
+        }
+        let mut normalizer =
+            (!options.get_strict()).then(|| IndentNormalizer::new(options.get_indent()));
+        let mut text = String::with_capacity(source.len());
+        let mut lines = Vec::new();
+        let mut expanded = String::new();
+        let mut start = 0;
+        for line in source.split_inclusive('\n') {
+            let line_start = start;
+            start += line.len();
+            let read = match unit {
+                Some(unit) => {
+                    expanded.clear();
+                    expand_indent(line, unit, options.get_indent(), &mut expanded);
+                    expanded.as_str()
+                }
+                None => line,
+            };
+            let before = text.len();
+            match &mut normalizer {
+                Some(normalizer) => normalizer.push_line(read, &mut text),
+                None => text.push_str(read),
+            }
+            if text.len() == before {
+                continue;
+            }
+            let written = &text[before..];
+            let written = written.strip_suffix('\n').unwrap_or(written);
+            let content = written.trim_start_matches([' ', '\t']);
+            // The source line ends with the same content, unless its line
+            // break or trailing whitespace were dropped
+            let mut kept = line.strip_suffix('\n').unwrap_or(line);
+            if !content.ends_with('\r') {
+                kept = kept.strip_suffix('\r').unwrap_or(kept);
+            }
+            if !kept.ends_with(content) {
+                kept = kept.trim_end_matches(' ');
+            }
+            lines.push((
+                before,
+                before + written.len() - content.len(),
+                line_start + kept.len() - content.len(),
+            ));
+        }
+        Some(Prepared { text, lines })
+    }
+
+    /// The source position of a position in the prepared text; one in a
+    /// line's indentation goes to the start of its content
+    fn position(&self, pos: usize) -> usize {
+        let i = self.lines.partition_point(|&(start, ..)| start <= pos);
+        match i.checked_sub(1).map(|i| self.lines[i]) {
+            Some((_, content, source)) => source + pos.saturating_sub(content),
+            None => pos,
+        }
+    }
+
+    /// A parse error moved to where it is in the source
+    fn error(&self, source: &str, err: Error) -> Error {
+        match err {
+            Error::Parse {
+                position, message, ..
+            } => {
+                let position = self.position(position).min(source.len());
+                let (line, column) = line_column(source, position);
+                Error::parse(position, line, column, message)
+            }
+            err => err,
+        }
+    }
+}
+
+/// A parsed document along with the text it was read from
+///
+/// It prints back as that text, unchanged apart from any edits.
+#[derive(Debug, Clone)]
+pub struct Document {
+    source: String,
+    root: Node,
+    /// Options to parse the text again with after an edit
+    options: DecodeOptions,
+}
+
+impl Document {
+    /// The text the document was parsed from
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The document's root: an object of entries, a root array, or a single
+    /// primitive
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// The options the document was parsed with
+    pub(crate) fn options(&self) -> &DecodeOptions {
+        &self.options
+    }
+
+    /// The value the document decodes to
+    ///
+    /// The options it was parsed with shape the value as they do in
+    /// [`crate::decode`]: dotted keys are expanded under
+    /// [`DecodeOptions::expand_paths`], repeated keys follow
+    /// [`DecodeOptions::duplicate_keys`], and table rows follow the length
+    /// policy, [`DecodeOptions::extra_cells_key`],
+    /// [`DecodeOptions::empty_cells`], [`DecodeOptions::include_columns`]
+    /// and [`DecodeOptions::row_filter`]. [`DecodeOptions::include_keys`]
+    /// doesn't apply, as the whole document is read. Where the decoder would
+    /// fail instead, the value keeps what is written: a repeated key under
+    /// [`DuplicateKeyPolicy::Error`](crate::options::DuplicateKeyPolicy::Error)
+    /// keeps its first value, and a row of the wrong length under
+    /// [`LengthPolicy::Error`](crate::options::LengthPolicy::Error) its
+    /// cells that have a field.
+    pub fn value(&self) -> Value {
+        self.node_value(&self.root)
+    }
+
+    /// The value of a node of this document, shaped by its options
+    pub(crate) fn node_value(&self, node: &Node) -> Value {
+        match node {
+            Node::Object(object) => {
+                let mut map = Map::new();
+                for entry in &object.entries {
+                    let quoted = self.source[entry.key.span.clone()].starts_with('"');
+                    let value = self.node_value(&entry.value);
+                    insert_key(
+                        &self.options,
+                        &mut map,
+                        entry.key.name.clone(),
+                        quoted,
+                        value,
+                    );
+                }
+                Value::Object(map)
+            }
+            Node::Array(Array {
+                header,
+                items: Items::Table(rows),
+                ..
+            }) => Value::Array(
+                rows.iter()
+                    .filter_map(|row| self.row_value(header, row))
+                    .collect(),
+            ),
+            Node::Array(Array {
+                items: Items::List(items),
+                ..
+            }) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| self.node_value(&item.value))
+                    .collect(),
+            ),
+            node => node.value(),
+        }
+    }
+
+    /// The object of a table row, or `None` when the row filter leaves it
+    /// out
+    pub(crate) fn row_value(&self, header: &Header, row: &Row) -> Option<Value> {
+        let fields = header.fields.as_deref().unwrap_or_default();
+        let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+        let columns: Vec<Column> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| Column {
+                ty: header.types.get(i).copied().flatten(),
+                default: header.defaults.get(i).cloned().flatten(),
+                dictionary: header.dictionaries.get(i).cloned().flatten(),
+                quoted: self.source[field.span.clone()].starts_with('"'),
+            })
+            .collect();
+        let cells: Vec<&str> = row
+            .cells
+            .iter()
+            .map(|cell| &self.source[cell.span.clone()])
+            .collect();
+        match build_row(&self.options, &names, &columns, &cells, |i| {
+            Ok(row.cells[i].value.clone())
+        }) {
+            Ok(object) => object.map(Value::Object),
+            Err(_) => Some(Node::row(fields, row)),
+        }
+    }
+
+    /// Replace the primitive at `path` with `value`
+    ///
+    /// `path` holds object keys and array indices, such as
+    /// `["items", "1", "qty"]` for a table cell. The new value is written in
+    /// place of the old one's text, quoted as its position needs, and
+    /// nothing else in the document changes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde_json::json;
+    /// use toon_rust::dom;
+    ///
+    /// let mut doc = dom::parse("items[2]{sku|qty}:\n  A1|  2\n  B2|  1", None)?;
+    /// doc.set(&["items", "1", "qty"], &json!(5))?;
+    /// assert_eq!(doc.to_string(), "items[2]{sku|qty}:\n  A1|  2\n  B2|  5");
+    /// # Ok::<(), toon_rust::Error>(())
+    /// ```
+    pub fn set(&mut self, path: &[&str], value: &Value) -> Result<(), Error> {
+        if value.is_object() || value.is_array() {
+            return Err(Error::Serialization(
+                "Only primitive values can be set in place".to_string(),
+            ));
+        }
+        let (span, text) = match self.find(path)? {
+            Target::Cell(cell, delimiter) => {
+                let mut text = String::new();
+                encode_scalar(value, &mut text, &encode_options(delimiter), Slot::Cell)?;
+                (cell.span.clone(), text)
+            }
+            Target::Item(item, delimiter) => {
+                let mut text = String::new();
+                let options = encode_options(delimiter);
+                encode_primitive_value(value, &mut text, &options, None, Slot::Cell)?;
+                (item.span.clone(), text)
+            }
+            Target::Node(Node::Scalar(scalar) | Node::Block(scalar), _) => {
+                let mut text = String::new();
+                let slot = if matches!(self.root, Node::Scalar(_)) {
+                    Slot::Root
+                } else {
+                    Slot::Value
+                };
+                encode_primitive_value(value, &mut text, &EncodeOptions::default(), None, slot)?;
+                // A key with nothing after its colon gets a space before the value
+                if scalar.span.is_empty() && self.source[..scalar.span.start].ends_with(':') {
+                    text.insert(0, ' ');
+                }
+                (scalar.span.clone(), text)
+            }
+            Target::Node(..) => {
+                return Err(Error::Serialization(format!(
+                    "The value at `{}` is not a primitive",
+                    path.join("/")
+                )))
+            }
+        };
+        self.splice(span, &text)
+    }
+
+    /// Add a row to the end of the table at `path`
+    ///
+    /// `row` is an object keyed by the table's fields; a missing field is an
+    /// empty cell. The row is written with the table's delimiter and
+    /// indentation, and a declared length in the header is updated to count
+    /// it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde_json::json;
+    /// use toon_rust::dom;
+    ///
+    /// let mut doc = dom::parse("items[#1]{sku,qty}:\n  A1,2\nnote: ok", None)?;
+    /// doc.push_row(&["items"], &json!({"sku": "B2", "qty": 1}))?;
+    /// assert_eq!(doc.to_string(), "items[#2]{sku,qty}:\n  A1,2\n  B2,1\nnote: ok");
+    /// # Ok::<(), toon_rust::Error>(())
+    /// ```
+    pub fn push_row(&mut self, path: &[&str], row: &Value) -> Result<(), Error> {
+        let Target::Node(Node::Array(array), depth) = self.find(path)? else {
+            return Err(Error::Serialization(format!(
+                "The value at `{}` is not a table",
+                path.join("/")
+            )));
+        };
+        let (Some(fields), Items::Table(rows)) = (&array.header.fields, &array.items) else {
+            return Err(Error::Serialization(format!(
+                "The value at `{}` is not a table",
+                path.join("/")
+            )));
+        };
+        let Value::Object(record) = row else {
+            return Err(Error::Serialization(
+                "Expected a JSON object row".to_string(),
+            ));
+        };
+        if let Some(key) = record
+            .keys()
+            .find(|key| !fields.iter().any(|field| &field.name == *key))
+        {
+            return Err(Error::Serialization(format!(
+                "Field `{key}` is not a column of the table"
+            )));
+        }
+
+        let delimiter = array.header.delimiter;
+        let options = encode_options(delimiter);
+        // The new row is indented as the last one, or one level past the
+        // header in the document's indent string
+        let indent = match rows.last() {
+            Some(row) => {
+                let line_start = self.source[..row.span.start]
+                    .rfind('\n')
+                    .map_or(0, |i| i + 1);
+                self.source[line_start..row.span.start].to_string()
+            }
+            None => match self.options.get_indent_string() {
+                Some(unit) => unit.repeat(depth),
+                None => " ".repeat(depth * self.options.get_indent()),
+            },
+        };
+        let mut line = format!("\n{indent}");
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                line.push(delimiter);
+            }
+            let cell = record.get(&field.name).unwrap_or(&Value::Null);
+            encode_scalar(cell, &mut line, &options, Slot::Cell)?;
+        }
+        let end = rows
+            .last()
+            .map_or(array.header.span.end, |row| row.span.end);
+
+        // The length goes in first, as it comes before the row in the text
+        let length = array.header.length.map(|_| {
+            let header = &self.source[array.header.span.clone()];
+            let start = array.header.span.start + header.find(|ch: char| ch.is_ascii_digit())?;
+            let digits = self.source[start..].len()
+                - self.source[start..]
+                    .trim_start_matches(|ch: char| ch.is_ascii_digit())
+                    .len();
+            Some((start..start + digits, (rows.len() + 1).to_string()))
+        });
+        let mut source = self.source.clone();
+        source.insert_str(end, &line);
+        if let Some(Some((digits, length))) = length {
+            source.replace_range(digits, &length);
+        }
+        *self = parse(&source, Some(&self.options))?;
+        Ok(())
+    }
+
+    /// Replace the text in `range` with `text`, parsing again only the part
+    /// of the document the edit falls in
+    ///
+    /// The smallest entry, list item or table row whose lines hold the edit
+    /// is parsed again on its own and the spans after it are moved, so an
+    /// edit costs about the size of what it touches rather than of the
+    /// document. When the edit changes which lines that part spans, such as
+    /// by adding a sibling or changing indentation, the whole document is
+    /// parsed again instead. Returns the byte range, in the new text, that
+    /// was parsed again.
+    ///
+    /// On an error the document is left as it was.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde_json::json;
+    /// use toon_rust::dom;
+    ///
+    /// let mut doc = dom::parse("name: shop\nitems[2]{sku,qty}:\n  A1,2\n  B2,1", None)?;
+    /// let qty = doc.source().rfind('1').unwrap();
+    /// let reparsed = doc.edit(qty..qty + 1, "10")?;
+    /// assert_eq!(&doc.source()[reparsed], "B2,10");
+    /// assert_eq!(doc.value()["items"][1]["qty"], json!(10));
+    /// # Ok::<(), toon_rust::Error>(())
+    /// ```
+    pub fn edit(&mut self, range: Range<usize>, text: &str) -> Result<Range<usize>, Error> {
+        if range.start > range.end
+            || !self.source.is_char_boundary(range.start)
+            || !self.source.is_char_boundary(range.end)
+        {
+            return Err(Error::Serialization(format!(
+                "The edit range {range:?} is not within the document's text"
+            )));
+        }
+        let mut source = self.source.clone();
+        source.replace_range(range.clone(), text);
+        if let Some(span) = self.reparse(source.clone(), &range, text) {
+            return Ok(span);
+        }
+        *self = parse(&source, Some(&self.options))?;
+        Ok(0..self.source.len())
+    }
+
+    /// Parse again the section holding an edit, or `None` when the edit
+    /// needs the whole document parsed again
+    fn reparse(&mut self, source: String, edit: &Range<usize>, text: &str) -> Option<Range<usize>> {
+        // Sections are parsed from the source, which must be what the
+        // decoder reads
+        if indent_unit(&self.options).is_some() || !self.options.get_strict() {
+            return None;
+        }
+        let (path, depth) = self.section(edit)?;
+        let old = self.section_span(&path);
+        let old_lines = line_spans(&self.source);
+        let first = line_of(&old_lines, old.start);
+        let last = line_of(&old_lines, old.end);
+        let added_lines = text.matches('\n').count();
+        let removed_lines = self.source[edit.clone()].matches('\n').count();
+
+        let mut parser = TreeParser {
+            source: &source,
+            options: &self.options,
+            lines: line_spans(&source),
+            line: first,
+            cells: 0,
+        };
+        if parser.text(first).is_empty() {
+            return None;
+        }
+        let section = match path.last()? {
+            Step::Entry(_) => {
+                if parser.indent(first).ok()? != depth {
+                    return None;
+                }
+                let start = parser.lines[first].start + depth * self.options.get_indent();
+                Section::Entry(parser.entry(start, depth).ok()?)
+            }
+            Step::Item(_) => Section::Item(parser.item(first, depth).ok()?),
+            Step::Row(_) => {
+                let Some(Node::Array(array)) = self.parent(&path) else {
+                    return None;
+                };
+                let header = &array.header;
+                let fields = header.fields.as_deref().unwrap_or_default();
+                let columns: Vec<_> = (0..fields.len())
+                    .map(|i| Column {
+                        ty: header.types.get(i).copied().flatten(),
+                        default: header.defaults.get(i).cloned().flatten(),
+                        dictionary: header.dictionaries.get(i).cloned().flatten(),
+                        ..Column::default()
+                    })
+                    .collect();
+                Section::Row(
+                    parser
+                        .row(first, depth, header.delimiter, fields, &columns)
+                        .ok()?,
+                )
+            }
+        };
+        // The section must span the same lines, give or take those the edit
+        // added or removed, or the lines around it would read differently
+        let span = section.span();
+        if line_of(&parser.lines, span.end) + removed_lines != last + added_lines {
+            return None;
+        }
+
+        let shift = Shift {
+            from: edit.end,
+            removed: edit.len(),
+            added: text.len(),
+        };
+        shift.node(&mut self.root);
+        replace(&mut self.root, &path, section);
+        self.source = source;
+        Some(span)
+    }
+
+    /// The path to the smallest section whose lines hold `edit`, with the
+    /// depth it sits at
+    ///
+    /// The first field of a list item shares the item's line and the first
+    /// row of a table decides its delimiter, so neither is a section of its
+    /// own.
+    fn section(&self, edit: &Range<usize>) -> Option<(Vec<Step>, usize)> {
+        let holds = |span: &Range<usize>| {
+            let start = self.source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+            let end = self.source[span.end..]
+                .find('\n')
+                .map_or(self.source.len(), |i| span.end + i);
+            start <= edit.start && edit.end <= end
+        };
+        let mut path = Vec::new();
+        let mut found = None;
+        let mut node = &self.root;
+        let mut depth = usize::from(matches!(self.root, Node::Array(_)));
+        let mut in_item = false;
+        loop {
+            let (step, next) = match node {
+                Node::Object(object) => {
+                    let i = object.entries.iter().position(|entry| holds(&entry.span))?;
+                    (Step::Entry(i), &object.entries[i].value)
+                }
+                Node::Array(array) => match &array.items {
+                    Items::List(items) => {
+                        let i = items.iter().position(|item| holds(&item.span))?;
+                        (Step::Item(i), &items[i].value)
+                    }
+                    Items::Table(rows) => {
+                        if let Some(i) = rows.iter().skip(1).position(|row| holds(&row.span)) {
+                            path.push(Step::Row(i + 1));
+                            found = Some((path.clone(), depth));
+                        }
+                        break;
+                    }
+                    Items::Inline(_) => break,
+                },
+                Node::Scalar(_) | Node::Block(_) => break,
+            };
+            path.push(step);
+            if !(in_item && step == Step::Entry(0)) {
+                found = Some((path.clone(), depth));
+            }
+            in_item = matches!(step, Step::Item(_));
+            node = next;
+            depth += 1;
+        }
+        found
+    }
+
+    /// Byte range of the section at `path`
+    fn section_span(&self, path: &[Step]) -> Range<usize> {
+        let Some((Step::Row(i), _)) = path.split_last() else {
+            return self.node_at(path).map_or(0..0, |(_, span)| span);
+        };
+        match self.parent(path) {
+            Some(Node::Array(Array {
+                items: Items::Table(rows),
+                ..
+            })) => rows[*i].span.clone(),
+            _ => 0..0,
+        }
+    }
+
+    /// The node holding the last step of `path`
+    fn parent(&self, path: &[Step]) -> Option<&Node> {
+        let (_, parent) = path.split_last()?;
+        if parent.is_empty() {
+            return Some(&self.root);
+        }
+        self.node_at(parent).map(|(node, _)| node)
+    }
+
+    /// The node an entry or item path leads to, with the span of its last
+    /// step's entry or item
+    fn node_at(&self, path: &[Step]) -> Option<(&Node, Range<usize>)> {
+        let mut node = &self.root;
+        let mut span = node.span();
+        for step in path {
+            (node, span) = match (node, step) {
+                (Node::Object(object), Step::Entry(i)) => {
+                    let entry = object.entries.get(*i)?;
+                    (&entry.value, entry.span.clone())
+                }
+                (Node::Array(array), Step::Item(i)) => match &array.items {
+                    Items::List(items) => {
+                        let item = items.get(*i)?;
+                        (&item.value, item.span.clone())
+                    }
+                    _ => return None,
+                },
+                _ => return None,
+            };
+        }
+        Some((node, span))
+    }
+
+    /// What `path` leads to, with the depth its nested lines sit at
+    fn find(&self, path: &[&str]) -> Result<Target<'_>, Error> {
+        let missing = || Error::Serialization(format!("No value at `{}`", path.join("/")));
+        // Entries of the root object sit at the top level
+        let mut depth = usize::from(!matches!(self.root, Node::Object(_)));
+        let mut node = &self.root;
+        let mut segments = path.iter();
+        while let Some(segment) = segments.next() {
+            let index = || segment.parse::<usize>().map_err(|_| missing());
+            node = match node {
+                Node::Object(object) => {
+                    let entry = object
+                        .entries
+                        .iter()
+                        .rev()
+                        .find(|entry| entry.key.name == *segment);
+                    &entry.ok_or_else(missing)?.value
+                }
+                Node::Array(array) => match &array.items {
+                    Items::Inline(items) => {
+                        let item = items.get(index()?).ok_or_else(missing)?;
+                        return match segments.next() {
+                            None => Ok(Target::Item(item, array.header.delimiter)),
+                            Some(_) => Err(missing()),
+                        };
+                    }
+                    Items::Table(rows) => {
+                        let row = rows.get(index()?).ok_or_else(missing)?;
+                        let fields = array.header.fields.as_deref().unwrap_or_default();
+                        let (Some(field), None) = (segments.next(), segments.next()) else {
+                            return Err(missing());
+                        };
+                        let column = fields.iter().position(|key| key.name == *field);
+                        let cell = column.and_then(|i| row.cells.get(i)).ok_or_else(missing)?;
+                        return Ok(Target::Cell(cell, array.header.delimiter));
+                    }
+                    Items::List(items) => &items.get(index()?).ok_or_else(missing)?.value,
+                },
+                Node::Scalar(_) | Node::Block(_) => return Err(missing()),
+            };
+            depth += 1;
+        }
+        Ok(Target::Node(node, depth))
+    }
+
+    /// Byte range of the text at `path`, or of the deepest node on the way
+    /// to it that exists
+    ///
+    /// A table row is reached by its index alone, and its cells by their
+    /// column name after that.
+    #[cfg(feature = "jsonschema")]
+    pub(crate) fn span_at(&self, path: &[&str]) -> Range<usize> {
+        let mut node = &self.root;
+        let mut segments = path.iter();
+        while let Some(segment) = segments.next() {
+            let index = segment.parse::<usize>().ok();
+            node = match node {
+                Node::Object(object) => {
+                    let entry = object
+                        .entries
+                        .iter()
+                        .rev()
+                        .find(|entry| entry.key.name == *segment);
+                    match entry {
+                        Some(entry) => &entry.value,
+                        None => break,
+                    }
+                }
+                Node::Array(array) => match (&array.items, index) {
+                    (Items::Inline(items), Some(i)) if i < items.len() => {
+                        return items[i].span.clone();
+                    }
+                    (Items::Table(rows), Some(i)) if i < rows.len() => {
+                        let row = &rows[i];
+                        let fields = array.header.fields.as_deref().unwrap_or_default();
+                        let column = segments
+                            .next()
+                            .and_then(|field| fields.iter().position(|key| key.name == *field));
+                        return match column.and_then(|i| row.cells.get(i)) {
+                            Some(cell) => cell.span.clone(),
+                            None => row.span.clone(),
+                        };
+                    }
+                    (Items::List(items), Some(i)) if i < items.len() => &items[i].value,
+                    _ => break,
+                },
+                Node::Scalar(_) | Node::Block(_) => break,
+            };
+        }
+        node.span()
+    }
+
+    /// Replace the text in `span` and parse the part of the document it is
+    /// in again
+    fn splice(&mut self, span: Range<usize>, text: &str) -> Result<(), Error> {
+        self.edit(span, text).map(drop)
+    }
+}
+
+/// What a path in a [`Document`] leads to
+enum Target<'d> {
+    /// A node, with the depth of the lines nested under it
+    Node(&'d Node, usize),
+    /// A table cell, with the table's delimiter
+    Cell(&'d Scalar, char),
+    /// An inline array item, with the array's delimiter
+    Item(&'d Scalar, char),
+}
+
+/// A step on the way to a section of a [`Document`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+    /// The object entry at an index
+    Entry(usize),
+    /// The list item at an index
+    Item(usize),
+    /// The table row at an index
+    Row(usize),
+}
+
+/// A section parsed again after an edit
+enum Section {
+    Entry(Entry),
+    Item(Item),
+    Row(Row),
+}
+
+impl Section {
+    fn span(&self) -> Range<usize> {
+        match self {
+            Section::Entry(entry) => entry.span.clone(),
+            Section::Item(item) => item.span.clone(),
+            Section::Row(row) => row.span.clone(),
+        }
+    }
+}
+
+/// Put `section` in place at `path`, and stretch the spans of the nodes
+/// holding it to its new end
+fn replace(node: &mut Node, path: &[Step], section: Section) {
+    let Some((step, rest)) = path.split_first() else {
+        return;
+    };
+    match (node, step, section) {
+        (Node::Object(object), Step::Entry(i), section) => {
+            let entry = &mut object.entries[*i];
+            match (rest.is_empty(), section) {
+                (true, Section::Entry(new)) => *entry = new,
+                (_, section) => {
+                    replace(&mut entry.value, rest, section);
+                    entry.span.end = entry.value.span().end;
+                }
+            }
+            object.span = span_of(&object.entries).unwrap_or(object.span.clone());
+        }
+        (Node::Array(array), Step::Item(i), section) => {
+            let Items::List(items) = &mut array.items else {
+                return;
+            };
+            let item = &mut items[*i];
+            match (rest.is_empty(), section) {
+                (true, Section::Item(new)) => *item = new,
+                (_, section) => {
+                    replace(&mut item.value, rest, section);
+                    item.span.end = item.value.span().end.max(item.span.start + 1);
+                }
+            }
+            if let Some(last) = items.last() {
+                array.span.end = last.span.end;
+            }
+        }
+        (Node::Array(array), Step::Row(i), Section::Row(new)) => {
+            if let Items::Table(rows) = &mut array.items {
+                rows[*i] = new;
+                if let Some(last) = rows.last() {
+                    array.span.end = last.span.end;
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Moves the spans after an edit by the change in length it made
+struct Shift {
+    /// End of the edited range, in the old text
+    from: usize,
+    removed: usize,
+    added: usize,
+}
+
+impl Shift {
+    fn pos(&self, pos: &mut usize) {
+        if *pos >= self.from {
+            *pos = *pos - self.removed + self.added;
+        }
+    }
+
+    fn span(&self, span: &mut Range<usize>) {
+        self.pos(&mut span.start);
+        self.pos(&mut span.end);
+    }
+
+    fn node(&self, node: &mut Node) {
+        each_span(node, &mut |span| self.span(span));
+    }
+}
+
+/// Call `f` on every span in the tree under `node`
+fn each_span(node: &mut Node, f: &mut impl FnMut(&mut Range<usize>)) {
+    match node {
+        Node::Scalar(scalar) | Node::Block(scalar) => f(&mut scalar.span),
+        Node::Object(object) => {
+            f(&mut object.span);
+            for entry in &mut object.entries {
+                f(&mut entry.span);
+                f(&mut entry.key.span);
+                each_span(&mut entry.value, f);
+            }
+        }
+        Node::Array(array) => {
+            f(&mut array.span);
+            f(&mut array.header.span);
+            for field in array.header.fields.iter_mut().flatten() {
+                f(&mut field.span);
+            }
+            match &mut array.items {
+                Items::Inline(items) => items.iter_mut().for_each(|item| f(&mut item.span)),
+                Items::Table(rows) => {
+                    for row in rows {
+                        f(&mut row.span);
+                        row.cells.iter_mut().for_each(|cell| f(&mut cell.span));
+                    }
+                }
+                Items::List(items) => {
+                    for item in items {
+                        f(&mut item.span);
+                        each_span(&mut item.value, f);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Options to write a value with the given delimiter
+fn encode_options(delimiter: char) -> EncodeOptions {
+    EncodeOptions::new().delimiter(match delimiter {
+        '\t' => Delimiter::Tab,
+        '|' => Delimiter::Pipe,
+        _ => Delimiter::Comma,
+    })
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// A value in the tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// A primitive written on the line of its key or `- ` marker, or as the
+    /// whole document
+    Scalar(Scalar),
+    /// A `|` or `|-` block string, spanning from the marker to the end of
+    /// its last line
+    Block(Scalar),
+    /// An object, indented under its key or written as `{}`
+    Object(Object),
+    /// An array with its header
+    Array(Array),
+}
+
+impl Node {
+    /// Byte range of the node in the source
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            Node::Scalar(scalar) | Node::Block(scalar) => scalar.span.clone(),
+            Node::Object(object) => object.span.clone(),
+            Node::Array(array) => array.span.clone(),
+        }
+    }
+
+    /// The value the node holds as written
+    ///
+    /// Unlike [`Document::value`], no decoding options apply: keys are kept
+    /// as they are, the last of a repeated key wins, and each table row
+    /// pairs its cells with the header's fields.
+    pub fn value(&self) -> Value {
+        match self {
+            Node::Scalar(scalar) | Node::Block(scalar) => scalar.value.clone(),
+            Node::Object(object) => Value::Object(
+                object
+                    .entries
+                    .iter()
+                    .map(|entry| (entry.key.name.clone(), entry.value.value()))
+                    .collect(),
+            ),
+            Node::Array(array) => Value::Array(match &array.items {
+                Items::Inline(items) => items.iter().map(|item| item.value.clone()).collect(),
+                Items::Table(rows) => {
+                    let fields = array.header.fields.as_deref().unwrap_or_default();
+                    rows.iter().map(|row| Node::row(fields, row)).collect()
+                }
+                Items::List(items) => items.iter().map(|item| item.value.value()).collect(),
+            }),
+        }
+    }
+
+    /// A table row's cells paired with the header's fields
+    fn row(fields: &[Key], row: &Row) -> Value {
+        Value::Object(
+            fields
+                .iter()
+                .zip(&row.cells)
+                .map(|(field, cell)| (field.name.clone(), cell.value.clone()))
+                .collect::<Map<_, _>>(),
+        )
+    }
+}
+
+/// A primitive value and the text it was read from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scalar {
+    /// Byte range of the value, quotes included; empty for a key with
+    /// nothing after its colon
+    pub span: Range<usize>,
+    pub value: Value,
+}
+
+/// An object's entries
+#[derive(Debug, Clone, PartialEq)]
+pub struct Object {
+    /// Byte range from the first entry to the end of the last, or of `{}`
+    pub span: Range<usize>,
+    pub entries: Vec<Entry>,
+}
+
+/// A `key: value` entry, or a `key[N]...` array entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Byte range from the key to the end of the value's last line
+    pub span: Range<usize>,
+    pub key: Key,
+    pub value: Node,
+}
+
+/// A key or table field name
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    /// Byte range of the key, quotes included
+    pub span: Range<usize>,
+    /// The key with any quotes and escapes resolved
+    pub name: String,
+}
+
+/// An array: its header and whatever follows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Array {
+    /// Byte range from the header to the end of the last item
+    pub span: Range<usize>,
+    pub header: Header,
+    pub items: Items,
+}
+
+/// The `[N]{fields}:` part of an array, after any key
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    /// Byte range from the `[` (or `{` without a length) to the colon;
+    /// empty for an array written without a header, as
+    /// [`DecodeOptions::lenient_arrays`] allows
+    pub span: Range<usize>,
+    /// The declared length, if one is written
+    pub length: Option<usize>,
+    /// The fields of a table
+    pub fields: Option<Vec<Key>>,
+    /// The types declared for the fields of a table, `None` for those
+    /// without one
+    pub types: Vec<Option<ColumnType>>,
+    /// The defaults declared for the fields of a table, which their empty
+    /// cells take
+    pub defaults: Vec<Option<Value>>,
+    /// The dictionaries declared for the fields of a table, whose entries
+    /// their cells refer to by index
+    pub dictionaries: Vec<Option<Vec<String>>>,
+    /// The delimiter between fields, cells and inline items
+    pub delimiter: char,
+}
+
+impl Header {
+    /// The header of an array written without one, at `pos`
+    fn none(pos: usize, delimiter: char) -> Self {
+        Header {
+            span: pos..pos,
+            length: None,
+            fields: None,
+            types: Vec::new(),
+            defaults: Vec::new(),
+            dictionaries: Vec::new(),
+            delimiter,
+        }
+    }
+}
+
+/// The items of an array, in the form they are written
+#[derive(Debug, Clone, PartialEq)]
+pub enum Items {
+    /// Delimited values on the header's line
+    Inline(Vec<Scalar>),
+    /// Rows of delimited cells, one per line
+    Table(Vec<Row>),
+    /// `- item` lines
+    List(Vec<Item>),
+}
+
+/// A table row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    /// Byte range of the row, without its indentation
+    pub span: Range<usize>,
+    pub cells: Vec<Scalar>,
+}
+
+/// A `- item` in a list array
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    /// Byte range from the `-` marker to the end of the item's last line
+    pub span: Range<usize>,
+    pub value: Node,
+}
+
+/// Builds the tree a line at a time
+///
+/// `line` is the index of the next line to read. A node whose key or marker
+/// sits at depth `d` has its nested lines at depth `d + 1`.
+struct TreeParser<'a> {
+    source: &'a str,
+    options: &'a DecodeOptions,
+    /// Byte range of each line, without its line break
+    lines: Vec<Range<usize>>,
+    line: usize,
+    /// Table cells and inline items read so far, held to
+    /// [`DecodeOptions::max_cells`]
+    cells: usize,
+}
+
+impl TreeParser<'_> {
+    fn document(&mut self) -> Result<Node, Error> {
+        let Some(first) = self.peek() else {
+            return Ok(Node::Object(Object {
+                span: 0..self.source.len(),
+                entries: Vec::new(),
+            }));
+        };
+        let start = self.lines[first].start;
+        if self.indent(first)? > 0 {
+            return Err(self.error(start, "Unexpected indentation"));
+        }
+        let text = self.text(first);
+        let root = if text.starts_with('[') {
+            self.line = first;
+            Node::Array(self.array(start, 0)?)
+        } else if is_root_primitive(text, self.options) && self.peek_from(first + 1).is_none() {
+            self.line = first;
+            Node::Scalar(self.scalar(start)?)
+        } else {
+            let entries = self.entries(0)?;
+            Node::Object(Object {
+                span: span_of(&entries).unwrap_or(0..0),
+                entries,
+            })
+        };
+        if let Some(line) = self.peek() {
+            return Err(self.error(self.lines[line].start, "Unexpected content"));
+        }
+        Ok(root)
+    }
+
+    /// Entries at `depth`, up to the first line indented less
+    fn entries(&mut self, depth: usize) -> Result<Vec<Entry>, Error> {
+        let mut entries = Vec::new();
+        while let Some(line) = self.peek() {
+            let indent = self.indent(line)?;
+            if indent < depth {
+                break;
+            }
+            if indent > depth {
+                return Err(self.error(self.lines[line].start, "Unexpected indentation"));
+            }
+            self.line = line;
+            let start = self.lines[line].start + indent * self.options.get_indent();
+            entries.push(self.entry(start, depth)?);
+        }
+        Ok(entries)
+    }
+
+    /// The entry whose key starts at `start`, on the current line
+    fn entry(&mut self, start: usize, depth: usize) -> Result<Entry, Error> {
+        let end = self.lines[self.line].end;
+        let text = &self.source[start..end];
+        let key_len = if text.starts_with('"') {
+            quoted_len(text).ok_or_else(|| self.error(start, "Unterminated quoted key"))?
+        } else {
+            text.find(|ch| ends_key(ch, self.options))
+                .unwrap_or(text.len())
+        };
+        if key_len == 0 {
+            return Err(self.error(start, "Expected key"));
+        }
+        let key = self.key(start..start + key_len)?;
+
+        let mut pos = start + key_len;
+        pos += self.source[pos..end].len() - self.source[pos..end].trim_start().len();
+        if opens_array(self.source[pos..].chars().next(), self.options) {
+            let array = self.array(pos, depth)?;
+            return Ok(Entry {
+                span: start..array.span.end,
+                key,
+                value: Node::Array(array),
+            });
+        }
+        if !self.source[pos..end].starts_with(':') {
+            let message = format!("Expected ':' after key '{}'", key.name);
+            return Err(self.error(pos, message));
+        }
+        pos += 1;
+        let rest = &self.source[pos..end];
+        pos += rest.len() - rest.trim_start().len();
+        let rest = rest.trim();
+
+        let value = if rest.is_empty() {
+            self.line += 1;
+            let nested = self
+                .peek()
+                .map(|line| self.indent(line))
+                .transpose()?
+                .is_some_and(|indent| indent > depth);
+            let list = self.options.get_lenient_arrays()
+                && self
+                    .peek()
+                    .is_some_and(|line| is_list_item(self.text(line)));
+            if nested && list {
+                let items = self.list(depth + 1)?;
+                Node::Array(Array {
+                    span: pos..items.last().map_or(pos, |item| item.span.end),
+                    header: Header::none(pos, ','),
+                    items: Items::List(items),
+                })
+            } else if nested {
+                let entries = self.entries(depth + 1)?;
+                Node::Object(Object {
+                    span: span_of(&entries).unwrap_or(pos..pos),
+                    entries,
+                })
+            } else {
+                Node::Scalar(Scalar {
+                    span: pos..pos,
+                    value: Value::Null,
+                })
+            }
+        } else if is_empty_object(rest) {
+            self.line += 1;
+            Node::Object(Object {
+                span: pos..pos + rest.len(),
+                entries: Vec::new(),
+            })
+        } else if let Some(keep_newline) = block_header(rest) {
+            self.block(pos, depth, keep_newline)?
+        } else if self.options.get_lenient_arrays() && is_unbracketed_array(rest) {
+            // Delimited values without a header are an inline array
+            let row = self.row_span(pos);
+            let items = split(self.source, row, ',')
+                .into_iter()
+                .map(|piece| self.cell(piece))
+                .collect::<Result<Vec<_>, _>>()?;
+            self.count_cells(items.len(), pos)?;
+            Node::Array(Array {
+                span: pos..items.last().map_or(pos, |item| item.span.end),
+                header: Header::none(pos, ','),
+                items: Items::Inline(items),
+            })
+        } else {
+            Node::Scalar(self.scalar(pos)?)
+        };
+        Ok(Entry {
+            span: start..value.span().end,
+            key,
+            value,
+        })
+    }
+
+    /// The array whose header starts at `start`, on the current line
+    fn array(&mut self, start: usize, depth: usize) -> Result<Array, Error> {
+        let end = self.lines[self.line].end;
+        let mut pos = start;
+        let mut length = None;
+        let mut declared = None;
+        if self.source[pos..].starts_with('[') {
+            let close = self.source[pos..end]
+                .find(']')
+                .map(|i| pos + i)
+                .ok_or_else(|| self.error(pos, "Expected ']'"))?;
+            let mut digits = self.source[pos + 1..close].trim_start_matches('#');
+            if let Some(rest) = digits.strip_suffix(is_declared_delimiter) {
+                declared = digits.chars().last();
+                digits = rest;
+            }
+            if !digits.is_empty() || !self.options.lengthless_headers() {
+                let n = digits
+                    .parse()
+                    .map_err(|_| self.error(pos + 1, "Invalid array length"))?;
+                check_array_length(self.options, n).map_err(|msg| self.error(pos + 1, msg))?;
+                length = Some(n);
+            }
+            pos = close + 1;
+        }
+
+        let mut fields_span = None;
+        if self.source[pos..end].starts_with('{') {
+            let mut in_fields = until_unquoted('}');
+            let len = self.source[pos + 1..end]
+                .chars()
+                .take_while(|&ch| in_fields(ch))
+                .map(char::len_utf8)
+                .sum::<usize>();
+            if !self.source[pos + 1 + len..end].starts_with('}') {
+                return Err(self.error(pos + 1 + len, "Expected '}'"));
+            }
+            fields_span = Some(pos + 1..pos + 1 + len);
+            pos += len + 2;
+        }
+        if !self.source[pos..end].starts_with(':') {
+            return Err(self.error(pos, "Expected ':'"));
+        }
+        pos += 1;
+        let header_span = start..pos;
+        let rest = &self.source[pos..end];
+
+        if let Some(fields_span) = fields_span {
+            self.line += 1;
+            let fields_text = &self.source[fields_span.clone()];
+            let first_row = self.peek().map(|line| self.text(line));
+            let delimiter = declared.unwrap_or_else(|| table_delimiter(fields_text, first_row));
+            let (fields, columns): (Vec<_>, Vec<_>) = split(self.source, fields_span, delimiter)
+                .into_iter()
+                .map(|field| self.field(field, delimiter))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .unzip();
+            let rows = self.rows(depth + 1, length, delimiter, &fields, &columns)?;
+            return Ok(Array {
+                span: start..rows.last().map_or(header_span.end, |row| row.span.end),
+                header: Header {
+                    span: header_span,
+                    length,
+                    fields: Some(fields),
+                    types: columns.iter().map(|column| column.ty).collect(),
+                    defaults: columns
+                        .iter()
+                        .map(|column| column.default.clone())
+                        .collect(),
+                    dictionaries: columns
+                        .into_iter()
+                        .map(|column| column.dictionary)
+                        .collect(),
+                    delimiter,
+                },
+                items: Items::Table(rows),
+            });
+        }
+
+        let header = |delimiter| Header {
+            span: header_span.clone(),
+            length,
+            ..Header::none(start, delimiter)
+        };
+        if !rest.trim().is_empty() {
+            let row = self.row_span(pos);
+            let delimiter =
+                declared.unwrap_or_else(|| inline_delimiter(&self.source[row.clone()], length));
+            let items = split(self.source, row, delimiter)
+                .into_iter()
+                .map(|piece| self.cell(piece))
+                .collect::<Result<Vec<_>, _>>()?;
+            self.count_cells(items.len(), pos)?;
+            return Ok(Array {
+                span: start..items.last().map_or(header_span.end, |item| item.span.end),
+                header: header(delimiter),
+                items: Items::Inline(items),
+            });
+        }
+
+        self.line += 1;
+        let list = self.peek().is_some_and(|line| {
+            self.indent(line).is_ok_and(|indent| indent == depth + 1)
+                && is_list_item(self.text(line))
+        });
+        if !list {
+            return Ok(Array {
+                span: header_span.clone(),
+                header: header(','),
+                items: Items::Inline(Vec::new()),
+            });
+        }
+        let items = self.list(depth + 1)?;
+        Ok(Array {
+            span: start..items.last().map_or(header_span.end, |item| item.span.end),
+            header: header(','),
+            items: Items::List(items),
+        })
+    }
+
+    /// Table rows at `depth`, with cells held to the types and defaults
+    /// declared for their columns
+    ///
+    /// Within the declared length, a blank line indented to `depth` is a row
+    /// with one empty cell, which is how a null in a one-column table is
+    /// written.
+    fn rows(
+        &mut self,
+        depth: usize,
+        length: Option<usize>,
+        delimiter: char,
+        fields: &[Key],
+        columns: &[Column],
+    ) -> Result<Vec<Row>, Error> {
+        let mut rows = Vec::new();
+        loop {
+            let line = match self.line {
+                line if length.is_some_and(|length| rows.len() < length)
+                    && self.is_blank_row(line, depth) =>
+                {
+                    line
+                }
+                _ => match self.peek() {
+                    Some(line) if self.indent(line)? >= depth => line,
+                    _ => break,
+                },
+            };
+            rows.push(self.row(line, depth, delimiter, fields, columns)?);
+        }
+        Ok(rows)
+    }
+
+    /// Whether `line` is blank but indented at least to `depth`
+    fn is_blank_row(&self, line: usize, depth: usize) -> bool {
+        self.lines.get(line).is_some_and(|range| {
+            self.text(line).is_empty() && range.len() >= depth * self.options.get_indent()
+        })
+    }
+
+    /// The table row on `line`, which must sit at `depth`
+    fn row(
+        &mut self,
+        line: usize,
+        depth: usize,
+        delimiter: char,
+        fields: &[Key],
+        columns: &[Column],
+    ) -> Result<Row, Error> {
+        if !self.is_blank_row(line, depth) && self.indent(line)? != depth {
+            return Err(self.error(self.lines[line].start, "Unexpected indentation"));
+        }
+        self.line = line;
+        let span = self.row_span(self.lines[line].start + depth * self.options.get_indent());
+        self.count_cells(fields.len(), span.start)?;
+        let cells = split(self.source, span.clone(), delimiter)
+            .into_iter()
+            .enumerate()
+            .map(|(i, cell)| match (fields.get(i), columns.get(i)) {
+                (Some(field), Some(column)) => self.column_cell(cell, &field.name, column),
+                _ => self.cell(cell),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Row { span, cells })
+    }
+
+    /// `- item` lines at `depth`
+    fn list(&mut self, depth: usize) -> Result<Vec<Item>, Error> {
+        let mut items = Vec::new();
+        while let Some(line) = self.peek() {
+            let indent = self.indent(line)?;
+            if indent < depth {
+                break;
+            }
+            items.push(self.item(line, depth)?);
+        }
+        Ok(items)
+    }
+
+    /// The `- item` starting on `line`, which must sit at `depth`
+    fn item(&mut self, line: usize, depth: usize) -> Result<Item, Error> {
+        let indent = self.indent(line)?;
+        let start = self.lines[line].start + indent * self.options.get_indent();
+        if indent != depth || !is_list_item(self.text(line)) {
+            return Err(self.error(start, "Expected a `- ` list item"));
+        }
+        self.line = line;
+        let end = self.lines[line].end;
+        let pos = (start + 2).min(end);
+        let rest = &self.source[pos..end];
+        let pos = pos + rest.len() - rest.trim_start().len();
+        let rest = rest.trim();
+
+        let value = if rest.starts_with('[') {
+            Node::Array(self.array(pos, depth)?)
+        } else if is_empty_object(rest) {
+            self.line += 1;
+            Node::Object(Object {
+                span: pos..pos + rest.len(),
+                entries: Vec::new(),
+            })
+        } else if is_object_item(rest, self.options) {
+            // The first field follows the marker, and the rest line up with it
+            let mut entries = vec![self.entry(pos, depth + 1)?];
+            entries.extend(self.entries(depth + 1)?);
+            Node::Object(Object {
+                span: span_of(&entries).unwrap_or(pos..pos),
+                entries,
+            })
+        } else {
+            Node::Scalar(self.scalar(pos)?)
+        };
+        Ok(Item {
+            span: start..value.span().end.max(start + 1),
+            value,
+        })
+    }
+
+    /// The body of a block string whose marker is at `start`
+    fn block(&mut self, start: usize, depth: usize, keep_newline: bool) -> Result<Node, Error> {
+        let prefix_len = (depth + 1) * self.options.get_indent();
+        let mut end = self.lines[self.line].end;
+        let mut lines = Vec::new();
+        self.line += 1;
+        while let Some(range) = self.lines.get(self.line) {
+            let Some(content) = block_line(&self.source[range.clone()], prefix_len) else {
+                break;
+            };
+            if !content.is_empty() {
+                end = range.end;
+            }
+            lines.push(content);
+            self.line += 1;
+        }
+        let text = join_block_lines(&lines, keep_newline);
+        check_string_length(self.options, text.len()).map_err(|msg| self.error(start, msg))?;
+        Ok(Node::Block(Scalar {
+            span: start..end,
+            value: Value::String(text),
+        }))
+    }
+
+    /// The primitive starting at `start` on the current line, moving past it
+    fn scalar(&mut self, start: usize) -> Result<Scalar, Error> {
+        let span = self.row_span(start);
+        let text = self.source[span.clone()].trim_end();
+        Ok(Scalar {
+            span: start..start + text.len(),
+            value: self.primitive(text, start)?,
+        })
+    }
+
+    /// A table cell or inline item, trimmed of the spaces around it
+    fn cell(&self, span: Range<usize>) -> Result<Scalar, Error> {
+        let raw = &self.source[span.clone()];
+        let start = span.start + (raw.len() - raw.trim_start().len());
+        let text = raw.trim();
+        Ok(Scalar {
+            span: start..start + text.len(),
+            value: self.primitive(text, start)?,
+        })
+    }
+
+    /// A table cell of a column declared with a type or a default
+    fn column_cell(
+        &self,
+        span: Range<usize>,
+        field: &str,
+        column: &Column,
+    ) -> Result<Scalar, Error> {
+        let raw = &self.source[span.clone()];
+        let start = span.start + (raw.len() - raw.trim_start().len());
+        let text = raw.trim();
+        check_string_length(self.options, text.len()).map_err(|msg| self.error(start, msg))?;
+        let value = match &column.default {
+            Some(default) if text.is_empty() => default.clone(),
+            _ => column_cell(self.options, text, field, column).map_err(|err| match err {
+                Error::Parse { .. } => err,
+                err => self.error(start, err.to_string()),
+            })?,
+        };
+        Ok(Scalar {
+            span: start..start + text.len(),
+            value,
+        })
+    }
+
+    fn primitive(&self, text: &str, start: usize) -> Result<Value, Error> {
+        check_string_length(self.options, text.len()).map_err(|msg| self.error(start, msg))?;
+        parse_primitive_value(self.options, text).map_err(|err| match err {
+            Error::Parse { .. } => err,
+            err => self.error(start, err.to_string()),
+        })
+    }
+
+    /// A table header field, and the type and default declared after it
+    fn field(&self, span: Range<usize>, delimiter: char) -> Result<(Key, Column), Error> {
+        let raw = &self.source[span.clone()];
+        let start = span.start + (raw.len() - raw.trim_start().len());
+        let text = raw.trim();
+        let (_, columns) = header_fields(text, delimiter, self.options)
+            .map_err(|err| self.error(start, err.to_string()))?;
+        let name_len = match text.starts_with('"') {
+            true => quoted_len(text).unwrap_or(text.len()),
+            false => field_name_len(text),
+        };
+        let key = self.key(start..start + name_len)?;
+        Ok((key, columns.into_iter().next().unwrap_or_default()))
+    }
+
+    fn key(&self, span: Range<usize>) -> Result<Key, Error> {
+        let raw = &self.source[span.clone()];
+        let start = span.start + (raw.len() - raw.trim_start().len());
+        let text = raw.trim();
+        let name = if text.starts_with('"') {
+            if quoted_len(text) != Some(text.len()) {
+                return Err(self.error(start, format!("Malformed quoted key {text}")));
+            }
+            unescape(text).map_err(|err| self.error(start, err.to_string()))?
+        } else {
+            text.to_string()
+        };
+        Ok(Key {
+            span: start..start + text.len(),
+            name,
+        })
+    }
+
+    /// The delimited row starting at `start` on the current line, which a
+    /// quoted value may carry onto later lines; moves past it
+    fn row_span(&mut self, start: usize) -> Range<usize> {
+        let len = row_len(&self.source[start..], true).unwrap_or_default();
+        let end = start + len;
+        while self.lines.get(self.line).is_some_and(|line| line.end < end) {
+            self.line += 1;
+        }
+        self.line += 1;
+        start..self.lines[self.line - 1].end.min(end)
+    }
+
+    /// The next line with any text on it, from the current one
+    fn peek(&self) -> Option<usize> {
+        self.peek_from(self.line)
+    }
+
+    fn peek_from(&self, line: usize) -> Option<usize> {
+        (line..self.lines.len()).find(|&i| !self.text(i).is_empty())
+    }
+
+    /// A line's text without its indentation or trailing spaces
+    fn text(&self, line: usize) -> &str {
+        self.source[self.lines[line].clone()].trim()
+    }
+
+    /// Depth of a line's indentation
+    fn indent(&self, line: usize) -> Result<usize, Error> {
+        let range = &self.lines[line];
+        let spaces = self.source[range.clone()].len()
+            - self.source[range.clone()].trim_start_matches(' ').len();
+        let unit = self.options.get_indent();
+        if !spaces.is_multiple_of(unit) {
+            let message = format!("Indentation must be a multiple of {unit} spaces");
+            return Err(self.error(range.start, message));
+        }
+        Ok(spaces / unit)
+    }
+
+    /// Add `count` cells read at `pos` to the running total
+    fn count_cells(&mut self, count: usize, pos: usize) -> Result<(), Error> {
+        self.cells = self.cells.saturating_add(count);
+        check_cells(self.options, self.cells).map_err(|msg| self.error(pos, msg))
+    }
+
+    fn error(&self, pos: usize, message: impl Into<String>) -> Error {
+        let (line, column) = line_column(self.source, pos);
+        Error::parse(pos, line, column, message)
+    }
+}
+
+/// Split the text in `span` at `delimiter` outside quotes, giving the span
+/// of each piece
+fn split(source: &str, span: Range<usize>, delimiter: char) -> Vec<Range<usize>> {
+    let text = &source[span.clone()];
+    simd::split_row_fallback(text, delimiter)
+        .into_iter()
+        .map(|piece| {
+            let start = span.start + (piece.as_ptr() as usize - text.as_ptr() as usize);
+            start..start + piece.len()
+        })
+        .collect()
+}
+
+/// Byte range from the first entry to the end of the last
+/// Byte range of each line of `input`, without its line break
+fn line_spans(input: &str) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in input.split('\n') {
+        let end = start + line.strip_suffix('\r').unwrap_or(line).len();
+        lines.push(start..end);
+        start += line.len() + 1;
+    }
+    lines
+}
+
+/// Index of the line holding byte `pos`
+fn line_of(lines: &[Range<usize>], pos: usize) -> usize {
+    lines.partition_point(|line| line.end < pos)
+}
+
+fn span_of(entries: &[Entry]) -> Option<Range<usize>> {
+    Some(entries.first()?.span.start..entries.last()?.span.end)
+}
@@ -1,10 +1,52 @@
 //! Encoding TOON format from JSON values
 
+use crate::decode::Column;
 use crate::error::Error;
-use crate::options::EncodeOptions;
+use crate::options::{
+    ColumnType, EncodeOptions, NanPolicy, NullPolicy, Order, Progress, ProgressCallback,
+    QuotingPolicy, SpecVersion, Style, TableSort,
+};
+use crate::pointer;
+use crate::query::compare;
 use serde_json::Value;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::io::Write;
 
+/// Key of the single-entry object used to carry a non-finite float through a
+/// `serde_json::Value` tree, following serde_json's own private-token scheme
+pub(crate) const NON_FINITE_TOKEN: &str = "$toon_rust::private::NonFinite";
+
+/// Return the TOON literal (`nan`, `inf`, `-inf`) for a non-finite marker value
+pub(crate) fn non_finite_literal(value: &Value) -> Option<&str> {
+    let obj = value.as_object()?;
+    if obj.len() != 1 {
+        return None;
+    }
+    match obj.get(NON_FINITE_TOKEN)?.as_str()? {
+        literal @ ("nan" | "inf" | "-inf") => Some(literal),
+        _ => None,
+    }
+}
+
+/// Build the marker value carrying a `nan`, `inf` or `-inf` literal
+pub(crate) fn non_finite_marker(literal: &str) -> Value {
+    let mut map = serde_json::Map::new();
+    map.insert(
+        NON_FINITE_TOKEN.to_string(),
+        Value::String(literal.to_string()),
+    );
+    Value::Object(map)
+}
+
+/// Error for a non-finite float under [`NanPolicy::Error`]
+pub(crate) fn non_finite_error(literal: &str) -> Error {
+    Error::Serialization(format!(
+        "cannot encode non-finite float `{literal}` (see EncodeOptions::nan_policy)"
+    ))
+}
+
 /// Encode a JSON value to TOON format
 ///
 /// # Arguments
@@ -19,200 +61,1253 @@ pub fn encode(value: &Value, options: Option<&EncodeOptions>) -> Result<String,
     let default_opts = EncodeOptions::default();
     let opts = options.unwrap_or(&default_opts);
     let mut output = String::new();
-    encode_value(value, &mut output, 0, opts)?;
+    encode_root(value, &mut output, opts)?;
     Ok(output)
 }
 
+/// Encode a JSON value to canonical TOON
+///
+/// Equal values always produce byte-identical output, which makes the result
+/// suitable for hashing, cache keys and snapshot tests. Object keys are
+/// sorted by their UTF-8 bytes at every level, whatever order the map keeps
+/// them in, `-0.0` is written as `0`, and everything else uses the default
+/// [`EncodeOptions`]: comma delimiters, two-space indentation, shortest
+/// round-trip floats and minimal quoting.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::encode_canonical;
+///
+/// let toon = encode_canonical(&json!({"b": 1, "a": [-0.0, 2.5]})).unwrap();
+/// assert_eq!(toon, "a[2]:0,2.5\nb: 1");
+/// ```
+pub fn encode_canonical(value: &Value) -> Result<String, Error> {
+    encode(&canonicalize(value), None)
+}
+
+/// Copy of `value` with sorted keys and negative zero normalized
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(obj) => {
+            let mut entries: Vec<_> = obj.iter().collect();
+            entries.sort_unstable_by_key(|(key, _)| *key);
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonicalize(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        Value::Number(n) if n.as_f64() == Some(0.0) && n.is_f64() => Value::from(0),
+        _ => value.clone(),
+    }
+}
+
+/// Destination for encoded text, so the same encoder serves [`encode`] and
+/// [`encode_stream`]
+pub(crate) trait Sink {
+    fn push_str(&mut self, s: &str) -> Result<(), Error>;
+
+    fn push(&mut self, ch: char) -> Result<(), Error> {
+        self.push_str(ch.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Note that a table row or list item has been written
+    fn row(&mut self) {}
+
+    /// Note that the top-level entry `key` is about to be written
+    fn section(&mut self, _key: &str) {}
+}
+
+impl Sink for String {
+    fn push_str(&mut self, s: &str) -> Result<(), Error> {
+        String::push_str(self, s);
+        Ok(())
+    }
+}
+
+/// Sink writing straight through to an `io::Write`
+pub(crate) struct WriteSink<'w, W>(pub(crate) &'w mut W);
+
+impl<W: Write> Sink for WriteSink<'_, W> {
+    fn push_str(&mut self, s: &str) -> Result<(), Error> {
+        self.0
+            .write_all(s.as_bytes())
+            .map_err(|e| Error::Io(e.to_string()))
+    }
+}
+
+/// Sink counting what passes through it for a [`ProgressCallback`]
+struct ProgressSink<'p, S> {
+    inner: S,
+    callback: &'p dyn ProgressCallback,
+    bytes: usize,
+    rows: usize,
+    section: Option<String>,
+}
+
+impl<S: Sink> ProgressSink<'_, S> {
+    fn report(&self) {
+        self.callback.report(&Progress {
+            bytes: self.bytes,
+            rows: self.rows,
+            section: self.section.as_deref(),
+        });
+    }
+}
+
+impl<S: Sink> Sink for ProgressSink<'_, S> {
+    fn push_str(&mut self, s: &str) -> Result<(), Error> {
+        self.bytes += s.len();
+        self.inner.push_str(s)
+    }
+
+    fn row(&mut self) {
+        self.rows += 1;
+        self.report();
+    }
+
+    fn section(&mut self, key: &str) {
+        self.section = Some(key.to_string());
+        self.report();
+    }
+}
+
+/// Encode a whole document's value
+pub(crate) fn encode_root(
+    value: &Value,
+    output: &mut impl Sink,
+    options: &EncodeOptions,
+) -> Result<(), Error> {
+    check_indent(options)?;
+    check_spec_version(options)?;
+    let value = sort_tables(value, options);
+    if is_primitive(&value) {
+        encode_primitive_value(&value, output, options, None, Slot::Root)
+    } else {
+        encode_value(&value, output, 0, options)
+    }
+}
+
+/// Fail unless each indentation level is written as one or more spaces or
+/// tabs, which is all the decoder reads as indentation
+pub(crate) fn check_indent(options: &EncodeOptions) -> Result<(), Error> {
+    let unit = options.get_indent_string();
+    if unit.is_empty() || unit.chars().any(|ch| ch != ' ' && ch != '\t') {
+        return Err(Error::Serialization(format!(
+            "indentation must be one or more spaces or tabs, got {unit:?}"
+        )));
+    }
+    Ok(())
+}
+
+/// Fail if the options ask for syntax the targeted spec revision lacks
+fn check_spec_version(options: &EncodeOptions) -> Result<(), Error> {
+    let Some(version) = options.get_spec_version() else {
+        return Ok(());
+    };
+    let unsupported = [
+        (
+            options.length_marker.is_some() && version >= SpecVersion::V2,
+            "`#` length markers",
+        ),
+        (options.get_typed_headers(), "typed headers"),
+        (options.get_column_defaults().is_some(), "column defaults"),
+        (options.get_dictionary_encoding(), "dictionary columns"),
+        (options.get_block_strings(), "block strings"),
+        (
+            options.get_nan_policy() == NanPolicy::Token,
+            "`nan` and `inf` literals",
+        ),
+        (!options.get_emit_lengths(), "arrays without lengths"),
+        (
+            options.get_max_rows_per_table() != usize::MAX,
+            "row elision markers",
+        ),
+    ];
+    match unsupported.iter().find(|(asked, _)| *asked) {
+        Some((_, construct)) => Err(Error::Serialization(format!(
+            "{construct} aren't part of {version}"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Copy of `value` with the rows of the tables named by
+/// [`EncodeOptions::sort_table_by`] sorted, or `value` itself when none of
+/// them is there
+fn sort_tables<'v>(value: &'v Value, options: &EncodeOptions) -> Cow<'v, Value> {
+    let sorts = options.get_table_sorts();
+    if !sorts
+        .iter()
+        .any(|sort| pointer::get(value, &sort.path).is_some_and(Value::is_array))
+    {
+        return Cow::Borrowed(value);
+    }
+    let mut value = value.clone();
+    let mut paths: Vec<&str> = Vec::new();
+    for sort in sorts {
+        if !paths.contains(&sort.path.as_str()) {
+            paths.push(&sort.path);
+        }
+    }
+    for path in paths {
+        let Some(Value::Array(rows)) = pointer::get_mut(&mut value, path) else {
+            continue;
+        };
+        let keys: Vec<&TableSort> = sorts.iter().filter(|sort| sort.path == path).collect();
+        rows.sort_by(|a, b| {
+            keys.iter()
+                .map(|sort| {
+                    let ordering = compare(&a[&sort.column], &b[&sort.column]);
+                    match sort.order {
+                        Order::Asc => ordering,
+                        Order::Desc => ordering.reverse(),
+                    }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+    Cow::Owned(value)
+}
+
 fn encode_value(
     value: &Value,
-    output: &mut String,
+    output: &mut impl Sink,
     indent_level: usize,
     options: &EncodeOptions,
 ) -> Result<(), Error> {
     match value {
-        Value::Null => {
-            // Null values are typically omitted or represented as empty
+        Value::Array(arr) => {
+            encode_array(arr, output, indent_level, options)?;
         }
-        Value::Bool(b) => {
-            output.push_str(if *b { "true" } else { "false" });
+        Value::Object(obj) if non_finite_literal(value).is_none() => {
+            encode_object(obj, output, indent_level, false, options)?;
         }
-        Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                output.push_str(&i.to_string());
-            } else if let Some(f) = n.as_f64() {
-                output.push_str(&f.to_string());
-            } else {
-                return Err(Error::Serialization("Invalid number".to_string()));
+        _ => {
+            encode_primitive_value(value, output, options, None, Slot::Value)?;
+        }
+    }
+    Ok(())
+}
+
+fn encode_string(
+    s: &str,
+    output: &mut impl Sink,
+    options: &EncodeOptions,
+    slot: Slot,
+) -> Result<(), Error> {
+    output.push_str(&format_string(s, options, slot))
+}
+
+/// Render a multi-line string as a `|` or `|-` block with its lines at
+/// `indent_level`, when block strings are enabled and the text fits one
+///
+/// Text a block can't reproduce exactly (leading whitespace, several trailing
+/// newlines, carriage returns) stays a quoted string.
+pub(crate) fn block_string(
+    value: &Value,
+    indent_level: usize,
+    options: &EncodeOptions,
+) -> Option<String> {
+    let Value::String(s) = value else {
+        return None;
+    };
+    if !options.get_block_strings() || options.get_escape_non_ascii() || !s.contains('\n') {
+        return None;
+    }
+    let (body, header) = match s.strip_suffix('\n') {
+        Some(body) => (body, "|"),
+        None => (s.as_str(), "|-"),
+    };
+    if body.is_empty()
+        || body.ends_with('\n')
+        || body.starts_with([' ', '\t', '\n'])
+        || body.contains('\r')
+    {
+        return None;
+    }
+    let indent_str = options.get_indent_string().repeat(indent_level);
+    let mut output = String::from(header);
+    for line in body.split('\n') {
+        output.push('\n');
+        if !line.is_empty() {
+            output.push_str(&indent_str);
+            output.push_str(line);
+        }
+    }
+    Some(output)
+}
+
+/// Where a string is written, which decides what the decoder could misread
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Slot {
+    /// An object value, list item or root value, read up to the first space
+    Value,
+    /// A table cell or inline array item, read up to the next delimiter
+    Cell,
+    /// A primitive making up the whole document, quoted under
+    /// `roundtrip_safe` so it can't be read as a key
+    Root,
+}
+
+/// Whether a string needs quotes to decode back unchanged in `slot`
+fn needs_quotes(s: &str, options: &EncodeOptions, slot: Slot) -> bool {
+    let escape_non_ascii = options.get_escape_non_ascii();
+    // Never readable unquoted, wherever the string is written
+    if s.is_empty()
+        || s.contains(['\n', '\r', '\t'])
+        || s == "|"
+        || s == "|-"
+        || s == "{}"
+        || s.parse::<f64>().is_ok()
+        || (escape_non_ascii && !s.is_ascii())
+    {
+        return true;
+    }
+    let policy = options.get_quoting();
+    if policy == QuotingPolicy::Always || (slot == Slot::Root && options.get_roundtrip_safe()) {
+        return true;
+    }
+    if options.get_quote_reserved_words() && matches!(s, "true" | "false" | "null") {
+        return true;
+    }
+    let padded = s.starts_with(char::is_whitespace) || s.ends_with(char::is_whitespace);
+    if options.get_quote_padded_strings() && padded {
+        return true;
+    }
+
+    // An object value ends at the first space and is typed by its first character
+    let misread_as_value = s.contains(char::is_whitespace)
+        || s.starts_with(|ch: char| ch.is_ascii_digit() || matches!(ch, '-' | '[' | '"'));
+    // A cell ends at the delimiter, and quotes inside it confuse the split
+    let misread_as_cell = s.contains(options.get_delimiter()) || s.contains('"');
+    match (policy, slot) {
+        (QuotingPolicy::WhenAmbiguous, Slot::Value | Slot::Root) => misread_as_value,
+        (QuotingPolicy::WhenAmbiguous, Slot::Cell) => misread_as_cell,
+        _ => misread_as_value || misread_as_cell,
+    }
+}
+
+/// Render a string value, quoting and escaping it when needed
+fn format_string<'s>(s: &'s str, options: &EncodeOptions, slot: Slot) -> Cow<'s, str> {
+    if !needs_quotes(s, options, slot) {
+        return Cow::Borrowed(s);
+    }
+    Cow::Owned(quote(s, options))
+}
+
+/// Whether a key must be quoted to decode back as the same key
+///
+/// Unquoted keys end at a colon, bracket, brace or whitespace, table header
+/// fields also at a comma or the delimiter, and a leading `-` reads as a
+/// list marker. Under key folding an unquoted `.` reads as a path.
+fn key_needs_quotes(key: &str, options: &EncodeOptions) -> bool {
+    key.is_empty()
+        || key.starts_with('-')
+        || (options.get_key_folding() && key.contains('.'))
+        || key.contains(|ch: char| {
+            matches!(ch, ':' | '[' | ']' | '{' | '}' | '"' | '\\' | ',')
+                || ch == options.get_delimiter()
+                || ch.is_whitespace()
+                || ch.is_control()
+        })
+        || (options.get_escape_non_ascii() && !key.is_ascii())
+}
+
+/// Render a key, quoting and escaping it when needed
+pub(crate) fn format_key<'k>(key: &'k str, options: &EncodeOptions) -> Cow<'k, str> {
+    if key_needs_quotes(key, options) {
+        Cow::Owned(quote(key, options))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/// The fields of a table header, without the braces
+pub(crate) fn format_fields(keys: &[String], options: &EncodeOptions) -> String {
+    keys.iter()
+        .map(|key| format_key(key, options))
+        .collect::<Vec<_>>()
+        .join(&options.get_delimiter().to_string())
+}
+
+/// The fields of a table header whose columns come from [`column_paths`],
+/// without the braces
+pub(crate) fn path_fields(keys: &[String], options: &EncodeOptions) -> String {
+    keys.iter()
+        .map(|key| format_column(key, options))
+        .collect::<Vec<_>>()
+        .join(&options.get_delimiter().to_string())
+}
+
+/// Render a column from [`column_paths`], where under key folding a dotted
+/// name is always a path and stays unquoted
+fn format_column<'k>(column: &'k str, options: &EncodeOptions) -> Cow<'k, str> {
+    let path = options.get_key_folding()
+        && column
+            .split('.')
+            .all(|segment| is_path_segment(segment, options));
+    match path {
+        true => Cow::Borrowed(column),
+        false => format_key(column, options),
+    }
+}
+
+/// The fields of a table header with a `:type` after each column that has
+/// one
+#[cfg(feature = "serde")]
+pub(crate) fn typed_fields(
+    keys: &[String],
+    types: &[Option<ColumnType>],
+    options: &EncodeOptions,
+) -> String {
+    let columns: Vec<_> = types
+        .iter()
+        .map(|&ty| Column {
+            ty,
+            ..Column::default()
+        })
+        .collect();
+    column_fields(keys, &columns, options)
+}
+
+/// The fields of a table header with the `:type`, `@[dictionary]` and
+/// `=default` declared for each column
+fn column_fields(keys: &[String], columns: &[Column], options: &EncodeOptions) -> String {
+    let none = Column::default();
+    keys.iter()
+        .zip(columns.iter().chain(std::iter::repeat(&none)))
+        .map(|(key, column)| {
+            // An unquoted name also ends at `=` and `@[`
+            let ends_early = key.contains('=') || key.contains("@[");
+            let mut field = match ends_early {
+                true => quote(key, options),
+                false => format_column(key, options).into_owned(),
+            };
+            if let Some(ty) = column.ty {
+                field.push_str(&format!(":{ty}"));
+            }
+            if let Some(dictionary) = &column.dictionary {
+                let entries: Vec<_> = dictionary
+                    .iter()
+                    .map(|entry| dictionary_entry(entry, options))
+                    .collect();
+                field.push_str(&format!("@[{}]", entries.join(";")));
             }
+            if let Some(default) = &column.default {
+                field.push('=');
+                // Only defaults that encode cleanly are chosen
+                let _ = encode_scalar(default, &mut field, options, Slot::Cell);
+            }
+            field
+        })
+        .collect::<Vec<_>>()
+        .join(&options.get_delimiter().to_string())
+}
+
+/// A dictionary entry, quoted when it holds text that ends it or the header
+/// or the decoder would trim
+fn dictionary_entry<'e>(entry: &'e str, options: &EncodeOptions) -> Cow<'e, str> {
+    let plain = !entry.is_empty()
+        && entry.trim() == entry
+        && !entry.contains([';', ']', '}', ',', '|', '"', '\\'])
+        && !entry.contains(char::is_control)
+        && (entry.is_ascii() || !options.get_escape_non_ascii());
+    match plain {
+        true => Cow::Borrowed(entry),
+        false => Cow::Owned(quote(entry, options)),
+    }
+}
+
+/// How the cells of one table are written
+pub(crate) struct TableStyle {
+    /// Whether some row lacks a column, so null cells are spelled out
+    sparse: bool,
+    /// The type, dictionary and default declared for each column
+    columns: Vec<Column>,
+    /// The code of each dictionary entry, by column
+    codes: Vec<HashMap<String, usize>>,
+}
+
+impl TableStyle {
+    /// A table declaring nothing about its columns
+    pub(crate) fn plain(sparse: bool) -> Self {
+        TableStyle::new(sparse, Vec::new())
+    }
+
+    fn new(sparse: bool, columns: Vec<Column>) -> Self {
+        let codes = columns
+            .iter()
+            .map(|column| {
+                let entries = column.dictionary.iter().flatten().cloned();
+                entries.zip(0..).collect()
+            })
+            .collect();
+        TableStyle {
+            sparse,
+            columns,
+            codes,
         }
-        Value::String(s) => {
-            encode_string(s, output, options.get_delimiter());
+    }
+
+    /// Write one cell of column `i`: empty for a missing cell or the
+    /// column's default, its code for a dictionary entry, and `null` for a
+    /// null that could be read as either
+    fn cell(
+        &self,
+        i: usize,
+        value: Option<&Value>,
+        output: &mut impl Sink,
+        options: &EncodeOptions,
+    ) -> Result<(), Error> {
+        let Some(value) = value else {
+            return Ok(());
+        };
+        let default = self
+            .columns
+            .get(i)
+            .and_then(|column| column.default.as_ref());
+        match default {
+            Some(default) if value == default => Ok(()),
+            _ if value.is_null() && (self.sparse || default.is_some()) => output.push_str("null"),
+            _ => match value.as_str().and_then(|s| self.codes.get(i)?.get(s)) {
+                Some(code) => output.push_str(&code.to_string()),
+                None => encode_scalar(value, output, options, Slot::Cell),
+            },
         }
-        Value::Array(arr) => {
-            encode_array(arr, output, indent_level, options)?;
+    }
+}
+
+/// The style of a table holding `arr`, with types declared under
+/// [`EncodeOptions::typed_headers`], defaults under
+/// [`EncodeOptions::column_defaults`] and dictionaries under
+/// [`EncodeOptions::dictionary_encoding`]
+fn table_style(arr: &[Value], keys: &[String], options: &EncodeOptions) -> TableStyle {
+    let sparse = is_sparse(arr, keys, options);
+    if !options.get_typed_headers()
+        && options.get_column_defaults().is_none()
+        && !options.get_dictionary_encoding()
+    {
+        return TableStyle::plain(sparse);
+    }
+    let columns = keys
+        .iter()
+        .map(|key| {
+            let cells: Vec<_> = arr
+                .iter()
+                .filter_map(Value::as_object)
+                .map(|obj| {
+                    table_cell(obj, key, options)
+                        .map(|(field, value)| transform_leaf(value, Some(field), options))
+                })
+                .collect();
+            let ty = match options.get_typed_headers() {
+                true => cells
+                    .iter()
+                    .flatten()
+                    .fold(ColumnCells::default(), |column, cell| column.add(cell))
+                    .column_type(),
+                false => None,
+            };
+            // A row of a lone column holding its default would be a blank line
+            let default = options
+                .get_column_defaults()
+                .filter(|_| keys.len() > 1)
+                .and_then(|share| column_default(&cells, share, options));
+            let dictionary = match options.get_dictionary_encoding() && default.is_none() {
+                true => column_dictionary(&cells, options),
+                false => None,
+            };
+            Column {
+                ty,
+                default,
+                dictionary,
+                ..Column::default()
+            }
+        })
+        .collect();
+    TableStyle::new(sparse, columns)
+}
+
+/// The distinct strings of a column, most common first, when referring to
+/// them by index is shorter than writing them out
+///
+/// Only columns holding nothing but strings and nulls get one.
+fn column_dictionary(
+    cells: &[Option<Cow<'_, Value>>],
+    options: &EncodeOptions,
+) -> Option<Vec<String>> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut written = 0;
+    for value in cells.iter().flatten() {
+        match value.as_ref() {
+            Value::Null => {}
+            Value::String(s) => {
+                *counts.entry(s).or_default() += 1;
+                written += format_string(s, options, Slot::Cell).len();
+            }
+            _ => return None,
         }
-        Value::Object(obj) => {
-            encode_object(obj, output, indent_level, options)?;
+    }
+    let mut entries: Vec<_> = counts.into_iter().collect();
+    entries.sort_by(|(a, x), (b, y)| y.cmp(x).then(a.cmp(b)));
+
+    // `@[` and `]`, the entries and their separators, then one code per cell
+    let mut coded = 2 + entries.len();
+    for (code, (entry, count)) in entries.iter().enumerate() {
+        coded += dictionary_entry(entry, options).len() + count * code.to_string().len();
+    }
+    (!entries.is_empty() && coded < written).then(|| {
+        entries
+            .into_iter()
+            .map(|(entry, _)| entry.to_string())
+            .collect()
+    })
+}
+
+/// The most common value of a column, ties going to the shortest, when it
+/// fills at least `share` of the rows and declaring it saves text
+///
+/// Columns missing from some row get none, since their empty cells already
+/// stand for the missing fields.
+fn column_default(
+    cells: &[Option<Cow<'_, Value>>],
+    share: f64,
+    options: &EncodeOptions,
+) -> Option<Value> {
+    let mut counts: BTreeMap<String, (&Value, usize)> = BTreeMap::new();
+    for cell in cells {
+        let value = cell.as_deref()?;
+        if value.is_null() {
+            continue;
+        }
+        let mut text = String::new();
+        encode_scalar(value, &mut text, options, Slot::Cell).ok()?;
+        counts.entry(text).or_insert((value, 0)).1 += 1;
+    }
+    let (text, (value, count)) = counts
+        .into_iter()
+        .min_by(|(a, (_, x)), (b, (_, y))| y.cmp(x).then(a.len().cmp(&b.len())).then(a.cmp(b)))?;
+    // Unquoted, the text would end the header early or be read as a delimiter
+    let clean = text.starts_with('"') || !text.contains(['}', ',', '|', '\t']);
+    let filled = count as f64 >= share * cells.len() as f64;
+    (clean && filled && count * text.len() > text.len() + 1).then(|| value.clone())
+}
+
+/// What the cells of a table column seen so far have in common
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum ColumnCells {
+    /// Only nulls
+    #[default]
+    Empty,
+    Typed(ColumnType),
+    Mixed,
+}
+
+impl ColumnCells {
+    /// Take in one more cell
+    pub(crate) fn add(self, cell: &Value) -> Self {
+        let ty = match cell {
+            Value::Null => return self,
+            Value::Bool(_) => ColumnType::Bool,
+            // Integers too large for `i64` are written as floats
+            Value::Number(n) if n.is_i64() => ColumnType::Int,
+            Value::Number(_) => ColumnType::Float,
+            Value::String(_) => ColumnType::Str,
+            _ => return ColumnCells::Mixed,
+        };
+        match self {
+            ColumnCells::Empty => ColumnCells::Typed(ty),
+            ColumnCells::Typed(seen) if seen == ty => self,
+            _ => ColumnCells::Mixed,
+        }
+    }
+
+    /// The type to declare for the column
+    pub(crate) fn column_type(self) -> Option<ColumnType> {
+        match self {
+            ColumnCells::Typed(ty) => Some(ty),
+            _ => None,
         }
     }
-    Ok(())
 }
 
-fn encode_string(s: &str, output: &mut String, delimiter: char) {
-    // Check if we need to quote the string
-    let needs_quoting = s.contains(delimiter)
-        || s.contains(' ')
-        || s.contains('\n')
-        || s.contains('\t')
-        || s == "true"
-        || s == "false"
-        || s == "null"
-        || s.parse::<f64>().is_ok();
-
-    if needs_quoting {
-        output.push('"');
-        for ch in s.chars() {
-            match ch {
-                '"' => output.push_str("\\\""),
-                '\\' => output.push_str("\\\\"),
-                '\n' => output.push_str("\\n"),
-                '\r' => output.push_str("\\r"),
-                '\t' => output.push_str("\\t"),
-                _ => output.push(ch),
+/// Wrap a string in quotes, escaping what the decoder would misread
+fn quote(s: &str, options: &EncodeOptions) -> String {
+    let escape_non_ascii = options.get_escape_non_ascii();
+    let mut output = String::with_capacity(s.len() + 2);
+    output.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            _ if escape_non_ascii && !ch.is_ascii() => {
+                let mut units = [0; 2];
+                for unit in ch.encode_utf16(&mut units) {
+                    output.push_str(&format!("\\u{unit:04x}"));
+                }
             }
+            _ => output.push(ch),
         }
-        output.push('"');
-    } else {
-        output.push_str(s);
     }
+    output.push('"');
+    output
 }
 
 fn encode_array(
     arr: &[Value],
-    output: &mut String,
+    output: &mut impl Sink,
     indent_level: usize,
     options: &EncodeOptions,
 ) -> Result<(), Error> {
     if arr.is_empty() {
-        output.push_str("[0]:");
+        output.push_str(&format!("{}:", length_header(0, options)))?;
         return Ok(());
     }
 
     // Check if array contains uniform objects (tabular format)
-    if let Some(keys) = check_uniform_objects(arr) {
+    if let Some(keys) = table_layout(arr, indent_level, options)? {
         // For root-level arrays, include the header
-        let length_marker = options
-            .length_marker
-            .map(|m| format!("{m}"))
-            .unwrap_or_default();
-        output.push_str(&format!("[{}{}]", length_marker, arr.len()));
-        output.push('{');
-        output.push_str(&keys.join(&options.get_delimiter().to_string()));
-        output.push_str("}:\n");
-        encode_tabular_array_rows(arr, keys, output, indent_level, options)?;
+        let style = table_style(arr, &keys, options);
+        output.push_str(&length_header(arr.len(), options))?;
+        output.push('{')?;
+        output.push_str(&column_fields(&keys, &style.columns, options))?;
+        output.push_str("}:\n")?;
+        encode_tabular_array_rows(arr, &keys, &style, output, indent_level, options)?;
         return Ok(());
     }
 
     // Check if all elements are primitives (inline format)
     if arr.iter().all(is_primitive) {
-        encode_inline_array(arr, output, options)?;
+        encode_inline_array(arr, output, indent_level, options)?;
         return Ok(());
     }
 
     // Otherwise, use list format
+    output.push_str(&format!("{}:\n", length_header(arr.len(), options)))?;
     encode_list_array(arr, output, indent_level, options)?;
     Ok(())
 }
 
+/// Format a number, using the shortest representation that round-trips
+/// unless a fixed float precision is configured
+fn format_number(n: &serde_json::Number, options: &EncodeOptions) -> Result<String, Error> {
+    if let Some(i) = n.as_i64() {
+        return Ok(i.to_string());
+    }
+    if let Some(u) = n.as_u64() {
+        return Ok(u.to_string());
+    }
+    let f = n
+        .as_f64()
+        .ok_or_else(|| Error::Serialization("Invalid number".to_string()))?;
+    Ok(match options.float_precision {
+        Some(precision) => format!("{f:.precision$}"),
+        None => ryu::Buffer::new().format_finite(f).to_string(),
+    })
+}
+
+/// Apply the configured value transformer to a leaf value
+pub(crate) fn transform_leaf<'v>(
+    value: &'v Value,
+    key: Option<&str>,
+    options: &EncodeOptions,
+) -> Cow<'v, Value> {
+    match &options.value_transformer {
+        Some(transformer) => match transformer.transform(key, value) {
+            Some(replacement) => Cow::Owned(replacement),
+            None => Cow::Borrowed(value),
+        },
+        None => Cow::Borrowed(value),
+    }
+}
+
+/// The transformed value of an object entry written as `key: value`, or
+/// `None` for arrays and nested objects
+fn object_leaf<'v>(value: &'v Value, key: &str, options: &EncodeOptions) -> Option<Cow<'v, Value>> {
+    match value {
+        Value::Array(_) => None,
+        Value::Object(_) if non_finite_literal(value).is_none() => None,
+        _ => Some(transform_leaf(value, Some(key), options)),
+    }
+}
+
+/// Follow a chain of single-key objects below `key` under key folding
+///
+/// Returns the dotted key to write, borrowed when nothing was folded, the
+/// innermost field name (passed to the value transformer) and the value at
+/// the end of the chain. Segments that contain a `.` or are empty end the
+/// chain, so the path splits back apart unambiguously, and so does a path
+/// spelled the same as a key among `siblings`.
+fn fold_key<'v>(
+    key: &'v str,
+    mut value: &'v Value,
+    siblings: Option<&serde_json::Map<String, Value>>,
+    options: &EncodeOptions,
+) -> (Cow<'v, str>, &'v str, &'v Value) {
+    if !options.get_key_folding() || !is_path_segment(key, options) {
+        return (Cow::Borrowed(key), key, value);
+    }
+    let mut path = Cow::Borrowed(key);
+    let mut field = key;
+    while let Value::Object(obj) = value {
+        if obj.len() != 1 || non_finite_literal(value).is_some() {
+            break;
+        }
+        let Some((next, inner)) = obj.iter().next() else {
+            break;
+        };
+        if !is_path_segment(next, options) {
+            break;
+        }
+        let folded = format!("{path}.{next}");
+        if siblings.is_some_and(|siblings| siblings.contains_key(&folded)) {
+            break;
+        }
+        path = Cow::Owned(folded);
+        field = next;
+        value = inner;
+    }
+    (path, field, value)
+}
+
+pub(crate) fn is_path_segment(key: &str, options: &EncodeOptions) -> bool {
+    !key.is_empty() && !key.contains('.') && !key_needs_quotes(key, options)
+}
+
+/// Text between a key and its value or nested block
+pub(crate) fn key_separator(options: &EncodeOptions) -> &'static str {
+    match options.get_style() {
+        Style::Compact => ":",
+        Style::Pretty => ": ",
+    }
+}
+
+/// Text written for a null object value under the null policy
+pub(crate) fn null_value(options: &EncodeOptions) -> &'static str {
+    match options.get_null_policy() {
+        NullPolicy::EmitNullLiteral => "null",
+        NullPolicy::OmitKey | NullPolicy::EmptyCell => "",
+    }
+}
+
+/// The `[N]` part of an array header, or `[]` when lengths are omitted
+pub(crate) fn length_header(len: usize, options: &EncodeOptions) -> String {
+    if !options.get_emit_lengths() {
+        return "[]".to_string();
+    }
+    let delimiter = match options.get_delimiter() {
+        ',' => None,
+        delimiter => Some(delimiter).filter(|_| options.get_length_delimiter()),
+    };
+    let marker = options.length_marker.map(String::from).unwrap_or_default();
+    let delimiter = delimiter.map(String::from).unwrap_or_default();
+    format!("[{marker}{len}{delimiter}]")
+}
+
+/// The length part of a keyed table header, left out entirely when
+/// lengths are omitted (`items{sku,qty}:`)
+pub(crate) fn table_length_header(len: usize, options: &EncodeOptions) -> String {
+    if options.get_emit_lengths() {
+        length_header(len, options)
+    } else {
+        String::new()
+    }
+}
+
 fn is_primitive(value: &Value) -> bool {
     matches!(
         value,
         Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_)
-    )
+    ) || non_finite_literal(value).is_some()
 }
 
-fn check_uniform_objects(arr: &[Value]) -> Option<Vec<String>> {
-    if arr.is_empty() {
+/// Header keys for writing an array of objects as a table, or `None` when
+/// it should use the list format
+///
+/// The header is the union of the objects' keys in first-seen order. The
+/// array qualifies when every value is a primitive, it has at least
+/// `min_tabular_rows` rows and at most `max_tabular_columns` keys, and the
+/// share of objects holding every key is at least `tabular_uniformity`; the
+/// others get empty cells for their missing fields. Under key folding,
+/// nested objects are flattened into dotted columns (`user.id`).
+fn check_uniform_objects(arr: &[Value], options: &EncodeOptions) -> Option<Vec<String>> {
+    if arr.is_empty() || arr.len() < options.get_min_tabular_rows() {
         return None;
     }
 
-    // Get keys from first object (preserve order)
-    let first = arr[0].as_object()?;
-    let keys: Vec<String> = first.keys().cloned().collect();
-    if keys.is_empty() {
+    // Non-finite markers are objects too, but encode as primitives
+    if arr.iter().any(|item| non_finite_literal(item).is_some()) {
         return None;
     }
 
-    // Check if all objects have the same keys (order doesn't matter for this check)
-    for item in arr.iter().skip(1) {
-        let obj = item.as_object()?;
-        let item_keys: std::collections::HashSet<String> = obj.keys().cloned().collect();
-        let first_keys: std::collections::HashSet<String> = keys.iter().cloned().collect();
-        if item_keys != first_keys {
-            return None;
+    let mut keys: Vec<String> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut widths = Vec::with_capacity(arr.len());
+    for item in arr {
+        let mut columns = Vec::new();
+        column_paths(item.as_object()?, None, options, &mut columns)?;
+        let mut row = std::collections::HashSet::new();
+        for column in &columns {
+            // A literal `a.b` key next to a nested `a: {b}` has no single cell
+            if !row.insert(column.as_str()) {
+                return None;
+            }
         }
+        widths.push(columns.len());
+        for column in columns {
+            if seen.insert(column.clone()) {
+                keys.push(column);
+            }
+        }
+    }
+    if keys.is_empty() || keys.len() > options.get_max_tabular_columns() {
+        return None;
+    }
+
+    // Rows only hold columns from the union, so a full one has all of them
+    let complete = widths.iter().filter(|&&width| width == keys.len()).count();
+    if (complete as f64) < options.get_tabular_uniformity() * arr.len() as f64 {
+        return None;
     }
 
     Some(keys)
 }
 
+/// Header keys for writing an array of objects as a table at `indent_level`,
+/// or `None` when it should use the list format
+///
+/// With `optimize_for_tokens`, every array of objects that can be a table is
+/// rendered both ways, regardless of `min_tabular_rows` and
+/// `tabular_uniformity`, and the shorter layout wins.
+fn table_layout(
+    arr: &[Value],
+    indent_level: usize,
+    options: &EncodeOptions,
+) -> Result<Option<Vec<String>>, Error> {
+    if !options.get_optimize_for_tokens() {
+        return Ok(check_uniform_objects(arr, options));
+    }
+    let relaxed = EncodeOptions {
+        min_tabular_rows: Some(1),
+        tabular_uniformity: Some(0.0),
+        ..options.clone()
+    };
+    let Some(keys) = check_uniform_objects(arr, &relaxed) else {
+        return Ok(None);
+    };
+
+    // Both layouts start with the array header; the table adds `{fields}`
+    let style = table_style(arr, &keys, options);
+    let mut table = column_fields(&keys, &style.columns, options);
+    table.push_str("{}");
+    encode_tabular_array_rows(arr, &keys, &style, &mut table, indent_level, options)?;
+    let mut list = String::new();
+    encode_list_array(arr, &mut list, indent_level, options)?;
+    Ok((table.len() <= list.len()).then_some(keys))
+}
+
+/// Collect the table columns of one row, or `None` if it has a value that
+/// can't be a cell
+pub(crate) fn column_paths(
+    obj: &serde_json::Map<String, Value>,
+    prefix: Option<&str>,
+    options: &EncodeOptions,
+    columns: &mut Vec<String>,
+) -> Option<()> {
+    for (key, value) in obj {
+        let path = match prefix {
+            Some(prefix) => format!("{prefix}.{key}"),
+            None => key.clone(),
+        };
+        match value {
+            // A dotted column can't carry a quoted segment, and under key
+            // folding a literal dotted key would read as a path
+            _ if prefix.is_some() && !is_path_segment(key, options) => return None,
+            _ if options.get_key_folding() && key.contains('.') => return None,
+            _ if is_primitive(value) => columns.push(path),
+            Value::Object(nested)
+                if options.get_key_folding()
+                    && !nested.is_empty()
+                    && is_path_segment(key, options) =>
+            {
+                column_paths(nested, Some(&path), options, columns)?;
+            }
+            _ => return None,
+        }
+    }
+    Some(())
+}
+
+/// The value in a table row for a column, with the field name passed to the
+/// value transformer
+///
+/// Dotted columns from key folding are looked up through nested objects.
+fn table_cell<'v>(
+    obj: &'v serde_json::Map<String, Value>,
+    column: &'v str,
+    options: &EncodeOptions,
+) -> Option<(&'v str, &'v Value)> {
+    if let Some(value) = obj.get(column) {
+        return Some((column, value));
+    }
+    if !options.get_key_folding() {
+        return None;
+    }
+    let mut segments = column.split('.');
+    let mut value = obj.get(segments.next()?)?;
+    let mut field = column;
+    for segment in segments {
+        value = value.as_object()?.get(segment)?;
+        field = segment;
+    }
+    Some((field, value))
+}
+
+/// Whether some row of a table lacks one of its columns
+///
+/// Empty cells then stand for missing fields, so null cells are written
+/// as `null` to tell the two apart.
+fn is_sparse(arr: &[Value], keys: &[String], options: &EncodeOptions) -> bool {
+    arr.iter().filter_map(Value::as_object).any(|obj| {
+        keys.iter()
+            .any(|key| table_cell(obj, key, options).is_none())
+    })
+}
+
 fn encode_tabular_array_rows(
     arr: &[Value],
-    keys: Vec<String>,
-    output: &mut String,
+    keys: &[String],
+    style: &TableStyle,
+    output: &mut impl Sink,
     indent_level: usize,
     options: &EncodeOptions,
 ) -> Result<(), Error> {
-    let indent = options.get_indent();
-    let indent_str = " ".repeat(indent_level * indent);
-    let delimiter = options.get_delimiter();
+    let indent = options.get_indent_string();
+    let prefix = format!("{}{indent}", indent.repeat(indent_level));
+    let (shown, elided) = arr.split_at(arr.len().min(options.get_max_rows_per_table()));
+
+    if options.get_align_columns() {
+        for line in aligned_rows(shown, keys, style, options)? {
+            output.push_str(&prefix)?;
+            output.push_str(&line)?;
+            output.push('\n')?;
+            output.row();
+        }
+    } else {
+        // Write rows (header already written by caller)
+        encode_plain_rows(shown, keys, style, &prefix, output, options)?;
+    }
+
+    if !elided.is_empty() {
+        output.push_str(&prefix)?;
+        output.push_str(&elision_marker(elided.len()))?;
+        output.push('\n')?;
+    }
+    Ok(())
+}
+
+fn encode_plain_rows(
+    arr: &[Value],
+    keys: &[String],
+    style: &TableStyle,
+    prefix: &str,
+    output: &mut impl Sink,
+    options: &EncodeOptions,
+) -> Result<(), Error> {
+    #[cfg(feature = "parallel")]
+    if arr.len() >= PARALLEL_MIN_ROWS {
+        return parallel_table_rows(arr, keys, style, prefix, output, options);
+    }
+    for item in arr {
+        output.push_str(prefix)?;
+        encode_table_row(item, keys, style, output, options)?;
+        output.push('\n')?;
+        output.row();
+    }
+    Ok(())
+}
+
+/// The line standing in for the rows of a table left out by
+/// [`EncodeOptions::max_rows_per_table`]
+pub(crate) fn elision_marker(count: usize) -> String {
+    let noun = if count == 1 { "row" } else { "rows" };
+    format!("… ({count} more {noun})")
+}
+
+/// Write one table row's cells, without its indentation or line break
+pub(crate) fn encode_table_row(
+    item: &Value,
+    keys: &[String],
+    style: &TableStyle,
+    output: &mut impl Sink,
+    options: &EncodeOptions,
+) -> Result<(), Error> {
+    let obj = item
+        .as_object()
+        .ok_or_else(|| Error::Serialization("Expected object in tabular array".to_string()))?;
+
+    for (i, key) in keys.iter().enumerate() {
+        if i > 0 {
+            output.push(options.get_delimiter())?;
+        }
+        let value = table_cell(obj, key, options)
+            .map(|(field, value)| transform_leaf(value, Some(field), options));
+        style.cell(i, value.as_deref(), output, options)?;
+    }
+    Ok(())
+}
+
+/// Fewest rows a table needs before its rows are formatted in parallel
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_ROWS: usize = 4096;
+
+/// Rows formatted together by one rayon task
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_ROWS: usize = 1024;
+
+/// Format table rows in chunks on the rayon pool and write them in order
+///
+/// Rows are rendered a batch of chunks at a time, one chunk per thread, so
+/// at most one batch of text is held before it is written.
+#[cfg(feature = "parallel")]
+fn parallel_table_rows(
+    arr: &[Value],
+    keys: &[String],
+    style: &TableStyle,
+    prefix: &str,
+    output: &mut impl Sink,
+    options: &EncodeOptions,
+) -> Result<(), Error> {
+    use rayon::prelude::*;
 
-    // Write rows (header already written by caller)
+    let batch_rows = PARALLEL_CHUNK_ROWS * rayon::current_num_threads();
+    for batch in arr.chunks(batch_rows) {
+        let chunks = batch
+            .par_chunks(PARALLEL_CHUNK_ROWS)
+            .map(|chunk| {
+                let mut text = String::new();
+                for item in chunk {
+                    text.push_str(prefix);
+                    encode_table_row(item, keys, style, &mut text, options)?;
+                    text.push('\n');
+                }
+                Ok(text)
+            })
+            .collect::<Result<Vec<String>, Error>>()?;
+        for (chunk, items) in chunks.iter().zip(batch.chunks(PARALLEL_CHUNK_ROWS)) {
+            output.push_str(chunk)?;
+            for _ in items {
+                output.row();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Render table rows with each cell padded after its delimiter, so every
+/// column starts at the same offset
+///
+/// The whole table is rendered before any of it is written, since column
+/// widths depend on every row.
+fn aligned_rows(
+    arr: &[Value],
+    keys: &[String],
+    style: &TableStyle,
+    options: &EncodeOptions,
+) -> Result<Vec<String>, Error> {
+    let mut rows = Vec::with_capacity(arr.len());
     for item in arr {
-        output.push_str(&indent_str);
-        output.push_str(&" ".repeat(indent));
         let obj = item
             .as_object()
             .ok_or_else(|| Error::Serialization("Expected object in tabular array".to_string()))?;
+        let mut cells = Vec::with_capacity(keys.len());
+        for (i, key) in keys.iter().enumerate() {
+            let mut cell = String::new();
+            let value = table_cell(obj, key, options)
+                .map(|(field, value)| transform_leaf(value, Some(field), options));
+            style.cell(i, value.as_deref(), &mut cell, options)?;
+            cells.push(cell);
+        }
+        rows.push(cells);
+    }
 
-        let mut first = true;
-        for key in &keys {
-            if !first {
-                output.push(delimiter);
-            }
-            let value = obj
-                .get(key)
-                .ok_or_else(|| Error::Serialization(format!("Missing key: {key}")))?;
-            encode_primitive_value(value, output, delimiter)?;
-            first = false;
+    let mut widths = vec![0; keys.len()];
+    for cells in &rows {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            *width = (*width).max(cell.chars().count());
         }
-        output.push('\n');
     }
 
-    Ok(())
+    let delimiter = options.get_delimiter();
+    Ok(rows
+        .into_iter()
+        .map(|cells| {
+            let mut line = String::new();
+            let last = cells.len().saturating_sub(1);
+            for (i, cell) in cells.iter().enumerate() {
+                line.push_str(cell);
+                if i < last {
+                    line.push(delimiter);
+                    line.push_str(&" ".repeat(widths[i] - cell.chars().count()));
+                }
+            }
+            line
+        })
+        .collect())
 }
 
-fn encode_primitive_value(
+/// Write a leaf value outside a table, such as an array item, spelling out nulls
+pub(crate) fn encode_primitive_value(
     value: &Value,
-    output: &mut String,
-    delimiter: char,
+    output: &mut impl Sink,
+    options: &EncodeOptions,
+    key: Option<&str>,
+    slot: Slot,
+) -> Result<(), Error> {
+    let value = transform_leaf(value, key, options);
+    if value.is_null() {
+        output.push_str("null")?;
+        return Ok(());
+    }
+    encode_scalar(&value, output, options, slot)
+}
+
+/// Write an already transformed leaf value
+pub(crate) fn encode_scalar(
+    value: &Value,
+    output: &mut impl Sink,
+    options: &EncodeOptions,
+    slot: Slot,
 ) -> Result<(), Error> {
+    if let Some(literal) = non_finite_literal(value) {
+        match options.get_nan_policy() {
+            NanPolicy::Null => output.push_str("null")?,
+            NanPolicy::Error => return Err(non_finite_error(literal)),
+            NanPolicy::Token => output.push_str(literal)?,
+        }
+        return Ok(());
+    }
     match value {
-        Value::Null => {}
+        Value::Null => {
+            // Null values are typically omitted or represented as empty
+        }
         Value::Bool(b) => {
-            output.push_str(if *b { "true" } else { "false" });
+            output.push_str(if *b { "true" } else { "false" })?;
         }
         Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                output.push_str(&i.to_string());
-            } else if let Some(f) = n.as_f64() {
-                output.push_str(&f.to_string());
-            } else {
-                return Err(Error::Serialization("Invalid number".to_string()));
-            }
+            output.push_str(&format_number(n, options)?)?;
         }
         Value::String(s) => {
-            encode_string(s, output, delimiter);
+            encode_string(s, output, options, slot)?;
         }
         _ => {
             return Err(Error::Serialization(
@@ -225,164 +1320,226 @@ fn encode_primitive_value(
 
 fn encode_inline_array(
     arr: &[Value],
-    output: &mut String,
+    output: &mut impl Sink,
+    indent_level: usize,
     options: &EncodeOptions,
 ) -> Result<(), Error> {
-    let length_marker = options
-        .length_marker
-        .map(|m| format!("{m}"))
-        .unwrap_or_default();
-    output.push_str(&format!("[{}{}]:", length_marker, arr.len()));
+    output.push_str(&format!("{}:", length_header(arr.len(), options)))?;
 
-    let delimiter = options.get_delimiter();
-    let mut first = true;
-    for item in arr {
-        if !first {
-            output.push(delimiter);
-        }
-        match item {
-            Value::Null => {}
-            Value::Bool(b) => {
-                output.push_str(if *b { "true" } else { "false" });
-            }
-            Value::Number(n) => {
-                if let Some(i) = n.as_i64() {
-                    output.push_str(&i.to_string());
-                } else if let Some(f) = n.as_f64() {
-                    output.push_str(&f.to_string());
-                }
-            }
-            Value::String(s) => {
-                encode_string(s, output, delimiter);
-            }
-            _ => {
-                return Err(Error::Serialization(
-                    "Non-primitive in inline array".to_string(),
-                ));
-            }
+    let line_start = indent_level * options.get_indent_string().chars().count();
+    match inline_items(arr, None, line_start, options)? {
+        Some(items) => output.push_str(&items)?,
+        None => {
+            output.push('\n')?;
+            encode_list_array(arr, output, indent_level, options)?;
         }
-        first = false;
     }
 
     Ok(())
 }
 
+/// Render a primitive array's items for the inline form, led by the space
+/// [`EncodeOptions::inline_array_space`] puts after the colon, or `None` when the
+/// array is over `max_inline_items` or its line, starting at column
+/// `line_start` before the `[N]:` header, would be over `max_line_width`
+pub(crate) fn inline_items(
+    arr: &[Value],
+    key: Option<&str>,
+    line_start: usize,
+    options: &EncodeOptions,
+) -> Result<Option<String>, Error> {
+    if arr.len() > options.get_max_inline_items() {
+        return Ok(None);
+    }
+    let delimiter = options.get_delimiter();
+    let mut items = String::new();
+    if options.get_inline_array_space() {
+        items.push(' ');
+    }
+    for (i, item) in arr.iter().enumerate() {
+        if i > 0 {
+            items.push(delimiter);
+        }
+        encode_primitive_value(item, &mut items, options, key, Slot::Cell)?;
+    }
+    let header_width = length_header(arr.len(), options).len() + 1;
+    let width = line_start + header_width + items.chars().count();
+    Ok((width <= options.get_max_line_width()).then_some(items))
+}
+
 fn encode_list_array(
     arr: &[Value],
-    output: &mut String,
+    output: &mut impl Sink,
     indent_level: usize,
     options: &EncodeOptions,
 ) -> Result<(), Error> {
-    let indent = options.get_indent();
-    let indent_str = " ".repeat(indent_level * indent);
+    let indent = options.get_indent_string();
+    let indent_str = indent.repeat(indent_level);
 
     for item in arr {
-        output.push_str(&indent_str);
-        output.push_str(&" ".repeat(indent));
-        output.push_str("- ");
-        // For objects in list arrays, encode them inline as key: value
+        output.push_str(&indent_str)?;
+        output.push_str(&indent)?;
+        output.push_str("- ")?;
         match item {
-            Value::Object(obj) => {
-                let mut first = true;
-                for (key, val) in obj {
-                    if !first {
-                        output.push(' ');
-                    }
-                    output.push_str(key);
-                    output.push_str(": ");
-                    encode_primitive_value(val, output, options.get_delimiter())?;
-                    first = false;
-                }
+            Value::Object(obj) if obj.is_empty() && options.get_roundtrip_safe() => {
+                output.push_str("{}")?;
+            }
+            Value::Object(obj) if non_finite_literal(item).is_none() => {
+                encode_object(obj, output, indent_level + 2, true, options)?;
             }
             _ => {
                 encode_value(item, output, indent_level + 1, options)?;
             }
         }
-        output.push('\n');
+        output.push('\n')?;
+        output.row();
     }
 
     Ok(())
 }
 
+/// Encode an object's fields, one per line
+///
+/// `list_item` means the first field goes on a `- ` marker line already
+/// written, and the rest are indented one level past the marker. That first
+/// field keeps the space after its colon in every style: the decoder only
+/// reads `- key: value` as an object, so `- http://x` stays a string.
 fn encode_object(
     obj: &serde_json::Map<String, Value>,
-    output: &mut String,
+    output: &mut impl Sink,
     indent_level: usize,
+    list_item: bool,
     options: &EncodeOptions,
 ) -> Result<(), Error> {
-    if obj.is_empty() {
-        return Ok(());
-    }
-
-    let indent = options.get_indent();
-    let indent_str = " ".repeat(indent_level * indent);
-
     let mut first = true;
     for (key, value) in obj {
-        if !first {
-            output.push('\n');
+        if indent_level == 0 && !list_item {
+            output.section(key);
         }
-        output.push_str(&indent_str);
-        output.push_str(key);
+        let placement = match list_item && first {
+            true => Placement::Marker,
+            false => Placement::Line { first },
+        };
+        if encode_entry(
+            key,
+            value,
+            Some(obj),
+            output,
+            indent_level,
+            placement,
+            options,
+        )? {
+            first = false;
+        }
+    }
+    Ok(())
+}
 
-        match value {
-            Value::Array(arr) => {
-                // For arrays, check the format and encode appropriately
-                if arr.is_empty() {
-                    output.push_str("[0]:");
-                } else if let Some(keys) = check_uniform_objects(arr) {
-                    // Tabular array - output on same line: key[N]{...}:
-                    let length_marker = options
-                        .length_marker
-                        .map(|m| format!("{m}"))
-                        .unwrap_or_default();
-                    output.push_str(&format!("[{}{}]", length_marker, arr.len()));
-                    output.push('{');
-                    output.push_str(&keys.join(&options.get_delimiter().to_string()));
-                    output.push_str("}:\n");
-                    // Now output the rows
-                    encode_tabular_array_rows(arr, keys, output, indent_level, options)?;
-                } else if arr.iter().all(is_primitive) {
-                    // Inline array - output on same line: key[N]: value1,value2
-                    let length_marker = options
-                        .length_marker
-                        .map(|m| format!("{m}"))
-                        .unwrap_or_default();
-                    output.push_str(&format!("[{}{}]:", length_marker, arr.len()));
-                    let delimiter = options.get_delimiter();
-                    let mut first = true;
-                    for item in arr {
-                        if !first {
-                            output.push(delimiter);
-                        }
-                        encode_primitive_value(item, output, delimiter)?;
-                        first = false;
-                    }
+/// Where an object entry goes relative to the text already written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Placement {
+    /// On a `- ` list marker line already written, so without indentation
+    Marker,
+    /// On a line of its own, after a newline unless it is the first entry
+    Line { first: bool },
+}
+
+/// Encode one `key: value` entry of an object at `indent_level` and return
+/// whether it was written
+///
+/// Null entries left out under [`NullPolicy::OmitKey`] write nothing, not
+/// even the newline before them. `siblings` is the object holding the
+/// entry, when known, so key folding can avoid a path that reads like one
+/// of its other keys.
+pub(crate) fn encode_entry(
+    key: &str,
+    value: &Value,
+    siblings: Option<&serde_json::Map<String, Value>>,
+    output: &mut impl Sink,
+    indent_level: usize,
+    placement: Placement,
+    options: &EncodeOptions,
+) -> Result<bool, Error> {
+    let indent_str = options.get_indent_string().repeat(indent_level);
+    let (path, key, value) = fold_key(key, value, siblings, options);
+    let leaf = object_leaf(value, key, options);
+    if leaf.as_deref().is_some_and(Value::is_null)
+        && options.get_null_policy() == NullPolicy::OmitKey
+    {
+        return Ok(false);
+    }
+    let on_marker = placement == Placement::Marker;
+    if placement == (Placement::Line { first: false }) {
+        output.push('\n')?;
+    }
+    if !on_marker {
+        output.push_str(&indent_str)?;
+    }
+    // A folded path is made of segments that need no quotes
+    let path = match path {
+        Cow::Borrowed(key) => format_key(key, options),
+        path => path,
+    };
+    output.push_str(&path)?;
+
+    match value {
+        Value::Array(arr) => {
+            // For arrays, check the format and encode appropriately
+            if arr.is_empty() {
+                output.push_str(&format!("{}:", length_header(0, options)))?;
+            } else if let Some(keys) = table_layout(arr, indent_level, options)? {
+                // Tabular array - output on same line: key[N]{...}:
+                let style = table_style(arr, &keys, options);
+                output.push_str(&table_length_header(arr.len(), options))?;
+                output.push('{')?;
+                output.push_str(&column_fields(&keys, &style.columns, options))?;
+                output.push_str("}:\n")?;
+                // Now output the rows
+                encode_tabular_array_rows(arr, &keys, &style, output, indent_level, options)?;
+            } else {
+                // Inline array on the same line (key[N]: value1,value2),
+                // or a list array below it
+                let inline = if arr.iter().all(is_primitive) {
+                    inline_items(arr, Some(key), indent_str.len() + path.len(), options)?
                 } else {
-                    // List array - output on same line: key[N]:
-                    let length_marker = options
-                        .length_marker
-                        .map(|m| format!("{m}"))
-                        .unwrap_or_default();
-                    output.push_str(&format!("[{}{}]:", length_marker, arr.len()));
-                    output.push('\n');
-                    encode_list_array(arr, output, indent_level, options)?;
+                    None
+                };
+                output.push_str(&format!("{}:", length_header(arr.len(), options)))?;
+                match inline {
+                    Some(items) => output.push_str(&items)?,
+                    None => {
+                        output.push('\n')?;
+                        encode_list_array(arr, output, indent_level, options)?;
+                    }
                 }
             }
-            Value::Object(_) => {
-                output.push_str(": ");
-                output.push('\n');
+        }
+        Value::Object(obj) if non_finite_literal(value).is_none() => {
+            output.push_str(key_separator(options))?;
+            if obj.is_empty() {
+                if options.get_roundtrip_safe() {
+                    output.push_str("{}")?;
+                }
+            } else {
+                output.push('\n')?;
                 encode_value(value, output, indent_level + 1, options)?;
             }
-            _ => {
-                output.push_str(": ");
-                encode_value(value, output, indent_level, options)?;
+        }
+        _ => {
+            output.push_str(if on_marker {
+                ": "
+            } else {
+                key_separator(options)
+            })?;
+            let value = leaf.unwrap_or(Cow::Borrowed(value));
+            match block_string(&value, indent_level + 1, options) {
+                Some(block) => output.push_str(&block)?,
+                None if value.is_null() => output.push_str(null_value(options))?,
+                None => encode_scalar(&value, output, options, Slot::Value)?,
             }
         }
-        first = false;
     }
-
-    Ok(())
+    Ok(true)
 }
 
 /// Encode a JSON value to TOON format and write it to a writer
@@ -420,467 +1577,20 @@ pub fn encode_stream<W: Write>(
 ) -> Result<(), Error> {
     let default_opts = EncodeOptions::default();
     let opts = options.unwrap_or(&default_opts);
-    encode_value_to_writer(value, writer, 0, opts)?;
-    writer.flush().map_err(|e| Error::Io(e.to_string()))?;
-    Ok(())
-}
-
-fn encode_value_to_writer<W: Write>(
-    value: &Value,
-    writer: &mut W,
-    indent_level: usize,
-    options: &EncodeOptions,
-) -> Result<(), Error> {
-    match value {
-        Value::Null => {
-            // Null values are typically omitted or represented as empty
-        }
-        Value::Bool(b) => {
-            let s = if *b { "true" } else { "false" };
-            writer
-                .write_all(s.as_bytes())
-                .map_err(|e| Error::Io(e.to_string()))?;
-        }
-        Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                let s = i.to_string();
-                writer
-                    .write_all(s.as_bytes())
-                    .map_err(|e| Error::Io(e.to_string()))?;
-            } else if let Some(f) = n.as_f64() {
-                let s = f.to_string();
-                writer
-                    .write_all(s.as_bytes())
-                    .map_err(|e| Error::Io(e.to_string()))?;
-            } else {
-                return Err(Error::Serialization("Invalid number".to_string()));
-            }
-        }
-        Value::String(s) => {
-            encode_string_to_writer(s, writer, options.get_delimiter())?;
-        }
-        Value::Array(arr) => {
-            encode_array_to_writer(arr, writer, indent_level, options)?;
-        }
-        Value::Object(obj) => {
-            encode_object_to_writer(obj, writer, indent_level, options)?;
-        }
-    }
-    Ok(())
-}
-
-fn encode_string_to_writer<W: Write>(
-    s: &str,
-    writer: &mut W,
-    delimiter: char,
-) -> Result<(), Error> {
-    // Check if we need to quote the string
-    let needs_quoting = s.contains(delimiter)
-        || s.contains(' ')
-        || s.contains('\n')
-        || s.contains('\t')
-        || s == "true"
-        || s == "false"
-        || s == "null"
-        || s.parse::<f64>().is_ok();
-
-    if needs_quoting {
-        writer
-            .write_all(b"\"")
-            .map_err(|e| Error::Io(e.to_string()))?;
-        for ch in s.chars() {
-            match ch {
-                '"' => writer
-                    .write_all(b"\\\"")
-                    .map_err(|e| Error::Io(e.to_string()))?,
-                '\\' => writer
-                    .write_all(b"\\\\")
-                    .map_err(|e| Error::Io(e.to_string()))?,
-                '\n' => writer
-                    .write_all(b"\\n")
-                    .map_err(|e| Error::Io(e.to_string()))?,
-                '\r' => writer
-                    .write_all(b"\\r")
-                    .map_err(|e| Error::Io(e.to_string()))?,
-                '\t' => writer
-                    .write_all(b"\\t")
-                    .map_err(|e| Error::Io(e.to_string()))?,
-                _ => {
-                    let mut buf = [0; 4];
-                    let bytes = ch.encode_utf8(&mut buf).as_bytes();
-                    writer
-                        .write_all(bytes)
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                }
-            }
-        }
-        writer
-            .write_all(b"\"")
-            .map_err(|e| Error::Io(e.to_string()))?;
-    } else {
-        writer
-            .write_all(s.as_bytes())
-            .map_err(|e| Error::Io(e.to_string()))?;
-    }
-    Ok(())
-}
-
-fn encode_array_to_writer<W: Write>(
-    arr: &[Value],
-    writer: &mut W,
-    indent_level: usize,
-    options: &EncodeOptions,
-) -> Result<(), Error> {
-    if arr.is_empty() {
-        writer
-            .write_all(b"[0]:")
-            .map_err(|e| Error::Io(e.to_string()))?;
-        return Ok(());
-    }
-
-    // Check if array contains uniform objects (tabular format)
-    if let Some(keys) = check_uniform_objects(arr) {
-        // For root-level arrays, include the header
-        let length_marker = options
-            .length_marker
-            .map(|m| format!("{m}"))
-            .unwrap_or_default();
-        let header = format!("[{}{}]", length_marker, arr.len());
-        writer
-            .write_all(header.as_bytes())
-            .map_err(|e| Error::Io(e.to_string()))?;
-        writer
-            .write_all(b"{")
-            .map_err(|e| Error::Io(e.to_string()))?;
-        let keys_str = keys.join(&options.get_delimiter().to_string());
-        writer
-            .write_all(keys_str.as_bytes())
-            .map_err(|e| Error::Io(e.to_string()))?;
-        writer
-            .write_all(b"}:\n")
-            .map_err(|e| Error::Io(e.to_string()))?;
-        encode_tabular_array_rows_to_writer(arr, keys, writer, indent_level, options)?;
-        return Ok(());
-    }
-
-    // Check if all elements are primitives (inline format)
-    if arr.iter().all(is_primitive) {
-        encode_inline_array_to_writer(arr, writer, options)?;
-        return Ok(());
-    }
-
-    // Otherwise, use list format
-    encode_list_array_to_writer(arr, writer, indent_level, options)?;
-    Ok(())
-}
-
-fn encode_tabular_array_rows_to_writer<W: Write>(
-    arr: &[Value],
-    keys: Vec<String>,
-    writer: &mut W,
-    indent_level: usize,
-    options: &EncodeOptions,
-) -> Result<(), Error> {
-    let indent = options.get_indent();
-    let indent_str = " ".repeat(indent_level * indent);
-    let delimiter = options.get_delimiter();
-
-    // Write rows (header already written by caller)
-    for item in arr {
-        writer
-            .write_all(indent_str.as_bytes())
-            .map_err(|e| Error::Io(e.to_string()))?;
-        writer
-            .write_all(" ".repeat(indent).as_bytes())
-            .map_err(|e| Error::Io(e.to_string()))?;
-        let obj = item
-            .as_object()
-            .ok_or_else(|| Error::Serialization("Expected object in tabular array".to_string()))?;
-
-        let mut first = true;
-        for key in &keys {
-            if !first {
-                let delim_bytes = [delimiter as u8];
-                writer
-                    .write_all(&delim_bytes)
-                    .map_err(|e| Error::Io(e.to_string()))?;
-            }
-            let value = obj
-                .get(key)
-                .ok_or_else(|| Error::Serialization(format!("Missing key: {key}")))?;
-            encode_primitive_value_to_writer(value, writer, delimiter)?;
-            first = false;
-        }
-        writer
-            .write_all(b"\n")
-            .map_err(|e| Error::Io(e.to_string()))?;
-    }
-
-    Ok(())
-}
-
-fn encode_primitive_value_to_writer<W: Write>(
-    value: &Value,
-    writer: &mut W,
-    delimiter: char,
-) -> Result<(), Error> {
-    match value {
-        Value::Null => {}
-        Value::Bool(b) => {
-            let s = if *b { "true" } else { "false" };
-            writer
-                .write_all(s.as_bytes())
-                .map_err(|e| Error::Io(e.to_string()))?;
-        }
-        Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                let s = i.to_string();
-                writer
-                    .write_all(s.as_bytes())
-                    .map_err(|e| Error::Io(e.to_string()))?;
-            } else if let Some(f) = n.as_f64() {
-                let s = f.to_string();
-                writer
-                    .write_all(s.as_bytes())
-                    .map_err(|e| Error::Io(e.to_string()))?;
-            } else {
-                return Err(Error::Serialization("Invalid number".to_string()));
-            }
-        }
-        Value::String(s) => {
-            encode_string_to_writer(s, writer, delimiter)?;
-        }
-        _ => {
-            return Err(Error::Serialization(
-                "Non-primitive value in tabular array".to_string(),
-            ));
-        }
-    }
-    Ok(())
-}
-
-fn encode_inline_array_to_writer<W: Write>(
-    arr: &[Value],
-    writer: &mut W,
-    options: &EncodeOptions,
-) -> Result<(), Error> {
-    let length_marker = options
-        .length_marker
-        .map(|m| format!("{m}"))
-        .unwrap_or_default();
-    let header = format!("[{}{}]:", length_marker, arr.len());
-    writer
-        .write_all(header.as_bytes())
-        .map_err(|e| Error::Io(e.to_string()))?;
-
-    let delimiter = options.get_delimiter();
-    let mut first = true;
-    for item in arr {
-        if !first {
-            let delim_bytes = [delimiter as u8];
-            writer
-                .write_all(&delim_bytes)
-                .map_err(|e| Error::Io(e.to_string()))?;
-        }
-        match item {
-            Value::Null => {}
-            Value::Bool(b) => {
-                let s = if *b { "true" } else { "false" };
-                writer
-                    .write_all(s.as_bytes())
-                    .map_err(|e| Error::Io(e.to_string()))?;
-            }
-            Value::Number(n) => {
-                if let Some(i) = n.as_i64() {
-                    let s = i.to_string();
-                    writer
-                        .write_all(s.as_bytes())
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                } else if let Some(f) = n.as_f64() {
-                    let s = f.to_string();
-                    writer
-                        .write_all(s.as_bytes())
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                }
-            }
-            Value::String(s) => {
-                encode_string_to_writer(s, writer, delimiter)?;
-            }
-            _ => {
-                return Err(Error::Serialization(
-                    "Non-primitive in inline array".to_string(),
-                ));
-            }
-        }
-        first = false;
-    }
-
-    Ok(())
-}
-
-fn encode_list_array_to_writer<W: Write>(
-    arr: &[Value],
-    writer: &mut W,
-    indent_level: usize,
-    options: &EncodeOptions,
-) -> Result<(), Error> {
-    let indent = options.get_indent();
-    let indent_str = " ".repeat(indent_level * indent);
-
-    for item in arr {
-        writer
-            .write_all(indent_str.as_bytes())
-            .map_err(|e| Error::Io(e.to_string()))?;
-        writer
-            .write_all(" ".repeat(indent).as_bytes())
-            .map_err(|e| Error::Io(e.to_string()))?;
-        writer
-            .write_all(b"- ")
-            .map_err(|e| Error::Io(e.to_string()))?;
-        // For objects in list arrays, encode them inline as key: value
-        match item {
-            Value::Object(obj) => {
-                let mut first = true;
-                for (key, val) in obj {
-                    if !first {
-                        writer
-                            .write_all(b" ")
-                            .map_err(|e| Error::Io(e.to_string()))?;
-                    }
-                    writer
-                        .write_all(key.as_bytes())
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                    writer
-                        .write_all(b": ")
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                    encode_primitive_value_to_writer(val, writer, options.get_delimiter())?;
-                    first = false;
-                }
-            }
-            _ => {
-                encode_value_to_writer(item, writer, indent_level + 1, options)?;
-            }
-        }
-        writer
-            .write_all(b"\n")
-            .map_err(|e| Error::Io(e.to_string()))?;
-    }
-
-    Ok(())
-}
-
-fn encode_object_to_writer<W: Write>(
-    obj: &serde_json::Map<String, Value>,
-    writer: &mut W,
-    indent_level: usize,
-    options: &EncodeOptions,
-) -> Result<(), Error> {
-    if obj.is_empty() {
-        return Ok(());
-    }
-
-    let indent = options.get_indent();
-    let indent_str = " ".repeat(indent_level * indent);
-
-    let mut first = true;
-    for (key, value) in obj {
-        if !first {
-            writer
-                .write_all(b"\n")
-                .map_err(|e| Error::Io(e.to_string()))?;
-        }
-        writer
-            .write_all(indent_str.as_bytes())
-            .map_err(|e| Error::Io(e.to_string()))?;
-        writer
-            .write_all(key.as_bytes())
-            .map_err(|e| Error::Io(e.to_string()))?;
-
-        match value {
-            Value::Array(arr) => {
-                // For arrays, check the format and encode appropriately
-                if arr.is_empty() {
-                    writer
-                        .write_all(b"[0]:")
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                } else if let Some(keys) = check_uniform_objects(arr) {
-                    // Tabular array - output on same line: key[N]{...}:
-                    let length_marker = options
-                        .length_marker
-                        .map(|m| format!("{m}"))
-                        .unwrap_or_default();
-                    let header = format!("[{}{}]", length_marker, arr.len());
-                    writer
-                        .write_all(header.as_bytes())
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                    writer
-                        .write_all(b"{")
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                    let keys_str = keys.join(&options.get_delimiter().to_string());
-                    writer
-                        .write_all(keys_str.as_bytes())
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                    writer
-                        .write_all(b"}:\n")
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                    // Now output the rows
-                    encode_tabular_array_rows_to_writer(arr, keys, writer, indent_level, options)?;
-                } else if arr.iter().all(is_primitive) {
-                    // Inline array - output on same line: key[N]: value1,value2
-                    let length_marker = options
-                        .length_marker
-                        .map(|m| format!("{m}"))
-                        .unwrap_or_default();
-                    let header = format!("[{}{}]:", length_marker, arr.len());
-                    writer
-                        .write_all(header.as_bytes())
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                    let delimiter = options.get_delimiter();
-                    let mut first = true;
-                    for item in arr {
-                        if !first {
-                            let delim_bytes = [delimiter as u8];
-                            writer
-                                .write_all(&delim_bytes)
-                                .map_err(|e| Error::Io(e.to_string()))?;
-                        }
-                        encode_primitive_value_to_writer(item, writer, delimiter)?;
-                        first = false;
-                    }
-                } else {
-                    // List array - output on same line: key[N]:
-                    let length_marker = options
-                        .length_marker
-                        .map(|m| format!("{m}"))
-                        .unwrap_or_default();
-                    let header = format!("[{}{}]:", length_marker, arr.len());
-                    writer
-                        .write_all(header.as_bytes())
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                    writer
-                        .write_all(b"\n")
-                        .map_err(|e| Error::Io(e.to_string()))?;
-                    encode_list_array_to_writer(arr, writer, indent_level, options)?;
-                }
-            }
-            Value::Object(_) => {
-                writer
-                    .write_all(b": ")
-                    .map_err(|e| Error::Io(e.to_string()))?;
-                writer
-                    .write_all(b"\n")
-                    .map_err(|e| Error::Io(e.to_string()))?;
-                encode_value_to_writer(value, writer, indent_level + 1, options)?;
-            }
-            _ => {
-                writer
-                    .write_all(b": ")
-                    .map_err(|e| Error::Io(e.to_string()))?;
-                encode_value_to_writer(value, writer, indent_level, options)?;
-            }
+    match &opts.progress {
+        Some(callback) => {
+            let mut sink = ProgressSink {
+                inner: WriteSink(&mut *writer),
+                callback: callback.as_ref(),
+                bytes: 0,
+                rows: 0,
+                section: None,
+            };
+            encode_root(value, &mut sink, opts)?;
+            sink.report();
         }
-        first = false;
+        None => encode_root(value, &mut WriteSink(writer), opts)?,
     }
-
+    writer.flush().map_err(|e| Error::Io(e.to_string()))?;
     Ok(())
 }
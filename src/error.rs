@@ -1,13 +1,22 @@
 //! Error types for TOON encoding and decoding
 
+use serde_json::{json, Map, Value};
 use thiserror::Error;
 
 /// Errors that can occur during TOON encoding or decoding
 #[derive(Error, Debug, PartialEq)]
 pub enum Error {
     /// Parse error with position information
-    #[error("Parse error at position {position}: {message}")]
-    Parse { position: usize, message: String },
+    ///
+    /// `position` is a byte offset into the input; `line` and `column` are
+    /// 1-based, with the column counted in characters.
+    #[error("Parse error at line {line}, column {column}: {message}")]
+    Parse {
+        position: usize,
+        line: usize,
+        column: usize,
+        message: String,
+    },
 
     /// Syntax error
     #[error("Syntax error: {0}")]
@@ -49,16 +58,52 @@ pub enum Error {
     #[error("Serialization error: {0}")]
     Serialization(String),
 
-    /// Deserialization error
-    #[error("Deserialization error: {0}")]
-    Deserialization(String),
+    /// Deserialization error, with the field path and source line when known
+    #[error("Deserialization error{}: {message}", location(.path, .line))]
+    Deserialization {
+        message: String,
+        path: Option<String>,
+        line: Option<usize>,
+    },
+}
+
+/// An error's message and the line of the input it points at
+pub(crate) struct Located<'a> {
+    pub(crate) message: String,
+    /// Text of the line, without its line break
+    pub(crate) source: &'a str,
+    /// 1-based line number
+    pub(crate) line: usize,
+    /// 1-based column, in characters
+    pub(crate) column: usize,
+}
+
+/// Spaces up to `column` of `source`, keeping its tabs so a caret after
+/// them lines up with the column
+pub(crate) fn caret_padding(source: &str, column: usize) -> String {
+    source
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+fn location(path: &Option<String>, line: &Option<usize>) -> String {
+    match (path, line) {
+        (Some(path), Some(line)) => format!(" at `{path}` (line {line})"),
+        (Some(path), None) => format!(" at `{path}`"),
+        (None, Some(line)) => format!(" (line {line})"),
+        (None, None) => String::new(),
+    }
 }
 
 impl Error {
     /// Create a parse error
-    pub fn parse(position: usize, message: impl Into<String>) -> Self {
+    pub fn parse(position: usize, line: usize, column: usize, message: impl Into<String>) -> Self {
         Self::Parse {
             position,
+            line,
+            column,
             message: message.into(),
         }
     }
@@ -67,4 +112,272 @@ impl Error {
     pub fn syntax(message: impl Into<String>) -> Self {
         Self::Syntax(message.into())
     }
+
+    /// Render the error with the offending source line and a caret under
+    /// the failure point, in the style of rustc diagnostics
+    ///
+    /// `input` must be the document that produced the error. Errors without
+    /// location information render as a single `error:` line.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use toon_rust::decode;
+    ///
+    /// let input = "user:\n  id 1";
+    /// let err = decode(input, None).unwrap_err();
+    /// assert_eq!(
+    ///     err.render(input),
+    ///     "error: Expected ':' after key 'id'\n --> line 2, column 6\n  |\n2 |   id 1\n  |      ^\n"
+    /// );
+    /// ```
+    pub fn render(&self, input: &str) -> String {
+        let Some(Located {
+            message,
+            source,
+            line,
+            column,
+        }) = self.locate(input)
+        else {
+            return format!("error: {self}\n");
+        };
+        let gutter = " ".repeat(line.to_string().len());
+        let padding = caret_padding(source, column);
+        format!(
+            "error: {message}\n{gutter}--> line {line}, column {column}\n{gutter} |\n{line} | {source}\n{gutter} | {padding}^\n"
+        )
+    }
+
+    /// Where in `input` the error happened, for errors that know
+    pub(crate) fn locate<'a>(&self, input: &'a str) -> Option<Located<'a>> {
+        let (message, line, column) = match self {
+            Self::Parse {
+                line,
+                column,
+                message,
+                ..
+            } => (message.clone(), *line, Some(*column)),
+            Self::Deserialization {
+                line: Some(line), ..
+            } => (self.to_string(), *line, None),
+            _ => return None,
+        };
+        let source = input.lines().nth(line.saturating_sub(1))?;
+        // Without a column, point at the start of the line's content
+        let column = column
+            .unwrap_or_else(|| source.chars().take_while(|ch| ch.is_whitespace()).count() + 1);
+        Some(Located {
+            message,
+            source,
+            line,
+            column,
+        })
+    }
+
+    /// The error as a JSON object, for services and tools that report
+    /// diagnostics rather than print them
+    ///
+    /// The object has the error's `code` (`"parse"`, `"length_mismatch"`
+    /// and so on), its `message`, and a `span` with the byte `offset`,
+    /// `line` and `column` of the failure when they are known. `path`,
+    /// `expected` and `found` are there when the error records them, and
+    /// `suggestions` lists ways to fix the input, which may be none.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde_json::json;
+    /// use toon_rust::decode;
+    ///
+    /// let err = decode("user:\n  id 1", None).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_json(),
+    ///     json!({
+    ///         "code": "parse",
+    ///         "message": "Expected ':' after key 'id'",
+    ///         "span": {"offset": 11, "line": 2, "column": 6},
+    ///         "expected": ":",
+    ///         "suggestions": ["Add `:` after the key, or quote the line if it is a string"]
+    ///     })
+    /// );
+    /// ```
+    pub fn to_json(&self) -> Value {
+        let mut json = Map::new();
+        json.insert("code".into(), self.code_name().into());
+        let message = match self {
+            Self::Parse { message, .. } | Self::Deserialization { message, .. } => message.clone(),
+            _ => self.to_string(),
+        };
+        json.insert("message".into(), message.into());
+        match self {
+            Self::Parse {
+                position,
+                line,
+                column,
+                message,
+            } => {
+                json.insert(
+                    "span".into(),
+                    json!({"offset": position, "line": line, "column": column}),
+                );
+                if let Some(expected) = expected_in(message) {
+                    json.insert("expected".into(), expected.into());
+                }
+            }
+            Self::Deserialization { path, line, .. } => {
+                if let Some(line) = line {
+                    json.insert("span".into(), json!({"line": line}));
+                }
+                if let Some(path) = path {
+                    json.insert("path".into(), path.clone().into());
+                }
+            }
+            Self::LengthMismatch { expected, found } => {
+                json.insert("expected".into(), (*expected).into());
+                json.insert("found".into(), (*found).into());
+            }
+            Self::DelimiterMismatch { expected, found } => {
+                json.insert("expected".into(), expected.to_string().into());
+                json.insert("found".into(), found.to_string().into());
+            }
+            Self::MissingField(field) => {
+                json.insert("path".into(), field.clone().into());
+            }
+            _ => {}
+        }
+        json.insert("suggestions".into(), self.suggestions().into());
+        Value::Object(json)
+    }
+
+    /// Short name of the kind of error, as in `to_json`
+    fn code_name(&self) -> &'static str {
+        match self {
+            Self::Parse { .. } => "parse",
+            Self::Syntax(_) => "syntax",
+            Self::InvalidEscape(_) => "invalid_escape",
+            Self::LengthMismatch { .. } => "length_mismatch",
+            Self::DelimiterMismatch { .. } => "delimiter_mismatch",
+            Self::UnterminatedString => "unterminated_string",
+            Self::InvalidNumber(_) => "invalid_number",
+            Self::MissingField(_) => "missing_field",
+            Self::InvalidHeader(_) => "invalid_header",
+            Self::Io(_) => "io",
+            Self::Serialization(_) => "serialization",
+            Self::Deserialization { .. } => "deserialization",
+        }
+    }
+
+    /// Ways to fix the input that caused the error
+    fn suggestions(&self) -> Vec<String> {
+        let suggestion = match self {
+            Self::Parse { message, .. } => match message.as_str() {
+                message if message.starts_with("Expected ':' after key") => {
+                    "Add `:` after the key, or quote the line if it is a string".to_string()
+                }
+                message if message.starts_with("Indentation must be a multiple of") => {
+                    "Indent each level by the same number of spaces, or set \
+                     `DecodeOptions::indent` to the width the document uses"
+                        .to_string()
+                }
+                "Unexpected indentation" => {
+                    "Indent a line one level below the key or `- ` item it belongs to".to_string()
+                }
+                "Unterminated quoted key" | "Unterminated quoted field" => {
+                    "Close the quotes on the same line".to_string()
+                }
+                message if message.starts_with("Duplicate key") => {
+                    "Rename one of the keys, or set `DecodeOptions::duplicate_keys` to keep \
+                     the first or last value"
+                        .to_string()
+                }
+                "Invalid array length" => {
+                    "Write the number of items between the brackets, such as `[3]`".to_string()
+                }
+                "Expected a `- ` list item" => {
+                    "Start each item of a list array with `- `".to_string()
+                }
+                "Unexpected content" => {
+                    "Remove the lines after the document's root value".to_string()
+                }
+                _ => return Vec::new(),
+            },
+            Self::LengthMismatch { found, .. } => {
+                return vec![
+                    format!("Change the declared length to {found}"),
+                    "Set `DecodeOptions::length_policy` to pad or truncate instead of failing"
+                        .to_string(),
+                ]
+            }
+            Self::DelimiterMismatch { expected, .. } => {
+                format!("Separate the values with '{expected}', as the header declares")
+            }
+            Self::UnterminatedString => "Close the string with `\"`".to_string(),
+            Self::InvalidEscape(_) => {
+                "Use one of `\\\\`, `\\\"`, `\\n`, `\\r`, `\\t` or `\\uXXXX`".to_string()
+            }
+            Self::InvalidNumber(_) => "Quote the value if it is a string".to_string(),
+            Self::MissingField(field) => format!("Add `{field}` to the input"),
+            _ => return Vec::new(),
+        };
+        vec![suggestion]
+    }
+
+    /// Create a deserialization error without location information
+    pub fn deserialization(message: impl Into<String>) -> Self {
+        Self::Deserialization {
+            message: message.into(),
+            path: None,
+            line: None,
+        }
+    }
+}
+
+/// What a parse error's message says was expected, such as `:` for
+/// "Expected ':' after key 'id'"
+fn expected_in(message: &str) -> Option<String> {
+    let rest = message.strip_prefix("Expected ")?;
+    let expected = rest.split(" after ").next().unwrap_or(rest);
+    let unquoted = expected
+        .strip_prefix('\'')
+        .and_then(|expected| expected.strip_suffix('\''));
+    Some(unquoted.unwrap_or(expected).to_string())
+}
+
+/// Serializes as [`Error::to_json`]
+#[cfg(feature = "serde")]
+impl serde::Serialize for Error {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self::Serialization(msg.to_string())
+    }
+}
+
+/// Labeled diagnostics for downstream reporting via `miette`
+///
+/// `Error` does not keep the input around, so attach it when building the
+/// report: `miette::Report::new(err).with_source_code(input)`.
+#[cfg(feature = "diagnostics")]
+impl miette::Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(format!("toon_rust::{}", self.code_name())))
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        match self {
+            Self::Parse {
+                position, message, ..
+            } => Some(Box::new(std::iter::once(miette::LabeledSpan::new(
+                Some(message.clone()),
+                *position,
+                0,
+            )))),
+            _ => None,
+        }
+    }
 }
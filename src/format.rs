@@ -0,0 +1,130 @@
+//! Formatting TOON text in place
+//!
+//! [`format_str`] rewrites the layout of a document, never its content:
+//! it works on the lines themselves rather than on decoded values, so
+//! numbers keep their spelling, keys their order and strings their
+//! quoting.
+
+use crate::decode::{validate_stream, IndentNormalizer};
+use crate::error::Error;
+use crate::options::{FormatOptions, Style};
+
+/// Re-indent and normalize the whitespace of a TOON document
+///
+/// Each level of nesting is indented by [`FormatOptions::indent`] spaces,
+/// whatever the input used, and blank lines and trailing spaces are
+/// dropped; a trailing tab is kept, as it may delimit an empty cell, and so
+/// is a blank row within a table's declared length, which holds a null. A
+/// list item's first field keeps its nested value two levels below the
+/// `- ` marker. A key and its value are separated as [`FormatOptions::style`]
+/// says (`key: value` or `key:value`), and the values of an array header
+/// follow its colon directly, as the encoder writes them. Table rows and
+/// block string bodies are only re-indented.
+///
+/// The document must decode with [`FormatOptions::decode_options`],
+/// leniently as to indentation; otherwise the first error is returned and
+/// nothing is formatted.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::format_str;
+/// use toon_rust::options::FormatOptions;
+///
+/// let messy = "id:1.50\nuser:   \n    name:   Ada\n\n    tags[2]:  a,b\n";
+/// assert_eq!(
+///     format_str(messy, &FormatOptions::new())?,
+///     "id: 1.50\nuser:\n  name: Ada\n  tags[2]:a,b\n"
+/// );
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn format_str(input: &str, options: &FormatOptions) -> Result<String, Error> {
+    let lenient = options
+        .get_decode_options()
+        .cloned()
+        .unwrap_or_default()
+        .strict(false);
+    if let Err(mut errors) = validate_stream(input.as_bytes(), Some(&lenient)) {
+        return Err(errors.remove(0));
+    }
+
+    let pretty = options.get_style() == Style::Pretty;
+    let mut normalizer = IndentNormalizer::new(options.get_indent());
+    let mut output = String::with_capacity(input.len());
+    // Level of the header of the table whose rows are being read
+    let mut table: Option<usize> = None;
+    for line in input.split_inclusive('\n') {
+        normalizer.push_line_with(line, &mut output, |content, level, out| {
+            if table.is_some_and(|header| level > header) {
+                out.push_str(content);
+                return;
+            }
+            table = None;
+            if write_line(content, pretty, out) {
+                table = Some(level);
+            }
+        });
+    }
+    Ok(output)
+}
+
+/// Write one line outside a table body, returning whether it is a table
+/// header
+fn write_line(content: &str, pretty: bool, out: &mut String) -> bool {
+    if let Some(item) = content.strip_prefix('-') {
+        if item.is_empty() || item.starts_with(' ') {
+            out.push('-');
+            let item = item.trim_start();
+            if item.is_empty() {
+                return false;
+            }
+            out.push(' ');
+            return write_line(item, pretty, out);
+        }
+    }
+    let Some((key, header, value)) = split_entry(content) else {
+        out.push_str(content);
+        return false;
+    };
+    out.push_str(key.trim_end());
+    out.push(':');
+    let value = value.trim_start();
+    if !value.is_empty() {
+        if pretty && !header {
+            out.push(' ');
+        }
+        out.push_str(value);
+    }
+    header && value.is_empty() && key.trim_end().ends_with('}')
+}
+
+/// Split `key: value` or `key[N]{fields}: values` at its colon, saying
+/// whether the key carries an array header
+fn split_entry(content: &str) -> Option<(&str, bool, &str)> {
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut depth = 0usize;
+    let mut header = false;
+    for (i, ch) in content.char_indices() {
+        if quoted {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {}
+            }
+            continue;
+        }
+        match ch {
+            '"' => quoted = true,
+            '[' | '{' => {
+                depth += 1;
+                header = true;
+            }
+            ']' | '}' => depth = depth.saturating_sub(1),
+            ':' if depth == 0 => return Some((&content[..i], header, &content[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
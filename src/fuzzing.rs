@@ -0,0 +1,168 @@
+//! Structured inputs for fuzzing (`arbitrary` feature)
+//!
+//! [`ArbitraryValue`] and the [`Arbitrary`] impls of the option types let a
+//! fuzzer build documents and settings out of raw bytes rather than guess
+//! at TOON text, for differential checks such as "decoding the TOON of any
+//! value gives back what `serde_json` holds". The crate's own targets are
+//! in `fuzz/`, run with `cargo fuzz run roundtrip`.
+
+use crate::options::{DecodeOptions, EncodeOptions};
+use arbitrary::{Arbitrary, Result, Unstructured};
+use serde_json::{Map, Number, Value};
+use std::ops::RangeInclusive;
+
+/// Deepest nesting of the values generated
+const MAX_DEPTH: usize = 4;
+/// Most items of each generated array and entries of each object
+const MAX_LEN: usize = 8;
+
+/// A `serde_json` [`Value`] generated for fuzzing
+///
+/// Numbers are finite, as JSON has no others, and nesting and lengths are
+/// kept small so each input runs quickly.
+///
+/// # Example
+///
+/// ```rust
+/// use arbitrary::{Arbitrary, Unstructured};
+/// use toon_rust::fuzzing::ArbitraryValue;
+/// use toon_rust::{decode, encode};
+///
+/// let bytes = [7, 1, 42, 3, 0, 98, 5, 12, 200, 31, 4, 77];
+/// let ArbitraryValue(value) = ArbitraryValue::arbitrary(&mut Unstructured::new(&bytes))?;
+/// if let Ok(toon) = encode(&value, None) {
+///     assert!(decode(&toon, None).is_ok());
+/// }
+/// # Ok::<(), arbitrary::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArbitraryValue(pub Value);
+
+impl<'a> Arbitrary<'a> for ArbitraryValue {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        value(u, 0).map(ArbitraryValue)
+    }
+}
+
+fn value(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    // Containers are only picked above the deepest level
+    let kinds = if depth < MAX_DEPTH { 7 } else { 5 };
+    let value = match u.choose_index(kinds)? {
+        0 => Value::Null,
+        1 => Value::Bool(u.arbitrary()?),
+        2 => Value::Number(match u.choose_index(3)? {
+            0 => Number::from(u.arbitrary::<i64>()?),
+            1 => Number::from(u.arbitrary::<u64>()?),
+            _ => Number::from_f64(u.arbitrary()?).unwrap_or_else(|| Number::from(0)),
+        }),
+        3 | 4 => Value::String(u.arbitrary()?),
+        5 => {
+            let len = u.int_in_range(0..=MAX_LEN)?;
+            let items = (0..len).map(|_| value(u, depth + 1));
+            Value::Array(items.collect::<Result<_>>()?)
+        }
+        _ => {
+            let mut map = Map::new();
+            for _ in 0..u.int_in_range(0..=MAX_LEN)? {
+                map.insert(u.arbitrary()?, value(u, depth + 1)?);
+            }
+            Value::Object(map)
+        }
+    };
+    Ok(value)
+}
+
+/// `None`, or a number in `range`
+fn small(u: &mut Unstructured<'_>, range: RangeInclusive<usize>) -> Result<Option<usize>> {
+    match u.arbitrary()? {
+        true => Ok(Some(u.int_in_range(range)?)),
+        false => Ok(None),
+    }
+}
+
+/// `None`, or a share between 0 and 1 in steps of a hundredth
+fn share(u: &mut Unstructured<'_>) -> Result<Option<f64>> {
+    Ok(small(u, 0..=100)?.map(|percent| percent as f64 / 100.0))
+}
+
+/// `None`, or an indentation unit the decoder can be told to expect
+fn indent_string(u: &mut Unstructured<'_>) -> Result<Option<String>> {
+    let unit = u.choose(&[None, Some("\t"), Some(" "), Some("    ")])?;
+    Ok(unit.map(str::to_string))
+}
+
+/// Options that change how the TOON is laid out; the hooks stay unset and
+/// sizes small enough for the output to be checked quickly
+impl<'a> Arbitrary<'a> for EncodeOptions {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(EncodeOptions {
+            delimiter: u.arbitrary()?,
+            length_marker: u.arbitrary::<bool>()?.then_some('#'),
+            indent: small(u, 1..=8)?,
+            indent_string: indent_string(u)?,
+            nan_policy: u.arbitrary()?,
+            float_precision: small(u, 0..=17)?,
+            value_transformer: None,
+            progress: None,
+            escape_non_ascii: u.arbitrary()?,
+            block_strings: u.arbitrary()?,
+            null_policy: u.arbitrary()?,
+            quoting: u.arbitrary()?,
+            quote_padded_strings: u.arbitrary()?,
+            quote_reserved_words: u.arbitrary()?,
+            align_columns: u.arbitrary()?,
+            max_inline_items: small(u, 0..=MAX_LEN)?,
+            max_line_width: small(u, 0..=120)?,
+            min_tabular_rows: small(u, 0..=4)?,
+            max_rows_per_table: small(u, 0..=MAX_LEN)?,
+            max_tabular_columns: small(u, 0..=MAX_LEN)?,
+            tabular_uniformity: share(u)?,
+            key_folding: u.arbitrary()?,
+            emit_lengths: u.arbitrary()?,
+            length_delimiter: u.arbitrary()?,
+            inline_array_space: u.arbitrary()?,
+            optimize_for_tokens: u.arbitrary()?,
+            style: u.arbitrary()?,
+            roundtrip_safe: u.arbitrary()?,
+            table_sorts: u.arbitrary()?,
+            typed_headers: u.arbitrary()?,
+            column_defaults: share(u)?,
+            dictionary_encoding: u.arbitrary()?,
+            spec_version: u.arbitrary()?,
+            bytes_policy: u.arbitrary()?,
+        })
+    }
+}
+
+/// Options that change how TOON is read; the hooks stay unset and
+/// `follow` is off so decoding never waits for more input
+impl<'a> Arbitrary<'a> for DecodeOptions {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(DecodeOptions {
+            indent: small(u, 1..=8)?,
+            indent_string: indent_string(u)?,
+            strict: u.arbitrary()?,
+            allow_nan: u.arbitrary()?,
+            lenient_numbers: u.arbitrary()?,
+            lenient_arrays: u.arbitrary()?,
+            allow_elided_rows: u.arbitrary()?,
+            max_array_length: u.arbitrary()?,
+            max_cells: u.arbitrary()?,
+            max_string_length: u.arbitrary()?,
+            duplicate_keys: u.arbitrary()?,
+            length_policy: u.arbitrary()?,
+            extra_cells_key: u.arbitrary()?,
+            coerce_numbers: u.arbitrary()?,
+            coerce_booleans: u.arbitrary()?,
+            cells_as_strings: u.arbitrary()?,
+            expand_paths: u.arbitrary()?,
+            empty_cells: u.arbitrary()?,
+            follow: None,
+            include_keys: u.arbitrary()?,
+            include_columns: u.arbitrary()?,
+            row_filter: None,
+            progress: None,
+            spec_version: u.arbitrary()?,
+        })
+    }
+}
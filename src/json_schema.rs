@@ -0,0 +1,152 @@
+//! Validation against JSON Schema
+//!
+//! [`validate`] checks a TOON document, or a value already decoded, against
+//! a JSON Schema and reports every violation with the path to the value
+//! that breaks it. For a document the violations also carry the line and
+//! column the value was written at, so data generated by a model can be
+//! checked and the problems pointed out before it is accepted.
+
+use crate::decode::{decode, line_column};
+use crate::dom;
+use crate::error::Error;
+use jsonschema::paths::LocationSegment;
+use serde_json::Value;
+use std::fmt;
+
+/// What to validate: TOON text, or a value that has already been decoded
+#[derive(Debug, Clone, Copy)]
+pub enum Instance<'a> {
+    /// A TOON document, decoded with the default options
+    Toon(&'a str),
+    /// A decoded value, whose violations have no position
+    Value(&'a Value),
+}
+
+impl<'a> From<&'a str> for Instance<'a> {
+    fn from(input: &'a str) -> Self {
+        Instance::Toon(input)
+    }
+}
+
+impl<'a> From<&'a String> for Instance<'a> {
+    fn from(input: &'a String) -> Self {
+        Instance::Toon(input)
+    }
+}
+
+impl<'a> From<&'a Value> for Instance<'a> {
+    fn from(value: &'a Value) -> Self {
+        Instance::Value(value)
+    }
+}
+
+/// One way in which a value breaks a schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Path to the value in TOON form (`items[3].qty`), empty for the root
+    pub path: String,
+    /// JSON Pointer to the value (`/items/3/qty`)
+    pub pointer: String,
+    /// JSON Pointer to the schema keyword that failed
+    pub schema_path: String,
+    /// What is wrong
+    pub message: String,
+    /// 1-based line of the value in the document
+    pub line: Option<usize>,
+    /// 1-based column of the value in the document
+    pub column: Option<usize>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "line {line}, column {column}: ")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "`{}`: ", self.path)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Check a TOON document or a decoded value against a JSON Schema
+///
+/// Returns every violation found, in the order the validator reports them,
+/// or an empty list when the value is valid. A schema that isn't valid
+/// JSON Schema and a document that doesn't decode are errors. Remote
+/// `$ref`s are not fetched.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::validate;
+///
+/// let schema = json!({
+///     "type": "object",
+///     "properties": {
+///         "items": {"type": "array", "items": {
+///             "type": "object",
+///             "properties": {"qty": {"type": "integer", "minimum": 1}}
+///         }}
+///     }
+/// });
+/// let violations = validate("items[2]{sku,qty}:\n  A1,2\n  B2,0", &schema)?;
+/// assert_eq!(violations.len(), 1);
+/// assert_eq!(violations[0].path, "items[1].qty");
+/// assert_eq!((violations[0].line, violations[0].column), (Some(3), Some(6)));
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn validate<'a>(
+    instance: impl Into<Instance<'a>>,
+    schema: &Value,
+) -> Result<Vec<Violation>, Error> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| Error::Syntax(format!("Invalid JSON Schema: {e}")))?;
+    let (document, value) = match instance.into() {
+        // The tree keeps what a decoder would reject, so the text is
+        // decoded as well to hold it to the usual checks
+        Instance::Toon(input) => (Some(dom::parse(input, None)?), decode(input, None)?),
+        Instance::Value(value) => (None, value.clone()),
+    };
+
+    Ok(validator
+        .iter_errors(&value)
+        .map(|error| {
+            let segments: Vec<String> = error
+                .instance_path()
+                .iter()
+                .map(|segment| segment.to_string())
+                .collect();
+            let position = document.as_ref().map(|document| {
+                let path: Vec<&str> = segments.iter().map(String::as_str).collect();
+                line_column(document.source(), document.span_at(&path).start)
+            });
+            Violation {
+                path: toon_path(error.instance_path().iter()),
+                pointer: error.instance_path().to_string(),
+                schema_path: error.schema_path().to_string(),
+                message: error.to_string(),
+                line: position.map(|(line, _)| line),
+                column: position.map(|(_, column)| column),
+            }
+        })
+        .collect())
+}
+
+/// `items[3].qty` for the segments of `/items/3/qty`
+fn toon_path<'a>(segments: impl Iterator<Item = LocationSegment<'a>>) -> String {
+    let mut path = String::new();
+    for segment in segments {
+        match segment {
+            LocationSegment::Index(i) => path.push_str(&format!("[{i}]")),
+            LocationSegment::Property(key) => {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&key);
+            }
+        }
+    }
+    path
+}
@@ -0,0 +1,194 @@
+//! Documents decoded one top-level section at a time
+//!
+//! [`LazyDocument`] scans a document once for the lines that start its
+//! top-level entries and reads their keys, but decodes an entry's value only
+//! when it is first asked for. Opening a large file is then about as cheap as
+//! reading it, and tools that look at a few sections never pay for the rest.
+
+use crate::decode::{
+    decode, decode_entries, entry_key, is_root_primitive, prepare_input, top_level_entries,
+};
+use crate::error::Error;
+use crate::options::DecodeOptions;
+use crate::raw::RawToon;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+/// A document whose top-level sections are decoded on first access
+///
+/// Each section is decoded at most once and kept, so later lookups are free.
+/// A section that fails to decode fails only the lookups that reach it. A
+/// document whose root is an array or a primitive has no sections; it is
+/// decoded as a whole by [`value`](Self::value).
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::lazy::LazyDocument;
+///
+/// let toon = "name: shop\nitems[2]{sku,qty}:\n  A1,2\n  B2,1\nbroken[3]: 1,2";
+/// let doc = LazyDocument::new(toon, None)?;
+/// assert_eq!(doc.keys().collect::<Vec<_>>(), ["broken", "items", "name"]);
+/// assert_eq!(doc.get("name")?, Some(&json!("shop")));
+/// assert!(doc.get("broken").is_err());
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct LazyDocument {
+    input: String,
+    options: DecodeOptions,
+    /// Sections by key, or `None` when the root isn't an object
+    sections: Option<BTreeMap<String, Section>>,
+}
+
+/// The entries holding one top-level key, and their value once decoded,
+/// `None` when [`DecodeOptions::include_keys`] leaves the key out
+#[derive(Debug)]
+struct Section {
+    entries: Vec<(usize, usize)>,
+    value: OnceLock<Option<Value>>,
+}
+
+impl LazyDocument {
+    /// Index the top-level sections of a document
+    ///
+    /// Only the keys are read, so a malformed key fails here, while a
+    /// malformed value fails when its section is accessed.
+    pub fn new(input: &str, options: Option<&DecodeOptions>) -> Result<Self, Error> {
+        let options = options.cloned().unwrap_or_default();
+        let input = prepare_input(input, &options).into_owned();
+        let entries = top_level_entries(&input);
+        let root_value = match entries.as_slice() {
+            _ if input.trim_start().starts_with('[') => true,
+            [(start, end)] => {
+                let line = input[*start..*end].trim();
+                !line.contains('\n') && is_root_primitive(line, &options)
+            }
+            _ => false,
+        };
+        let sections = if root_value {
+            None
+        } else {
+            let mut sections = BTreeMap::new();
+            for entry in entries {
+                let (key, _) = entry_key(&input, entry, &options)?;
+                // Folded keys belong to the section they expand into, while
+                // quoted ones are taken literally
+                let quoted = input[entry.0..].starts_with('"');
+                let key = match key.split_once('.') {
+                    Some((first, _)) if options.get_expand_paths() && !quoted => first.to_string(),
+                    _ => key,
+                };
+                sections
+                    .entry(key)
+                    .or_insert_with(|| Section {
+                        entries: Vec::new(),
+                        value: OnceLock::new(),
+                    })
+                    .entries
+                    .push(entry);
+            }
+            Some(sections)
+        };
+        Ok(LazyDocument {
+            input,
+            options,
+            sections,
+        })
+    }
+
+    /// The text the document was read from, re-indented as it is parsed
+    pub fn source(&self) -> &str {
+        &self.input
+    }
+
+    /// The top-level keys, in the order decoded objects keep them
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.sections
+            .iter()
+            .flat_map(|sections| sections.keys().map(String::as_str))
+    }
+
+    /// Number of top-level sections
+    pub fn len(&self) -> usize {
+        self.sections.as_ref().map_or(0, BTreeMap::len)
+    }
+
+    /// Whether the document has no top-level sections
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the document has a top-level section under `key`
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.sections
+            .as_ref()
+            .is_some_and(|sections| sections.contains_key(key))
+    }
+
+    /// Whether the section under `key` has been decoded yet
+    pub fn is_decoded(&self, key: &str) -> bool {
+        self.section(key)
+            .is_some_and(|section| section.value.get().is_some())
+    }
+
+    /// Decode the value of a top-level key, or return it if it already was
+    ///
+    /// Returns `None` when the key is absent, when
+    /// [`DecodeOptions::include_keys`] leaves it out, or when the root isn't
+    /// an object.
+    pub fn get(&self, key: &str) -> Result<Option<&Value>, Error> {
+        let Some(section) = self.section(key) else {
+            return Ok(None);
+        };
+        if let Some(value) = section.value.get() {
+            return Ok(value.as_ref());
+        }
+        let value = decode_entries(&self.input, &section.entries, key, &self.options, true)?;
+        Ok(section.value.get_or_init(|| value).as_ref())
+    }
+
+    /// The text of a top-level key's value, without decoding it
+    ///
+    /// Returns `None` when the key is absent or the root isn't an object. A
+    /// key written as more than one entry, repeated or folded, is decoded and
+    /// encoded again as a whole.
+    pub fn raw(&self, key: &str) -> Result<Option<RawToon>, Error> {
+        let Some(section) = self.section(key) else {
+            return Ok(None);
+        };
+        if let [entry] = section.entries[..] {
+            if entry_key(&self.input, entry, &self.options)?.0 == key {
+                return RawToon::from_entry(&self.input, entry, &self.options).map(Some);
+            }
+        }
+        match self.get(key)? {
+            Some(value) => RawToon::from_value(value, None).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Decode the whole document, reusing the sections decoded so far
+    ///
+    /// This is the value [`decode`] gives with the same options, so
+    /// sections [`DecodeOptions::include_keys`] leaves out are left out here
+    /// too.
+    pub fn value(&self) -> Result<Value, Error> {
+        let Some(sections) = &self.sections else {
+            return decode(&self.input, Some(&self.options));
+        };
+        let mut map = Map::new();
+        for key in sections.keys() {
+            if let Some(value) = self.get(key)? {
+                map.insert(key.clone(), value.clone());
+            }
+        }
+        Ok(Value::Object(map))
+    }
+
+    fn section(&self, key: &str) -> Option<&Section> {
+        self.sections.as_ref()?.get(key)
+    }
+}
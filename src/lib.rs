@@ -65,19 +65,118 @@
 //! let decoded: Vec<Product> = from_str(&toon).unwrap();
 //! ```
 
+pub mod budget;
+pub mod csv;
 pub mod decode;
+pub mod dom;
 pub mod encode;
 pub mod error;
+pub mod format;
+pub mod lazy;
+pub mod lint;
+pub mod merge;
+pub mod ndjson;
 pub mod options;
+pub mod partial;
+pub mod patch;
+pub mod pointer;
+pub mod query;
+pub mod raw;
+pub mod schema;
 mod simd;
+pub mod syntax;
+pub mod tokens;
+pub mod transcode;
+pub mod writer;
 
-pub use decode::{decode, decode_stream};
-pub use encode::{encode, encode_stream};
+pub use budget::encode_with_budget;
+pub use csv::{from_csv, to_csv};
+pub use decode::{
+    decode, decode_all_errors, decode_iter, decode_key, decode_path, decode_rows,
+    decode_rows_stream, decode_stream, decode_with_stats, resume_rows_stream, validate_stream,
+    Checkpoint, DecodeStats, Stats,
+};
+pub use encode::{encode, encode_canonical, encode_stream};
 pub use error::Error;
+pub use format::format_str;
+pub use lint::lint;
+pub use merge::{merge, merge_stream};
+pub use ndjson::{ndjson_to_toon, toon_to_ndjson};
 pub use options::{DecodeOptions, EncodeOptions};
+pub use partial::{decode_partial, PartialParser};
+pub use patch::apply_table_patch;
+pub use query::{query, query_stream};
+pub use raw::RawToon;
+pub use schema::{infer_schema, ToonSchema};
+pub use syntax::tokenize;
+pub use tokens::estimate_tokens;
+pub use transcode::transcode;
+pub use writer::ToonWriter;
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "async")]
+pub mod async_io;
+#[cfg(any(feature = "rmp", feature = "cbor", feature = "rusqlite"))]
+mod bytes;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "color")]
+pub mod color;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub mod compression;
+#[cfg(feature = "arbitrary")]
+pub mod fuzzing;
+#[cfg(feature = "jsonschema")]
+pub mod json_schema;
+#[cfg(feature = "rmp")]
+pub mod msgpack;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "rusqlite")]
+pub mod sql;
+#[cfg(feature = "toml")]
+pub mod toml;
+#[cfg(feature = "yaml")]
+pub mod yaml;
+#[cfg(feature = "arrow")]
+pub use arrow::encode_record_batches;
+#[cfg(all(feature = "async", feature = "serde"))]
+pub use async_io::AsyncTabularWriter;
+#[cfg(feature = "async")]
+pub use async_io::{decode_stream_async, encode_stream_async};
+#[cfg(feature = "cbor")]
+pub use cbor::{cbor_to_toon, toon_to_cbor};
+#[cfg(feature = "color")]
+pub use color::{render_error, render_lint};
+#[cfg(feature = "gzip")]
+pub use compression::{decode_stream_gz, encode_stream_gz};
+#[cfg(feature = "zstd")]
+pub use compression::{decode_stream_zstd, encode_stream_zstd};
+#[cfg(feature = "arbitrary")]
+pub use fuzzing::ArbitraryValue;
+#[cfg(feature = "jsonschema")]
+pub use json_schema::validate;
+#[cfg(feature = "rmp")]
+pub use msgpack::{msgpack_to_toon, toon_to_msgpack};
+#[cfg(feature = "parquet")]
+pub use parquet::{parquet_to_toon, toon_to_parquet};
+#[cfg(feature = "rusqlite")]
+pub use sql::encode_query;
+#[cfg(feature = "toml")]
+pub use toml::{toml_to_toon, toon_to_toml};
+#[cfg(feature = "yaml")]
+pub use yaml::{toon_to_yaml, toon_to_yaml_stream, yaml_to_toon, yaml_to_toon_stream};
+
+#[cfg(feature = "serde")]
+mod ser;
+#[cfg(feature = "serde")]
+mod ser_stream;
 #[cfg(feature = "serde")]
 pub mod serde_api;
 
 #[cfg(feature = "serde")]
-pub use serde_api::{from_reader, from_str, to_string, to_writer};
+pub use serde_api::{
+    from_reader, from_str, from_str_iter, to_string, to_writer, to_writer_streaming,
+    RotatingWriter, TabularWriter,
+};
@@ -0,0 +1,298 @@
+//! Non-fatal problems in TOON text
+//!
+//! [`lint`] reports what a document can get away with but probably
+//! shouldn't: layout a strict decoder rejects, values that read differently
+//! than they look, and lines a decoder skips. Each [`Lint`] carries the
+//! byte range it is about, so editors and CI tools can underline it.
+
+use crate::decode::{elided_rows, line_column, opens_block};
+use crate::dom::{self, Header, Items, Node, Scalar};
+use crate::options::DecodeOptions;
+use serde_json::Value;
+use std::ops::Range;
+
+/// What a [`Lint`] is about
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintKind {
+    /// Indentation with tabs, a width that isn't a multiple of the
+    /// document's unit, or a line nested deeper than the one above allows
+    InconsistentIndent,
+    /// A table whose delimiter differs from the document's first table
+    MixedDelimiters,
+    /// An unquoted value that looks like a number, but that decoders read as
+    /// a string or don't agree on
+    NumericString,
+    /// An array header whose length differs from the items that follow
+    LengthMismatch,
+    /// Lines after a root array, which decoding ignores
+    TrailingContent,
+}
+
+/// A problem found by [`lint`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub message: String,
+    /// Byte range of the text the lint is about
+    pub span: Range<usize>,
+    /// 1-based line of the start of `span`
+    pub line: usize,
+    /// 1-based column, in characters, of the start of `span`
+    pub column: usize,
+}
+
+/// Report the non-fatal problems in a TOON document, in the order they
+/// appear
+///
+/// The indentation unit is the width of the first indented line. Lints on
+/// tables, lengths and values need the document's structure, so they are
+/// only reported when the document parses with that unit, once any
+/// trailing content is set aside.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::lint;
+/// use toon_rust::lint::LintKind;
+///
+/// let toon = "tags[3]: a,b\nzip: 04532";
+/// let lints = lint(toon);
+/// assert_eq!(lints[0].kind, LintKind::LengthMismatch);
+/// assert_eq!(&toon[lints[0].span.clone()], "[3]");
+/// assert_eq!((lints[1].kind, lints[1].line), (LintKind::NumericString, 2));
+/// ```
+pub fn lint(input: &str) -> Vec<Lint> {
+    let mut linter = Linter {
+        source: input,
+        lints: Vec::new(),
+        delimiter: None,
+    };
+    let (unit, end) = linter.lines();
+    let options = DecodeOptions::new().indent(unit);
+    if let Ok(doc) = dom::parse(&input[..end], Some(&options)) {
+        linter.node(doc.root());
+    }
+    linter.lints.sort_by_key(|lint| lint.span.start);
+    linter.lints
+}
+
+struct Linter<'a> {
+    source: &'a str,
+    lints: Vec<Lint>,
+    /// Delimiter of the first table
+    delimiter: Option<char>,
+}
+
+impl Linter<'_> {
+    /// Check the indentation of each line and look for content after a root
+    /// array, giving the indentation unit and where the document's content
+    /// ends
+    fn lines(&mut self) -> (usize, usize) {
+        let mut unit = None;
+        let mut root_array = false;
+        // Level of the line above, and whether it was a `- item`
+        let mut above: Option<(usize, bool)> = None;
+        // Width of the line opening the block string being read
+        let mut block: Option<usize> = None;
+        let mut start = 0;
+        for line in self.source.split_inclusive('\n') {
+            let line_start = start;
+            start += line.len();
+            let text = line.trim_end();
+            let content = text.trim_start_matches([' ', '\t']);
+            if content.is_empty() {
+                continue;
+            }
+            let width = text.len() - content.len();
+            if block.is_some_and(|opener| width > opener) {
+                continue;
+            }
+            block = opens_block(content).then_some(width);
+
+            if width == 0 {
+                if root_array {
+                    let span = line_start..self.source.trim_end().len();
+                    self.push(
+                        LintKind::TrailingContent,
+                        "Content after the root array is ignored when decoding",
+                        span,
+                    );
+                    return (unit.unwrap_or(2), line_start);
+                }
+                root_array = above.is_none() && content.starts_with('[');
+            }
+            let indent = line_start..line_start + width;
+            if text[..width].contains('\t') {
+                self.push(
+                    LintKind::InconsistentIndent,
+                    "Indentation uses a tab",
+                    indent,
+                );
+                above = None;
+                continue;
+            }
+            if width > 0 && unit.is_none() {
+                unit = Some(width);
+            }
+            let step = unit.unwrap_or(1);
+            let level = width / step;
+            if !width.is_multiple_of(step) {
+                let message = format!("Indentation of {width} spaces isn't a multiple of {step}");
+                self.push(LintKind::InconsistentIndent, message, indent);
+            } else if let Some((parent, item)) = above {
+                let deepest = parent + 1 + usize::from(item);
+                if level > deepest {
+                    let message = format!(
+                        "Indented {} levels deeper than the line above",
+                        level - parent
+                    );
+                    self.push(LintKind::InconsistentIndent, message, indent);
+                }
+            }
+            above = Some((level, content.starts_with("- ")));
+        }
+        (unit.unwrap_or(2), self.source.len())
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Scalar(scalar) => self.scalar(scalar),
+            Node::Block(_) => {}
+            Node::Object(object) => {
+                for entry in &object.entries {
+                    self.node(&entry.value);
+                }
+            }
+            Node::Array(array) => {
+                let header = &array.header;
+                let found = match &array.items {
+                    Items::Inline(items) => {
+                        items.iter().for_each(|item| self.scalar(item));
+                        Some(items.len())
+                    }
+                    Items::Table(rows) => {
+                        self.table(header);
+                        for row in rows {
+                            for (column, cell) in row.cells.iter().enumerate() {
+                                if is_plain_column(header, column) {
+                                    self.scalar(cell);
+                                }
+                            }
+                        }
+                        table_length(self.source, rows)
+                    }
+                    Items::List(items) => {
+                        items.iter().for_each(|item| self.node(&item.value));
+                        Some(items.len())
+                    }
+                };
+                if let (Some(declared), Some(found)) = (header.length, found) {
+                    if declared != found {
+                        let message =
+                            format!("The header says {declared}, but the array has {found}");
+                        self.push(
+                            LintKind::LengthMismatch,
+                            message,
+                            length_span(self.source, header),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check a table's delimiter against the first table's
+    fn table(&mut self, header: &Header) {
+        let first = *self.delimiter.get_or_insert(header.delimiter);
+        if header.delimiter != first {
+            let message = format!(
+                "This table is delimited by {:?}, but the first table by {first:?}",
+                header.delimiter
+            );
+            self.push(LintKind::MixedDelimiters, message, header.span.clone());
+        }
+    }
+
+    fn scalar(&mut self, scalar: &Scalar) {
+        let text = &self.source[scalar.span.clone()];
+        if text.starts_with('"') || !looks_numeric(text) {
+            return;
+        }
+        let message = match &scalar.value {
+            Value::String(_) => format!("`{text}` is read as a string, but looks like a number; quote it to say so"),
+            Value::Number(_) if has_leading_zero(text) => format!(
+                "`{text}` has a leading zero, which decoders read as a number or a string; quote it if it is a string"
+            ),
+            _ => return,
+        };
+        self.push(LintKind::NumericString, message, scalar.span.clone());
+    }
+
+    fn push(&mut self, kind: LintKind, message: impl Into<String>, span: Range<usize>) {
+        let (line, column) = line_column(self.source, span.start);
+        self.lints.push(Lint {
+            kind,
+            message: message.into(),
+            span,
+            line,
+            column,
+        });
+    }
+}
+
+/// Whether the cells of `column` are written as they read, rather than
+/// through a declared type or dictionary
+fn is_plain_column(header: &Header, column: usize) -> bool {
+    header.types.get(column).is_none_or(Option::is_none)
+        && header.dictionaries.get(column).is_none_or(Option::is_none)
+}
+
+/// Rows a table stands for, counting those an elision marker in its last
+/// row says were left out; `None` when the marker gives no count
+fn table_length(source: &str, rows: &[dom::Row]) -> Option<usize> {
+    let Some(last) = rows.last() else {
+        return Some(0);
+    };
+    match elided_rows(
+        &source[last.span.clone()],
+        &DecodeOptions::new().strict(false),
+    ) {
+        Some(count) => count.map(|count| rows.len() - 1 + count),
+        None => Some(rows.len()),
+    }
+}
+
+/// The span of a header's `[N]`
+fn length_span(source: &str, header: &Header) -> Range<usize> {
+    let text = &source[header.span.clone()];
+    let end = text.find(']').map_or(text.len(), |end| end + 1);
+    header.span.start..header.span.start + end
+}
+
+/// Whether `text` is written like a number: digits with an optional sign,
+/// `_` or `,` separators, fraction and exponent
+fn looks_numeric(text: &str) -> bool {
+    let unsigned = text.strip_prefix(['+', '-']).unwrap_or(text);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (unsigned, None),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = |part: &str| {
+        part.chars()
+            .all(|ch| ch.is_ascii_digit() || ch == '_' || ch == ',')
+    };
+    whole.starts_with(|ch: char| ch.is_ascii_digit())
+        && digits(whole)
+        && fraction.chars().all(|ch| ch.is_ascii_digit())
+        && exponent.is_none_or(|exponent| {
+            let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            !exponent.is_empty() && exponent.chars().all(|ch| ch.is_ascii_digit())
+        })
+}
+
+fn has_leading_zero(text: &str) -> bool {
+    let unsigned = text.strip_prefix(['+', '-']).unwrap_or(text);
+    unsigned.len() > 1 && unsigned.starts_with('0') && unsigned.as_bytes()[1].is_ascii_digit()
+}
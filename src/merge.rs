@@ -0,0 +1,124 @@
+//! JSON Merge Patch (RFC 7386) over decoded values
+//!
+//! A patch is a value shaped like the document it changes. Each field of a
+//! patch object replaces the field of the same name, a `null` field removes
+//! it, and nested objects are merged the same way. Anything other than an
+//! object, arrays included, replaces the target outright.
+
+use crate::encode::encode_stream;
+use crate::error::Error;
+use crate::options::EncodeOptions;
+use crate::writer::ToonWriter;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::io::Write;
+
+/// Apply a merge patch to `target` in place
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::{decode, encode, merge};
+///
+/// let mut doc = decode("name: shop\nmeta: \n  owner: ann\n  draft: true", None)?;
+/// merge(&mut doc, &json!({"meta": {"draft": null, "owner": "bo"}, "open": true}));
+/// assert_eq!(encode(&doc, None)?, "meta: \n  owner: bo\nname: shop\nopen: true");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(map) = target else {
+        unreachable!("target was just made an object");
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            map.remove(key);
+        } else {
+            merge(map.entry(key.as_str()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Encode `target` with a merge patch applied, without building the merged
+/// document first
+///
+/// Fields the patch doesn't touch are encoded straight from `target`, and
+/// objects present in both are merged field by field as they are written,
+/// so only the values the patch replaces are copied. The document is
+/// written the way [`ToonWriter`] writes fields.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::{decode, merge_stream};
+///
+/// let doc = decode("items[2]{sku,qty}:\n  A1,2\n  B2,1\nmeta: \n  rev: 3", None)?;
+/// let mut out = Vec::new();
+/// merge_stream(&doc, &json!({"meta": {"rev": 4}}), &mut out, None)?;
+/// assert_eq!(
+///     String::from_utf8(out).unwrap(),
+///     "items[2]{qty,sku}:\n  2,A1\n  1,B2\nmeta: \n  rev: 4\n"
+/// );
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn merge_stream<W: Write>(
+    target: &Value,
+    patch: &Value,
+    writer: &mut W,
+    options: Option<&EncodeOptions>,
+) -> Result<(), Error> {
+    let (Value::Object(target), Value::Object(patch)) = (target, patch) else {
+        // Without two objects to walk, the patch decides the whole result
+        let mut merged = Value::Null;
+        merge(&mut merged, patch);
+        return encode_stream(&merged, writer, options);
+    };
+    let default_opts = EncodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let mut toon = ToonWriter::with_options(&mut *writer, opts);
+    write_merged(&mut toon, target, patch, opts)?;
+    toon.finish()?;
+    Ok(())
+}
+
+/// Write the fields of `target` merged with `patch`, in key order
+fn write_merged<W: Write>(
+    toon: &mut ToonWriter<W>,
+    target: &Map<String, Value>,
+    patch: &Map<String, Value>,
+    options: &EncodeOptions,
+) -> Result<(), Error> {
+    let keys: BTreeSet<&String> = target.keys().chain(patch.keys()).collect();
+    for key in keys {
+        match (target.get(key), patch.get(key)) {
+            (_, Some(Value::Null)) => {}
+            (Some(value), None) => {
+                toon.field(key, value)?;
+            }
+            // Folding may join the key with the merged object's own keys,
+            // so those are encoded as a whole below
+            (Some(Value::Object(inner)), Some(Value::Object(changes)))
+                if !options.get_key_folding() =>
+            {
+                toon.begin_object(key)?;
+                write_merged(toon, inner, changes, options)?;
+                toon.end()?;
+            }
+            (value, Some(changes)) => {
+                let mut merged = value.cloned().unwrap_or(Value::Null);
+                merge(&mut merged, changes);
+                toon.field(key, &merged)?;
+            }
+            (None, None) => unreachable!("keys come from one of the maps"),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,145 @@
+//! Conversion between TOON and MessagePack
+//!
+//! Requires the `rmp` feature. MessagePack values are converted straight
+//! to and from the decoded document, with no JSON text in between. Byte
+//! strings (`bin`) are written as set by
+//! [`EncodeOptions::bytes_policy`], base64 strings by default.
+
+use crate::bytes::bytes_value;
+use crate::decode::decode;
+use crate::encode::encode;
+use crate::error::Error;
+use crate::options::{DecodeOptions, EncodeOptions};
+use rmpv::Value as MsgPack;
+use serde_json::{Map, Number, Value};
+
+/// Convert a MessagePack value to TOON
+///
+/// Integer and boolean map keys become strings, as JSON keys must be; other
+/// keys, extension types and strings that aren't UTF-8 are errors, as are
+/// bytes after the value. Floats that aren't finite become null.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::{msgpack_to_toon, toon_to_msgpack};
+///
+/// let toon = "items[2]{sku,qty}:\n  A1,2\n  B2,1";
+/// let bytes = toon_to_msgpack(toon, None)?;
+/// assert_eq!(msgpack_to_toon(&bytes, None)?, "items[2]{qty,sku}:\n  2,A1\n  1,B2\n");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn msgpack_to_toon(bytes: &[u8], options: Option<&EncodeOptions>) -> Result<String, Error> {
+    let default_opts = EncodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let mut rest = bytes;
+    let value = rmpv::decode::read_value(&mut rest)
+        .map_err(|e| invalid(format!("Invalid MessagePack: {e}"), None))?;
+    if !rest.is_empty() {
+        return Err(invalid(
+            format!("Invalid MessagePack: {} bytes after the value", rest.len()),
+            None,
+        ));
+    }
+    encode(&from_msgpack(value, opts, "")?, Some(opts))
+}
+
+/// Convert a TOON document to MessagePack
+///
+/// Integers are written in the smallest MessagePack form that holds them.
+pub fn toon_to_msgpack(input: &str, options: Option<&DecodeOptions>) -> Result<Vec<u8>, Error> {
+    let value = to_msgpack(decode(input, options)?);
+    let mut bytes = Vec::new();
+    rmpv::encode::write_value(&mut bytes, &value)
+        .map_err(|e| Error::Serialization(e.to_string()))?;
+    Ok(bytes)
+}
+
+/// The document a MessagePack value stands for, with `path` naming it in
+/// errors
+fn from_msgpack(value: MsgPack, options: &EncodeOptions, path: &str) -> Result<Value, Error> {
+    Ok(match value {
+        MsgPack::Nil => Value::Null,
+        MsgPack::Boolean(b) => Value::Bool(b),
+        MsgPack::Integer(n) => n
+            .as_i64()
+            .map(Value::from)
+            .or_else(|| n.as_u64().map(Value::from))
+            .unwrap_or(Value::Null),
+        // Through the shortest text of the `f32`, so 0.1 stays 0.1
+        MsgPack::F32(f) => float(f.to_string().parse().unwrap_or(f64::from(f))),
+        MsgPack::F64(f) => float(f),
+        MsgPack::String(s) => match s.into_str() {
+            Some(s) => Value::String(s),
+            None => return Err(invalid("String is not UTF-8", Some(path))),
+        },
+        MsgPack::Binary(bytes) => bytes_value(&bytes, options.get_bytes_policy()),
+        MsgPack::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| from_msgpack(item, options, &format!("{path}[{i}]")))
+                .collect::<Result<_, _>>()?,
+        ),
+        MsgPack::Map(entries) => {
+            let mut map = Map::with_capacity(entries.len());
+            for (key, value) in entries {
+                let key = match key {
+                    MsgPack::String(s) if s.is_str() => s.into_str().unwrap_or_default(),
+                    MsgPack::Integer(n) => n.to_string(),
+                    MsgPack::Boolean(b) => b.to_string(),
+                    key => {
+                        return Err(invalid(
+                            format!("Map key {key} is not a string"),
+                            Some(path),
+                        ))
+                    }
+                };
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                map.insert(key, from_msgpack(value, options, &child)?);
+            }
+            Value::Object(map)
+        }
+        MsgPack::Ext(ty, _) => {
+            return Err(invalid(
+                format!("Extension type {ty} has no TOON form"),
+                Some(path),
+            ))
+        }
+    })
+}
+
+fn float(f: f64) -> Value {
+    Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+fn to_msgpack(value: Value) -> MsgPack {
+    match value {
+        Value::Null => MsgPack::Nil,
+        Value::Bool(b) => MsgPack::Boolean(b),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => MsgPack::from(u),
+            (None, Some(i)) => MsgPack::from(i),
+            (None, None) => MsgPack::F64(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => MsgPack::from(s),
+        Value::Array(items) => MsgPack::Array(items.into_iter().map(to_msgpack).collect()),
+        Value::Object(map) => MsgPack::Map(
+            map.into_iter()
+                .map(|(key, value)| (MsgPack::from(key), to_msgpack(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn invalid(message: impl Into<String>, path: Option<&str>) -> Error {
+    Error::Deserialization {
+        message: message.into(),
+        path: path.filter(|path| !path.is_empty()).map(str::to_string),
+        line: None,
+    }
+}
@@ -0,0 +1,231 @@
+//! Conversion between newline-delimited JSON and TOON tables
+//!
+//! NDJSON files hold one JSON object per line, which maps onto a TOON
+//! tabular root array with one row per record. Both directions work a
+//! record at a time, so a dataset never has to be held as one `Value`.
+
+use crate::decode::decode_iter;
+use crate::encode::{column_paths, encode_stream};
+use crate::error::Error;
+use crate::options::{DecodeOptions, EncodeOptions};
+use crate::transcode::TableSink;
+use serde_json::Value;
+use std::io::{BufRead, Read, Write};
+
+/// Convert newline-delimited JSON records to a TOON tabular root array
+///
+/// Every non-blank line must hold a JSON object. The columns are the first
+/// record's fields; later records may leave fields out but may not add new
+/// ones. A field left out gives an empty cell while an explicit null is
+/// written `null`, so decoding with [`EmptyCellPolicy::Omit`] leaves the
+/// missing fields out again; by default both read back as null.
+///
+/// With key folding, nested objects become dotted columns. A record holding
+/// a value that still can't be a cell, such as an array, turns the whole
+/// output into a list array with one item per record, written by the
+/// regular encoder once the input is read.
+///
+/// Records are read and rendered one line at a time. With lengths turned
+/// off through [`EncodeOptions::emit_lengths`], each row is written as soon
+/// as it is read, so memory use stays constant however long the input is,
+/// but there is no list array to fall back on and nested values are an
+/// error. Otherwise the records are held until the count for the header is
+/// known.
+///
+/// [`EmptyCellPolicy::Omit`]: crate::options::EmptyCellPolicy::Omit
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::ndjson_to_toon;
+///
+/// let ndjson = "{\"sku\": \"A1\", \"qty\": 2}\n{\"sku\": \"B2\", \"qty\": 1}\n";
+/// let mut toon = Vec::new();
+/// ndjson_to_toon(ndjson.as_bytes(), &mut toon, None)?;
+/// assert_eq!(toon, b"[2]{qty,sku}:\n  2,A1\n  1,B2\n");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn ndjson_to_toon<R: BufRead, W: Write>(
+    reader: R,
+    writer: &mut W,
+    options: Option<&EncodeOptions>,
+) -> Result<(), Error> {
+    ndjson_to_toon_with(reader, writer, options, Some)
+}
+
+/// Convert newline-delimited JSON records to a TOON table, passing each
+/// record through `transform`
+///
+/// This is [`ndjson_to_toon`] with a step in between: returning `None`
+/// drops the record, and the columns come from the first record that is
+/// kept. Errors still point at the line the record was read from.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::ndjson::ndjson_to_toon_with;
+///
+/// let ndjson = "{\"id\": 1, \"score\": 0.4}\n{\"id\": 2, \"score\": 0.9}\n";
+/// let mut toon = Vec::new();
+/// ndjson_to_toon_with(ndjson.as_bytes(), &mut toon, None, |record| {
+///     (record["score"].as_f64() > Some(0.5)).then_some(record)
+/// })?;
+/// assert_eq!(toon, b"[1]{id,score}:\n  2,0.9\n");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn ndjson_to_toon_with<R, W, F>(
+    mut reader: R,
+    writer: &mut W,
+    options: Option<&EncodeOptions>,
+    mut transform: F,
+) -> Result<(), Error>
+where
+    R: BufRead,
+    W: Write,
+    F: FnMut(Value) -> Option<Value>,
+{
+    let default_opts = EncodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let mut table = TableSink::new(&mut *writer, opts);
+    // Kept while the table is held back, in case a later record needs the
+    // list array instead
+    let mut records = Vec::new();
+    let mut list = false;
+    let mut line = String::new();
+    let mut line_number = 0;
+    let mut offset = 0;
+    loop {
+        line.clear();
+        let n = reader
+            .read_line(&mut line)
+            .map_err(|e| Error::Io(e.to_string()))?;
+        if n == 0 {
+            break;
+        }
+        line_number += 1;
+        let start = offset;
+        offset += n;
+        let text = line.trim_end_matches(['\n', '\r']);
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        let record: Value = serde_json::from_str(text).map_err(|e| {
+            let message = e.to_string();
+            let message = message
+                .rsplit_once(" at line ")
+                .map_or(message.as_str(), |(message, _)| message);
+            Error::parse(
+                start + e.column().saturating_sub(1),
+                line_number,
+                e.column().max(1),
+                format!("Invalid JSON record: {message}"),
+            )
+        })?;
+        let Some(record) = transform(record) else {
+            continue;
+        };
+        let fail = |message: String| Error::parse(start, line_number, 1, message);
+        if !list && opts.get_emit_lengths() && !is_row(&record, opts) {
+            list = true;
+        }
+        match list {
+            false => {
+                table.check(&record).map_err(fail)?;
+                table.write(&record)?;
+            }
+            true if !record.is_object() => {
+                return Err(fail("Expected a JSON object record".to_string()))
+            }
+            true => {}
+        }
+        if opts.get_emit_lengths() {
+            records.push(record);
+        }
+    }
+    match list {
+        true => encode_stream(&Value::Array(records), writer, Some(opts)),
+        false => table.finish(),
+    }
+}
+
+/// Whether `record` has no value that can't be a table cell
+fn is_row(record: &Value, options: &EncodeOptions) -> bool {
+    record
+        .as_object()
+        .is_none_or(|obj| column_paths(obj, None, options, &mut Vec::new()).is_some())
+}
+
+/// Convert a TOON root array to newline-delimited JSON, one item per line
+///
+/// The TOON text is read whole, but its items are decoded and written one
+/// at a time through [`decode_iter`], so the array itself is never built.
+/// The first malformed item stops the conversion with its error; items
+/// before it have already been written.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::toon_to_ndjson;
+///
+/// let toon = "[2]{sku,qty}:\n  A1,2\n  B2,1";
+/// let mut ndjson = Vec::new();
+/// toon_to_ndjson(toon.as_bytes(), &mut ndjson, None)?;
+/// assert_eq!(ndjson, b"{\"qty\":2,\"sku\":\"A1\"}\n{\"qty\":1,\"sku\":\"B2\"}\n");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn toon_to_ndjson<R: Read, W: Write>(
+    reader: R,
+    writer: &mut W,
+    options: Option<&DecodeOptions>,
+) -> Result<(), Error> {
+    toon_to_ndjson_with(reader, writer, options, Some)
+}
+
+/// Convert a TOON root array to newline-delimited JSON, passing each item
+/// through `transform`
+///
+/// This is [`toon_to_ndjson`] with a step in between; returning `None`
+/// drops the item.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::ndjson::toon_to_ndjson_with;
+///
+/// let toon = "[2]{id,token}:\n  1,s3cr3t\n  2,hunter2";
+/// let mut ndjson = Vec::new();
+/// toon_to_ndjson_with(toon.as_bytes(), &mut ndjson, None, |mut item| {
+///     item.as_object_mut()?.remove("token");
+///     Some(item)
+/// })?;
+/// assert_eq!(ndjson, b"{\"id\":1}\n{\"id\":2}\n");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn toon_to_ndjson_with<R, W, F>(
+    mut reader: R,
+    writer: &mut W,
+    options: Option<&DecodeOptions>,
+    mut transform: F,
+) -> Result<(), Error>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(Value) -> Option<Value>,
+{
+    let mut input = String::new();
+    reader
+        .read_to_string(&mut input)
+        .map_err(|e| Error::Io(e.to_string()))?;
+    for item in decode_iter(&input, options) {
+        let Some(item) = transform(item?) else {
+            continue;
+        };
+        serde_json::to_writer(&mut *writer, &item)
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        writer
+            .write_all(b"\n")
+            .map_err(|e| Error::Io(e.to_string()))?;
+    }
+    writer.flush().map_err(|e| Error::Io(e.to_string()))
+}
@@ -1,7 +1,15 @@
 //! Options for encoding and decoding TOON format
 
+use crate::error::Error;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
 /// Delimiter character for tabular arrays
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum Delimiter {
     /// Comma delimiter (default)
     #[default]
@@ -23,6 +31,347 @@ impl Delimiter {
     }
 }
 
+/// How non-finite floats (`NaN`, `inf`, `-inf`) are encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum NanPolicy {
+    /// Encode non-finite floats as null (default, matches `serde_json`)
+    #[default]
+    Null,
+    /// Fail with a serialization error
+    Error,
+    /// Emit the `nan`, `inf` and `-inf` literals
+    ///
+    /// Serializing through serde carries each float as a single-key object
+    /// under a private key, which serde maps may not use. The same object is
+    /// what [`DecodeOptions::allow_nan`] decodes the literals to, and it is
+    /// written under whichever policy is set.
+    Token,
+}
+
+/// When string values are quoted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum QuotingPolicy {
+    /// Quote any string that could be misread in some position, such as one
+    /// containing a space or the delimiter (default)
+    #[default]
+    Minimal,
+    /// Quote every string
+    Always,
+    /// Quote only what would be misread where the string is written: spaces
+    /// are fine inside table cells and delimiters are fine in object values
+    WhenAmbiguous,
+}
+
+/// Whitespace style of encoded output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Style {
+    /// No space after a key's colon (`key:value`), for the fewest tokens
+    Compact,
+    /// A space after each key's colon (`key: value`) (default); combine with
+    /// [`EncodeOptions::align_columns`] for tables people read
+    #[default]
+    Pretty,
+}
+
+/// How null object values are encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum NullPolicy {
+    /// Leave the key out entirely; it decodes as absent rather than null
+    OmitKey,
+    /// Write `key: null` (default)
+    #[default]
+    EmitNullLiteral,
+    /// Write the key with an empty value (`key:`), which decodes as null
+    EmptyCell,
+}
+
+/// How byte strings read from binary formats, such as MessagePack `bin`
+/// values and CBOR byte strings, are written in TOON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum BytesPolicy {
+    /// A standard, padded base64 string (default)
+    #[default]
+    Base64,
+    /// An array of the byte values
+    Array,
+}
+
+/// Direction of a sort
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Order {
+    /// Smallest first (default)
+    #[default]
+    Asc,
+    /// Largest first
+    Desc,
+}
+
+/// A sort of one table's rows, set with [`EncodeOptions::sort_table_by`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TableSort {
+    /// Path to the table, as taken by [`crate::pointer::get`]
+    pub path: String,
+    /// Column the rows are ordered by
+    pub column: String,
+    /// Direction of the order
+    pub order: Order,
+}
+
+/// Type declared for a table column in its header (`qty:int`)
+///
+/// The decoder holds the column's cells to it instead of guessing each
+/// cell's type from its text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum ColumnType {
+    /// `str`: unquoted cells are kept as written, so `04532` stays a string
+    Str,
+    /// `int`: integers
+    Int,
+    /// `float`: numbers, decoded as floats
+    Float,
+    /// `bool`: `true` or `false`
+    Bool,
+}
+
+impl ColumnType {
+    /// The type's name in a header
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ColumnType::Str => "str",
+            ColumnType::Int => "int",
+            ColumnType::Float => "float",
+            ColumnType::Bool => "bool",
+        }
+    }
+
+    /// The type with the given name in a header
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "str" => Some(ColumnType::Str),
+            "int" => Some(ColumnType::Int),
+            "float" => Some(ColumnType::Float),
+            "bool" => Some(ColumnType::Bool),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Revision of the TOON specification to keep to, set with
+/// [`EncodeOptions::spec_version`] and [`DecodeOptions::spec_version`]
+///
+/// Only syntax the revision defines is allowed: this crate's own
+/// extensions, such as typed headers, are refused under either one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+pub enum SpecVersion {
+    /// Spec 1, where an array length may carry a `#` marker (`[#3]`)
+    V1,
+    /// Spec 2, which dropped the `#` length marker
+    V2,
+}
+
+impl SpecVersion {
+    /// The revision's major version number
+    pub fn number(self) -> u32 {
+        match self {
+            SpecVersion::V1 => 1,
+            SpecVersion::V2 => 2,
+        }
+    }
+}
+
+impl fmt::Display for SpecVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TOON spec {}", self.number())
+    }
+}
+
+/// Hook invoked for every leaf (primitive) value before it is written
+///
+/// Returning `Some` replaces the value in the output; returning `None` keeps
+/// it unchanged. The replacement should itself be a primitive. `key` is the
+/// name of the field (or table column) the value belongs to, if any.
+///
+/// Closures of the form `Fn(Option<&str>, &Value) -> Option<Value>` implement
+/// this trait, so redacting a secret is a one-liner:
+///
+/// ```rust
+/// use serde_json::{json, Value};
+/// use toon_rust::{encode, EncodeOptions};
+///
+/// let options = EncodeOptions::new().value_transformer(|key: Option<&str>, _: &Value| {
+///     (key == Some("password")).then(|| json!("***"))
+/// });
+/// let toon = encode(&json!({"user": "ada", "password": "hunter2"}), Some(&options)).unwrap();
+/// assert!(toon.contains("password: ***"));
+/// ```
+pub trait ValueTransformer: Send + Sync {
+    /// Transform a leaf value, or return `None` to keep it as is
+    fn transform(&self, key: Option<&str>, value: &Value) -> Option<Value>;
+}
+
+impl<F> ValueTransformer for F
+where
+    F: Fn(Option<&str>, &Value) -> Option<Value> + Send + Sync,
+{
+    fn transform(&self, key: Option<&str>, value: &Value) -> Option<Value> {
+        self(key, value)
+    }
+}
+
+impl fmt::Debug for dyn ValueTransformer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ValueTransformer")
+    }
+}
+
+/// How far a streaming encode or decode has got, passed to a
+/// [`ProgressCallback`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress<'a> {
+    /// Bytes written, when encoding, or parsed, when decoding, so far
+    pub bytes: usize,
+    /// Table rows and list items written or parsed so far, at any depth
+    pub rows: usize,
+    /// Key of the top-level entry being processed, when the document is an
+    /// object
+    pub section: Option<&'a str>,
+}
+
+/// Hook reporting the progress of [`crate::encode_stream`] and
+/// [`crate::decode_stream`], to drive a progress bar or export metrics
+///
+/// It is called as each top-level entry starts, after every table row and
+/// list item, and once more at the end, so it should be cheap; throttle
+/// inside it if the work it does is not. Closures taking `&Progress`
+/// implement this trait:
+///
+/// ```rust
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use serde_json::json;
+/// use toon_rust::options::Progress;
+/// use toon_rust::{encode_stream, EncodeOptions};
+///
+/// let rows = Arc::new(AtomicUsize::new(0));
+/// let seen = rows.clone();
+/// let options = EncodeOptions::new().progress(move |progress: &Progress| {
+///     seen.store(progress.rows, Ordering::Relaxed);
+/// });
+/// let value = json!({"items": [{"id": 1}, {"id": 2}]});
+/// encode_stream(&value, &mut Vec::new(), Some(&options)).unwrap();
+/// assert_eq!(rows.load(Ordering::Relaxed), 2);
+/// ```
+pub trait ProgressCallback: Send + Sync {
+    /// Report the progress made so far
+    fn report(&self, progress: &Progress<'_>);
+}
+
+impl<F> ProgressCallback for F
+where
+    F: Fn(&Progress<'_>) + Send + Sync,
+{
+    fn report(&self, progress: &Progress<'_>) {
+        self(progress)
+    }
+}
+
+impl fmt::Debug for dyn ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// A table row as written, split into cells but not yet decoded, passed to
+/// a [`RowFilter`]
+#[derive(Debug, Clone, Copy)]
+pub struct RawRow<'a> {
+    fields: &'a [&'a str],
+    cells: &'a [&'a str],
+}
+
+impl<'a> RawRow<'a> {
+    pub(crate) fn new(fields: &'a [&'a str], cells: &'a [&'a str]) -> Self {
+        RawRow { fields, cells }
+    }
+
+    /// The fields of the table's header
+    pub fn fields(&self) -> &'a [&'a str] {
+        self.fields
+    }
+
+    /// Number of cells in the row
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Whether the row has no cells
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// The text of the cell at `index`, trimmed, with any quotes kept
+    pub fn cell(&self, index: usize) -> Option<&'a str> {
+        self.cells.get(index).map(|cell| cell.trim())
+    }
+
+    /// The text of the cell under `field`, trimmed, with any quotes kept
+    pub fn get(&self, field: &str) -> Option<&'a str> {
+        let index = self.fields.iter().position(|name| *name == field)?;
+        self.cell(index)
+    }
+}
+
+/// Hook deciding which table rows to decode, from their raw cells
+///
+/// It runs before a row's object is built, so rows it rejects cost no
+/// allocation. Rejected rows still count towards the table's declared
+/// length. Closures taking `&RawRow` implement this trait:
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::options::RawRow;
+/// use toon_rust::{decode, DecodeOptions};
+///
+/// let options = DecodeOptions::new().row_filter(|row: &RawRow| row.get("qty") != Some("0"));
+/// let value = decode("items[3]{sku,qty}:\n  A1,2\n  B2,0\n  C3,5", Some(&options)).unwrap();
+/// assert_eq!(value, json!({"items": [{"sku": "A1", "qty": 2}, {"sku": "C3", "qty": 5}]}));
+/// ```
+pub trait RowFilter: Send + Sync {
+    /// Whether to decode the row
+    fn keep(&self, row: &RawRow<'_>) -> bool;
+}
+
+impl<F> RowFilter for F
+where
+    F: Fn(&RawRow<'_>) -> bool + Send + Sync,
+{
+    fn keep(&self, row: &RawRow<'_>) -> bool {
+        self(row)
+    }
+}
+
+impl fmt::Debug for dyn RowFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RowFilter")
+    }
+}
+
 /// Options for encoding TOON format
 #[derive(Debug, Clone, Default)]
 pub struct EncodeOptions {
@@ -32,6 +381,71 @@ pub struct EncodeOptions {
     pub length_marker: Option<char>,
     /// Number of spaces per indentation level (default: 2)
     pub indent: Option<usize>,
+    /// Text written for each indentation level, overriding `indent`
+    pub indent_string: Option<String>,
+    /// Handling of non-finite floats (default: null)
+    pub nan_policy: Option<NanPolicy>,
+    /// Fixed number of fractional digits for floats (default: shortest round-trip form)
+    pub float_precision: Option<usize>,
+    /// Hook applied to every leaf value before it is written
+    pub value_transformer: Option<Arc<dyn ValueTransformer>>,
+    /// Hook told how far [`crate::encode_stream`] has got
+    pub progress: Option<Arc<dyn ProgressCallback>>,
+    /// Write non-ASCII characters in string values as `\uXXXX` escapes (default: false)
+    pub escape_non_ascii: Option<bool>,
+    /// Write multi-line string values as `|` block strings (default: false)
+    pub block_strings: Option<bool>,
+    /// Handling of null object values (default: `null` literal)
+    pub null_policy: Option<NullPolicy>,
+    /// When string values are quoted (default: minimal)
+    pub quoting: Option<QuotingPolicy>,
+    /// Quote strings with leading or trailing whitespace (default: true)
+    pub quote_padded_strings: Option<bool>,
+    /// Quote the strings `true`, `false` and `null` (default: true)
+    pub quote_reserved_words: Option<bool>,
+    /// Pad table cells so columns line up (default: false)
+    pub align_columns: Option<bool>,
+    /// Most items a primitive array may have on one line (default: unlimited)
+    pub max_inline_items: Option<usize>,
+    /// Widest line an inline primitive array may produce (default: unlimited)
+    pub max_line_width: Option<usize>,
+    /// Fewest objects an array needs to be written as a table (default: 1)
+    pub min_tabular_rows: Option<usize>,
+    /// Most rows written for each table, the rest replaced by a marker (default: unlimited)
+    pub max_rows_per_table: Option<usize>,
+    /// Most keys an array of objects may have to be written as a table (default: unlimited)
+    pub max_tabular_columns: Option<usize>,
+    /// Share of objects that must hold every key for a table (default: 1.0)
+    pub tabular_uniformity: Option<f64>,
+    /// Fold chains of single-key objects into dotted keys (default: false)
+    pub key_folding: Option<bool>,
+    /// Write array lengths in headers (default: true)
+    pub emit_lengths: Option<bool>,
+    /// Repeat a tab or pipe delimiter in array lengths, as in `[2|]` (default: false)
+    pub length_delimiter: Option<bool>,
+    /// Write a space after an inline array's colon (default: false)
+    pub inline_array_space: Option<bool>,
+    /// Pick the shorter of the table and list layouts for each array (default: false)
+    pub optimize_for_tokens: Option<bool>,
+    /// Whitespace style (default: pretty)
+    pub style: Option<Style>,
+    /// Mark values the default layout loses so they decode back exactly (default: false)
+    pub roundtrip_safe: Option<bool>,
+    /// Row orders for tables, by path (default: rows as given)
+    pub table_sorts: Option<Vec<TableSort>>,
+    /// Declare each table column's type in its header (default: false)
+    pub typed_headers: Option<bool>,
+    /// Smallest share of a table's rows one value must fill to become its
+    /// column's default (default: no defaults)
+    pub column_defaults: Option<f64>,
+    /// Refer to the strings of repetitive table columns by index into a
+    /// dictionary in the header (default: false)
+    pub dictionary_encoding: Option<bool>,
+    /// Specification revision the output keeps to (default: none, any
+    /// syntax this crate writes)
+    pub spec_version: Option<SpecVersion>,
+    /// Form of byte strings read from binary formats (default: base64)
+    pub bytes_policy: Option<BytesPolicy>,
 }
 
 impl EncodeOptions {
@@ -40,6 +454,69 @@ impl EncodeOptions {
         Self::default()
     }
 
+    /// Options matching a configuration of the reference TypeScript
+    /// library, `@toon-format/toon`, for porting code that calls its
+    /// `encode`
+    ///
+    /// Takes the library's option names: `indent`, `delimiter` (`","`, `"\t"`
+    /// or `"|"`), `lengthMarker` (`"#"` or `false`), `keyFolding` (`"off"`
+    /// or `"safe"`) and `flattenDepth` (only `null`, for no limit). Its
+    /// decode options `strict` and `expandPaths` are skipped, so one object
+    /// can configure both directions with [`DecodeOptions::from_toon_js`].
+    /// Strings are quoted under [`QuotingPolicy::WhenAmbiguous`], the
+    /// policy nearest the library's rules, and inline arrays and lengths
+    /// are written as the library writes them (`tags[2|]: a|b`), through
+    /// [`inline_array_space`](Self::inline_array_space) and
+    /// [`length_delimiter`](Self::length_delimiter).
+    ///
+    /// Unknown names and unsupported values are deserialization errors
+    /// naming the option. The library also leaves no space after the colon
+    /// of a key holding an object (`user:`) and no blank line or final
+    /// newline after a table, where this crate's layout has them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde_json::json;
+    /// use toon_rust::{encode, EncodeOptions};
+    ///
+    /// let config = json!({"delimiter": "|", "lengthMarker": "#", "strict": true});
+    /// let options = EncodeOptions::from_toon_js(&config).unwrap();
+    /// let value = json!({"users": [{"id": 1, "name": "Ada Lovelace"}]});
+    /// assert_eq!(
+    ///     encode(&value, Some(&options)).unwrap(),
+    ///     "users[#1|]{id|name}:\n  1|Ada Lovelace\n"
+    /// );
+    /// assert!(EncodeOptions::from_toon_js(&json!({"delimter": ","})).is_err());
+    /// ```
+    pub fn from_toon_js(config: &Value) -> Result<Self, Error> {
+        let mut options = EncodeOptions::new()
+            .quoting(QuotingPolicy::WhenAmbiguous)
+            .inline_array_space(true)
+            .length_delimiter(true);
+        for (name, value) in toon_js_entries(config)? {
+            options = match (name.as_str(), value) {
+                ("indent", _) => options.indent(toon_js_indent(name, value)?),
+                ("delimiter", Value::String(delimiter)) => match delimiter.as_str() {
+                    "," => options.delimiter(Delimiter::Comma),
+                    "\t" => options.delimiter(Delimiter::Tab),
+                    "|" => options.delimiter(Delimiter::Pipe),
+                    _ => return Err(toon_js_error(name)),
+                },
+                ("lengthMarker", Value::String(marker)) if marker == "#" => {
+                    options.length_marker('#')
+                }
+                ("lengthMarker", Value::Bool(false)) | ("flattenDepth", Value::Null) => options,
+                ("keyFolding", Value::String(mode)) if mode == "off" || mode == "safe" => {
+                    options.key_folding(mode == "safe")
+                }
+                ("strict" | "expandPaths", _) => options,
+                _ => return Err(toon_js_error(name)),
+            };
+        }
+        Ok(options)
+    }
+
     /// Set the delimiter
     pub fn delimiter(mut self, delimiter: Delimiter) -> Self {
         self.delimiter = Some(delimiter);
@@ -53,11 +530,395 @@ impl EncodeOptions {
     }
 
     /// Set the indentation level
+    ///
+    /// Encoding fails with a width of 0.
     pub fn indent(mut self, indent: usize) -> Self {
         self.indent = Some(indent);
         self
     }
 
+    /// Set the text written for each indentation level, such as `"\t"`
+    ///
+    /// Takes precedence over [`indent`](Self::indent). Decode the output
+    /// with the same unit via [`DecodeOptions::indent_string`]. Encoding
+    /// fails unless the unit is one or more spaces or tabs.
+    pub fn indent_string(mut self, unit: impl Into<String>) -> Self {
+        self.indent_string = Some(unit.into());
+        self
+    }
+
+    /// Set the non-finite float policy
+    pub fn nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.nan_policy = Some(policy);
+        self
+    }
+
+    /// Format floats with a fixed number of fractional digits
+    pub fn float_precision(mut self, precision: usize) -> Self {
+        self.float_precision = Some(precision);
+        self
+    }
+
+    /// Set a hook that can rewrite leaf values during encoding
+    pub fn value_transformer(mut self, transformer: impl ValueTransformer + 'static) -> Self {
+        self.value_transformer = Some(Arc::new(transformer));
+        self
+    }
+
+    /// Set a hook that [`crate::encode_stream`] reports its progress to
+    pub fn progress(mut self, callback: impl ProgressCallback + 'static) -> Self {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Escape non-ASCII characters in string values as `\uXXXX`
+    ///
+    /// Strings containing them are quoted, and characters outside the Basic
+    /// Multilingual Plane are written as surrogate pairs, as in JSON.
+    pub fn escape_non_ascii(mut self, escape: bool) -> Self {
+        self.escape_non_ascii = Some(escape);
+        self
+    }
+
+    /// Write multi-line object values as YAML-style block strings
+    ///
+    /// ```text
+    /// notes: |-
+    ///   first line
+    ///   second line
+    /// ```
+    ///
+    /// `|` keeps a single trailing newline and `|-` means there is none.
+    /// Strings a block can't reproduce exactly stay quoted.
+    pub fn block_strings(mut self, enabled: bool) -> Self {
+        self.block_strings = Some(enabled);
+        self
+    }
+
+    /// Set how null object values are written
+    ///
+    /// Table cells and array items are unaffected.
+    pub fn null_policy(mut self, policy: NullPolicy) -> Self {
+        self.null_policy = Some(policy);
+        self
+    }
+
+    /// Set when string values are quoted
+    ///
+    /// Strings the decoder could never read back, such as empty or
+    /// numeric-looking ones, are quoted under every policy.
+    pub fn quoting(mut self, policy: QuotingPolicy) -> Self {
+        self.quoting = Some(policy);
+        self
+    }
+
+    /// Set whether strings with leading or trailing whitespace are quoted
+    ///
+    /// Unquoted cells are trimmed when decoded, so turning this off trades
+    /// the padding for fewer tokens. Object values still need quotes for
+    /// any whitespace, so this only matters for cells under
+    /// [`QuotingPolicy::WhenAmbiguous`].
+    pub fn quote_padded_strings(mut self, quote: bool) -> Self {
+        self.quote_padded_strings = Some(quote);
+        self
+    }
+
+    /// Set whether the strings `true`, `false` and `null` are quoted
+    ///
+    /// Left bare, they decode as booleans and null rather than strings.
+    pub fn quote_reserved_words(mut self, quote: bool) -> Self {
+        self.quote_reserved_words = Some(quote);
+        self
+    }
+
+    /// Pad table cells so that each column starts at the same offset
+    ///
+    /// ```text
+    /// users[2]{id,name,role}:
+    ///   1,  Alice,admin
+    ///   100,Bob,  user
+    /// ```
+    ///
+    /// Meant for TOON that people read before it goes into a prompt; the
+    /// padding costs tokens, and the decoder trims it from unquoted cells.
+    pub fn align_columns(mut self, align: bool) -> Self {
+        self.align_columns = Some(align);
+        self
+    }
+
+    /// Declare the type of each table column whose cells all share one, as
+    /// in `items[2]{sku:str,qty:int}:`
+    ///
+    /// Columns that are all null, or that mix types, integers and floats
+    /// included, are left without one. The decoder then reads the typed
+    /// columns strictly, rejecting cells of another type.
+    pub fn typed_headers(mut self, typed: bool) -> Self {
+        self.typed_headers = Some(typed);
+        self
+    }
+
+    /// Declare a default for each table column where one value fills at
+    /// least `share` of the rows, as in `items[3]{sku,region=EU}:`, and
+    /// leave the cells holding it empty
+    ///
+    /// The decoder fills empty cells of such a column with its default, so
+    /// a null in it is written `null`. A column only gets a default when
+    /// every row has it and the declaration is shorter than the cells it
+    /// empties; `1.0` keeps only constant columns. Applies to [`encode`]
+    /// and [`encode_stream`], not to the streaming serializer or the
+    /// incremental writers.
+    ///
+    /// [`encode`]: crate::encode
+    /// [`encode_stream`]: crate::encode_stream
+    pub fn column_defaults(mut self, share: f64) -> Self {
+        self.column_defaults = Some(share);
+        self
+    }
+
+    /// Declare a dictionary of the distinct strings of each table column
+    /// where that is shorter, as in `orders[3]{id,status@[shipped;pending]}:`,
+    /// and write the column's cells as their index into it
+    ///
+    /// Only columns of strings and nulls qualify, and a column given a
+    /// default keeps it instead. The most common strings get the shortest
+    /// codes. The decoder expands the codes back into the strings. Like
+    /// [`column_defaults`](Self::column_defaults), applies to
+    /// [`encode`](crate::encode) and [`encode_stream`](crate::encode_stream)
+    /// only.
+    pub fn dictionary_encoding(mut self, dictionary: bool) -> Self {
+        self.dictionary_encoding = Some(dictionary);
+        self
+    }
+
+    /// Keep the output to a revision of the TOON specification
+    ///
+    /// Encoding fails with a serialization error when another option asks
+    /// for syntax the revision lacks: the `#` length marker after spec 1,
+    /// or at any revision typed headers, column defaults, dictionary
+    /// columns, block strings, `nan`/`inf` literals, arrays without
+    /// lengths and row elision markers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde_json::json;
+    /// use toon_rust::options::SpecVersion;
+    /// use toon_rust::{encode, EncodeOptions};
+    ///
+    /// let value = json!({"tags": ["a", "b"]});
+    /// let v1 = EncodeOptions::new().length_marker('#').spec_version(SpecVersion::V1);
+    /// assert_eq!(encode(&value, Some(&v1)).unwrap(), "tags[#2]:a,b");
+    ///
+    /// let v2 = v1.spec_version(SpecVersion::V2);
+    /// assert!(encode(&value, Some(&v2)).is_err());
+    /// ```
+    pub fn spec_version(mut self, version: SpecVersion) -> Self {
+        self.spec_version = Some(version);
+        self
+    }
+
+    /// Set the form of byte strings read from binary formats
+    ///
+    /// Byte strings are written as base64 strings by default. JSON has no
+    /// bytes, so either way they decode as a string or an array; the
+    /// conversions back to binary formats don't turn them into bytes again.
+    pub fn bytes_policy(mut self, policy: BytesPolicy) -> Self {
+        self.bytes_policy = Some(policy);
+        self
+    }
+
+    /// Write primitive arrays with more items than this as `- item` lists
+    pub fn max_inline_items(mut self, max: usize) -> Self {
+        self.max_inline_items = Some(max);
+        self
+    }
+
+    /// Write primitive arrays as `- item` lists when their inline line,
+    /// including indentation and key, would be wider than this many characters
+    pub fn max_line_width(mut self, max: usize) -> Self {
+        self.max_line_width = Some(max);
+        self
+    }
+
+    /// Write arrays with fewer objects than this in the list format
+    pub fn min_tabular_rows(mut self, min: usize) -> Self {
+        self.min_tabular_rows = Some(min);
+        self
+    }
+
+    /// Write only the first `max` rows of each table, followed by a marker
+    /// line such as `… (9950 more rows)` in place of the rest
+    ///
+    /// The header still declares the full length, so a preview put into a
+    /// prompt says how much was left out. The decoder reads the marker as
+    /// the end of the table; strict decoding accepts the shortfall it
+    /// accounts for only with [`DecodeOptions::allow_elided_rows`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde_json::json;
+    /// use toon_rust::{decode, encode, DecodeOptions, EncodeOptions};
+    ///
+    /// let value = json!({"ids": [{"id": 1}, {"id": 2}, {"id": 3}]});
+    /// let options = EncodeOptions::new().max_rows_per_table(1);
+    /// let toon = encode(&value, Some(&options)).unwrap();
+    /// assert_eq!(toon, "ids[3]{id}:\n  1\n  … (2 more rows)\n");
+    /// assert!(decode(&toon, None).is_err());
+    /// let preview = DecodeOptions::new().allow_elided_rows(true);
+    /// assert_eq!(decode(&toon, Some(&preview)).unwrap(), json!({"ids": [{"id": 1}]}));
+    /// ```
+    pub fn max_rows_per_table(mut self, max: usize) -> Self {
+        self.max_rows_per_table = Some(max);
+        self
+    }
+
+    /// Write arrays of objects with more distinct keys than this in the list format
+    pub fn max_tabular_columns(mut self, max: usize) -> Self {
+        self.max_tabular_columns = Some(max);
+        self
+    }
+
+    /// Write an array of objects as a table when at least this share of
+    /// them (between 0.0 and 1.0) hold every key that appears in any of them
+    ///
+    /// The header lists every key, and objects missing some get empty cells
+    /// for them. Nulls in such tables are written as `null`, so decoding
+    /// with [`EmptyCellPolicy::Omit`] tells missing fields from null ones.
+    /// At the default of 1.0, only arrays whose objects all have the same
+    /// keys become tables.
+    pub fn tabular_uniformity(mut self, ratio: f64) -> Self {
+        self.tabular_uniformity = Some(ratio);
+        self
+    }
+
+    /// Fold chains of single-key objects into one dotted key
+    ///
+    /// ```text
+    /// server.http.port: 8080
+    /// ```
+    ///
+    /// instead of three nested levels. Nested objects in table rows are
+    /// flattened into dotted columns the same way (`users[2]{id,name.first}:`),
+    /// so those arrays stay tables. Keys that already contain a `.` are never
+    /// folded and are written quoted, a chain isn't folded into a path
+    /// spelled like one of its sibling keys, and rows holding a dotted key
+    /// aren't made a table, so decoding with [`DecodeOptions::expand_paths`]
+    /// restores the original nesting.
+    pub fn key_folding(mut self, enabled: bool) -> Self {
+        self.key_folding = Some(enabled);
+        self
+    }
+
+    /// Write array headers without lengths
+    ///
+    /// Tables become `items{sku,qty}:` and other arrays `tags[]: a,b`, which
+    /// saves tokens and lets rows be added or removed by hand without
+    /// fixing up a count. Strict decoding needs a length, so decode the
+    /// output with [`DecodeOptions::strict`] off or with
+    /// [`DecodeOptions::lenient_arrays`], either of which infers lengths
+    /// from the content.
+    pub fn emit_lengths(mut self, emit: bool) -> Self {
+        self.emit_lengths = Some(emit);
+        self
+    }
+
+    /// Repeat a tab or pipe delimiter inside each array length
+    ///
+    /// Headers become `tags[2|]: a|b` and `rows[2|]{id|name}:`, declaring
+    /// the delimiter as the TOON spec allows instead of leaving the decoder
+    /// to infer it from the fields and cells. A comma delimiter is never
+    /// written.
+    pub fn length_delimiter(mut self, enabled: bool) -> Self {
+        self.length_delimiter = Some(enabled);
+        self
+    }
+
+    /// Write a space after an inline array's colon (`tags[2]: a,b`), as
+    /// the reference TypeScript library does
+    pub fn inline_array_space(mut self, enabled: bool) -> Self {
+        self.inline_array_space = Some(enabled);
+        self
+    }
+
+    /// Set the whitespace style
+    ///
+    /// [`Style::Compact`] drops the space after each key's colon, except on
+    /// a list item's `- ` line, where the decoder needs it to recognize an
+    /// object. The decoder reads both styles.
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    /// Choose between the table and list layouts for each array of objects
+    /// by rendering both and keeping the shorter one
+    ///
+    /// The fixed heuristics ([`min_tabular_rows`](Self::min_tabular_rows),
+    /// [`tabular_uniformity`](Self::tabular_uniformity)) are ignored, so a
+    /// mostly uniform array becomes a sparse table when that is smaller, and
+    /// a sparse one stays a list when its empty cells would cost more.
+    /// Primitive arrays are always shortest inline, and keys are only folded
+    /// with [`key_folding`](Self::key_folding), since the decoder needs
+    /// [`DecodeOptions::expand_paths`] to restore them. Size is measured in
+    /// bytes of output, which tracks token counts closely for TOON.
+    pub fn optimize_for_tokens(mut self, optimize: bool) -> Self {
+        self.optimize_for_tokens = Some(optimize);
+        self
+    }
+
+    /// Write the output so that decoding it gives back the encoded value
+    ///
+    /// Empty objects are written as `{}` rather than as a bare key, a string
+    /// at the root is always quoted, and settings that drop information are
+    /// overridden: reserved words and padded strings are quoted, null fields
+    /// are kept under [`NullPolicy::OmitKey`], and keys are not folded.
+    /// [`float_precision`](Self::float_precision) and value transformers
+    /// still apply, since they change values on purpose.
+    pub fn roundtrip_safe(mut self, safe: bool) -> Self {
+        self.roundtrip_safe = Some(safe);
+        self
+    }
+
+    /// Sort the rows of the array at `path` by `column` before encoding
+    ///
+    /// `path` is a TOON path or JSON Pointer, as taken by
+    /// [`crate::pointer::get`], with `""` for a root array. Values are
+    /// ordered as `query` compares them, with rows missing the column
+    /// treated as null. Calling this again for the same path breaks ties in
+    /// the earlier sorts. Paths that don't lead to an array are left alone.
+    /// Applies to [`crate::encode`] and [`crate::encode_stream`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde_json::json;
+    /// use toon_rust::options::Order;
+    /// use toon_rust::{encode, EncodeOptions};
+    ///
+    /// let value = json!({"items": [{"sku": "A1", "price": 2}, {"sku": "B2", "price": 5}]});
+    /// let options = EncodeOptions::new().sort_table_by("items", "price", Order::Desc);
+    /// assert_eq!(
+    ///     encode(&value, Some(&options)).unwrap(),
+    ///     "items[2]{price,sku}:\n  5,B2\n  2,A1\n"
+    /// );
+    /// ```
+    pub fn sort_table_by(
+        mut self,
+        path: impl Into<String>,
+        column: impl Into<String>,
+        order: Order,
+    ) -> Self {
+        self.table_sorts
+            .get_or_insert_with(Vec::new)
+            .push(TableSort {
+                path: path.into(),
+                column: column.into(),
+                order,
+            });
+        self
+    }
+
     /// Get the delimiter, defaulting to comma
     pub fn get_delimiter(&self) -> char {
         self.delimiter.unwrap_or_default().as_char()
@@ -67,6 +928,191 @@ impl EncodeOptions {
     pub fn get_indent(&self) -> usize {
         self.indent.unwrap_or(2)
     }
+
+    /// Get the text for one indentation level, defaulting to
+    /// [`get_indent`](Self::get_indent) spaces
+    pub fn get_indent_string(&self) -> Cow<'_, str> {
+        match &self.indent_string {
+            Some(unit) => Cow::Borrowed(unit),
+            None => Cow::Owned(" ".repeat(self.get_indent())),
+        }
+    }
+
+    /// Get the non-finite float policy, defaulting to null
+    pub fn get_nan_policy(&self) -> NanPolicy {
+        self.nan_policy.unwrap_or_default()
+    }
+
+    /// Get non-ASCII escaping, defaulting to false
+    pub fn get_escape_non_ascii(&self) -> bool {
+        self.escape_non_ascii.unwrap_or(false)
+    }
+
+    /// Get block string output, defaulting to false
+    pub fn get_block_strings(&self) -> bool {
+        self.block_strings.unwrap_or(false)
+    }
+
+    /// Get the quoting policy, defaulting to minimal
+    pub fn get_quoting(&self) -> QuotingPolicy {
+        self.quoting.unwrap_or_default()
+    }
+
+    /// Get quoting of padded strings, defaulting to true
+    pub fn get_quote_padded_strings(&self) -> bool {
+        self.get_roundtrip_safe() || self.quote_padded_strings.unwrap_or(true)
+    }
+
+    /// Get quoting of reserved words, defaulting to true
+    pub fn get_quote_reserved_words(&self) -> bool {
+        self.get_roundtrip_safe() || self.quote_reserved_words.unwrap_or(true)
+    }
+
+    /// Get column alignment, defaulting to false
+    pub fn get_align_columns(&self) -> bool {
+        self.align_columns.unwrap_or(false)
+    }
+
+    /// Get whether table headers declare column types, defaulting to false
+    pub fn get_typed_headers(&self) -> bool {
+        self.typed_headers.unwrap_or(false)
+    }
+
+    /// Get the share of rows a column default must fill, if defaults are on
+    pub fn get_column_defaults(&self) -> Option<f64> {
+        self.column_defaults
+    }
+
+    /// Get whether repetitive string columns use dictionaries, defaulting
+    /// to false
+    pub fn get_dictionary_encoding(&self) -> bool {
+        self.dictionary_encoding.unwrap_or(false)
+    }
+
+    /// Get the inline item limit, defaulting to unlimited
+    pub fn get_max_inline_items(&self) -> usize {
+        self.max_inline_items.unwrap_or(usize::MAX)
+    }
+
+    /// Get the inline line width limit, defaulting to unlimited
+    pub fn get_max_line_width(&self) -> usize {
+        self.max_line_width.unwrap_or(usize::MAX)
+    }
+
+    /// Get the most rows written for each table, defaulting to unlimited
+    pub fn get_max_rows_per_table(&self) -> usize {
+        self.max_rows_per_table.unwrap_or(usize::MAX)
+    }
+
+    /// Get the minimum table row count, defaulting to 1
+    pub fn get_min_tabular_rows(&self) -> usize {
+        self.min_tabular_rows.unwrap_or(1)
+    }
+
+    /// Get the maximum table column count, defaulting to unlimited
+    pub fn get_max_tabular_columns(&self) -> usize {
+        self.max_tabular_columns.unwrap_or(usize::MAX)
+    }
+
+    /// Get the table uniformity ratio, defaulting to 1.0
+    pub fn get_tabular_uniformity(&self) -> f64 {
+        self.tabular_uniformity.unwrap_or(1.0)
+    }
+
+    /// Get the null policy, defaulting to the `null` literal
+    pub fn get_null_policy(&self) -> NullPolicy {
+        match self.null_policy.unwrap_or_default() {
+            NullPolicy::OmitKey if self.get_roundtrip_safe() => NullPolicy::EmitNullLiteral,
+            policy => policy,
+        }
+    }
+
+    /// Get key folding, defaulting to false
+    pub fn get_key_folding(&self) -> bool {
+        !self.get_roundtrip_safe() && self.key_folding.unwrap_or(false)
+    }
+
+    /// Get length output, defaulting to true
+    pub fn get_emit_lengths(&self) -> bool {
+        self.emit_lengths.unwrap_or(true)
+    }
+
+    /// Get delimiter repetition in array lengths, defaulting to false
+    pub fn get_length_delimiter(&self) -> bool {
+        self.length_delimiter.unwrap_or(false)
+    }
+
+    /// Get the space after an inline array's colon, defaulting to false
+    pub fn get_inline_array_space(&self) -> bool {
+        self.inline_array_space.unwrap_or(false)
+    }
+
+    /// Get the whitespace style, defaulting to pretty
+    pub fn get_style(&self) -> Style {
+        self.style.unwrap_or_default()
+    }
+
+    /// Get layout optimization, defaulting to false
+    pub fn get_optimize_for_tokens(&self) -> bool {
+        self.optimize_for_tokens.unwrap_or(false)
+    }
+
+    /// Get round-trip safety, defaulting to false
+    pub fn get_roundtrip_safe(&self) -> bool {
+        self.roundtrip_safe.unwrap_or(false)
+    }
+
+    /// Get the table sorts, in the order they were added
+    pub fn get_table_sorts(&self) -> &[TableSort] {
+        self.table_sorts.as_deref().unwrap_or_default()
+    }
+
+    /// Get the specification revision kept to, if any
+    pub fn get_spec_version(&self) -> Option<SpecVersion> {
+        self.spec_version
+    }
+
+    /// Get the form of byte strings, defaulting to base64
+    pub fn get_bytes_policy(&self) -> BytesPolicy {
+        self.bytes_policy.unwrap_or_default()
+    }
+}
+
+/// How repeated keys within one object are handled when decoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum DuplicateKeyPolicy {
+    /// Fail with a parse error at the repeated key
+    Error,
+    /// Keep the first value and ignore later ones
+    FirstWins,
+    /// Keep the last value (default, matches `serde_json`)
+    #[default]
+    LastWins,
+}
+
+/// How a table row or array whose size differs from its header is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum LengthPolicy {
+    /// Fail with a length mismatch error (default in strict mode)
+    Error,
+    /// Fill missing cells and items with null and drop extra ones
+    /// (default in non-strict mode)
+    Pad,
+    /// Drop extra cells and items, leaving short rows and arrays as they are
+    Truncate,
+}
+
+/// How empty table cells are decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum EmptyCellPolicy {
+    /// Decode them as null (default)
+    #[default]
+    Null,
+    /// Leave the field out of the row, as the encoder does for sparse tables
+    Omit,
 }
 
 /// Options for decoding TOON format
@@ -74,8 +1120,53 @@ impl EncodeOptions {
 pub struct DecodeOptions {
     /// Expected number of spaces per indentation level (default: 2)
     pub indent: Option<usize>,
+    /// Text expected for each indentation level, overriding `indent`
+    pub indent_string: Option<String>,
     /// Enable strict validation (default: true)
     pub strict: Option<bool>,
+    /// Accept `nan`, `inf` and `-inf` literals (default: false)
+    pub allow_nan: Option<bool>,
+    /// Accept hex (`0xFF`) and underscore-separated (`1_000`) integers (default: false)
+    pub lenient_numbers: Option<bool>,
+    /// Accept arrays without a declared length and infer it from the content (default: false)
+    pub lenient_arrays: Option<bool>,
+    /// Accept tables cut short by an elision marker when strict (default: false)
+    pub allow_elided_rows: Option<bool>,
+    /// Largest array length a header may declare (default: unlimited)
+    pub max_array_length: Option<usize>,
+    /// Largest number of tabular and inline array cells in a document (default: unlimited)
+    pub max_cells: Option<usize>,
+    /// Largest string value, in bytes (default: unlimited)
+    pub max_string_length: Option<usize>,
+    /// Handling of repeated keys in an object or table header (default: last wins)
+    pub duplicate_keys: Option<DuplicateKeyPolicy>,
+    /// Handling of rows and arrays that don't match their header (default: error when strict, pad otherwise)
+    pub length_policy: Option<LengthPolicy>,
+    /// Key that collects a table row's cells beyond the declared fields (default: none, they are dropped)
+    pub extra_cells_key: Option<String>,
+    /// Decode unquoted numeric literals as numbers (default: true)
+    pub coerce_numbers: Option<bool>,
+    /// Decode unquoted `true` and `false` as booleans (default: true)
+    pub coerce_booleans: Option<bool>,
+    /// Decode every tabular and inline array cell as a string (default: false)
+    pub cells_as_strings: Option<bool>,
+    /// Expand dotted keys (`a.b.c`) into nested objects (default: false)
+    pub expand_paths: Option<bool>,
+    /// Handling of empty table cells (default: null)
+    pub empty_cells: Option<EmptyCellPolicy>,
+    /// Wait between reads at the end of a growing input (default: none, stop at the end)
+    pub follow: Option<Duration>,
+    /// Dotted paths of the only entries to decode (default: all of them)
+    pub include_keys: Option<Vec<String>>,
+    /// The only table columns to decode (default: all of them)
+    pub include_columns: Option<Vec<String>>,
+    /// Hook deciding which table rows to decode
+    pub row_filter: Option<Arc<dyn RowFilter>>,
+    /// Hook told how far [`crate::decode_stream`] has got
+    pub progress: Option<Arc<dyn ProgressCallback>>,
+    /// Specification revision documents must keep to (default: none,
+    /// accept any revision and this crate's extensions)
+    pub spec_version: Option<SpecVersion>,
 }
 
 impl DecodeOptions {
@@ -84,25 +1175,624 @@ impl DecodeOptions {
         Self::default()
     }
 
+    /// Options matching a configuration of the reference TypeScript
+    /// library, `@toon-format/toon`, for porting code that calls its
+    /// `decode`
+    ///
+    /// Takes the library's option names: `indent`, `strict` and `expandPaths`
+    /// (`"off"` or `"safe"`). Its encode options are skipped, as in
+    /// [`EncodeOptions::from_toon_js`]; unknown names and unsupported
+    /// values are deserialization errors naming the option.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use serde_json::json;
+    /// use toon_rust::{decode, DecodeOptions};
+    ///
+    /// let config = json!({"indent": 4, "expandPaths": "safe", "delimiter": "|"});
+    /// let options = DecodeOptions::from_toon_js(&config).unwrap();
+    /// let value = decode("a.b: 1", Some(&options)).unwrap();
+    /// assert_eq!(value, json!({"a": {"b": 1}}));
+    /// ```
+    pub fn from_toon_js(config: &Value) -> Result<Self, Error> {
+        let mut options = DecodeOptions::new();
+        for (name, value) in toon_js_entries(config)? {
+            options = match (name.as_str(), value) {
+                ("indent", _) => options.indent(toon_js_indent(name, value)?),
+                ("strict", Value::Bool(strict)) => options.strict(*strict),
+                ("expandPaths", Value::String(mode)) if mode == "off" || mode == "safe" => {
+                    options.expand_paths(mode == "safe")
+                }
+                ("delimiter" | "lengthMarker" | "keyFolding" | "flattenDepth", _) => options,
+                _ => return Err(toon_js_error(name)),
+            };
+        }
+        Ok(options)
+    }
+
     /// Set the expected indentation level
     pub fn indent(mut self, indent: usize) -> Self {
         self.indent = Some(indent);
         self
     }
 
+    /// Set the text expected for each indentation level, matching
+    /// [`EncodeOptions::indent_string`]
+    ///
+    /// Takes precedence over [`indent`](Self::indent). A unit of spaces is
+    /// the same as setting `indent` to its length; any other unit, such as
+    /// `"\t"`, is replaced with spaces at the start of each line before
+    /// parsing, so error positions refer to the re-indented text.
+    pub fn indent_string(mut self, unit: impl Into<String>) -> Self {
+        self.indent_string = Some(unit.into());
+        self
+    }
+
     /// Set strict mode
+    ///
+    /// Non-strict decoding skips length validation and tolerates loose
+    /// whitespace: blank lines, trailing spaces, and indentation that is
+    /// inconsistent or uses a different unit than [`indent`](Self::indent).
     pub fn strict(mut self, strict: bool) -> Self {
         self.strict = Some(strict);
         self
     }
 
-    /// Get the indentation, defaulting to 2
+    /// Accept non-finite float literals
+    ///
+    /// In a decoded `Value` each literal is the single-key object that
+    /// [`NanPolicy::Token`] encodes back to the literal, and [`from_str`]
+    /// reads it into a float field as the non-finite float.
+    ///
+    /// [`from_str`]: crate::from_str
+    pub fn allow_nan(mut self, allow: bool) -> Self {
+        self.allow_nan = Some(allow);
+        self
+    }
+
+    /// Accept hex and underscore-separated integer literals as numbers
+    pub fn lenient_numbers(mut self, lenient: bool) -> Self {
+        self.lenient_numbers = Some(lenient);
+        self
+    }
+
+    /// Accept arrays whose length is missing, as hand-written and
+    /// LLM-generated TOON often omits it
+    ///
+    /// Empty brackets (`tags[]: a,b`, `rows[]{id}:`) and table headers
+    /// without brackets (`rows{id}:`) take their length from the content,
+    /// as they also do when not [`strict`](Self::strict). Without brackets,
+    /// a comma-separated inline value (`tags: a,b,c`) or an indented block
+    /// of `- item` lines decodes as an array too.
+    pub fn lenient_arrays(mut self, lenient: bool) -> Self {
+        self.lenient_arrays = Some(lenient);
+        self
+    }
+
+    /// Accept tables that end with the `… (N more rows)` marker written by
+    /// [`EncodeOptions::max_rows_per_table`] when strict
+    ///
+    /// The rows the marker counts make up the table's declared length, and
+    /// the decoded array holds only the rows that were written. Strict
+    /// decoding otherwise reports the missing rows as a length mismatch;
+    /// non-strict decoding always keeps the rows it has.
+    pub fn allow_elided_rows(mut self, allow: bool) -> Self {
+        self.allow_elided_rows = Some(allow);
+        self
+    }
+
+    /// Limit the array length a header may declare
+    ///
+    /// Use this with the other limits when decoding untrusted input, so a
+    /// header like `items[999999999]{...}:` is rejected up front.
+    pub fn max_array_length(mut self, max: usize) -> Self {
+        self.max_array_length = Some(max);
+        self
+    }
+
+    /// Limit the total number of tabular and inline array cells
+    pub fn max_cells(mut self, max: usize) -> Self {
+        self.max_cells = Some(max);
+        self
+    }
+
+    /// Limit the length of any single string value, in bytes
+    pub fn max_string_length(mut self, max: usize) -> Self {
+        self.max_string_length = Some(max);
+        self
+    }
+
+    /// Set how repeated keys within one object are handled
+    pub fn duplicate_keys(mut self, policy: DuplicateKeyPolicy) -> Self {
+        self.duplicate_keys = Some(policy);
+        self
+    }
+
+    /// Set how rows and arrays that don't match their header are handled
+    ///
+    /// A table row with a different number of cells than the header has
+    /// fields, or an array with a different number of items than it declares,
+    /// is an error under [`LengthPolicy::Error`] and is reshaped to fit
+    /// otherwise.
+    pub fn length_policy(mut self, policy: LengthPolicy) -> Self {
+        self.length_policy = Some(policy);
+        self
+    }
+
+    /// Keep a table row's cells beyond the declared fields as an array under `key`
+    ///
+    /// Only applies when the length policy isn't [`LengthPolicy::Error`].
+    pub fn extra_cells_key(mut self, key: impl Into<String>) -> Self {
+        self.extra_cells_key = Some(key.into());
+        self
+    }
+
+    /// Decode unquoted numeric literals as numbers
+    ///
+    /// Turn this off to keep values like zip codes (`04532`) or ids intact as
+    /// strings; the encoder quotes numeric-looking strings, so they still
+    /// round-trip either way.
+    pub fn coerce_numbers(mut self, coerce: bool) -> Self {
+        self.coerce_numbers = Some(coerce);
+        self
+    }
+
+    /// Decode unquoted `true` and `false` as booleans
+    pub fn coerce_booleans(mut self, coerce: bool) -> Self {
+        self.coerce_booleans = Some(coerce);
+        self
+    }
+
+    /// Decode every tabular and inline array cell as a string, for
+    /// schemaless ingestion
+    ///
+    /// Cells are taken verbatim, apart from unescaping quoted ones: `null`
+    /// stays the string `"null"` and an empty cell is `""`. Object values
+    /// are unaffected.
+    pub fn cells_as_strings(mut self, enabled: bool) -> Self {
+        self.cells_as_strings = Some(enabled);
+        self
+    }
+
+    /// Expand dotted keys into nested objects, undoing
+    /// [`EncodeOptions::key_folding`]
+    ///
+    /// `a.b: 1` and `a.c: 2` decode as `{"a": {"b": 1, "c": 2}}`. Paths that
+    /// meet a value that isn't an object are resolved with the
+    /// [duplicate key policy](Self::duplicate_keys).
+    /// Quoted keys such as `"a.b"`, including table header fields, are
+    /// literal, as are keys with empty segments, such as `.a` or `a..b`.
+    pub fn expand_paths(mut self, enabled: bool) -> Self {
+        self.expand_paths = Some(enabled);
+        self
+    }
+
+    /// Set how empty table cells are decoded
+    ///
+    /// The encoder leaves a cell empty when a row lacks that field, writing
+    /// nulls as `null` in such tables, so [`EmptyCellPolicy::Omit`] restores
+    /// the rows exactly. This also applies under
+    /// [`cells_as_strings`](Self::cells_as_strings).
+    pub fn empty_cells(mut self, policy: EmptyCellPolicy) -> Self {
+        self.empty_cells = Some(policy);
+        self
+    }
+
+    /// Keep reading past the end of the input, like `tail -f`
+    ///
+    /// [`decode_rows_stream`](crate::decode_rows_stream) then waits
+    /// `poll_interval` and reads again whenever it runs out of input, and
+    /// yields rows as they are appended. Other decoders ignore this option.
+    pub fn follow(mut self, poll_interval: Duration) -> Self {
+        self.follow = Some(poll_interval);
+        self
+    }
+
+    /// Decode only the entries at these dotted paths, such as `items` or
+    /// `meta.total`
+    ///
+    /// [`decode`](crate::decode) and [`decode_stream`](crate::decode_stream)
+    /// step over every other entry of the root object line by line without
+    /// parsing it, so it costs little to leave out. Objects on the way to a
+    /// selected path keep only what the selection reaches; a path that
+    /// meets an array or a primitive selects nothing. A document that isn't
+    /// an object is decoded whole.
+    pub fn include_keys<I>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let keys = keys.into_iter().map(|key| key.as_ref().to_string());
+        self.include_keys = Some(keys.collect());
+        self
+    }
+
+    /// Decode only these columns of tabular arrays
+    ///
+    /// Other cells of each row are split off but never parsed, and their
+    /// fields are left out of the row objects. This applies to every table
+    /// in the document and to the row iterators
+    /// ([`decode_rows`](crate::decode_rows),
+    /// [`decode_rows_stream`](crate::decode_rows_stream) and
+    /// [`decode_iter`](crate::decode_iter)); arrays of objects written as
+    /// lists are decoded whole.
+    pub fn include_columns<I>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let columns = columns
+            .into_iter()
+            .map(|column| column.as_ref().to_string());
+        self.include_columns = Some(columns.collect());
+        self
+    }
+
+    /// Set a hook that picks the table rows to decode, at any depth
+    ///
+    /// See [`RowFilter`]; it applies to [`crate::decode`],
+    /// [`crate::decode_stream`] and the row iterators alike.
+    pub fn row_filter(mut self, filter: impl RowFilter + 'static) -> Self {
+        self.row_filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Set a hook that [`crate::decode_stream`] reports its progress to
+    ///
+    /// Byte counts are of the text parsed, after any re-indentation.
+    pub fn progress(mut self, callback: impl ProgressCallback + 'static) -> Self {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Accept only documents written to a revision of the TOON
+    /// specification
+    ///
+    /// Without a revision the decoder reads documents of any revision. With
+    /// one, a `#` length marker after spec 1 or a column type, default or
+    /// dictionary in a table header is a parse error naming the revision.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use toon_rust::options::SpecVersion;
+    /// use toon_rust::{decode, DecodeOptions};
+    ///
+    /// assert!(decode("tags[#2]: a,b", None).is_ok());
+    /// let v2 = DecodeOptions::new().spec_version(SpecVersion::V2);
+    /// let err = decode("tags[#2]: a,b", Some(&v2)).unwrap_err();
+    /// assert!(err.to_string().contains("TOON spec 2"));
+    /// ```
+    pub fn spec_version(mut self, version: SpecVersion) -> Self {
+        self.spec_version = Some(version);
+        self
+    }
+
+    /// Get the indentation in spaces, defaulting to 2
+    ///
+    /// An indent string of spaces sets it to the string's length.
     pub fn get_indent(&self) -> usize {
-        self.indent.unwrap_or(2)
+        match self.get_indent_string() {
+            Some(unit) if unit.bytes().all(|byte| byte == b' ') => unit.len(),
+            _ => self.indent.unwrap_or(2),
+        }
+    }
+
+    /// Get the indent string, if one was set
+    pub fn get_indent_string(&self) -> Option<&str> {
+        self.indent_string.as_deref()
     }
 
     /// Get strict mode, defaulting to true
     pub fn get_strict(&self) -> bool {
         self.strict.unwrap_or(true)
     }
+
+    /// Get non-finite literal acceptance, defaulting to false
+    pub fn get_allow_nan(&self) -> bool {
+        self.allow_nan.unwrap_or(false)
+    }
+
+    /// Get lenient integer literal acceptance, defaulting to false
+    pub fn get_lenient_numbers(&self) -> bool {
+        self.lenient_numbers.unwrap_or(false)
+    }
+
+    /// Get length-optional array acceptance, defaulting to false
+    pub fn get_lenient_arrays(&self) -> bool {
+        self.lenient_arrays.unwrap_or(false)
+    }
+
+    /// Get elided table acceptance, defaulting to false
+    pub fn get_allow_elided_rows(&self) -> bool {
+        self.allow_elided_rows.unwrap_or(false)
+    }
+
+    /// Whether array headers without a length (`tags[]:`, `rows{id}:`), as
+    /// [`EncodeOptions::emit_lengths`] writes them, are read: with
+    /// `lenient_arrays` or when not strict
+    pub(crate) fn lengthless_headers(&self) -> bool {
+        self.get_lenient_arrays() || !self.get_strict()
+    }
+
+    /// Get the maximum declared array length, defaulting to unlimited
+    pub fn get_max_array_length(&self) -> usize {
+        self.max_array_length.unwrap_or(usize::MAX)
+    }
+
+    /// Get the maximum number of cells, defaulting to unlimited
+    pub fn get_max_cells(&self) -> usize {
+        self.max_cells.unwrap_or(usize::MAX)
+    }
+
+    /// Get the maximum string length, defaulting to unlimited
+    pub fn get_max_string_length(&self) -> usize {
+        self.max_string_length.unwrap_or(usize::MAX)
+    }
+
+    /// Get the duplicate key policy, defaulting to last wins
+    pub fn get_duplicate_keys(&self) -> DuplicateKeyPolicy {
+        self.duplicate_keys.unwrap_or_default()
+    }
+
+    /// Get the length policy, defaulting to error in strict mode and pad otherwise
+    pub fn get_length_policy(&self) -> LengthPolicy {
+        self.length_policy.unwrap_or(if self.get_strict() {
+            LengthPolicy::Error
+        } else {
+            LengthPolicy::Pad
+        })
+    }
+
+    /// Get numeric coercion, defaulting to true
+    pub fn get_coerce_numbers(&self) -> bool {
+        self.coerce_numbers.unwrap_or(true)
+    }
+
+    /// Get boolean coercion, defaulting to true
+    pub fn get_coerce_booleans(&self) -> bool {
+        self.coerce_booleans.unwrap_or(true)
+    }
+
+    /// Get string-only cell decoding, defaulting to false
+    pub fn get_cells_as_strings(&self) -> bool {
+        self.cells_as_strings.unwrap_or(false)
+    }
+
+    /// Get the empty cell policy, defaulting to null
+    pub fn get_empty_cells(&self) -> EmptyCellPolicy {
+        self.empty_cells.unwrap_or_default()
+    }
+
+    /// Get dotted key expansion, defaulting to false
+    pub fn get_expand_paths(&self) -> bool {
+        self.expand_paths.unwrap_or(false)
+    }
+
+    /// Get the catch-all key for extra row cells, if any
+    pub fn get_extra_cells_key(&self) -> Option<&str> {
+        self.extra_cells_key.as_deref()
+    }
+
+    /// Get the follow poll interval, if following is on
+    pub fn get_follow(&self) -> Option<Duration> {
+        self.follow
+    }
+
+    /// Get the paths of the entries to decode, if only some are wanted
+    pub fn get_include_keys(&self) -> Option<&[String]> {
+        self.include_keys.as_deref()
+    }
+
+    /// Get the table columns to decode, if only some are wanted
+    pub fn get_include_columns(&self) -> Option<&[String]> {
+        self.include_columns.as_deref()
+    }
+
+    /// Get the row filter, if any
+    pub fn get_row_filter(&self) -> Option<&dyn RowFilter> {
+        self.row_filter.as_deref()
+    }
+
+    /// Get the specification revision kept to, if any
+    pub fn get_spec_version(&self) -> Option<SpecVersion> {
+        self.spec_version
+    }
+}
+
+/// The entries of a `@toon-format/toon` options object
+fn toon_js_entries(config: &Value) -> Result<&serde_json::Map<String, Value>, Error> {
+    config.as_object().ok_or_else(|| Error::Deserialization {
+        message: "expected an object of options".to_string(),
+        path: None,
+        line: None,
+    })
+}
+
+/// The `indent` of a `@toon-format/toon` options object
+fn toon_js_indent(name: &str, value: &Value) -> Result<usize, Error> {
+    match value.as_u64() {
+        Some(indent) if indent > 0 => Ok(indent as usize),
+        _ => Err(toon_js_error(name)),
+    }
+}
+
+/// Error for an option of a `@toon-format/toon` options object that is
+/// unknown or has a value this crate can't match
+fn toon_js_error(name: &str) -> Error {
+    let message = match name {
+        "indent" => "expected a positive number of spaces",
+        "delimiter" => "expected \",\", \"\\t\" or \"|\"",
+        "lengthMarker" => "expected \"#\" or false",
+        "keyFolding" | "expandPaths" => "expected \"off\" or \"safe\"",
+        "flattenDepth" => "expected null, as folding depth can't be limited",
+        "strict" => "expected a boolean",
+        _ => "unknown option",
+    };
+    Error::Deserialization {
+        message: message.to_string(),
+        path: Some(name.to_string()),
+        line: None,
+    }
+}
+
+/// Options for converting between CSV and TOON
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct CsvOptions {
+    /// Key to write the table under (default: a root array)
+    pub key: Option<String>,
+    /// Options for the TOON that is written
+    pub encode: Option<EncodeOptions>,
+    /// Character between fields (default: `,`)
+    pub delimiter: Option<char>,
+    /// Read numbers and booleans in untyped columns (default: true)
+    pub infer_types: Option<bool>,
+    /// Read empty cells as null rather than `""` (default: true)
+    pub empty_as_null: Option<bool>,
+    /// Types of named columns, overriding inference
+    pub column_types: Option<Vec<(String, ColumnType)>>,
+}
+
+impl CsvOptions {
+    /// Create new default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write the table under `key`, as in `items[2]{sku,qty}:`, rather than
+    /// as a root array
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Set the options for the TOON that is written
+    pub fn encode_options(mut self, options: EncodeOptions) -> Self {
+        self.encode = Some(options);
+        self
+    }
+
+    /// Set the character between fields, such as `;` or `\t`
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    /// Set whether cells of untyped columns that read as numbers or
+    /// `true`/`false` become numbers and booleans, rather than staying
+    /// strings
+    pub fn infer_types(mut self, infer: bool) -> Self {
+        self.infer_types = Some(infer);
+        self
+    }
+
+    /// Set whether empty cells become null rather than empty strings
+    pub fn empty_as_null(mut self, null: bool) -> Self {
+        self.empty_as_null = Some(null);
+        self
+    }
+
+    /// Read the column named `column` as `ty` instead of inferring each
+    /// cell's type
+    pub fn column_type(mut self, column: impl Into<String>, ty: ColumnType) -> Self {
+        self.column_types
+            .get_or_insert_with(Vec::new)
+            .push((column.into(), ty));
+        self
+    }
+
+    /// Get the key the table goes under, if any
+    pub fn get_key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Get the character between fields
+    pub fn get_delimiter(&self) -> char {
+        self.delimiter.unwrap_or(',')
+    }
+
+    /// Get whether types are inferred in untyped columns
+    pub fn get_infer_types(&self) -> bool {
+        self.infer_types.unwrap_or(true)
+    }
+
+    /// Get whether empty cells become null
+    pub fn get_empty_as_null(&self) -> bool {
+        self.empty_as_null.unwrap_or(true)
+    }
+
+    /// Get the type set for a column, if any
+    pub fn get_column_type(&self, column: &str) -> Option<ColumnType> {
+        self.column_types
+            .iter()
+            .flatten()
+            .rev()
+            .find(|(name, _)| name == column)
+            .map(|&(_, ty)| ty)
+    }
+
+    /// Get the options for the TOON that is written
+    pub fn get_encode_options(&self) -> Cow<'_, EncodeOptions> {
+        match &self.encode {
+            Some(options) => Cow::Borrowed(options),
+            None => Cow::Owned(EncodeOptions::default()),
+        }
+    }
+}
+
+/// Options for [`crate::format_str`]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct FormatOptions {
+    /// Number of spaces per indentation level (default: 2)
+    pub indent: Option<usize>,
+    /// Whitespace after a key's colon (default: pretty)
+    pub style: Option<Style>,
+    /// Options the document is checked against before formatting
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub decode_options: Option<DecodeOptions>,
+}
+
+impl FormatOptions {
+    /// Create new default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the indentation level
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = Some(indent);
+        self
+    }
+
+    /// Set the whitespace style
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    /// Check the document against these decoding options, such as
+    /// [`DecodeOptions::lenient_arrays`] for tables written without
+    /// lengths; indentation is read leniently whatever they say
+    pub fn decode_options(mut self, options: DecodeOptions) -> Self {
+        self.decode_options = Some(options);
+        self
+    }
+
+    /// Get the indentation in spaces, defaulting to 2
+    pub fn get_indent(&self) -> usize {
+        self.indent.unwrap_or(2)
+    }
+
+    /// Get the whitespace style, defaulting to pretty
+    pub fn get_style(&self) -> Style {
+        self.style.unwrap_or_default()
+    }
+
+    /// Get the options the document is checked against
+    pub fn get_decode_options(&self) -> Option<&DecodeOptions> {
+        self.decode_options.as_ref()
+    }
 }
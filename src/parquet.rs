@@ -0,0 +1,259 @@
+//! Conversion between TOON tables and Parquet files
+//!
+//! Requires the `parquet` feature. A Parquet file is read a batch of rows
+//! at a time and written straight out as the rows of a root table, whose
+//! length comes from the file's metadata, so the whole file is never held
+//! in memory. Cells are written as by [`crate::arrow`].
+//!
+//! The way back writes a root table, or any root array of objects, as a
+//! Parquet file with a column per field.
+
+use crate::arrow::{column_names, write_header, write_rows};
+use crate::decode::decode;
+use crate::encode::WriteSink;
+use crate::error::Error;
+use crate::options::{DecodeOptions, EncodeOptions};
+use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use ::parquet::arrow::{ArrowWriter, ProjectionMask};
+use ::parquet::errors::ParquetError;
+use ::parquet::file::reader::ChunkReader;
+use arrow_array::builder::{BooleanBuilder, Float64Builder, Int64Builder, StringBuilder};
+use arrow_array::{ArrayRef, RecordBatch, RecordBatchReader};
+use arrow_schema::{Field, Schema};
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::sync::Arc;
+
+/// Stream a Parquet file into `writer` as a TOON table
+///
+/// `columns` picks the columns to read, in the order they are written;
+/// the others are never decoded. `None` reads them all. Nested columns
+/// can't be table cells, so a file holding lists or structs needs them
+/// left out.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::fs::File;
+/// use toon_rust::parquet_to_toon;
+///
+/// let file = File::open("orders.parquet")?;
+/// let mut output = Vec::new();
+/// parquet_to_toon(file, &mut output, Some(&["id", "total"]), None)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn parquet_to_toon<R, W>(
+    reader: R,
+    writer: &mut W,
+    columns: Option<&[&str]>,
+    options: Option<&EncodeOptions>,
+) -> Result<(), Error>
+where
+    R: ChunkReader + 'static,
+    W: Write,
+{
+    let default_opts = EncodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+
+    let mut builder = ParquetRecordBatchReaderBuilder::try_new(reader).map_err(invalid)?;
+    let rows = usize::try_from(builder.metadata().file_metadata().num_rows()).unwrap_or(0);
+    let mut order = None;
+    if let Some(columns) = columns {
+        let schema = builder.schema();
+        let indices = columns
+            .iter()
+            .map(|&name| {
+                schema.index_of(name).map_err(|_| Error::Deserialization {
+                    message: format!("No column `{name}` in the Parquet file"),
+                    path: None,
+                    line: None,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // Read batches hold the picked columns in file order
+        let mut sorted = indices.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        order = Some(
+            indices
+                .iter()
+                .map(|index| sorted.binary_search(index).unwrap_or_default())
+                .collect::<Vec<_>>(),
+        );
+        let mask = ProjectionMask::roots(builder.parquet_schema(), sorted);
+        builder = builder.with_projection(mask);
+    }
+    let reader = builder.build().map_err(invalid)?;
+
+    let fields = reader.schema().fields().clone();
+    let mut names = column_names(&fields);
+    if let Some(order) = &order {
+        names = order.iter().map(|&i| names[i].clone()).collect();
+    }
+    let mut sink = WriteSink(&mut *writer);
+    write_header(Some(&names), rows, &mut sink, opts)?;
+    for batch in reader {
+        let batch = batch.map_err(|err| invalid(err.into()))?;
+        let batch = match &order {
+            Some(order) => batch
+                .project(order)
+                .map_err(|err| Error::Serialization(err.to_string()))?,
+            None => batch,
+        };
+        write_rows(&batch, &mut sink, opts)?;
+    }
+    writer.flush().map_err(|e| Error::Io(e.to_string()))
+}
+
+/// Convert a TOON table to a Parquet file written to `writer`
+///
+/// The root must be an array of objects. Columns are the fields of the
+/// rows, in decoded key order, and a row without a field is null there. A
+/// column of integers is `Int64`, one mixing integers and floats is
+/// `Float64`, and one of booleans is `Boolean`; any other column is
+/// `Utf8`, with values that aren't strings as compact JSON. The writer is
+/// returned once the file is complete.
+///
+/// # Example
+///
+/// ```rust
+/// use std::fs::File;
+/// use toon_rust::{parquet_to_toon, toon_to_parquet};
+///
+/// let path = std::env::temp_dir().join("toon_to_parquet_example.parquet");
+/// let toon = "[2]{qty,sku}:\n  2,A1\n  1,B2\n";
+/// toon_to_parquet(toon, File::create(&path)?, None)?;
+/// let mut output = Vec::new();
+/// parquet_to_toon(File::open(&path)?, &mut output, None, None)?;
+/// assert_eq!(String::from_utf8(output)?, toon);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn toon_to_parquet<W: Write + Send>(
+    input: &str,
+    writer: W,
+    options: Option<&DecodeOptions>,
+) -> Result<W, Error> {
+    let Value::Array(items) = decode(input, options)? else {
+        return Err(Error::Serialization(
+            "The document must be a root array of objects".to_string(),
+        ));
+    };
+    let rows = items
+        .iter()
+        .map(|item| match item {
+            Value::Object(obj) => Ok(obj),
+            _ => Err(Error::Serialization(
+                "The document must be a root array of objects".to_string(),
+            )),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut columns: Vec<&String> = Vec::new();
+    for row in &rows {
+        for key in row.keys() {
+            if !columns.contains(&key) {
+                columns.push(key);
+            }
+        }
+    }
+
+    let (fields, arrays): (Vec<Field>, Vec<ArrayRef>) = columns
+        .iter()
+        .map(|&name| {
+            let array = column_array(&rows, name);
+            (Field::new(name, array.data_type().clone(), true), array)
+        })
+        .unzip();
+    let schema = Arc::new(Schema::new(fields));
+    let failed = |err: ParquetError| Error::Serialization(format!("Can't write Parquet: {err}"));
+    let mut writer = ArrowWriter::try_new(writer, schema.clone(), None).map_err(failed)?;
+    if !columns.is_empty() {
+        let batch = RecordBatch::try_new(schema, arrays)
+            .map_err(|err| Error::Serialization(err.to_string()))?;
+        writer.write(&batch).map_err(failed)?;
+    }
+    writer.into_inner().map_err(failed)
+}
+
+/// The kind of values in a column, widening as more are seen
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Null,
+    Int,
+    Float,
+    Bool,
+    Text,
+}
+
+impl Kind {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => Kind::Null,
+            Value::Bool(_) => Kind::Bool,
+            Value::Number(n) if n.is_i64() => Kind::Int,
+            Value::Number(_) => Kind::Float,
+            _ => Kind::Text,
+        }
+    }
+
+    fn add(self, other: Kind) -> Self {
+        match (self, other) {
+            (kind, Kind::Null) | (Kind::Null, kind) => kind,
+            (Kind::Int, Kind::Float) | (Kind::Float, Kind::Int) => Kind::Float,
+            (a, b) if a == b => a,
+            _ => Kind::Text,
+        }
+    }
+}
+
+/// The Arrow array holding column `name` of `rows`
+fn column_array(rows: &[&Map<String, Value>], name: &str) -> ArrayRef {
+    let cells: Vec<&Value> = rows
+        .iter()
+        .map(|row| row.get(name).unwrap_or(&Value::Null))
+        .collect();
+    let kind = cells
+        .iter()
+        .fold(Kind::Null, |kind, cell| kind.add(Kind::of(cell)));
+    match kind {
+        Kind::Int => {
+            let mut builder = Int64Builder::with_capacity(cells.len());
+            cells
+                .iter()
+                .for_each(|cell| builder.append_option(cell.as_i64()));
+            Arc::new(builder.finish())
+        }
+        Kind::Float => {
+            let mut builder = Float64Builder::with_capacity(cells.len());
+            cells
+                .iter()
+                .for_each(|cell| builder.append_option(cell.as_f64()));
+            Arc::new(builder.finish())
+        }
+        Kind::Bool => {
+            let mut builder = BooleanBuilder::with_capacity(cells.len());
+            cells
+                .iter()
+                .for_each(|cell| builder.append_option(cell.as_bool()));
+            Arc::new(builder.finish())
+        }
+        Kind::Null | Kind::Text => {
+            let mut builder = StringBuilder::new();
+            for cell in cells {
+                match cell {
+                    Value::Null => builder.append_null(),
+                    Value::String(s) => builder.append_value(s),
+                    other => builder.append_value(other.to_string()),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+fn invalid(err: ParquetError) -> Error {
+    Error::Deserialization {
+        message: format!("Invalid Parquet: {err}"),
+        path: None,
+        line: None,
+    }
+}
@@ -0,0 +1,334 @@
+//! Decoding documents that are still being written
+//!
+//! An LLM streams its answer a few characters at a time, and a UI showing
+//! structured output would rather not wait for the last token. The
+//! functions here read an incomplete TOON prefix and keep only what more
+//! text can't change: whole lines, less any trailing line that opens
+//! something whose content hasn't arrived yet (a `key:` with nothing after
+//! it, a table header or a `|` block string).
+//!
+//! [`decode_partial`] gives the value of such a prefix in one go, and
+//! [`PartialParser`] takes chunks as they arrive and reports each field,
+//! item and table row as it completes, with its TOON path (see
+//! [`crate::pointer`]).
+
+use crate::decode::{entry_end, opens_block, push_utf8};
+use crate::dom::{self, Document, Items, Node};
+use crate::error::Error;
+use crate::options::DecodeOptions;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+/// Decode the settled part of an incomplete TOON document
+///
+/// A last line without a line break may still grow (`count: 12` could be
+/// the start of `count: 125`), so it is left out, as is a trailing line
+/// whose value is still to come. Arrays hold the items received so far,
+/// whatever their headers declare.
+///
+/// The options apply as in [`crate::decode`], limits included, so a prefix
+/// declaring an array longer than [`DecodeOptions::max_array_length`] is
+/// rejected before its items arrive. See [`Document::value`] for how the
+/// value differs from a full decode.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::partial::decode_partial;
+///
+/// let prefix = "name: shop\nitems[3]{sku,qty}:\n  A1,2\n  B2,1\n  C3";
+/// let value = decode_partial(prefix, None)?;
+/// assert_eq!(
+///     value,
+///     json!({"name": "shop", "items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}]})
+/// );
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn decode_partial(prefix: &str, options: Option<&DecodeOptions>) -> Result<Value, Error> {
+    let settled = settled_len(prefix);
+    let complete = prefix.rfind('\n').map_or(0, |i| i + 1);
+    // Lines held back are still held to the options' limits
+    if settled < complete {
+        dom::parse(&prefix[..complete], options)?;
+    }
+    Ok(dom::parse(&prefix[..settled], options)?.value())
+}
+
+/// A field, item or table row that won't change as more input arrives
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completed {
+    /// TOON path of the value in the document, such as `users[3]` or
+    /// `user.name`
+    pub path: String,
+    pub value: Value,
+}
+
+/// Incremental decoder reporting values as they complete
+///
+/// Unlike [`crate::decode::PushParser`], which waits for a whole top-level
+/// entry, each [`feed`](Self::feed) returns every primitive field, inline
+/// or list item and table row completed by the chunk. Objects and arrays
+/// are not reported themselves, only what they hold, except when they are
+/// empty. Each path reads back its value from the finished document with
+/// [`crate::pointer::get`].
+///
+/// The top-level entry being received is re-parsed each time a line of it
+/// completes, so a large root array costs time quadratic in its size.
+///
+/// Error positions refer to the whole document fed so far.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::partial::PartialParser;
+///
+/// let mut parser = PartialParser::new(None);
+/// assert!(parser.feed(b"users[2]{id,name}:\n  1,A").unwrap().is_empty());
+/// let completed = parser.feed(b"da\n  2,Bob").unwrap();
+/// assert_eq!(completed[0].path, "users[0]");
+/// assert_eq!(completed[0].value, json!({"id": 1, "name": "Ada"}));
+/// let completed = parser.finish().unwrap();
+/// assert_eq!(completed[0].path, "users[1]");
+/// ```
+#[derive(Debug)]
+pub struct PartialParser {
+    options: DecodeOptions,
+    /// Text of the entry being received
+    buffer: String,
+    /// Trailing bytes of an incomplete UTF-8 sequence
+    partial: Vec<u8>,
+    /// End of the lines in `buffer` known to belong to the current entry
+    scanned: usize,
+    /// Length of the settled text of `buffer` last parsed
+    settled: usize,
+    /// Length of the complete lines of `buffer` last checked, when some
+    /// were held back from the settled text
+    checked: usize,
+    /// Paths reported from the current entry
+    reported: HashSet<String>,
+    /// Bytes and lines of the document before `buffer`, for error positions
+    offset: usize,
+    lines: usize,
+}
+
+impl PartialParser {
+    /// Create a parser with the given decoding options
+    pub fn new(options: Option<&DecodeOptions>) -> Self {
+        Self {
+            options: options.cloned().unwrap_or_default(),
+            buffer: String::new(),
+            partial: Vec::new(),
+            scanned: 0,
+            settled: 0,
+            checked: 0,
+            reported: HashSet::new(),
+            offset: 0,
+            lines: 0,
+        }
+    }
+
+    /// Add a chunk of input, returning the values it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<Completed>, Error> {
+        self.partial.extend_from_slice(chunk);
+        push_utf8(&mut self.partial, &mut self.buffer)?;
+
+        let mut completed = Vec::new();
+        while let Some(end) = entry_end(&self.buffer, &mut self.scanned) {
+            let text: String = self.buffer.drain(..end).collect();
+            self.scanned = 0;
+            self.settled = 0;
+            self.checked = 0;
+            self.report(&text, false, &mut completed)?;
+            self.reported.clear();
+            self.offset += text.len();
+            self.lines += text.matches('\n').count();
+        }
+
+        let settled = settled_len(&self.buffer);
+        let complete = self.buffer.rfind('\n').map_or(0, |i| i + 1);
+        // Lines held back are still held to the options' limits
+        if settled < complete && complete != self.checked {
+            self.checked = complete;
+            self.parse(&self.buffer[..complete])?;
+        }
+        if settled != self.settled {
+            self.settled = settled;
+            let text = self.buffer[..settled].to_string();
+            self.report(&text, false, &mut completed)?;
+        }
+        Ok(completed)
+    }
+
+    /// Signal the end of input, returning the values still to be reported
+    pub fn finish(mut self) -> Result<Vec<Completed>, Error> {
+        if !self.partial.is_empty() {
+            return Err(Error::Io(
+                "Invalid UTF-8: incomplete sequence at end of input".to_string(),
+            ));
+        }
+        let text = std::mem::take(&mut self.buffer);
+        let mut completed = Vec::new();
+        self.report(&text, true, &mut completed)?;
+        Ok(completed)
+    }
+
+    /// Parse text of the current entry, with error positions in the whole
+    /// document
+    fn parse(&self, text: &str) -> Result<Document, Error> {
+        dom::parse(text, Some(&self.options)).map_err(|err| match err {
+            Error::Parse {
+                position,
+                line,
+                column,
+                message,
+            } => Error::parse(position + self.offset, line + self.lines, column, message),
+            err => err,
+        })
+    }
+
+    /// Parse the text of the current entry and queue the values not yet
+    /// reported
+    ///
+    /// `last` means nothing follows the text, so it may be a document made
+    /// of a single primitive.
+    fn report(
+        &mut self,
+        text: &str,
+        last: bool,
+        completed: &mut Vec<Completed>,
+    ) -> Result<(), Error> {
+        let doc = self.parse(text)?;
+        if matches!(doc.root(), Node::Scalar(_) | Node::Block(_)) && !last {
+            return Ok(());
+        }
+        let mut leaves = Vec::new();
+        collect(&doc, doc.root(), String::new(), &mut leaves);
+        for leaf in leaves {
+            if self.reported.insert(leaf.path.clone()) {
+                completed.push(leaf);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Length of the lines at the start of `text` that more text can't change
+fn settled_len(text: &str) -> usize {
+    let complete = text.rfind('\n').map_or(0, |i| i + 1);
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in text[..complete].split_inclusive('\n') {
+        if !line.trim().is_empty() {
+            lines.push((start, line.trim_end()));
+        }
+        start += line.len();
+    }
+    let Some(&(last_start, last)) = lines.last() else {
+        return complete;
+    };
+
+    // A block string takes every line below it that is indented further
+    let mut shallowest = usize::MAX;
+    for &(start, line) in lines.iter().rev() {
+        let content = line.trim_start_matches([' ', '\t']);
+        let width = line.len() - content.len();
+        if width < shallowest {
+            if opens_block(content) {
+                return start;
+            }
+            shallowest = width;
+        }
+        if width == 0 {
+            break;
+        }
+    }
+    let content = last.trim_start();
+    if content.ends_with(':') || content == "-" {
+        return last_start;
+    }
+    complete
+}
+
+/// The values under `node` that are reported on their own
+fn collect(doc: &Document, node: &Node, path: String, leaves: &mut Vec<Completed>) {
+    match node {
+        Node::Scalar(scalar) | Node::Block(scalar) => leaves.push(Completed {
+            path,
+            value: scalar.value.clone(),
+        }),
+        Node::Object(object) if object.entries.is_empty() => leaves.push(Completed {
+            path,
+            value: Value::Object(Map::new()),
+        }),
+        Node::Object(object) => {
+            let expand = doc.options().get_expand_paths();
+            for entry in &object.entries {
+                let name = &entry.key.name;
+                // An unquoted dotted key expands into nested objects, so
+                // its path goes through each of them
+                let quoted = doc.source()[entry.key.span.clone()].starts_with('"');
+                let path = if expand && !quoted && name.contains('.') {
+                    name.split('.')
+                        .fold(path.clone(), |path, key| child(&path, key))
+                } else {
+                    child(&path, name)
+                };
+                collect(doc, &entry.value, path, leaves);
+            }
+        }
+        Node::Array(array) => match &array.items {
+            Items::Inline(items) if items.is_empty() => leaves.push(Completed {
+                path,
+                value: Value::Array(Vec::new()),
+            }),
+            Items::Inline(items) => {
+                leaves.extend(items.iter().enumerate().map(|(i, item)| Completed {
+                    path: index(&path, i),
+                    value: item.value.clone(),
+                }));
+            }
+            Items::Table(rows) => {
+                // Rows the row filter leaves out take no index
+                let values = rows
+                    .iter()
+                    .filter_map(|row| doc.row_value(&array.header, row));
+                leaves.extend(values.enumerate().map(|(i, value)| Completed {
+                    path: index(&path, i),
+                    value,
+                }));
+            }
+            Items::List(items) if items.is_empty() => leaves.push(Completed {
+                path,
+                value: Value::Array(Vec::new()),
+            }),
+            Items::List(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    collect(doc, &item.value, index(&path, i), leaves);
+                }
+            }
+        },
+    }
+}
+
+/// Path of `key` in the object at `path`, quoting keys a path would split
+fn child(path: &str, key: &str) -> String {
+    let plain = !key.is_empty()
+        && !key.starts_with('"')
+        && !key.contains(|ch: char| matches!(ch, '.' | '[' | ']') || ch.is_whitespace());
+    let key = if plain {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    };
+    if path.is_empty() {
+        key
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn index(path: &str, i: usize) -> String {
+    format!("{path}[{i}]")
+}
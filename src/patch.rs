@@ -0,0 +1,359 @@
+//! Row-level patches for tabular arrays
+//!
+//! A [`TablePatch`] lists changes to the rows of a table, each row picked
+//! out by the value in a primary column, so a large table that changes a
+//! little can be kept in sync without sending all of it again. The first
+//! column is the primary one.
+//!
+//! Its text form is a table header followed by one line per change, with
+//! `+` adding a row, `~` overwriting the patch's columns in an existing row
+//! and `-` removing the row with the given key:
+//!
+//! ```text
+//! {sku,qty,price}:
+//!   +C3,5,2.5
+//!   ~A1,3,9.5
+//!   -B2
+//! ```
+
+use crate::decode::{header_fields, line_column, parse_primitive_value, table_delimiter};
+use crate::encode::{encode_scalar, format_fields, Slot};
+use crate::error::Error;
+use crate::options::{DecodeOptions, EncodeOptions};
+use crate::simd;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Changes to the rows of a table, keyed by its first column
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::patch::TablePatch;
+/// use toon_rust::{apply_table_patch, decode};
+///
+/// let mut doc = decode("items[2]{sku,qty}:\n  A1,2\n  B2,1", None)?;
+/// let patch: TablePatch = "{sku,qty}:\n  ~A1,3\n  -B2\n  +C3,5".parse()?;
+/// apply_table_patch(&mut doc["items"], &patch)?;
+/// assert_eq!(doc["items"], json!([{"sku": "A1", "qty": 3}, {"sku": "C3", "qty": 5}]));
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TablePatch {
+    columns: Vec<String>,
+    changes: Vec<RowChange>,
+}
+
+/// One change in a [`TablePatch`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowChange {
+    /// Add a row, with one cell per column
+    Insert(Vec<Value>),
+    /// Overwrite the patch's columns in the row keyed by the first cell
+    Update(Vec<Value>),
+    /// Remove the row with this key
+    Delete(Value),
+}
+
+impl TablePatch {
+    /// Create an empty patch over the given columns, the first being the key
+    pub fn new<I>(columns: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        TablePatch {
+            columns: columns.into_iter().map(Into::into).collect(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Work out the changes that turn the rows of `old` into those of `new`
+    ///
+    /// Both must be arrays of objects with a `key_column` field. The patch
+    /// covers the key and every field found in the rows of `new`; rows that
+    /// differ are updated in full, and rows of `new` are added in order.
+    pub fn diff(old: &Value, new: &Value, key_column: &str) -> Result<Self, Error> {
+        let old = rows(old)?;
+        let new = rows(new)?;
+        let mut columns = vec![key_column.to_string()];
+        for row in &new {
+            for field in row.keys() {
+                if !columns.contains(field) {
+                    columns.push(field.clone());
+                }
+            }
+        }
+        let mut patch = TablePatch::new(columns);
+
+        let new_keys: HashSet<String> = new
+            .iter()
+            .map(|row| row_key(row, key_column))
+            .collect::<Result<_, _>>()?;
+        let mut old_rows = HashMap::new();
+        for row in &old {
+            let key = row_key(row, key_column)?;
+            if !new_keys.contains(&key) {
+                patch.delete(&row[key_column]);
+            }
+            old_rows.insert(key, *row);
+        }
+        for row in new {
+            let cells = patch.cells(row);
+            match old_rows.get(&row_key(row, key_column)?) {
+                None => patch.changes.push(RowChange::Insert(cells)),
+                Some(old) if *old != row => patch.changes.push(RowChange::Update(cells)),
+                Some(_) => {}
+            }
+        }
+        Ok(patch)
+    }
+
+    /// The patch's columns, the key first
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// The changes, in the order they apply
+    pub fn changes(&self) -> &[RowChange] {
+        &self.changes
+    }
+
+    /// Add a row, with one cell per column
+    pub fn insert(&mut self, cells: &[Value]) -> Result<&mut Self, Error> {
+        self.check_len(cells)?;
+        self.changes.push(RowChange::Insert(cells.to_vec()));
+        Ok(self)
+    }
+
+    /// Overwrite the patch's columns in the row keyed by the first cell
+    pub fn update(&mut self, cells: &[Value]) -> Result<&mut Self, Error> {
+        self.check_len(cells)?;
+        self.changes.push(RowChange::Update(cells.to_vec()));
+        Ok(self)
+    }
+
+    /// Remove the row with this key
+    pub fn delete(&mut self, key: &Value) -> &mut Self {
+        self.changes.push(RowChange::Delete(key.clone()));
+        self
+    }
+
+    fn check_len(&self, cells: &[Value]) -> Result<(), Error> {
+        if cells.len() != self.columns.len() {
+            return Err(Error::LengthMismatch {
+                expected: self.columns.len(),
+                found: cells.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The cells of a row under the patch's columns, null where it has none
+    fn cells(&self, row: &Map<String, Value>) -> Vec<Value> {
+        self.columns
+            .iter()
+            .map(|column| row.get(column).cloned().unwrap_or(Value::Null))
+            .collect()
+    }
+}
+
+/// Apply a [`TablePatch`] to an array of rows
+///
+/// Changes apply in order, and added rows go at the end. A change that
+/// doesn't fit the rows, such as removing a key that isn't there or adding
+/// one that is, fails before anything is changed.
+pub fn apply_table_patch(table: &mut Value, patch: &TablePatch) -> Result<(), Error> {
+    let Some(key_column) = patch.columns.first() else {
+        return Err(Error::InvalidHeader("patch has no columns".to_string()));
+    };
+    let Value::Array(items) = table else {
+        return Err(Error::Serialization(
+            "a table patch needs an array".to_string(),
+        ));
+    };
+    let mut index = HashMap::new();
+    for (i, item) in items.iter().enumerate() {
+        let Value::Object(row) = item else {
+            return Err(Error::Serialization(format!("row {i} is not an object")));
+        };
+        index.insert(row_key(row, key_column)?, i);
+    }
+
+    // Check every change against the keys before touching the rows
+    let mut present: HashSet<&String> = index.keys().collect();
+    let keys: Vec<String> = patch.changes.iter().map(change_key).collect();
+    for (change, key) in patch.changes.iter().zip(&keys) {
+        let found = match change {
+            RowChange::Insert(_) => !present.insert(key),
+            RowChange::Update(_) => present.contains(key),
+            RowChange::Delete(_) => present.remove(key),
+        };
+        match (change, found) {
+            (RowChange::Insert(_), true) => {
+                return Err(Error::Serialization(format!(
+                    "a row with {key_column} = {key} already exists"
+                )))
+            }
+            (RowChange::Update(_) | RowChange::Delete(_), false) => {
+                return Err(Error::MissingField(format!(
+                    "row with {key_column} = {key}"
+                )))
+            }
+            _ => {}
+        }
+    }
+
+    let mut rows: Vec<Option<Value>> = std::mem::take(items).into_iter().map(Some).collect();
+    for (change, key) in patch.changes.iter().zip(keys) {
+        match change {
+            RowChange::Insert(cells) => {
+                let row = patch.columns.iter().cloned().zip(cells.iter().cloned());
+                index.insert(key, rows.len());
+                rows.push(Some(Value::Object(row.collect())));
+            }
+            RowChange::Update(cells) => {
+                if let Some(Some(Value::Object(row))) = index.get(&key).map(|&i| &mut rows[i]) {
+                    for (column, cell) in patch.columns.iter().zip(cells) {
+                        row.insert(column.clone(), cell.clone());
+                    }
+                }
+            }
+            RowChange::Delete(_) => {
+                if let Some(i) = index.remove(&key) {
+                    rows[i] = None;
+                }
+            }
+        }
+    }
+    *items = rows.into_iter().flatten().collect();
+    Ok(())
+}
+
+/// The array's rows, which must all be objects
+fn rows(table: &Value) -> Result<Vec<&Map<String, Value>>, Error> {
+    let Value::Array(items) = table else {
+        return Err(Error::Serialization(
+            "a table patch needs an array".to_string(),
+        ));
+    };
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| match item {
+            Value::Object(row) => Ok(row),
+            _ => Err(Error::Serialization(format!("row {i} is not an object"))),
+        })
+        .collect()
+}
+
+/// A row's key as text, so rows can be looked up by it
+fn row_key(row: &Map<String, Value>, key_column: &str) -> Result<String, Error> {
+    row.get(key_column)
+        .map(Value::to_string)
+        .ok_or_else(|| Error::MissingField(key_column.to_string()))
+}
+
+fn change_key(change: &RowChange) -> String {
+    match change {
+        RowChange::Insert(cells) | RowChange::Update(cells) => cells[0].to_string(),
+        RowChange::Delete(key) => key.to_string(),
+    }
+}
+
+impl fmt::Display for TablePatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = EncodeOptions::default();
+        writeln!(f, "{{{}}}:", format_fields(&self.columns, &options))?;
+        for change in &self.changes {
+            let (marker, cells) = match change {
+                RowChange::Insert(cells) => ('+', cells.as_slice()),
+                RowChange::Update(cells) => ('~', cells.as_slice()),
+                RowChange::Delete(key) => ('-', std::slice::from_ref(key)),
+            };
+            let mut line = format!("  {marker}");
+            for (i, cell) in cells.iter().enumerate() {
+                if i > 0 {
+                    line.push(options.get_delimiter());
+                }
+                encode_scalar(cell, &mut line, &options, Slot::Cell).map_err(|_| fmt::Error)?;
+            }
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for TablePatch {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Error> {
+        let error = |pos: usize, message: String| {
+            let (line, column) = line_column(text, pos);
+            Error::parse(pos, line, column, message)
+        };
+        // Each non-blank line with its byte offset, past its indentation
+        let mut lines = text
+            .split_inclusive('\n')
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len();
+                let trimmed = line.trim();
+                let indent = line.len() - line.trim_start().len();
+                Some((start + indent, trimmed))
+            })
+            .filter(|(_, line)| !line.is_empty())
+            .peekable();
+
+        let Some((start, header)) = lines.next() else {
+            return Err(error(0, "Expected a `{...}:` header".to_string()));
+        };
+        let Some(fields) = header
+            .strip_prefix('{')
+            .and_then(|rest| rest.strip_suffix("}:"))
+        else {
+            return Err(error(start, "Expected a `{...}:` header".to_string()));
+        };
+        let first_row = lines
+            .peek()
+            .filter(|(_, line)| !line.starts_with('-'))
+            .map(|(_, line)| &line[1..]);
+        let delimiter = table_delimiter(fields, first_row);
+        let options = DecodeOptions::default();
+        let (columns, _) = header_fields(fields, delimiter, &options)
+            .map_err(|err| error(start, err.to_string()))?;
+        if columns.is_empty() || columns[0].is_empty() {
+            return Err(error(start, "Expected at least one column".to_string()));
+        }
+
+        let mut patch = TablePatch::new(columns);
+        for (start, line) in lines {
+            let mut chars = line.chars();
+            let marker = chars.next().unwrap_or_default();
+            let cells = simd::split_row_fallback(chars.as_str(), delimiter)
+                .into_iter()
+                .map(|cell| parse_primitive_value(&options, cell.trim()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| error(start + 1, err.to_string()))?;
+            let result = match (marker, cells.as_slice()) {
+                ('-', [key]) => {
+                    patch.delete(key);
+                    Ok(())
+                }
+                ('-', _) => Err(error(start, "A removed row takes only its key".to_string())),
+                ('+', cells) => patch.insert(cells).map(drop),
+                ('~', cells) => patch.update(cells).map(drop),
+                _ => Err(error(start, "Expected `+`, `-` or `~`".to_string())),
+            };
+            result.map_err(|err| match err {
+                Error::LengthMismatch { expected, found } => {
+                    error(start, format!("Expected {expected} cells, found {found}"))
+                }
+                err => err,
+            })?;
+        }
+        Ok(patch)
+    }
+}
@@ -0,0 +1,207 @@
+//! Reading and writing values inside a decoded document by path
+//!
+//! Paths come in two forms. A path starting with `/` (or the empty path) is
+//! a JSON Pointer (RFC 6901), such as `/items/3/price`. Anything else is a
+//! TOON path, written the way the document reads: keys joined with dots and
+//! array steps in brackets, as in `items[3].price`. A bracket may also pick a
+//! table row by one of its fields, so `items[sku=A1].price` is the price in
+//! the first row whose `sku` is `A1`. Keys holding dots, brackets or spaces
+//! are quoted, as in `"unit price"` or `meta."a.b"`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use serde_json::json;
+//! use toon_rust::{decode, pointer};
+//!
+//! let mut doc = decode("items[2]{sku,price}:\n  A1,9.5\n  B2,3", None)?;
+//! assert_eq!(pointer::get(&doc, "/items/1/price"), Some(&json!(3)));
+//! assert_eq!(pointer::get(&doc, "items[sku=A1].price"), Some(&json!(9.5)));
+//!
+//! pointer::set(&mut doc, "items[sku=B2].price", json!(4))?;
+//! assert_eq!(doc["items"][1]["price"], json!(4));
+//! # Ok::<(), toon_rust::Error>(())
+//! ```
+
+use crate::error::Error;
+use serde_json::Value;
+
+/// The value at `path`, if there is one
+pub fn get<'v>(value: &'v Value, path: &str) -> Option<&'v Value> {
+    let steps = parse(path).ok()?;
+    steps.iter().try_fold(value, |value, step| step.get(value))
+}
+
+/// A mutable reference to the value at `path`, if there is one
+pub fn get_mut<'v>(value: &'v mut Value, path: &str) -> Option<&'v mut Value> {
+    let steps = parse(path).ok()?;
+    steps
+        .iter()
+        .try_fold(value, |value, step| step.get_mut(value))
+}
+
+/// Put `new` at `path`, returning the value it replaced
+///
+/// Everything up to the last step must already exist. The last step may
+/// name a new key of an object, or append to an array with `-` in a JSON
+/// Pointer or an index one past the end. Setting the empty path replaces
+/// the whole document.
+pub fn set(value: &mut Value, path: &str, new: Value) -> Result<Option<Value>, Error> {
+    let steps = parse(path)?;
+    let Some((last, parents)) = steps.split_last() else {
+        return Ok(Some(std::mem::replace(value, new)));
+    };
+    let missing = || Error::MissingField(path.to_string());
+    let parent = parents
+        .iter()
+        .try_fold(value, |value, step| step.get_mut(value))
+        .ok_or_else(missing)?;
+    match (parent, last) {
+        (Value::Object(map), Step::Token(key) | Step::Key(key)) => Ok(map.insert(key.clone(), new)),
+        (Value::Array(items), step) => {
+            let index = match step {
+                Step::Token(token) if token == "-" => items.len(),
+                Step::Token(token) => array_index(token).ok_or_else(missing)?,
+                Step::Index(index) => *index,
+                Step::Match { field, value } => items
+                    .iter()
+                    .position(|item| matches(item, field, value))
+                    .ok_or_else(missing)?,
+                Step::Key(_) => return Err(missing()),
+            };
+            match index.cmp(&items.len()) {
+                std::cmp::Ordering::Less => Ok(Some(std::mem::replace(&mut items[index], new))),
+                std::cmp::Ordering::Equal => {
+                    items.push(new);
+                    Ok(None)
+                }
+                std::cmp::Ordering::Greater => Err(missing()),
+            }
+        }
+        _ => Err(missing()),
+    }
+}
+
+/// One step of a path
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    /// A JSON Pointer reference token: a key, or an index into an array
+    Token(String),
+    /// An object key
+    Key(String),
+    /// An array index
+    Index(usize),
+    /// The first array item that is an object whose `field` reads as `value`
+    Match { field: String, value: String },
+}
+
+impl Step {
+    fn get<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        match (self, value) {
+            (Step::Token(key) | Step::Key(key), Value::Object(map)) => map.get(key),
+            (Step::Token(token), Value::Array(items)) => items.get(array_index(token)?),
+            (Step::Index(index), Value::Array(items)) => items.get(*index),
+            (Step::Match { field, value }, Value::Array(items)) => {
+                items.iter().find(|item| matches(item, field, value))
+            }
+            _ => None,
+        }
+    }
+
+    fn get_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        match (self, value) {
+            (Step::Token(key) | Step::Key(key), Value::Object(map)) => map.get_mut(key),
+            (Step::Token(token), Value::Array(items)) => items.get_mut(array_index(token)?),
+            (Step::Index(index), Value::Array(items)) => items.get_mut(*index),
+            (Step::Match { field, value }, Value::Array(items)) => {
+                items.iter_mut().find(|item| matches(item, field, value))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether `item` is an object whose `field` is a primitive reading as `text`
+fn matches(item: &Value, field: &str, text: &str) -> bool {
+    match item.get(field) {
+        Some(Value::String(s)) => s == text,
+        Some(Value::Number(n)) => n.to_string() == text,
+        Some(Value::Bool(b)) => b.to_string() == text,
+        Some(Value::Null) => text == "null",
+        _ => false,
+    }
+}
+
+/// An array index in a JSON Pointer: digits without leading zeros
+fn array_index(token: &str) -> Option<usize> {
+    if token.len() > 1 && token.starts_with('0') {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// Split a path into steps, telling the two syntaxes apart by a leading `/`
+fn parse(path: &str) -> Result<Vec<Step>, Error> {
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    match path.strip_prefix('/') {
+        Some(pointer) => Ok(pointer
+            .split('/')
+            .map(|token| Step::Token(token.replace("~1", "/").replace("~0", "~")))
+            .collect()),
+        None => parse_toon_path(path),
+    }
+}
+
+/// Split a TOON path such as `orders[0].items[sku=A1]."unit price"`
+fn parse_toon_path(path: &str) -> Result<Vec<Step>, Error> {
+    let invalid = |message: &str| Error::Syntax(format!("Invalid path `{path}`: {message}"));
+    let mut steps = Vec::new();
+    let mut rest = path;
+    let mut first = true;
+    while !rest.is_empty() {
+        if !first && !rest.starts_with('[') {
+            rest = rest
+                .strip_prefix('.')
+                .ok_or_else(|| invalid("expected `.` or `[`"))?;
+        }
+        first = false;
+
+        // A key, unless the segment is only array steps
+        if rest.starts_with('"') {
+            let len = crate::decode::quoted_len(rest).ok_or_else(|| invalid("unclosed quote"))?;
+            let key =
+                crate::decode::unescape(&rest[..len]).map_err(|err| invalid(&err.to_string()))?;
+            steps.push(Step::Key(key));
+            rest = &rest[len..];
+        } else if !rest.starts_with('[') {
+            let len = rest.find(['.', '[']).unwrap_or(rest.len());
+            if len == 0 {
+                return Err(invalid("empty key"));
+            }
+            steps.push(Step::Key(rest[..len].to_string()));
+            rest = &rest[len..];
+        }
+
+        while let Some(inner) = rest.strip_prefix('[') {
+            let close = inner.find(']').ok_or_else(|| invalid("unclosed `[`"))?;
+            let selector = &inner[..close];
+            let step = match selector.split_once('=') {
+                Some((field, value)) => Step::Match {
+                    field: field.trim().to_string(),
+                    value: value.trim().to_string(),
+                },
+                None => Step::Index(
+                    selector
+                        .trim()
+                        .parse()
+                        .map_err(|_| invalid("expected an index or `field=value`"))?,
+                ),
+            };
+            steps.push(step);
+            rest = &inner[close + 1..];
+        }
+    }
+    Ok(steps)
+}
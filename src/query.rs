@@ -0,0 +1,595 @@
+//! A small jq-like query language evaluated against TOON documents
+//!
+//! A query is a pipeline of stages joined with `|`, each fed the outputs of
+//! the one before:
+//!
+//! - a path: `.` for the input itself, `.key` or `."quoted key"` for an
+//!   object field, `.[2]` (or `.[-1]` from the end) for an array item and
+//!   `.[]` for every item or field value, chained as in `.orders[].lines`.
+//!   The leading dot may be left out of the first stage, as in `items[]`;
+//!   anywhere else a bare word would be a jq function such as `length`,
+//!   and as none are supported it fails to parse.
+//! - `select(condition)`, which passes its input on when the condition
+//!   holds. A condition compares two operands with `==`, `!=`, `<`, `<=`,
+//!   `>` or `>=`, or tests one operand for a value other than `null` and
+//!   `false`, and conditions combine with `and`, `or` and parentheses. An
+//!   operand is a path or a literal (`1.5`, `"A1"`, `true`, `false`,
+//!   `null`); when a path has several values, the condition holds if any of
+//!   them satisfies it.
+//!
+//! A missing field or index reads as `null` and iterating a value that is
+//! neither an array nor an object gives nothing, so queries never fail on
+//! the shape of the data.
+//!
+//! [`query`] and [`query_stream`] avoid decoding the whole document where
+//! they can: a query starting `key[]` over a tabular array walks its rows
+//! one at a time, and one starting `key` parses only that top-level entry.
+
+use crate::decode::{
+    decode, decode_iter, decode_key, decode_rows, decode_rows_stream, decode_stream,
+};
+use crate::error::Error;
+use crate::options::DecodeOptions;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::io::{Cursor, Read};
+use std::rc::Rc;
+
+static NULL: Value = Value::Null;
+
+/// Run a query against a TOON document, returning every value it outputs
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::query;
+///
+/// let toon = "items[3]{sku,qty}:\n  A1,2\n  B2,1\n  C3,5";
+/// let skus = query(toon, "items[] | select(.qty > 1) | .sku", None)?;
+/// assert_eq!(skus, [json!("A1"), json!("C3")]);
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn query(
+    input: &str,
+    query: &str,
+    options: Option<&DecodeOptions>,
+) -> Result<Vec<Value>, Error> {
+    let query: Query = query.parse()?;
+    if let Some((key, rest)) = query.split_rows() {
+        let rows: Box<dyn Iterator<Item = Result<Value, Error>>> = if key.is_empty() {
+            Box::new(decode_iter(input, options))
+        } else {
+            Box::new(decode_rows(input, key, options).map(|row| row.map(Value::Object)))
+        };
+        // Anything but a table, or a table whose first row is bad, falls
+        // back to decoding the entry below, which reports its own errors
+        if let Some(out) = run_rows(rows, &rest)? {
+            return Ok(out);
+        }
+    }
+    let value = match query.split_key() {
+        Some((key, rest)) => {
+            let value = decode_key(input, key, options)?.unwrap_or(Value::Null);
+            return Ok(rest.apply(&value));
+        }
+        None => decode(input, options)?,
+    };
+    Ok(query.apply(&value))
+}
+
+/// Run a query against a TOON document read from a stream
+///
+/// A query starting `key[]` over a tabular array reads the rows as they
+/// arrive, like [`decode_rows_stream`], so only the matching values are
+/// held. Any other query decodes the whole document first.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::query_stream;
+///
+/// let log = "events[3]{id,level}:\n  1,info\n  2,warn\n  3,warn\n";
+/// let ids = query_stream(log.as_bytes(), r#"events[] | select(.level == "warn") | .id"#, None)?;
+/// assert_eq!(ids, [json!(2), json!(3)]);
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn query_stream<R: Read>(
+    mut reader: R,
+    query: &str,
+    options: Option<&DecodeOptions>,
+) -> Result<Vec<Value>, Error> {
+    let query: Query = query.parse()?;
+    if let Some((key, rest)) = query.split_rows() {
+        // Keep what is read until the first row, to decode it again if the
+        // key turns out not to be a table
+        let seen = Rc::new(RefCell::new(Some(Vec::new())));
+        let recorder = Recorder {
+            inner: &mut reader,
+            seen: Rc::clone(&seen),
+        };
+        let rows = decode_rows_stream(recorder, key, options).map(|row| {
+            if row.is_ok() {
+                seen.borrow_mut().take();
+            }
+            row.map(Value::Object)
+        });
+        if let Some(out) = run_rows(rows, &rest)? {
+            return Ok(out);
+        }
+        let seen = seen.borrow_mut().take().unwrap_or_default();
+        let value = decode_stream(Cursor::new(seen).chain(reader), options)?;
+        return Ok(query.apply(&value));
+    }
+    let value = decode_stream(reader, options)?;
+    Ok(query.apply(&value))
+}
+
+/// Feed each row to `rest`, or return `None` if the first one is an error
+fn run_rows(
+    mut rows: impl Iterator<Item = Result<Value, Error>>,
+    rest: &Query,
+) -> Result<Option<Vec<Value>>, Error> {
+    let mut out = Vec::new();
+    match rows.next() {
+        None => return Ok(Some(out)),
+        Some(Err(_)) => return Ok(None),
+        Some(Ok(row)) => out.extend(rest.apply(&row)),
+    }
+    for row in rows {
+        out.extend(rest.apply(&row?));
+    }
+    Ok(Some(out))
+}
+
+/// A reader that keeps a copy of what it reads until told to stop
+struct Recorder<'r, R> {
+    inner: &'r mut R,
+    seen: Rc<RefCell<Option<Vec<u8>>>>,
+}
+
+impl<R: Read> Read for Recorder<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(seen) = self.seen.borrow_mut().as_mut() {
+            seen.extend_from_slice(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+/// A parsed query, which can be run against decoded values
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::query::Query;
+///
+/// let query: Query = ".users[] | select(.age >= 18 and .active) | .name".parse()?;
+/// let data = json!({"users": [
+///     {"name": "Ann", "age": 31, "active": true},
+///     {"name": "Bo", "age": 17, "active": true},
+///     {"name": "Cy", "age": 40, "active": false}
+/// ]});
+/// assert_eq!(query.apply(&data), [json!("Ann")]);
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    stages: Vec<Stage>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Stage {
+    Path(Vec<Step>),
+    Select(Condition),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(i64),
+    Iterate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Or(Box<Condition>, Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Compare(Operand, Comparison, Operand),
+    Truthy(Operand),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Path(Vec<Step>),
+    Literal(Value),
+}
+
+impl Query {
+    /// Run the query against a value, returning every value it outputs
+    pub fn apply(&self, value: &Value) -> Vec<Value> {
+        let mut values = vec![value.clone()];
+        for stage in &self.stages {
+            let mut next = Vec::new();
+            for value in &values {
+                match stage {
+                    Stage::Path(steps) => walk(value, steps, &mut |v| next.push(v.clone())),
+                    Stage::Select(condition) => {
+                        if condition.holds(value) {
+                            next.push(value.clone());
+                        }
+                    }
+                }
+            }
+            values = next;
+        }
+        values
+    }
+
+    /// For a query starting `key[]` (or `[]` for a root array), the key and
+    /// the query to run on each item
+    fn split_rows(&self) -> Option<(&str, Query)> {
+        let (Stage::Path(steps), stages) = self.stages.split_first()? else {
+            return None;
+        };
+        let (key, rest) = match steps.as_slice() {
+            [Step::Iterate, rest @ ..] => ("", rest),
+            [Step::Key(key), Step::Iterate, rest @ ..] => (key.as_str(), rest),
+            _ => return None,
+        };
+        Some((key, self.with_first(rest, stages)))
+    }
+
+    /// For a query starting `key`, the key and the query to run on its value
+    fn split_key(&self) -> Option<(&str, Query)> {
+        let (Stage::Path(steps), stages) = self.stages.split_first()? else {
+            return None;
+        };
+        let [Step::Key(key), rest @ ..] = steps.as_slice() else {
+            return None;
+        };
+        Some((key, self.with_first(rest, stages)))
+    }
+
+    fn with_first(&self, steps: &[Step], stages: &[Stage]) -> Query {
+        let mut rest = vec![Stage::Path(steps.to_vec())];
+        rest.extend_from_slice(stages);
+        Query { stages: rest }
+    }
+}
+
+impl std::str::FromStr for Query {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Error> {
+        let mut parser = QueryParser { text, pos: 0 };
+        let mut stages = Vec::new();
+        loop {
+            stages.push(parser.stage(stages.is_empty())?);
+            if !parser.eat("|") {
+                break;
+            }
+        }
+        parser.skip_whitespace();
+        if parser.pos < text.len() {
+            return Err(parser.error("expected `|`"));
+        }
+        Ok(Query { stages })
+    }
+}
+
+/// Call `f` with every value `steps` leads to from `value`
+fn walk<'v>(value: &'v Value, steps: &[Step], f: &mut dyn FnMut(&'v Value)) {
+    let Some((step, rest)) = steps.split_first() else {
+        return f(value);
+    };
+    match (step, value) {
+        (Step::Key(key), value) => walk(value.get(key).unwrap_or(&NULL), rest, f),
+        (Step::Index(index), Value::Array(items)) => {
+            let index = if *index < 0 {
+                items.len().checked_sub(index.unsigned_abs() as usize)
+            } else {
+                Some(*index as usize)
+            };
+            let item = index.and_then(|index| items.get(index));
+            walk(item.unwrap_or(&NULL), rest, f)
+        }
+        (Step::Index(_), _) => walk(&NULL, rest, f),
+        (Step::Iterate, Value::Array(items)) => {
+            for item in items {
+                walk(item, rest, f);
+            }
+        }
+        (Step::Iterate, Value::Object(map)) => {
+            for item in map.values() {
+                walk(item, rest, f);
+            }
+        }
+        (Step::Iterate, _) => {}
+    }
+}
+
+impl Condition {
+    fn holds(&self, value: &Value) -> bool {
+        match self {
+            Condition::Or(a, b) => a.holds(value) || b.holds(value),
+            Condition::And(a, b) => a.holds(value) && b.holds(value),
+            Condition::Truthy(operand) => operand
+                .values(value)
+                .iter()
+                .any(|v| !matches!(v, Value::Null | Value::Bool(false))),
+            Condition::Compare(left, comparison, right) => {
+                let rights = right.values(value);
+                left.values(value).iter().any(|l| {
+                    rights.iter().any(|r| {
+                        let ordering = compare(l, r);
+                        match comparison {
+                            Comparison::Eq => ordering == Ordering::Equal,
+                            Comparison::Ne => ordering != Ordering::Equal,
+                            Comparison::Lt => ordering == Ordering::Less,
+                            Comparison::Le => ordering != Ordering::Greater,
+                            Comparison::Gt => ordering == Ordering::Greater,
+                            Comparison::Ge => ordering != Ordering::Less,
+                        }
+                    })
+                })
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn values<'v>(&'v self, value: &'v Value) -> Vec<&'v Value> {
+        match self {
+            Operand::Literal(literal) => vec![literal],
+            Operand::Path(steps) => {
+                let mut values = Vec::new();
+                walk(value, steps, &mut |v| values.push(v));
+                values
+            }
+        }
+    }
+}
+
+/// Order values as jq does: null, false, true, numbers, strings, arrays,
+/// then objects
+pub(crate) fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => {
+            let (a, b) = (a.as_f64().unwrap_or(0.0), b.as_f64().unwrap_or(0.0));
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        }
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Array(a), Value::Array(b)) => a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        (Value::Object(_), Value::Object(_)) => a.to_string().cmp(&b.to_string()),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Recursive descent over the text of a query
+struct QueryParser<'q> {
+    text: &'q str,
+    pos: usize,
+}
+
+impl QueryParser<'_> {
+    /// One stage, where only the first may start its path with a bare key
+    fn stage(&mut self, first: bool) -> Result<Stage, Error> {
+        self.skip_whitespace();
+        let start = self.pos;
+        if self.keyword("select") && self.eat("(") {
+            let condition = self.or()?;
+            if !self.eat(")") {
+                return Err(self.error("expected `)`"));
+            }
+            return Ok(Stage::Select(condition));
+        }
+        self.pos = start;
+        self.path(first).map(Stage::Path)
+    }
+
+    fn or(&mut self) -> Result<Condition, Error> {
+        let mut condition = self.and()?;
+        while self.keyword("or") {
+            condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<Condition, Error> {
+        let mut condition = self.comparison()?;
+        while self.keyword("and") {
+            condition = Condition::And(Box::new(condition), Box::new(self.comparison()?));
+        }
+        Ok(condition)
+    }
+
+    fn comparison(&mut self) -> Result<Condition, Error> {
+        if self.eat("(") {
+            let condition = self.or()?;
+            if !self.eat(")") {
+                return Err(self.error("expected `)`"));
+            }
+            return Ok(condition);
+        }
+        let left = self.operand()?;
+        let comparisons = [
+            ("==", Comparison::Eq),
+            ("!=", Comparison::Ne),
+            ("<=", Comparison::Le),
+            (">=", Comparison::Ge),
+            ("<", Comparison::Lt),
+            (">", Comparison::Gt),
+        ];
+        for (symbol, comparison) in comparisons {
+            if self.eat(symbol) {
+                return Ok(Condition::Compare(left, comparison, self.operand()?));
+            }
+        }
+        Ok(Condition::Truthy(left))
+    }
+
+    fn operand(&mut self) -> Result<Operand, Error> {
+        self.skip_whitespace();
+        let rest = &self.text[self.pos..];
+        for (word, literal) in [
+            ("true", Value::Bool(true)),
+            ("false", Value::Bool(false)),
+            ("null", Value::Null),
+        ] {
+            if self.keyword(word) {
+                return Ok(Operand::Literal(literal));
+            }
+        }
+        if rest.starts_with('"') {
+            return self.quoted().map(|s| Operand::Literal(Value::String(s)));
+        }
+        if rest.starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')))
+                .unwrap_or(rest.len());
+            let number = serde_json::from_str::<serde_json::Number>(&rest[..len])
+                .map_err(|_| self.error("invalid number"))?;
+            self.pos += len;
+            return Ok(Operand::Literal(Value::Number(number)));
+        }
+        self.path(false).map(Operand::Path)
+    }
+
+    /// A path such as `.`, `.a."b c"[0]` or, when `bare`, `items[]`
+    fn path(&mut self, bare: bool) -> Result<Vec<Step>, Error> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let mut steps = Vec::new();
+        let mut dotted = self.eat_char('.');
+        if !dotted && !bare && self.text[self.pos..].starts_with(is_key_char) {
+            let rest = &self.text[self.pos..];
+            let word = &rest[..rest.find(|c| !is_key_char(c)).unwrap_or(rest.len())];
+            return Err(self.error(&format!("unsupported function `{word}`")));
+        }
+        loop {
+            let keyed = dotted || self.pos == start;
+            match self.text[self.pos..].chars().next() {
+                Some('"') if keyed => steps.push(Step::Key(self.quoted()?)),
+                Some(c) if keyed && is_key_char(c) => {
+                    let rest = &self.text[self.pos..];
+                    let len = rest.find(|c| !is_key_char(c)).unwrap_or(rest.len());
+                    steps.push(Step::Key(rest[..len].to_string()));
+                    self.pos += len;
+                }
+                Some('[') => steps.push(self.bracket()?),
+                _ if dotted && !steps.is_empty() => return Err(self.error("expected a key")),
+                _ => break,
+            }
+            dotted = self.eat_char('.');
+        }
+        if self.pos == start {
+            return Err(self.error("expected a path or `select(...)`"));
+        }
+        Ok(steps)
+    }
+
+    /// `[]` or `[index]`
+    fn bracket(&mut self) -> Result<Step, Error> {
+        self.pos += 1; // consume '['
+        let rest = &self.text[self.pos..];
+        let close = rest.find(']').ok_or_else(|| self.error("unclosed `[`"))?;
+        let inner = rest[..close].trim();
+        let step = if inner.is_empty() {
+            Step::Iterate
+        } else {
+            Step::Index(inner.parse().map_err(|_| self.error("expected an index"))?)
+        };
+        self.pos += close + 1;
+        Ok(step)
+    }
+
+    fn quoted(&mut self) -> Result<String, Error> {
+        let rest = &self.text[self.pos..];
+        let len = crate::decode::quoted_len(rest).ok_or_else(|| self.error("unclosed quote"))?;
+        let text =
+            crate::decode::unescape(&rest[..len]).map_err(|err| self.error(&err.to_string()))?;
+        self.pos += len;
+        Ok(text)
+    }
+
+    /// Consume `word` if it comes next as a whole word
+    fn keyword(&mut self, word: &str) -> bool {
+        self.skip_whitespace();
+        let rest = &self.text[self.pos..];
+        let whole = rest
+            .strip_prefix(word)
+            .is_some_and(|after| !after.starts_with(is_key_char));
+        if whole {
+            self.pos += word.len();
+        }
+        whole
+    }
+
+    /// Consume `symbol` if it comes next, after any whitespace
+    fn eat(&mut self, symbol: &str) -> bool {
+        self.skip_whitespace();
+        if self.text[self.pos..].starts_with(symbol) {
+            self.pos += symbol.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_char(&mut self, ch: char) -> bool {
+        if self.text[self.pos..].starts_with(ch) {
+            self.pos += ch.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::Syntax(format!(
+            "Invalid query `{}`: {message} at column {}",
+            self.text,
+            self.text[..self.pos].chars().count() + 1
+        ))
+    }
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
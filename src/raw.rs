@@ -0,0 +1,119 @@
+//! Pre-encoded TOON fragments
+//!
+//! A [`RawToon`] holds the text of a value as it would be written at the
+//! root of a document. [`crate::ToonWriter::raw_field`] copies one into a
+//! larger document without decoding and encoding it again, and
+//! [`crate::lazy::LazyDocument::raw`] captures a top-level section's text
+//! without decoding it, so a cached subtree can be passed through untouched.
+
+use crate::decode::{decode, decode_entries, entry_key, opens_array};
+use crate::encode::encode;
+use crate::error::Error;
+use crate::options::{DecodeOptions, EncodeOptions};
+use serde_json::Value;
+use std::fmt;
+
+/// The text of one TOON value, kept as written
+///
+/// Objects are written as their entries, arrays from their `[N]` header on
+/// and primitives as they would be as a field's value, each at the root's
+/// indentation.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::{RawToon, ToonWriter};
+///
+/// let cached = RawToon::new("[2]{sku,qty}:\n  A1,2\n  B2,1")?;
+/// let mut writer = ToonWriter::new(Vec::new());
+/// writer.field("name", &json!("shop"))?.raw_field("items", &cached)?;
+/// let toon = String::from_utf8(writer.finish()?).unwrap();
+/// assert_eq!(toon, "name: shop\nitems[2]{sku,qty}:\n  A1,2\n  B2,1\n");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawToon {
+    text: String,
+}
+
+impl RawToon {
+    /// Wrap the text of a value, checking that it decodes
+    pub fn new(text: impl Into<String>) -> Result<Self, Error> {
+        let text = text.into();
+        decode(&text, None)?;
+        Ok(RawToon { text })
+    }
+
+    /// Encode a value once, to be written as it is from then on
+    pub fn from_value(value: &Value, options: Option<&EncodeOptions>) -> Result<Self, Error> {
+        Ok(RawToon {
+            text: encode(value, options)?,
+        })
+    }
+
+    /// The text of the value
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Take the text of the value
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    /// Decode the value
+    pub fn decode(&self, options: Option<&DecodeOptions>) -> Result<Value, Error> {
+        decode(&self.text, options)
+    }
+
+    /// Capture the value of the top-level entry at `start..end` of a
+    /// prepared input
+    ///
+    /// The text after the key is taken as it is, with an object's entries
+    /// brought back to the root. Values written over more than one line
+    /// without entries, such as block strings, can't stand at the root, so
+    /// they are decoded and encoded again.
+    pub(crate) fn from_entry(
+        input: &str,
+        entry: (usize, usize),
+        options: &DecodeOptions,
+    ) -> Result<Self, Error> {
+        let (key, key_end) = entry_key(input, entry, options)?;
+        let text = &input[key_end..entry.1];
+        let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+        let first = first.trim_start_matches([' ', '\t']);
+        let rest = rest.trim_end();
+
+        let text = if opens_array(first.chars().next(), options) {
+            if rest.is_empty() {
+                first.trim_end().to_string()
+            } else {
+                format!("{}\n{rest}", first.trim_end())
+            }
+        } else {
+            match first.strip_prefix(':').map(str::trim) {
+                Some("") if rest.is_empty() => "null".to_string(),
+                Some("") => {
+                    let indent = " ".repeat(options.get_indent());
+                    let lines = rest
+                        .lines()
+                        .map(|line| line.strip_prefix(indent.as_str()).unwrap_or(line));
+                    lines.collect::<Vec<_>>().join("\n")
+                }
+                Some(value) if rest.is_empty() => value.to_string(),
+                _ => {
+                    let value = decode_entries(input, &[entry], &key, options, false)?;
+                    return Self::from_value(&value.unwrap_or_default(), None);
+                }
+            }
+        };
+        Ok(RawToon { text })
+    }
+}
+
+impl fmt::Display for RawToon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
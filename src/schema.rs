@@ -0,0 +1,390 @@
+//! Schemas inferred from decoded values
+//!
+//! [`infer_schema`] works out the shape of a value: the fields of each
+//! object with their types and whether every object has them, and which
+//! arrays are tables. The resulting [`ToonSchema`] prints as a compact block
+//! that reads like the document it describes, for putting in a prompt, and
+//! can check other values against it with [`ToonSchema::validate`].
+//!
+//! ```text
+//! name: string
+//! items[]{sku: string, qty: integer, note?: string|null}
+//! meta:
+//!   tags[]: string
+//! ```
+
+use crate::encode::format_key;
+use crate::error::Error;
+use crate::options::EncodeOptions;
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// The shape of a value
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToonSchema {
+    /// Always null
+    Null,
+    /// `true` or `false`
+    Boolean,
+    /// A number without a fractional part
+    Integer,
+    /// Any number
+    Number,
+    /// A string
+    String,
+    /// Anything, as for the items of an array that was always empty
+    Any,
+    /// An array of objects of primitives, written as a table
+    Table(Vec<Field>),
+    /// An array whose items all have the given shape
+    Array(Box<ToonSchema>),
+    /// An object with the given fields
+    Object(Vec<Field>),
+    /// Any one of several shapes
+    Union(Vec<ToonSchema>),
+}
+
+/// A field of an object or a column of a table
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    /// The field's key
+    pub name: String,
+    /// The shape of its value
+    pub schema: ToonSchema,
+    /// Whether some objects leave it out
+    pub optional: bool,
+}
+
+/// Infer the schema of a value
+///
+/// Arrays of objects whose values are all primitives become tables, with a
+/// column for every key found in any row; keys missing from some rows are
+/// optional. Items of other arrays are merged into one shape, falling back
+/// to a union where they differ in kind. Integers and other numbers merge
+/// into `number`.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::{decode, infer_schema};
+///
+/// let doc = decode("name: shop\nitems[2]{sku,qty}:\n  A1,2\n  B2,1.5", None)?;
+/// let schema = infer_schema(&doc);
+/// assert_eq!(schema.to_string(), "items[]{qty: number, sku: string}\nname: string");
+/// assert!(schema.validate(&doc).is_ok());
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn infer_schema(value: &Value) -> ToonSchema {
+    match value {
+        Value::Null => ToonSchema::Null,
+        Value::Bool(_) => ToonSchema::Boolean,
+        Value::Number(n) if n.is_i64() || n.is_u64() => ToonSchema::Integer,
+        Value::Number(_) => ToonSchema::Number,
+        Value::String(_) => ToonSchema::String,
+        Value::Object(map) => ToonSchema::Object(fields(map)),
+        Value::Array(items) if !items.is_empty() && items.iter().all(is_flat_object) => {
+            let rows = items.iter().filter_map(Value::as_object).map(fields);
+            ToonSchema::Table(rows.reduce(merge_fields).unwrap_or_default())
+        }
+        Value::Array(items) => {
+            let item = items.iter().map(infer_schema).reduce(ToonSchema::merge);
+            ToonSchema::Array(Box::new(item.unwrap_or(ToonSchema::Any)))
+        }
+    }
+}
+
+fn fields(map: &Map<String, Value>) -> Vec<Field> {
+    map.iter()
+        .map(|(name, value)| Field {
+            name: name.clone(),
+            schema: infer_schema(value),
+            optional: false,
+        })
+        .collect()
+}
+
+/// Whether a value is an object that could be a table row
+fn is_flat_object(value: &Value) -> bool {
+    value
+        .as_object()
+        .is_some_and(|map| map.values().all(|v| !v.is_array() && !v.is_object()))
+}
+
+impl ToonSchema {
+    /// Combine two shapes into one that both fit
+    pub fn merge(self, other: ToonSchema) -> ToonSchema {
+        use ToonSchema::*;
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Any, other) | (other, Any) => other,
+            (Integer, Number) | (Number, Integer) => Number,
+            (Object(a), Object(b)) => Object(merge_fields(a, b)),
+            (Table(a), Table(b)) => Table(merge_fields(a, b)),
+            (Table(row), Array(item)) | (Array(item), Table(row)) => {
+                Array(Box::new(Object(row).merge(*item)))
+            }
+            (Array(a), Array(b)) => Array(Box::new(a.merge(*b))),
+            (Union(members), other) | (other, Union(members)) => {
+                let mut union = members;
+                match other {
+                    Union(more) => {
+                        for member in more {
+                            add_member(&mut union, member);
+                        }
+                    }
+                    other => add_member(&mut union, other),
+                }
+                Union(union)
+            }
+            (a, b) if a.kind() == b.kind() => a.merge(b),
+            (a, b) => Union(vec![a, b]),
+        }
+    }
+
+    /// Check a value against the schema, returning every place it doesn't
+    /// fit
+    ///
+    /// Each violation is a [`Error::Deserialization`] with the path to the
+    /// value (`items[2].qty`). Missing fields that aren't optional and keys
+    /// the schema doesn't list are violations too.
+    pub fn validate(&self, value: &Value) -> Result<(), Vec<Error>> {
+        let mut errors = Vec::new();
+        self.check(value, "", &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check(&self, value: &Value, path: &str, errors: &mut Vec<Error>) {
+        let fits = match (self, value) {
+            (ToonSchema::Any, _)
+            | (ToonSchema::Null, Value::Null)
+            | (ToonSchema::Boolean, Value::Bool(_))
+            | (ToonSchema::Number, Value::Number(_))
+            | (ToonSchema::String, Value::String(_)) => true,
+            (ToonSchema::Integer, Value::Number(n)) => n.is_i64() || n.is_u64(),
+            (ToonSchema::Object(fields), Value::Object(map)) => {
+                check_fields(fields, map, path, errors);
+                true
+            }
+            (ToonSchema::Table(fields), Value::Array(items)) => {
+                for (i, item) in items.iter().enumerate() {
+                    let path = format!("{path}[{i}]");
+                    match item {
+                        Value::Object(map) => check_fields(fields, map, &path, errors),
+                        _ => errors.push(violation(&path, "expected a table row", item)),
+                    }
+                }
+                true
+            }
+            (ToonSchema::Array(schema), Value::Array(items)) => {
+                for (i, item) in items.iter().enumerate() {
+                    schema.check(item, &format!("{path}[{i}]"), errors);
+                }
+                true
+            }
+            (ToonSchema::Union(members), value) => members.iter().any(|member| {
+                let mut found = Vec::new();
+                member.check(value, path, &mut found);
+                found.is_empty()
+            }),
+            _ => false,
+        };
+        if !fits {
+            errors.push(violation(path, &format!("expected {self}"), value));
+        }
+    }
+
+    /// Shapes of the same kind merge into one rather than a union
+    fn kind(&self) -> u8 {
+        match self {
+            ToonSchema::Null => 0,
+            ToonSchema::Boolean => 1,
+            ToonSchema::Integer | ToonSchema::Number => 2,
+            ToonSchema::String => 3,
+            ToonSchema::Table(_) | ToonSchema::Array(_) => 4,
+            ToonSchema::Object(_) => 5,
+            ToonSchema::Any => 6,
+            ToonSchema::Union(_) => 7,
+        }
+    }
+}
+
+/// Fields of two objects merged, those missing from either made optional
+fn merge_fields(a: Vec<Field>, b: Vec<Field>) -> Vec<Field> {
+    let mut merged: Vec<Field> = a
+        .into_iter()
+        .map(|field| Field {
+            optional: field.optional || !b.iter().any(|other| other.name == field.name),
+            ..field
+        })
+        .collect();
+    for field in b {
+        match merged.iter_mut().find(|other| other.name == field.name) {
+            Some(other) => {
+                other.schema =
+                    std::mem::replace(&mut other.schema, ToonSchema::Any).merge(field.schema);
+                other.optional |= field.optional;
+            }
+            None => merged.push(Field {
+                optional: true,
+                ..field
+            }),
+        }
+    }
+    merged
+}
+
+/// Add a shape to a union, merging it into a member of the same kind
+fn add_member(union: &mut Vec<ToonSchema>, schema: ToonSchema) {
+    match union
+        .iter_mut()
+        .find(|member| member.kind() == schema.kind())
+    {
+        Some(member) => {
+            *member = std::mem::replace(member, ToonSchema::Any).merge(schema);
+        }
+        None => union.push(schema),
+    }
+}
+
+fn check_fields(fields: &[Field], map: &Map<String, Value>, path: &str, errors: &mut Vec<Error>) {
+    for field in fields {
+        let field_path = child(path, &field.name);
+        match map.get(&field.name) {
+            Some(value) => field.schema.check(value, &field_path, errors),
+            None if !field.optional => errors.push(Error::Deserialization {
+                message: "missing field".to_string(),
+                path: Some(field_path),
+                line: None,
+            }),
+            None => {}
+        }
+    }
+    for key in map.keys() {
+        if !fields.iter().any(|field| &field.name == key) {
+            errors.push(Error::Deserialization {
+                message: "unexpected field".to_string(),
+                path: Some(child(path, key)),
+                line: None,
+            });
+        }
+    }
+}
+
+fn child(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn violation(path: &str, expected: &str, value: &Value) -> Error {
+    let found = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    };
+    Error::Deserialization {
+        message: format!("{expected}, found {found}"),
+        path: (!path.is_empty()).then(|| path.to_string()),
+        line: None,
+    }
+}
+
+impl fmt::Display for ToonSchema {
+    /// A field-per-line block for objects, and the inline form otherwise
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToonSchema::Object(fields) if !fields.is_empty() => {
+                let mut lines = Vec::new();
+                entry_lines(fields, 0, &mut lines);
+                f.write_str(&lines.join("\n"))
+            }
+            ToonSchema::Table(fields) => write!(f, "[]{}", Inline(fields)),
+            ToonSchema::Array(item) => write!(f, "[]: {}", InlineSchema(item)),
+            _ => write!(f, "{}", InlineSchema(self)),
+        }
+    }
+}
+
+/// Lines for the fields of an object, nested objects indented below their key
+fn entry_lines(fields: &[Field], depth: usize, lines: &mut Vec<String>) {
+    let options = EncodeOptions::default();
+    for field in fields {
+        let key = format!(
+            "{}{}{}",
+            "  ".repeat(depth),
+            format_key(&field.name, &options),
+            if field.optional { "?" } else { "" }
+        );
+        match &field.schema {
+            ToonSchema::Object(inner) if !inner.is_empty() => {
+                lines.push(format!("{key}:"));
+                entry_lines(inner, depth + 1, lines);
+            }
+            ToonSchema::Table(columns) => lines.push(format!("{key}[]{}", Inline(columns))),
+            ToonSchema::Array(item) => {
+                lines.push(format!("{key}[]: {}", InlineSchema(item)));
+            }
+            schema => lines.push(format!("{key}: {}", InlineSchema(schema))),
+        }
+    }
+}
+
+/// A schema written on one line
+struct InlineSchema<'s>(&'s ToonSchema);
+
+impl fmt::Display for InlineSchema<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            ToonSchema::Null => f.write_str("null"),
+            ToonSchema::Boolean => f.write_str("boolean"),
+            ToonSchema::Integer => f.write_str("integer"),
+            ToonSchema::Number => f.write_str("number"),
+            ToonSchema::String => f.write_str("string"),
+            ToonSchema::Any => f.write_str("any"),
+            ToonSchema::Table(fields) => write!(f, "[]{}", Inline(fields)),
+            ToonSchema::Array(item) => write!(f, "[{}]", InlineSchema(item)),
+            ToonSchema::Object(fields) => write!(f, "{}", Inline(fields)),
+            ToonSchema::Union(members) => {
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("|")?;
+                    }
+                    write!(f, "{}", InlineSchema(member))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Fields written on one line, in braces
+struct Inline<'s>(&'s [Field]);
+
+impl fmt::Display for Inline<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = EncodeOptions::default();
+        f.write_str("{")?;
+        for (i, field) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            let optional = if field.optional { "?" } else { "" };
+            write!(
+                f,
+                "{}{optional}: {}",
+                format_key(&field.name, &options),
+                InlineSchema(&field.schema)
+            )?;
+        }
+        f.write_str("}")
+    }
+}
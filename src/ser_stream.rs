@@ -0,0 +1,1414 @@
+//! Encoding `Serialize` types straight to a writer
+//!
+//! Unlike [`crate::serde_api::to_writer`], this never builds a
+//! `serde_json::Value` tree or the whole document as a `String`. A TOON
+//! header needs an array's length and layout before its items, so each
+//! container is walked twice through its `Serialize` impl: once to survey
+//! its shape, then again to write it. Only single leaf values, one table row
+//! and one inline array are held in memory at a time.
+//!
+//! The layout is the same as [`crate::serde_api::to_string_with_options`]
+//! picks, but entries are written in the order the value serializes them
+//! instead of being sorted by `serde_json::Map`.
+
+use crate::encode::{
+    block_string, check_indent, encode_primitive_value, encode_scalar, format_key, inline_items,
+    is_path_segment, key_separator, length_header, null_value, table_length_header, transform_leaf,
+    typed_fields, ColumnCells, Sink, Slot, WriteSink,
+};
+use crate::error::Error;
+use crate::options::{ColumnType, EncodeOptions, NullPolicy};
+use crate::ser::{raw_value, to_value, MapKeySerializer, RAW_VALUE_TOKEN};
+use serde::ser::{self, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// Encode `value` to `writer` as it is serialized
+pub(crate) fn to_writer<T: Serialize + ?Sized, W: Write>(
+    value: &T,
+    writer: &mut W,
+    options: &EncodeOptions,
+) -> Result<(), Error> {
+    check_indent(options)?;
+    let encoder = Encoder { options };
+    let node = Node::new(value);
+    let output = &mut WriteSink(writer);
+    match encoder.shape(node)? {
+        Shape::Leaf(value) => encode_primitive_value(&value, output, options, None, Slot::Root)?,
+        _ => encoder.value(node, output, 0)?,
+    }
+    writer.flush().map_err(|e| Error::Io(e.to_string()))
+}
+
+/// Writes a keyed table one row at a time, for data whose length isn't
+/// known up front, such as rows read from a database cursor
+///
+/// Each row is serialized as soon as it is pushed. The `[N]` header needs the
+/// final count, so rendered rows are held back until [`finish`] unless
+/// lengths are turned off with [`EncodeOptions::emit_lengths`], in which case
+/// the header goes out with the first row and every row is written straight
+/// through. Rows must be objects of primitive values (flattened under key
+/// folding); fields missing from a row are left as empty cells, and a field
+/// that isn't one of the columns is an error. Columns are not aligned.
+///
+/// [`finish`]: TabularWriter::finish
+///
+/// # Example
+///
+/// ```rust
+/// use serde::Serialize;
+/// use toon_rust::TabularWriter;
+///
+/// #[derive(Serialize)]
+/// struct Product {
+///     sku: String,
+///     qty: u32,
+/// }
+///
+/// let mut table = TabularWriter::new("items", ["sku", "qty"], Vec::new());
+/// table.push_row(&Product { sku: "A1".to_string(), qty: 2 }).unwrap();
+/// table.push_row(&Product { sku: "B2".to_string(), qty: 1 }).unwrap();
+/// let out = table.finish().unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "items[2]{sku,qty}:\n  A1,2\n  B2,1\n");
+/// ```
+pub struct TabularWriter<W: Write> {
+    writer: W,
+    rows: RowRenderer,
+}
+
+impl<W: Write> TabularWriter<W> {
+    /// Create a writer for the table `key` with the given columns
+    pub fn new<I>(key: impl Into<String>, columns: I, writer: W) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self::with_options(key, columns, writer, &EncodeOptions::default())
+    }
+
+    /// Create a writer for the table `key` with the given columns and options
+    pub fn with_options<I>(
+        key: impl Into<String>,
+        columns: I,
+        writer: W,
+        options: &EncodeOptions,
+    ) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        TabularWriter {
+            writer,
+            rows: RowRenderer::new(key, columns, options),
+        }
+    }
+
+    /// Serialize one row and write it, or hold it until [`finish`] when
+    /// the row count goes in the header
+    ///
+    /// [`finish`]: TabularWriter::finish
+    pub fn push_row<T: Serialize + ?Sized>(&mut self, row: &T) -> Result<(), Error> {
+        if let Some(text) = self.rows.push(row)? {
+            self.write(&text)?;
+        }
+        Ok(())
+    }
+
+    /// Write the header and any rows still held back, and return the writer
+    pub fn finish(mut self) -> Result<W, Error> {
+        let text = self.rows.finish();
+        self.write(&text)?;
+        self.writer.flush().map_err(|e| Error::Io(e.to_string()))?;
+        Ok(self.writer)
+    }
+
+    fn write(&mut self, s: &str) -> Result<(), Error> {
+        self.writer
+            .write_all(s.as_bytes())
+            .map_err(|e| Error::Io(e.to_string()))
+    }
+}
+
+/// Writes a keyed table across numbered files, starting a new file every
+/// `rows_per_file` rows, for cutting a dataset into prompt-sized pieces
+///
+/// Each file holds a complete table under the same key and columns, with
+/// its own `[N]` header, written through a [`TabularWriter`]. `name` is
+/// called with the index of each file, counting from 0, to get its path.
+/// No file is created until the first row is pushed, and a `rows_per_file`
+/// of zero is taken as one.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::RotatingWriter;
+///
+/// let dir = std::env::temp_dir();
+/// let mut table = RotatingWriter::new("items", ["sku", "qty"], 2, |i| {
+///     dir.join(format!("doc-rotating-{i}.toon"))
+/// });
+/// for (sku, qty) in [("A1", 2), ("B2", 1), ("C3", 5)] {
+///     table.push_row(&json!({"sku": sku, "qty": qty}))?;
+/// }
+/// let files = table.finish()?;
+/// assert_eq!(files.len(), 2);
+/// assert_eq!(std::fs::read_to_string(&files[1]).unwrap(), "items[1]{sku,qty}:\n  C3,5\n");
+/// # for file in &files {
+/// #     std::fs::remove_file(file).unwrap();
+/// # }
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub struct RotatingWriter<F> {
+    key: String,
+    columns: Vec<String>,
+    options: EncodeOptions,
+    rows_per_file: usize,
+    name: F,
+    current: Option<TabularWriter<BufWriter<File>>>,
+    /// Rows pushed into the current file
+    rows: usize,
+    files: Vec<PathBuf>,
+}
+
+impl<F: FnMut(usize) -> PathBuf> RotatingWriter<F> {
+    /// Create a writer for the table `key` with the given columns
+    pub fn new<I>(key: impl Into<String>, columns: I, rows_per_file: usize, name: F) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self::with_options(key, columns, rows_per_file, name, &EncodeOptions::default())
+    }
+
+    /// Create a writer for the table `key` with the given columns and options
+    pub fn with_options<I>(
+        key: impl Into<String>,
+        columns: I,
+        rows_per_file: usize,
+        name: F,
+        options: &EncodeOptions,
+    ) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        RotatingWriter {
+            key: key.into(),
+            columns: columns.into_iter().map(Into::into).collect(),
+            options: options.clone(),
+            rows_per_file: rows_per_file.max(1),
+            name,
+            current: None,
+            rows: 0,
+            files: Vec::new(),
+        }
+    }
+
+    /// Serialize one row into the current file, first finishing it and
+    /// starting the next one when it is full
+    pub fn push_row<T: Serialize + ?Sized>(&mut self, row: &T) -> Result<(), Error> {
+        if self.current.is_none() || self.rows == self.rows_per_file {
+            self.rotate()?;
+        }
+        if let Some(table) = &mut self.current {
+            table.push_row(row)?;
+            self.rows += 1;
+        }
+        Ok(())
+    }
+
+    /// Finish the last file and return the paths of every file written
+    pub fn finish(mut self) -> Result<Vec<PathBuf>, Error> {
+        if let Some(table) = self.current.take() {
+            table.finish()?;
+        }
+        Ok(self.files)
+    }
+
+    fn rotate(&mut self) -> Result<(), Error> {
+        if let Some(table) = self.current.take() {
+            table.finish()?;
+        }
+        let path = (self.name)(self.files.len());
+        let file = File::create(&path).map_err(|e| Error::Io(e.to_string()))?;
+        self.current = Some(TabularWriter::with_options(
+            self.key.clone(),
+            self.columns.clone(),
+            BufWriter::new(file),
+            &self.options,
+        ));
+        self.files.push(path);
+        self.rows = 0;
+        Ok(())
+    }
+}
+
+/// The rendering behind [`TabularWriter`], apart from the writing, so
+/// blocking and async writers share it
+pub(crate) struct RowRenderer {
+    key: String,
+    table: Table,
+    options: EncodeOptions,
+    rows: usize,
+    /// Rendered rows waiting for the count to be known
+    pending: String,
+}
+
+impl RowRenderer {
+    pub(crate) fn new<I>(key: impl Into<String>, columns: I, options: &EncodeOptions) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        RowRenderer {
+            key: key.into(),
+            table: Table {
+                keys: columns.into_iter().map(Into::into).collect(),
+                sparse: false,
+                types: Vec::new(),
+            },
+            options: options.clone(),
+            rows: 0,
+            pending: String::new(),
+        }
+    }
+
+    /// Serialize one row, returning the text to write now, or `None` when
+    /// the row is held until [`finish`](Self::finish)
+    pub(crate) fn push<T: Serialize + ?Sized>(&mut self, row: &T) -> Result<Option<String>, Error> {
+        check_indent(&self.options)?;
+        let encoder = Encoder {
+            options: &self.options,
+        };
+        let row = encoder.row(Node::new(row))?.ok_or_else(|| {
+            Error::Serialization("table row must be an object of primitive values".to_string())
+        })?;
+        if let Some((column, ..)) = row
+            .iter()
+            .find(|(column, ..)| !self.table.keys.contains(column))
+        {
+            return Err(Error::Serialization(format!(
+                "row field `{column}` is not a column of table `{}`",
+                self.key
+            )));
+        }
+
+        let mut line = self.options.get_indent_string().into_owned();
+        line.push_str(
+            &encoder
+                .cells(&row, &self.table)?
+                .join(&self.options.get_delimiter().to_string()),
+        );
+        line.push('\n');
+        self.rows += 1;
+        if self.options.get_emit_lengths() {
+            self.pending.push_str(&line);
+            return Ok(None);
+        }
+        if self.rows == 1 {
+            line.insert_str(0, &self.header(0));
+        }
+        Ok(Some(line))
+    }
+
+    /// The header and any rows still held back
+    pub(crate) fn finish(&mut self) -> String {
+        if self.rows == 0 {
+            return format!(
+                "{}{}:\n",
+                format_key(&self.key, &self.options),
+                length_header(0, &self.options)
+            );
+        }
+        if !self.options.get_emit_lengths() {
+            return String::new();
+        }
+        let mut text = self.header(self.rows);
+        text.push_str(&std::mem::take(&mut self.pending));
+        text
+    }
+
+    fn header(&self, len: usize) -> String {
+        format!(
+            "{}{}{{{}}}:\n",
+            format_key(&self.key, &self.options),
+            table_length_header(len, &self.options),
+            typed_fields(&self.table.keys, &self.table.types, &self.options)
+        )
+    }
+}
+
+/// A value to encode: a `Serialize` type, or with `body` set, the fields of
+/// the tuple or struct variant it serializes as
+struct Node<'a, T: ?Sized> {
+    value: &'a T,
+    body: bool,
+}
+
+impl<'a, T: ?Sized> Node<'a, T> {
+    fn new(value: &'a T) -> Self {
+        Node { value, body: false }
+    }
+}
+
+impl<T: ?Sized> Clone for Node<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for Node<'_, T> {}
+
+/// What a node serializes as, without its contents
+enum Shape {
+    /// A primitive, as the JSON value it converts to
+    Leaf(Value),
+    /// An array with this many items
+    Array(usize),
+    /// An object with this many entries, and the key of the first one
+    Object(usize, Option<String>),
+}
+
+/// Receives the items of an array or the entries of an object
+trait Visit {
+    fn child<T: Serialize + ?Sized>(
+        &mut self,
+        key: Option<&str>,
+        node: Node<'_, T>,
+    ) -> Result<(), Error>;
+}
+
+/// Visitor that skips the children, for finding a node's shape
+struct Ignore;
+
+impl Visit for Ignore {
+    fn child<T: Serialize + ?Sized>(
+        &mut self,
+        _: Option<&str>,
+        _: Node<'_, T>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Serialize `node`, passing its children to `visit` and returning its shape
+fn walk<T: Serialize + ?Sized>(
+    node: Node<'_, T>,
+    options: &EncodeOptions,
+    visit: &mut impl Visit,
+) -> Result<Shape, Error> {
+    node.value.serialize(Walker {
+        node,
+        options,
+        visit,
+    })
+}
+
+/// A row of a table: each cell's column, field name and value
+type Row = Vec<(String, String, Value)>;
+
+/// Layout of an array written as a table
+struct Table {
+    keys: Vec<String>,
+    /// Some row lacks a column, so null cells are spelled out
+    sparse: bool,
+    /// Types declared for the columns, under `typed_headers`
+    types: Vec<Option<ColumnType>>,
+}
+
+/// The encoder's layout rules, applied to nodes instead of JSON values
+#[derive(Clone, Copy)]
+struct Encoder<'o> {
+    options: &'o EncodeOptions,
+}
+
+impl<'o> Encoder<'o> {
+    fn shape<T: Serialize + ?Sized>(self, node: Node<'_, T>) -> Result<Shape, Error> {
+        walk(node, self.options, &mut Ignore)
+    }
+
+    fn value<T: Serialize + ?Sized>(
+        self,
+        node: Node<'_, T>,
+        output: &mut impl Sink,
+        indent_level: usize,
+    ) -> Result<(), Error> {
+        match self.shape(node)? {
+            Shape::Leaf(value) => {
+                encode_primitive_value(&value, output, self.options, None, Slot::Value)
+            }
+            Shape::Array(len) => self.array(node, len, output, indent_level),
+            Shape::Object(..) => self.object(node, output, indent_level, false),
+        }
+    }
+
+    fn array<T: Serialize + ?Sized>(
+        self,
+        node: Node<'_, T>,
+        len: usize,
+        output: &mut impl Sink,
+        indent_level: usize,
+    ) -> Result<(), Error> {
+        let options = self.options;
+        if len == 0 {
+            return output.push_str(&format!("{}:", length_header(0, options)));
+        }
+
+        if let Some(table) = self.table_layout(node, len, indent_level)? {
+            output.push_str(&length_header(len, options))?;
+            output.push('{')?;
+            output.push_str(&typed_fields(&table.keys, &table.types, options))?;
+            output.push_str("}:\n")?;
+            return self.table_rows(node, &table, output, indent_level);
+        }
+
+        let Some(leaves) = self.leaves(node, len)? else {
+            output.push_str(&format!("{}:\n", length_header(len, options)))?;
+            return self.list(node, output, indent_level);
+        };
+        output.push_str(&format!("{}:", length_header(len, options)))?;
+        let line_start = indent_level * options.get_indent_string().chars().count();
+        let items = match len <= options.get_max_inline_items() {
+            true => inline_items(&leaves, None, line_start, options)?,
+            false => None,
+        };
+        match items {
+            Some(items) => output.push_str(&items),
+            None => {
+                output.push('\n')?;
+                self.list(node, output, indent_level)
+            }
+        }
+    }
+
+    /// The items of an array when they are all primitives, or `None`
+    ///
+    /// Items are only kept when the array is short enough to be inline.
+    fn leaves<T: Serialize + ?Sized>(
+        self,
+        node: Node<'_, T>,
+        len: usize,
+    ) -> Result<Option<Vec<Value>>, Error> {
+        let mut leaves = Leaves {
+            encoder: self,
+            keep: len <= self.options.get_max_inline_items(),
+            items: Some(Vec::new()),
+        };
+        walk(node, self.options, &mut leaves)?;
+        Ok(leaves.items)
+    }
+
+    /// Mirror of the encoder's `table_layout` for nodes
+    fn table_layout<T: Serialize + ?Sized>(
+        self,
+        node: Node<'_, T>,
+        len: usize,
+        indent_level: usize,
+    ) -> Result<Option<Table>, Error> {
+        let options = self.options;
+        if !options.get_optimize_for_tokens() {
+            return self.uniform_columns(node, len);
+        }
+        let relaxed = EncodeOptions {
+            min_tabular_rows: Some(1),
+            tabular_uniformity: Some(0.0),
+            ..options.clone()
+        };
+        let Some(table) = (Encoder { options: &relaxed }).uniform_columns(node, len)? else {
+            return Ok(None);
+        };
+
+        let mut table_len = Count(typed_fields(&table.keys, &table.types, options).len() + 2);
+        self.table_rows(node, &table, &mut table_len, indent_level)?;
+        let mut list_len = Count(0);
+        self.list(node, &mut list_len, indent_level)?;
+        Ok((table_len.0 <= list_len.0).then_some(table))
+    }
+
+    /// Mirror of the encoder's `check_uniform_objects` for nodes
+    fn uniform_columns<T: Serialize + ?Sized>(
+        self,
+        node: Node<'_, T>,
+        len: usize,
+    ) -> Result<Option<Table>, Error> {
+        let options = self.options;
+        if len == 0 || len < options.get_min_tabular_rows() {
+            return Ok(None);
+        }
+
+        let mut columns = Columns {
+            encoder: self,
+            keys: Some(Vec::new()),
+            seen: std::collections::HashSet::new(),
+            widths: Vec::with_capacity(len),
+            cells: std::collections::HashMap::new(),
+        };
+        walk(node, options, &mut columns)?;
+        let Some(keys) = columns.keys else {
+            return Ok(None);
+        };
+        if keys.is_empty() || keys.len() > options.get_max_tabular_columns() {
+            return Ok(None);
+        }
+
+        let widths = columns.widths;
+        let complete = widths.iter().filter(|&&width| width == keys.len()).count();
+        if (complete as f64) < options.get_tabular_uniformity() * len as f64 {
+            return Ok(None);
+        }
+        let sparse = complete < widths.len();
+        let types = keys
+            .iter()
+            .map(|key| columns.cells.get(key).and_then(|cells| cells.column_type()))
+            .collect();
+        Ok(Some(Table {
+            keys,
+            sparse,
+            types,
+        }))
+    }
+
+    /// The cells of a table row, or `None` if it has a value that can't be a
+    /// cell, with nested objects flattened into dotted columns under key
+    /// folding
+    fn row<T: Serialize + ?Sized>(self, node: Node<'_, T>) -> Result<Option<Row>, Error> {
+        let mut row = RowCells {
+            encoder: self,
+            prefix: None,
+            cells: Some(Vec::new()),
+        };
+        match self.shape(node)? {
+            Shape::Object(..) => walk(node, self.options, &mut row)?,
+            _ => return Ok(None),
+        };
+        Ok(row.cells)
+    }
+
+    fn table_rows<T: Serialize + ?Sized>(
+        self,
+        node: Node<'_, T>,
+        table: &Table,
+        output: &mut impl Sink,
+        indent_level: usize,
+    ) -> Result<(), Error> {
+        let widths = match self.options.get_align_columns() {
+            true => {
+                let mut measure = TableRows {
+                    encoder: self,
+                    table,
+                    widths: vec![0; table.keys.len()],
+                    output: None::<&mut Count>,
+                    indent_level,
+                };
+                walk(node, self.options, &mut measure)?;
+                Some(measure.widths)
+            }
+            false => None,
+        };
+        let mut rows = TableRows {
+            encoder: self,
+            table,
+            widths: widths.unwrap_or_default(),
+            output: Some(output),
+            indent_level,
+        };
+        walk(node, self.options, &mut rows)?;
+        Ok(())
+    }
+
+    /// Render one table row's cells in column order
+    fn cells(self, row: &Row, table: &Table) -> Result<Vec<String>, Error> {
+        let mut cells = Vec::with_capacity(table.keys.len());
+        for key in &table.keys {
+            let mut cell = String::new();
+            if let Some((_, field, value)) = row.iter().find(|(column, ..)| column == key) {
+                let value = transform_leaf(value, Some(field), self.options);
+                if table.sparse && value.is_null() {
+                    cell.push_str("null");
+                } else {
+                    encode_scalar(&value, &mut cell, self.options, Slot::Cell)?;
+                }
+            }
+            cells.push(cell);
+        }
+        Ok(cells)
+    }
+
+    fn list<T: Serialize + ?Sized>(
+        self,
+        node: Node<'_, T>,
+        output: &mut impl Sink,
+        indent_level: usize,
+    ) -> Result<(), Error> {
+        let mut items = ListItems {
+            encoder: self,
+            output,
+            indent_level,
+        };
+        walk(node, self.options, &mut items)?;
+        Ok(())
+    }
+
+    fn object<T: Serialize + ?Sized>(
+        self,
+        node: Node<'_, T>,
+        output: &mut impl Sink,
+        indent_level: usize,
+        list_item: bool,
+    ) -> Result<(), Error> {
+        let mut fields = Fields {
+            encoder: self,
+            output,
+            indent_level,
+            list_item,
+            first: true,
+        };
+        walk(node, self.options, &mut fields)?;
+        Ok(())
+    }
+}
+
+/// Sink that only counts bytes, for comparing layouts
+struct Count(usize);
+
+impl Sink for Count {
+    fn push_str(&mut self, s: &str) -> Result<(), Error> {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+struct Leaves<'o> {
+    encoder: Encoder<'o>,
+    keep: bool,
+    items: Option<Vec<Value>>,
+}
+
+impl Visit for Leaves<'_> {
+    fn child<T: Serialize + ?Sized>(
+        &mut self,
+        _: Option<&str>,
+        node: Node<'_, T>,
+    ) -> Result<(), Error> {
+        let Some(items) = &mut self.items else {
+            return Ok(());
+        };
+        match self.encoder.shape(node)? {
+            Shape::Leaf(value) if self.keep => items.push(value),
+            Shape::Leaf(_) => {}
+            _ => self.items = None,
+        }
+        Ok(())
+    }
+}
+
+struct Columns<'o> {
+    encoder: Encoder<'o>,
+    keys: Option<Vec<String>>,
+    seen: std::collections::HashSet<String>,
+    widths: Vec<usize>,
+    /// What each column's cells have in common, under `typed_headers`
+    cells: std::collections::HashMap<String, ColumnCells>,
+}
+
+impl Visit for Columns<'_> {
+    fn child<T: Serialize + ?Sized>(
+        &mut self,
+        _: Option<&str>,
+        node: Node<'_, T>,
+    ) -> Result<(), Error> {
+        if self.keys.is_none() {
+            return Ok(());
+        }
+        let Some(row) = self.encoder.row(node)? else {
+            self.keys = None;
+            return Ok(());
+        };
+        let mut columns = std::collections::HashSet::new();
+        // A literal `a.b` key next to a nested `a: {b}` has no single cell
+        if !row
+            .iter()
+            .all(|(column, ..)| columns.insert(column.as_str()))
+        {
+            self.keys = None;
+            return Ok(());
+        }
+        self.widths.push(row.len());
+        let options = self.encoder.options;
+        if options.get_typed_headers() {
+            for (column, field, value) in &row {
+                let cells = self.cells.entry(column.clone()).or_default();
+                *cells = cells.add(&transform_leaf(value, Some(field), options));
+            }
+        }
+        if let Some(keys) = &mut self.keys {
+            for (column, ..) in row {
+                if self.seen.insert(column.clone()) {
+                    keys.push(column);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct RowCells<'o> {
+    encoder: Encoder<'o>,
+    prefix: Option<String>,
+    cells: Option<Row>,
+}
+
+impl Visit for RowCells<'_> {
+    fn child<T: Serialize + ?Sized>(
+        &mut self,
+        key: Option<&str>,
+        node: Node<'_, T>,
+    ) -> Result<(), Error> {
+        let (Some(cells), Some(key)) = (&mut self.cells, key) else {
+            return Ok(());
+        };
+        let path = match &self.prefix {
+            Some(prefix) => format!("{prefix}.{key}"),
+            None => key.to_string(),
+        };
+        let options = self.encoder.options;
+        match self.encoder.shape(node)? {
+            _ if self.prefix.is_some() && !is_path_segment(key, options) => self.cells = None,
+            // Under key folding a literal dotted key would read as a path
+            _ if options.get_key_folding() && key.contains('.') => self.cells = None,
+            Shape::Leaf(value) => cells.push((path, key.to_string(), value)),
+            Shape::Object(len, _)
+                if options.get_key_folding() && len > 0 && is_path_segment(key, options) =>
+            {
+                let mut nested = RowCells {
+                    encoder: self.encoder,
+                    prefix: Some(path),
+                    cells: self.cells.take(),
+                };
+                walk(node, options, &mut nested)?;
+                self.cells = nested.cells;
+            }
+            _ => self.cells = None,
+        }
+        Ok(())
+    }
+}
+
+/// Writes table rows, or with no output, measures their column widths
+struct TableRows<'t, 'o, S> {
+    encoder: Encoder<'o>,
+    table: &'t Table,
+    widths: Vec<usize>,
+    output: Option<&'t mut S>,
+    indent_level: usize,
+}
+
+impl<S: Sink> Visit for TableRows<'_, '_, S> {
+    fn child<T: Serialize + ?Sized>(
+        &mut self,
+        _: Option<&str>,
+        node: Node<'_, T>,
+    ) -> Result<(), Error> {
+        let options = self.encoder.options;
+        let row = self
+            .encoder
+            .row(node)?
+            .ok_or_else(|| Error::Serialization("Expected object in tabular array".to_string()))?;
+        let cells = self.encoder.cells(&row, self.table)?;
+        let Some(output) = &mut self.output else {
+            for (width, cell) in self.widths.iter_mut().zip(&cells) {
+                *width = (*width).max(cell.chars().count());
+            }
+            return Ok(());
+        };
+
+        let indent = options.get_indent_string();
+        output.push_str(&indent.repeat(self.indent_level))?;
+        output.push_str(&indent)?;
+        let last = cells.len().saturating_sub(1);
+        for (i, cell) in cells.iter().enumerate() {
+            output.push_str(cell)?;
+            if i < last {
+                output.push(options.get_delimiter())?;
+                if let Some(width) = self.widths.get(i) {
+                    output.push_str(&" ".repeat(width - cell.chars().count()))?;
+                }
+            }
+        }
+        output.push('\n')
+    }
+}
+
+struct ListItems<'s, 'o, S> {
+    encoder: Encoder<'o>,
+    output: &'s mut S,
+    indent_level: usize,
+}
+
+impl<S: Sink> Visit for ListItems<'_, '_, S> {
+    fn child<T: Serialize + ?Sized>(
+        &mut self,
+        _: Option<&str>,
+        node: Node<'_, T>,
+    ) -> Result<(), Error> {
+        let indent = self.encoder.options.get_indent_string();
+        self.output.push_str(&indent.repeat(self.indent_level))?;
+        self.output.push_str(&indent)?;
+        self.output.push_str("- ")?;
+        match self.encoder.shape(node)? {
+            Shape::Object(0, _) if self.encoder.options.get_roundtrip_safe() => {
+                self.output.push_str("{}")?;
+            }
+            Shape::Object(..) => {
+                self.encoder
+                    .object(node, self.output, self.indent_level + 2, true)?;
+            }
+            _ => self
+                .encoder
+                .value(node, self.output, self.indent_level + 1)?,
+        }
+        self.output.push('\n')
+    }
+}
+
+/// Writes an object's entries, mirroring the encoder's `encode_object`
+struct Fields<'s, 'o, S> {
+    encoder: Encoder<'o>,
+    output: &'s mut S,
+    indent_level: usize,
+    list_item: bool,
+    first: bool,
+}
+
+impl<S: Sink> Visit for Fields<'_, '_, S> {
+    fn child<T: Serialize + ?Sized>(
+        &mut self,
+        key: Option<&str>,
+        node: Node<'_, T>,
+    ) -> Result<(), Error> {
+        let key = key.unwrap_or_default();
+        self.entry(key.to_string(), key, node)
+    }
+}
+
+impl<S: Sink> Fields<'_, '_, S> {
+    /// Write the entry at `path`, first folding a chain of single-key
+    /// objects below it into a dotted key under key folding
+    fn entry<T: Serialize + ?Sized>(
+        &mut self,
+        path: String,
+        key: &str,
+        node: Node<'_, T>,
+    ) -> Result<(), Error> {
+        let shape = self.encoder.shape(node)?;
+        let options = self.encoder.options;
+        if let Shape::Object(1, Some(next)) = &shape {
+            if options.get_key_folding()
+                && is_path_segment(key, options)
+                && is_path_segment(next, options)
+            {
+                return walk(node, options, &mut Fold { fields: self, path }).map(|_| ());
+            }
+        }
+        self.write_entry(&path, key, node, shape)
+    }
+
+    fn write_entry<T: Serialize + ?Sized>(
+        &mut self,
+        path: &str,
+        key: &str,
+        node: Node<'_, T>,
+        shape: Shape,
+    ) -> Result<(), Error> {
+        let encoder = self.encoder;
+        let options = encoder.options;
+        let indent_level = self.indent_level;
+        let leaf = match &shape {
+            Shape::Leaf(value) => Some(transform_leaf(value, Some(key), options)),
+            _ => None,
+        };
+        if leaf.as_deref().is_some_and(Value::is_null)
+            && options.get_null_policy() == NullPolicy::OmitKey
+        {
+            return Ok(());
+        }
+        let output = &mut *self.output;
+        let indent_str = options.get_indent_string().repeat(indent_level);
+        let first = std::mem::replace(&mut self.first, false);
+        if !first {
+            output.push('\n')?;
+        }
+        if !(self.list_item && first) {
+            output.push_str(&indent_str)?;
+        }
+        // A folded path is made of segments that need no quotes
+        let path = match path == key {
+            true => format_key(path, options),
+            false => Cow::Borrowed(path),
+        };
+        output.push_str(&path)?;
+
+        match (&shape, leaf) {
+            (Shape::Array(0), _) => output.push_str(&format!("{}:", length_header(0, options))),
+            (&Shape::Array(len), _) => {
+                if let Some(table) = encoder.table_layout(node, len, indent_level)? {
+                    output.push_str(&table_length_header(len, options))?;
+                    output.push('{')?;
+                    output.push_str(&typed_fields(&table.keys, &table.types, options))?;
+                    output.push_str("}:\n")?;
+                    return encoder.table_rows(node, &table, output, indent_level);
+                }
+                let inline = match encoder.leaves(node, len)? {
+                    Some(leaves) if len <= options.get_max_inline_items() => {
+                        inline_items(&leaves, Some(key), indent_str.len() + path.len(), options)?
+                    }
+                    _ => None,
+                };
+                output.push_str(&format!("{}:", length_header(len, options)))?;
+                match inline {
+                    Some(items) => output.push_str(&items),
+                    None => {
+                        output.push('\n')?;
+                        encoder.list(node, output, indent_level)
+                    }
+                }
+            }
+            (Shape::Object(0, _), _) => {
+                output.push_str(key_separator(options))?;
+                if options.get_roundtrip_safe() {
+                    output.push_str("{}")?;
+                }
+                Ok(())
+            }
+            (Shape::Object(..), _) => {
+                output.push_str(key_separator(options))?;
+                output.push('\n')?;
+                encoder.value(node, output, indent_level + 1)
+            }
+            (Shape::Leaf(_), leaf) => {
+                output.push_str(if self.list_item && first {
+                    ": "
+                } else {
+                    key_separator(options)
+                })?;
+                let value = leaf.unwrap_or_default();
+                match block_string(&value, indent_level + 1, options) {
+                    Some(block) => output.push_str(&block),
+                    None if value.is_null() => output.push_str(null_value(options)),
+                    None => encode_scalar(&value, output, options, Slot::Value),
+                }
+            }
+        }
+    }
+}
+
+/// Continues a folded key into the only entry of a nested object
+struct Fold<'f, 's, 'o, S> {
+    fields: &'f mut Fields<'s, 'o, S>,
+    path: String,
+}
+
+impl<S: Sink> Visit for Fold<'_, '_, '_, S> {
+    fn child<T: Serialize + ?Sized>(
+        &mut self,
+        key: Option<&str>,
+        node: Node<'_, T>,
+    ) -> Result<(), Error> {
+        let key = key.unwrap_or_default();
+        let path = format!("{}.{key}", self.path);
+        self.fields.entry(path, key, node)
+    }
+}
+
+/// Serializer reporting a node's shape and passing its children to a visitor
+struct Walker<'n, 'o, 'v, T: ?Sized, V> {
+    node: Node<'n, T>,
+    options: &'o EncodeOptions,
+    visit: &'v mut V,
+}
+
+impl<'o, 'v, T: Serialize + ?Sized, V: Visit> Walker<'_, 'o, 'v, T, V> {
+    fn leaf<L: Serialize + ?Sized>(self, value: &L) -> Result<Shape, Error> {
+        to_value(value, self.options).map(Shape::Leaf)
+    }
+
+    /// Walk a wrapped value in place of this one
+    fn inner<U: Serialize + ?Sized>(self, value: &U) -> Result<Shape, Error> {
+        value.serialize(Walker {
+            node: Node {
+                value,
+                body: self.node.body,
+            },
+            options: self.options,
+            visit: self.visit,
+        })
+    }
+
+    /// Start a container; a variant not walked as a body becomes a
+    /// one-entry object whose value is the body
+    fn compound(
+        self,
+        variant: Option<&'static str>,
+        array: bool,
+    ) -> Result<Compound<'o, 'v, V>, Error> {
+        let kind = match variant {
+            Some(variant) if !self.node.body => {
+                let body = Node {
+                    value: self.node.value,
+                    body: true,
+                };
+                self.visit.child(Some(variant), body)?;
+                Kind::Variant(variant)
+            }
+            _ if array => Kind::Array,
+            _ => Kind::Object,
+        };
+        Ok(Compound {
+            visit: self.visit,
+            options: self.options,
+            kind,
+            len: 0,
+            first_key: None,
+            next_key: None,
+        })
+    }
+}
+
+impl<'o, 'v, T: Serialize + ?Sized, V: Visit> ser::Serializer for Walker<'_, 'o, 'v, T, V> {
+    type Ok = Shape;
+    type Error = Error;
+
+    type SerializeSeq = Compound<'o, 'v, V>;
+    type SerializeTuple = Compound<'o, 'v, V>;
+    type SerializeTupleStruct = Compound<'o, 'v, V>;
+    type SerializeTupleVariant = Compound<'o, 'v, V>;
+    type SerializeMap = Compound<'o, 'v, V>;
+    type SerializeStruct = Compound<'o, 'v, V>;
+    type SerializeStructVariant = Compound<'o, 'v, V>;
+
+    fn serialize_bool(self, v: bool) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Shape, Error> {
+        self.leaf(&v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Shape, Error> {
+        self.leaf(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Shape, Error> {
+        for byte in v {
+            self.visit.child(None, Node::new(byte))?;
+        }
+        Ok(Shape::Array(v.len()))
+    }
+
+    fn serialize_none(self) -> Result<Shape, Error> {
+        Ok(Shape::Leaf(Value::Null))
+    }
+
+    fn serialize_some<U: Serialize + ?Sized>(self, value: &U) -> Result<Shape, Error> {
+        self.inner(value)
+    }
+
+    fn serialize_unit(self) -> Result<Shape, Error> {
+        Ok(Shape::Leaf(Value::Null))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Shape, Error> {
+        Ok(Shape::Leaf(Value::Null))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Shape, Error> {
+        Ok(Shape::Leaf(Value::String(variant.to_string())))
+    }
+
+    fn serialize_newtype_struct<U: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &U,
+    ) -> Result<Shape, Error> {
+        self.inner(value)
+    }
+
+    fn serialize_newtype_variant<U: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &U,
+    ) -> Result<Shape, Error> {
+        self.visit.child(Some(variant), Node::new(value))?;
+        Ok(Shape::Object(1, Some(variant.to_string())))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'o, 'v, V>, Error> {
+        self.compound(None, true)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'o, 'v, V>, Error> {
+        self.compound(None, true)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'o, 'v, V>, Error> {
+        self.compound(None, true)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'o, 'v, V>, Error> {
+        self.compound(Some(variant), true)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'o, 'v, V>, Error> {
+        self.compound(None, false)
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'o, 'v, V>, Error> {
+        let mut compound = self.compound(None, false)?;
+        if name == RAW_VALUE_TOKEN {
+            compound.kind = Kind::Raw(None);
+        }
+        Ok(compound)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'o, 'v, V>, Error> {
+        self.compound(Some(variant), false)
+    }
+}
+
+enum Kind {
+    Array,
+    Object,
+    /// A variant whose body was already passed on as its only entry
+    Variant(&'static str),
+    /// A `serde_json` `RawValue`, with the shape of its JSON once parsed
+    Raw(Option<Shape>),
+}
+
+struct Compound<'o, 'v, V> {
+    visit: &'v mut V,
+    options: &'o EncodeOptions,
+    kind: Kind,
+    len: usize,
+    first_key: Option<String>,
+    next_key: Option<String>,
+}
+
+impl<V: Visit> Compound<'_, '_, V> {
+    fn element<T: Serialize + ?Sized>(
+        &mut self,
+        key: Option<&str>,
+        value: &T,
+    ) -> Result<(), Error> {
+        match &mut self.kind {
+            Kind::Variant(_) => return Ok(()),
+            // The raw JSON is walked in place of the struct holding it
+            Kind::Raw(shape) => {
+                let value = raw_value(value, self.options)?;
+                *shape = Some(walk(Node::new(&value), self.options, self.visit)?);
+                return Ok(());
+            }
+            Kind::Array | Kind::Object => {}
+        }
+        if self.len == 0 {
+            self.first_key = key.map(str::to_string);
+        }
+        self.len += 1;
+        self.visit.child(key, Node::new(value))
+    }
+
+    fn finish(self) -> Result<Shape, Error> {
+        Ok(match self.kind {
+            Kind::Array => Shape::Array(self.len),
+            Kind::Object => Shape::Object(self.len, self.first_key),
+            Kind::Variant(variant) => Shape::Object(1, Some(variant.to_string())),
+            Kind::Raw(shape) => shape.unwrap_or(Shape::Leaf(Value::Null)),
+        })
+    }
+}
+
+impl<V: Visit> ser::SerializeSeq for Compound<'_, '_, V> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(None, value)
+    }
+
+    fn end(self) -> Result<Shape, Error> {
+        self.finish()
+    }
+}
+
+impl<V: Visit> ser::SerializeTuple for Compound<'_, '_, V> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(None, value)
+    }
+
+    fn end(self) -> Result<Shape, Error> {
+        self.finish()
+    }
+}
+
+impl<V: Visit> ser::SerializeTupleStruct for Compound<'_, '_, V> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(None, value)
+    }
+
+    fn end(self) -> Result<Shape, Error> {
+        self.finish()
+    }
+}
+
+impl<V: Visit> ser::SerializeTupleVariant for Compound<'_, '_, V> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(None, value)
+    }
+
+    fn end(self) -> Result<Shape, Error> {
+        self.finish()
+    }
+}
+
+impl<V: Visit> ser::SerializeMap for Compound<'_, '_, V> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.next_key = Some(key.serialize(MapKeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| Error::Serialization("map value without a key".to_string()))?;
+        self.element(Some(&key), value)
+    }
+
+    fn end(self) -> Result<Shape, Error> {
+        self.finish()
+    }
+}
+
+impl<V: Visit> ser::SerializeStruct for Compound<'_, '_, V> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.element(Some(key), value)
+    }
+
+    fn end(self) -> Result<Shape, Error> {
+        self.finish()
+    }
+}
+
+impl<V: Visit> ser::SerializeStructVariant for Compound<'_, '_, V> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.element(Some(key), value)
+    }
+
+    fn end(self) -> Result<Shape, Error> {
+        self.finish()
+    }
+}
@@ -1,14 +1,26 @@
 //! Serde-compatible API for TOON encoding and decoding
 
-use crate::decode::decode;
-use crate::encode::encode;
+use crate::decode::{decode, decode_iter, line_column};
+use crate::dom::{self, Document, Items, Node};
+use crate::encode::{encode, non_finite_literal};
 use crate::error::Error;
-use crate::options::{DecodeOptions, EncodeOptions};
-use serde::{de::DeserializeOwned, Serialize};
+use crate::options::{DecodeOptions, DuplicateKeyPolicy, EncodeOptions};
+use crate::ser::to_value;
+use crate::ser_stream;
+use serde::de::value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer};
+use serde::de::{DeserializeOwned, Deserializer, IntoDeserializer, Visitor};
+use serde::Serialize;
+use serde_json::Value;
+use serde_path_to_error::Segment;
 use std::io::{Read, Write};
 
+pub use crate::ser_stream::{RotatingWriter, TabularWriter};
+
 /// Serialize a value to a TOON-formatted string
 ///
+/// A `serde_json::value::RawValue` inside `value` is parsed and written as
+/// the JSON it holds, so fragments kept as raw JSON can be embedded as is.
+///
 /// # Arguments
 ///
 /// * `value` - The value to serialize (must implement `Serialize`)
@@ -33,9 +45,7 @@ use std::io::{Read, Write};
 /// let toon = to_string(&product).unwrap();
 /// ```
 pub fn to_string<T: Serialize>(value: &T) -> Result<String, Error> {
-    let json_value =
-        serde_json::to_value(value).map_err(|e| Error::Serialization(e.to_string()))?;
-    encode(&json_value, None)
+    to_string_with_options(value, &EncodeOptions::default())
 }
 
 /// Serialize a value to a TOON-formatted string with options
@@ -52,8 +62,7 @@ pub fn to_string_with_options<T: Serialize>(
     value: &T,
     options: &EncodeOptions,
 ) -> Result<String, Error> {
-    let json_value =
-        serde_json::to_value(value).map_err(|e| Error::Serialization(e.to_string()))?;
+    let json_value = to_value(value, options)?;
     encode(&json_value, Some(options))
 }
 
@@ -98,6 +107,70 @@ pub fn to_writer_with_options<T: Serialize, W: Write>(
     Ok(())
 }
 
+/// Serialize a value straight into a writer in TOON format
+///
+/// Where [`to_writer`] converts the value to a `serde_json::Value` and then
+/// renders the whole document as a `String`, this drives the value's
+/// `Serialize` impl directly into `writer`. Each array is walked twice, once
+/// to find its length and layout and once to write it, so memory use stays
+/// at about one table row regardless of the data size. The layout matches
+/// [`to_writer`]'s, but fields are written in the order they serialize in
+/// rather than sorted by key.
+///
+/// # Arguments
+///
+/// * `value` - The value to serialize (must implement `Serialize`)
+/// * `writer` - The writer to write to
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure
+///
+/// # Example
+///
+/// ```rust
+/// use serde::Serialize;
+/// use toon_rust::to_writer_streaming;
+///
+/// #[derive(Serialize)]
+/// struct Product {
+///     sku: String,
+///     qty: u32,
+/// }
+///
+/// let products = vec![
+///     Product { sku: "A1".to_string(), qty: 2 },
+///     Product { sku: "B2".to_string(), qty: 1 },
+/// ];
+/// let mut out = Vec::new();
+/// to_writer_streaming(&products, &mut out).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "[2]{sku,qty}:\n  A1,2\n  B2,1\n");
+/// ```
+pub fn to_writer_streaming<T: Serialize, W: Write>(value: &T, writer: &mut W) -> Result<(), Error> {
+    to_writer_streaming_with_options(value, writer, &EncodeOptions::default())
+}
+
+/// Serialize a value straight into a writer in TOON format with options
+///
+/// See [`to_writer_streaming`].
+///
+/// # Arguments
+///
+/// * `value` - The value to serialize (must implement `Serialize`)
+/// * `writer` - The writer to write to
+/// * `options` - Encoding options
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure
+pub fn to_writer_streaming_with_options<T: Serialize, W: Write>(
+    value: &T,
+    writer: &mut W,
+    options: &EncodeOptions,
+) -> Result<(), Error> {
+    ser_stream::to_writer(value, writer, options)
+}
+
 /// Deserialize a TOON-formatted string to a value
 ///
 /// # Arguments
@@ -142,7 +215,256 @@ pub fn from_str_with_options<T: DeserializeOwned>(
     options: Option<&DecodeOptions>,
 ) -> Result<T, Error> {
     let json_value = decode(s, options)?;
-    serde_json::from_value(json_value).map_err(|e| Error::Deserialization(e.to_string()))
+    deserialize(json_value, s, options, None)
+}
+
+/// Lazily deserialize the items of a root array
+///
+/// This is [`decode_iter`] with each item converted to `T`, so a large
+/// array of records can be read one record at a time. Deserialization
+/// errors carry the item's index in their path and point at its line.
+///
+/// # Example
+///
+/// ```rust
+/// use serde::Deserialize;
+/// use toon_rust::from_str_iter;
+///
+/// #[derive(Deserialize)]
+/// struct Product {
+///     sku: String,
+///     qty: u32,
+/// }
+///
+/// let toon = "[2]{sku,qty}:\n  A1,2\n  B2,1";
+/// let mut total = 0;
+/// for product in from_str_iter::<Product>(toon, None) {
+///     total += product.unwrap().qty;
+/// }
+/// assert_eq!(total, 3);
+/// ```
+pub fn from_str_iter<'a, T: DeserializeOwned>(
+    s: &'a str,
+    options: Option<&'a DecodeOptions>,
+) -> impl Iterator<Item = Result<T, Error>> + 'a {
+    decode_iter(s, options)
+        .enumerate()
+        .map(move |(index, item)| item.and_then(|item| deserialize(item, s, options, Some(index))))
+}
+
+/// Convert a decoded value to `T`, locating any error in `input`; `index`
+/// is set when the value is an item of the root array
+fn deserialize<T: DeserializeOwned>(
+    value: Value,
+    input: &str,
+    options: Option<&DecodeOptions>,
+    index: Option<usize>,
+) -> Result<T, Error> {
+    let result = if contains_non_finite(&value) {
+        serde_path_to_error::deserialize(NonFinite(value))
+    } else {
+        serde_path_to_error::deserialize(value)
+    };
+    result.map_err(|e| {
+        let segments: Vec<PathSegment> = index
+            .map(PathSegment::Index)
+            .into_iter()
+            .chain(e.path().iter().filter_map(|segment| match segment {
+                Segment::Seq { index } => Some(PathSegment::Index(*index)),
+                Segment::Map { key } => Some(PathSegment::Key(key)),
+                Segment::Enum { .. } | Segment::Unknown => None,
+            }))
+            .collect();
+        let path = match (index, e.path().to_string()) {
+            (Some(index), path) if path == "." => format!("[{index}]"),
+            (Some(index), path) if path.starts_with('[') => format!("[{index}]{path}"),
+            (Some(index), path) => format!("[{index}].{path}"),
+            (None, path) => path,
+        };
+        Error::Deserialization {
+            message: e.inner().to_string(),
+            path: (!segments.is_empty()).then_some(path),
+            line: locate_path(input, options, &segments),
+        }
+    })
+}
+
+/// Whether `value` holds a `nan`, `inf` or `-inf` literal accepted by
+/// [`DecodeOptions::allow_nan`]
+fn contains_non_finite(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => items.iter().any(contains_non_finite),
+        Value::Object(map) => {
+            non_finite_literal(value).is_some() || map.values().any(contains_non_finite)
+        }
+        _ => false,
+    }
+}
+
+/// Deserializer over a decoded value that reads the markers left by
+/// [`DecodeOptions::allow_nan`] as floats, so `from_str::<f64>("nan")` reads
+/// back what [`NanPolicy::Token`](crate::options::NanPolicy::Token) writes
+struct NonFinite(Value);
+
+impl<'de> IntoDeserializer<'de, serde_json::Error> for NonFinite {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for NonFinite {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if let Some(literal) = non_finite_literal(&self.0) {
+            return visitor.visit_f64(literal.parse().unwrap_or(f64::NAN));
+        }
+        match self.0 {
+            Value::Array(items) => {
+                let mut seq = SeqDeserializer::new(items.into_iter().map(NonFinite));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Object(map) => {
+                let mut entries = MapDeserializer::new(
+                    map.into_iter().map(|(key, value)| (key, NonFinite(value))),
+                );
+                let value = visitor.visit_map(&mut entries)?;
+                entries.end()?;
+                Ok(value)
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Object(map) if map.len() == 1 => {
+                let entries = MapDeserializer::new(
+                    map.into_iter().map(|(key, value)| (key, NonFinite(value))),
+                );
+                visitor.visit_enum(MapAccessDeserializer::new(entries))
+            }
+            value => value.deserialize_enum(name, variants, visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+enum PathSegment<'p> {
+    Key(&'p str),
+    Index(usize),
+}
+
+/// Find the 1-based source line of a deserialization path
+///
+/// The document is parsed again into a [`dom::Document`] with the same
+/// options, so indentation, quoted keys and dotted keys expanded by
+/// [`DecodeOptions::expand_paths`] are read as the decoder read them. The
+/// walk stops at the deepest segment it can resolve, so the result always
+/// points at or above the offending value.
+fn locate_path(
+    input: &str,
+    options: Option<&DecodeOptions>,
+    path: &[PathSegment],
+) -> Option<usize> {
+    let doc = dom::parse(input, options).ok()?;
+    let mut node = doc.root();
+    let mut rest = path;
+    let mut found = None;
+    while let Some(segment) = rest.first() {
+        let next = match (node, segment) {
+            (Node::Object(object), PathSegment::Key(_)) => {
+                // The entry whose key covers the most of the path, or that
+                // the path ends inside of
+                let matches = object.entries.iter().filter_map(|entry| {
+                    let keys = entry_keys(&doc, entry);
+                    let len = keys
+                        .iter()
+                        .zip(rest)
+                        .take_while(|(key, segment)| {
+                            matches!(segment, PathSegment::Key(name) if name == *key)
+                        })
+                        .count();
+                    (len == keys.len() || len == rest.len()).then_some((entry, len))
+                });
+                let best = match doc.options().get_duplicate_keys() {
+                    DuplicateKeyPolicy::LastWins => matches.max_by_key(|&(_, len)| len),
+                    _ => matches.rev().max_by_key(|&(_, len)| len),
+                };
+                let Some((entry, len)) = best else { break };
+                found = Some(entry.span.start);
+                rest = &rest[len..];
+                if len < entry_keys(&doc, entry).len() {
+                    break;
+                }
+                Some(&entry.value)
+            }
+            (Node::Array(array), PathSegment::Index(index)) => match &array.items {
+                Items::Inline(items) => {
+                    found = items.get(*index).map(|item| item.span.start).or(found);
+                    break;
+                }
+                Items::Table(rows) => {
+                    let mut rows = rows
+                        .iter()
+                        .filter(|row| doc.row_value(&array.header, row).is_some());
+                    found = rows.nth(*index).map(|row| row.span.start).or(found);
+                    break;
+                }
+                Items::List(items) => {
+                    let Some(item) = items.get(*index) else { break };
+                    found = Some(item.span.start);
+                    rest = &rest[1..];
+                    Some(&item.value)
+                }
+            },
+            _ => None,
+        };
+        let Some(next) = next else { break };
+        node = next;
+    }
+    found.map(|pos| line_column(input, pos).0)
+}
+
+/// The keys an entry's key stands for: its segments when it is a dotted
+/// path that [`DecodeOptions::expand_paths`] expands, otherwise the key
+fn entry_keys<'d>(doc: &'d Document, entry: &'d dom::Entry) -> Vec<&'d str> {
+    let name = entry.key.name.as_str();
+    let quoted = doc.source()[entry.key.span.clone()].starts_with('"');
+    if doc.options().get_expand_paths() && !quoted {
+        name.split('.').collect()
+    } else {
+        vec![name]
+    }
 }
 
 /// Deserialize a TOON-formatted reader to a value
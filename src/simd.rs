@@ -241,19 +241,20 @@ pub fn split_row_fallback(row: &str, delimiter: char) -> Vec<&str> {
     let mut result = Vec::new();
     let mut start = 0;
     let mut in_quotes = false;
-    let chars: Vec<char> = row.chars().collect();
+    let mut prev = None;
 
-    for (i, ch) in chars.iter().enumerate() {
+    for (i, ch) in row.char_indices() {
         match ch {
-            '"' if i == 0 || chars[i - 1] != '\\' => {
+            '"' if prev != Some('\\') => {
                 in_quotes = !in_quotes;
             }
-            _ if *ch == delimiter && !in_quotes => {
+            _ if ch == delimiter && !in_quotes => {
                 result.push(&row[start..i]);
-                start = i + 1;
+                start = i + ch.len_utf8();
             }
             _ => {}
         }
+        prev = Some(ch);
     }
     result.push(&row[start..]);
     result
@@ -315,4 +316,11 @@ mod tests {
         let result = split_row_simd(row, '\t');
         assert_eq!(result, vec!["a", "b", "c"]);
     }
+
+    #[test]
+    fn test_split_row_fallback_multibyte() {
+        let row = "é,ü|x,ñ";
+        let result = split_row_fallback(row, ',');
+        assert_eq!(result, vec!["é", "ü|x", "ñ"]);
+    }
 }
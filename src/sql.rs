@@ -0,0 +1,93 @@
+//! Writing SQL query results as a TOON table
+//!
+//! Requires the `rusqlite` feature. [`encode_query`] runs a prepared
+//! SQLite statement and writes each result row as a table row, so a query
+//! goes into a prompt without collecting its rows first.
+
+use crate::bytes::bytes_value;
+use crate::error::Error;
+use crate::options::{BytesPolicy, EncodeOptions};
+use crate::transcode::TableSink;
+use rusqlite::types::ValueRef;
+use rusqlite::{Params, Statement};
+use serde_json::{Number, Value};
+use std::io::Write;
+
+/// Run `statement` with `params` and write its rows as a tabular root array
+///
+/// The columns are the statement's result columns, which must have
+/// distinct names; alias them with `AS` otherwise. Integers, reals and
+/// text are written as such, NULL as `null`, and blobs as base64 strings.
+/// As with [`crate::transcode`], rows are held until the count is known,
+/// and written as they come once lengths are turned off through
+/// [`EncodeOptions::emit_lengths`].
+///
+/// # Example
+///
+/// ```rust
+/// use rusqlite::Connection;
+/// use toon_rust::encode_query;
+///
+/// let db = Connection::open_in_memory()?;
+/// db.execute_batch(
+///     "CREATE TABLE users (id INTEGER, name TEXT);
+///      INSERT INTO users VALUES (1, 'Ada'), (2, 'Bob'), (3, NULL);",
+/// )?;
+/// let mut statement = db.prepare("SELECT id, name FROM users WHERE id < ?1")?;
+/// let mut output = Vec::new();
+/// encode_query(&mut statement, [3], &mut output, None)?;
+/// assert_eq!(String::from_utf8(output)?, "[2]{id,name}:\n  1,Ada\n  2,Bob\n");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn encode_query<P, W>(
+    statement: &mut Statement<'_>,
+    params: P,
+    writer: &mut W,
+    options: Option<&EncodeOptions>,
+) -> Result<(), Error>
+where
+    P: Params,
+    W: Write,
+{
+    let default_opts = EncodeOptions::default();
+    let opts = options.unwrap_or(&default_opts);
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    for (i, column) in columns.iter().enumerate() {
+        if columns[..i].contains(column) {
+            return Err(Error::Serialization(format!(
+                "Column `{column}` appears more than once in the query; alias it with AS"
+            )));
+        }
+    }
+
+    let mut table = TableSink::new(writer, opts).with_columns(columns.clone());
+    let mut rows = statement.query(params).map_err(failed)?;
+    let mut cells = Vec::with_capacity(columns.len());
+    while let Some(row) = rows.next().map_err(failed)? {
+        cells.clear();
+        for i in 0..columns.len() {
+            cells.push(cell(row.get_ref(i).map_err(failed)?));
+        }
+        table.write_cells(&cells)?;
+    }
+    table.finish()
+}
+
+/// The value written for an SQLite cell
+fn cell(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => Value::from(i),
+        ValueRef::Real(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(blob) => bytes_value(blob, BytesPolicy::Base64),
+    }
+}
+
+fn failed(err: rusqlite::Error) -> Error {
+    Error::Serialization(format!("Query failed: {err}"))
+}
@@ -0,0 +1,262 @@
+//! Classified tokens of TOON text, for syntax highlighting
+//!
+//! [`tokenize`] splits a document into [`Token`]s with the byte range each
+//! covers. It reads a line at a time and never fails: text it can't make
+//! sense of comes out as a scalar, so an editor can highlight a document
+//! while it is being typed. Whitespace and line breaks have no tokens; they
+//! are the gaps between spans.
+
+use crate::decode::{block_header, inline_delimiter, quoted_len, table_delimiter, until_unquoted};
+use crate::simd;
+use std::ops::Range;
+
+/// What a [`Token`] is
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// An object key, quotes included
+    Key,
+    /// An array's `[N]` bracket, with its length marker and delimiter
+    Length,
+    /// A field in a table header's `{…}`, with any type, default or
+    /// dictionary written after its name
+    Field,
+    /// An unquoted value: a number, `true`, `false`, `null` or a bare string
+    Scalar,
+    /// A quoted string, or a line of a block string's body
+    String,
+    /// `:`, `-`, `{`, `}`, a `|` block marker or a delimiter
+    Punctuation,
+}
+
+/// A classified piece of TOON text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Byte range of the token in the input
+    pub span: Range<usize>,
+}
+
+/// Split a TOON document into classified tokens, in the order they appear
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::syntax::{tokenize, TokenKind};
+///
+/// let toon = "users[1]{id,name}:\n  1,\"Ada\"";
+/// let kinds: Vec<_> = tokenize(toon)
+///     .into_iter()
+///     .map(|token| (token.kind, &toon[token.span]))
+///     .collect();
+/// assert_eq!(
+///     kinds,
+///     [
+///         (TokenKind::Key, "users"),
+///         (TokenKind::Length, "[1]"),
+///         (TokenKind::Punctuation, "{"),
+///         (TokenKind::Field, "id"),
+///         (TokenKind::Punctuation, ","),
+///         (TokenKind::Field, "name"),
+///         (TokenKind::Punctuation, "}"),
+///         (TokenKind::Punctuation, ":"),
+///         (TokenKind::Scalar, "1"),
+///         (TokenKind::Punctuation, ","),
+///         (TokenKind::String, "\"Ada\""),
+///     ]
+/// );
+/// ```
+pub fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokenizer = Tokenizer {
+        source: input,
+        tokens: Vec::new(),
+    };
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in input.split('\n') {
+        let end = start + line.strip_suffix('\r').unwrap_or(line).len();
+        lines.push(start..end);
+        start += line.len() + 1;
+    }
+
+    // What the lines indented past `column` belong to, if not entries
+    let mut body: Option<(usize, Body)> = None;
+    for (i, line) in lines.iter().enumerate() {
+        let text = input[line.clone()].trim_end();
+        let content = text.trim_start_matches([' ', '\t']);
+        if content.is_empty() {
+            continue;
+        }
+        let width = text.len() - content.len();
+        let start = line.start + width;
+        let end = start + content.len();
+        match body {
+            Some((column, Body::Block)) if width > column => {
+                tokenizer.push(TokenKind::String, start..end);
+                continue;
+            }
+            Some((column, Body::Rows(delimiter))) if width > column => {
+                tokenizer.cells(start..end, delimiter);
+                continue;
+            }
+            _ => {}
+        }
+        let next = lines.get(i + 1).map(|next| input[next.clone()].trim());
+        body = tokenizer
+            .line(start, end, next)
+            .map(|(key, opened)| (key - line.start, opened));
+    }
+    tokenizer.tokens
+}
+
+/// Lines a header or key opens, read differently from entries
+#[derive(Debug, Clone, Copy)]
+enum Body {
+    /// A block string's lines
+    Block,
+    /// A table's rows, split at the delimiter
+    Rows(char),
+}
+
+struct Tokenizer<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+}
+
+impl Tokenizer<'_> {
+    /// Tokens of the line content from `start` to `end`, returning where its
+    /// key starts and what the lines below it hold when it opens a block
+    /// string or a table
+    fn line(&mut self, start: usize, end: usize, next: Option<&str>) -> Option<(usize, Body)> {
+        let text = &self.source[start..end];
+        if text == "-" || text.starts_with("- ") {
+            self.push(TokenKind::Punctuation, start..start + 1);
+            let item = start + 1 + (text[1..].len() - text[1..].trim_start().len());
+            return (item < end).then(|| self.line(item, end, next)).flatten();
+        }
+        let checkpoint = self.tokens.len();
+        match self.entry(start, end, next) {
+            Some(opened) => opened.map(|body| (start, body)),
+            None => {
+                self.tokens.truncate(checkpoint);
+                self.value(start..end);
+                None
+            }
+        }
+    }
+
+    /// Tokens of `key: value` or an array header, or `None` when the text
+    /// isn't an entry
+    fn entry(&mut self, start: usize, end: usize, next: Option<&str>) -> Option<Option<Body>> {
+        let text = &self.source[start..end];
+        let key_len = if text.starts_with('"') {
+            quoted_len(text)?
+        } else {
+            text.find([':', '[', '{'])?
+        };
+        let key = text[..key_len].trim_end();
+        if !key.is_empty() {
+            self.push(TokenKind::Key, start..start + key.len());
+        }
+
+        let mut pos = key_len;
+        let mut length = None;
+        if text[pos..].starts_with('[') {
+            let close = pos + text[pos..].find(']')?;
+            length = text[pos + 1..close]
+                .trim_start_matches('#')
+                .trim_end_matches(['|', '\t'])
+                .parse()
+                .ok();
+            self.push(TokenKind::Length, start + pos..start + close + 1);
+            pos = close + 1;
+        }
+        let mut fields = None;
+        if text[pos..].starts_with('{') {
+            let mut scan = until_unquoted('}');
+            let close = pos + text[pos..].find(|ch| !scan(ch)).unwrap_or(0);
+            if close == pos || !text[close..].starts_with('}') {
+                return None;
+            }
+            let inner = &text[pos + 1..close];
+            let delimiter = table_delimiter(inner, next);
+            self.push(TokenKind::Punctuation, start + pos..start + pos + 1);
+            self.split(start + pos + 1..start + close, delimiter, |_| {
+                TokenKind::Field
+            });
+            self.push(TokenKind::Punctuation, start + close..start + close + 1);
+            fields = Some(delimiter);
+            pos = close + 1;
+        }
+        let header = pos > key_len;
+
+        let colon = pos + (text[pos..].len() - text[pos..].trim_start().len());
+        if !text[colon..].starts_with(':') {
+            return None;
+        }
+        self.push(TokenKind::Punctuation, start + colon..start + colon + 1);
+        let rest = &text[colon + 1..];
+        let value = start + colon + 1 + (rest.len() - rest.trim_start().len());
+        let rest = rest.trim();
+        if rest.is_empty() {
+            return Some(fields.map(Body::Rows));
+        }
+        if header {
+            self.cells(
+                value..end,
+                fields.unwrap_or_else(|| inline_delimiter(rest, length)),
+            );
+            return Some(None);
+        }
+        if block_header(rest).is_some() {
+            self.push(TokenKind::Punctuation, value..end);
+            return Some(Some(Body::Block));
+        }
+        self.value(value..end);
+        Some(None)
+    }
+
+    /// Tokens of a row of delimited values
+    fn cells(&mut self, span: Range<usize>, delimiter: char) {
+        self.split(span, delimiter, value_kind);
+    }
+
+    /// Tokens of the pieces of `span` between `delimiter`s outside quotes,
+    /// and of the delimiters themselves
+    fn split(&mut self, span: Range<usize>, delimiter: char, kind: fn(&str) -> TokenKind) {
+        let text = &self.source[span.clone()];
+        let pieces = simd::split_row_fallback(text, delimiter);
+        let count = pieces.len();
+        for (i, piece) in pieces.into_iter().enumerate() {
+            let offset = span.start + (piece.as_ptr() as usize - text.as_ptr() as usize);
+            let trimmed = piece.trim_start();
+            let start = offset + (piece.len() - trimmed.len());
+            let trimmed = trimmed.trim_end();
+            if !trimmed.is_empty() {
+                self.push(kind(trimmed), start..start + trimmed.len());
+            }
+            if i + 1 < count {
+                let at = offset + piece.len();
+                self.push(TokenKind::Punctuation, at..at + delimiter.len_utf8());
+            }
+        }
+    }
+
+    /// A lone value
+    fn value(&mut self, span: Range<usize>) {
+        let kind = value_kind(&self.source[span.clone()]);
+        self.push(kind, span);
+    }
+
+    fn push(&mut self, kind: TokenKind, span: Range<usize>) {
+        self.tokens.push(Token { kind, span });
+    }
+}
+
+fn value_kind(text: &str) -> TokenKind {
+    if text.starts_with('"') {
+        TokenKind::String
+    } else {
+        TokenKind::Scalar
+    }
+}
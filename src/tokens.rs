@@ -0,0 +1,145 @@
+//! Estimating how many tokens a text costs a language model
+//!
+//! [`estimate_tokens`] splits text the way BPE tokenizers pre-split it
+//! (words with their leading space, runs of up to three digits, runs of
+//! punctuation and of whitespace) and charges each piece the tokens such a
+//! piece usually takes in the model's vocabulary. The estimate needs no
+//! vocabulary files and is typically within about 10% of the real count for
+//! TOON and JSON, which is enough to compare formats and check budgets.
+//!
+//! With the `tiktoken` feature, counts for the OpenAI models are exact,
+//! using the `cl100k_base` and `o200k_base` encodings from `tiktoken-rs`.
+
+/// A model family whose tokenizer to estimate for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Model {
+    /// GPT-4 and GPT-3.5 (`cl100k_base`)
+    #[default]
+    Gpt4,
+    /// GPT-4o and the o-series (`o200k_base`)
+    Gpt4o,
+    /// Claude
+    Claude,
+    /// Llama 3
+    Llama3,
+}
+
+/// What the pieces of text cost under a model's tokenizer, fitted to the
+/// real counts of TOON, JSON and prose samples
+struct Profile {
+    /// ASCII letters per token in a word, past the first token
+    word_chars: f64,
+    /// Characters per token in a run of punctuation
+    punctuation_chars: f64,
+    /// UTF-8 bytes per token in a run of non-ASCII letters
+    non_ascii_bytes: f64,
+}
+
+impl Model {
+    fn profile(self) -> Profile {
+        match self {
+            Model::Gpt4 => Profile {
+                word_chars: 8.0,
+                punctuation_chars: 4.0,
+                non_ascii_bytes: 2.5,
+            },
+            Model::Gpt4o => Profile {
+                word_chars: 8.0,
+                punctuation_chars: 4.0,
+                non_ascii_bytes: 4.0,
+            },
+            // Claude's tokenizer splits words and symbols sooner
+            Model::Claude => Profile {
+                word_chars: 6.0,
+                punctuation_chars: 3.0,
+                non_ascii_bytes: 2.5,
+            },
+            Model::Llama3 => Profile {
+                word_chars: 8.0,
+                punctuation_chars: 4.0,
+                non_ascii_bytes: 3.0,
+            },
+        }
+    }
+}
+
+/// Estimate the number of tokens `text` takes for `model`
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::{encode, estimate_tokens, tokens::Model};
+///
+/// let data = json!({"items": [
+///     {"sku": "A1", "qty": 2, "price": 9.99},
+///     {"sku": "B2", "qty": 1, "price": 14.5}
+/// ]});
+/// let toon = estimate_tokens(&encode(&data, None)?, Model::Gpt4o);
+/// let json = estimate_tokens(&data.to_string(), Model::Gpt4o);
+/// assert!(toon < json);
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn estimate_tokens(text: &str, model: Model) -> usize {
+    #[cfg(feature = "tiktoken")]
+    match model {
+        Model::Gpt4 => {
+            return tiktoken_rs::cl100k_base_singleton()
+                .encode_ordinary(text)
+                .len()
+        }
+        Model::Gpt4o => {
+            return tiktoken_rs::o200k_base_singleton()
+                .encode_ordinary(text)
+                .len()
+        }
+        _ => {}
+    }
+    approximate(text, model.profile())
+}
+
+/// The kinds of piece a BPE pre-tokenizer splits text into
+#[derive(Clone, Copy, PartialEq)]
+enum Piece {
+    Letters,
+    Digits,
+    Punctuation,
+    Space,
+}
+
+fn piece(ch: char) -> Piece {
+    match ch {
+        _ if ch.is_alphabetic() => Piece::Letters,
+        _ if ch.is_numeric() => Piece::Digits,
+        _ if ch.is_whitespace() => Piece::Space,
+        _ => Piece::Punctuation,
+    }
+}
+
+fn approximate(text: &str, profile: Profile) -> usize {
+    let mut tokens = 0.0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, ch)) = chars.next() {
+        let kind = piece(ch);
+        let mut end = start + ch.len_utf8();
+        while let Some((i, next)) = chars.next_if(|&(_, next)| piece(next) == kind) {
+            end = i + next.len_utf8();
+        }
+        let run = &text[start..end];
+        let len = run.chars().count() as f64;
+        let joins_next = chars
+            .peek()
+            .is_some_and(|&(_, next)| matches!(piece(next), Piece::Letters | Piece::Punctuation));
+        tokens += match kind {
+            // A lone space goes with the word or punctuation after it
+            Piece::Space if run == " " && joins_next => 0.0,
+            Piece::Space => 1.0,
+            Piece::Letters if run.is_ascii() => 1.0 + ((len - 1.0) / profile.word_chars).floor(),
+            Piece::Letters => (run.len() as f64 / profile.non_ascii_bytes).ceil(),
+            Piece::Digits => (len / 3.0).ceil(),
+            Piece::Punctuation => (len / profile.punctuation_chars).ceil(),
+        };
+    }
+    tokens as usize
+}
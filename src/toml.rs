@@ -0,0 +1,134 @@
+//! Conversion between TOON and TOML
+//!
+//! Requires the `toml` feature. A TOML document is a table, so it reads
+//! into a JSON object and encodes like any other: an array of tables
+//! (`[[items]]`) whose tables share their keys becomes a TOON table. The
+//! way back writes each array of objects as an array of tables.
+//!
+//! The two data models differ at the edges. TOML dates and times become
+//! strings in TOON, and TOML has no null, so null object fields are left
+//! out of TOML.
+
+use crate::decode::{decode, line_column};
+use crate::encode::encode;
+use crate::error::Error;
+use crate::options::{DecodeOptions, EncodeOptions};
+use serde_json::{Map, Number, Value};
+
+/// Convert a TOML document to TOON
+///
+/// Syntax errors are [`Error::Parse`] errors at their place in the TOML.
+/// Floats that aren't finite become null, as in JSON.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::toml_to_toon;
+///
+/// let toml = "name = \"shop\"\n\n[[items]]\nsku = \"A1\"\nqty = 2\n\n[[items]]\nsku = \"B2\"\nqty = 1\n";
+/// let toon = toml_to_toon(toml, None)?;
+/// assert_eq!(toon, "items[2]{qty,sku}:\n  2,A1\n  1,B2\n\nname: shop");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn toml_to_toon(input: &str, options: Option<&EncodeOptions>) -> Result<String, Error> {
+    let table: ::toml::Table = ::toml::from_str(input).map_err(|err| {
+        let message = err.message().to_string();
+        match err.span() {
+            Some(span) => {
+                let (line, column) = line_column(input, span.start);
+                Error::parse(span.start, line, column, message)
+            }
+            None => Error::Serialization(message),
+        }
+    })?;
+    encode(&from_toml(::toml::Value::Table(table)), options)
+}
+
+/// Convert a TOON document to TOML
+///
+/// The document must be an object. Null fields are left out, and null in
+/// an array is an error, as TOML has no way to write it.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::toon_to_toml;
+///
+/// let toml = toon_to_toml("name: shop\nitems[2]{sku,qty}:\n  A1,2\n  B2,1", None)?;
+/// assert_eq!(
+///     toml,
+///     "name = \"shop\"\n\n[[items]]\nqty = 2\nsku = \"A1\"\n\n[[items]]\nqty = 1\nsku = \"B2\"\n"
+/// );
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn toon_to_toml(input: &str, options: Option<&DecodeOptions>) -> Result<String, Error> {
+    let Value::Object(map) = decode(input, options)? else {
+        return Err(Error::Serialization(
+            "A TOML document must be an object".to_string(),
+        ));
+    };
+    let table = to_table(map, "")?;
+    ::toml::to_string(&table).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+fn from_toml(value: ::toml::Value) -> Value {
+    match value {
+        ::toml::Value::String(s) => Value::String(s),
+        ::toml::Value::Integer(n) => Value::from(n),
+        ::toml::Value::Float(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        ::toml::Value::Boolean(b) => Value::Bool(b),
+        ::toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        ::toml::Value::Array(items) => Value::Array(items.into_iter().map(from_toml).collect()),
+        ::toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, from_toml(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn to_table(map: Map<String, Value>, path: &str) -> Result<::toml::Table, Error> {
+    let mut table = ::toml::Table::new();
+    for (key, value) in map {
+        let path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{path}.{key}")
+        };
+        if !value.is_null() {
+            table.insert(key, to_toml(value, &path)?);
+        }
+    }
+    Ok(table)
+}
+
+/// The TOML for a value other than null, with `path` naming it in errors
+fn to_toml(value: Value, path: &str) -> Result<::toml::Value, Error> {
+    Ok(match value {
+        Value::Null => {
+            return Err(Error::Serialization(format!(
+                "TOML has no null, found at `{path}`"
+            )))
+        }
+        Value::Bool(b) => ::toml::Value::Boolean(b),
+        Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => ::toml::Value::Integer(i),
+            (None, Some(f)) => ::toml::Value::Float(f),
+            (None, None) => {
+                return Err(Error::Serialization(format!(
+                    "{n} at `{path}` is not a TOML number"
+                )))
+            }
+        },
+        Value::String(s) => ::toml::Value::String(s),
+        Value::Array(items) => ::toml::Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| to_toml(item, &format!("{path}[{i}]")))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(map) => ::toml::Value::Table(to_table(map, path)?),
+    })
+}
@@ -0,0 +1,241 @@
+//! Streaming conversion with a per-item transform
+//!
+//! [`transcode`] reads the items of a TOON root array one at a time, hands
+//! each to a closure that may rewrite or drop it, and writes the survivors
+//! as a TOON table, so filter, map and redact steps run in a single pass.
+//! The NDJSON converters take the same kind of closure through
+//! [`ndjson_to_toon_with`](crate::ndjson::ndjson_to_toon_with) and
+//! [`toon_to_ndjson_with`](crate::ndjson::toon_to_ndjson_with).
+
+use crate::decode::decode_iter;
+use crate::encode::{
+    check_indent, column_paths, encode_table_row, format_fields, format_key, length_header,
+    path_fields, table_length_header, Sink, TableStyle, WriteSink,
+};
+use crate::error::Error;
+use crate::options::{DecodeOptions, EncodeOptions};
+use serde_json::Value;
+use std::io::{Read, Write};
+
+/// Transcode a TOON root array, passing each item through `transform`
+///
+/// Items are decoded one at a time with [`decode_iter`]; returning `None`
+/// from `transform` drops the item, and the values it returns are written
+/// as the rows of a tabular root array. As with
+/// [`ndjson_to_toon`](crate::ndjson_to_toon), the columns are the first
+/// written row's fields, and rows are written as they come once lengths are
+/// turned off through [`EncodeOptions::emit_lengths`].
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::transcode;
+///
+/// let toon = "[3]{id,email,active}:\n  1,a@x.io,true\n  2,b@x.io,false\n  3,c@x.io,true";
+/// let mut out = Vec::new();
+/// transcode(toon.as_bytes(), &mut out, None, None, |mut user| {
+///     if user["active"] != true {
+///         return None;
+///     }
+///     user["email"] = "<redacted>".into();
+///     Some(user)
+/// })?;
+/// assert_eq!(out, b"[2]{active,email,id}:\n  true,<redacted>,1\n  true,<redacted>,3\n");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn transcode<R, W, F>(
+    mut reader: R,
+    writer: &mut W,
+    decode_options: Option<&DecodeOptions>,
+    encode_options: Option<&EncodeOptions>,
+    mut transform: F,
+) -> Result<(), Error>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(Value) -> Option<Value>,
+{
+    let default_opts = EncodeOptions::default();
+    let opts = encode_options.unwrap_or(&default_opts);
+    let mut input = String::new();
+    reader
+        .read_to_string(&mut input)
+        .map_err(|e| Error::Io(e.to_string()))?;
+    let mut table = TableSink::new(writer, opts);
+    for (index, item) in decode_iter(&input, decode_options).enumerate() {
+        let Some(row) = transform(item?) else {
+            continue;
+        };
+        table
+            .check(&row)
+            .map_err(|message| Error::Serialization(format!("item {index}: {message}")))?;
+        table.write(&row)?;
+    }
+    table.finish()
+}
+
+/// Writes records as the rows of a tabular root array, or of a table
+/// under a key
+///
+/// The columns come from the first record. Rows are held until
+/// [`finish`](Self::finish) when the count goes in the header, and written
+/// straight away otherwise.
+pub(crate) struct TableSink<'w, W: Write> {
+    writer: &'w mut W,
+    options: &'w EncodeOptions,
+    key: Option<&'w str>,
+    columns: Option<Vec<String>>,
+    /// Whether the columns were given as names rather than taken from the
+    /// first record, where under key folding they are paths
+    named: bool,
+    rows: usize,
+    /// Rendered rows waiting for the count to be known
+    pending: String,
+}
+
+impl<'w, W: Write> TableSink<'w, W> {
+    pub(crate) fn new(writer: &'w mut W, options: &'w EncodeOptions) -> Self {
+        TableSink {
+            writer,
+            options,
+            key: None,
+            columns: None,
+            named: false,
+            rows: 0,
+            pending: String::new(),
+        }
+    }
+
+    /// Write the table under `key` rather than as the root
+    pub(crate) fn under_key(mut self, key: Option<&'w str>) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Use `columns`, in this order, rather than the first record's fields
+    pub(crate) fn with_columns(mut self, columns: Vec<String>) -> Self {
+        self.columns = Some(columns);
+        self.named = true;
+        self
+    }
+
+    /// Check that `record` fits the table, taking the columns from it if
+    /// it is the first
+    pub(crate) fn check(&mut self, record: &Value) -> Result<(), String> {
+        let Value::Object(obj) = record else {
+            return Err("Expected a JSON object record".to_string());
+        };
+        let mut fields = Vec::new();
+        if column_paths(obj, None, self.options, &mut fields).is_none() {
+            return Err("Record has a nested value that can't be a table cell".to_string());
+        }
+        let columns = self.columns.get_or_insert_with(|| fields.clone());
+        match fields.iter().find(|field| !columns.contains(field)) {
+            Some(field) => Err(format!(
+                "Field `{field}` is not a column; columns come from the first record"
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Render a record that has passed [`check`](Self::check)
+    pub(crate) fn write(&mut self, record: &Value) -> Result<(), Error> {
+        let columns = self.columns.as_deref().unwrap_or_default();
+        let mut row = self.options.get_indent_string().into_owned();
+        encode_table_row(
+            record,
+            columns,
+            &TableStyle::plain(true),
+            &mut row,
+            self.options,
+        )?;
+        self.push_row(row)
+    }
+
+    /// Render a row given as one primitive cell per column, in order
+    #[cfg(feature = "rusqlite")]
+    pub(crate) fn write_cells(&mut self, cells: &[Value]) -> Result<(), Error> {
+        use crate::encode::{encode_scalar, transform_leaf, Slot};
+
+        let columns = self.columns.as_deref().unwrap_or_default();
+        let mut row = self.options.get_indent_string().into_owned();
+        for (i, (column, cell)) in columns.iter().zip(cells).enumerate() {
+            if i > 0 {
+                row.push(self.options.get_delimiter());
+            }
+            let cell = transform_leaf(cell, Some(column), self.options);
+            match cell.is_null() {
+                true => row.push_str("null"),
+                false => encode_scalar(&cell, &mut row, self.options, Slot::Cell)?,
+            }
+        }
+        self.push_row(row)
+    }
+
+    /// Write a rendered row, or hold it back until the count is known
+    fn push_row(&mut self, mut row: String) -> Result<(), Error> {
+        check_indent(self.options)?;
+        let columns = self.columns.as_deref().unwrap_or_default();
+        row.push('\n');
+        self.rows += 1;
+        if self.options.get_emit_lengths() {
+            self.pending.push_str(&row);
+            return Ok(());
+        }
+        let mut sink = WriteSink(&mut *self.writer);
+        if self.rows == 1 {
+            write_header(self.key, columns, self.named, 0, &mut sink, self.options)?;
+        }
+        sink.push_str(&row)
+    }
+
+    /// Write the header and any rows still held back, and flush
+    pub(crate) fn finish(self) -> Result<(), Error> {
+        let mut sink = WriteSink(&mut *self.writer);
+        match &self.columns {
+            None => {
+                let key = self.key.map(|key| format_key(key, self.options));
+                let length = length_header(0, self.options);
+                sink.push_str(&format!("{}{length}:", key.unwrap_or_default()))?;
+            }
+            Some(columns) if self.options.get_emit_lengths() => {
+                write_header(
+                    self.key,
+                    columns,
+                    self.named,
+                    self.rows,
+                    &mut sink,
+                    self.options,
+                )?;
+                sink.push_str(&self.pending)?;
+            }
+            Some(_) => {}
+        }
+        self.writer.flush().map_err(|e| Error::Io(e.to_string()))
+    }
+}
+
+/// Write the table header, `[N]{fields}:` for the root and
+/// `key[N]{fields}:` under a key
+fn write_header(
+    key: Option<&str>,
+    columns: &[String],
+    named: bool,
+    rows: usize,
+    output: &mut impl Sink,
+    options: &EncodeOptions,
+) -> Result<(), Error> {
+    let prefix = match key {
+        Some(key) => format!(
+            "{}{}",
+            format_key(key, options),
+            table_length_header(rows, options)
+        ),
+        None => length_header(rows, options),
+    };
+    let fields = match named {
+        true => format_fields(columns, options),
+        false => path_fields(columns, options),
+    };
+    output.push_str(&format!("{prefix}{{{fields}}}:\n"))
+}
@@ -0,0 +1,307 @@
+//! Incremental construction of TOON documents
+
+use crate::decode::is_root_primitive;
+use crate::encode::{
+    check_indent, encode_entry, encode_scalar, format_fields, format_key, key_separator,
+    length_header, table_length_header, transform_leaf, Placement, Slot,
+};
+use crate::error::Error;
+use crate::options::{DecodeOptions, EncodeOptions};
+use crate::raw::RawToon;
+use serde_json::Value;
+use std::io::Write;
+
+/// Writes a TOON document piece by piece, without building a `Value` for
+/// the whole of it first
+///
+/// The document is a top-level object. Fields are written as soon as they
+/// are added, in the same layout [`crate::encode`] would give them, and
+/// [`begin_object`] opens a nested object that later fields go into until
+/// [`end`]. Tables are written row by row between [`begin_table`] and
+/// [`end`]; their rows are held back until the count is known, unless
+/// lengths are turned off with [`EncodeOptions::emit_lengths`].
+///
+/// [`begin_object`]: ToonWriter::begin_object
+/// [`begin_table`]: ToonWriter::begin_table
+/// [`end`]: ToonWriter::end
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use toon_rust::ToonWriter;
+///
+/// let mut writer = ToonWriter::new(Vec::new());
+/// writer
+///     .begin_object("meta")?
+///     .field("source", &json!("erp"))?
+///     .end()?
+///     .begin_table("items", ["sku", "qty"])?
+///     .row(&[json!("A1"), json!(2)])?
+///     .row(&[json!("B2"), json!(1)])?
+///     .end()?;
+/// let toon = String::from_utf8(writer.finish()?).unwrap();
+/// assert_eq!(toon, "meta: \n  source: erp\nitems[2]{sku,qty}:\n  A1,2\n  B2,1\n");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub struct ToonWriter<W: Write> {
+    writer: W,
+    options: EncodeOptions,
+    /// Open objects and tables, innermost last
+    scopes: Vec<Scope>,
+}
+
+enum Scope {
+    Object,
+    Table {
+        key: String,
+        columns: Vec<String>,
+        rows: usize,
+        /// Rendered rows waiting for the count to be known
+        pending: String,
+    },
+}
+
+impl<W: Write> ToonWriter<W> {
+    /// Create a writer with default options
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, &EncodeOptions::default())
+    }
+
+    /// Create a writer with the given options
+    pub fn with_options(writer: W, options: &EncodeOptions) -> Self {
+        ToonWriter {
+            writer,
+            options: options.clone(),
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Write a `key: value` field into the innermost open object
+    pub fn field(&mut self, key: &str, value: &Value) -> Result<&mut Self, Error> {
+        let depth = self.object_depth("field")?;
+        let mut entry = String::new();
+        let placement = Placement::Line { first: true };
+        if encode_entry(
+            key,
+            value,
+            None,
+            &mut entry,
+            depth,
+            placement,
+            &self.options,
+        )? {
+            // Tables already end their last row with a newline
+            if !entry.ends_with('\n') {
+                entry.push('\n');
+            }
+            self.write(&entry)?;
+        }
+        Ok(self)
+    }
+
+    /// Write a pre-encoded value under `key` in the innermost open object
+    ///
+    /// The fragment's lines are copied as they are, indented to fit, so it
+    /// should use the same indentation as the writer.
+    pub fn raw_field(&mut self, key: &str, raw: &RawToon) -> Result<&mut Self, Error> {
+        let depth = self.object_depth("raw_field")?;
+        let indent = self.options.get_indent_string();
+        let mut lines = raw.as_str().trim().lines();
+        let first = lines.next().unwrap_or_default();
+        let mut entry = indent.repeat(depth) + &format_key(key, &self.options);
+        // An array's header goes on the key, while an object's entries go
+        // below it a level further in
+        let prefix = if first.starts_with('[') {
+            entry.push_str(first);
+            indent.repeat(depth)
+        } else if is_root_primitive(first, &DecodeOptions::default()) {
+            entry.push_str(key_separator(&self.options));
+            entry.push_str(first.trim());
+            indent.repeat(depth)
+        } else {
+            let prefix = indent.repeat(depth + 1);
+            entry.push_str(key_separator(&self.options));
+            entry.push('\n');
+            entry.push_str(&prefix);
+            entry.push_str(first);
+            prefix
+        };
+        entry.push('\n');
+        for line in lines {
+            // Blank lines, such as those inside block strings, stay blank
+            if !line.is_empty() {
+                entry.push_str(&prefix);
+                entry.push_str(line);
+            }
+            entry.push('\n');
+        }
+        self.write(&entry)?;
+        Ok(self)
+    }
+
+    /// Open a nested object under `key`; later fields go into it until
+    /// [`end`](ToonWriter::end)
+    pub fn begin_object(&mut self, key: &str) -> Result<&mut Self, Error> {
+        let depth = self.object_depth("begin_object")?;
+        let line = format!(
+            "{}{}{}\n",
+            self.options.get_indent_string().repeat(depth),
+            format_key(key, &self.options),
+            key_separator(&self.options)
+        );
+        self.write(&line)?;
+        self.scopes.push(Scope::Object);
+        Ok(self)
+    }
+
+    /// Open a table under `key` with the given columns, to be filled with
+    /// [`row`](ToonWriter::row) and closed with [`end`](ToonWriter::end)
+    pub fn begin_table<I>(&mut self, key: &str, columns: I) -> Result<&mut Self, Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.object_depth("begin_table")?;
+        self.scopes.push(Scope::Table {
+            key: key.to_string(),
+            columns: columns.into_iter().map(Into::into).collect(),
+            rows: 0,
+            pending: String::new(),
+        });
+        Ok(self)
+    }
+
+    /// Add a row to the open table, with one primitive cell per column
+    pub fn row(&mut self, cells: &[Value]) -> Result<&mut Self, Error> {
+        let depth = self.depth();
+        let options = &self.options;
+        let Some(Scope::Table {
+            key,
+            columns,
+            rows,
+            pending,
+        }) = self.scopes.last_mut()
+        else {
+            return Err(Error::Serialization(
+                "row() called without an open table".to_string(),
+            ));
+        };
+        if cells.len() != columns.len() {
+            return Err(Error::Serialization(format!(
+                "table `{key}` has {} columns but the row has {} cells",
+                columns.len(),
+                cells.len()
+            )));
+        }
+
+        let mut line = options.get_indent_string().repeat(depth + 1);
+        for (i, (column, cell)) in columns.iter().zip(cells).enumerate() {
+            if i > 0 {
+                line.push(options.get_delimiter());
+            }
+            let cell = transform_leaf(cell, Some(column), options);
+            encode_scalar(&cell, &mut line, options, Slot::Cell)?;
+        }
+        line.push('\n');
+
+        *rows += 1;
+        if options.get_emit_lengths() {
+            pending.push_str(&line);
+            return Ok(self);
+        }
+        // Without a count the header can go out with the first row
+        if *rows == 1 {
+            line.insert_str(0, &table_header(key, columns, None, depth, options));
+        }
+        self.write(&line)?;
+        Ok(self)
+    }
+
+    /// Close the innermost open object or table
+    pub fn end(&mut self) -> Result<&mut Self, Error> {
+        let depth = self.depth();
+        let Some(scope) = self.scopes.pop() else {
+            return Err(Error::Serialization(
+                "end() called without an open object or table".to_string(),
+            ));
+        };
+        if let Scope::Table {
+            key,
+            columns,
+            rows,
+            pending,
+        } = scope
+        {
+            let options = &self.options;
+            if rows == 0 {
+                let line = format!(
+                    "{}{}{}:\n",
+                    options.get_indent_string().repeat(depth),
+                    format_key(&key, options),
+                    length_header(0, options)
+                );
+                self.write(&line)?;
+            } else if options.get_emit_lengths() {
+                let header = table_header(&key, &columns, Some(rows), depth, options);
+                self.write(&header)?;
+                self.write(&pending)?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Close everything still open, flush, and return the underlying writer
+    pub fn finish(mut self) -> Result<W, Error> {
+        while !self.scopes.is_empty() {
+            self.end()?;
+        }
+        self.writer.flush().map_err(|e| Error::Io(e.to_string()))?;
+        Ok(self.writer)
+    }
+
+    /// Number of open objects, which is the indentation of new entries
+    fn depth(&self) -> usize {
+        self.scopes
+            .iter()
+            .filter(|scope| matches!(scope, Scope::Object))
+            .count()
+    }
+
+    /// The indentation for a new entry, or an error while a table is open
+    fn object_depth(&self, method: &str) -> Result<usize, Error> {
+        match self.scopes.last() {
+            Some(Scope::Table { key, .. }) => Err(Error::Serialization(format!(
+                "{method}() called while table `{key}` is open"
+            ))),
+            _ => Ok(self.depth()),
+        }
+    }
+
+    fn write(&mut self, s: &str) -> Result<(), Error> {
+        check_indent(&self.options)?;
+        self.writer
+            .write_all(s.as_bytes())
+            .map_err(|e| Error::Io(e.to_string()))
+    }
+}
+
+/// The `key[N]{columns}:` line opening a table
+fn table_header(
+    key: &str,
+    columns: &[String],
+    rows: Option<usize>,
+    depth: usize,
+    options: &EncodeOptions,
+) -> String {
+    let length = match rows {
+        Some(rows) => table_length_header(rows, options),
+        None => String::new(),
+    };
+    format!(
+        "{}{}{length}{{{}}}:\n",
+        options.get_indent_string().repeat(depth),
+        format_key(key, options),
+        format_fields(columns, options)
+    )
+}
@@ -0,0 +1,104 @@
+//! Conversion between TOON and YAML
+//!
+//! Requires the `yaml` feature. YAML maps onto the same data model as
+//! JSON, so a decoded document is written out as YAML unchanged, with keys
+//! in the order TOON decodes them, and YAML is read into a `Value` and
+//! encoded. Converting a YAML file and comparing
+//! [`estimate_tokens`](crate::estimate_tokens) of both texts shows what a
+//! prompt saves in TOON.
+
+use crate::decode::{decode, decode_stream};
+use crate::encode::{encode, encode_stream};
+use crate::error::Error;
+use crate::options::{DecodeOptions, EncodeOptions};
+use serde::Deserialize;
+use serde_json::Value;
+use std::io::{Read, Write};
+
+/// Convert a TOON document to YAML
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::toon_to_yaml;
+///
+/// let yaml = toon_to_yaml("name: shop\nitems[2]{sku,qty}:\n  A1,2\n  B2,1", None)?;
+/// assert_eq!(yaml, "items:\n- qty: 2\n  sku: A1\n- qty: 1\n  sku: B2\nname: shop\n");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn toon_to_yaml(input: &str, options: Option<&DecodeOptions>) -> Result<String, Error> {
+    let value = decode(input, options)?;
+    serde_yaml::to_string(&value).map_err(|e| Error::Serialization(e.to_string()))
+}
+
+/// Convert a TOON document from a reader to YAML written to `writer`
+pub fn toon_to_yaml_stream<R: Read, W: Write>(
+    reader: R,
+    writer: &mut W,
+    options: Option<&DecodeOptions>,
+) -> Result<(), Error> {
+    let value = decode_stream(reader, options)?;
+    serde_yaml::to_writer(&mut *writer, &value).map_err(|e| Error::Serialization(e.to_string()))?;
+    writer.flush().map_err(|e| Error::Io(e.to_string()))
+}
+
+/// Convert YAML to a TOON document
+///
+/// Anchors and aliases are expanded and tags are dropped. Keys must be
+/// strings, and a float that isn't finite becomes null, as in JSON. A
+/// stream of several `---` documents becomes a root array holding each
+/// of them. Syntax errors are [`Error::Parse`] errors at their place in
+/// the YAML.
+///
+/// # Example
+///
+/// ```rust
+/// use toon_rust::yaml_to_toon;
+///
+/// let yaml = "name: shop\ntags: [new, sale]\nopen: true\n";
+/// let toon = yaml_to_toon(yaml, None)?;
+/// assert_eq!(toon, "name: shop\nopen: true\ntags[2]:new,sale");
+/// # Ok::<(), toon_rust::Error>(())
+/// ```
+pub fn yaml_to_toon(input: &str, options: Option<&EncodeOptions>) -> Result<String, Error> {
+    let value = read_documents(serde_yaml::Deserializer::from_str(input))?;
+    encode(&value, options)
+}
+
+/// Convert YAML from a reader to TOON written to `writer`
+///
+/// The YAML is read whole before it is converted, as YAML can refer back
+/// to anything above with an alias; the TOON is written as it is encoded.
+pub fn yaml_to_toon_stream<R: Read, W: Write>(
+    reader: R,
+    writer: &mut W,
+    options: Option<&EncodeOptions>,
+) -> Result<(), Error> {
+    let value = read_documents(serde_yaml::Deserializer::from_reader(reader))?;
+    encode_stream(&value, writer, options)
+}
+
+/// The value of a YAML stream: its document, or an array of them when
+/// there are several
+fn read_documents(stream: serde_yaml::Deserializer) -> Result<Value, Error> {
+    let mut documents = stream
+        .map(|document| Value::deserialize(document).map_err(yaml_error))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(match documents.len() {
+        0 => Value::Null,
+        1 => documents.remove(0),
+        _ => Value::Array(documents),
+    })
+}
+
+fn yaml_error(err: serde_yaml::Error) -> Error {
+    match err.location() {
+        Some(location) => Error::parse(
+            location.index(),
+            location.line(),
+            location.column(),
+            err.to_string(),
+        ),
+        None => Error::Serialization(err.to_string()),
+    }
+}
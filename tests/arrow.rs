@@ -0,0 +1,149 @@
+#![cfg(feature = "arrow")]
+
+use arrow_array::builder::{ListBuilder, StringDictionaryBuilder};
+use arrow_array::types::{Int32Type, Int64Type};
+use arrow_array::{
+    ArrayRef, BooleanArray, Date32Array, Decimal128Array, Float32Array, Float64Array, Int64Array,
+    RecordBatch, StringArray,
+};
+use serde_json::json;
+use std::sync::Arc;
+use toon_rust::options::Delimiter;
+use toon_rust::{decode, encode_record_batches, EncodeOptions, Error};
+
+fn batch(columns: Vec<(&str, ArrayRef)>) -> RecordBatch {
+    RecordBatch::try_from_iter(columns).unwrap()
+}
+
+fn encode(batches: &[RecordBatch], options: Option<&EncodeOptions>) -> Result<String, Error> {
+    let mut output = Vec::new();
+    encode_record_batches(batches, &mut output, options)?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn test_arrow_batches_form_one_table() {
+    let first = batch(vec![
+        ("id", Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef),
+        ("name", Arc::new(StringArray::from(vec!["Ada", "Bob"]))),
+        ("active", Arc::new(BooleanArray::from(vec![true, false]))),
+    ]);
+    let second = batch(vec![
+        ("id", Arc::new(Int64Array::from(vec![3])) as ArrayRef),
+        ("name", Arc::new(StringArray::from(vec!["Cy, Jr."]))),
+        ("active", Arc::new(BooleanArray::from(vec![true]))),
+    ]);
+    let toon = encode(&[first, second], None).unwrap();
+    assert_eq!(
+        toon,
+        "[3]{id,name,active}:\n  1,Ada,true\n  2,Bob,false\n  3,\"Cy, Jr.\",true\n"
+    );
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!([
+            {"id": 1, "name": "Ada", "active": true},
+            {"id": 2, "name": "Bob", "active": false},
+            {"id": 3, "name": "Cy, Jr.", "active": true}
+        ])
+    );
+}
+
+#[test]
+fn test_arrow_floats_and_nulls() {
+    let batch = batch(vec![
+        (
+            "f32",
+            Arc::new(Float32Array::from(vec![Some(0.1), None, Some(2.0)])) as ArrayRef,
+        ),
+        (
+            "f64",
+            Arc::new(Float64Array::from(vec![1.5, f64::NAN, -0.25])),
+        ),
+        (
+            "s",
+            Arc::new(StringArray::from(vec![Some("null"), None, Some("")])),
+        ),
+    ]);
+    let toon = encode(&[batch], None).unwrap();
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!([
+            {"f32": 0.1, "f64": 1.5, "s": "null"},
+            {"f32": null, "f64": null, "s": null},
+            {"f32": 2.0, "f64": -0.25, "s": ""}
+        ])
+    );
+}
+
+#[test]
+fn test_arrow_display_types() {
+    let decimals = Decimal128Array::from(vec![1250, -5])
+        .with_precision_and_scale(10, 2)
+        .unwrap();
+    let mut dictionary = StringDictionaryBuilder::<Int32Type>::new();
+    dictionary.append_value("eu");
+    dictionary.append_value("us");
+    let batch = batch(vec![
+        (
+            "day",
+            Arc::new(Date32Array::from(vec![19844, 0])) as ArrayRef,
+        ),
+        ("price", Arc::new(decimals)),
+        ("region", Arc::new(dictionary.finish())),
+    ]);
+    let toon = encode(&[batch], None).unwrap();
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!([
+            {"day": "2024-05-01", "price": "12.50", "region": "eu"},
+            {"day": "1970-01-01", "price": "-0.05", "region": "us"}
+        ])
+    );
+}
+
+#[test]
+fn test_arrow_options() {
+    let batch = batch(vec![
+        ("a", Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef),
+        ("b", Arc::new(StringArray::from(vec!["x", "y"]))),
+    ]);
+    let options = EncodeOptions::new()
+        .delimiter(Delimiter::Pipe)
+        .emit_lengths(false);
+    let toon = encode(&[batch], Some(&options)).unwrap();
+    assert_eq!(toon, "[]{a|b}:\n  1|x\n  2|y\n");
+}
+
+#[test]
+fn test_arrow_empty() {
+    assert_eq!(encode(&[], None).unwrap(), "[0]:");
+    let empty = batch(vec![(
+        "id",
+        Arc::new(Int64Array::from(Vec::<i64>::new())) as ArrayRef,
+    )]);
+    let toon = encode(&[empty], None).unwrap();
+    assert_eq!(decode(&toon, None).unwrap(), json!([]));
+}
+
+#[test]
+fn test_arrow_errors() {
+    let mut list = ListBuilder::new(arrow_array::builder::PrimitiveBuilder::<Int64Type>::new());
+    list.values().append_value(1);
+    list.append(true);
+    let nested = batch(vec![("tags", Arc::new(list.finish()) as ArrayRef)]);
+    let err = encode(&[nested], None).unwrap_err();
+    assert!(err.to_string().contains("Column `tags` has type List"));
+
+    let first = batch(vec![(
+        "id",
+        Arc::new(Int64Array::from(vec![1])) as ArrayRef,
+    )]);
+    let second = batch(vec![(
+        "id",
+        Arc::new(StringArray::from(vec!["2"])) as ArrayRef,
+    )]);
+    let err = encode(&[first, second], None).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Record batch 1 has different fields from the first"));
+}
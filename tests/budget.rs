@@ -0,0 +1,124 @@
+//! Tests for encoding within a token budget
+
+use serde_json::json;
+use toon_rust::budget::{encode_with_budget_for, BudgetPolicy, Removal};
+use toon_rust::options::Delimiter;
+use toon_rust::tokens::Model;
+use toon_rust::{decode, encode, encode_with_budget, estimate_tokens, EncodeOptions};
+
+#[test]
+fn test_budget_fits_untouched() {
+    let data = json!({"name": "Alice", "tags": ["a", "b"]});
+    let budgeted = encode_with_budget(&data, 1000, BudgetPolicy::DropRows).unwrap();
+    assert_eq!(budgeted.toon, encode(&data, None).unwrap());
+    assert_eq!(
+        budgeted.tokens,
+        estimate_tokens(&budgeted.toon, Model::Gpt4)
+    );
+    assert!(budgeted.removed.is_empty());
+}
+
+#[test]
+fn test_budget_drop_rows() {
+    let rows: Vec<_> = (0..100)
+        .map(|i| json!({"id": i, "level": "info"}))
+        .collect();
+    let data = json!({"logs": rows, "tags": vec!["x"; 40]});
+    let budgeted = encode_with_budget(&data, 80, BudgetPolicy::DropRows).unwrap();
+    assert!(budgeted.tokens <= 80);
+
+    let decoded = decode(&budgeted.toon, None).unwrap();
+    let logs = decoded["logs"].as_array().unwrap();
+    let tags = decoded["tags"].as_array().unwrap();
+    // Rows are dropped evenly, in the same share from each array
+    assert_eq!(logs[0]["id"], 0);
+    assert!(logs.last().unwrap()["id"].as_u64().unwrap() > 50);
+    assert_eq!(tags.len(), (logs.len() * 40).div_ceil(100));
+    assert_eq!(
+        budgeted.removed,
+        vec![
+            Removal::Rows {
+                path: "logs".to_string(),
+                count: 100 - logs.len(),
+            },
+            Removal::Rows {
+                path: "tags".to_string(),
+                count: 40 - tags.len(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_budget_truncate_strings() {
+    let data = json!({
+        "id": 7,
+        "title": "Short",
+        "body": "lorem ipsum dolor sit amet ".repeat(40)
+    });
+    let budgeted = encode_with_budget(&data, 40, BudgetPolicy::TruncateStrings).unwrap();
+    assert!(budgeted.tokens <= 40);
+
+    let decoded = decode(&budgeted.toon, None).unwrap();
+    let body = decoded["body"].as_str().unwrap();
+    assert!(body.ends_with('…'));
+    assert_eq!(decoded["title"], "Short");
+    assert_eq!(
+        budgeted.removed,
+        vec![Removal::Truncated {
+            path: "body".to_string(),
+            chars: 40 * 27 - (body.chars().count() - 1),
+        }]
+    );
+}
+
+#[test]
+fn test_budget_elide_sections() {
+    let data = json!({
+        "summary": {"total": 3, "status": "ok"},
+        "events": (0..50).map(|i| json!({"id": i, "kind": "click"})).collect::<Vec<_>>()
+    });
+    let budgeted = encode_with_budget(&data, 30, BudgetPolicy::ElideSections).unwrap();
+    assert!(budgeted.tokens <= 30);
+    assert_eq!(
+        budgeted.removed,
+        vec![Removal::Elided {
+            path: "events".to_string()
+        }]
+    );
+    let decoded = decode(&budgeted.toon, None).unwrap();
+    assert_eq!(decoded["events"], "…");
+    assert_eq!(decoded["summary"], data["summary"]);
+}
+
+#[test]
+fn test_budget_model_and_options() {
+    let rows: Vec<_> = (0..30)
+        .map(|i| json!({"id": i, "name": "internationalization"}))
+        .collect();
+    let data = json!({"rows": rows});
+    let options = EncodeOptions::new().delimiter(Delimiter::Pipe);
+    let budgeted = encode_with_budget_for(
+        &data,
+        60,
+        BudgetPolicy::DropRows,
+        Model::Claude,
+        Some(&options),
+    )
+    .unwrap();
+    assert!(budgeted.toon.contains("{id|name}"));
+    assert!(estimate_tokens(&budgeted.toon, Model::Claude) <= 60);
+}
+
+#[test]
+fn test_budget_too_small() {
+    let data = json!({"name": "Alice", "age": 30, "city": "Paris"});
+    for policy in [
+        BudgetPolicy::DropRows,
+        BudgetPolicy::TruncateStrings,
+        BudgetPolicy::ElideSections,
+    ] {
+        let err = encode_with_budget(&data, 2, policy).unwrap_err();
+        assert!(err.to_string().contains("doesn't fit in 2 tokens"), "{err}");
+    }
+}
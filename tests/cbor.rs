@@ -0,0 +1,77 @@
+#![cfg(feature = "cbor")]
+
+use ciborium::value::Value as C;
+use serde_json::json;
+use toon_rust::options::BytesPolicy;
+use toon_rust::{cbor_to_toon, decode, toon_to_cbor, EncodeOptions, Error};
+
+/// CBOR for `value`, written with `ciborium`
+fn cbor(value: &C) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn test_cbor_round_trip() {
+    let toon = "device: d1\nratio: 0.25\nmin: -9223372036854775808\nreadings[2]{t,value}:\n  1,21.5\n  2,null\ntags[2]: a,\"true\"";
+    let bytes = toon_to_cbor(toon, None).unwrap();
+    let back = cbor_to_toon(&bytes, None).unwrap();
+    assert_eq!(decode(&back, None).unwrap(), decode(toon, None).unwrap());
+}
+
+#[test]
+fn test_cbor_bytes_and_tags() {
+    let reading = |t: i64, payload: &[u8]| {
+        C::Map(vec![
+            (C::from("t"), C::from(t)),
+            (C::from("payload"), C::Bytes(payload.to_vec())),
+        ])
+    };
+    let value = C::Map(vec![
+        (
+            C::from("at"),
+            C::Tag(0, Box::new(C::from("2024-05-01T09:30:00Z"))),
+        ),
+        (C::from(7), C::from("seven")),
+        (
+            C::from("readings"),
+            C::Array(vec![reading(1, &[0, 1, 255]), reading(2, b"ok")]),
+        ),
+    ]);
+    let toon = cbor_to_toon(&cbor(&value), None).unwrap();
+    assert!(toon.contains("readings[2]{payload,t}:\n  AAH/,1\n  b2s=,2"));
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!({
+            "at": "2024-05-01T09:30:00Z",
+            "7": "seven",
+            "readings": [{"payload": "AAH/", "t": 1}, {"payload": "b2s=", "t": 2}]
+        })
+    );
+
+    let options = EncodeOptions::new().bytes_policy(BytesPolicy::Array);
+    let toon = cbor_to_toon(&cbor(&C::Bytes(vec![0, 255])), Some(&options)).unwrap();
+    assert_eq!(decode(&toon, None).unwrap(), json!([0, 255]));
+}
+
+#[test]
+fn test_cbor_errors() {
+    let value = C::Map(vec![(
+        C::from("a"),
+        C::Array(vec![C::Null, C::Map(vec![(C::Null, C::Null)])]),
+    )]);
+    match cbor_to_toon(&cbor(&value), None) {
+        Err(Error::Deserialization { path, .. }) => assert_eq!(path.as_deref(), Some("a[1]")),
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    let mut bytes = cbor(&C::from(1));
+    bytes.push(0xf6);
+    assert!(matches!(
+        cbor_to_toon(&bytes, None),
+        Err(Error::Deserialization { .. })
+    ));
+    // An array of two items that stops after one
+    assert!(cbor_to_toon(&[0x82, 0x01], None).is_err());
+}
@@ -0,0 +1,181 @@
+//! Tests for the `toon` command line tool
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const SHOP: &str = "\
+name: \"Corner shop\"
+items[3]{sku,qty,price}:
+  A1,2,9.5
+  B2,1,3
+  C3,5,4
+";
+
+/// Run `toon` with `args`, feeding `SHOP` to standard input
+fn toon(args: &[&str]) -> Output {
+    toon_with(args, SHOP)
+}
+
+/// Run `toon` with `args`, feeding `input` to standard input
+fn toon_with(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_toon"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The tool may exit without reading, as it does on a usage error
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    assert!(output.status.success(), "{output:?}");
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_cli_get() {
+    assert_eq!(stdout(&toon(&["get", "-", "/items/2/price"])), "4\n");
+    assert_eq!(stdout(&toon(&["get", "-", "items[sku=B2].qty"])), "1\n");
+    assert_eq!(stdout(&toon(&["get", "-", "name"])), "\"Corner shop\"\n");
+    assert_eq!(
+        stdout(&toon(&["get", "-", "name", "--raw"])),
+        "Corner shop\n"
+    );
+    assert_eq!(
+        stdout(&toon(&["get", "-", "items[0]"])),
+        "price: 9.5\nqty: 2\nsku: A1\n"
+    );
+    assert_eq!(
+        stdout(&toon(&["get", "--raw", "-", "items[0]"])),
+        "{\"price\":9.5,\"qty\":2,\"sku\":\"A1\"}\n"
+    );
+
+    let missing = toon(&["get", "-", "/items/7"]);
+    assert_eq!(missing.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("no value at /items/7"));
+}
+
+#[test]
+fn test_cli_query() {
+    let query = "items[] | select(.qty > 1) | .sku";
+    assert_eq!(stdout(&toon(&["query", "-", query])), "A1\nC3\n");
+    assert_eq!(
+        stdout(&toon(&["query", "-", "items[].price", "--raw"])),
+        "9.5\n3\n4\n"
+    );
+    assert_eq!(
+        stdout(&toon(&["query", "-", "items[] | select(.qty > 9)"])),
+        ""
+    );
+
+    let bad = toon(&["query", "-", "items[] | select("]);
+    assert_eq!(bad.status.code(), Some(1));
+}
+
+#[test]
+fn test_cli_from_csv() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_toon"))
+        .args(["from-csv", "-", "--key", "items"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let csv = b"sku,qty\nA1,2\n\"B,2\",1\n";
+    child.stdin.take().unwrap().write_all(csv).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(stdout(&output), "items[2]{sku,qty}:\n  A1,2\n  \"B,2\",1\n");
+
+    // SHOP isn't CSV with a field count to match
+    assert_eq!(toon(&["from-csv", "-"]).status.code(), Some(1));
+
+    let output = toon_with(
+        &["from-csv", "-", "--delimiter", ";", "--no-infer"],
+        "sku;qty\nA1;2\n",
+    );
+    assert_eq!(stdout(&output), "[1]{sku,qty}:\n  A1,\"2\"\n");
+    assert_eq!(toon(&["fmt", "-", "--no-infer"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_cli_to_csv() {
+    let output = toon(&["to-csv", "-", "items", "--delimiter", ";"]);
+    assert!(stdout(&output).starts_with("price;qty;sku\r\n9.5;2;A1\r\n"));
+    assert_eq!(toon(&["to-csv", "-", "name"]).status.code(), Some(1));
+}
+
+#[test]
+fn test_cli_to_yaml() {
+    let output = toon(&["to-yaml", "-"]);
+    if cfg!(feature = "yaml") {
+        assert!(stdout(&output).starts_with("items:\n- price: 9.5\n  qty: 2\n  sku: A1\n"));
+    } else {
+        assert_eq!(output.status.code(), Some(1));
+    }
+}
+
+#[test]
+fn test_cli_from_yaml() {
+    let output = toon_with(&["from-yaml", "-"], "items:\n- sku: A1\n  qty: 2\n");
+    if cfg!(feature = "yaml") {
+        assert_eq!(stdout(&output), "items[1]{qty,sku}:\n  2,A1\n");
+    } else {
+        assert_eq!(output.status.code(), Some(1));
+    }
+}
+
+#[test]
+fn test_cli_fmt() {
+    assert_eq!(stdout(&toon(&["fmt", "-"])), SHOP);
+    assert_eq!(
+        stdout(&toon(&["fmt", "-", "--indent", "4"])),
+        SHOP.replace("\n  ", "\n    ")
+    );
+}
+
+#[test]
+fn test_cli_fmt_error() {
+    let output = toon_with(&["fmt", "-"], "name: shop\nitems 2\n");
+    assert!(!output.status.success());
+    // Standard error isn't a terminal, so the error is shown without color
+    assert_eq!(
+        std::str::from_utf8(&output.stderr).unwrap(),
+        "error: Expected a `key:` entry\n --> line 2, column 1\n  |\n2 | items 2\n  | ^\n"
+    );
+}
+
+#[test]
+fn test_cli_lint() {
+    assert_eq!(stdout(&toon(&["lint", "-"])), "");
+    let output = toon_with(&["lint", "-"], "tags[3]: a,b\nzip: 04532\n");
+    assert!(!output.status.success());
+    let lints = std::str::from_utf8(&output.stdout).unwrap();
+    assert!(
+        lints.starts_with("1:5: warning: The header says 3"),
+        "{lints}"
+    );
+    assert!(lints.contains("\n2:6: warning: `04532` has a leading zero"));
+    assert_eq!(
+        std::str::from_utf8(&output.stderr).unwrap(),
+        "toon: 2 problems found\n"
+    );
+}
+
+#[test]
+fn test_cli_usage() {
+    for args in [
+        &[][..],
+        &["get", "-"],
+        &["frobnicate", "-", "x"],
+        &["get", "-", "x", "--pretty"],
+        &["get", "-", "x", "--indent", "4"],
+        &["fmt", "-", "--indent", "wide"],
+        &["lint", "-", "--raw"],
+    ] {
+        let output = toon(args);
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("Usage:"));
+    }
+}
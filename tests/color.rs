@@ -0,0 +1,46 @@
+//! Tests for colored rendering
+#![cfg(feature = "color")]
+
+use toon_rust::{decode, lint, render_error, render_lint, Error};
+
+/// `text` with its ANSI escape sequences removed
+fn strip(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            chars.by_ref().find(|&ch| ch == 'm');
+        } else {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+#[test]
+fn test_color_error_matches_plain() {
+    let input = "name: Alice\nuser:\n  id 1";
+    let err = decode(input, None).unwrap_err();
+    let rendered = render_error(&err, input);
+    assert_ne!(rendered, err.render(input));
+    assert_eq!(strip(&rendered), err.render(input));
+
+    let err = Error::Serialization("boom".to_string());
+    assert_eq!(
+        render_error(&err, input),
+        "\x1b[1;31merror\x1b[0m\x1b[1m: Serialization error: boom\x1b[0m\n"
+    );
+}
+
+#[test]
+fn test_color_lint() {
+    let input = "items[1]{id,zip}:\n  1,04532\n";
+    let lints = lint(input);
+    let rendered = render_lint(&lints[0], input);
+    assert_eq!(
+        strip(&rendered),
+        "warning: `04532` has a leading zero, which decoders read as a number or a string; quote it if it is a string\n --> line 2, column 5\n  |\n2 |   1,04532\n  |     ^^^^^\n"
+    );
+    // The table row is highlighted as cells, not as a key
+    assert!(rendered.contains("\x1b[35m1\x1b[0m\x1b[2m,\x1b[0m\x1b[35m04532\x1b[0m"));
+}
@@ -0,0 +1,175 @@
+//! Tests for CSV conversion
+
+use serde_json::{json, Value};
+use toon_rust::options::{ColumnType, CsvOptions};
+use toon_rust::{decode, from_csv, to_csv, EncodeOptions, Error};
+
+fn convert(csv: &str, options: Option<&CsvOptions>) -> Result<String, Error> {
+    let mut toon = Vec::new();
+    from_csv(csv.as_bytes(), &mut toon, options)?;
+    Ok(String::from_utf8(toon).unwrap())
+}
+
+#[test]
+fn test_from_csv() {
+    let csv = "\u{feff}id,name,zip,active,score\r\n\
+               1,\"Smith, Ann\",04532,true,1.5\r\n\
+               \r\n\
+               2,\"says \"\"hi\"\"\nthen leaves\",,false,-2\r\n";
+    let toon = convert(csv, None).unwrap();
+    assert_eq!(
+        toon,
+        "[2]{id,name,zip,active,score}:\n  \
+         1,\"Smith, Ann\",\"04532\",true,1.5\n  \
+         2,\"says \\\"hi\\\"\\nthen leaves\",null,false,-2\n"
+    );
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!([
+            {"id": 1, "name": "Smith, Ann", "zip": "04532", "active": true, "score": 1.5},
+            {"id": 2, "name": "says \"hi\"\nthen leaves", "zip": null, "active": false, "score": -2}
+        ])
+    );
+
+    // Under a key, and with rows written as they are read
+    let options = CsvOptions::new().key("people");
+    assert_eq!(
+        convert("a,b\n1,x\n", Some(&options)).unwrap(),
+        "people[1]{a,b}:\n  1,x\n"
+    );
+    let options = options.encode_options(EncodeOptions::new().emit_lengths(false));
+    assert_eq!(
+        convert("a,b\n1,x\n2,y", Some(&options)).unwrap(),
+        "people{a,b}:\n  1,x\n  2,y\n"
+    );
+
+    // No records, and no header either
+    assert_eq!(convert("a,b\n", None).unwrap(), "[0]{a,b}:\n");
+    assert_eq!(convert("", None).unwrap(), "[0]:");
+}
+
+#[test]
+fn test_from_csv_errors() {
+    let position = |csv: &str| match convert(csv, None) {
+        Err(Error::Parse { line, message, .. }) => (line, message),
+        other => panic!("{csv:?}: {other:?}"),
+    };
+    assert_eq!(
+        position("a,b\n1,2\n3\n"),
+        (3, "Expected 2 fields, found 1".to_string())
+    );
+    assert_eq!(
+        position("a,b\n1,\"open\n"),
+        (2, "Unterminated quoted field".to_string())
+    );
+    assert_eq!(
+        position("a,b\n1,x\"y\"\n"),
+        (2, "Unexpected quote inside a field".to_string())
+    );
+    assert_eq!(
+        position("a,b\n\"1\"2,3\n"),
+        (2, "Unexpected text after a quoted field".to_string())
+    );
+    assert_eq!(position("a,a\n"), (1, "Duplicate column `a`".to_string()));
+}
+
+#[test]
+fn test_from_csv_delimiter_and_types() {
+    let csv = "id;zip;price;note\n1;04532;9,5;\"a;b\"\n2;;3;\n";
+
+    let options = CsvOptions::new().delimiter(';');
+    assert_eq!(
+        decode(&convert(csv, Some(&options)).unwrap(), None).unwrap(),
+        json!([
+            {"id": 1, "zip": "04532", "price": "9,5", "note": "a;b"},
+            {"id": 2, "zip": null, "price": 3, "note": null}
+        ])
+    );
+
+    // Everything as written
+    let options = options.infer_types(false).empty_as_null(false);
+    assert_eq!(
+        decode(&convert(csv, Some(&options)).unwrap(), None).unwrap(),
+        json!([
+            {"id": "1", "zip": "04532", "price": "9,5", "note": "a;b"},
+            {"id": "2", "zip": "", "price": "3", "note": ""}
+        ])
+    );
+
+    // Typed columns win over inference, and hold every cell to their type
+    let options = CsvOptions::new()
+        .delimiter('\t')
+        .column_type("zip", ColumnType::Str)
+        .column_type("score", ColumnType::Float)
+        .column_type("ok", ColumnType::Bool);
+    let csv = "zip\tscore\tok\n123\t2\ttrue\n\t\t\n";
+    assert_eq!(
+        decode(&convert(csv, Some(&options)).unwrap(), None).unwrap(),
+        json!([
+            {"zip": "123", "score": 2.0, "ok": true},
+            {"zip": null, "score": null, "ok": null}
+        ])
+    );
+    let err = convert("zip\tscore\tok\n1\tx\ttrue\n", Some(&options)).unwrap_err();
+    assert!(
+        matches!(&err, Error::Parse { line: 2, message, .. } if message.contains("'score'")),
+        "{err:?}"
+    );
+    let options = CsvOptions::new().column_type("missing", ColumnType::Int);
+    assert!(matches!(
+        convert("a\n1\n", Some(&options)),
+        Err(Error::Parse { line: 1, .. })
+    ));
+    assert!(matches!(
+        convert("a\n", Some(&CsvOptions::new().delimiter('"'))),
+        Err(Error::Syntax(_))
+    ));
+}
+
+#[test]
+fn test_to_csv() {
+    let doc = decode(
+        "shop:\n  items[3]{sku,note,qty}:\n    A1,\"a, b\",2\n    B2,\"say \\\"hi\\\"\\nbye\",null\n    \"\",x,1.5\n",
+        None,
+    )
+    .unwrap();
+    fn write(value: &Value, path: &str, options: Option<&CsvOptions>) -> Result<String, Error> {
+        let mut csv = Vec::new();
+        to_csv(value, path, &mut csv, options)?;
+        Ok(String::from_utf8(csv).unwrap())
+    }
+    let csv = write(&doc, "shop.items", None).unwrap();
+    assert_eq!(
+        csv,
+        "note,qty,sku\r\n\"a, b\",2,A1\r\n\"say \"\"hi\"\"\nbye\",,B2\r\nx,1.5,\r\n"
+    );
+    assert_eq!(write(&doc, "/shop/items", None).unwrap(), csv);
+
+    // What `from_csv` reads back is the table, but for types CSV can't carry
+    let mut toon = Vec::new();
+    from_csv(csv.as_bytes(), &mut toon, None).unwrap();
+    assert_eq!(
+        decode(std::str::from_utf8(&toon).unwrap(), None).unwrap(),
+        json!([
+            {"note": "a, b", "qty": 2, "sku": "A1"},
+            {"note": "say \"hi\"\nbye", "qty": null, "sku": "B2"},
+            {"note": "x", "qty": 1.5, "sku": null}
+        ])
+    );
+
+    // Columns in the order they first appear, nested values as JSON
+    let value = json!([{"b": 1}, {"a": [1, 2], "b": 2}]);
+    let options = CsvOptions::new().delimiter('\t');
+    assert_eq!(
+        write(&value, "", Some(&options)).unwrap(),
+        "b\ta\r\n1\t\r\n2\t[1,2]\r\n"
+    );
+    assert_eq!(write(&json!([{}]), "", None).unwrap(), "");
+
+    for path in ["shop", "missing", "shop.items[0]"] {
+        assert!(
+            matches!(write(&doc, path, None), Err(Error::Serialization(_))),
+            "{path}"
+        );
+    }
+}
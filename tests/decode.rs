@@ -1,6 +1,7 @@
 //! Tests for TOON decoding
 
-use toon_rust::{decode, DecodeOptions};
+use serde_json::json;
+use toon_rust::{decode, decode_stream, DecodeOptions};
 
 #[test]
 fn test_decode_simple_object() {
@@ -86,3 +87,1080 @@ fn test_decode_with_length_marker() {
     let tags = result["tags"].as_array().unwrap();
     assert_eq!(tags.len(), 3);
 }
+
+#[test]
+fn test_decode_declared_delimiter() {
+    use toon_rust::{decode_rows, dom};
+
+    // A delimiter in the length wins over one the cells would suggest
+    let toon = "a[2|]: x,1|y\nb[#2\t]: 1\t2\nt[2|]{a}:\n  1,2\n  3\nl[1|]:\n  - [2|]: p,q|r";
+    let expected = json!({
+        "a": ["x,1", "y"],
+        "b": [1, 2],
+        "t": [{"a": "1,2"}, {"a": 3}],
+        "l": [["p,q", "r"]]
+    });
+    assert_eq!(decode(toon, None).unwrap(), expected);
+    assert_eq!(decode_stream(toon.as_bytes(), None).unwrap(), expected);
+    assert_eq!(dom::parse(toon, None).unwrap().value(), expected);
+    let lenient = DecodeOptions::new().strict(false);
+    assert_eq!(decode(toon, Some(&lenient)).unwrap(), expected);
+    let rows: Vec<_> = decode_rows(toon, "t", None).map(Result::unwrap).collect();
+    assert_eq!(json!(rows), expected["t"]);
+}
+
+#[test]
+fn test_decode_non_finite_literals() {
+    let toon = "items[2]{id,value}:\n  1,nan\n  2,-inf";
+    assert!(decode(toon, None).is_err());
+
+    // Accepted literals decode to the marker the encoder writes back as is
+    let marker = |literal| json!({"$toon_rust::private::NonFinite": literal});
+    let options = DecodeOptions::new().allow_nan(true);
+    let result = decode(toon, Some(&options)).unwrap();
+    assert_eq!(result["items"][0]["value"], marker("nan"));
+    assert_eq!(result["items"][1]["value"], marker("-inf"));
+
+    let result = decode("a: inf\nb: -inf\nd: info", Some(&options)).unwrap();
+    assert_eq!(result["a"], marker("inf"));
+    assert_eq!(result["b"], marker("-inf"));
+    assert_eq!(result["d"], "info");
+    let token = toon_rust::EncodeOptions::new().nan_policy(toon_rust::options::NanPolicy::Token);
+    assert_eq!(
+        toon_rust::encode(&result, Some(&token)).unwrap(),
+        "a: inf\nb: -inf\nd: info"
+    );
+}
+
+#[test]
+fn test_decode_lenient_numbers() {
+    let toon = "mask: 0xFF\ncount: 1_000_000\nitems[2]{id,size}:\n  0x1F,2_048\n  -0x10,ab_c";
+    let result = decode(toon, None).unwrap();
+    assert_eq!(result["items"][0]["id"], "0x1F");
+
+    let options = DecodeOptions::new().lenient_numbers(true);
+    let result = decode(toon, Some(&options)).unwrap();
+    assert_eq!(result["mask"], 255);
+    assert_eq!(result["count"], 1_000_000);
+    assert_eq!(result["items"][0]["id"], 31);
+    assert_eq!(result["items"][0]["size"], 2048);
+    assert_eq!(result["items"][1]["id"], -16);
+    assert_eq!(result["items"][1]["size"], "ab_c");
+
+    let toon = "a: 0xFFFFFFFFFFFFFFFF\nb: -0x8000000000000000\nc: 0xFFFFFFFFFFFFFFFFFF\nd: 0x\ne: 0xZZ\nf: 0b101\ng: 1_000.5\nh: 1__0\ni: 1_\nj: 1e_5";
+    let result = decode(toon, Some(&options)).unwrap();
+    assert_eq!(result["a"], u64::MAX);
+    assert_eq!(result["b"], i64::MIN);
+    for (key, text) in [
+        ("c", "0xFFFFFFFFFFFFFFFFFF"),
+        ("d", "0x"),
+        ("e", "0xZZ"),
+        ("f", "0b101"),
+        ("g", "1_000.5"),
+        ("h", "1__0"),
+        ("i", "1_"),
+        ("j", "1e_5"),
+    ] {
+        assert_eq!(result[key], text, "{key}");
+    }
+}
+
+#[test]
+fn test_decode_number_prefix_stays_string() {
+    let toon = "date: 2024-01-01\nid: 12abc\nversion: 1.5.6\nrows[1]{date,id}:\n  2024-01-01,12abc";
+    let result = decode(toon, None).unwrap();
+    assert_eq!(result["date"], "2024-01-01");
+    assert_eq!(result["id"], "12abc");
+    assert_eq!(result["version"], "1.5.6");
+    assert_eq!(result["rows"][0]["date"], result["date"]);
+    assert_eq!(result["rows"][0]["id"], result["id"]);
+
+    let result = decode_stream(toon.as_bytes(), None).unwrap();
+    assert_eq!(result["date"], "2024-01-01");
+    assert_eq!(result["id"], "12abc");
+}
+
+#[test]
+fn test_decode_error_line_and_column() {
+    let toon = "name: Alice\nuser:\n  id 1";
+    let err = decode(toon, None).unwrap_err();
+    match &err {
+        toon_rust::Error::Parse { line, column, .. } => {
+            assert_eq!(*line, 3);
+            assert_eq!(*column, 6);
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+    assert!(err
+        .to_string()
+        .starts_with("Parse error at line 3, column 6:"));
+}
+
+#[test]
+fn test_decode_error_render() {
+    let toon = "name: Alice\nuser:\n  id 1";
+    let err = decode(toon, None).unwrap_err();
+    let rendered = err.render(toon);
+    assert_eq!(
+        rendered,
+        "error: Expected ':' after key 'id'\n --> line 3, column 6\n  |\n3 |   id 1\n  |      ^\n"
+    );
+
+    let err = toon_rust::Error::Serialization("boom".to_string());
+    assert_eq!(err.render(toon), "error: Serialization error: boom\n");
+}
+
+#[test]
+fn test_decode_error_json() {
+    let err = toon_rust::dom::parse("a: 1\n   b: 2", None).unwrap_err();
+    let json = err.to_json();
+    assert_eq!(json["code"], "parse");
+    assert_eq!(json["span"], json!({"offset": 5, "line": 2, "column": 1}));
+    assert_eq!(
+        json["message"],
+        "Indentation must be a multiple of 2 spaces"
+    );
+    assert!(json["suggestions"][0]
+        .as_str()
+        .unwrap()
+        .contains("DecodeOptions::indent"));
+
+    let err = decode("tags[3]: a,b", None).unwrap_err();
+    assert_eq!(
+        err.to_json(),
+        json!({
+            "code": "length_mismatch",
+            "message": "Array length mismatch: expected 3, found 2",
+            "expected": 3,
+            "found": 2,
+            "suggestions": [
+                "Change the declared length to 2",
+                "Set `DecodeOptions::length_policy` to pad or truncate instead of failing"
+            ]
+        })
+    );
+
+    let err = toon_rust::Error::Deserialization {
+        message: "invalid type".to_string(),
+        path: Some("users[0].id".to_string()),
+        line: Some(3),
+    };
+    assert_eq!(
+        err.to_json(),
+        json!({
+            "code": "deserialization",
+            "message": "invalid type",
+            "span": {"line": 3},
+            "path": "users[0].id",
+            "suggestions": []
+        })
+    );
+    #[cfg(feature = "serde")]
+    assert_eq!(serde_json::to_value(&err).unwrap(), err.to_json());
+}
+
+#[test]
+fn test_decode_all_errors_recovers() {
+    use toon_rust::decode_all_errors;
+
+    let toon = "name: Alice\nage 30\nitems[3]{id,qty}:\n  1,2\n  3\n  5,6\ntags[2]: a\ncity: Paris";
+    let (value, errors) = decode_all_errors(toon, None);
+    assert_eq!(
+        value,
+        json!({
+            "name": "Alice",
+            "items": [{"id": 1, "qty": 2}, {"id": 5, "qty": 6}],
+            "tags": ["a"],
+            "city": "Paris"
+        })
+    );
+    assert_eq!(errors.len(), 3);
+    assert!(matches!(errors[0], toon_rust::Error::Parse { line: 2, .. }));
+    assert_eq!(
+        errors[1],
+        toon_rust::Error::LengthMismatch {
+            expected: 2,
+            found: 1
+        }
+    );
+    assert_eq!(
+        errors[2],
+        toon_rust::Error::LengthMismatch {
+            expected: 2,
+            found: 1
+        }
+    );
+
+    let (value, errors) = decode_all_errors("a: 1\nb: 2", None);
+    assert_eq!(value, json!({"a": 1, "b": 2}));
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn test_decode_resource_limits() {
+    let options = DecodeOptions::new()
+        .max_array_length(100)
+        .max_cells(4)
+        .max_string_length(5);
+
+    let err = decode("items[999999999]{id,name}:\n  1,a", Some(&options)).unwrap_err();
+    assert!(err
+        .to_string()
+        .ends_with("Array length 999999999 exceeds the limit of 100"));
+
+    let toon = "items[3]{id,name}:\n  1,a\n  2,b\n  3,c";
+    let err = decode(toon, Some(&options)).unwrap_err();
+    assert!(err
+        .to_string()
+        .ends_with("Document exceeds the limit of 4 array cells"));
+
+    let err = decode("name: Alexander", Some(&options)).unwrap_err();
+    assert!(err
+        .to_string()
+        .ends_with("String of 9 bytes exceeds the limit of 5 bytes"));
+    assert!(decode("tags[2]: a,\"abcdefgh\"", Some(&options)).is_err());
+
+    let ok = decode("tags[2]: a,b\nname: Alice", Some(&options)).unwrap();
+    assert_eq!(ok, json!({"tags": ["a", "b"], "name": "Alice"}));
+}
+
+#[test]
+fn test_decode_multibyte_text() {
+    let toon =
+        "name: Zoë\ncity: Zürich\nitems[2]{sku,label}:\n  é1,Café\n  ü2,naïve\ntags[2]: 日本,東京";
+    let result = decode(toon, None).unwrap();
+    assert_eq!(
+        result,
+        json!({
+            "name": "Zoë",
+            "city": "Zürich",
+            "items": [
+                {"sku": "é1", "label": "Café"},
+                {"sku": "ü2", "label": "naïve"}
+            ],
+            "tags": ["日本", "東京"]
+        })
+    );
+
+    let err = decode("ключ: 1\nимя 2", None).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("Parse error at line 2, column 5:"));
+}
+
+#[test]
+fn test_decode_lenient_whitespace() {
+    let toon = "name: Alice   \n\nuser:\n   id: 1\n   tags[2]: a,b\n\n   address:\n     city: Paris  \n    zip: 75001\nactive: true\n";
+    let lenient = DecodeOptions::new().strict(false);
+    let expected = json!({
+        "name": "Alice",
+        "user": {
+            "id": 1,
+            "tags": ["a", "b"],
+            "address": {"city": "Paris", "zip": 75001}
+        },
+        "active": true
+    });
+    assert_eq!(decode(toon, Some(&lenient)).unwrap(), expected);
+    assert_eq!(
+        toon_rust::decode_stream(toon.as_bytes(), Some(&lenient)).unwrap(),
+        expected
+    );
+
+    // A blank line inside a table's declared length is a row, however it's indented
+    for data in [
+        json!({"t": [{"x": 1}, {"x": null}, {"x": 2}]}),
+        json!({"a": {"t": [{"x": null}]}, "b": [{"x": null}], "c": 1}),
+    ] {
+        let toon = toon_rust::encode(&data, None).unwrap();
+        assert_eq!(decode(&toon, Some(&lenient)).unwrap(), data, "{toon:?}");
+        assert_eq!(
+            decode_stream(toon.as_bytes(), Some(&lenient)).unwrap(),
+            data
+        );
+    }
+    assert_eq!(
+        decode("t[3]{x}:\n    1\n\n    2\n\ny: 1", Some(&lenient)).unwrap(),
+        json!({"t": [{"x": 1}, {"x": null}, {"x": 2}], "y": 1})
+    );
+
+    // Except after an elision marker, which ends the table
+    let toon = "a[5]{id}:\n  1\n  … (4 more rows)\n\nb: 1";
+    let expected = json!({"a": [{"id": 1}], "b": 1});
+    assert_eq!(decode(toon, Some(&lenient)).unwrap(), expected);
+    assert_eq!(
+        decode_stream(toon.as_bytes(), Some(&lenient)).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn test_decode_duplicate_key_policy() {
+    use toon_rust::options::DuplicateKeyPolicy;
+
+    let toon = "id: 1\nname: a\nid: 2";
+    assert_eq!(decode(toon, None).unwrap(), json!({"id": 2, "name": "a"}));
+
+    let first = DecodeOptions::new().duplicate_keys(DuplicateKeyPolicy::FirstWins);
+    assert_eq!(
+        decode(toon, Some(&first)).unwrap(),
+        json!({"id": 1, "name": "a"})
+    );
+
+    let error = DecodeOptions::new().duplicate_keys(DuplicateKeyPolicy::Error);
+    let err = decode(toon, Some(&error)).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("Parse error at line 3, column 3: Duplicate key 'id'"));
+    assert!(decode("rows[1]{id,id}:\n  1,2", Some(&error)).is_err());
+    assert_eq!(
+        decode("rows[1]{id,id}:\n  1,2", Some(&first)).unwrap(),
+        json!({"rows": [{"id": 1}]})
+    );
+
+    let (value, errors) = toon_rust::decode_all_errors(toon, Some(&error));
+    assert_eq!(value, json!({"id": 1, "name": "a"}));
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn test_decode_unicode_escapes() {
+    let result = decode(r#"name: "\u0041l\u00e9x""#, None).unwrap();
+    assert_eq!(result, json!({"name": "Aléx"}));
+
+    let result = decode(r#"emoji: "\ud83d\ude00 ok""#, None).unwrap();
+    assert_eq!(result, json!({"emoji": "😀 ok"}));
+
+    let result = decode("tags[2]: \"\\u00fc\",b", None).unwrap();
+    assert_eq!(result, json!({"tags": ["ü", "b"]}));
+
+    assert!(matches!(
+        decode(r#"bad: "\ud83d""#, None),
+        Err(toon_rust::Error::InvalidEscape(_))
+    ));
+    assert!(matches!(
+        decode(r#"bad: "\u00zz""#, None),
+        Err(toon_rust::Error::InvalidEscape(_))
+    ));
+}
+
+#[test]
+fn test_decode_lenient_arrays() {
+    let options = DecodeOptions::new().lenient_arrays(true);
+
+    let toon = "tags[]: a,b,c\nusers[]{id,name}:\n  1,Ann\n  2,Bo\nitems:\n  - x\n  - 2\ncolors: red,green\nname: Alice";
+    let expected = json!({
+        "tags": ["a", "b", "c"],
+        "users": [{"id": 1, "name": "Ann"}, {"id": 2, "name": "Bo"}],
+        "items": ["x", 2],
+        "colors": ["red", "green"],
+        "name": "Alice"
+    });
+    assert_eq!(decode(toon, Some(&options)).unwrap(), expected);
+    assert_eq!(
+        toon_rust::decode_stream(toon.as_bytes(), Some(&options)).unwrap(),
+        expected
+    );
+
+    assert_eq!(
+        decode("[]: 1,2,3", Some(&options)).unwrap(),
+        json!([1, 2, 3])
+    );
+    // Quoted values stay strings
+    assert_eq!(
+        decode("note: \"a, b\"", Some(&options)).unwrap(),
+        json!({"note": "a, b"})
+    );
+
+    // Off by default
+    assert!(decode("tags[]: a,b", None).is_err());
+    assert_eq!(
+        decode("colors: red,green", None).unwrap(),
+        json!({"colors": "red,green"})
+    );
+}
+
+#[test]
+fn test_decode_quoted_cells_with_newlines() {
+    let toon = "rows[2]{id,note}:\n  1,\"first\nsecond\"\n  2,plain\ntags[2]: \"a\nb\",c";
+    let expected = json!({
+        "rows": [{"id": 1, "note": "first\nsecond"}, {"id": 2, "note": "plain"}],
+        "tags": ["a\nb", "c"]
+    });
+    assert_eq!(decode(toon, None).unwrap(), expected);
+    assert_eq!(
+        toon_rust::decode_stream(toon.as_bytes(), None).unwrap(),
+        expected
+    );
+
+    // An unterminated quote only affects its own line
+    let options = toon_rust::DecodeOptions::new().strict(false);
+    assert_eq!(
+        decode("rows[2]{a}:\n  \"x\n  y", Some(&options)).unwrap(),
+        json!({"rows": [{"a": "\"x"}, {"a": "y"}]})
+    );
+}
+
+#[test]
+fn test_decode_length_policy() {
+    use toon_rust::options::LengthPolicy;
+
+    let toon = "rows[3]{a,b}:\n  1\n  2,3,4\ntags[2]: x,y,z";
+
+    // Strict mode rejects mismatches by default
+    assert!(decode(toon, None).is_err());
+
+    // Non-strict mode pads and truncates by default
+    let padded = json!({
+        "rows": [{"a": 1, "b": null}, {"a": 2, "b": 3}, null],
+        "tags": ["x", "y"]
+    });
+    let options = DecodeOptions::new().strict(false);
+    assert_eq!(decode(toon, Some(&options)).unwrap(), padded);
+    assert_eq!(
+        toon_rust::decode_stream(toon.as_bytes(), Some(&options)).unwrap(),
+        padded
+    );
+
+    let options = DecodeOptions::new().length_policy(LengthPolicy::Truncate);
+    assert_eq!(
+        decode(toon, Some(&options)).unwrap(),
+        json!({"rows": [{"a": 1}, {"a": 2, "b": 3}], "tags": ["x", "y"]})
+    );
+
+    let options = DecodeOptions::new()
+        .strict(false)
+        .length_policy(LengthPolicy::Error);
+    assert!(decode(toon, Some(&options)).is_err());
+
+    // Extra cells can be kept under a catch-all key
+    let options = DecodeOptions::new()
+        .length_policy(LengthPolicy::Pad)
+        .extra_cells_key("_extra");
+    assert_eq!(
+        decode("rows[1]{a,b}:\n  2,3,4,x", Some(&options)).unwrap(),
+        json!({"rows": [{"a": 2, "b": 3, "_extra": [4, "x"]}]})
+    );
+}
+
+#[test]
+fn test_decode_coercion_controls() {
+    let toon = "zip: 04532\nactive: true\nrows[1]{zip,ok,note}:\n  04532,false,\ntags[2]: 1,x";
+
+    assert_eq!(
+        decode(toon, None).unwrap(),
+        json!({
+            "zip": 4532,
+            "active": true,
+            "rows": [{"zip": 4532, "ok": false, "note": null}],
+            "tags": [1, "x"]
+        })
+    );
+
+    let options = DecodeOptions::new()
+        .coerce_numbers(false)
+        .coerce_booleans(false);
+    let expected = json!({
+        "zip": "04532",
+        "active": "true",
+        "rows": [{"zip": "04532", "ok": "false", "note": null}],
+        "tags": ["1", "x"]
+    });
+    assert_eq!(decode(toon, Some(&options)).unwrap(), expected);
+    assert_eq!(
+        toon_rust::decode_stream(toon.as_bytes(), Some(&options)).unwrap(),
+        expected
+    );
+
+    // Every cell is a string, while object values are still coerced
+    let options = DecodeOptions::new().cells_as_strings(true);
+    assert_eq!(
+        decode(toon, Some(&options)).unwrap(),
+        json!({
+            "zip": 4532,
+            "active": true,
+            "rows": [{"zip": "04532", "ok": "false", "note": ""}],
+            "tags": ["1", "x"]
+        })
+    );
+}
+
+#[test]
+fn test_decode_rows() {
+    use toon_rust::decode_rows;
+
+    let toon = "name: report\nitems[3]{id,qty}:\n  1,5\n  2,x,9\n  3,7\nafter: true";
+    let rows: Vec<_> = decode_rows(toon, "items", None).collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(
+        serde_json::Value::Object(rows[0].as_ref().unwrap().clone()),
+        json!({"id": 1, "qty": 5})
+    );
+    // A bad row is reported without ending the iteration
+    assert!(rows[1].is_err());
+    assert_eq!(
+        serde_json::Value::Object(rows[2].as_ref().unwrap().clone()),
+        json!({"id": 3, "qty": 7})
+    );
+
+    // Root arrays use an empty key
+    let ids: Vec<i64> = decode_rows("[2]{id}:\n  4\n  5", "", None)
+        .map(|row| row.unwrap()["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![4, 5]);
+
+    // Short tables end with a length error
+    let rows: Vec<_> = decode_rows("items[3]{id}:\n  1", "items", None).collect();
+    assert_eq!(rows.len(), 2);
+    assert!(matches!(
+        rows[1],
+        Err(toon_rust::Error::LengthMismatch {
+            expected: 3,
+            found: 1
+        })
+    ));
+
+    // Missing or non-tabular arrays yield a single error
+    let mut rows = decode_rows(toon, "missing", None);
+    assert!(rows.next().unwrap().is_err());
+    assert!(rows.next().is_none());
+    let mut rows = decode_rows("tags[2]: a,b", "tags", None);
+    assert!(rows.next().unwrap().is_err());
+    assert!(rows.next().is_none());
+}
+
+#[test]
+fn test_decode_iter() {
+    use toon_rust::decode_iter;
+
+    for toon in [
+        "[3]:\n  - id: 1\n    tags[1]: a\n  - 2\n  - [2]: x,y",
+        "[3]{id,qty}:\n  1,5\n  2,6\n  3,7",
+        "[3]: a,b,c",
+        "[0]:",
+    ] {
+        let items: Vec<_> = decode_iter(toon, None).map(Result::unwrap).collect();
+        assert_eq!(serde_json::Value::Array(items), decode(toon, None).unwrap());
+    }
+
+    // Items after the ones taken are never parsed
+    let broken = "[3]:\n  - 1\n  - [x]: 1\n  - 3";
+    assert!(decode(broken, None).is_err());
+    let mut items = decode_iter(broken, None);
+    assert_eq!(items.next().unwrap().unwrap(), json!(1));
+
+    // A bad item is reported without ending the iteration
+    let items: Vec<_> = decode_iter("[3]:\n  - a\n  - [x]: 1\n  - c", None).collect();
+    assert_eq!(items.len(), 3);
+    assert!(items[1].is_err());
+    assert_eq!(items[2].as_ref().unwrap(), &json!("c"));
+
+    // Short arrays end with a length error
+    let items: Vec<_> = decode_iter("[3]:\n  - 1", None).collect();
+    assert_eq!(items.len(), 2);
+    assert!(matches!(
+        items[1],
+        Err(toon_rust::Error::LengthMismatch {
+            expected: 3,
+            found: 1
+        })
+    ));
+
+    let expand = DecodeOptions::new().expand_paths(true);
+    let items: Vec<_> = decode_iter("[1]{a.b}:\n  1", Some(&expand)).collect();
+    assert_eq!(items[0].as_ref().unwrap(), &json!({"a": {"b": 1}}));
+
+    // Anything but a root array yields a single error
+    let mut items = decode_iter("name: Alice", None);
+    assert!(items.next().unwrap().is_err());
+    assert!(items.next().is_none());
+}
+
+#[test]
+fn test_decode_key() {
+    use toon_rust::options::DuplicateKeyPolicy;
+    use toon_rust::{decode_key, decode_path};
+
+    let toon = "meta: \n  source: erp\n  note: \"line one\nlast: line\"\n\"my key\": 1\nitems[2]{sku,qty}:\n  A1,2\n  B2,1\ncount: 2";
+    let full = decode(toon, None).unwrap();
+    for key in ["meta", "my key", "items", "count"] {
+        assert_eq!(decode_key(toon, key, None).unwrap().as_ref(), full.get(key));
+    }
+    // Text inside a quoted value is not an entry
+    assert_eq!(decode_key(toon, "last", None).unwrap(), None);
+    assert_eq!(decode_key("[2]: 1,2", "items", None).unwrap(), None);
+
+    // Other entries are never parsed, so their errors go unnoticed
+    let broken = "bad[2]: 1\nwanted: yes\nalso bad";
+    assert_eq!(
+        decode_key(broken, "wanted", None).unwrap(),
+        Some(json!("yes"))
+    );
+    let err = decode_key("a: 1\nb[x]: 2", "b", None).unwrap_err();
+    assert!(err.to_string().contains("line 2"));
+
+    let repeated = "a: 1\nb: 2\na: 3";
+    assert_eq!(decode_key(repeated, "a", None).unwrap(), Some(json!(3)));
+    let first = DecodeOptions::new().duplicate_keys(DuplicateKeyPolicy::FirstWins);
+    assert_eq!(
+        decode_key(repeated, "a", Some(&first)).unwrap(),
+        Some(json!(1))
+    );
+    let error = DecodeOptions::new().duplicate_keys(DuplicateKeyPolicy::Error);
+    assert!(decode_key(repeated, "a", Some(&error)).is_err());
+
+    let folded = "a.b: 1\nz: 0\na.c[2]: x,y";
+    let expand = DecodeOptions::new().expand_paths(true);
+    assert_eq!(
+        decode_key(folded, "a", Some(&expand)).unwrap(),
+        Some(json!({"b": 1, "c": ["x", "y"]}))
+    );
+    assert_eq!(decode_key(folded, "a", None).unwrap(), None);
+
+    assert_eq!(
+        decode_path(toon, &["items", "1", "sku"], None).unwrap(),
+        Some(json!("B2"))
+    );
+    assert_eq!(
+        decode_path(toon, &["meta", "source"], None).unwrap(),
+        Some(json!("erp"))
+    );
+    assert_eq!(decode_path(toon, &["items", "5"], None).unwrap(), None);
+    assert_eq!(decode_path(toon, &["count", "x"], None).unwrap(), None);
+    assert_eq!(decode_path(toon, &[], None).unwrap(), Some(full));
+}
+
+#[test]
+fn test_decode_include_keys() {
+    use toon_rust::decode_stream;
+
+    let toon = "\
+name: shop
+items[2]{sku,qty}:
+  A1,2
+  B2,1
+meta:
+  total: 3
+  note: \"multi
+line: 1\"
+  pages[2]:
+    - n: 1
+    - n: 2
+  nested:
+    deep: x
+    other: y
+log: |
+  not: an entry
+
+tags[2]: a,b
+\"my key\": 1
+count: 2";
+    let run = |keys: &[&str]| {
+        let options = DecodeOptions::new().include_keys(keys);
+        let value = decode(toon, Some(&options)).unwrap();
+        let streamed = decode_stream(toon.as_bytes(), Some(&options)).unwrap();
+        assert_eq!(value, streamed, "{keys:?}");
+        value
+    };
+
+    assert_eq!(
+        run(&["items", "meta.total"]),
+        json!({
+            "items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}],
+            "meta": {"total": 3}
+        })
+    );
+    assert_eq!(
+        run(&["meta.nested.other", "count", "my key"]),
+        json!({"meta": {"nested": {"other": "y"}}, "count": 2, "my key": 1})
+    );
+    assert_eq!(
+        run(&["meta.pages", "tags", "log"]),
+        json!({
+            "meta": {"pages": [{"n": 1}, {"n": 2}]},
+            "tags": ["a", "b"],
+            "log": "not: an entry\n"
+        })
+    );
+    // Paths through arrays and primitives, or to nothing, select nothing
+    assert_eq!(run(&["items.sku", "name.x", "missing"]), json!({}));
+    assert_eq!(run(&[]), json!({}));
+
+    // Skipped entries are never parsed, so their errors go unnoticed
+    let broken = "bad[2]: 1\nwanted: yes\nworse:\n  x[3]: 1";
+    let options = DecodeOptions::new().include_keys(["wanted"]);
+    assert_eq!(
+        decode(broken, Some(&options)).unwrap(),
+        json!({"wanted": "yes"})
+    );
+    assert_eq!(
+        decode_stream(broken.as_bytes(), Some(&options)).unwrap(),
+        json!({"wanted": "yes"})
+    );
+
+    // Folded keys match the paths they stand for
+    let folded = "a.b: 1\na.c: 2";
+    let options = DecodeOptions::new()
+        .include_keys(["a.b"])
+        .expand_paths(true);
+    assert_eq!(
+        decode(folded, Some(&options)).unwrap(),
+        json!({"a": {"b": 1}})
+    );
+    // Root arrays are decoded whole
+    assert_eq!(decode("[2]: 1,2", Some(&options)).unwrap(), json!([1, 2]));
+}
+
+#[test]
+fn test_decode_include_columns() {
+    use toon_rust::{decode_iter, decode_rows, decode_rows_stream, decode_stream};
+
+    let toon = "items[2]{sku,qty,note}:\n  A1,2,x\n  B2,1,\"y,z\"\nmeta:\n  sku: kept";
+    let options = DecodeOptions::new().include_columns(["sku", "note"]);
+    let expected = json!({
+        "items": [{"sku": "A1", "note": "x"}, {"sku": "B2", "note": "y,z"}],
+        "meta": {"sku": "kept"}
+    });
+    assert_eq!(decode(toon, Some(&options)).unwrap(), expected);
+    assert_eq!(
+        decode_stream(toon.as_bytes(), Some(&options)).unwrap(),
+        expected
+    );
+
+    let rows: Vec<_> = decode_rows(toon, "items", Some(&options))
+        .map(|row| serde_json::Value::Object(row.unwrap()))
+        .collect();
+    assert_eq!(rows, expected["items"].as_array().unwrap().clone());
+    let streamed: Vec<_> = decode_rows_stream(toon.as_bytes(), "items", Some(&options))
+        .map(|row| serde_json::Value::Object(row.unwrap()))
+        .collect();
+    assert_eq!(streamed, rows);
+
+    // Cells that aren't decoded can't fail, but row lengths are still checked
+    let only_id = DecodeOptions::new().include_columns(["id"]);
+    let items: Vec<_> = decode_iter("[2]{id,n}:\n  1,0x\n  2,\"bad", Some(&only_id))
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(items, [json!({"id": 1}), json!({"id": 2})]);
+    assert!(decode("[1]{id,n}:\n  1", Some(&only_id)).is_err());
+
+    let none = DecodeOptions::new().include_columns(Vec::<String>::new());
+    assert_eq!(
+        decode("[2]{id}:\n  1\n  2", Some(&none)).unwrap(),
+        json!([{}, {}])
+    );
+}
+
+#[test]
+fn test_decode_row_filter() {
+    use toon_rust::options::RawRow;
+    use toon_rust::{decode_iter, decode_rows, decode_rows_stream, decode_stream};
+
+    let toon = "items[3]{sku,qty}:\n  A1,2\n  \"B 2\",0\n  C3,5\nmeta:\n  owner: ann";
+    let options = DecodeOptions::new().row_filter(|row: &RawRow| row.get("qty") != Some("0"));
+    let expected = json!({
+        "items": [{"sku": "A1", "qty": 2}, {"sku": "C3", "qty": 5}],
+        "meta": {"owner": "ann"}
+    });
+    assert_eq!(decode(toon, Some(&options)).unwrap(), expected);
+    assert_eq!(
+        decode_stream(toon.as_bytes(), Some(&options)).unwrap(),
+        expected
+    );
+
+    let rows: Vec<_> = decode_rows(toon, "items", Some(&options))
+        .map(|row| serde_json::Value::Object(row.unwrap()))
+        .collect();
+    assert_eq!(rows, expected["items"].as_array().unwrap().clone());
+    let streamed: Vec<_> = decode_rows_stream(toon.as_bytes(), "items", Some(&options))
+        .map(|row| serde_json::Value::Object(row.unwrap()))
+        .collect();
+    assert_eq!(streamed, rows);
+
+    // Cells are seen as written, quotes and all
+    let quoted = DecodeOptions::new().row_filter(|row: &RawRow| row.cell(0) == Some("\"B 2\""));
+    let items: Vec<_> = decode_iter("[2]{sku}:\n  A1\n  \"B 2\"", Some(&quoted))
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(items, [json!({"sku": "B 2"})]);
+
+    // Rejected rows still count towards the declared length
+    let none = DecodeOptions::new().row_filter(|_: &RawRow| false);
+    assert_eq!(
+        decode("[2]{id}:\n  1\n  2", Some(&none)).unwrap(),
+        json!([])
+    );
+    assert!(decode("[3]{id}:\n  1\n  2", Some(&none)).is_err());
+    assert!(decode_stream("[3]{id}:\n  1\n  2".as_bytes(), Some(&none)).is_err());
+}
+
+#[test]
+fn test_decode_lenient_elision() {
+    use toon_rust::{decode_rows, decode_rows_stream, decode_stream, decode_with_stats};
+
+    let lenient = DecodeOptions::new().strict(false);
+    let cut = "items[100]{id,name}:\n  1,a\n  2,b\n  ...\nnext:\n  rows[9]{id}:\n    1\n    … (truncated)\ncount: 2";
+    let expected = json!({
+        "items": [{"id": 1, "name": "a"}, {"id": 2, "name": "b"}],
+        "next": {"rows": [{"id": 1}]},
+        "count": 2
+    });
+    assert_eq!(decode(cut, Some(&lenient)).unwrap(), expected);
+    assert_eq!(
+        decode_stream(cut.as_bytes(), Some(&lenient)).unwrap(),
+        expected
+    );
+    assert_eq!(decode_rows(cut, "items", Some(&lenient)).count(), 2);
+    assert_eq!(
+        decode_rows_stream(cut.as_bytes(), "items", Some(&lenient)).count(),
+        2
+    );
+
+    // The lengths the tables were meant to have are kept with the stats
+    let (value, stats) = decode_with_stats(cut.as_bytes(), Some(&lenient)).unwrap();
+    assert_eq!(value, expected);
+    assert_eq!(
+        stats.truncated,
+        [("items".to_string(), 100), ("next.rows".to_string(), 9)]
+    );
+    let preview = DecodeOptions::new().allow_elided_rows(true);
+    let (_, stats) = decode_with_stats(
+        "[3]{id}:\n  1\n  … (2 more rows)".as_bytes(),
+        Some(&preview),
+    )
+    .unwrap();
+    assert_eq!(stats.truncated, [(String::new(), 3)]);
+    let unsized_arrays = lenient.clone().lenient_arrays(true);
+    let (_, stats) = decode_with_stats(
+        "items{id}:\n  1\n  … (4 more rows)".as_bytes(),
+        Some(&unsized_arrays),
+    )
+    .unwrap();
+    assert_eq!(stats.truncated, [("items".to_string(), 5)]);
+
+    // Strict decoding takes only markers that account for every row, and
+    // only when allowed
+    assert!(decode(cut, None).is_err());
+    assert!(decode("[3]{id}:\n  1\n  … (1 more row)", Some(&lenient)).is_ok());
+    assert!(decode("[3]{id}:\n  1\n  … (1 more row)", Some(&preview)).is_err());
+    let elided = "x[3]{a}:\n  1\n  … (2 more rows)";
+    let mismatch = |err: toon_rust::Error| {
+        matches!(
+            err,
+            toon_rust::Error::LengthMismatch {
+                expected: 3,
+                found: 1
+            }
+        )
+    };
+    assert!(mismatch(decode(elided, None).unwrap_err()));
+    assert!(mismatch(
+        decode_stream(elided.as_bytes(), None).unwrap_err()
+    ));
+    assert!(decode_rows(elided, "x", None).any(|row| row.is_err_and(mismatch)));
+    assert!(decode_rows_stream(elided.as_bytes(), "x", None).any(|row| row.is_err_and(mismatch)));
+    let expected = json!({"x": [{"a": 1}]});
+    assert_eq!(decode(elided, Some(&preview)).unwrap(), expected);
+    assert_eq!(
+        decode_stream(elided.as_bytes(), Some(&preview)).unwrap(),
+        expected
+    );
+    assert_eq!(decode_rows(elided, "x", Some(&preview)).count(), 1);
+    assert_eq!(
+        decode_rows_stream(elided.as_bytes(), "x", Some(&preview)).count(),
+        1
+    );
+}
+
+#[test]
+fn test_decode_typed_headers() {
+    use toon_rust::{decode_rows, decode_stream, dom};
+
+    let toon = "items[3]{sku:str,qty:int,price:float,gift:bool}:\n  04532,2,3,true\n  \"B2\",,1.5,false\n  true,-1,null,true";
+    let expected = json!({"items": [
+        {"sku": "04532", "qty": 2, "price": 3.0, "gift": true},
+        {"sku": "B2", "qty": null, "price": 1.5, "gift": false},
+        {"sku": "true", "qty": -1, "price": null, "gift": true}
+    ]});
+    assert_eq!(decode(toon, None).unwrap(), expected);
+    assert_eq!(decode_stream(toon.as_bytes(), None).unwrap(), expected);
+    assert_eq!(dom::parse(toon, None).unwrap().value(), expected);
+    let rows: Vec<_> = decode_rows(toon, "items", None)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(json!(rows), expected["items"]);
+    let options = DecodeOptions::new().coerce_numbers(false);
+    assert_eq!(decode(toon, Some(&options)).unwrap(), expected);
+
+    // Cells of another type, and unknown types, are errors
+    for bad in [
+        "items[1]{qty:int}:\n  1.5",
+        "items[1]{qty:int}:\n  \"2\"",
+        "items[1]{price:float}:\n  cheap",
+        "items[1]{gift:bool}:\n  yes",
+        "items[1]{qty:integer}:\n  1",
+    ] {
+        assert!(decode(bad, None).is_err(), "{bad}");
+        assert!(decode_stream(bad.as_bytes(), None).is_err(), "{bad}");
+        assert!(dom::parse(bad, None).is_err(), "{bad}");
+    }
+    assert!(decode_rows("items[1]{qty:int}:\n  x", "items", None).any(|row| row.is_err()));
+
+    // A quoted field name keeps its colon, with the type after the quote
+    let quoted = decode("t[1]{\"a:b\":int,c}:\n  1,2", None).unwrap();
+    assert_eq!(quoted, json!({"t": [{"a:b": 1, "c": 2}]}));
+}
+
+#[test]
+fn test_decode_column_defaults() {
+    use toon_rust::options::LengthPolicy;
+    use toon_rust::{decode_rows, decode_stream, dom};
+
+    let toon = "items[3]{sku,region=EU,qty:int=1}:\n  A1,,\n  B2,US,null\n  C3,,4";
+    let expected = json!({"items": [
+        {"sku": "A1", "region": "EU", "qty": 1},
+        {"sku": "B2", "region": "US", "qty": null},
+        {"sku": "C3", "region": "EU", "qty": 4}
+    ]});
+    assert_eq!(decode(toon, None).unwrap(), expected);
+    assert_eq!(decode_stream(toon.as_bytes(), None).unwrap(), expected);
+    let document = dom::parse(toon, None).unwrap();
+    assert_eq!(document.value(), expected);
+    let rows: Vec<_> = decode_rows(toon, "items", None)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(json!(rows), expected["items"]);
+
+    // Padded rows take the defaults of their missing cells
+    let options = DecodeOptions::new().length_policy(LengthPolicy::Pad);
+    let short = decode("t[1]{a,b=x,c=2}:\n  1", Some(&options)).unwrap();
+    assert_eq!(short, json!({"t": [{"a": 1, "b": "x", "c": 2}]}));
+
+    // A default must be given, and be of its column's type
+    for bad in ["t[1]{a,b=}:\n  1,2", "t[1]{a,b:int=x}:\n  1,2"] {
+        assert!(decode(bad, None).is_err(), "{bad}");
+        assert!(decode_stream(bad.as_bytes(), None).is_err(), "{bad}");
+        assert!(dom::parse(bad, None).is_err(), "{bad}");
+    }
+
+    // A quoted field name keeps its `=`
+    let quoted = decode("t[1]{\"a=b\",c}:\n  1,2", None).unwrap();
+    assert_eq!(quoted, json!({"t": [{"a=b": 1, "c": 2}]}));
+}
+
+#[test]
+fn test_decode_dictionary_columns() {
+    use toon_rust::{decode_rows, decode_rows_stream, decode_stream, dom, resume_rows_stream};
+
+    let toon = "orders[4]{id,status:str@[shipped; \"on hold\";pending]=none}:\n  1,0\n  2,2\n  3,\n  4,null\n";
+    let expected = json!({"orders": [
+        {"id": 1, "status": "shipped"},
+        {"id": 2, "status": "pending"},
+        {"id": 3, "status": "none"},
+        {"id": 4, "status": null}
+    ]});
+    assert_eq!(decode(toon, None).unwrap(), expected);
+    assert_eq!(decode_stream(toon.as_bytes(), None).unwrap(), expected);
+    let document = dom::parse(toon, None).unwrap();
+    assert_eq!(document.value(), expected);
+    let rows: Vec<_> = decode_rows(toon, "orders", None)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(json!(rows), expected["orders"]);
+
+    // The dictionary survives a checkpoint
+    let mut rows = decode_rows_stream(toon.as_bytes(), "orders", None);
+    rows.next();
+    let checkpoint = rows.checkpoint().to_string().parse().unwrap();
+    let rest = &toon.as_bytes()[rows.checkpoint().offset()..];
+    let row = resume_rows_stream(rest, &checkpoint, None).next().unwrap();
+    assert_eq!(json!(row.unwrap()), expected["orders"][1]);
+
+    // Codes must be in the dictionary, and entries not empty
+    for bad in [
+        "t[1]{a,b@[x;y]}:\n  1,2",
+        "t[1]{a,b@[x;y]}:\n  1,x",
+        "t[1]{a,b@[x;;y]}:\n  1,0",
+        "t[1]{a,b@[x}:\n  1,0",
+    ] {
+        assert!(decode(bad, None).is_err(), "{bad}");
+        assert!(decode_stream(bad.as_bytes(), None).is_err(), "{bad}");
+        assert!(dom::parse(bad, None).is_err(), "{bad}");
+    }
+
+    // An `@` not opening a dictionary belongs to the name
+    let named = decode("t[1]{a@b,c}:\n  1,2", None).unwrap();
+    assert_eq!(named, json!({"t": [{"a@b": 1, "c": 2}]}));
+}
+
+#[test]
+fn test_decode_spec_version() {
+    use toon_rust::options::SpecVersion;
+
+    let v1 = DecodeOptions::new().spec_version(SpecVersion::V1);
+    let v2 = DecodeOptions::new().spec_version(SpecVersion::V2);
+    let marked = "items[#2]{sku,qty}:\n  A1,2\n  B2,1\ntags[#1]: x";
+    let expected =
+        json!({"items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}], "tags": ["x"]});
+
+    // Without a revision, documents of either are read
+    assert_eq!(decode(marked, None).unwrap(), expected);
+    assert_eq!(decode(&marked.replace('#', ""), None).unwrap(), expected);
+    assert_eq!(decode(marked, Some(&v1)).unwrap(), expected);
+    assert_eq!(
+        decode(&marked.replace('#', ""), Some(&v2)).unwrap(),
+        expected
+    );
+
+    let err = decode(marked, Some(&v2)).unwrap_err();
+    assert!(
+        err.to_string()
+            .ends_with("line 1, column 7: `#` length markers aren't part of TOON spec 2"),
+        "{err}"
+    );
+    let err = decode_stream(marked.as_bytes(), Some(&v2)).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("`#` length markers aren't part of TOON spec 2"));
+
+    // Header annotations are this crate's own
+    for header in [
+        "t[1]{a:int,b}:\n  1,2",
+        "t[1]{a,b=2}:\n  1,",
+        "t[1]{a,b@[x]}:\n  1,0",
+    ] {
+        assert!(decode(header, None).is_ok());
+        let err = decode(header, Some(&v1)).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("column types, defaults and dictionaries aren't part of TOON spec 1"),
+            "{err}"
+        );
+    }
+}
+
+#[test]
+fn test_decode_options_from_toon_js() {
+    let config = json!({"indent": 4, "strict": false, "expandPaths": "safe", "delimiter": "|"});
+    let options = DecodeOptions::from_toon_js(&config).unwrap();
+    assert_eq!(options.get_indent(), 4);
+    assert!(!options.get_strict());
+    let value = decode("a.b:\n    c: 1\nt[3]: x,y", Some(&options)).unwrap();
+    assert_eq!(value, json!({"a": {"b": {"c": 1}}, "t": ["x", "y", null]}));
+
+    let options = DecodeOptions::from_toon_js(&json!({"expandPaths": "off"})).unwrap();
+    assert!(options.get_strict());
+    assert!(!options.get_expand_paths());
+
+    for (config, message) in [
+        (json!({"strict": "yes"}), "at `strict`: expected a boolean"),
+        (
+            json!({"expandPaths": true}),
+            "at `expandPaths`: expected \"off\" or \"safe\"",
+        ),
+        (json!({"indnet": 2}), "at `indnet`: unknown option"),
+    ] {
+        let err = DecodeOptions::from_toon_js(&config).unwrap_err();
+        assert!(err.to_string().ends_with(message), "{err}");
+    }
+}
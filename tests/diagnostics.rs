@@ -0,0 +1,26 @@
+#![cfg(feature = "diagnostics")]
+
+use miette::Diagnostic;
+use toon_rust::decode;
+
+#[test]
+fn test_parse_error_diagnostic() {
+    let toon = "name: Alice\nuser:\n  id 1";
+    let err = decode(toon, None).unwrap_err();
+
+    assert_eq!(err.code().unwrap().to_string(), "toon_rust::parse");
+    let labels: Vec<_> = err.labels().unwrap().collect();
+    assert_eq!(labels.len(), 1);
+    assert_eq!(labels[0].offset(), 23);
+    assert_eq!(labels[0].label(), Some("Expected ':' after key 'id'"));
+
+    let report = miette::Report::new(err).with_source_code(toon);
+    assert!(report.to_string().starts_with("Parse error at line 3"));
+}
+
+#[test]
+fn test_non_parse_error_has_no_labels() {
+    let err = toon_rust::Error::Serialization("boom".to_string());
+    assert_eq!(err.code().unwrap().to_string(), "toon_rust::serialization");
+    assert!(err.labels().is_none());
+}
@@ -0,0 +1,356 @@
+//! Tests for the lossless syntax tree
+
+use serde_json::json;
+use toon_rust::dom::{self, Items, Node};
+use toon_rust::options::{Delimiter, DuplicateKeyPolicy, EmptyCellPolicy, LengthPolicy};
+use toon_rust::{decode, encode, DecodeOptions, EncodeOptions};
+
+#[test]
+fn test_dom_value_matches_decode() {
+    let data = json!({
+        "name": "shop",
+        "empty": {},
+        "none": null,
+        "tags": ["a", "b,c", ""],
+        "nothing": [],
+        "note": "line 1\nline 2\n",
+        "nested": {"deep": {"x": 1.5, "y": "-dash"}},
+        "items": [
+            {"sku": "A1", "qty": 2, "note": "x,y"},
+            {"sku": "B2", "qty": 1, "note": null}
+        ],
+        "list": [
+            [{"a": 1}, {"a": 2}],
+            [1, 2],
+            {"w": "a\nb", "x": [{"a": 1}], "y": {"z": 1}},
+            [[1], [2]],
+            "s",
+            {"only": true}
+        ],
+        "quoted key": "\"hi\""
+    });
+    let options = [
+        EncodeOptions::new(),
+        EncodeOptions::new().block_strings(true),
+        EncodeOptions::new().delimiter(Delimiter::Pipe),
+        EncodeOptions::new().delimiter(Delimiter::Tab).indent(4),
+    ];
+    for options in &options {
+        let toon = encode(&data, Some(options)).unwrap();
+        let decode_options = toon_rust::DecodeOptions::new().indent(options.get_indent());
+        let doc = dom::parse(&toon, Some(&decode_options)).expect(&toon);
+        assert_eq!(
+            doc.value(),
+            decode(&toon, Some(&decode_options)).unwrap(),
+            "{toon}"
+        );
+        assert_eq!(doc.to_string(), toon);
+    }
+
+    for toon in [
+        "",
+        "42",
+        "[2]: x,y",
+        "[2]{a}:\n  1\n  2",
+        "[1]:\n  - a: 1\n    b: 2",
+    ] {
+        let doc = dom::parse(toon, None).unwrap();
+        assert_eq!(doc.value(), decode(toon, None).unwrap(), "{toon}");
+    }
+}
+
+#[test]
+fn test_dom_blank_table_rows() {
+    // A null in a one-column table is written as a blank row
+    for data in [
+        json!({"t": [{"x": 1}, {"x": null}, {"x": 2}]}),
+        json!({"t": [{"x": null}, {"x": null}]}),
+        json!([{"x": 1}, {"x": null}]),
+        json!({"a": {"t": [{"x": null}, {"x": "y"}]}, "b": 1}),
+    ] {
+        let toon = encode(&data, None).unwrap();
+        let doc = dom::parse(&toon, None).expect(&toon);
+        assert_eq!(doc.value(), decode(&toon, None).unwrap(), "{toon:?}");
+        assert_eq!(doc.value(), data);
+        assert_eq!(doc.to_string(), toon);
+    }
+}
+
+#[test]
+fn test_dom_value_follows_decode_options() {
+    let cases = [
+        (
+            "a:\n\tb: 1\n\tc[2]{x,y}:\n\t\t1,2\n\t\t3,4",
+            DecodeOptions::new().indent_string("\t"),
+        ),
+        (
+            "a:\n   b: 1\n   c:\n      d: 2  \n\ne: 3",
+            DecodeOptions::new().strict(false),
+        ),
+        (
+            "tags: a,b\nlist:\n  - x\n  - y\nname: shop",
+            DecodeOptions::new().lenient_arrays(true),
+        ),
+        (
+            "a.b: 1\na.c: 2\n\"d.e\": 3\nrows[1]{x.y,z}:\n  1,2",
+            DecodeOptions::new().expand_paths(true),
+        ),
+        (
+            "rows[2]{a,b}:\n  1,\n  ,2",
+            DecodeOptions::new().empty_cells(EmptyCellPolicy::Omit),
+        ),
+        (
+            "a: 1\na: 2",
+            DecodeOptions::new().duplicate_keys(DuplicateKeyPolicy::FirstWins),
+        ),
+        (
+            "a: 1\na: 2",
+            DecodeOptions::new().duplicate_keys(DuplicateKeyPolicy::LastWins),
+        ),
+        (
+            "rows[2]{a,b}:\n  1\n  2,3,4",
+            DecodeOptions::new().length_policy(LengthPolicy::Pad),
+        ),
+        (
+            "rows[2]{a,b}:\n  1\n  2,3,4",
+            DecodeOptions::new().length_policy(LengthPolicy::Truncate),
+        ),
+        (
+            "rows[1]{a,b}:\n  1,2,3,4",
+            DecodeOptions::new()
+                .length_policy(LengthPolicy::Pad)
+                .extra_cells_key("rest"),
+        ),
+    ];
+    for (toon, options) in &cases {
+        let doc = dom::parse(toon, Some(options)).expect(toon);
+        assert_eq!(doc.value(), decode(toon, Some(options)).unwrap(), "{toon}");
+        assert_eq!(doc.to_string(), *toon);
+    }
+
+    // Spans point into the source, whatever the indentation was read as
+    let toon = "a:\n\tb: x\n";
+    let doc = dom::parse(toon, Some(&DecodeOptions::new().indent_string("\t"))).unwrap();
+    let Node::Object(root) = doc.root() else {
+        panic!("expected an object")
+    };
+    let Node::Object(a) = &root.entries[0].value else {
+        panic!("expected an object")
+    };
+    assert_eq!(&toon[a.entries[0].key.span.clone()], "b");
+    assert_eq!(&toon[a.entries[0].span.clone()], "b: x");
+
+    let err = dom::parse("a:\n\tb 1", Some(&DecodeOptions::new().indent_string("\t"))).unwrap_err();
+    assert!(
+        matches!(err, toon_rust::Error::Parse { line: 2, .. }),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_dom_spans() {
+    let toon = "name: \"shop\"\nitems[2]{sku|\"unit price\"}:\n  A1|9.5\n  B2|\nnote: |\n  hi\n\ntags[2]: x,y\nlist[2]:\n  - id: 1\n    ok: true\n  - [1]: z";
+    let doc = dom::parse(toon, None).unwrap();
+    let text = |span: &std::ops::Range<usize>| &toon[span.clone()];
+    let Node::Object(root) = doc.root() else {
+        panic!("expected an object")
+    };
+    let [name, items, note, tags, list] = &root.entries[..] else {
+        panic!("expected five entries")
+    };
+
+    assert_eq!(text(&name.key.span), "name");
+    assert_eq!(text(&name.value.span()), "\"shop\"");
+    assert_eq!(text(&name.span), "name: \"shop\"");
+
+    let Node::Array(table) = &items.value else {
+        panic!("expected an array")
+    };
+    assert_eq!(text(&table.header.span), "[2]{sku|\"unit price\"}:");
+    assert_eq!(
+        (table.header.length, table.header.delimiter),
+        (Some(2), '|')
+    );
+    let fields = table.header.fields.as_ref().unwrap();
+    assert_eq!(text(&fields[1].span), "\"unit price\"");
+    assert_eq!(fields[1].name, "unit price");
+    let Items::Table(rows) = &table.items else {
+        panic!("expected a table")
+    };
+    assert_eq!(text(&rows[0].span), "A1|9.5");
+    assert_eq!(text(&rows[0].cells[1].span), "9.5");
+    // An empty cell has an empty span where it would be
+    assert_eq!(
+        rows[1].cells[1].span,
+        toon.find("B2|").unwrap() + 3..toon.find("B2|").unwrap() + 3
+    );
+    assert_eq!(
+        text(&items.span),
+        "items[2]{sku|\"unit price\"}:\n  A1|9.5\n  B2|"
+    );
+
+    assert!(matches!(note.value, Node::Block(_)));
+    assert_eq!(text(&note.value.span()), "|\n  hi");
+
+    let Node::Array(tags) = &tags.value else {
+        panic!("expected an array")
+    };
+    let Items::Inline(values) = &tags.items else {
+        panic!("expected inline items")
+    };
+    assert_eq!(text(&values[1].span), "y");
+
+    let Node::Array(list) = &list.value else {
+        panic!("expected an array")
+    };
+    let Items::List(list_items) = &list.items else {
+        panic!("expected list items")
+    };
+    assert_eq!(text(&list_items[0].span), "- id: 1\n    ok: true");
+    assert_eq!(text(&list_items[1].span), "- [1]: z");
+    let Node::Object(first) = &list_items[0].value else {
+        panic!("expected an object item")
+    };
+    assert_eq!(text(&first.entries[1].key.span), "ok");
+}
+
+#[test]
+fn test_dom_errors() {
+    let err = dom::parse("a:\n   b: 1", None).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            toon_rust::Error::Parse {
+                line: 2,
+                column: 1,
+                ..
+            }
+        ),
+        "{err}"
+    );
+    let err = dom::parse("a: 1\nitems[x]: 1", None).unwrap_err();
+    assert!(
+        matches!(err, toon_rust::Error::Parse { line: 2, .. }),
+        "{err}"
+    );
+    assert!(dom::parse("a: 1\nb 2", None).is_err());
+
+    // Lengths are kept as written, even where the array disagrees
+    let doc = dom::parse("tags[3]: x,y", None).unwrap();
+    let Node::Object(root) = doc.root() else {
+        panic!("expected an object")
+    };
+    let Node::Array(tags) = &root.entries[0].value else {
+        panic!("expected an array")
+    };
+    assert_eq!(tags.header.length, Some(3));
+    assert!(matches!(&tags.items, Items::Inline(items) if items.len() == 2));
+}
+
+#[test]
+fn test_dom_edit() {
+    let toon = "name:   shop\nitems[2]{sku|\"unit price\"}:\n  A1|9.5\n  B2|\ntags[3]: x, y, z\nlist[2]:\n  - id: 1\n    rows[1]{a,b}:\n      1,2\n  - plain\nempty:";
+    let mut doc = dom::parse(toon, None).unwrap();
+
+    doc.set(&["name"], &json!("a: b")).unwrap();
+    doc.set(&["items", "1", "unit price"], &json!("1|2"))
+        .unwrap();
+    doc.set(&["tags", "1"], &json!(null)).unwrap();
+    doc.set(&["list", "1"], &json!(true)).unwrap();
+    doc.set(&["empty"], &json!(0)).unwrap();
+    assert_eq!(
+        doc.to_string(),
+        "name:   \"a: b\"\nitems[2]{sku|\"unit price\"}:\n  A1|9.5\n  B2|\"1|2\"\ntags[3]: x, null, z\nlist[2]:\n  - id: 1\n    rows[1]{a,b}:\n      1,2\n  - true\nempty: 0"
+    );
+
+    doc.push_row(&["items"], &json!({"sku": "C3"})).unwrap();
+    doc.push_row(&["list", "0", "rows"], &json!({"a": "x,y", "b": 4}))
+        .unwrap();
+    assert_eq!(
+        doc.to_string(),
+        "name:   \"a: b\"\nitems[3]{sku|\"unit price\"}:\n  A1|9.5\n  B2|\"1|2\"\n  C3|\ntags[3]: x, null, z\nlist[2]:\n  - id: 1\n    rows[2]{a,b}:\n      1,2\n      \"x,y\",4\n  - true\nempty: 0"
+    );
+    let value = doc.value();
+    assert_eq!(value, decode(&doc.to_string(), None).unwrap());
+    assert_eq!(value["items"][2], json!({"sku": "C3", "unit price": null}));
+
+    assert!(doc.set(&["list", "0"], &json!(1)).is_err());
+    assert!(doc.set(&["missing"], &json!(1)).is_err());
+    assert!(doc.set(&["name"], &json!([1])).is_err());
+    assert!(doc.push_row(&["tags"], &json!({"a": 1})).is_err());
+    assert!(doc.push_row(&["items"], &json!({"other": 1})).is_err());
+}
+
+#[test]
+fn test_dom_incremental_edit() {
+    let toon = "name: shop\nitems[3]{sku,qty}:\n  A1,2\n  B2,1\n  C3,7\nlist[2]:\n  - id: 1\n    tags[2]: a,b\n  - plain\nnote: |\n  text";
+    let mut doc = dom::parse(toon, None).unwrap();
+
+    // A cell edit parses only its row again
+    let qty = toon.find("B2,1").unwrap() + 3;
+    let reparsed = doc.edit(qty..qty + 1, "10").unwrap();
+    assert_eq!(&doc.source()[reparsed], "B2,10");
+    // Spans after the edit move with it
+    let len = doc.source().len();
+    let reparsed = doc.edit(len - 4..len, "more text").unwrap();
+    assert_eq!(&doc.source()[reparsed], "note: |\n  more text");
+    let tags = doc.source().find("a,b").unwrap();
+    let reparsed = doc.edit(tags..tags + 3, "x,y").unwrap();
+    assert_eq!(&doc.source()[reparsed], "tags[2]: x,y");
+    assert_eq!(doc.root(), dom::parse(doc.source(), None).unwrap().root());
+
+    // A new sibling changes the lines around the section
+    let end = doc.source().find("\nnote").unwrap();
+    let reparsed = doc.edit(end..end, "\nextra: 1").unwrap();
+    assert_eq!(reparsed, 0..doc.source().len());
+    assert_eq!(doc.value()["extra"], json!(1));
+
+    // A failed edit leaves the document as it was
+    let before = doc.source().to_string();
+    assert!(doc.edit(0..4, "  name").is_err());
+    assert!(doc.edit(0..before.len() + 1, "").is_err());
+    assert_eq!(doc.source(), before);
+}
+
+#[test]
+fn test_dom_incremental_edit_matches_parse() {
+    let toon = "\
+a: 1
+obj:
+  b: \"x,y\"
+  deep:
+    c: true
+rows[2]{k|v}:
+  p|1
+  q|2
+list[3]:
+  - k: 1
+    v[2]: 3,4
+  - [2]:
+    - 5
+    - x: 6
+  - last
+note: |
+  block
+z: end";
+    for replacement in ["", "9", "\n", ":", " ", "- ", "\n  w: 1", "\"", "|", "[2]"] {
+        for start in 0..=toon.len() {
+            for end in [start, start + 1, start + 4].map(|end| end.min(toon.len())) {
+                let mut doc = dom::parse(toon, None).unwrap();
+                let mut source = toon.to_string();
+                source.replace_range(start..end, replacement);
+                match dom::parse(&source, None) {
+                    Ok(expected) => {
+                        doc.edit(start..end, replacement).unwrap();
+                        assert_eq!(doc.root(), expected.root(), "{source:?}");
+                        assert_eq!(doc.source(), source);
+                    }
+                    Err(_) => {
+                        assert!(doc.edit(start..end, replacement).is_err(), "{source:?}");
+                        assert_eq!(doc.source(), toon);
+                    }
+                }
+            }
+        }
+    }
+}
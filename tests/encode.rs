@@ -121,3 +121,664 @@ fn test_encode_boolean_string_vs_boolean() {
     assert!(result.contains("\"true\""));
     assert!(result.contains(",true"));
 }
+
+#[test]
+fn test_encode_float_precision() {
+    let data = json!({
+        "whole": 2.0,
+        "pi": std::f64::consts::PI
+    });
+    let result = encode(&data, None).unwrap();
+    assert!(result.contains("whole: 2.0"));
+    assert!(result.contains("pi: 3.141592653589793"));
+
+    let options = EncodeOptions::new().float_precision(2);
+    let result = encode(&data, Some(&options)).unwrap();
+    assert!(result.contains("whole: 2.00"));
+    assert!(result.contains("pi: 3.14"));
+}
+
+#[test]
+fn test_encode_value_transformer() {
+    use serde_json::Value;
+
+    let data = json!({
+        "token": "secret",
+        "items": [
+            {"sku": "A1", "price": 9.999},
+            {"sku": "B2", "price": 14.5}
+        ]
+    });
+    let options =
+        EncodeOptions::new().value_transformer(|key: Option<&str>, value: &Value| match key {
+            Some("token") => Some(json!("REDACTED")),
+            Some("price") => value.as_f64().map(|f| json!((f * 100.0).round() / 100.0)),
+            _ => None,
+        });
+    let result = encode(&data, Some(&options)).unwrap();
+    assert!(result.contains("token: REDACTED"));
+    assert!(result.contains("A1,10.0") || result.contains("10.0,A1"));
+
+    let mut buffer = Vec::new();
+    toon_rust::encode_stream(&data, &mut buffer, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), result);
+}
+
+#[test]
+fn test_encode_escape_non_ascii() {
+    let data = json!({"city": "Zürich", "emoji": "😀", "plain": "abc"});
+    let options = EncodeOptions::new().escape_non_ascii(true);
+    let result = encode(&data, Some(&options)).unwrap();
+    assert_eq!(
+        result,
+        "city: \"Z\\u00fcrich\"\nemoji: \"\\ud83d\\ude00\"\nplain: abc"
+    );
+    assert!(result.is_ascii());
+    assert_eq!(toon_rust::decode(&result, None).unwrap(), data);
+
+    // Off by default
+    assert_eq!(
+        encode(&data, None).unwrap(),
+        "city: Zürich\nemoji: 😀\nplain: abc"
+    );
+}
+
+#[test]
+fn test_encode_block_strings() {
+    let data = json!({
+        "user": {"bio": "line one\n  indented\n\nline four"},
+        "poem": "roses\nviolets\n",
+        "spaced": " leading\nspace",
+        "pipe": "|"
+    });
+    let options = EncodeOptions::new().block_strings(true);
+    let result = encode(&data, Some(&options)).unwrap();
+    assert_eq!(
+        result,
+        "pipe: \"|\"\npoem: |\n  roses\n  violets\nspaced: \" leading\\nspace\"\nuser: \n  bio: |-\n    line one\n      indented\n\n    line four"
+    );
+
+    let mut streamed = Vec::new();
+    toon_rust::encode_stream(&data, &mut streamed, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(streamed).unwrap(), result);
+}
+
+#[test]
+fn test_encode_quoting_policy() {
+    use toon_rust::decode;
+    use toon_rust::options::QuotingPolicy;
+
+    let data = json!({
+        "city": "New York",
+        "list": "a,b",
+        "date": "2024-01-01",
+        "empty": "",
+        "flag": "true",
+        "rows": [{"city": "New York", "note": " padded "}, {"city": "Oslo", "note": "a,b"}]
+    });
+
+    // Minimal quotes anything ambiguous in any position, including strings
+    // the decoder would read as a number or array header
+    let toon = encode(&data, None).unwrap();
+    assert!(toon.contains("city: \"New York\""));
+    assert!(toon.contains("list: \"a,b\""));
+    assert!(toon.contains("date: \"2024-01-01\""));
+    assert!(toon.contains("empty: \"\""));
+    assert!(toon.contains("\"New York\",\" padded \""));
+    assert_eq!(decode(&toon, None).unwrap(), data);
+    assert_eq!(
+        encode(&json!({"a": "[x]", "b": "1abc"}), None).unwrap(),
+        "a: \"[x]\"\nb: \"1abc\""
+    );
+
+    let options = EncodeOptions::new().quoting(QuotingPolicy::WhenAmbiguous);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("list: a,b"));
+    assert!(toon.contains("New York,\" padded \""));
+    assert!(toon.contains("Oslo,\"a,b\""));
+    assert_eq!(decode(&toon, None).unwrap(), data);
+
+    let options = EncodeOptions::new().quoting(QuotingPolicy::Always);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("rows[2]{city,note}:\n  \"New York\",\" padded \"\n  \"Oslo\",\"a,b\""));
+    assert_eq!(decode(&toon, None).unwrap(), data);
+
+    // Dropping safety for tokens is lossy by design
+    let options = EncodeOptions::new()
+        .quoting(QuotingPolicy::WhenAmbiguous)
+        .quote_padded_strings(false)
+        .quote_reserved_words(false);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("flag: true"));
+    assert!(toon.contains("New York, padded "));
+    let decoded = decode(&toon, None).unwrap();
+    assert_eq!(decoded["flag"], json!(true));
+    assert_eq!(decoded["rows"][0]["note"], json!("padded"));
+}
+
+#[test]
+fn test_encode_align_columns() {
+    use toon_rust::options::Delimiter;
+    use toon_rust::{decode, decode_stream, encode_stream};
+
+    let data = json!({"users": [
+        {"id": 1, "name": "Alice", "role": "admin"},
+        {"id": 100, "name": "Bob", "role": "user"},
+        {"id": 7, "name": "Zoë Lee", "role": null}
+    ]});
+    let options = EncodeOptions::new().align_columns(true);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert_eq!(
+        toon,
+        "users[3]{id,name,role}:\n  1,  Alice,    admin\n  100,Bob,      user\n  7,  \"Zoë Lee\",\n"
+    );
+    assert_eq!(decode(&toon, None).unwrap(), data);
+    assert_eq!(decode_stream(toon.as_bytes(), None).unwrap(), data);
+
+    let mut buffer = Vec::new();
+    encode_stream(&data, &mut buffer, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), toon);
+
+    let options = options.delimiter(Delimiter::Tab);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("1\t  Alice\t    admin"));
+}
+
+#[test]
+fn test_encode_tabular_thresholds() {
+    use toon_rust::decode;
+
+    let mostly = json!({"rows": [
+        {"id": 1, "name": "a"},
+        {"id": 2, "name": "b"},
+        {"id": 3, "name": "c"},
+        {"id": 4}
+    ]});
+
+    // Uneven objects fall back to the list format by default
+    assert!(!encode(&mostly, None).unwrap().contains('{'));
+
+    let options = EncodeOptions::new().tabular_uniformity(0.75);
+    let toon = encode(&mostly, Some(&options)).unwrap();
+    assert_eq!(toon, "rows[4]{id,name}:\n  1,a\n  2,b\n  3,c\n  4,\n");
+    assert_eq!(
+        decode(&toon, None).unwrap()["rows"][3],
+        json!({"id": 4, "name": null})
+    );
+    let mut buffer = Vec::new();
+    toon_rust::encode_stream(&mostly, &mut buffer, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), toon);
+
+    let options = EncodeOptions::new().tabular_uniformity(0.8);
+    assert!(!encode(&mostly, Some(&options)).unwrap().contains('{'));
+
+    let uniform = json!({"rows": [{"id": 1, "name": "a"}, {"id": 2, "name": "b"}]});
+    let options = EncodeOptions::new().min_tabular_rows(3);
+    let toon = encode(&uniform, Some(&options)).unwrap();
+    assert!(toon.starts_with("rows[2]:\n  - id: 1"));
+    assert_eq!(decode(&toon, None).unwrap(), uniform);
+
+    let options = EncodeOptions::new().max_tabular_columns(1);
+    assert!(!encode(&uniform, Some(&options)).unwrap().contains('{'));
+    let options = EncodeOptions::new().max_tabular_columns(2);
+    assert!(encode(&uniform, Some(&options))
+        .unwrap()
+        .contains("rows[2]{id,name}:"));
+}
+
+#[test]
+fn test_encode_canonical() {
+    use toon_rust::encode_canonical;
+
+    let mut first = serde_json::Map::new();
+    first.insert("z".into(), json!(1));
+    first.insert("a".into(), json!({"y": -0.0, "b": [{"k": 2, "j": 1}]}));
+    let mut second = serde_json::Map::new();
+    second.insert("a".into(), json!({"b": [{"j": 1, "k": 2}], "y": 0.0}));
+    second.insert("z".into(), json!(1));
+
+    let canonical = encode_canonical(&serde_json::Value::Object(first)).unwrap();
+    assert_eq!(
+        canonical,
+        encode_canonical(&serde_json::Value::Object(second)).unwrap()
+    );
+    assert_eq!(canonical, "a: \n  b[1]{j,k}:\n    1,2\n\n  y: 0\nz: 1");
+}
+
+#[test]
+fn test_encode_optimize_for_tokens() {
+    let options = EncodeOptions::new().optimize_for_tokens(true);
+
+    // Mostly uniform: a sparse table beats the list the default rules pick
+    let mostly = json!({"rows": [
+        {"id": 1, "name": "a"},
+        {"id": 2, "name": "b"},
+        {"id": 3}
+    ]});
+    assert!(!encode(&mostly, None).unwrap().contains('{'));
+    let toon = encode(&mostly, Some(&options)).unwrap();
+    assert_eq!(toon, "rows[3]{id,name}:\n  1,a\n  2,b\n  3,\n");
+
+    // Disjoint keys: empty cells would cost more than the list
+    let disjoint = json!({"rows": [{"a": 1}, {"b": 2}, {"c": 3}, {"d": 4}]});
+    let toon = encode(&disjoint, Some(&options)).unwrap();
+    assert!(toon.starts_with("rows[4]:\n  - a: 1"));
+    let relaxed = EncodeOptions::new().tabular_uniformity(0.0);
+    assert!(encode(&disjoint, Some(&relaxed)).unwrap().len() > toon.len());
+
+    let mut buffer = Vec::new();
+    toon_rust::encode_stream(&mostly, &mut buffer, Some(&options)).unwrap();
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        encode(&mostly, Some(&options)).unwrap()
+    );
+}
+
+#[test]
+fn test_encode_large_table() {
+    // Large enough to be formatted in parallel chunks with the `parallel` feature
+    let rows: Vec<_> = (0..10_000)
+        .map(|i| match i % 500 {
+            0 => json!({"id": i, "name": null, "tag": "a,b"}),
+            _ => json!({"id": i, "name": format!("user {i}"), "tag": i % 7}),
+        })
+        .collect();
+    let data = json!({ "rows": rows });
+    let toon = encode(&data, None).unwrap();
+
+    let mut expected = String::from("rows[10000]{id,name,tag}:\n");
+    for row in &rows {
+        let single = encode(&json!({ "rows": [row] }), None).unwrap();
+        expected.push_str(single.split_once('\n').unwrap().1);
+    }
+    assert_eq!(toon, expected);
+    assert_eq!(toon_rust::decode(&toon, None).unwrap(), data);
+}
+
+#[test]
+fn test_encode_sort_table_by() {
+    use toon_rust::encode_stream;
+    use toon_rust::options::Order;
+
+    let value = json!({
+        "shop": {"items": [
+            {"sku": "C3", "price": 2},
+            {"sku": "A1", "price": 5},
+            {"sku": "B2", "price": 2},
+            {"sku": "D4", "price": null}
+        ]},
+        "tags": ["b", "a"]
+    });
+    let options = EncodeOptions::new()
+        .sort_table_by("shop.items", "price", Order::Desc)
+        .sort_table_by("shop.items", "sku", Order::Asc)
+        .sort_table_by("tags", "none", Order::Asc)
+        .sort_table_by("missing", "price", Order::Asc);
+    let toon = encode(&value, Some(&options)).unwrap();
+    assert_eq!(
+        toon,
+        "shop: \n  items[4]{price,sku}:\n    5,A1\n    2,B2\n    2,C3\n    ,D4\n\ntags[2]:b,a"
+    );
+    let mut streamed = Vec::new();
+    encode_stream(&value, &mut streamed, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(streamed).unwrap(), toon);
+
+    let root = json!([{"id": 2}, {"id": 1}]);
+    let options = EncodeOptions::new().sort_table_by("", "id", Order::Asc);
+    assert_eq!(
+        encode(&root, Some(&options)).unwrap(),
+        "[2]{id}:\n  1\n  2\n"
+    );
+}
+
+#[test]
+fn test_encode_max_rows_per_table() {
+    use toon_rust::{decode, decode_rows, decode_rows_stream, decode_stream, validate_stream};
+
+    let rows: Vec<_> = (1..=5).map(|id| json!({"id": id})).collect();
+    let value = json!({"a": rows, "b": [{"id": 9}], "c": 1});
+    let options = EncodeOptions::new().max_rows_per_table(2);
+    let toon = encode(&value, Some(&options)).unwrap();
+    assert_eq!(
+        toon,
+        "a[5]{id}:\n  1\n  2\n  … (3 more rows)\n\nb[1]{id}:\n  9\n\nc: 1"
+    );
+    let aligned = options.clone().align_columns(true);
+    assert_eq!(encode(&value, Some(&aligned)).unwrap(), toon);
+
+    // With elided tables allowed, the marker ends the table and makes up its
+    // declared length
+    use toon_rust::DecodeOptions;
+    let allowed = DecodeOptions::new().allow_elided_rows(true);
+    let allowed = Some(&allowed);
+    let preview = json!({"a": [{"id": 1}, {"id": 2}], "b": [{"id": 9}], "c": 1});
+    assert_eq!(decode(&toon, allowed).unwrap(), preview);
+    assert_eq!(decode_stream(toon.as_bytes(), allowed).unwrap(), preview);
+    assert!(validate_stream(toon.as_bytes(), allowed).is_ok());
+    assert_eq!(decode_rows(&toon, "a", allowed).count(), 2);
+    assert_eq!(decode_rows_stream(toon.as_bytes(), "a", allowed).count(), 2);
+    let one = encode(
+        &json!([{"id": 1}, {"id": 2}]),
+        Some(&EncodeOptions::new().max_rows_per_table(1)),
+    )
+    .unwrap();
+    assert!(one.ends_with("… (1 more row)\n"));
+    assert_eq!(decode(&one, allowed).unwrap(), json!([{"id": 1}]));
+
+    // Strict decoding otherwise reports the missing rows
+    assert!(decode(&toon, None).is_err());
+    assert!(validate_stream(toon.as_bytes(), None).is_err());
+
+    // A marker that doesn't account for the missing rows is still an error
+    let short = "a[5]{id}:\n  1\n  … (2 more rows)";
+    assert!(decode(short, allowed).is_err());
+    assert!(decode_stream(short.as_bytes(), allowed).is_err());
+    assert!(decode_rows(short, "a", allowed).any(|row| row.is_err()));
+    assert!(decode_rows_stream(short.as_bytes(), "a", allowed).any(|row| row.is_err()));
+}
+
+#[test]
+fn test_encode_typed_headers() {
+    use toon_rust::decode;
+
+    let value = json!({
+        "items": [
+            {"sku": "04532", "qty": 2, "price": 1.5, "gift": true, "note": null},
+            {"sku": "B2", "qty": 3, "price": 2.0, "gift": false, "note": null}
+        ],
+        "mixed": [{"a": 1, "b": 1}, {"a": 1.5, "b": "x"}]
+    });
+    let options = EncodeOptions::new().typed_headers(true);
+    let toon = encode(&value, Some(&options)).unwrap();
+    assert_eq!(
+        toon,
+        "items[2]{gift:bool,note,price:float,qty:int,sku:str}:\n  true,,1.5,2,\"04532\"\n  false,,2.0,3,B2\n\n\
+         mixed[2]{a,b}:\n  1,1\n  1.5,x\n"
+    );
+    assert_eq!(decode(&toon, None).unwrap(), value);
+
+    #[cfg(feature = "serde")]
+    {
+        let mut streamed = Vec::new();
+        toon_rust::serde_api::to_writer_streaming_with_options(&value, &mut streamed, &options)
+            .unwrap();
+        assert_eq!(String::from_utf8(streamed).unwrap(), toon);
+    }
+}
+
+#[test]
+fn test_encode_column_defaults() {
+    use toon_rust::decode;
+
+    let value = json!({
+        "items": [
+            {"sku": "A1", "region": "EU", "qty": 1, "note": null},
+            {"sku": "B2", "region": "EU", "qty": 1, "note": "gift"},
+            {"sku": "C3", "region": "US", "qty": 5, "note": "gift"},
+            {"sku": "D4", "region": "EU", "qty": 1, "note": "gift"}
+        ],
+        "tags": [{"t": "x"}, {"t": "x"}],
+        "odd": [{"k=v": 1, "s": "a}b"}, {"k=v": 2, "s": "a}b"}]
+    });
+    let options = EncodeOptions::new().column_defaults(0.75);
+    let toon = encode(&value, Some(&options)).unwrap();
+    assert_eq!(
+        toon,
+        "items[4]{note=gift,qty=1,region=EU,sku}:\n  null,,,A1\n  ,,,B2\n  ,5,US,C3\n  ,,,D4\n\n\
+         odd[2]{\"k=v\",s}:\n  1,a}b\n  2,a}b\n\n\
+         tags[2]{t}:\n  x\n  x\n"
+    );
+    assert_eq!(decode(&toon, None).unwrap(), value);
+    let typed = EncodeOptions::new()
+        .column_defaults(0.75)
+        .typed_headers(true);
+    let toon = encode(&value, Some(&typed)).unwrap();
+    assert!(toon.starts_with("items[4]{note:str=gift,qty:int=1,region:str=EU,sku:str}:"));
+    assert_eq!(decode(&toon, None).unwrap(), value);
+
+    // Below the share, or when a row lacks the column, there is no default
+    let strict = EncodeOptions::new().column_defaults(1.0);
+    let toon = encode(&value, Some(&strict)).unwrap();
+    assert!(toon.starts_with("items[4]{note,qty,region,sku}:"));
+    let sparse = json!([{"a": "same", "b": 1}, {"a": "same", "b": 2}, {"b": 3}]);
+    let options = options.tabular_uniformity(0.5);
+    let toon = encode(&sparse, Some(&options)).unwrap();
+    assert_eq!(toon, "[3]{a,b}:\n  same,1\n  same,2\n  ,3\n");
+}
+
+#[test]
+fn test_encode_dictionary_encoding() {
+    use toon_rust::decode;
+
+    let value = json!({"orders": [
+        {"id": 1, "status": "shipped", "note": "a;b]", "tag": "x"},
+        {"id": 2, "status": "pending", "note": "a;b]", "tag": "x"},
+        {"id": 3, "status": "shipped", "note": null, "tag": "y"},
+        {"id": 4, "status": "shipped", "note": "a;b]", "tag": "x"},
+        {"id": 5, "status": "cancelled", "note": "a;b]", "tag": "y"}
+    ]});
+    let options = EncodeOptions::new().dictionary_encoding(true);
+    let toon = encode(&value, Some(&options)).unwrap();
+    assert_eq!(
+        toon,
+        "orders[5]{id,note@[\"a;b]\"],status@[shipped;cancelled;pending],tag}:\n  \
+         1,0,0,x\n  2,0,2,x\n  3,,0,y\n  4,0,0,x\n  5,0,1,y\n"
+    );
+    assert_eq!(decode(&toon, None).unwrap(), value);
+
+    // A column given a default keeps it
+    let options = options.column_defaults(0.6).typed_headers(true);
+    let toon = encode(&value, Some(&options)).unwrap();
+    assert!(toon.starts_with("orders[5]{id:int,note:str=a;b],status:str=shipped,tag:str=x}:"));
+    assert_eq!(decode(&toon, None).unwrap(), value);
+}
+
+#[test]
+fn test_encode_spec_version() {
+    use toon_rust::options::{NanPolicy, SpecVersion};
+
+    let data = json!({"items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}]});
+    let plain = encode(&data, None).unwrap();
+    for version in [SpecVersion::V1, SpecVersion::V2] {
+        let options = EncodeOptions::new().spec_version(version);
+        assert_eq!(encode(&data, Some(&options)).unwrap(), plain);
+    }
+
+    // Spec 1 still has the `#` length marker
+    let marked = EncodeOptions::new().length_marker('#');
+    let v1 = marked.clone().spec_version(SpecVersion::V1);
+    assert_eq!(
+        encode(&data, Some(&v1)).unwrap(),
+        encode(&data, Some(&marked)).unwrap()
+    );
+    let err = encode(&data, Some(&marked.spec_version(SpecVersion::V2))).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Serialization error: `#` length markers aren't part of TOON spec 2"
+    );
+
+    // This crate's extensions are in no revision
+    for (options, construct) in [
+        (EncodeOptions::new().typed_headers(true), "typed headers"),
+        (EncodeOptions::new().column_defaults(0.5), "column defaults"),
+        (
+            EncodeOptions::new().dictionary_encoding(true),
+            "dictionary columns",
+        ),
+        (EncodeOptions::new().block_strings(true), "block strings"),
+        (
+            EncodeOptions::new().nan_policy(NanPolicy::Token),
+            "`nan` and `inf` literals",
+        ),
+        (
+            EncodeOptions::new().emit_lengths(false),
+            "arrays without lengths",
+        ),
+        (
+            EncodeOptions::new().max_rows_per_table(1),
+            "row elision markers",
+        ),
+    ] {
+        assert!(encode(&data, Some(&options)).is_ok());
+        let err = encode(&data, Some(&options.spec_version(SpecVersion::V1))).unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with(&format!("{construct} aren't part of TOON spec 1")));
+    }
+}
+
+#[test]
+fn test_encode_rejects_invalid_indent() {
+    let data = json!({"user": {"name": "Ann"}, "rows": [{"id": 1}, {"id": 2}]});
+    for options in [
+        EncodeOptions::new().indent(0),
+        EncodeOptions::new().indent_string(""),
+        EncodeOptions::new().indent_string("ab"),
+        EncodeOptions::new().indent_string(" -"),
+    ] {
+        let err = encode(&data, Some(&options)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("indentation must be one or more spaces or tabs"));
+        let mut buffer = Vec::new();
+        assert!(toon_rust::encode_stream(&data, &mut buffer, Some(&options)).is_err());
+        let mut writer = toon_rust::ToonWriter::with_options(Vec::new(), &options);
+        assert!(writer.field("name", &json!("Ann")).is_err());
+    }
+
+    // Any mix of spaces and tabs is a unit the decoder can be given
+    let options = EncodeOptions::new().indent_string("\t ");
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("\n\t name: Ann"));
+    let decode_options = toon_rust::DecodeOptions::new().indent_string("\t ");
+    assert_eq!(
+        toon_rust::decode(&toon, Some(&decode_options)).unwrap(),
+        data
+    );
+}
+
+#[test]
+fn test_encode_non_finite_marker_value() {
+    // The private marker is written under the nan policy like a float
+    use toon_rust::options::NanPolicy;
+    let data = json!({"x": {"$toon_rust::private::NonFinite": "-inf"}});
+    assert_eq!(encode(&data, None).unwrap(), "x: null");
+    let options = EncodeOptions::new().nan_policy(NanPolicy::Error);
+    assert!(encode(&data, Some(&options)).is_err());
+    let options = EncodeOptions::new().nan_policy(NanPolicy::Token);
+    assert_eq!(encode(&data, Some(&options)).unwrap(), "x: -inf");
+
+    // Anything else under the key stays an ordinary object
+    let data = json!({"x": {"$toon_rust::private::NonFinite": "hello"}});
+    assert_eq!(
+        encode(&data, None).unwrap(),
+        "x: \n  \"$toon_rust::private::NonFinite\": hello"
+    );
+}
+
+#[test]
+fn test_encode_options_from_toon_js() {
+    let data = json!({
+        "user": {"profile": {"name": "Ada Lovelace"}},
+        "tags": ["a", "b c"]
+    });
+    let config = json!({
+        "indent": 4,
+        "delimiter": "\t",
+        "lengthMarker": "#",
+        "keyFolding": "safe",
+        "flattenDepth": null,
+        "strict": false
+    });
+    let options = EncodeOptions::from_toon_js(&config).unwrap();
+    assert_eq!(
+        encode(&data, Some(&options)).unwrap(),
+        "tags[#2\t]: a\tb c\nuser.profile.name: \"Ada Lovelace\""
+    );
+    // The library's defaults
+    let defaults = EncodeOptions::from_toon_js(&json!({"lengthMarker": false})).unwrap();
+    assert_eq!(
+        encode(&data, Some(&defaults)).unwrap(),
+        "tags[2]: a,b c\nuser: \n  profile: \n    name: \"Ada Lovelace\""
+    );
+
+    // Byte for byte what the library writes for the same input and options
+    let record = json!({"active": true, "id": 123, "name": "Ada", "score": -1.5, "tags": ["admin", "ops", "dev"]});
+    let marks = json!({"marks": ["c|d", "true", "", 7]});
+    let root = json!(["x", "y"]);
+    for (config, value, reference) in [
+        (
+            json!({}),
+            &record,
+            "active: true\nid: 123\nname: Ada\nscore: -1.5\ntags[3]: admin,ops,dev",
+        ),
+        (
+            json!({"delimiter": "|"}),
+            &record,
+            "active: true\nid: 123\nname: Ada\nscore: -1.5\ntags[3|]: admin|ops|dev",
+        ),
+        (
+            json!({"delimiter": "\t", "lengthMarker": "#"}),
+            &record,
+            "active: true\nid: 123\nname: Ada\nscore: -1.5\ntags[#3\t]: admin\tops\tdev",
+        ),
+        (
+            json!({"delimiter": "|"}),
+            &marks,
+            "marks[4|]: \"c|d\"|\"true\"|\"\"|7",
+        ),
+        (json!({}), &root, "[2]: x,y"),
+        (json!({"delimiter": "|"}), &root, "[2|]: x|y"),
+        (json!({"delimiter": "\t"}), &json!({"e": []}), "e[0\t]:"),
+    ] {
+        let options = EncodeOptions::from_toon_js(&config).unwrap();
+        let toon = encode(value, Some(&options)).unwrap();
+        assert_eq!(toon, reference);
+        assert_eq!(&toon_rust::decode(&toon, None).unwrap(), value);
+    }
+    // Tables and list arrays have the library's lines; only the final
+    // newline after them is this crate's own
+    let options = EncodeOptions::from_toon_js(&json!({"delimiter": "|"})).unwrap();
+    for (value, reference) in [
+        (
+            json!({"items": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Bob"}]}),
+            "items[2|]{id|name}:\n  1|Ada\n  2|Bob",
+        ),
+        (
+            json!({"pairs": [[1, 2], [3, 4]]}),
+            "pairs[2|]:\n  - [2|]: 1|2\n  - [2|]: 3|4",
+        ),
+    ] {
+        let toon = encode(&value, Some(&options)).unwrap();
+        assert_eq!(toon, format!("{reference}\n"));
+        assert_eq!(toon_rust::decode(&toon, None).unwrap(), value);
+    }
+
+    for (config, message) in [
+        (json!([]), "Deserialization error: expected an object of options"),
+        (
+            json!({"delimiter": ";"}),
+            "Deserialization error at `delimiter`: expected \",\", \"\\t\" or \"|\"",
+        ),
+        (
+            json!({"lengthMarker": true}),
+            "Deserialization error at `lengthMarker`: expected \"#\" or false",
+        ),
+        (
+            json!({"keyFolding": "aggressive"}),
+            "Deserialization error at `keyFolding`: expected \"off\" or \"safe\"",
+        ),
+        (
+            json!({"flattenDepth": 2}),
+            "Deserialization error at `flattenDepth`: expected null, as folding depth can't be limited",
+        ),
+        (
+            json!({"indent": 0}),
+            "Deserialization error at `indent`: expected a positive number of spaces",
+        ),
+        (json!({"replacer": null}), "Deserialization error at `replacer`: unknown option"),
+    ] {
+        let err = EncodeOptions::from_toon_js(&config).unwrap_err();
+        assert_eq!(err.to_string(), message);
+    }
+}
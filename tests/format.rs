@@ -0,0 +1,240 @@
+//! Tests for formatting TOON text
+
+use serde_json::{json, Map, Value};
+use toon_rust::options::{DecodeOptions, Delimiter, EncodeOptions, FormatOptions, Style};
+use toon_rust::{decode, encode, format_str};
+
+#[test]
+fn test_format_reindents() {
+    let input = "\
+order:
+    id:   1e3
+    items[2]{sku,qty}:
+          A1,  2
+          B2,1
+
+    notes[2]:
+        -   first
+        - by:   ops
+          at:0.10
+meta:\t
+   empty:
+total:  12.50
+";
+    let formatted = format_str(input, &FormatOptions::new()).unwrap();
+    assert_eq!(
+        formatted,
+        "\
+order:
+  id: 1e3
+  items[2]{sku,qty}:
+    A1,  2
+    B2,1
+  notes[2]:
+    - first
+    - by: ops
+      at: 0.10
+meta:
+  empty:
+total: 12.50
+"
+    );
+    // Formatting changes no value and settles after one pass
+    assert_eq!(
+        decode(&formatted, None).unwrap(),
+        decode(input, None).unwrap()
+    );
+    assert_eq!(
+        format_str(&formatted, &FormatOptions::new()).unwrap(),
+        formatted
+    );
+}
+
+#[test]
+fn test_format_options() {
+    let input = "a:\n  b: 1\n  c[2]:x,y\n- ";
+    let options = FormatOptions::new().indent(4).style(Style::Compact);
+    assert_eq!(
+        format_str("a:\n  b: 1\n  c[2]: x,y", &options).unwrap(),
+        "a:\n    b:1\n    c[2]:x,y\n"
+    );
+    assert!(format_str(input, &options).is_err());
+}
+
+#[test]
+fn test_format_keeps_text() {
+    let input = "\
+\"key: quoted\":    \"a:b\"
+text: |
+      line one
+        indented
+list[2]:
+  - [2]:  1,2
+  - \"- dash\"
+";
+    assert_eq!(
+        format_str(input, &FormatOptions::new()).unwrap(),
+        "\
+\"key: quoted\": \"a:b\"
+text: |
+  line one
+    indented
+list[2]:
+  - [2]:1,2
+  - \"- dash\"
+"
+    );
+}
+
+#[test]
+fn test_format_encoder_output() {
+    let data = json!({
+        "users": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Grace"}],
+        "nested": {"deep": {"list": [1, {"a": 1}], "empty": {}}},
+        "note": "multi\nline"
+    });
+    let options = EncodeOptions::new().indent(4).block_strings(true);
+    let toon = encode(&data, Some(&options)).unwrap();
+    let formatted = format_str(&toon, &FormatOptions::new()).unwrap();
+    assert_eq!(
+        decode(&formatted, None).unwrap(),
+        decode(&toon, Some(&DecodeOptions::new().indent(4))).unwrap()
+    );
+    assert_eq!(
+        format_str(&formatted, &FormatOptions::new()).unwrap(),
+        formatted
+    );
+}
+
+#[test]
+fn test_format_tab_tables_and_lenient_arrays() {
+    // A trailing tab delimits an empty last cell, so it stays
+    let input = "items[2]{a\tb}:\n     1\t\n     2\tx  \n";
+    let formatted = format_str(input, &FormatOptions::new()).unwrap();
+    assert_eq!(formatted, "items[2]{a\tb}:\n  1\t\n  2\tx\n");
+    assert_eq!(
+        decode(&formatted, None).unwrap(),
+        decode(input, None).unwrap()
+    );
+
+    let data = json!({"rows": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Bob"}]});
+    let toon = encode(&data, Some(&EncodeOptions::new().emit_lengths(false))).unwrap();
+    let formatted = format_str(&toon, &FormatOptions::new()).unwrap();
+    assert_eq!(formatted, toon);
+    let lenient = DecodeOptions::new().lenient_arrays(true);
+    let options = FormatOptions::new()
+        .indent(4)
+        .decode_options(lenient.clone());
+    let formatted = format_str(&toon, &options).unwrap();
+    assert_eq!(
+        decode(&formatted, Some(&lenient.clone().indent(4))).unwrap(),
+        data
+    );
+}
+
+#[test]
+fn test_format_rejects_invalid() {
+    assert!(format_str("a: \"open\n", &FormatOptions::new()).is_err());
+    let err = format_str("a: 1\nb 2\n", &FormatOptions::new()).unwrap_err();
+    assert!(err.to_string().contains("line 2"), "{err}");
+}
+
+/// Pseudo-random values, the same on every run, leaning on the layouts the
+/// encoder picks between: tables, one-column tables with nulls, list items
+/// with nested first fields and multi-line strings
+struct Values(u64);
+
+impl Values {
+    fn pick(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+
+    fn primitive(&mut self) -> Value {
+        let strings = [
+            "", "s", "-", "-1", "a\nb", "x,y", " pad", "true", "a: b", "|",
+        ];
+        match self.pick(4) {
+            0 => Value::Null,
+            1 => json!([true, 0, -1, 2.5, -2.5, 1e20, u64::MAX])[self.pick(7)].take(),
+            _ => json!(strings[self.pick(strings.len())]),
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Value {
+        let keys = ["a", "b", "-", "id", "x y"];
+        match self.pick(if depth < 3 { 6 } else { 1 }) {
+            0 => self.primitive(),
+            1 | 2 => {
+                let len = self.pick(4);
+                Value::Array((0..len).map(|_| self.value(depth + 1)).collect())
+            }
+            3 => {
+                let fields = &keys[..1 + self.pick(2)];
+                let rows = (0..1 + self.pick(3)).map(|_| {
+                    let row = fields.iter().map(|key| (key.to_string(), self.primitive()));
+                    Value::Object(row.collect())
+                });
+                Value::Array(rows.collect())
+            }
+            _ => {
+                let mut map = Map::new();
+                for _ in 0..self.pick(4) {
+                    map.insert(
+                        keys[self.pick(keys.len())].to_string(),
+                        self.value(depth + 1),
+                    );
+                }
+                Value::Object(map)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_format_keeps_encoder_output_meaning() {
+    let options = [
+        EncodeOptions::new(),
+        EncodeOptions::new().block_strings(true),
+        EncodeOptions::new().delimiter(Delimiter::Pipe),
+        EncodeOptions::new().indent(4),
+    ];
+    let mut values = Values(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..500 {
+        let value = values.value(0);
+        for options in &options {
+            let toon = encode(&value, Some(options)).unwrap();
+            let decode_options = DecodeOptions::new().indent(options.get_indent());
+            let Ok(expected) = decode(&toon, Some(&decode_options)) else {
+                continue;
+            };
+            let formatted = format_str(&toon, &FormatOptions::new()).expect(&toon);
+            assert_eq!(
+                decode(&formatted, None).ok().as_ref(),
+                Some(&expected),
+                "{toon:?} formatted as {formatted:?}"
+            );
+            // Non-strict decoding normalizes the same way
+            let lenient = decode_options.strict(false);
+            assert_eq!(decode(&toon, Some(&lenient)).unwrap(), expected, "{toon:?}");
+        }
+    }
+
+    // The first field of a list item keeps its nested value below the item's other fields
+    let toon = "x[1]:\n  - \"-\": \n      y: 1\n    id: 2\n";
+    let formatted = format_str(toon, &FormatOptions::new()).unwrap();
+    assert_eq!(formatted, "x[1]:\n  - \"-\":\n      y: 1\n    id: 2\n");
+    assert_eq!(
+        decode(&formatted, None).unwrap(),
+        json!({"x": [{"-": {"y": 1}, "id": 2}]})
+    );
+    let toon = "x[1]:\n    - n: |-\n            a\n            b\n        id: 2\n";
+    let formatted = format_str(toon, &FormatOptions::new()).unwrap();
+    assert_eq!(formatted, "x[1]:\n  - n: |-\n      a\n      b\n    id: 2\n");
+    assert_eq!(
+        decode(&formatted, None).unwrap(),
+        json!({"x": [{"n": "a\nb", "id": 2}]})
+    );
+}
@@ -0,0 +1,53 @@
+//! Tests for the fuzzing inputs
+#![cfg(feature = "arbitrary")]
+
+use arbitrary::{Arbitrary, Unstructured};
+use toon_rust::fuzzing::ArbitraryValue;
+use toon_rust::{decode, encode, DecodeOptions, EncodeOptions};
+
+/// Pseudo-random bytes, the same on every run
+fn bytes(seed: u64, len: usize) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+#[test]
+fn test_arbitrary_value() {
+    let mut containers = 0;
+    for seed in 0..500 {
+        let data = bytes(seed, 256);
+        let ArbitraryValue(value) =
+            ArbitraryValue::arbitrary(&mut Unstructured::new(&data)).unwrap();
+        let again = ArbitraryValue::arbitrary(&mut Unstructured::new(&data)).unwrap();
+        assert_eq!(again.0, value);
+        containers += usize::from(value.is_array() || value.is_object());
+        // Numbers are finite, so the default options encode any value
+        let toon = encode(&value, None).unwrap();
+        let _ = decode(&toon, None);
+    }
+    assert!(containers > 100);
+}
+
+#[test]
+fn test_arbitrary_options() {
+    for seed in 0..200 {
+        let data = bytes(seed, 512);
+        let mut u = Unstructured::new(&data);
+        let ArbitraryValue(value) = ArbitraryValue::arbitrary(&mut u).unwrap();
+        let encode_options = EncodeOptions::arbitrary(&mut u).unwrap();
+        let decode_options = DecodeOptions::arbitrary(&mut u).unwrap();
+        assert!(encode_options.get_indent() > 0);
+        assert!(decode_options.follow.is_none());
+        // Any options may fail, but neither side may panic
+        if let Ok(toon) = encode(&value, Some(&encode_options)) {
+            let _ = decode(&toon, Some(&decode_options));
+        }
+    }
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "jsonschema")]
+
+use serde_json::json;
+use toon_rust::{decode, validate};
+
+#[test]
+fn test_validate_json_schema() {
+    let schema = json!({
+        "type": "object",
+        "required": ["name", "items"],
+        "properties": {
+            "name": {"type": "string"},
+            "meta": {"type": "object", "properties": {"tags": {"type": "array", "items": {"type": "string"}}}},
+            "items": {
+                "type": "array",
+                "maxItems": 2,
+                "items": {
+                    "type": "object",
+                    "required": ["sku", "qty"],
+                    "properties": {"sku": {"type": "string"}, "qty": {"type": "integer", "minimum": 1}}
+                }
+            }
+        }
+    });
+
+    let good = "name: shop\nitems[2]{sku,qty}:\n  A1,2\n  B2,1";
+    assert!(validate(good, &schema).unwrap().is_empty());
+    assert!(validate(&decode(good, None).unwrap(), &schema)
+        .unwrap()
+        .is_empty());
+
+    let bad = "meta:\n  tags[2]: a,3\nitems[2]{sku,qty}:\n  A1,two\n  B2,0";
+    let mut violations = validate(bad, &schema).unwrap();
+    violations.sort_by_key(|v| (v.line, v.column));
+    let found: Vec<_> = violations
+        .iter()
+        .map(|v| (v.path.as_str(), v.line, v.column))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("", Some(1), Some(1)),
+            ("meta.tags[1]", Some(2), Some(14)),
+            ("items[0].qty", Some(4), Some(6)),
+            ("items[1].qty", Some(5), Some(6)),
+        ]
+    );
+    let missing = &violations[0];
+    assert_eq!(missing.schema_path, "/required");
+    assert!(missing.message.contains("\"name\" is a required property"));
+    assert_eq!(violations[2].pointer, "/items/0/qty");
+    assert_eq!(
+        violations[3].to_string(),
+        "line 5, column 6: `items[1].qty`: 0 is less than the minimum of 1"
+    );
+
+    // Decoded values are checked the same way, without positions
+    let value = decode(bad, None).unwrap();
+    let violations = validate(&value, &schema).unwrap();
+    assert_eq!(violations.len(), 4);
+    assert!(violations.iter().all(|v| v.line.is_none()));
+
+    assert!(validate("a: 1", &json!({"type": "nope"})).is_err());
+    assert!(validate("a[2]: 1", &json!({})).is_err());
+}
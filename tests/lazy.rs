@@ -0,0 +1,82 @@
+//! Tests for lazily decoded documents
+
+use serde_json::json;
+use toon_rust::lazy::LazyDocument;
+use toon_rust::{decode, DecodeOptions, Error};
+
+#[test]
+fn test_lazy_document_sections() {
+    let toon = "\
+name: shop
+items[2]{sku,qty}:
+  A1,2
+  B2,1
+meta:
+  owner: ann
+  tags[2]: a,b
+broken[3]: 1,2";
+    let doc = LazyDocument::new(toon, None).unwrap();
+    assert_eq!(doc.len(), 4);
+    assert!(doc.contains_key("meta") && !doc.contains_key("owner"));
+    assert!(!doc.is_decoded("items"));
+
+    assert_eq!(
+        doc.get("items").unwrap(),
+        Some(&json!([{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}]))
+    );
+    assert!(doc.is_decoded("items"));
+    assert!(!doc.is_decoded("meta"));
+    assert_eq!(doc.get("meta").unwrap().unwrap()["tags"], json!(["a", "b"]));
+    assert_eq!(doc.get("missing").unwrap(), None);
+
+    // Only lookups reaching the broken section fail, and keep failing
+    assert!(matches!(
+        doc.get("broken"),
+        Err(Error::LengthMismatch { .. })
+    ));
+    assert!(doc.get("broken").is_err());
+    assert!(doc.value().is_err());
+    assert_eq!(doc.get("name").unwrap(), Some(&json!("shop")));
+}
+
+#[test]
+fn test_lazy_document_matches_decode() {
+    let docs = [
+        "a: 1\nb: \n  c: 2\na: 3",
+        "[2]{id}:\n  1\n  2",
+        "hello",
+        "",
+        "\"quoted key\": \"x\ny\"\nz: 1",
+    ];
+    for toon in docs {
+        let doc = LazyDocument::new(toon, None).unwrap();
+        assert_eq!(
+            doc.value().unwrap(),
+            decode(toon, None).unwrap(),
+            "{toon:?}"
+        );
+    }
+    assert!(LazyDocument::new("[2]: 1,2", None).unwrap().is_empty());
+
+    let expanded = DecodeOptions::new().expand_paths(true);
+    let toon = "a.b: 1\nc: 2\na.d: 3";
+    let doc = LazyDocument::new(toon, Some(&expanded)).unwrap();
+    assert_eq!(doc.keys().collect::<Vec<_>>(), ["a", "c"]);
+    assert_eq!(doc.get("a").unwrap(), Some(&json!({"b": 1, "d": 3})));
+    assert_eq!(doc.value().unwrap(), decode(toon, Some(&expanded)).unwrap());
+
+    // Sections left out by include_keys are left out of the value too
+    let toon = "meta:\n  total: 2\n  owner: ann\nname: shop\ntags[2]: a,b";
+    let included = DecodeOptions::new().include_keys(["name", "meta.total"]);
+    let doc = LazyDocument::new(toon, Some(&included)).unwrap();
+    assert_eq!(doc.get("tags").unwrap(), None);
+    assert_eq!(doc.get("meta").unwrap(), Some(&json!({"total": 2})));
+    assert_eq!(
+        doc.value().unwrap(),
+        json!({"meta": {"total": 2}, "name": "shop"})
+    );
+    assert_eq!(doc.value().unwrap(), decode(toon, Some(&included)).unwrap());
+
+    // Keys are read up front
+    assert!(LazyDocument::new("a: 1\n\"b: 2", None).is_err());
+}
@@ -0,0 +1,106 @@
+//! Tests for linting TOON text
+
+use serde_json::json;
+use toon_rust::encode;
+use toon_rust::lint;
+use toon_rust::lint::{Lint, LintKind};
+
+/// Each lint's kind, position and the text its span covers
+fn found<'a>(input: &'a str, lints: &[Lint]) -> Vec<(LintKind, usize, usize, &'a str)> {
+    lints
+        .iter()
+        .map(|lint| (lint.kind, lint.line, lint.column, &input[lint.span.clone()]))
+        .collect()
+}
+
+#[test]
+fn test_lint_clean() {
+    let input = "\
+users[2]{id,name}:
+  1,Ada
+  2,Grace
+zip: \"04532\"
+note: |
+  free text
+      indented as it likes
+tags[2]:
+  - a:
+      b: 1
+    c: 2
+  - 3
+";
+    assert_eq!(lint(input), vec![]);
+}
+
+#[test]
+fn test_lint_accepts_encoder_output() {
+    // A null in a one-column table is written as a blank row
+    for data in [
+        json!({"t": [{"x": 1}, {"x": null}, {"x": 2}]}),
+        json!({"t": [{"x": null}], "n": 1}),
+        json!([{"x": 1}, {"x": null}]),
+        json!({"a": [{"t": [{"x": null}, {"x": 2}], "z": 1}, {"b": {"c": 1}, "d": [1, 2]}]}),
+        json!({"zip": "04532", "n": "1_000", "s": "-1", "list": [[1, 2], {"a": null}]}),
+    ] {
+        let toon = encode(&data, None).unwrap();
+        assert_eq!(lint(&toon), vec![], "{toon:?}");
+    }
+}
+
+#[test]
+fn test_lint_indentation() {
+    let input = "a:\n    b: 1\n  c: 2\nd:\n\te: 1\nf:\n            g: 1\n";
+    assert_eq!(
+        found(input, &lint(input)),
+        vec![
+            (LintKind::InconsistentIndent, 3, 1, "  "),
+            (LintKind::InconsistentIndent, 5, 1, "\t"),
+            (LintKind::InconsistentIndent, 7, 1, "            "),
+        ]
+    );
+}
+
+#[test]
+fn test_lint_values() {
+    let input = "\
+counts[3]: 1,2
+rows[1]{id,zip}:
+  1,04532
+  2,1_000
+version: 1.2.3
+when: 2024-01-02
+";
+    let lints = lint(input);
+    assert_eq!(
+        found(input, &lints),
+        vec![
+            (LintKind::LengthMismatch, 1, 7, "[3]"),
+            (LintKind::LengthMismatch, 2, 5, "[1]"),
+            (LintKind::NumericString, 3, 5, "04532"),
+            (LintKind::NumericString, 4, 5, "1_000"),
+        ]
+    );
+    assert!(lints[0].message.contains("says 3"), "{}", lints[0].message);
+}
+
+#[test]
+fn test_lint_delimiters() {
+    let input = "a[1]{x,y}:\n  1,2\nb[1]{x|y}:\n  1|2\nc[1]{x,y}:\n  1,2\n";
+    assert_eq!(
+        found(input, &lint(input)),
+        vec![(LintKind::MixedDelimiters, 3, 2, "[1]{x|y}:")]
+    );
+}
+
+#[test]
+fn test_lint_trailing_content() {
+    let input = "[2]:\n  - 1\n  - 2\n- 3\nx: 04\n";
+    // Lints before the trailing content are still reported
+    let lints = lint(&input.replace("[2]", "[3]"));
+    assert_eq!(lints.len(), 2);
+    assert_eq!(lints[0].kind, LintKind::LengthMismatch);
+    assert_eq!(
+        found(input, &lint(input)),
+        vec![(LintKind::TrailingContent, 4, 1, "- 3\nx: 04")]
+    );
+}
@@ -0,0 +1,102 @@
+//! Tests for merge patches
+
+use serde_json::json;
+use toon_rust::{decode, merge, merge_stream, DecodeOptions, EncodeOptions};
+
+#[test]
+fn test_merge_rfc_examples() {
+    // The examples from RFC 7386, appendix A
+    let cases = [
+        (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+        (
+            json!({"a": "b"}),
+            json!({"b": "c"}),
+            json!({"a": "b", "b": "c"}),
+        ),
+        (json!({"a": "b"}), json!({"a": null}), json!({})),
+        (
+            json!({"a": "b", "b": "c"}),
+            json!({"a": null}),
+            json!({"b": "c"}),
+        ),
+        (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+        (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+        (
+            json!({"a": {"b": "c"}}),
+            json!({"a": {"b": "d", "c": null}}),
+            json!({"a": {"b": "d"}}),
+        ),
+        (
+            json!({"a": [{"b": "c"}]}),
+            json!({"a": [1]}),
+            json!({"a": [1]}),
+        ),
+        (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+        (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+        (json!({"a": "foo"}), json!(null), json!(null)),
+        (json!({"a": "foo"}), json!("bar"), json!("bar")),
+        (
+            json!({"e": null}),
+            json!({"a": 1}),
+            json!({"e": null, "a": 1}),
+        ),
+        (
+            json!([1, 2]),
+            json!({"a": "b", "c": null}),
+            json!({"a": "b"}),
+        ),
+        (
+            json!({}),
+            json!({"a": {"bb": {"ccc": null}}}),
+            json!({"a": {"bb": {}}}),
+        ),
+    ];
+    for (target, patch, expected) in cases {
+        let mut merged = target.clone();
+        merge(&mut merged, &patch);
+        assert_eq!(merged, expected, "{target} merged with {patch}");
+    }
+}
+
+#[test]
+fn test_merge_stream_matches_merge() {
+    let toon = "\
+name: shop
+items[2]{sku,qty}:
+  A1,2
+  B2,1
+meta:
+  owner: ann
+  flags:
+    draft: true
+    hidden: false
+  tags[2]: a,b";
+    let target = decode(toon, None).unwrap();
+    let patches = [
+        json!({}),
+        json!({"name": "store"}),
+        json!({"items": null, "open": true}),
+        json!({"meta": {"flags": {"draft": null}, "tags": ["c"]}}),
+        json!({"meta": {"flags": {"hidden": true, "new": 1}}}),
+        json!({"meta": {"owner": {"name": "bo"}}, "items": [{"sku": "C3", "qty": 4}]}),
+        json!({"name": null, "items": null, "meta": null}),
+        json!([1, 2]),
+    ];
+    let folded = EncodeOptions::new().key_folding(true);
+    for options in [None, Some(&folded)] {
+        for patch in &patches {
+            let mut merged = target.clone();
+            merge(&mut merged, patch);
+
+            let mut out = Vec::new();
+            merge_stream(&target, patch, &mut out, options).unwrap();
+            let streamed = String::from_utf8(out).unwrap();
+            let expanded = DecodeOptions::new().expand_paths(true);
+            assert_eq!(
+                decode(&streamed, Some(&expanded)).unwrap(),
+                merged,
+                "patch {patch}"
+            );
+        }
+    }
+}
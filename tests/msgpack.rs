@@ -0,0 +1,64 @@
+#![cfg(feature = "rmp")]
+
+use serde_json::json;
+use toon_rust::options::BytesPolicy;
+use toon_rust::{decode, msgpack_to_toon, toon_to_msgpack, EncodeOptions, Error};
+
+/// MessagePack for `value`, written with `rmpv`
+fn pack(value: &rmpv::Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    rmpv::encode::write_value(&mut bytes, value).unwrap();
+    bytes
+}
+
+#[test]
+fn test_msgpack_round_trip() {
+    let toon = "name: shop\nratio: 0.25\nbig: -9223372036854775808\nitems[2]{sku,qty}:\n  A1,-2\n  B2,null\ntags[2]: a,\"true\"";
+    let bytes = toon_to_msgpack(toon, None).unwrap();
+    let back = msgpack_to_toon(&bytes, None).unwrap();
+    assert_eq!(decode(&back, None).unwrap(), decode(toon, None).unwrap());
+}
+
+#[test]
+fn test_msgpack_to_toon_values() {
+    use rmpv::Value as M;
+    let value = M::Map(vec![
+        (M::from("id"), M::from(1)),
+        (M::from(7), M::from("seven")),
+        (M::from("score"), M::F32(0.1)),
+        (M::from("blob"), M::Binary(vec![0, 1, 255])),
+        (M::from("nan"), M::F64(f64::NAN)),
+    ]);
+    let toon = msgpack_to_toon(&pack(&value), None).unwrap();
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!({"id": 1, "7": "seven", "score": 0.1, "blob": "AAH/", "nan": null})
+    );
+
+    let options = EncodeOptions::new().bytes_policy(BytesPolicy::Array);
+    let toon = msgpack_to_toon(&pack(&M::Binary(vec![0, 1, 255])), Some(&options)).unwrap();
+    assert_eq!(decode(&toon, None).unwrap(), json!([0, 1, 255]));
+}
+
+#[test]
+fn test_msgpack_errors() {
+    use rmpv::Value as M;
+    let path = |value: M| match msgpack_to_toon(&pack(&value), None) {
+        Err(Error::Deserialization { path, .. }) => path,
+        other => panic!("unexpected result: {other:?}"),
+    };
+    let nested = |value| M::Map(vec![(M::from("a"), M::Array(vec![M::Nil, value]))]);
+    assert_eq!(path(nested(M::Ext(1, vec![0]))), Some("a[1]".to_string()));
+    assert_eq!(
+        path(nested(M::Map(vec![(M::Nil, M::Nil)]))),
+        Some("a[1]".to_string())
+    );
+
+    let mut bytes = pack(&M::from(1));
+    bytes.push(0xc0);
+    assert!(matches!(
+        msgpack_to_toon(&bytes, None),
+        Err(Error::Deserialization { .. })
+    ));
+    assert!(msgpack_to_toon(&[0x92, 0x01], None).is_err());
+}
@@ -0,0 +1,115 @@
+#![cfg(feature = "parquet")]
+
+use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+use serde_json::json;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
+use toon_rust::{decode, parquet_to_toon, toon_to_parquet, Error};
+
+/// A path in the temp directory for the test called `name`
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("toon_{name}_{}.parquet", std::process::id()))
+}
+
+fn read(path: &PathBuf, columns: Option<&[&str]>) -> Result<String, Error> {
+    let mut output = Vec::new();
+    parquet_to_toon(File::open(path).unwrap(), &mut output, columns, None)?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn test_parquet_round_trip() {
+    let path = temp_path("round_trip");
+    let toon = "[3]:\n  - id: 1\n    name: Ada\n    score: 9.5\n    active: true\n  - id: 2\n    name: null\n    score: 7\n    active: false\n  - id: 3\n    name: Cy\n    active: true";
+    toon_to_parquet(toon, File::create(&path).unwrap(), None).unwrap();
+    let back = read(&path, None).unwrap();
+    assert_eq!(
+        decode(&back, None).unwrap(),
+        json!([
+            {"active": true, "id": 1, "name": "Ada", "score": 9.5},
+            {"active": false, "id": 2, "name": null, "score": 7.0},
+            {"active": true, "id": 3, "name": "Cy", "score": null}
+        ])
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_parquet_mixed_column_is_text() {
+    let path = temp_path("mixed");
+    let toon = "[3]{code}:\n  1\n  A7\n  true";
+    toon_to_parquet(toon, File::create(&path).unwrap(), None).unwrap();
+    assert_eq!(
+        read(&path, None).unwrap(),
+        "[3]{code}:\n  \"1\"\n  A7\n  \"true\"\n"
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_parquet_projection() {
+    let path = temp_path("projection");
+    let toon = "[2]{id,note,sku}:\n  1,x,A1\n  2,y,B2";
+    toon_to_parquet(toon, File::create(&path).unwrap(), None).unwrap();
+    assert_eq!(
+        read(&path, Some(&["sku", "id"])).unwrap(),
+        "[2]{sku,id}:\n  A1,1\n  B2,2\n"
+    );
+    let err = read(&path, Some(&["sku", "price"])).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("No column `price` in the Parquet file"));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_parquet_row_groups_stream_into_one_table() {
+    let path = temp_path("row_groups");
+    let batch = |ids: Vec<i64>, names: Vec<&str>| {
+        RecordBatch::try_from_iter([
+            ("id", Arc::new(Int64Array::from(ids)) as ArrayRef),
+            ("name", Arc::new(StringArray::from(names)) as ArrayRef),
+        ])
+        .unwrap()
+    };
+    let first = batch(vec![1, 2], vec!["a", "b"]);
+    let properties = WriterProperties::builder()
+        .set_max_row_group_size(1)
+        .build();
+    let mut writer = ArrowWriter::try_new(
+        File::create(&path).unwrap(),
+        first.schema(),
+        Some(properties),
+    )
+    .unwrap();
+    writer.write(&first).unwrap();
+    writer.write(&batch(vec![3], vec!["c"])).unwrap();
+    writer.close().unwrap();
+
+    assert_eq!(
+        read(&path, None).unwrap(),
+        "[3]{id,name}:\n  1,a\n  2,b\n  3,c\n"
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_parquet_errors() {
+    let err = toon_to_parquet("name: shop", Vec::new(), None).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("The document must be a root array of objects"));
+    let err = toon_to_parquet("[2]: 1,2", Vec::new(), None).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("The document must be a root array of objects"));
+
+    let path = temp_path("invalid");
+    std::fs::write(&path, b"not parquet").unwrap();
+    let err = read(&path, None).unwrap_err();
+    assert!(err.to_string().contains("Invalid Parquet"));
+    std::fs::remove_file(path).unwrap();
+}
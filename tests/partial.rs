@@ -0,0 +1,225 @@
+//! Tests for decoding incomplete documents
+
+use serde_json::json;
+use toon_rust::options::EmptyCellPolicy;
+use toon_rust::partial::{decode_partial, Completed, PartialParser};
+use toon_rust::{decode, pointer, DecodeOptions, Error};
+
+const DOC: &str = "\
+name: shop
+open: true
+items[2]{sku,qty}:
+  A1,2
+  B2,1
+tags[3]: new,sale,\"a b\"
+owner:
+  name: Ada
+  \"unit price\": 9.5
+notes: |
+  first
+  second
+steps[2]:
+  - id: 1
+    done: false
+  - last
+empty[0]:
+meta: {}
+";
+
+fn paths(completed: &[Completed]) -> Vec<&str> {
+    completed.iter().map(|c| c.path.as_str()).collect()
+}
+
+#[test]
+fn test_decode_partial_keeps_settled_lines() {
+    let cases = [
+        ("", json!({})),
+        ("name: sh", json!({})),
+        ("name: shop\n", json!({"name": "shop"})),
+        ("name: shop\nitems[2]{sku,qty}:\n", json!({"name": "shop"})),
+        (
+            "name: shop\nitems[2]{sku,qty}:\n  A1,2\n  B",
+            json!({"name": "shop", "items": [{"sku": "A1", "qty": 2}]}),
+        ),
+        ("owner:\n  name: Ada\n", json!({"owner": {"name": "Ada"}})),
+        ("a: 1\nnotes: |\n  first\n", json!({"a": 1})),
+        (
+            "a: 1\nnotes: |\n  first\nb: 2\n",
+            json!({"a": 1, "notes": "first\n", "b": 2}),
+        ),
+        ("steps[2]:\n  - id: 1\n", json!({"steps": [{"id": 1}]})),
+        ("[3]: a,b\n", json!(["a", "b"])),
+    ];
+    for (prefix, expected) in cases {
+        assert_eq!(
+            decode_partial(prefix, None).unwrap(),
+            expected,
+            "{prefix:?}"
+        );
+    }
+}
+
+#[test]
+fn test_decode_partial_every_prefix() {
+    let full = decode(DOC, None).unwrap();
+    for end in (0..=DOC.len()).filter(|&end| DOC.is_char_boundary(end)) {
+        let value = decode_partial(&DOC[..end], None).unwrap();
+        for (key, value) in value.as_object().unwrap() {
+            if !matches!(key.as_str(), "items" | "owner" | "steps") {
+                assert_eq!(Some(value), full.get(key), "prefix of {end} bytes");
+            }
+        }
+    }
+    assert_eq!(decode_partial(DOC, None).unwrap(), full);
+}
+
+#[test]
+fn test_decode_partial_complete_document() {
+    // A null in a one-column table is written as a blank row
+    for data in [
+        json!({"t": [{"x": 1}, {"x": null}, {"x": 2}], "n": 1}),
+        json!({"t": [{"x": 1}, {"x": null}]}),
+        json!([{"x": null}, {"x": "a"}]),
+        json!({"a": [{"t": [{"x": null}, {"x": 2}], "z": 1}]}),
+    ] {
+        let toon = toon_rust::encode(&data, None).unwrap();
+        let toon = toon.trim_end_matches('\n');
+        let full = decode(toon, None).unwrap();
+        assert_eq!(full, data);
+        assert_eq!(decode_partial(&format!("{toon}\n"), None).unwrap(), full);
+
+        let mut parser = PartialParser::new(None);
+        let mut completed = parser.feed(toon.as_bytes()).unwrap();
+        completed.extend(parser.finish().unwrap());
+        for leaf in &completed {
+            assert_eq!(pointer::get(&full, &leaf.path), Some(&leaf.value));
+        }
+        assert!(completed
+            .iter()
+            .any(|leaf| leaf.value == json!({"x": null})));
+    }
+}
+
+#[test]
+fn test_partial_parser_byte_at_a_time() {
+    let full = decode(DOC, None).unwrap();
+    let mut parser = PartialParser::new(None);
+    let mut completed = Vec::new();
+    // Values come out with the line that completes them
+    let mut reported_at = Vec::new();
+    for (i, byte) in DOC.bytes().enumerate() {
+        let chunk = parser.feed(&[byte]).unwrap();
+        reported_at.extend(chunk.iter().map(|_| i));
+        completed.extend(chunk);
+    }
+    completed.extend(parser.finish().unwrap());
+
+    assert_eq!(
+        paths(&completed),
+        [
+            "name",
+            "open",
+            "items[0]",
+            "items[1]",
+            "tags[0]",
+            "tags[1]",
+            "tags[2]",
+            "owner.name",
+            "owner.\"unit price\"",
+            "notes",
+            "steps[0].id",
+            "steps[0].done",
+            "steps[1]",
+            "empty",
+            "meta",
+        ]
+    );
+    for leaf in &completed {
+        assert_eq!(
+            pointer::get(&full, &leaf.path),
+            Some(&leaf.value),
+            "{}",
+            leaf.path
+        );
+    }
+    assert_eq!(completed[2].value, json!({"sku": "A1", "qty": 2}));
+    // The first row is reported at the end of its own line
+    assert_eq!(reported_at[2], DOC.find("  B2").unwrap() - 1);
+}
+
+#[test]
+fn test_partial_parser_root_array_and_primitive() {
+    let mut parser = PartialParser::new(None);
+    let completed = parser.feed(b"[2]{id}:\n  1\n  2").unwrap();
+    assert_eq!(paths(&completed), ["[0]"]);
+    assert_eq!(paths(&parser.finish().unwrap()), ["[1]"]);
+
+    let mut parser = PartialParser::new(None);
+    assert!(parser.feed(b"hello\n").unwrap().is_empty());
+    let completed = parser.finish().unwrap();
+    assert_eq!(completed[0].path, "");
+    assert_eq!(completed[0].value, json!("hello"));
+}
+
+#[test]
+fn test_partial_parser_split_utf8() {
+    let text = "city: Zürich\n".as_bytes();
+    let split = text.iter().position(|&b| b >= 0x80).unwrap() + 1;
+    let mut parser = PartialParser::new(None);
+    assert!(parser.feed(&text[..split]).unwrap().is_empty());
+    let completed = parser.feed(&text[split..]).unwrap();
+    assert_eq!(completed[0].value, json!("Zürich"));
+}
+
+#[test]
+fn test_partial_parser_error_position() {
+    let mut parser = PartialParser::new(None);
+    parser.feed(b"a: 1\nb: 2\nc:\n  d: 1\n").unwrap();
+    let err = parser.feed(b"   e: 2\n").unwrap_err();
+    match err {
+        Error::Parse { line, .. } => assert_eq!(line, 5),
+        err => panic!("unexpected error: {err:?}"),
+    }
+}
+
+#[test]
+fn test_partial_limits() {
+    let options = DecodeOptions::new().max_array_length(1000);
+    let err = decode_partial("items[999999]:\n", Some(&options)).unwrap_err();
+    assert!(err.to_string().contains("exceeds the limit"), "{err}");
+    let mut parser = PartialParser::new(Some(&options));
+    assert!(parser.feed(b"name: shop\n").is_ok());
+    assert!(parser.feed(b"items[999999]{a}:\n").is_err());
+
+    let options = DecodeOptions::new().max_string_length(3).max_cells(3);
+    assert!(decode_partial("name: shopping\n", Some(&options)).is_err());
+    assert!(decode_partial("tags[4]: a,b,c,d\n", Some(&options)).is_err());
+    assert!(decode_partial("rows[2]{a,b}:\n  1,2\n  3,4\n", Some(&options)).is_err());
+    assert_eq!(
+        decode_partial("tags[4]: a,b,c\n", Some(&options)).unwrap(),
+        json!({"tags": ["a", "b", "c"]})
+    );
+}
+
+#[test]
+fn test_partial_follows_decode_options() {
+    let options = DecodeOptions::new()
+        .indent_string("\t")
+        .expand_paths(true)
+        .empty_cells(EmptyCellPolicy::Omit);
+    let toon = "a.b: 1\nrows[2]{x,y}:\n\t1,\n\t,2\nc:\n\td: 2\n";
+    assert_eq!(
+        decode_partial(toon, Some(&options)).unwrap(),
+        decode(toon, Some(&options)).unwrap()
+    );
+
+    let mut parser = PartialParser::new(Some(&options));
+    let mut completed = parser.feed(toon.as_bytes()).unwrap();
+    completed.extend(parser.finish().unwrap());
+    let value = decode(toon, Some(&options)).unwrap();
+    for leaf in &completed {
+        assert_eq!(pointer::get(&value, &leaf.path).unwrap(), &leaf.value);
+    }
+    assert_eq!(paths(&completed), ["a.b", "rows[0]", "rows[1]", "c.d"]);
+    assert_eq!(completed[1].value, json!({"x": 1}));
+}
@@ -0,0 +1,123 @@
+//! Tests for row-level table patches
+
+use serde_json::json;
+use toon_rust::patch::{RowChange, TablePatch};
+use toon_rust::{apply_table_patch, decode, Error};
+
+#[test]
+fn test_table_patch_text() {
+    let text = "{sku,qty,note}:\n  +C3,5,\"a,b\"\n  ~A1,3,\n  -B2\n";
+    let patch: TablePatch = text.parse().unwrap();
+    assert_eq!(patch.columns(), ["sku", "qty", "note"]);
+    assert_eq!(
+        patch.changes(),
+        [
+            RowChange::Insert(vec![json!("C3"), json!(5), json!("a,b")]),
+            RowChange::Update(vec![json!("A1"), json!(3), json!(null)]),
+            RowChange::Delete(json!("B2")),
+        ]
+    );
+    assert_eq!(patch.to_string(), text);
+    assert_eq!(patch.to_string().parse::<TablePatch>().unwrap(), patch);
+
+    // Delimiters are read from the header, as in a table
+    let piped: TablePatch = "{id|name}:\n+1|a,b\n-2".parse().unwrap();
+    assert_eq!(
+        piped.changes()[0],
+        RowChange::Insert(vec![json!(1), json!("a,b")])
+    );
+
+    let errors = [
+        "",
+        "sku,qty:\n  +A1,2",
+        "{sku,qty}:\n  +A1",
+        "{sku,qty}:\n  -A1,2",
+        "{sku,qty}:\n  *A1,2",
+    ];
+    for text in errors {
+        assert!(
+            matches!(text.parse::<TablePatch>(), Err(Error::Parse { .. })),
+            "{text:?} should not parse"
+        );
+    }
+    let Err(Error::Parse { line, .. }) = "{sku,qty}:\n  +A1,2\n\n  ~B2".parse::<TablePatch>()
+    else {
+        panic!("expected a parse error");
+    };
+    assert_eq!(line, 4);
+}
+
+#[test]
+fn test_apply_table_patch() {
+    let mut doc = decode(
+        "items[3]{sku,qty,price}:\n  A1,2,9.5\n  B2,1,3\n  C3,0,1",
+        None,
+    )
+    .unwrap();
+    let mut patch = TablePatch::new(["sku", "qty"]);
+    patch
+        .update(&[json!("A1"), json!(4)])
+        .unwrap()
+        .delete(&json!("B2"))
+        .insert(&[json!("B2"), json!(7)])
+        .unwrap();
+    apply_table_patch(&mut doc["items"], &patch).unwrap();
+    assert_eq!(
+        doc["items"],
+        json!([
+            {"sku": "A1", "qty": 4, "price": 9.5},
+            {"sku": "C3", "qty": 0, "price": 1},
+            {"sku": "B2", "qty": 7}
+        ])
+    );
+
+    // Nothing changes when one change doesn't fit
+    let before = doc.clone();
+    let mut bad = TablePatch::new(["sku"]);
+    bad.delete(&json!("A1")).delete(&json!("A1"));
+    assert!(matches!(
+        apply_table_patch(&mut doc["items"], &bad),
+        Err(Error::MissingField(_))
+    ));
+    let mut bad = TablePatch::new(["sku"]);
+    bad.delete(&json!("A1")).insert(&[json!("C3")]).unwrap();
+    assert!(matches!(
+        apply_table_patch(&mut doc["items"], &bad),
+        Err(Error::Serialization(_))
+    ));
+    assert_eq!(doc, before);
+
+    assert!(matches!(
+        TablePatch::new(["sku", "qty"]).insert(&[json!("A1")]),
+        Err(Error::LengthMismatch { .. })
+    ));
+    assert!(apply_table_patch(&mut json!({"a": 1}), &patch).is_err());
+}
+
+#[test]
+fn test_table_patch_diff() {
+    let old = json!([
+        {"id": 1, "name": "a"},
+        {"id": 2, "name": "b"},
+        {"id": 3, "name": "c"}
+    ]);
+    let new = json!([
+        {"id": 3, "name": "c"},
+        {"id": 1, "name": "z"},
+        {"id": 4, "name": "d"}
+    ]);
+    let patch = TablePatch::diff(&old, &new, "id").unwrap();
+    assert_eq!(patch.to_string(), "{id,name}:\n  -2\n  ~1,z\n  +4,d\n");
+
+    let mut table = old.clone();
+    apply_table_patch(&mut table, &patch).unwrap();
+    assert_eq!(
+        table,
+        json!([
+            {"id": 1, "name": "z"},
+            {"id": 3, "name": "c"},
+            {"id": 4, "name": "d"}
+        ])
+    );
+    assert!(TablePatch::diff(&old, &json!([{"name": "x"}]), "id").is_err());
+}
@@ -0,0 +1,89 @@
+//! Tests for path lookups and updates
+
+use serde_json::json;
+use toon_rust::{decode, pointer, Error};
+
+#[test]
+fn test_pointer_get() {
+    let doc = json!({
+        "name": "shop",
+        "a/b": {"m~n": 1},
+        "meta": {"a.b": true, "unit price": 2},
+        "items": [
+            {"sku": "A1", "qty": 2, "price": 9.5},
+            {"sku": "B2", "qty": 1, "price": 3},
+            {"sku": 7, "qty": 0, "price": null}
+        ],
+        "grid": [[1, 2], [3, 4]]
+    });
+
+    assert_eq!(pointer::get(&doc, ""), Some(&doc));
+    assert_eq!(pointer::get(&doc, "/name"), Some(&json!("shop")));
+    assert_eq!(pointer::get(&doc, "/items/1/price"), Some(&json!(3)));
+    assert_eq!(pointer::get(&doc, "/a~1b/m~0n"), Some(&json!(1)));
+    assert_eq!(pointer::get(&doc, "/items/01"), None);
+    assert_eq!(pointer::get(&doc, "/items/3"), None);
+    assert_eq!(pointer::get(&doc, "/name/0"), None);
+
+    assert_eq!(pointer::get(&doc, "name"), Some(&json!("shop")));
+    assert_eq!(pointer::get(&doc, "items[0].sku"), Some(&json!("A1")));
+    assert_eq!(pointer::get(&doc, "items[sku=B2].qty"), Some(&json!(1)));
+    assert_eq!(pointer::get(&doc, "items[sku=7].qty"), Some(&json!(0)));
+    assert_eq!(pointer::get(&doc, "items[sku=C3]"), None);
+    assert_eq!(pointer::get(&doc, "grid[1][0]"), Some(&json!(3)));
+    assert_eq!(pointer::get(&doc, "meta.\"a.b\""), Some(&json!(true)));
+    assert_eq!(pointer::get(&doc, "meta.\"unit price\""), Some(&json!(2)));
+    assert_eq!(pointer::get(&doc, "items.0"), None);
+    assert_eq!(pointer::get(&json!([1, 2]), "[1]"), Some(&json!(2)));
+
+    // Malformed paths find nothing
+    assert_eq!(pointer::get(&doc, "items[x"), None);
+    assert_eq!(pointer::get(&doc, "items..sku"), None);
+}
+
+#[test]
+fn test_pointer_set() {
+    let mut doc = decode("items[2]{sku,price}:\n  A1,9.5\n  B2,3\ntags[1]: x", None).unwrap();
+
+    let old = pointer::set(&mut doc, "items[sku=B2].price", json!(4)).unwrap();
+    assert_eq!(old, Some(json!(3)));
+    assert_eq!(doc["items"][1]["price"], json!(4));
+
+    assert_eq!(
+        pointer::set(&mut doc, "/items/0/qty", json!(1)).unwrap(),
+        None
+    );
+    assert_eq!(doc["items"][0]["qty"], json!(1));
+
+    assert_eq!(pointer::set(&mut doc, "/tags/-", json!("y")).unwrap(), None);
+    assert_eq!(pointer::set(&mut doc, "tags[2]", json!("z")).unwrap(), None);
+    assert_eq!(doc["tags"], json!(["x", "y", "z"]));
+
+    *pointer::get_mut(&mut doc, "tags[0]").unwrap() = json!("w");
+    assert_eq!(doc["tags"][0], json!("w"));
+
+    assert!(matches!(
+        pointer::set(&mut doc, "/missing/key", json!(1)),
+        Err(Error::MissingField(_))
+    ));
+    assert!(matches!(
+        pointer::set(&mut doc, "tags[5]", json!(1)),
+        Err(Error::MissingField(_))
+    ));
+    assert!(matches!(
+        pointer::set(&mut doc, "items[sku=C3].price", json!(1)),
+        Err(Error::MissingField(_))
+    ));
+    assert!(matches!(
+        pointer::set(&mut doc, "items[", json!(1)),
+        Err(Error::Syntax(_))
+    ));
+
+    assert_eq!(
+        pointer::set(&mut doc, "", json!(1))
+            .unwrap()
+            .map(|v| v.is_object()),
+        Some(true)
+    );
+    assert_eq!(doc, json!(1));
+}
@@ -0,0 +1,130 @@
+//! Tests for the query language
+
+use serde_json::json;
+use toon_rust::query::Query;
+use toon_rust::{query, query_stream, Error};
+
+const SHOP: &str = "\
+name: shop
+items[3]{sku,qty,price}:
+  A1,2,9.5
+  B2,1,3
+  C3,5,null
+orders[2]:
+  - id: 7
+    tags[2]: new,gift
+  - id: 8
+    tags[0]:
+meta:
+  \"unit price\": 2
+  owner: ann";
+
+#[test]
+fn test_query_paths() {
+    assert_eq!(query(SHOP, "name", None).unwrap(), [json!("shop")]);
+    assert_eq!(query(SHOP, ".meta.owner", None).unwrap(), [json!("ann")]);
+    assert_eq!(
+        query(SHOP, r#".meta."unit price""#, None).unwrap(),
+        [json!(2)]
+    );
+    assert_eq!(query(SHOP, ".items[1].sku", None).unwrap(), [json!("B2")]);
+    assert_eq!(query(SHOP, ".items[-1].sku", None).unwrap(), [json!("C3")]);
+    assert_eq!(
+        query(SHOP, "items[].sku", None).unwrap(),
+        [json!("A1"), json!("B2"), json!("C3")]
+    );
+    assert_eq!(
+        query(SHOP, "orders[].tags[]", None).unwrap(),
+        [json!("new"), json!("gift")]
+    );
+    assert_eq!(
+        query(SHOP, ".meta | .[]", None).unwrap(),
+        [json!("ann"), json!(2)]
+    );
+    assert_eq!(query(SHOP, ".missing.deeper", None).unwrap(), [json!(null)]);
+    assert_eq!(
+        query(SHOP, ".name[]", None).unwrap(),
+        Vec::<serde_json::Value>::new()
+    );
+    assert_eq!(query(SHOP, ".", None).unwrap().len(), 1);
+
+    let root = "[2]{id}:\n  1\n  2";
+    assert_eq!(
+        query(root, ".[] | .id", None).unwrap(),
+        [json!(1), json!(2)]
+    );
+}
+
+#[test]
+fn test_query_select() {
+    let run = |q| query(SHOP, q, None).unwrap();
+    assert_eq!(
+        run("items[] | select(.qty > 1) | .sku"),
+        [json!("A1"), json!("C3")]
+    );
+    assert_eq!(run(r#"items[] | select(.sku == "B2") | .qty"#), [json!(1)]);
+    assert_eq!(
+        run("items[] | select(.price) | .sku"),
+        [json!("A1"), json!("B2")]
+    );
+    assert_eq!(
+        run("items[] | select(.price == null or .qty <= 1) | .sku"),
+        [json!("B2"), json!("C3")]
+    );
+    assert_eq!(
+        run("items[] | select((.qty >= 2 and .price > 5) or .sku == \"B2\") | .sku"),
+        [json!("A1"), json!("B2")]
+    );
+    assert_eq!(
+        run("items[] | select(.qty != 2.0) | .sku"),
+        [json!("B2"), json!("C3")]
+    );
+    assert_eq!(
+        run(r#"orders[] | select(.tags[] == "gift") | .id"#),
+        [json!(7)]
+    );
+}
+
+#[test]
+fn test_query_stream() {
+    let run = |q| query_stream(SHOP.as_bytes(), q, None).unwrap();
+    assert_eq!(
+        run("items[] | select(.qty > 1) | .sku"),
+        [json!("A1"), json!("C3")]
+    );
+    // Not a table, so decoded again from what was read while looking
+    assert_eq!(run("orders[].id"), [json!(7), json!(8)]);
+    assert_eq!(run("meta.owner"), [json!("ann")]);
+
+    let bad = "items[2]{sku,qty}:\n  A1,2\n  B2";
+    assert!(query_stream(bad.as_bytes(), "items[]", None).is_err());
+    assert!(query(bad, "items[]", None).is_err());
+}
+
+#[test]
+fn test_query_syntax_errors() {
+    for text in [
+        "",
+        "items[",
+        ".a.",
+        "select(.a > )",
+        "select(.a",
+        ".a .b",
+        "items[x]",
+        "| .a",
+        ".items | length",
+        "items[] | select(qty > 1)",
+    ] {
+        assert!(
+            matches!(text.parse::<Query>(), Err(Error::Syntax(_))),
+            "{text:?} should not parse"
+        );
+    }
+    assert!(matches!(query(SHOP, ".a |", None), Err(Error::Syntax(_))));
+    let err = query(SHOP, ".items | keys", None).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("unsupported function `keys` at column 10"),
+        "{err}"
+    );
+}
@@ -0,0 +1,82 @@
+//! Tests for pre-encoded fragments
+
+use serde_json::json;
+use toon_rust::lazy::LazyDocument;
+use toon_rust::{decode, DecodeOptions, RawToon, ToonWriter};
+
+const DOC: &str = "\
+name: shop
+items[2]{sku,qty}:
+  A1,2
+  B2,1
+tags[2]: a,b
+meta:
+  owner: ann
+  notes: |
+    first
+
+    third
+  orders[1]:
+    - id: 7
+      tags[1]: x
+empty:
+note: |
+  multi
+  line";
+
+#[test]
+fn test_raw_capture() {
+    let doc = LazyDocument::new(DOC, None).unwrap();
+    let raw = |key| doc.raw(key).unwrap().unwrap();
+    assert_eq!(raw("name").as_str(), "shop");
+    assert_eq!(raw("items").as_str(), "[2]{sku,qty}:\n  A1,2\n  B2,1");
+    assert_eq!(raw("tags").as_str(), "[2]: a,b");
+    assert!(raw("meta")
+        .as_str()
+        .starts_with("owner: ann\nnotes: |\n  first\n\n"));
+    assert!(!doc.is_decoded("items"));
+    for key in ["name", "items", "tags", "meta", "empty", "note"] {
+        assert_eq!(
+            raw(key).decode(None).unwrap(),
+            *doc.get(key).unwrap().unwrap(),
+            "{key}"
+        );
+    }
+    assert_eq!(doc.raw("missing").unwrap(), None);
+
+    // A key spread over several entries is gathered first
+    let expanded = DecodeOptions::new().expand_paths(true);
+    let doc = LazyDocument::new("a.b: 1\na.c: 2", Some(&expanded)).unwrap();
+    assert_eq!(
+        doc.raw("a").unwrap().unwrap().decode(None).unwrap(),
+        json!({"b": 1, "c": 2})
+    );
+}
+
+#[test]
+fn test_raw_field() {
+    let doc = LazyDocument::new(DOC, None).unwrap();
+    let mut writer = ToonWriter::new(Vec::new());
+    writer.begin_object("copy").unwrap();
+    for key in doc.keys() {
+        writer
+            .raw_field(key, &doc.raw(key).unwrap().unwrap())
+            .unwrap();
+    }
+    writer.end().unwrap().field("after", &json!(1)).unwrap();
+    let toon = String::from_utf8(writer.finish().unwrap()).unwrap();
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!({"copy": decode(DOC, None).unwrap(), "after": 1})
+    );
+
+    let value = json!({"a": [{"id": 1}, {"id": 2}], "b": {"c": true}});
+    let raw = RawToon::from_value(&value, None).unwrap();
+    let mut writer = ToonWriter::new(Vec::new());
+    writer.raw_field("value", &raw).unwrap();
+    let toon = String::from_utf8(writer.finish().unwrap()).unwrap();
+    assert_eq!(decode(&toon, None).unwrap(), json!({ "value": value }));
+
+    assert!(RawToon::new("a: 1\nb[3]: 1,2").is_err());
+    assert_eq!(RawToon::new("42").unwrap().to_string(), "42");
+}
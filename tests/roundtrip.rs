@@ -14,6 +14,44 @@ fn test_roundtrip_simple_object() {
     assert_eq!(original, decoded);
 }
 
+#[test]
+fn test_roundtrip_u64_max() {
+    let original = json!({
+        "id": u64::MAX,
+        "ids": [u64::MAX, i64::MAX as u64 + 1, i64::MIN],
+        "rows": [{"n": u64::MAX}, {"n": 1}]
+    });
+    let toon = encode(&original, None).unwrap();
+    assert!(toon.contains("id: 18446744073709551615\n"), "{toon}");
+    assert_eq!(decode(&toon, None).unwrap(), original);
+
+    // The streaming decoder and typed columns read them too
+    let streamed = toon_rust::decode_stream(toon.as_bytes(), None).unwrap();
+    assert_eq!(streamed, original);
+    let typed = "rows[1]{n:int}:\n  18446744073709551615";
+    assert_eq!(
+        decode(typed, None).unwrap(),
+        json!({"rows": [{"n": u64::MAX}]})
+    );
+}
+
+#[test]
+fn test_roundtrip_negative_list_items() {
+    // Mixed arrays are lists, whose negative items follow the `- ` marker
+    let original = json!({"a": [1, {"a": 1}, -1, -2.5, "-x", [-3, -4], -0.5e-3]});
+    let toon = encode(&original, None).unwrap();
+    assert!(toon.contains("\n  - -1\n  - -2.5\n"), "{toon}");
+    assert_eq!(decode(&toon, None).unwrap(), original);
+    let streamed = toon_rust::decode_stream(toon.as_bytes(), None).unwrap();
+    assert_eq!(streamed, original);
+    let lazy = toon_rust::lazy::LazyDocument::new(&toon, None).unwrap();
+    assert_eq!(lazy.get("a").unwrap(), Some(&original["a"]));
+
+    let original = json!([1, {"a": 1}, -1]);
+    let toon = encode(&original, None).unwrap();
+    assert_eq!(decode(&toon, None).unwrap(), original);
+}
+
 #[test]
 fn test_roundtrip_primitive_array() {
     let original = json!({
@@ -68,3 +106,596 @@ fn test_roundtrip_mixed_types() {
     let decoded = decode(&toon, None).unwrap();
     assert_eq!(original, decoded);
 }
+
+#[test]
+fn test_roundtrip_float_formatting() {
+    let floats = [
+        1.0,
+        0.1,
+        -0.5,
+        123.456,
+        1e21,
+        1e-7,
+        f64::MAX,
+        f64::MIN_POSITIVE,
+    ];
+    for f in floats {
+        let original = json!({ "value": f, "rows": [{"id": 1, "value": f}] });
+        let toon = encode(&original, None).unwrap();
+        let decoded = decode(&toon, None).unwrap();
+        assert_eq!(original, decoded, "{toon}");
+        assert_eq!(decoded["value"].as_f64(), Some(f));
+    }
+}
+
+#[test]
+fn test_roundtrip_block_strings() {
+    use toon_rust::{decode_stream, DecodeOptions, EncodeOptions};
+
+    let data = json!({
+        "notes": "first\n\n  second\nthird\n",
+        "nested": {"text": "a\nb", "after": 1},
+        "last": true
+    });
+    let options = EncodeOptions::new().block_strings(true);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert_eq!(decode(&toon, None).unwrap(), data);
+    assert_eq!(decode_stream(toon.as_bytes(), None).unwrap(), data);
+
+    // Non-strict decoding keeps block bodies intact while re-indenting
+    let loose = toon
+        .replace("\n  ", "\n   ")
+        .replace("\n      ", "\n         ");
+    let lenient = DecodeOptions::new().strict(false);
+    assert_eq!(decode(&loose, Some(&lenient)).unwrap(), data);
+}
+
+#[test]
+fn test_roundtrip_list_objects() {
+    use toon_rust::{decode_stream, encode_stream};
+
+    let data = json!({
+        "items": [
+            {"id": 1, "name": "Ann", "meta": {"role": "admin", "tags": ["a", "b"]}},
+            {"id": 2, "url": "http://example.com"},
+            "plain",
+            {"rows": [{"x": 1, "y": 2}, {"x": 3, "y": 4}], "z": true}
+        ],
+        "after": 1
+    });
+    let toon = encode(&data, None).unwrap();
+    assert_eq!(
+        toon,
+        "after: 1\nitems[4]:\n  - id: 1\n    meta: \n      role: admin\n      tags[2]:a,b\n    name: Ann\n  - id: 2\n    url: http://example.com\n  - plain\n  - rows[2]{x,y}:\n      1,2\n      3,4\n\n    z: true\n"
+    );
+    assert_eq!(decode(&toon, None).unwrap(), data);
+    assert_eq!(decode_stream(toon.as_bytes(), None).unwrap(), data);
+
+    let mut streamed = Vec::new();
+    encode_stream(&data, &mut streamed, None).unwrap();
+    assert_eq!(String::from_utf8(streamed).unwrap(), toon);
+}
+
+#[test]
+fn test_roundtrip_tabular_newlines() {
+    let data = json!({"rows": [{"id": 1, "note": "line one\nline two"}, {"id": 2, "note": "x"}]});
+    let toon = encode(&data, None).unwrap();
+    assert_eq!(decode(&toon, None).unwrap(), data);
+}
+
+#[test]
+fn test_roundtrip_null_policy() {
+    use toon_rust::options::NullPolicy;
+    use toon_rust::EncodeOptions;
+
+    let data = json!({"a": null, "user": {"id": 1, "email": null}, "items": [{"x": null, "y": 1}, 2], "z": null});
+
+    let toon = encode(&data, None).unwrap();
+    assert!(toon.starts_with("a: null\n"));
+    assert_eq!(decode(&toon, None).unwrap(), data);
+
+    let options = EncodeOptions::new().null_policy(NullPolicy::EmptyCell);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(!toon.contains("null"));
+    assert_eq!(decode(&toon, None).unwrap(), data);
+
+    let options = EncodeOptions::new().null_policy(NullPolicy::OmitKey);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!({"user": {"id": 1}, "items": [{"y": 1}, 2]})
+    );
+
+    let mut buffer = Vec::new();
+    toon_rust::encode_stream(&data, &mut buffer, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), toon);
+}
+
+#[test]
+fn test_roundtrip_inline_nulls() {
+    let data = json!({"values": [1, null, 3], "words": ["null", null], "mixed": [{"a": 1}, null]});
+    let toon = encode(&data, None).unwrap();
+    assert!(toon.contains("values[3]:1,null,3"));
+    assert_eq!(decode(&toon, None).unwrap(), data);
+
+    let mut buffer = Vec::new();
+    toon_rust::encode_stream(&data, &mut buffer, None).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), toon);
+
+    // Empty cells from older output still decode as nulls
+    assert_eq!(
+        decode("values[3]: 1,,3", None).unwrap(),
+        json!({"values": [1, null, 3]})
+    );
+    assert_eq!(
+        toon_rust::decode_stream("values[3]: 1,,3".as_bytes(), None).unwrap(),
+        json!({"values": [1, null, 3]})
+    );
+}
+
+#[test]
+fn test_roundtrip_inline_thresholds() {
+    use toon_rust::EncodeOptions;
+
+    let data = json!({"short": [1, 2], "long": [1, 2, 3, 4], "items": [[5, 6, 7, 8], "x"]});
+
+    let options = EncodeOptions::new().max_inline_items(3);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("short[2]:1,2"));
+    assert!(toon.contains("long[4]:\n  - 1\n  - 2\n  - 3\n  - 4\n"));
+    assert_eq!(decode(&toon, None).unwrap(), data);
+
+    let mut buffer = Vec::new();
+    toon_rust::encode_stream(&data, &mut buffer, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), toon);
+
+    // `long[4]:1,2,3,4` is 15 characters wide
+    let options = EncodeOptions::new().max_line_width(14);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("long[4]:\n"));
+    assert!(toon.contains("short[2]:1,2"));
+    assert_eq!(decode(&toon, None).unwrap(), data);
+    let options = EncodeOptions::new().max_line_width(15);
+    assert!(encode(&data, Some(&options))
+        .unwrap()
+        .contains("long[4]:1,2,3,4"));
+
+    // Root arrays switch too
+    let options = EncodeOptions::new().max_inline_items(1);
+    let toon = encode(&json!(["a", "b"]), Some(&options)).unwrap();
+    assert_eq!(toon, "[2]:\n  - a\n  - b\n");
+    assert_eq!(decode(&toon, None).unwrap(), json!(["a", "b"]));
+}
+
+#[test]
+fn test_roundtrip_key_folding() {
+    use toon_rust::{DecodeOptions, EncodeOptions};
+
+    let data = json!({
+        "server": {"http": {"port": 8080}},
+        "db": {"pool": {"min": 1, "max": 4}},
+        "tags": {"list": ["a", "b"]},
+        "example.com": {"owner": "me"},
+        "plain": 1
+    });
+    let options = EncodeOptions::new().key_folding(true);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("server.http.port: 8080"));
+    assert!(toon.contains("db.pool: \n  max: 4\n  min: 1"));
+    assert!(toon.contains("tags.list[2]:a,b"));
+    // A key that already holds a dot is never folded, and is quoted so it
+    // doesn't expand as a path
+    assert!(toon.contains("\"example.com\": \n  owner: me"));
+
+    let mut buffer = Vec::new();
+    toon_rust::encode_stream(&data, &mut buffer, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), toon);
+
+    // Without expansion, dotted keys are ordinary keys
+    let decoded = decode(&toon, None).unwrap();
+    assert_eq!(decoded["server.http.port"], json!(8080));
+
+    let decode_options = DecodeOptions::new().expand_paths(true);
+    let expanded = decode(
+        "server.http.port: 8080\nserver.http.host: x\n",
+        Some(&decode_options),
+    )
+    .unwrap();
+    assert_eq!(
+        expanded,
+        json!({"server": {"http": {"port": 8080, "host": "x"}}})
+    );
+    let streamed =
+        toon_rust::decode_stream("a.b: 1\na.c: 2\n".as_bytes(), Some(&decode_options)).unwrap();
+    assert_eq!(streamed, json!({"a": {"b": 1, "c": 2}}));
+    assert_eq!(
+        decode("a..b: 1\n", Some(&decode_options)).unwrap(),
+        json!({"a..b": 1})
+    );
+
+    assert_eq!(decode(&toon, Some(&decode_options)).unwrap(), data);
+    assert_eq!(
+        decode("\"a.b\": 1\n", Some(&decode_options)).unwrap(),
+        json!({"a.b": 1})
+    );
+}
+
+#[test]
+fn test_roundtrip_key_folding_literal_dots() {
+    use toon_rust::{DecodeOptions, EncodeOptions};
+
+    let options = EncodeOptions::new().key_folding(true);
+    let decode_options = DecodeOptions::new().expand_paths(true);
+    let roundtrip = |data: &serde_json::Value| {
+        let toon = encode(data, Some(&options)).unwrap();
+        assert_eq!(&decode(&toon, Some(&decode_options)).unwrap(), data);
+        let mut buffer = Vec::new();
+        toon_rust::encode_stream(data, &mut buffer, Some(&options)).unwrap();
+        let streamed = String::from_utf8(buffer).unwrap();
+        assert_eq!(&decode(&streamed, Some(&decode_options)).unwrap(), data);
+        toon
+    };
+
+    let data = json!({"version.major": 1});
+    assert_eq!(roundtrip(&data), "\"version.major\": 1");
+
+    // A path spelled like a sibling key isn't folded
+    let data = json!({"a": {"b": 2}, "a.b": 1});
+    assert_eq!(roundtrip(&data), "a: \n  b: 2\n\"a.b\": 1");
+
+    // Rows with both `u` and `u.id` can't share a `u.id` column
+    let data = json!({"rows": [
+        {"u": {"id": 1}, "u.id": 2},
+        {"u": {"id": 3}, "u.id": 4}
+    ]});
+    let toon = roundtrip(&data);
+    assert!(!toon.contains('{'));
+    assert!(toon.contains("\"u.id\": 2"));
+
+    // Dotted header fields are paths only when unquoted
+    let toon = "rows[1]{\"u.id\",u.name}:\n  1,Ann\n";
+    assert_eq!(
+        decode(toon, Some(&decode_options)).unwrap(),
+        json!({"rows": [{"u.id": 1, "u": {"name": "Ann"}}]})
+    );
+}
+
+#[test]
+fn test_roundtrip_dotted_columns() {
+    use toon_rust::{DecodeOptions, EncodeOptions};
+
+    let data = json!({"scores": [
+        {"user": {"id": 1, "name": "Ann"}, "score": 5},
+        {"user": {"id": 2, "name": "Bob"}, "score": 7}
+    ]});
+
+    // Without key folding, nested values keep the array in the list format
+    let toon = encode(&data, None).unwrap();
+    assert!(toon.contains("scores[2]:\n  - "));
+    assert_eq!(decode(&toon, None).unwrap(), data);
+
+    let options = EncodeOptions::new().key_folding(true);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert_eq!(
+        toon,
+        "scores[2]{score,user.id,user.name}:\n  5,1,Ann\n  7,2,Bob\n"
+    );
+    let mut buffer = Vec::new();
+    toon_rust::encode_stream(&data, &mut buffer, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), toon);
+
+    let decode_options = DecodeOptions::new().expand_paths(true);
+    assert_eq!(decode(&toon, Some(&decode_options)).unwrap(), data);
+
+    // Nested arrays can't be cells
+    let data = json!([{"user": {"tags": ["a"]}}]);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(!toon.contains('{'));
+}
+
+#[test]
+fn test_roundtrip_sparse_tables() {
+    use toon_rust::options::EmptyCellPolicy;
+    use toon_rust::{DecodeOptions, EncodeOptions};
+
+    let data = json!({"rows": [
+        {"id": 1, "name": "a", "note": null},
+        {"id": 2, "name": "b", "note": "x"},
+        {"id": 3, "note": null}
+    ]});
+    let options = EncodeOptions::new().tabular_uniformity(0.5);
+    let toon = encode(&data, Some(&options)).unwrap();
+    // Nulls are spelled out, so an empty cell only means a missing field
+    assert_eq!(
+        toon,
+        "rows[3]{id,name,note}:\n  1,a,null\n  2,b,x\n  3,,null\n"
+    );
+    let mut buffer = Vec::new();
+    toon_rust::encode_stream(&data, &mut buffer, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), toon);
+
+    let omit = DecodeOptions::new().empty_cells(EmptyCellPolicy::Omit);
+    assert_eq!(decode(&toon, Some(&omit)).unwrap(), data);
+    assert_eq!(
+        decode(&toon, None).unwrap()["rows"][2],
+        json!({"id": 3, "name": null, "note": null})
+    );
+
+    // Quoted empty strings are not empty cells
+    let decoded = decode("rows[1]{a,b}:\n  \"\",\n", Some(&omit)).unwrap();
+    assert_eq!(decoded, json!({"rows": [{"a": ""}]}));
+
+    // Uniform tables keep null cells empty
+    let uniform = json!([{"a": null, "b": 1}]);
+    assert_eq!(encode(&uniform, None).unwrap(), "[1]{a,b}:\n  ,1\n");
+}
+
+#[test]
+fn test_roundtrip_without_lengths() {
+    use toon_rust::{DecodeOptions, EncodeOptions};
+
+    let data = json!({
+        "items": [{"sku": "a1", "qty": 2}, {"sku": "b2", "qty": 1}],
+        "tags": ["x", "y"],
+        "empty": [],
+        "groups": [{"rows": [{"id": 1}], "name": "g"}, [1, 2]]
+    });
+    let options = EncodeOptions::new().emit_lengths(false);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("items{qty,sku}:\n  2,a1\n  1,b2\n"));
+    assert!(toon.contains("tags[]:x,y"));
+    assert!(toon.contains("empty[]:"));
+    assert!(!["[0]", "[1]", "[2]"]
+        .iter()
+        .any(|header| toon.contains(header)));
+    let mut buffer = Vec::new();
+    toon_rust::encode_stream(&data, &mut buffer, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), toon);
+
+    let lenient = DecodeOptions::new().lenient_arrays(true);
+    assert_eq!(decode(&toon, Some(&lenient)).unwrap(), data);
+    assert_eq!(
+        toon_rust::decode_stream(toon.as_bytes(), Some(&lenient)).unwrap(),
+        data
+    );
+    // Non-strict decoding reads the headers too; strict decoding still
+    // requires lengths
+    let loose = DecodeOptions::new().strict(false);
+    assert_eq!(decode(&toon, Some(&loose)).unwrap(), data);
+    assert_eq!(
+        toon_rust::decode_stream(toon.as_bytes(), Some(&loose)).unwrap(),
+        data
+    );
+    assert!(decode(&toon, None).is_err());
+
+    // Rows can be added by hand without updating a count
+    let edited = toon.replace("  1,b2\n", "  1,b2\n  5,c3\n");
+    let decoded = decode(&edited, Some(&lenient)).unwrap();
+    assert_eq!(decoded["items"][2], json!({"sku": "c3", "qty": 5}));
+
+    let rows: Vec<_> = toon_rust::decode_rows(&toon, "items", Some(&lenient))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(rows.len(), 2);
+
+    let root = json!([{"id": 1}, {"id": 2}]);
+    let toon = encode(&root, Some(&options)).unwrap();
+    assert_eq!(toon, "[]{id}:\n  1\n  2\n");
+    assert_eq!(decode(&toon, Some(&lenient)).unwrap(), root);
+    assert_eq!(decode(&toon, Some(&loose)).unwrap(), root);
+}
+
+#[test]
+fn test_roundtrip_indent_string() {
+    use toon_rust::{DecodeOptions, EncodeOptions};
+
+    let data = json!({
+        "user": {"name": "Ann", "tags": ["a", "b"], "address": {"city": "Oslo"}},
+        "rows": [{"id": 1, "ok": true}, {"id": 2, "ok": false}],
+        "items": [{"name": "x", "qty": 1}, "y"],
+        "notes": "line one\nline two"
+    });
+    let options = EncodeOptions::new().indent_string("\t").block_strings(true);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("user: \n\taddress: \n\t\tcity: Oslo"));
+    assert!(toon.contains("rows[2]{id,ok}:\n\t1,true\n"));
+    assert!(!toon.contains("  "));
+    let mut buffer = Vec::new();
+    toon_rust::encode_stream(&data, &mut buffer, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), toon);
+
+    let tabs = DecodeOptions::new().indent_string("\t");
+    assert_eq!(decode(&toon, Some(&tabs)).unwrap(), data);
+    assert_eq!(
+        toon_rust::decode_stream(toon.as_bytes(), Some(&tabs)).unwrap(),
+        data
+    );
+    assert_eq!(
+        decode(&toon, Some(&tabs.clone().strict(false))).unwrap(),
+        data
+    );
+
+    // A unit of spaces is the same as a width
+    let options = EncodeOptions::new().indent_string("    ");
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("\n        city: Oslo"));
+    let spaces = DecodeOptions::new().indent_string("    ");
+    assert_eq!(decode(&toon, Some(&spaces)).unwrap(), data);
+    assert_eq!(
+        decode(&toon, Some(&DecodeOptions::new().indent(4))).unwrap(),
+        data
+    );
+}
+
+#[test]
+fn test_roundtrip_compact_style() {
+    use toon_rust::options::Style;
+    use toon_rust::{DecodeOptions, EncodeOptions};
+
+    let data = json!({
+        "name": "Ann Lee",
+        "n": -1.5,
+        "ok": true,
+        "none": null,
+        "url": "http://x",
+        "user": {"id": 1, "tags": ["a", "b"]},
+        "items": [{"sku": "a", "qty": 1}, {"sku": "b", "qty": 2}],
+        "mixed": [{"k": 1, "v": [1, 2]}, "http://y", 3]
+    });
+    let options = EncodeOptions::new().style(Style::Compact);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("name:\"Ann Lee\"\n"));
+    assert!(toon.contains("user:\n  id:1\n"));
+    // The marker line keeps its space so the item still reads as an object
+    assert!(toon.contains("  - k: 1\n    v[2]:1,2\n"));
+    assert!(toon.contains("  - http://y\n"));
+    let mut buffer = Vec::new();
+    toon_rust::encode_stream(&data, &mut buffer, Some(&options)).unwrap();
+    assert_eq!(String::from_utf8(buffer).unwrap(), toon);
+
+    assert_eq!(decode(&toon, None).unwrap(), data);
+    assert_eq!(
+        toon_rust::decode_stream(toon.as_bytes(), None).unwrap(),
+        data
+    );
+    let loose = DecodeOptions::new().strict(false);
+    assert_eq!(decode(&toon, Some(&loose)).unwrap(), data);
+
+    let notes = json!({"notes": "one\ntwo", "z": 1});
+    let block = encode(&notes, Some(&options.clone().block_strings(true))).unwrap();
+    assert_eq!(block, "notes:|-\n  one\n  two\nz:1");
+    assert_eq!(decode(&block, None).unwrap(), notes);
+
+    let pretty = encode(&data, Some(&EncodeOptions::new().style(Style::Pretty))).unwrap();
+    assert_eq!(pretty, encode(&data, None).unwrap());
+    assert!(pretty.len() > toon.len());
+}
+
+#[test]
+fn test_roundtrip_quoted_keys() {
+    use toon_rust::options::Delimiter;
+    use toon_rust::{decode_rows, decode_stream, EncodeOptions};
+
+    let data = json!({
+        "order id": 5,
+        "a:b": "colon",
+        "tags[0]": ["x", "y"],
+        "": "empty",
+        "-dash": true,
+        "say \"hi\"": "quote",
+        "plain": {"inner key": 1, "ok": 2},
+        "items": [
+            {"sku id": "A1", "a,b": 2, "c|d": 3},
+            {"sku id": "B2", "a,b": 4, "c|d": 5}
+        ],
+        "list": [{"first key": 1, "x": [1, {"y": 2}]}, "text"]
+    });
+
+    let toon = encode(&data, None).unwrap();
+    assert!(toon.contains("\"order id\": 5\n"));
+    assert!(toon.contains("\"tags[0]\"[2]:x,y"));
+    assert!(toon.contains("items[2]{\"a,b\",c|d,\"sku id\"}:\n"));
+    assert!(toon.contains("  - \"first key\": 1\n"));
+    assert_eq!(decode(&toon, None).unwrap(), data);
+    assert_eq!(decode_stream(toon.as_bytes(), None).unwrap(), data);
+
+    let rows: Vec<_> = decode_rows("\"my items\"[1]{\"a b\",c}:\n  1,2", "my items", None)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(rows[0]["a b"], json!(1));
+
+    let options = EncodeOptions::new().delimiter(Delimiter::Pipe);
+    let toon = encode(&data, Some(&options)).unwrap();
+    assert!(toon.contains("\"c|d\""));
+
+    // Keys needing quotes are never folded into a dotted path
+    let nested = json!({"a": {"b c": {"d": 1}}});
+    let options = EncodeOptions::new().key_folding(true);
+    let toon = encode(&nested, Some(&options)).unwrap();
+    assert_eq!(toon, "a: \n  \"b c\": \n    d: 1");
+}
+
+#[test]
+fn test_roundtrip_delimiters() {
+    use toon_rust::options::Delimiter;
+    use toon_rust::{decode_stream, EncodeOptions};
+
+    let data = json!({
+        "items": [
+            {"sku": "A1", "qty": 2, "note": "x,y"},
+            {"sku": "B2", "qty": 1, "note": "z"}
+        ],
+        "tags": ["a", "b,c"],
+        "z": "p|q\tr"
+    });
+    for delimiter in [Delimiter::Comma, Delimiter::Tab, Delimiter::Pipe] {
+        let options = EncodeOptions::new().delimiter(delimiter);
+        let toon = encode(&data, Some(&options)).unwrap();
+        assert_eq!(decode(&toon, None).unwrap(), data, "{delimiter:?}");
+        assert_eq!(decode_stream(toon.as_bytes(), None).unwrap(), data);
+    }
+
+    // A delimiter used further down doesn't change how earlier rows split
+    let toon = "items[2]{a,b}:\n  1,2\n  3,4\ntags[2]: x,y\nnote: p|q\tr";
+    let decoded = decode(toon, None).unwrap();
+    assert_eq!(decoded["items"][1], json!({"a": 3, "b": 4}));
+    assert_eq!(decoded["tags"], json!(["x", "y"]));
+    assert_eq!(decode_stream(toon.as_bytes(), None).unwrap(), decoded);
+}
+
+#[test]
+fn test_roundtrip_safe() {
+    use toon_rust::{decode_stream, EncodeOptions};
+
+    let options = EncodeOptions::new().roundtrip_safe(true);
+    let cases = [
+        json!({"a": {}}),
+        json!({"o": {"x": {}, "y": 1}}),
+        json!({"l": [[], {}, "", [1], {"a": {}, "b": 1}, "{}", [[{}]]]}),
+        json!([{}]),
+        json!([[]]),
+        json!(""),
+        json!("a:b"),
+        json!("true"),
+        json!(42),
+    ];
+    for value in cases {
+        let toon = encode(&value, Some(&options)).unwrap();
+        assert_eq!(decode(&toon, None).unwrap(), value, "{toon:?}");
+        assert_eq!(decode_stream(toon.as_bytes(), None).unwrap(), value);
+    }
+    assert_eq!(encode(&json!({"a": {}}), Some(&options)).unwrap(), "a: {}");
+    assert_eq!(
+        encode(&json!([{}]), Some(&options)).unwrap(),
+        "[1]:\n  - {}\n"
+    );
+    assert_eq!(encode(&json!("x"), Some(&options)).unwrap(), "\"x\"");
+
+    // Lossy settings are overridden
+    let lossy = EncodeOptions::new()
+        .roundtrip_safe(true)
+        .null_policy(toon_rust::options::NullPolicy::OmitKey)
+        .key_folding(true)
+        .quote_reserved_words(false);
+    let value = json!({"n": null, "a": {"b": {"c": "true"}}});
+    let toon = encode(&value, Some(&lossy)).unwrap();
+    assert_eq!(decode(&toon, None).unwrap(), value);
+
+    #[cfg(feature = "serde")]
+    {
+        use toon_rust::serde_api::to_writer_streaming_with_options;
+
+        let value = json!({"l": [{}, {"a": {}}], "o": {}});
+        let mut out = Vec::new();
+        to_writer_streaming_with_options(&value, &mut out, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            encode(&value, Some(&options)).unwrap()
+        );
+    }
+
+    // Without the option, an empty object still decodes as null
+    assert_eq!(encode(&json!({"a": {}}), None).unwrap(), "a: ");
+    assert_eq!(decode("a: ", None).unwrap(), json!({"a": null}));
+    assert_eq!(decode("hello", None).unwrap(), json!("hello"));
+}
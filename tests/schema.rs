@@ -0,0 +1,106 @@
+use serde_json::json;
+use toon_rust::schema::Field;
+use toon_rust::{decode, infer_schema, Error, ToonSchema};
+
+#[test]
+fn test_infer_schema() {
+    let value = json!({
+        "name": "shop",
+        "items": [
+            {"sku": "A1", "qty": 2, "note": "gift"},
+            {"sku": "B2", "qty": 1.5, "note": null},
+            {"sku": "C3", "qty": 4}
+        ],
+        "meta": {"tags": ["a", "b"], "owner": {"id": 7}},
+        "history": [[1, 2], []],
+        "mixed": [1, "two"]
+    });
+    let schema = infer_schema(&value);
+    let ToonSchema::Object(fields) = &schema else {
+        panic!("expected an object, got {schema:?}");
+    };
+    assert_eq!(
+        fields[1],
+        Field {
+            name: "items".to_string(),
+            schema: ToonSchema::Table(vec![
+                Field {
+                    name: "note".to_string(),
+                    schema: ToonSchema::Union(vec![ToonSchema::String, ToonSchema::Null]),
+                    optional: true,
+                },
+                Field {
+                    name: "qty".to_string(),
+                    schema: ToonSchema::Number,
+                    optional: false,
+                },
+                Field {
+                    name: "sku".to_string(),
+                    schema: ToonSchema::String,
+                    optional: false,
+                },
+            ]),
+            optional: false,
+        }
+    );
+    assert_eq!(
+        schema.to_string(),
+        "history[]: [integer]\n\
+         items[]{note?: string|null, qty: number, sku: string}\n\
+         meta:\n  owner:\n    id: integer\n  tags[]: string\n\
+         mixed[]: integer|string\n\
+         name: string"
+    );
+
+    assert_eq!(infer_schema(&json!([])).to_string(), "[]: any");
+    assert_eq!(infer_schema(&json!({})).to_string(), "{}");
+    assert_eq!(infer_schema(&json!({"a b": {}})).to_string(), "\"a b\": {}");
+
+    // Objects holding nested values aren't table rows
+    let list = infer_schema(&json!([{"id": 1, "tags": ["x"]}, {"id": 2}]));
+    assert_eq!(list.to_string(), "[]: {id: integer, tags?: [string]}");
+}
+
+#[test]
+fn test_schema_validate() {
+    let doc = decode("name: shop\nitems[2]{sku,qty}:\n  A1,2\n  B2,1", None).unwrap();
+    let schema = infer_schema(&doc);
+    assert!(schema.validate(&doc).is_ok());
+
+    let bad = json!({
+        "items": [{"sku": "A1", "qty": "two"}, {"sku": "B2", "qty": 1, "extra": true}, 5]
+    });
+    let errors = schema.validate(&bad).unwrap_err();
+    let found: Vec<_> = errors
+        .iter()
+        .map(|error| match error {
+            Error::Deserialization { message, path, .. } => (path.clone(), message.clone()),
+            other => panic!("unexpected error {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (
+                Some("items[0].qty".to_string()),
+                "expected integer, found a string".to_string()
+            ),
+            (
+                Some("items[1].extra".to_string()),
+                "unexpected field".to_string()
+            ),
+            (
+                Some("items[2]".to_string()),
+                "expected a table row, found a number".to_string()
+            ),
+            (Some("name".to_string()), "missing field".to_string()),
+        ]
+    );
+
+    // Each row only has to fit one member of a union
+    let schema = infer_schema(&json!([1, "a", null]));
+    assert!(schema.validate(&json!([null, 2, "b"])).is_ok());
+    assert_eq!(schema.validate(&json!([true])).unwrap_err().len(), 1);
+    assert!(infer_schema(&json!(1.5)).validate(&json!(3)).is_ok());
+    assert!(infer_schema(&json!(3)).validate(&json!(1.5)).is_err());
+}
@@ -3,7 +3,14 @@
 #[cfg(feature = "serde")]
 mod serde_tests {
     use serde::{Deserialize, Serialize};
-    use toon_rust::{from_str, to_string};
+    use toon_rust::options::NanPolicy;
+    use toon_rust::serde_api::{
+        from_str_with_options, to_string_with_options, to_writer_streaming_with_options,
+    };
+    use toon_rust::{
+        decode, from_str, from_str_iter, to_string, to_writer_streaming, DecodeOptions,
+        EncodeOptions, RotatingWriter, TabularWriter,
+    };
 
     #[derive(Serialize, Deserialize, Debug, PartialEq)]
     struct Product {
@@ -51,4 +58,409 @@ mod serde_tests {
         let decoded: User = from_str(&toon).unwrap();
         assert_eq!(user, decoded);
     }
+
+    #[derive(Serialize)]
+    struct Reading {
+        sensor: String,
+        value: f64,
+    }
+
+    #[test]
+    fn test_serde_nan_policy() {
+        let readings = vec![
+            Reading {
+                sensor: "a".to_string(),
+                value: f64::NAN,
+            },
+            Reading {
+                sensor: "b".to_string(),
+                value: f64::NEG_INFINITY,
+            },
+        ];
+
+        let options = EncodeOptions::new().nan_policy(NanPolicy::Error);
+        assert!(to_string_with_options(&readings, &options).is_err());
+
+        let options = EncodeOptions::new().nan_policy(NanPolicy::Token);
+        let toon = to_string_with_options(&readings, &options).unwrap();
+        assert!(toon.contains("a,nan"));
+        assert!(toon.contains("b,-inf"));
+
+        let toon = to_string(&readings).unwrap();
+        assert!(toon.contains("a,\n"));
+
+        // Maps cannot forge the private marker used for the literals
+        let mut forged = std::collections::HashMap::new();
+        forged.insert("$toon_rust::private::NonFinite".to_string(), "nan");
+        assert!(to_string_with_options(&forged, &options).is_err());
+        assert!(to_string(&serde_json::json!({"$toon_rust::private::NonFinite": "nan"})).is_err());
+    }
+
+    #[test]
+    fn test_serde_nan_round_trip() {
+        #[derive(Serialize, Deserialize, Debug)]
+        struct Sample {
+            sensor: String,
+            value: f64,
+            limit: Option<f32>,
+        }
+
+        let options = EncodeOptions::new().nan_policy(NanPolicy::Token);
+        let allow_nan = DecodeOptions::new().allow_nan(true);
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let toon = to_string_with_options(&value, &options).unwrap();
+            let back: f64 = from_str_with_options(&toon, Some(&allow_nan)).unwrap();
+            assert_eq!(back.to_bits(), value.to_bits(), "{toon}");
+        }
+
+        let samples = vec![
+            Sample {
+                sensor: "a".to_string(),
+                value: f64::NAN,
+                limit: Some(f32::INFINITY),
+            },
+            Sample {
+                sensor: "b".to_string(),
+                value: -1.5,
+                limit: None,
+            },
+        ];
+        let toon = to_string_with_options(&samples, &options).unwrap();
+        let back: Vec<Sample> = from_str_with_options(&toon, Some(&allow_nan)).unwrap();
+        assert!(back[0].value.is_nan());
+        assert_eq!(back[0].limit, Some(f32::INFINITY));
+        assert_eq!(back[1].value, -1.5);
+        assert_eq!(back[1].limit, None);
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct LineItem {
+        sku: String,
+        qty: u32,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Order {
+        id: u32,
+        items: Vec<LineItem>,
+    }
+
+    #[test]
+    fn test_serde_error_path_and_line() {
+        let toon = "id: 7\nitems[2]{sku,qty}:\n  A1,2\n  B2,many";
+        let err = from_str::<Order>(toon).unwrap_err();
+        match &err {
+            toon_rust::Error::Deserialization { path, line, .. } => {
+                assert_eq!(path.as_deref(), Some("items[1].qty"));
+                assert_eq!(*line, Some(4));
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+        assert!(err.to_string().contains("at `items[1].qty` (line 4)"));
+
+        let err = from_str::<Order>("id: 7").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Deserialization error: missing field `items`"
+        );
+    }
+
+    #[test]
+    fn test_serde_error_line_follows_options() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Wrapper {
+            order: Order,
+        }
+
+        let line = |toon: &str, options: &DecodeOptions| match from_str_with_options::<Wrapper>(
+            toon,
+            Some(options),
+        )
+        .unwrap_err()
+        {
+            toon_rust::Error::Deserialization { line, .. } => line,
+            other => panic!("unexpected error: {other:?}"),
+        };
+        let tabs = DecodeOptions::new().indent_string("\t");
+        let toon = "order:\n\tid: 7\n\titems[2]{sku,qty}:\n\t\tA1,2\n\t\tB2,many";
+        assert_eq!(line(toon, &tabs), Some(5));
+
+        let toon = "\"order\":\n  \"id\": 7\n  \"items\"[1]:\n    - sku: A1\n      qty: x";
+        assert_eq!(line(toon, &DecodeOptions::new()), Some(5));
+
+        let expand = DecodeOptions::new().expand_paths(true);
+        let toon = "order.id: 7\norder.items[2]{sku,qty}:\n  A1,2\n  B2,many";
+        assert_eq!(line(toon, &expand), Some(4));
+        let toon = "order.items[1]{sku,qty}:\n  A1,2\norder.id: seven";
+        assert_eq!(line(toon, &expand), Some(3));
+    }
+
+    #[test]
+    fn test_serde_from_str_iter() {
+        let toon = "[3]{sku,qty}:\n  A1,2\n  B2,many\n  C3,1";
+        let items: Vec<_> = from_str_iter::<LineItem>(toon, None).collect();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap().qty, 2);
+        match &items[1] {
+            Err(toon_rust::Error::Deserialization { path, line, .. }) => {
+                assert_eq!(path.as_deref(), Some("[1].qty"));
+                assert_eq!(*line, Some(3));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert_eq!(items[2].as_ref().unwrap().sku, "C3");
+
+        let ids: Vec<u32> = from_str_iter("[2]: 4,5", None)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(ids, vec![4, 5]);
+    }
+
+    #[derive(Serialize)]
+    enum Event {
+        Ping,
+        Move { x: i32, y: i32 },
+        Label(String),
+        Pair(u8, u8),
+    }
+
+    #[derive(Serialize)]
+    struct Catalog {
+        name: String,
+        owner: Option<String>,
+        tags: Vec<&'static str>,
+        products: Vec<Product>,
+        nested: std::collections::BTreeMap<&'static str, Vec<u32>>,
+        events: Vec<Event>,
+        empty: Vec<u32>,
+        meta: Meta,
+    }
+
+    #[derive(Serialize)]
+    struct Meta {
+        source: Source,
+    }
+
+    #[derive(Serialize)]
+    struct Source {
+        id: u32,
+    }
+
+    #[test]
+    fn test_serde_to_writer_streaming_matches_to_string() {
+        let catalog = Catalog {
+            name: "spring sale".to_string(),
+            owner: None,
+            tags: vec!["new", "a,b", ""],
+            products: vec![
+                Product {
+                    sku: "A1".to_string(),
+                    qty: 2,
+                    price: 9.99,
+                },
+                Product {
+                    sku: "B2".to_string(),
+                    qty: 10,
+                    price: 14.5,
+                },
+            ],
+            nested: [("odd", vec![1, 3]), ("even", vec![])]
+                .into_iter()
+                .collect(),
+            events: vec![
+                Event::Ping,
+                Event::Move { x: 1, y: -2 },
+                Event::Label("go".to_string()),
+                Event::Pair(3, 4),
+            ],
+            empty: vec![],
+            meta: Meta {
+                source: Source { id: 7 },
+            },
+        };
+
+        let all_options = [
+            EncodeOptions::new(),
+            EncodeOptions::new().key_folding(true),
+            EncodeOptions::new().align_columns(true),
+            EncodeOptions::new().null_policy(toon_rust::options::NullPolicy::OmitKey),
+            EncodeOptions::new().max_inline_items(1),
+            EncodeOptions::new().optimize_for_tokens(true),
+            EncodeOptions::new().indent_string("\t").emit_lengths(false),
+        ];
+        for options in &all_options {
+            let expected = to_string_with_options(&catalog, options).unwrap();
+            let mut out = Vec::new();
+            to_writer_streaming_with_options(&catalog, &mut out, options).unwrap();
+            // Only the entry order differs: fields stay in declaration order
+            let decode_options = DecodeOptions::new()
+                .indent_string(options.get_indent_string())
+                .lenient_arrays(!options.get_emit_lengths())
+                .expand_paths(options.get_key_folding());
+            assert_eq!(
+                decode(&String::from_utf8(out).unwrap(), Some(&decode_options)).unwrap(),
+                decode(&expected, Some(&decode_options)).unwrap()
+            );
+
+            // A JSON value already has its keys in `to_string`'s order
+            let value = serde_json::to_value(&catalog).unwrap();
+            let mut out = Vec::new();
+            to_writer_streaming_with_options(&value, &mut out, options).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), expected);
+        }
+
+        let mut out = Vec::new();
+        to_writer_streaming(&catalog.products, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[2]{sku,qty,price}:\n  A1,2,9.99\n  B2,10,14.5\n"
+        );
+    }
+
+    #[test]
+    fn test_serde_to_writer_streaming_nan_policy() {
+        let readings = vec![Reading {
+            sensor: "a".to_string(),
+            value: f64::NAN,
+        }];
+        let options = EncodeOptions::new().nan_policy(NanPolicy::Error);
+        let mut out = Vec::new();
+        assert!(to_writer_streaming_with_options(&readings, &mut out, &options).is_err());
+
+        let options = EncodeOptions::new().nan_policy(NanPolicy::Token);
+        let mut out = Vec::new();
+        to_writer_streaming_with_options(&readings, &mut out, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[1]{sensor,value}:\n  a,nan\n"
+        );
+    }
+
+    #[test]
+    fn test_serde_tabular_writer() {
+        let products = [
+            Product {
+                sku: "A1".to_string(),
+                qty: 2,
+                price: 9.99,
+            },
+            Product {
+                sku: "B2".to_string(),
+                qty: 1,
+                price: 14.5,
+            },
+        ];
+
+        let mut table = TabularWriter::new("items", ["sku", "qty", "price"], Vec::new());
+        for product in &products {
+            table.push_row(product).unwrap();
+        }
+        let toon = String::from_utf8(table.finish().unwrap()).unwrap();
+        assert_eq!(toon, "items[2]{sku,qty,price}:\n  A1,2,9.99\n  B2,1,14.5\n");
+        let decoded: std::collections::HashMap<String, Vec<Product>> = from_str(&toon).unwrap();
+        assert_eq!(decoded["items"], products);
+
+        let table = TabularWriter::new("items", ["sku"], Vec::new());
+        assert_eq!(table.finish().unwrap(), b"items[0]:\n");
+
+        let mut table = TabularWriter::new("items", ["sku", "qty"], Vec::new());
+        let err = table.push_row(&products[0]).unwrap_err();
+        assert!(err.to_string().contains("`price` is not a column"));
+        assert!(table.push_row(&[1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_serde_rotating_writer() {
+        let dir = std::env::temp_dir().join(format!("toon-rotating-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut names = Vec::new();
+        let mut table = RotatingWriter::new("items", ["sku", "qty"], 2, |i| {
+            names.push(i);
+            dir.join(format!("part-{i}.toon"))
+        });
+        for qty in 0..5 {
+            table
+                .push_row(&serde_json::json!({"sku": format!("S{qty}"), "qty": qty}))
+                .unwrap();
+        }
+        let files = table.finish().unwrap();
+        assert_eq!(names, [0, 1, 2]);
+        let texts: Vec<_> = files
+            .iter()
+            .map(|path| std::fs::read_to_string(path).unwrap())
+            .collect();
+        assert_eq!(texts[0], "items[2]{sku,qty}:\n  S0,0\n  S1,1\n");
+        assert_eq!(texts[2], "items[1]{sku,qty}:\n  S4,4\n");
+        for text in &texts {
+            assert!(decode(text, None).is_ok());
+        }
+
+        // Without lengths every file still opens with the header
+        let options = EncodeOptions::new().emit_lengths(false);
+        let mut table = RotatingWriter::with_options(
+            "items",
+            ["sku"],
+            1,
+            |i| dir.join(format!("plain-{i}.toon")),
+            &options,
+        );
+        table.push_row(&serde_json::json!({"sku": "A1"})).unwrap();
+        table.push_row(&serde_json::json!({"sku": "B2"})).unwrap();
+        let files = table.finish().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&files[1]).unwrap(),
+            "items{sku}:\n  B2\n"
+        );
+
+        let table = RotatingWriter::new("items", ["sku"], 10, |i| dir.join(format!("none-{i}")));
+        assert!(table.finish().unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_serde_tabular_writer_without_lengths() {
+        let options = EncodeOptions::new().emit_lengths(false);
+        let mut table = TabularWriter::with_options("items", ["sku", "qty"], Vec::new(), &options);
+        table
+            .push_row(&serde_json::json!({"sku": "A1", "qty": 2}))
+            .unwrap();
+        // Nothing is held back once the header doesn't need the count
+        table.push_row(&serde_json::json!({"sku": "B2"})).unwrap();
+        let toon = String::from_utf8(table.finish().unwrap()).unwrap();
+        assert_eq!(toon, "items{sku,qty}:\n  A1,2\n  B2,\n");
+    }
+
+    #[test]
+    fn test_serde_raw_value() {
+        use serde_json::value::RawValue;
+
+        #[derive(Serialize)]
+        struct Response {
+            id: u32,
+            items: Box<RawValue>,
+            meta: Box<RawValue>,
+        }
+
+        let response = Response {
+            id: 7,
+            items: RawValue::from_string(r#"[{"sku":"A1","qty":2},{"sku":"B2","qty":1}]"#.into())
+                .unwrap(),
+            meta: RawValue::from_string(r#""cached""#.into()).unwrap(),
+        };
+        let toon = to_string(&response).unwrap();
+        assert_eq!(
+            toon,
+            "id: 7\nitems[2]{qty,sku}:\n  2,A1\n  1,B2\n\nmeta: cached"
+        );
+
+        let mut out = Vec::new();
+        to_writer_streaming(&response, &mut out).unwrap();
+        assert_eq!(
+            decode(&String::from_utf8(out).unwrap(), None).unwrap(),
+            decode(&toon, None).unwrap()
+        );
+    }
 }
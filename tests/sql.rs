@@ -0,0 +1,76 @@
+#![cfg(feature = "rusqlite")]
+
+use rusqlite::Connection;
+use serde_json::json;
+use toon_rust::{decode, encode_query, EncodeOptions, Error};
+
+fn orders() -> Connection {
+    let db = Connection::open_in_memory().unwrap();
+    db.execute_batch(
+        "CREATE TABLE orders (id INTEGER, customer TEXT, total REAL, receipt BLOB);
+         INSERT INTO orders VALUES
+           (1, 'Ada', 12.5, x'CAFE'),
+           (2, 'Lovelace, A.', NULL, NULL),
+           (3, '42', 7.0, x'');",
+    )
+    .unwrap();
+    db
+}
+
+fn query(db: &Connection, sql: &str, options: Option<&EncodeOptions>) -> Result<String, Error> {
+    let mut statement = db.prepare(sql).unwrap();
+    let mut output = Vec::new();
+    encode_query(&mut statement, [], &mut output, options)?;
+    Ok(String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn test_sql_typed_cells() {
+    let db = orders();
+    let toon = query(&db, "SELECT * FROM orders ORDER BY id", None).unwrap();
+    assert_eq!(
+        toon,
+        "[3]{id,customer,total,receipt}:\n  1,Ada,12.5,yv4=\n  2,\"Lovelace, A.\",null,null\n  3,\"42\",7.0,\"\"\n"
+    );
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!([
+            {"id": 1, "customer": "Ada", "total": 12.5, "receipt": "yv4="},
+            {"id": 2, "customer": "Lovelace, A.", "total": null, "receipt": null},
+            {"id": 3, "customer": "42", "total": 7.0, "receipt": ""}
+        ])
+    );
+}
+
+#[test]
+fn test_sql_without_lengths_streams_rows() {
+    let db = orders();
+    let options = EncodeOptions::new().emit_lengths(false);
+    let toon = query(
+        &db,
+        "SELECT id, customer AS name FROM orders WHERE id > 1 ORDER BY id",
+        Some(&options),
+    )
+    .unwrap();
+    assert_eq!(toon, "[]{id,name}:\n  2,\"Lovelace, A.\"\n  3,\"42\"\n");
+}
+
+#[test]
+fn test_sql_no_rows() {
+    let db = orders();
+    let toon = query(&db, "SELECT id FROM orders WHERE id > 9", None).unwrap();
+    assert_eq!(decode(&toon, None).unwrap(), json!([]));
+}
+
+#[test]
+fn test_sql_errors() {
+    let db = orders();
+    let err = query(&db, "SELECT id, id FROM orders", None).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Column `id` appears more than once in the query"));
+
+    let mut statement = db.prepare("SELECT id FROM orders WHERE id = ?1").unwrap();
+    let err = encode_query(&mut statement, [], &mut Vec::new(), None).unwrap_err();
+    assert!(err.to_string().contains("Query failed"));
+}
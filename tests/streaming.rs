@@ -2,7 +2,9 @@
 
 use serde_json::json;
 use std::io::{BufWriter, Cursor};
-use toon_rust::{decode, decode_stream, encode, encode_stream, DecodeOptions, EncodeOptions};
+use toon_rust::{
+    decode, decode_stream, encode, encode_stream, DecodeOptions, EncodeOptions, ToonWriter,
+};
 
 #[test]
 fn test_encode_stream_simple_object() {
@@ -228,6 +230,7 @@ fn test_streaming_empty_object() {
 }
 
 #[test]
+#[allow(clippy::approx_constant)]
 fn test_streaming_mixed_types() {
     let data = json!({
         "string": "hello",
@@ -312,3 +315,894 @@ fn test_streaming_output_identical() {
         );
     }
 }
+
+#[test]
+fn test_decode_stream_resource_limits() {
+    let options = DecodeOptions::new().max_array_length(10);
+    let input = Cursor::new("items[999999999]{id}:\n  1");
+    let err = decode_stream(input, Some(&options)).unwrap_err();
+    assert!(err
+        .to_string()
+        .ends_with("Array length 999999999 exceeds the limit of 10"));
+}
+
+#[test]
+fn test_decode_stream_multibyte_across_reads() {
+    /// Reader that returns one byte per call, splitting every multi-byte character
+    struct OneByte<'a>(&'a [u8]);
+
+    impl std::io::Read for OneByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.split_first() {
+                Some((&byte, rest)) if !buf.is_empty() => {
+                    buf[0] = byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    let toon = "name: Zoë\ntags[2]: 日本,東京";
+    let result = decode_stream(OneByte(toon.as_bytes()), None).unwrap();
+    assert_eq!(result, decode(toon, None).unwrap());
+    assert_eq!(result["tags"], json!(["日本", "東京"]));
+}
+
+#[test]
+fn test_decode_stream_large_document() {
+    /// Reader that hands out a few bytes at a time
+    struct Trickle<'a>(&'a [u8]);
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(7);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    let rows: Vec<_> = (0..5000)
+        .map(|i| json!({"id": i, "name": format!("user {i}"), "tag": "x"}))
+        .collect();
+    let items: Vec<_> = (0..500)
+        .map(|i| json!({"key": i, "nested": {"a": [1, 2], "b": "text"}}))
+        .collect();
+    let data = json!({"rows": rows, "items": items, "tail": "end"});
+    let toon = encode(&data, None).unwrap();
+    let result = decode_stream(Trickle(toon.as_bytes()), None).unwrap();
+    assert_eq!(result, data);
+
+    // Positions count the lines released from the buffer
+    let broken = format!("{toon}\nbad line\n");
+    let err = decode_stream(Cursor::new(broken.as_bytes()), None).unwrap_err();
+    assert_eq!(err, decode(&broken, None).unwrap_err());
+    assert!(
+        matches!(err, toon_rust::Error::Parse { line, .. } if line == toon.lines().count() + 1)
+    );
+
+    // Read errors are reported even when they cut an entry short
+    struct Failing;
+    impl std::io::Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::ConnectionReset.into())
+        }
+    }
+    let reader = std::io::Read::chain("a: 1\nb".as_bytes(), Failing);
+    assert!(matches!(
+        decode_stream(reader, None),
+        Err(toon_rust::Error::Io(_))
+    ));
+}
+
+#[test]
+fn test_push_parser() {
+    use toon_rust::decode::{PushEvent, PushParser};
+
+    let toon = "\nname: Zoë\nuser:\n  id: 1\n  tags[2]: a,b\nrows[2]{id,note}:\n  1,\"multi\nline\"\n  2,x\nlast: true\n";
+    let expected = json!({
+        "name": "Zoë",
+        "user": {"id": 1, "tags": ["a", "b"]},
+        "rows": [{"id": 1, "note": "multi\nline"}, {"id": 2, "note": "x"}],
+        "last": true
+    });
+
+    // Feed one byte at a time; entries arrive as soon as the next one starts
+    let mut parser = PushParser::new(None);
+    let mut entries = serde_json::Map::new();
+    let mut fed = Vec::new();
+    for byte in toon.as_bytes() {
+        for event in parser.feed(&[*byte]).unwrap() {
+            let PushEvent::Entry { key, value } = event else {
+                panic!("unexpected event");
+            };
+            fed.push(key.clone());
+            entries.insert(key, value);
+        }
+    }
+    assert_eq!(fed, vec!["name", "user", "rows"]);
+    for event in parser.finish().unwrap() {
+        if let PushEvent::Entry { key, value } = event {
+            entries.insert(key, value);
+        }
+    }
+    assert_eq!(serde_json::Value::Object(entries), expected);
+
+    // Root arrays are emitted whole once the input is finished
+    let mut parser = PushParser::new(None);
+    assert!(parser.feed(b"[2]: 1,2\n").unwrap().is_empty());
+    assert_eq!(
+        parser.finish().unwrap(),
+        vec![PushEvent::Root(json!([1, 2]))]
+    );
+
+    // Error positions refer to the whole document
+    let mut parser = PushParser::new(None);
+    parser.feed(b"a: 1\nb: 2\nc 3\n").unwrap();
+    match parser.finish() {
+        Err(toon_rust::Error::Parse { line, .. }) => assert_eq!(line, 3),
+        other => panic!("expected a parse error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_encode_stream_matches_encode() {
+    use toon_rust::options::{NullPolicy, Style};
+
+    struct Full;
+    impl std::io::Write for Full {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::WriteZero.into())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let data = json!({
+        "user": {"name": "Ann", "tags": ["a", "b"]},
+        "rows": [{"id": 1, "note": null}, {"id": 2}],
+        "items": [{"k": 1, "v": {"x": 1}}, "s", [1, 2]],
+        "text": "one\ntwo"
+    });
+    let options = [
+        EncodeOptions::new(),
+        EncodeOptions::new()
+            .indent_string("\t")
+            .style(Style::Compact)
+            .block_strings(true)
+            .null_policy(NullPolicy::EmptyCell)
+            .tabular_uniformity(0.5)
+            .align_columns(true)
+            .key_folding(true),
+    ];
+    for options in &options {
+        let mut buffer = Vec::new();
+        encode_stream(&data, &mut buffer, Some(options)).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            encode(&data, Some(options)).unwrap()
+        );
+    }
+
+    let err = encode_stream(&data, &mut Full, None).unwrap_err();
+    assert!(matches!(err, toon_rust::Error::Io(_)));
+}
+
+#[test]
+fn test_toon_writer_builds_document() {
+    let mut writer = ToonWriter::new(Vec::new());
+    writer
+        .field("name", &json!("inventory"))
+        .unwrap()
+        .begin_object("meta")
+        .unwrap()
+        .field("tags", &json!(["a", "b"]))
+        .unwrap()
+        .field("owner", &json!({"id": 7}))
+        .unwrap()
+        .end()
+        .unwrap()
+        .begin_table("items", ["sku", "qty", "note"])
+        .unwrap()
+        .row(&[json!("A1"), json!(2), json!(null)])
+        .unwrap()
+        .row(&[json!("B 2"), json!(1), json!("x,y")])
+        .unwrap()
+        .end()
+        .unwrap()
+        .field("total", &json!(3))
+        .unwrap();
+    let toon = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+    assert_eq!(
+        toon,
+        "name: inventory\nmeta: \n  tags[2]:a,b\n  owner: \n    id: 7\n\
+         items[2]{sku,qty,note}:\n  A1,2,\n  \"B 2\",1,\"x,y\"\ntotal: 3\n"
+    );
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!({
+            "name": "inventory",
+            "meta": {"tags": ["a", "b"], "owner": {"id": 7}},
+            "items": [
+                {"sku": "A1", "qty": 2, "note": null},
+                {"sku": "B 2", "qty": 1, "note": "x,y"}
+            ],
+            "total": 3
+        })
+    );
+}
+
+#[test]
+fn test_toon_writer_tables() {
+    // Tables left open are closed by finish, and empty ones keep a length
+    let mut writer = ToonWriter::new(Vec::new());
+    writer
+        .begin_object("a")
+        .unwrap()
+        .begin_table("rows", ["x"])
+        .unwrap();
+    assert_eq!(writer.finish().unwrap(), b"a: \n  rows[0]:\n");
+
+    let options = EncodeOptions::new().emit_lengths(false);
+    let mut writer = ToonWriter::with_options(Vec::new(), &options);
+    writer
+        .begin_table("rows", ["x", "y"])
+        .unwrap()
+        .row(&[json!(1), json!(2)])
+        .unwrap();
+    assert_eq!(writer.finish().unwrap(), b"rows{x,y}:\n  1,2\n");
+}
+
+#[test]
+fn test_toon_writer_misuse() {
+    let mut writer = ToonWriter::new(Vec::new());
+    assert!(writer.end().is_err());
+    assert!(writer.row(&[json!(1)]).is_err());
+    writer.begin_table("rows", ["x", "y"]).unwrap();
+    assert!(writer.row(&[json!(1)]).is_err());
+    assert!(writer.row(&[json!(1), json!([2])]).is_err());
+    assert!(writer.field("a", &json!(1)).is_err());
+    assert!(writer.begin_object("a").is_err());
+}
+
+#[test]
+fn test_decode_rows_stream() {
+    use std::time::Duration;
+    use toon_rust::decode_rows_stream;
+
+    let toon = "name: app\nlog[3]{level,msg}:\n  info,up\n\n  warn,x,y\n  error,down\nafter: 1";
+    let rows: Vec<_> = decode_rows_stream(toon.as_bytes(), "log", None).collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0].as_ref().unwrap()["msg"], "up");
+    // A bad row doesn't end the table
+    assert!(rows[1].is_err());
+    assert_eq!(rows[2].as_ref().unwrap()["level"], "error");
+
+    let rows: Vec<_> = decode_rows_stream(&b"log[2]{id}:\n  1\nnext: 2"[..], "log", None).collect();
+    assert!(matches!(
+        rows[1],
+        Err(toon_rust::Error::LengthMismatch {
+            expected: 2,
+            found: 1
+        })
+    ));
+    let mut rows = decode_rows_stream(&b"a: 1"[..], "log", None);
+    assert!(rows.next().unwrap().is_err());
+    assert!(rows.next().is_none());
+    // Header errors point at the header's line
+    let err = decode_rows_stream(&b"a: 1\nlog[x]{id}:"[..], "log", None)
+        .next()
+        .unwrap()
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            toon_rust::Error::Parse {
+                line: 2,
+                column: 5,
+                ..
+            }
+        ),
+        "{err}"
+    );
+
+    /// A file that is still being written: each empty chunk is a read that
+    /// finds no new data yet
+    struct Growing(Vec<&'static str>);
+
+    impl std::io::Read for Growing {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            let chunk = self.0.remove(0);
+            buf[..chunk.len()].copy_from_slice(chunk.as_bytes());
+            Ok(chunk.len())
+        }
+    }
+
+    let file = Growing(vec![
+        "log{level}:\n  info\n",
+        "",
+        "  wa",
+        "",
+        "rn\n",
+        "",
+        "",
+        "  error\n",
+        "done: true\n",
+    ]);
+    let options = DecodeOptions::new()
+        .lenient_arrays(true)
+        .follow(Duration::from_millis(1));
+    let levels: Vec<_> = decode_rows_stream(file, "log", Some(&options))
+        .map(|row| row.unwrap()["level"].clone())
+        .collect();
+    assert_eq!(levels, [json!("info"), json!("warn"), json!("error")]);
+
+    // Without following, the first empty read ends the table
+    let file = Growing(vec!["log{level}:\n  info\n", "", "  warn\n"]);
+    let lenient = DecodeOptions::new().lenient_arrays(true);
+    assert_eq!(decode_rows_stream(file, "log", Some(&lenient)).count(), 1);
+}
+
+#[test]
+fn test_decode_rows_stream_matches_decode_rows() {
+    use toon_rust::{decode_rows, decode_rows_stream, query, query_stream};
+
+    let cases = [
+        (
+            "name: app\nlog[2]{level,msg}:\n\tinfo,up\n\twarn,x\nafter: 1",
+            DecodeOptions::new().indent_string("\t"),
+        ),
+        (
+            "log[3]{x,y}:\n  1,\"p\n  q\"\n  2,\"a,\n\"\n  3,z\n",
+            DecodeOptions::new(),
+        ),
+        // A null in a one-column table is written as a blank row
+        ("log[3]{x}:\n  1\n  \n  2\n", DecodeOptions::new()),
+        (
+            "log[3]{x}:\n  1\n\n  2\n\nafter: 1",
+            DecodeOptions::new().strict(false),
+        ),
+    ];
+    for (toon, options) in &cases {
+        let rows: Vec<_> = decode_rows(toon, "log", Some(options))
+            .map(Result::unwrap)
+            .collect();
+        let streamed: Vec<_> = decode_rows_stream(toon.as_bytes(), "log", Some(options))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(streamed, rows, "{toon}");
+        assert_eq!(
+            query_stream(toon.as_bytes(), "log[]", Some(options)).unwrap(),
+            query(toon, "log[]", Some(options)).unwrap()
+        );
+    }
+    let rows: Vec<_> = decode_rows_stream(cases[1].0.as_bytes(), "log", None)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(rows[0]["y"], "p\n  q");
+    assert_eq!(rows[2]["y"], "z");
+    let rows: Vec<_> = decode_rows_stream(cases[2].0.as_bytes(), "log", None)
+        .map(|row| serde_json::Value::Object(row.unwrap()))
+        .collect();
+    assert_eq!(rows, [json!({"x": 1}), json!({"x": null}), json!({"x": 2})]);
+
+    // The row after a multi-line one is read from its own line
+    let toon = "log[2]{x:int,y}:\n  1,\"p\nq\"\n  x,y";
+    let rows: Vec<_> = decode_rows_stream(toon.as_bytes(), "log", None).collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].as_ref().unwrap()["y"], "p\nq");
+    let err = rows[1].as_ref().unwrap_err();
+    assert!(err.to_string().contains("Cell x in column 'x'"), "{err}");
+}
+
+#[test]
+fn test_decode_with_stats() {
+    use toon_rust::{decode_with_stats, DecodeStats};
+
+    let toon = "a:\n  b:\n    items[2]{x,y}:\n      1,2\n      3,4\nlist[2]:\n  - [1]{z}:\n    5\n  - 6\nn: 1\n\n";
+    let (value, stats) = decode_with_stats(toon.as_bytes(), None).unwrap();
+    assert_eq!(value, decode(toon, None).unwrap());
+    assert_eq!(
+        stats,
+        DecodeStats {
+            lines: 11,
+            sections: 3,
+            tables: vec![("a.b.items".to_string(), 2), ("list[0]".to_string(), 1)],
+            truncated: vec![],
+            max_depth: 5,
+        }
+    );
+
+    let (_, stats) = decode_with_stats(&b"[2]{x}:\n  1\n  2"[..], None).unwrap();
+    assert_eq!(stats.tables, [(String::new(), 2)]);
+    assert_eq!((stats.lines, stats.sections, stats.max_depth), (3, 1, 2));
+    let (_, stats) = decode_with_stats(&b"42"[..], None).unwrap();
+    assert_eq!((stats.lines, stats.sections, stats.max_depth), (1, 1, 0));
+    assert!(decode_with_stats(&b"a: 1\nb[2]: x"[..], None).is_err());
+}
+
+#[test]
+fn test_rows_stream_checkpoint() {
+    use toon_rust::{decode_rows_stream, resume_rows_stream, Checkpoint};
+
+    let toon =
+        "name: app\nlog[4]{level,msg}:\n  info,up\n  warn,x,y\n  error,down\n  info,ok\nafter: 1";
+    let all: Vec<_> = decode_rows_stream(toon.as_bytes(), "log", None).collect();
+
+    // Stopping after any number of rows and resuming gives the rest
+    for taken in 0..=all.len() {
+        let mut rows = decode_rows_stream(toon.as_bytes(), "log", None);
+        rows.by_ref().take(taken).for_each(drop);
+        let checkpoint: Checkpoint = rows.checkpoint().to_string().parse().unwrap();
+        assert_eq!(checkpoint, rows.checkpoint());
+        let rest: Vec<_> =
+            resume_rows_stream(&toon.as_bytes()[checkpoint.offset()..], &checkpoint, None)
+                .collect();
+        assert_eq!(rest, all[taken..], "after {taken} rows");
+    }
+
+    // The cell limit and error positions carry on from the checkpoint
+    let options = DecodeOptions::new().max_cells(5);
+    let mut rows = decode_rows_stream(toon.as_bytes(), "log", Some(&options));
+    rows.next();
+    let checkpoint = rows.checkpoint();
+    let rest = &toon.as_bytes()[checkpoint.offset()..];
+    let mut rows = resume_rows_stream(rest, &checkpoint, Some(&options));
+    assert!(rows.next().unwrap().is_err());
+    let err = rows.next().unwrap().unwrap_err();
+    assert!(
+        matches!(err, toon_rust::Error::Parse { line: 5, .. }),
+        "{err}"
+    );
+
+    assert!("{}".parse::<Checkpoint>().is_err());
+    assert!("not a checkpoint".parse::<Checkpoint>().is_err());
+}
+
+#[test]
+fn test_stream_progress() {
+    use std::sync::{Arc, Mutex};
+    use toon_rust::options::Progress;
+
+    type Reports = Arc<Mutex<Vec<(usize, usize, Option<String>)>>>;
+    fn recorder(reports: &Reports) -> impl Fn(&Progress) + Send + Sync + 'static {
+        let reports = reports.clone();
+        move |progress: &Progress| {
+            reports.lock().unwrap().push((
+                progress.bytes,
+                progress.rows,
+                progress.section.map(str::to_string),
+            ));
+        }
+    }
+
+    let value = json!({
+        "items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}],
+        "name": "report",
+        "tags": [{"a": 1}, [1, 2], "x"]
+    });
+    let reports = Reports::default();
+    let options = EncodeOptions::new().progress(recorder(&reports));
+    let mut output = Vec::new();
+    encode_stream(&value, &mut output, Some(&options)).unwrap();
+    assert_eq!(output, encode(&value, None).unwrap().as_bytes());
+
+    let encoded = reports.lock().unwrap().clone();
+    let sections: Vec<_> = encoded.iter().filter_map(|r| r.2.clone()).collect();
+    assert!(sections.starts_with(&["items".to_string()]));
+    assert_eq!(
+        encoded.last().unwrap(),
+        &(output.len(), 5, Some("tags".to_string()))
+    );
+    // Counts only ever go up
+    assert!(encoded
+        .windows(2)
+        .all(|w| w[0].0 <= w[1].0 && w[0].1 <= w[1].1));
+
+    let reports = Reports::default();
+    let options = DecodeOptions::new().progress(recorder(&reports));
+    let decoded = decode_stream(Cursor::new(&output), Some(&options)).unwrap();
+    assert_eq!(decoded, value);
+    let decoded = reports.lock().unwrap().clone();
+    let sections: Vec<_> = decoded
+        .iter()
+        .filter(|r| r.1 == 0)
+        .filter_map(|r| r.2.clone())
+        .collect();
+    assert_eq!(sections, ["items"]);
+    assert!(decoded.iter().any(|r| r.2.as_deref() == Some("name")));
+    assert_eq!(
+        decoded.last().unwrap(),
+        &(output.len(), 5, Some("tags".to_string()))
+    );
+    assert!(decoded
+        .windows(2)
+        .all(|w| w[0].0 <= w[1].0 && w[0].1 <= w[1].1));
+}
+
+#[test]
+fn test_validate_stream() {
+    use toon_rust::options::DuplicateKeyPolicy;
+    use toon_rust::{validate_stream, Error, Stats};
+
+    let toon = "name: report\nitems[2]{sku,qty}:\n  A1,2\n  B2,1\n\nnotes[2]:\n  - a\n  - rows[1]{x}:\n      1\n";
+    assert_eq!(
+        validate_stream(toon.as_bytes(), None).unwrap(),
+        Stats {
+            lines: 9,
+            sections: 3,
+            rows: 5
+        }
+    );
+    let stats = validate_stream(&b"[2]:\n  - 1\n  - 2"[..], None).unwrap();
+    assert_eq!((stats.lines, stats.sections, stats.rows), (3, 1, 2));
+    assert_eq!(validate_stream(&b"42"[..], None).unwrap().sections, 1);
+
+    // Every error is reported, in order, with its place in the document
+    let toon = "a: 1\nb 2\nitems[2]{sku,qty}:\n  A1|2\n  B2,1\ntags[3]: x,y\nc: 3";
+    let errors = validate_stream(toon.as_bytes(), None).unwrap_err();
+    assert_eq!(errors.len(), 3, "{errors:?}");
+    assert!(matches!(errors[0], Error::Parse { line: 2, .. }));
+    assert_eq!(
+        errors[1],
+        Error::DelimiterMismatch {
+            expected: ',',
+            found: '|'
+        }
+    );
+    assert_eq!(
+        errors[2],
+        Error::LengthMismatch {
+            expected: 3,
+            found: 2
+        }
+    );
+
+    let strict = DecodeOptions::new().duplicate_keys(DuplicateKeyPolicy::Error);
+    let errors = validate_stream(&b"a: 1\nb: 2\na: 3"[..], Some(&strict)).unwrap_err();
+    assert!(matches!(errors[..], [Error::Parse { line: 3, .. }]));
+    assert!(validate_stream(&b"a: 1\nb: 2\na: 3"[..], None).is_ok());
+
+    // The cell limit counts across entries, as decode does
+    let toon = "a[2]: 1,2\nb[2]{x}:\n  3\n  4\n";
+    let limited = DecodeOptions::new().max_cells(3);
+    assert!(decode(toon, Some(&limited)).is_err());
+    let errors = validate_stream(toon.as_bytes(), Some(&limited)).unwrap_err();
+    assert!(
+        errors[0].to_string().contains("limit of 3 array cells"),
+        "{errors:?}"
+    );
+    let roomy = DecodeOptions::new().max_cells(4);
+    assert!(validate_stream(toon.as_bytes(), Some(&roomy)).is_ok());
+}
+
+#[test]
+fn test_ndjson_conversion() {
+    use toon_rust::options::EmptyCellPolicy;
+    use toon_rust::{ndjson_to_toon, toon_to_ndjson};
+
+    let toon_of = |ndjson: &str, options: Option<&EncodeOptions>| {
+        let mut toon = Vec::new();
+        ndjson_to_toon(ndjson.as_bytes(), &mut toon, options).unwrap();
+        String::from_utf8(toon).unwrap()
+    };
+
+    let ndjson =
+        "{\"id\": 1, \"name\": \"Ada\", \"note\": null}\n\n{\"id\": 2, \"name\": \"a, b\"}\r\n";
+    let mut toon = Vec::new();
+    ndjson_to_toon(ndjson.as_bytes(), &mut toon, None).unwrap();
+    let toon = String::from_utf8(toon).unwrap();
+    assert_eq!(toon, "[2]{id,name,note}:\n  1,Ada,null\n  2,\"a, b\",\n");
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!([{"id": 1, "name": "Ada", "note": null}, {"id": 2, "name": "a, b", "note": null}])
+    );
+
+    let mut back = Vec::new();
+    toon_to_ndjson(toon.as_bytes(), &mut back, None).unwrap();
+    assert_eq!(
+        String::from_utf8(back).unwrap(),
+        "{\"id\":1,\"name\":\"Ada\",\"note\":null}\n{\"id\":2,\"name\":\"a, b\",\"note\":null}\n"
+    );
+
+    // Without lengths each row is written as it is read
+    let options = EncodeOptions::new().emit_lengths(false);
+    let mut toon = Vec::new();
+    ndjson_to_toon(ndjson.as_bytes(), &mut toon, Some(&options)).unwrap();
+    assert!(toon.starts_with(b"[]{id,name,note}:\n  1,Ada,null\n"));
+
+    let mut toon = Vec::new();
+    ndjson_to_toon(&b""[..], &mut toon, None).unwrap();
+    assert_eq!(toon, b"[0]:");
+
+    // Missing fields come back out with the omitting empty-cell policy
+    let omit = DecodeOptions::new().empty_cells(EmptyCellPolicy::Omit);
+    assert_eq!(
+        decode(&toon_of(ndjson, None), Some(&omit)).unwrap(),
+        json!([{"id": 1, "name": "Ada", "note": null}, {"id": 2, "name": "a, b"}])
+    );
+
+    // Nested objects become dotted columns under key folding, and a value
+    // that can't be a cell turns the output into a list array
+    let nested =
+        "{\"id\": 1, \"user\": {\"name\": \"Ada\"}}\n{\"id\": 2, \"user\": {\"name\": \"Bo\"}}\n";
+    let folding = EncodeOptions::new().key_folding(true);
+    assert_eq!(
+        toon_of(nested, Some(&folding)),
+        "[2]{id,user.name}:\n  1,Ada\n  2,Bo\n"
+    );
+    let mixed =
+        "{\"id\": 1, \"user\": {\"name\": \"Ada\"}}\n{\"id\": 2, \"tags\": [\"x\", \"y\"]}\n";
+    let toon = toon_of(mixed, None);
+    assert!(toon.starts_with("[2]:\n  - id: 1\n"), "{toon}");
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!([{"id": 1, "user": {"name": "Ada"}}, {"id": 2, "tags": ["x", "y"]}])
+    );
+    // Rows already written without lengths leave nothing to fall back on
+    let err = ndjson_to_toon(mixed.as_bytes(), &mut Vec::new(), Some(&options)).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("line 1, column 1: Record has a nested value"));
+
+    for (input, message) in [
+        (
+            "{\"a\": 1}\n{\"a\": 2, \"b\": 3}",
+            "line 2, column 1: Field `b` is not a column",
+        ),
+        (
+            "{\"a\": 1}\n[1, 2]",
+            "line 2, column 1: Expected a JSON object record",
+        ),
+        (
+            "{\"a\": [1]}\n[1, 2]",
+            "line 2, column 1: Expected a JSON object record",
+        ),
+        (
+            "{\"a\": 1}\n{\"a\": }",
+            "line 2, column 7: Invalid JSON record",
+        ),
+    ] {
+        let err = ndjson_to_toon(input.as_bytes(), &mut Vec::new(), None).unwrap_err();
+        assert!(err.to_string().contains(message), "{err}");
+    }
+}
+
+#[test]
+fn test_transcode() {
+    use toon_rust::ndjson::{ndjson_to_toon_with, toon_to_ndjson_with};
+    use toon_rust::transcode;
+
+    let toon = "[4]{id,email}:\n  1,a@x.io\n  2,b@x.io\n  3,c@x.io\n  4,d@x.io";
+    let mut seen = 0;
+    let mut out = Vec::new();
+    transcode(toon.as_bytes(), &mut out, None, None, |mut user| {
+        seen += 1;
+        if user["id"].as_i64()? % 2 == 1 {
+            return None;
+        }
+        user["email"] = json!("***");
+        Some(user)
+    })
+    .unwrap();
+    assert_eq!(seen, 4);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "[2]{email,id}:\n  ***,2\n  ***,4\n"
+    );
+
+    // Nested values have to be removed before they reach the table
+    let list = "[2]:\n  - id: 1\n    tags[1]: x\n  - id: 2";
+    let err = transcode(list.as_bytes(), &mut Vec::new(), None, None, Some).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("item 0: Record has a nested value"));
+    let mut out = Vec::new();
+    transcode(list.as_bytes(), &mut out, None, None, |mut item| {
+        item.as_object_mut()?.remove("tags");
+        Some(item)
+    })
+    .unwrap();
+    assert_eq!(out, b"[2]{id}:\n  1\n  2\n");
+
+    let ndjson = "{\"id\": 1}\n{\"id\": 2, \"extra\": true}\n";
+    let mut out = Vec::new();
+    ndjson_to_toon_with(ndjson.as_bytes(), &mut out, None, |record| {
+        Some(json!({"id": record["id"]}))
+    })
+    .unwrap();
+    assert_eq!(out, b"[2]{id}:\n  1\n  2\n");
+
+    let mut out = Vec::new();
+    toon_to_ndjson_with(toon.as_bytes(), &mut out, None, |user| {
+        (user["id"] == 3).then_some(user)
+    })
+    .unwrap();
+    assert_eq!(out, b"{\"email\":\"c@x.io\",\"id\":3}\n");
+}
+
+#[cfg(feature = "async")]
+mod async_tests {
+    use serde_json::json;
+    use toon_rust::{decode, decode_stream_async, encode, encode_stream_async};
+    use toon_rust::{DecodeOptions, EncodeOptions};
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_encode_stream_async_matches_encode() {
+        let data = json!({
+            "user": {"name": "Ann", "tags": ["a", "b"]},
+            "rows": [{"id": 1, "note": null}, {"id": 2, "note": "x"}],
+            "items": [{"k": 1, "v": {"x": 1}}, "s", [1, 2]],
+            "empty": {}
+        });
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().key_folding(true),
+            EncodeOptions::new().roundtrip_safe(true),
+        ] {
+            for value in [&data, &json!([1, 2, 3]), &json!("text")] {
+                let mut out = Vec::new();
+                block_on(encode_stream_async(value, &mut out, Some(&options))).unwrap();
+                let expected = encode(value, Some(&options)).unwrap();
+                assert_eq!(String::from_utf8(out).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_stream_async() {
+        let data = json!({
+            "user": {"name": "Ann", "tags": ["a", "b"]},
+            "rows": [{"id": 1, "note": "x"}, {"id": 2, "note": "y"}],
+            "n": 3
+        });
+        let toon = encode(&data, None).unwrap();
+
+        // Small reads split entries across chunks
+        let (client, mut server) = tokio::io::duplex(5);
+        let decoded = block_on(async {
+            let write = async {
+                tokio::io::AsyncWriteExt::write_all(&mut server, toon.as_bytes()).await?;
+                drop(server);
+                Ok::<_, std::io::Error>(())
+            };
+            let (decoded, written) = tokio::join!(decode_stream_async(client, None), write);
+            written.unwrap();
+            decoded
+        });
+        assert_eq!(decoded.unwrap(), data);
+
+        let root = block_on(decode_stream_async(&b"[2]: 1,2"[..], None)).unwrap();
+        assert_eq!(root, json!([1, 2]));
+
+        let options = DecodeOptions::new().expand_paths(true);
+        let toon = "a.b: 1\na.c: 2";
+        let decoded = block_on(decode_stream_async(toon.as_bytes(), Some(&options))).unwrap();
+        assert_eq!(decoded, decode(toon, Some(&options)).unwrap());
+
+        assert!(block_on(decode_stream_async(&b"a: 1\nb 2\nc: 3"[..], None)).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_async_tabular_writer() {
+        use std::future::Future;
+        use std::task::Poll;
+        use tokio::io::AsyncReadExt;
+        use toon_rust::{AsyncTabularWriter, TabularWriter};
+
+        let rows = [
+            json!({"sku": "A1", "qty": 2}),
+            json!({"sku": "B 2", "qty": 1}),
+        ];
+        for options in [
+            EncodeOptions::new(),
+            EncodeOptions::new().emit_lengths(false),
+        ] {
+            let mut blocking =
+                TabularWriter::with_options("items", ["sku", "qty"], Vec::new(), &options);
+            let mut table =
+                AsyncTabularWriter::with_options("items", ["sku", "qty"], Vec::new(), &options);
+            for row in &rows {
+                blocking.push_row(row).unwrap();
+                block_on(table.push_row(row)).unwrap();
+            }
+            assert_eq!(
+                block_on(table.finish()).unwrap(),
+                blocking.finish().unwrap()
+            );
+        }
+        let table = AsyncTabularWriter::new("empty", ["a"], Vec::new());
+        assert_eq!(block_on(table.finish()).unwrap(), b"empty[0]:\n");
+
+        // Without lengths a row waits for the writer to take it
+        let options = EncodeOptions::new().emit_lengths(false);
+        let (mut client, server) = tokio::io::duplex(8);
+        let mut table = AsyncTabularWriter::with_options("t", ["a"], server, &options);
+        block_on(async {
+            let row = json!({"a": "a row longer than the pipe"});
+            let mut push = std::pin::pin!(table.push_row(&row));
+            let blocked =
+                std::future::poll_fn(|cx| Poll::Ready(push.as_mut().poll(cx).is_pending()));
+            assert!(blocked.await);
+            let expected = "t{a}:\n  \"a row longer than the pipe\"\n";
+            let mut received = vec![0; expected.len()];
+            let (pushed, read) = tokio::join!(push, client.read_exact(&mut received));
+            pushed.unwrap();
+            read.unwrap();
+            assert_eq!(received, expected.as_bytes());
+        });
+        assert!(block_on(table.push_row(&json!({"b": 1}))).is_err());
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compression_tests {
+    use serde_json::json;
+
+    fn sample() -> serde_json::Value {
+        let items: Vec<_> = (0..500)
+            .map(|i| json!({"id": i, "sku": format!("SKU-{i}"), "qty": i % 7}))
+            .collect();
+        json!({"name": "inventory", "items": items})
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_roundtrip() {
+        use std::io::Write;
+        use toon_rust::{decode_stream_gz, encode, encode_stream_gz};
+
+        let value = sample();
+        let mut archive = Vec::new();
+        encode_stream_gz(&value, &mut archive, None).unwrap();
+        assert!(archive.starts_with(&[0x1f, 0x8b]));
+        assert!(archive.len() < encode(&value, None).unwrap().len());
+        assert_eq!(decode_stream_gz(archive.as_slice(), None).unwrap(), value);
+
+        // Concatenated members read as one document
+        let mut joined = Vec::new();
+        for part in ["a: 1\n", "b: 2"] {
+            let mut member = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+            member.write_all(part.as_bytes()).unwrap();
+            joined.extend(member.finish().unwrap());
+        }
+        assert_eq!(
+            decode_stream_gz(joined.as_slice(), None).unwrap(),
+            json!({"a": 1, "b": 2})
+        );
+
+        let err = decode_stream_gz(&b"not gzip"[..], None).unwrap_err();
+        assert!(matches!(err, toon_rust::Error::Io(_)));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_roundtrip() {
+        use toon_rust::{decode_stream_zstd, encode, encode_stream_zstd};
+
+        let value = sample();
+        let mut archive = Vec::new();
+        encode_stream_zstd(&value, &mut archive, None).unwrap();
+        assert!(archive.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]));
+        assert!(archive.len() < encode(&value, None).unwrap().len());
+        assert_eq!(decode_stream_zstd(archive.as_slice(), None).unwrap(), value);
+
+        let err = decode_stream_zstd(&b"not zstd"[..], None).unwrap_err();
+        assert!(matches!(err, toon_rust::Error::Io(_)));
+    }
+}
@@ -0,0 +1,122 @@
+//! Tests for the syntax token stream
+
+use toon_rust::syntax::TokenKind::{self, Field, Key, Length, Punctuation, Scalar, String};
+use toon_rust::tokenize;
+
+/// Each token's kind and text
+fn tokens(input: &str) -> Vec<(TokenKind, &str)> {
+    tokenize(input)
+        .into_iter()
+        .map(|token| (token.kind, &input[token.span]))
+        .collect()
+}
+
+#[test]
+fn test_tokenize_entries() {
+    assert_eq!(
+        tokens("name: Ada\n\"full name\":  \"Ada L\"\nuser:\n  age: 36\n"),
+        [
+            (Key, "name"),
+            (Punctuation, ":"),
+            (Scalar, "Ada"),
+            (Key, "\"full name\""),
+            (Punctuation, ":"),
+            (String, "\"Ada L\""),
+            (Key, "user"),
+            (Punctuation, ":"),
+            (Key, "age"),
+            (Punctuation, ":"),
+            (Scalar, "36"),
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_arrays() {
+    let input = "\
+tags[#2|]: a|\"b|c\"
+rows[2]{id:int,region=EU}:
+  1,
+  2,\"US\"
+list[2]:
+  - [2]: 1,2
+  - x[1]{a}:
+      1
+    b: 2
+";
+    assert_eq!(
+        tokens(input),
+        [
+            (Key, "tags"),
+            (Length, "[#2|]"),
+            (Punctuation, ":"),
+            (Scalar, "a"),
+            (Punctuation, "|"),
+            (String, "\"b|c\""),
+            (Key, "rows"),
+            (Length, "[2]"),
+            (Punctuation, "{"),
+            (Field, "id:int"),
+            (Punctuation, ","),
+            (Field, "region=EU"),
+            (Punctuation, "}"),
+            (Punctuation, ":"),
+            (Scalar, "1"),
+            (Punctuation, ","),
+            (Scalar, "2"),
+            (Punctuation, ","),
+            (String, "\"US\""),
+            (Key, "list"),
+            (Length, "[2]"),
+            (Punctuation, ":"),
+            (Punctuation, "-"),
+            (Length, "[2]"),
+            (Punctuation, ":"),
+            (Scalar, "1"),
+            (Punctuation, ","),
+            (Scalar, "2"),
+            (Punctuation, "-"),
+            (Key, "x"),
+            (Length, "[1]"),
+            (Punctuation, "{"),
+            (Field, "a"),
+            (Punctuation, "}"),
+            (Punctuation, ":"),
+            (Scalar, "1"),
+            (Key, "b"),
+            (Punctuation, ":"),
+            (Scalar, "2"),
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_block_strings() {
+    assert_eq!(
+        tokens("text: |-\n  key: not a key\n    - nor an item\nnext: 1"),
+        [
+            (Key, "text"),
+            (Punctuation, ":"),
+            (Punctuation, "|-"),
+            (String, "key: not a key"),
+            (String, "- nor an item"),
+            (Key, "next"),
+            (Punctuation, ":"),
+            (Scalar, "1"),
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_malformed() {
+    assert_eq!(
+        tokens("\"open: 1\nbad {x\nhalf[2\n42\n"),
+        [
+            (String, "\"open: 1"),
+            (Scalar, "bad {x"),
+            (Scalar, "half[2"),
+            (Scalar, "42"),
+        ]
+    );
+    assert!(tokenize("").is_empty());
+}
@@ -0,0 +1,43 @@
+//! Tests for token estimation
+
+use serde_json::json;
+use toon_rust::tokens::Model;
+use toon_rust::{encode, estimate_tokens};
+
+#[test]
+fn test_estimate_tokens() {
+    for model in [Model::Gpt4, Model::Gpt4o, Model::Claude, Model::Llama3] {
+        assert_eq!(estimate_tokens("", model), 0);
+        assert_eq!(estimate_tokens("hello world", model), 2, "{model:?}");
+        assert_eq!(estimate_tokens("1234567", model), 3, "{model:?}");
+    }
+    // Long words and symbol runs cost more under some tokenizers
+    assert_eq!(estimate_tokens("internationalization", Model::Llama3), 3);
+    assert_eq!(estimate_tokens("internationalization", Model::Claude), 4);
+    assert_eq!(estimate_tokens("a  b\n\n  c", Model::Llama3), 5);
+    assert!(estimate_tokens("東京 日本語", Model::Claude) > 2);
+}
+
+#[test]
+fn test_estimate_tokens_toon_vs_json() {
+    let data = json!({"users": (0..20).map(|i| json!({
+        "id": i,
+        "name": format!("User {i}"),
+        "role": if i % 3 == 0 { "admin" } else { "member" },
+        "active": i % 2 == 0
+    })).collect::<Vec<_>>()});
+    let toon = encode(&data, None).unwrap();
+    let json = serde_json::to_string_pretty(&data).unwrap();
+    for model in [Model::Gpt4, Model::Gpt4o, Model::Claude, Model::Llama3] {
+        let (toon, json) = (estimate_tokens(&toon, model), estimate_tokens(&json, model));
+        assert!(toon * 2 < json, "{model:?}: {toon} vs {json}");
+    }
+}
+
+#[cfg(feature = "tiktoken")]
+#[test]
+fn test_estimate_tokens_tiktoken() {
+    // Exact counts from the real encodings
+    assert_eq!(estimate_tokens("tiktoken is great!", Model::Gpt4), 6);
+    assert_eq!(estimate_tokens("tiktoken is great!", Model::Gpt4o), 6);
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "toml")]
+
+use serde_json::json;
+use toon_rust::{decode, toml_to_toon, toon_to_toml, Error};
+
+const CONFIG: &str = r#"
+title = "shop"
+opened = 2024-05-01T09:30:00Z
+ratio = nan
+
+[server]
+host = "localhost"
+ports = [8080, 8081]
+
+[[servers]]
+name = "alpha"
+weight = 1.5
+
+[[servers]]
+name = "beta"
+weight = 2.0
+"#;
+
+#[test]
+fn test_toml_to_toon() {
+    let toon = toml_to_toon(CONFIG, None).unwrap();
+    assert!(toon.contains("servers[2]{name,weight}:\n  alpha,1.5\n  beta,2"));
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!({
+            "title": "shop",
+            "opened": "2024-05-01T09:30:00Z",
+            "ratio": null,
+            "server": {"host": "localhost", "ports": [8080, 8081]},
+            "servers": [{"name": "alpha", "weight": 1.5}, {"name": "beta", "weight": 2.0}]
+        })
+    );
+
+    match toml_to_toon("a = 1\nb = [1,\n", None) {
+        Err(Error::Parse { line, .. }) => assert_eq!(line, 3),
+        other => panic!("unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn test_toon_to_toml() {
+    let toon = "name: shop\nnote: null\nserver:\n  host: localhost\n  ports[2]: 8080,8081\n\
+                servers[2]{name,weight}:\n  alpha,1.5\n  beta,2";
+    let toml = toon_to_toml(toon, None).unwrap();
+    assert_eq!(
+        toml,
+        "name = \"shop\"\n\n[server]\nhost = \"localhost\"\nports = [8080, 8081]\n\n\
+         [[servers]]\nname = \"alpha\"\nweight = 1.5\n\n[[servers]]\nname = \"beta\"\nweight = 2\n"
+    );
+    // Back again, less the null
+    assert_eq!(
+        decode(&toml_to_toon(&toml, None).unwrap(), None).unwrap(),
+        json!({
+            "name": "shop",
+            "server": {"host": "localhost", "ports": [8080, 8081]},
+            "servers": [{"name": "alpha", "weight": 1.5}, {"name": "beta", "weight": 2}]
+        })
+    );
+
+    let error = |toon| match toon_to_toml(toon, None) {
+        Err(Error::Serialization(message)) => message,
+        other => panic!("unexpected result: {other:?}"),
+    };
+    assert_eq!(error("[2]: 1,2"), "A TOML document must be an object");
+    assert_eq!(
+        error("a:\n  b[2]: 1,null"),
+        "TOML has no null, found at `a.b[1]`"
+    );
+}
@@ -0,0 +1,76 @@
+#![cfg(feature = "yaml")]
+
+use serde_json::json;
+use toon_rust::{
+    decode, toon_to_yaml, toon_to_yaml_stream, yaml_to_toon, yaml_to_toon_stream, EncodeOptions,
+    Error,
+};
+
+#[test]
+fn test_toon_to_yaml() {
+    let toon = "name: shop\nitems[2]{sku,qty,note}:\n  A1,2,\"a: b\"\n  B2,1,\ntags[2]: x,\"true\"";
+    assert_eq!(
+        toon_to_yaml(toon, None).unwrap(),
+        "items:\n- note: 'a: b'\n  qty: 2\n  sku: A1\n- note: null\n  qty: 1\n  sku: B2\n\
+         name: shop\ntags:\n- x\n- 'true'\n"
+    );
+    assert!(toon_to_yaml("a[2]: 1", None).is_err());
+
+    let mut yaml = Vec::new();
+    toon_to_yaml_stream(toon.as_bytes(), &mut yaml, None).unwrap();
+    assert_eq!(
+        String::from_utf8(yaml).unwrap(),
+        toon_to_yaml(toon, None).unwrap()
+    );
+}
+
+#[test]
+fn test_yaml_to_toon() {
+    let yaml = "\
+defaults: &defaults
+  qty: 1
+items:
+  - sku: A1
+    <<: *defaults
+  - {sku: B2, qty: 3}
+tags: [x, 'true', 04532]
+note: |
+  two
+  lines
+";
+    let toon = yaml_to_toon(yaml, None).unwrap();
+    assert_eq!(
+        decode(&toon, None).unwrap(),
+        json!({
+            "defaults": {"qty": 1},
+            "items": [{"sku": "A1", "<<": {"qty": 1}}, {"sku": "B2", "qty": 3}],
+            "tags": ["x", "true", "04532"],
+            "note": "two\nlines\n"
+        })
+    );
+
+    // Through a reader, with encoding options
+    let options = EncodeOptions::new().indent(4);
+    let mut streamed = Vec::new();
+    yaml_to_toon_stream(yaml.as_bytes(), &mut streamed, Some(&options)).unwrap();
+    assert_eq!(
+        String::from_utf8(streamed).unwrap(),
+        yaml_to_toon(yaml, Some(&options)).unwrap()
+    );
+
+    // Several documents make a root array
+    assert_eq!(
+        yaml_to_toon("a: 1\n---\na: 2\n", None).unwrap(),
+        "[2]{a}:\n  1\n  2\n"
+    );
+    assert_eq!(yaml_to_toon("- 1\n- 2\n", None).unwrap(), "[2]:1,2");
+}
+
+#[test]
+fn test_yaml_to_toon_errors() {
+    match yaml_to_toon("a: 1\nb: [1, 2\n", None) {
+        Err(Error::Parse { line, .. }) => assert_eq!(line, 3),
+        other => panic!("unexpected result: {other:?}"),
+    }
+    assert!(yaml_to_toon("? [1, 2]\n: x\n", None).is_err());
+}